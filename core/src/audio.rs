@@ -5,6 +5,7 @@ mod io;
 use crate::{
     cpu::{self, arm7, Schedule as _},
     emu::Emu,
+    profiling::TimeCounter,
     utils::{schedule::RawTimestamp, Savestate},
 };
 use capture::CaptureUnit;
@@ -32,6 +33,36 @@ pub enum ChannelInterpMethod {
     Cubic,
 }
 
+/// How the final left and right outputs are routed to the backend. This isn't part of the
+/// console's state: it imitates setups where both sides end up on the same speaker, or where the
+/// headphones are worn the other way around.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum StereoMode {
+    #[default]
+    Stereo,
+    Swapped,
+    Mono,
+}
+
+impl StereoMode {
+    #[inline]
+    fn apply(self, [l, r]: [OutputSample; 2]) -> [OutputSample; 2] {
+        match self {
+            StereoMode::Stereo => [l, r],
+            StereoMode::Swapped => [r, l],
+            StereoMode::Mono => {
+                #[cfg(feature = "xq-audio")]
+                let mixed = (l + r) * 0.5;
+                #[cfg(not(feature = "xq-audio"))]
+                let mixed = ((l as u32 + r as u32) >> 1) as OutputSample;
+                [mixed; 2]
+            }
+        }
+    }
+}
+
 type RawChannelSample = i32;
 type RawMixerInterpSample = i64;
 
@@ -69,9 +100,20 @@ impl Backend for DummyBackend {
 #[cfg(feature = "channel-audio-capture")]
 pub struct ChannelAudioCaptureData {
     pub mask: u16,
+    /// An additional set of channels to capture, for consumers that need to be independent from
+    /// the ones using `mask` (i.e. audio dumps).
+    pub dump_mask: u16,
     pub buffers: [Vec<i16>; 16],
 }
 
+#[cfg(feature = "channel-audio-capture")]
+impl ChannelAudioCaptureData {
+    #[inline]
+    fn is_capturing(&self, i: usize) -> bool {
+        (self.mask | self.dump_mask) & 1 << i != 0
+    }
+}
+
 #[derive(Savestate)]
 #[load(in_place_only, post = "self.post_load()")]
 pub struct Audio {
@@ -84,11 +126,18 @@ pub struct Audio {
     sample_chunk: Vec<[OutputSample; 2]>,
     #[savestate(skip)]
     pub sample_chunk_size: u16,
+    /// Measures the time spent mixing output samples, excluding the backend's handling of them.
+    #[savestate(skip)]
+    pub time_counter: TimeCounter,
     pub channels: [Channel; 16],
     pub capture: [CaptureUnit; 2],
     control: Control,
     bias: u16,
     master_volume: u8,
+    #[savestate(skip)]
+    muted_channels: u16,
+    #[savestate(skip)]
+    stereo_mode: StereoMode,
     #[cfg(feature = "xq-audio")]
     #[savestate(skip)]
     custom_sample_rate: Option<NonZeroU32>,
@@ -143,11 +192,14 @@ impl Audio {
             backend,
             sample_chunk: Vec::with_capacity(sample_chunk_size as usize),
             sample_chunk_size,
+            time_counter: TimeCounter::new(),
             channels,
             capture: [CaptureUnit::new(), CaptureUnit::new()],
             control: Control(0),
             bias: 0,
             master_volume: 0,
+            muted_channels: 0,
+            stereo_mode: StereoMode::Stereo,
             #[cfg(feature = "xq-audio")]
             custom_sample_rate,
             #[cfg(feature = "xq-audio")]
@@ -168,6 +220,7 @@ impl Audio {
                 }
                 ChannelAudioCaptureData {
                     mask: 0,
+                    dump_mask: 0,
                     buffers: buffers!(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15),
                 }
             },
@@ -240,6 +293,30 @@ impl Audio {
         }
     }
 
+    /// Returns the mask of channels whose output is silenced before reaching the mixer and capture
+    /// units (bit `i` corresponding to channel `i`); this is a debugging aid not present on
+    /// hardware, and muted channels keep running (and being captured in
+    /// `channel_audio_capture_data`) as usual.
+    #[inline]
+    pub fn muted_channels(&self) -> u16 {
+        self.muted_channels
+    }
+
+    #[inline]
+    pub fn set_muted_channels(&mut self, value: u16) {
+        self.muted_channels = value;
+    }
+
+    #[inline]
+    pub fn stereo_mode(&self) -> StereoMode {
+        self.stereo_mode
+    }
+
+    #[inline]
+    pub fn set_stereo_mode(&mut self, value: StereoMode) {
+        self.stereo_mode = value;
+    }
+
     #[inline]
     pub fn bias(&self) -> u16 {
         self.bias
@@ -258,6 +335,8 @@ impl Audio {
             Self::handle_xq_sample_ready(emu, time);
         }
 
+        let start = emu.audio.time_counter.start();
+
         #[allow(unused_variables)]
         let output = if emu.audio.control.master_enable() {
             fn raw_channel_sample_to_i16(sample: RawChannelSample) -> i16 {
@@ -274,11 +353,16 @@ impl Audio {
                         Channel::run::<_, true>(emu, channel::Index::new($i as u8), time);
                         let sample = emu.audio.channels[$i].raw_output();
                         #[cfg(feature = "channel-audio-capture")]
-                        if emu.audio.channel_audio_capture_data.mask & 1 << $i != 0 {
+                        if emu.audio.channel_audio_capture_data.is_capturing($i) {
                             emu.audio.channel_audio_capture_data.buffers[$i].push(
                                 raw_channel_sample_to_i16(sample),
                             );
                         }
+                        let sample = if emu.audio.muted_channels & 1 << $i == 0 {
+                            sample
+                        } else {
+                            0
+                        };
                         #[allow(path_statements)]
                         {
                             sample
@@ -290,7 +374,7 @@ impl Audio {
                         }
                     } else {
                         #[cfg(feature = "channel-audio-capture")]
-                        if emu.audio.channel_audio_capture_data.mask & 1 << $i != 0 {
+                        if emu.audio.channel_audio_capture_data.is_capturing($i) {
                             emu.audio.channel_audio_capture_data.buffers[$i].push(0);
                         }
                         Default::default()
@@ -421,9 +505,12 @@ impl Audio {
                 [0; 2]
             }
         };
+        emu.audio.time_counter.stop(start);
         #[cfg(not(feature = "xq-audio"))]
         {
-            emu.audio.sample_chunk.push(output);
+            emu.audio
+                .sample_chunk
+                .push(emu.audio.stereo_mode.apply(output));
             if emu.audio.sample_chunk.len() >= emu.audio.sample_chunk_size as usize {
                 emu.audio
                     .backend
@@ -442,6 +529,7 @@ impl Audio {
     #[inline(never)]
     #[cfg(feature = "xq-audio")]
     pub(crate) fn handle_xq_sample_ready<E: cpu::Engine>(emu: &mut Emu<E>, time: arm7::Timestamp) {
+        let start = emu.audio.time_counter.start();
         let output = if emu.audio.control.master_enable() {
             macro_rules! channel_output {
                 ($i: expr$(, |$ident: ident| $code: expr)?) => {
@@ -449,10 +537,14 @@ impl Audio {
                         || emu.audio.channel_interp_method != ChannelInterpMethod::Nearest
                     {
                         Channel::run::<_, false>(emu, channel::Index::new($i as u8), time);
-                        let sample = emu.audio.channels[$i].interp_output(
-                            time,
-                            emu.audio.channel_interp_method,
-                        );
+                        let sample = if emu.audio.muted_channels & 1 << $i == 0 {
+                            emu.audio.channels[$i].interp_output(
+                                time,
+                                emu.audio.channel_interp_method,
+                            )
+                        } else {
+                            0.0
+                        };
                         #[allow(path_statements)]
                         {
                             sample
@@ -537,8 +629,11 @@ impl Audio {
         } else {
             [0.0; 2]
         };
+        emu.audio.time_counter.stop(start);
 
-        emu.audio.sample_chunk.push(output);
+        emu.audio
+            .sample_chunk
+            .push(emu.audio.stereo_mode.apply(output));
         if emu.audio.sample_chunk.len() >= emu.audio.sample_chunk_size as usize {
             emu.audio
                 .backend
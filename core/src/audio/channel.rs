@@ -135,6 +135,41 @@ static ADPCM_TABLE: [u16; 89] = [
     0x5771, 0x602F, 0x69CE, 0x7462, 0x7FFF,
 ];
 
+/// Extracts the initial value and table index from an ADPCM header word; only bits 16-22 are used
+/// for the index, and -0x8000 can't be produced by the decoder, so it gets clamped like any other
+/// decoded value.
+fn decode_adpcm_header(header: u32) -> (i16, AdpcmIndex) {
+    (
+        (header as i16).max(-0x7FFF),
+        AdpcmIndex::new(((header >> 16) as u8 & 0x7F).min(88)),
+    )
+}
+
+/// Decodes a single 4-bit ADPCM sample, returning the new value and table index.
+fn decode_adpcm_sample(value: i16, index: AdpcmIndex, sample: u8) -> (i16, AdpcmIndex) {
+    let table_entry = ADPCM_TABLE[index.get() as usize] as i32;
+    // The difference is computed by summing shifted table entries rather than through a
+    // multiplication, truncating each term separately
+    let mut diff = table_entry >> 3;
+    if sample & 1 != 0 {
+        diff += table_entry >> 2;
+    }
+    if sample & 2 != 0 {
+        diff += table_entry >> 1;
+    }
+    if sample & 4 != 0 {
+        diff += table_entry;
+    }
+    // The result is clamped symmetrically, so -0x8000 is never output
+    let value = if sample & 8 == 0 {
+        (value as i32 + diff).min(0x7FFF)
+    } else {
+        (value as i32 - diff).max(-0x7FFF)
+    } as i16;
+    let index = (index.get() as i8 + ADPCM_INDEX_TABLE[sample as usize & 7]).clamp(0, 88) as u8;
+    (value, AdpcmIndex::new(index))
+}
+
 #[rustfmt::skip]
 static PSG_TABLE: [i16; 64] = [
     -0x7FFF, -0x7FFF, -0x7FFF, -0x7FFF, -0x7FFF, -0x7FFF, -0x7FFF,  0x7FFF,
@@ -295,6 +330,13 @@ impl Channel {
         self.src_addr = value & 0x07FF_FFFC;
     }
 
+    /// Returns the address the channel will fetch its next sample data from; this runs slightly
+    /// ahead of playback, as data is buffered in a FIFO before being played.
+    #[inline]
+    pub fn cur_addr(&self) -> u32 {
+        self.src_addr.wrapping_add(self.cur_src_off)
+    }
+
     #[inline]
     pub fn timer_reload(&self) -> u16 {
         self.timer_reload
@@ -517,6 +559,30 @@ impl Channel {
         emu.audio.channels[i.get() as usize].push_sample(sample);
     }
 
+    fn start_adpcm(&mut self, header: u32) {
+        (self.adpcm_value, self.adpcm_index) = decode_adpcm_header(header);
+        // Initialize the loop start values in case the loop start sample index is < 8...?
+        // TODO: Check what actually happens
+        self.loop_start_adpcm_value = self.adpcm_value;
+        self.loop_start_adpcm_index = self.adpcm_index;
+    }
+
+    /// Decodes the current sample, saving the decoder's state if it's the first one of the loop.
+    fn decode_adpcm(&mut self, sample: u8) {
+        (self.adpcm_value, self.adpcm_index) =
+            decode_adpcm_sample(self.adpcm_value, self.adpcm_index, sample);
+        if self.cur_sample_index as u32 == self.loop_start_sample_index {
+            self.loop_start_adpcm_value = self.adpcm_value;
+            self.loop_start_adpcm_index = self.adpcm_index;
+        }
+    }
+
+    fn restart_adpcm_loop(&mut self) {
+        self.cur_sample_index = self.loop_start_sample_index as i32;
+        self.adpcm_value = self.loop_start_adpcm_value;
+        self.adpcm_index = self.loop_start_adpcm_index;
+    }
+
     fn run_adpcm(emu: &mut Emu<impl cpu::Engine>, i: Index) {
         let channel = &mut emu.audio.channels[i.get() as usize];
         channel.cur_sample_index += 1;
@@ -528,13 +594,7 @@ impl Channel {
             }
             if channel.cur_sample_index == 0 {
                 let header = Self::read_fifo::<u32, _>(emu, i);
-                let channel = &mut emu.audio.channels[i.get() as usize];
-                channel.adpcm_value = (header as i16).max(-0x7FFF);
-                channel.adpcm_index = AdpcmIndex::new(((header >> 16) as u8).min(88));
-                // Initialize the loop start values in case the loop start sample index is < 8...?
-                // TODO: Check what actually happens
-                channel.loop_start_adpcm_value = channel.adpcm_value;
-                channel.loop_start_adpcm_index = channel.adpcm_index;
+                emu.audio.channels[i.get() as usize].start_adpcm(header);
             }
             return;
         }
@@ -542,9 +602,7 @@ impl Channel {
             match channel.repeat_mode {
                 RepeatMode::Manual => {}
                 RepeatMode::LoopInfinite => {
-                    channel.cur_sample_index = channel.loop_start_sample_index as i32;
-                    channel.adpcm_value = channel.loop_start_adpcm_value;
-                    channel.adpcm_index = channel.loop_start_adpcm_index;
+                    channel.restart_adpcm_loop();
                     channel.push_sample(channel.adpcm_value);
                     // Re-read the loop start byte and ignore it, as the values are already
                     // calculated.
@@ -572,30 +630,7 @@ impl Channel {
             channel.adpcm_byte >> 4
         };
         let channel = &mut emu.audio.channels[i.get() as usize];
-        let adpcm_table_entry = ADPCM_TABLE[channel.adpcm_index.get() as usize] as u32;
-        let mut diff = adpcm_table_entry >> 3;
-        if sample & 1 != 0 {
-            diff += adpcm_table_entry >> 2;
-        }
-        if sample & 2 != 0 {
-            diff += adpcm_table_entry >> 1;
-        }
-        if sample & 4 != 0 {
-            diff += adpcm_table_entry;
-        }
-        channel.adpcm_value = if sample & 8 == 0 {
-            (channel.adpcm_value as i32 + diff as i32).min(0x7FFF)
-        } else {
-            (channel.adpcm_value as i32 - diff as i32).max(-0x7FFF)
-        } as i16;
-        channel.adpcm_index = AdpcmIndex::new(
-            (channel.adpcm_index.get() as i8 + ADPCM_INDEX_TABLE[sample as usize & 7]).clamp(0, 88)
-                as u8,
-        );
-        if channel.cur_sample_index as u32 == channel.loop_start_sample_index {
-            channel.loop_start_adpcm_value = channel.adpcm_value;
-            channel.loop_start_adpcm_index = channel.adpcm_index;
-        }
+        channel.decode_adpcm(sample);
         channel.push_sample(channel.adpcm_value);
     }
 
@@ -738,3 +773,106 @@ impl Channel {
         channel.timer_counter = timer_counter as u16;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel() -> Channel {
+        Channel::new(
+            Index::new(0),
+            #[cfg(feature = "log")]
+            slog::Logger::root(slog::Discard, slog::o!()),
+        )
+    }
+
+    #[test]
+    fn header_initial_value() {
+        assert_eq!(decode_adpcm_header(0x0000_1234).0, 0x1234);
+        assert_eq!(decode_adpcm_header(0x0000_FFFF).0, -1);
+        // -0x8000 is clamped, as the decoder can't output it
+        assert_eq!(decode_adpcm_header(0x0000_8000).0, -0x7FFF);
+    }
+
+    #[test]
+    fn header_index_masking() {
+        assert_eq!(decode_adpcm_header(0x0050_0000).1.get(), 0x50);
+        // Only bits 16-22 are used
+        assert_eq!(decode_adpcm_header(0x0080_0000).1.get(), 0);
+        assert_eq!(decode_adpcm_header(0xFF85_0000).1.get(), 5);
+        // Out of range indices are clamped to the end of the table
+        assert_eq!(decode_adpcm_header(0x007F_0000).1.get(), 88);
+    }
+
+    #[test]
+    fn sample_diff() {
+        let index = AdpcmIndex::new(0);
+        // Table entry 7: 7 >> 3, 7 >> 2, 7 >> 1 and 7 are summed separately
+        assert_eq!(decode_adpcm_sample(0, index, 0).0, 0);
+        assert_eq!(decode_adpcm_sample(0, index, 1).0, 1);
+        assert_eq!(decode_adpcm_sample(0, index, 7).0, 11);
+        assert_eq!(decode_adpcm_sample(0, index, 0xF).0, -11);
+    }
+
+    #[test]
+    fn sample_clamping() {
+        let index = AdpcmIndex::new(88);
+        assert_eq!(decode_adpcm_sample(0x7000, index, 7).0, 0x7FFF);
+        assert_eq!(decode_adpcm_sample(0x7FFF, index, 0).0, 0x7FFF);
+        assert_eq!(decode_adpcm_sample(-0x7000, index, 0xF).0, -0x7FFF);
+        assert_eq!(decode_adpcm_sample(-0x7FFF, index, 8).0, -0x7FFF);
+    }
+
+    #[test]
+    fn step_index() {
+        let decode_index = |index: u8, sample: u8| {
+            decode_adpcm_sample(0, AdpcmIndex::new(index), sample)
+                .1
+                .get()
+        };
+        assert_eq!(decode_index(10, 0), 9);
+        assert_eq!(decode_index(10, 4), 12);
+        assert_eq!(decode_index(10, 7), 18);
+        // The sign bit doesn't affect the index
+        assert_eq!(decode_index(10, 0xC), 12);
+        // Indices are clamped to the table's bounds
+        assert_eq!(decode_index(0, 3), 0);
+        assert_eq!(decode_index(85, 7), 88);
+    }
+
+    #[test]
+    fn loop_start_state() {
+        let mut channel = channel();
+        channel.loop_start_sample_index = 10;
+        channel.start_adpcm(0x0020_0100);
+
+        let mut states = Vec::new();
+        for (i, sample) in [0x7, 0x3, 0x9, 0x4, 0x2].into_iter().enumerate() {
+            channel.cur_sample_index = 8 + i as i32;
+            channel.decode_adpcm(sample);
+            states.push((channel.adpcm_value, channel.adpcm_index.get()));
+        }
+
+        channel.restart_adpcm_loop();
+        assert_eq!(channel.cur_sample_index, 10);
+        // The state after decoding the loop start sample is restored, not the header's
+        assert_eq!((channel.adpcm_value, channel.adpcm_index.get()), states[2]);
+    }
+
+    #[test]
+    fn loop_start_in_header() {
+        let mut channel = channel();
+        // Loop start sample indices within the header keep the header's values
+        channel.loop_start_sample_index = 0;
+        channel.start_adpcm(0x0020_0100);
+        for i in 8..12 {
+            channel.cur_sample_index = i;
+            channel.decode_adpcm(0x7);
+        }
+        channel.restart_adpcm_loop();
+        assert_eq!(
+            (channel.adpcm_value, channel.adpcm_index.get()),
+            (0x100, 0x20)
+        );
+    }
+}
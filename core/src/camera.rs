@@ -0,0 +1,659 @@
+use crate::{cpu::arm9, utils::Savestate};
+use core::any::Any;
+
+// TODO: Frames are captured on demand (when CAM_DAT is read after the previous frame was drained,
+// or once per VBlank for NDMA transfers) instead of at the sensor's framerate.
+
+pub const SENSOR_WIDTH: u16 = 640;
+pub const SENSOR_HEIGHT: u16 = 480;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Savestate)]
+pub enum CameraIndex {
+    Inner,
+    Outer,
+}
+
+pub trait Backend {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn start(&mut self, camera: CameraIndex);
+    fn stop(&mut self, camera: CameraIndex);
+    /// Fills `pixels` (`width * height` entries, row-major) with the camera's next frame, in
+    /// RGB555 format.
+    fn capture_frame(&mut self, camera: CameraIndex, width: u16, height: u16, pixels: &mut [u16]);
+}
+
+pub struct DummyBackend;
+
+impl Backend for DummyBackend {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn start(&mut self, _: CameraIndex) {}
+
+    fn stop(&mut self, _: CameraIndex) {}
+
+    fn capture_frame(&mut self, _: CameraIndex, _: u16, _: u16, pixels: &mut [u16]) {
+        pixels.fill(0x4210);
+    }
+}
+
+/// A backend always returning the same image (in RGB555 format), scaled to the requested output
+/// size using nearest-neighbor sampling.
+pub struct StaticImageBackend {
+    width: u16,
+    height: u16,
+    pixels: Box<[u16]>,
+}
+
+impl StaticImageBackend {
+    pub fn new(width: u16, height: u16, pixels: Box<[u16]>) -> Self {
+        assert!(width != 0 && height != 0);
+        assert_eq!(pixels.len(), width as usize * height as usize);
+        StaticImageBackend {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+impl Backend for StaticImageBackend {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn start(&mut self, _: CameraIndex) {}
+
+    fn stop(&mut self, _: CameraIndex) {}
+
+    fn capture_frame(&mut self, _: CameraIndex, width: u16, height: u16, pixels: &mut [u16]) {
+        for y in 0..height as usize {
+            let src_y = y * self.height as usize / height as usize;
+            let src_line = &self.pixels[src_y * self.width as usize..][..self.width as usize];
+            for (x, pixel) in pixels[y * width as usize..][..width as usize]
+                .iter_mut()
+                .enumerate()
+            {
+                *pixel = src_line[x * self.width as usize / width as usize];
+            }
+        }
+    }
+}
+
+proc_bitfield::bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq, Savestate)]
+    pub struct ModuleControl(pub u16): Debug {
+        pub reset_released: bool @ 1,
+        pub clock_enabled: bool @ 5,
+        pub ready: bool [read_only] @ 7,
+    }
+}
+
+proc_bitfield::bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq, Savestate)]
+    pub struct Control(pub u16): Debug {
+        pub lines_per_block_minus_1: u8 @ 0..=3,
+        pub overrun: bool @ 4,
+        pub flush: bool @ 5,
+        pub irq_enabled: bool @ 11,
+        pub rgb_output: bool @ 13,
+        pub trimming_enabled: bool @ 14,
+        pub transfer_enabled: bool @ 15,
+    }
+}
+
+proc_bitfield::bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq, Savestate)]
+    pub struct I2cControl(pub u8): Debug {
+        pub stop: bool @ 0,
+        pub start: bool @ 1,
+        pub pause: bool @ 2,
+        pub ack: bool @ 4,
+        pub read: bool @ 5,
+        pub irq_enabled: bool @ 6,
+        pub busy: bool @ 7,
+    }
+}
+
+const INNER_I2C_ADDR: u8 = 0x7A;
+const OUTER_I2C_ADDR: u8 = 0x78;
+
+const CHIP_VERSION: u16 = 0x2280;
+
+const SEQ_CMD_VAR: u16 = 0x0103;
+const SEQ_STATE_VAR: u16 = 0x0104;
+const MODE_OUTPUT_WIDTH_A_VAR: u16 = 0x0703;
+const MODE_OUTPUT_HEIGHT_A_VAR: u16 = 0x0705;
+
+/// An Aptina MT9V113 image sensor, as accessed through the DSi's I2C bus.
+#[derive(Savestate)]
+#[load(in_place_only)]
+pub struct Sensor {
+    reg_addr: u16,
+    i2c_pos: u8,
+    data_latch: u8,
+    high_byte_read: bool,
+    clocks_control: u16,
+    standby_control: u16,
+    reset_control: u16,
+    mcu_addr: u16,
+    mcu_vars: [u8; 0x2000],
+}
+
+impl Sensor {
+    fn new() -> Self {
+        let mut sensor = Sensor {
+            reg_addr: 0,
+            i2c_pos: 0,
+            data_latch: 0,
+            high_byte_read: false,
+            clocks_control: 0,
+            standby_control: 0x4029,
+            reset_control: 0,
+            mcu_addr: 0,
+            mcu_vars: [0; 0x2000],
+        };
+        sensor.write_mcu_var(MODE_OUTPUT_WIDTH_A_VAR, SENSOR_WIDTH, false);
+        sensor.write_mcu_var(MODE_OUTPUT_HEIGHT_A_VAR, SENSOR_HEIGHT, false);
+        sensor
+    }
+
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.standby_control & 1 == 0 && self.reset_control & 1 == 0
+    }
+
+    #[inline]
+    pub fn is_streaming(&self) -> bool {
+        self.is_active() && self.read_mcu_var(SEQ_STATE_VAR, true) == 3
+    }
+
+    #[inline]
+    pub fn output_size(&self) -> (u16, u16) {
+        (
+            // Pixels are output in pairs, so the width can't be less than 2
+            self.read_mcu_var(MODE_OUTPUT_WIDTH_A_VAR, false)
+                .clamp(2, SENSOR_WIDTH),
+            self.read_mcu_var(MODE_OUTPUT_HEIGHT_A_VAR, false)
+                .clamp(1, SENSOR_HEIGHT),
+        )
+    }
+
+    fn mcu_var_index(addr: u16) -> usize {
+        ((addr >> 8 & 0x1F) as usize) << 8 | (addr & 0xFF) as usize
+    }
+
+    fn read_mcu_var(&self, addr: u16, is_8_bit: bool) -> u16 {
+        let i = Self::mcu_var_index(addr);
+        if is_8_bit {
+            self.mcu_vars[i] as u16
+        } else {
+            (self.mcu_vars[i] as u16) << 8 | self.mcu_vars[(i + 1) & 0x1FFF] as u16
+        }
+    }
+
+    fn write_mcu_var(&mut self, addr: u16, value: u16, is_8_bit: bool) {
+        let i = Self::mcu_var_index(addr);
+        if is_8_bit {
+            self.mcu_vars[i] = value as u8;
+        } else {
+            self.mcu_vars[i] = (value >> 8) as u8;
+            self.mcu_vars[(i + 1) & 0x1FFF] = value as u8;
+        }
+    }
+
+    fn read_reg(&self, addr: u16) -> u16 {
+        match addr {
+            0x0000 => CHIP_VERSION,
+            0x0016 => self.clocks_control,
+            // Standby is entered immediately, so the status bit always mirrors the request
+            0x0018 => (self.standby_control & !0x4000) | (self.standby_control & 1) << 14,
+            0x001A => self.reset_control,
+            0x098C => self.mcu_addr,
+            0x0990 => self.read_mcu_var(self.mcu_addr, self.mcu_addr & 0x8000 != 0),
+            _ => 0,
+        }
+    }
+
+    fn write_reg(&mut self, addr: u16, value: u16) {
+        match addr {
+            0x0016 => self.clocks_control = value,
+            0x0018 => self.standby_control = value & !0x4000,
+            0x001A => self.reset_control = value,
+            0x098C => self.mcu_addr = value,
+            0x0990 => {
+                let is_8_bit = self.mcu_addr & 0x8000 != 0;
+                self.write_mcu_var(self.mcu_addr, value, is_8_bit);
+                if Self::mcu_var_index(self.mcu_addr) == Self::mcu_var_index(SEQ_CMD_VAR) {
+                    self.run_seq_cmd(value as u8);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn run_seq_cmd(&mut self, cmd: u8) {
+        // Sequencer commands complete instantly; the command variable is cleared to signal
+        // completion, and the state is updated to preview (3) or capture (7)
+        let state = match cmd {
+            1 | 5 | 6 => 3,
+            2 => 7,
+            _ => return,
+        };
+        self.write_mcu_var(SEQ_STATE_VAR, state, true);
+        self.write_mcu_var(SEQ_CMD_VAR, 0, true);
+    }
+
+    fn i2c_start(&mut self, reading: bool) {
+        if reading {
+            self.high_byte_read = false;
+        } else {
+            self.i2c_pos = 0;
+        }
+    }
+
+    fn i2c_write(&mut self, value: u8) {
+        match self.i2c_pos {
+            0 => self.reg_addr = (value as u16) << 8,
+            1 => self.reg_addr |= value as u16,
+            _ => {
+                if self.i2c_pos & 1 == 0 {
+                    self.data_latch = value;
+                } else {
+                    self.write_reg(self.reg_addr, (self.data_latch as u16) << 8 | value as u16);
+                    self.reg_addr = self.reg_addr.wrapping_add(2);
+                }
+            }
+        }
+        self.i2c_pos = if self.i2c_pos == 3 { 2 } else { self.i2c_pos + 1 };
+    }
+
+    fn i2c_read(&mut self) -> u8 {
+        let value = self.read_reg(self.reg_addr);
+        self.high_byte_read = !self.high_byte_read;
+        if self.high_byte_read {
+            (value >> 8) as u8
+        } else {
+            self.reg_addr = self.reg_addr.wrapping_add(2);
+            value as u8
+        }
+    }
+}
+
+#[derive(Savestate)]
+#[load(in_place_only)]
+pub struct Camera {
+    #[cfg(feature = "log")]
+    #[savestate(skip)]
+    logger: slog::Logger,
+    #[savestate(skip)]
+    pub backend: Box<dyn Backend>,
+    #[savestate(skip)]
+    enabled: bool,
+    pub inner: Sensor,
+    pub outer: Sensor,
+    module_control: ModuleControl,
+    control: Control,
+    trimming_start: u32,
+    trimming_end: u32,
+    i2c_control: I2cControl,
+    i2c_data: u8,
+    i2c_device: Option<CameraIndex>,
+    i2c_reading: bool,
+    #[savestate(skip)]
+    streaming: [bool; 2],
+    #[savestate(skip)]
+    frame: Vec<u32>,
+    #[savestate(skip)]
+    frame_pos: usize,
+}
+
+impl Camera {
+    pub(crate) fn new(
+        backend: Box<dyn Backend>,
+        enabled: bool,
+        #[cfg(feature = "log")] logger: slog::Logger,
+    ) -> Self {
+        Camera {
+            #[cfg(feature = "log")]
+            logger,
+            backend,
+            enabled,
+            inner: Sensor::new(),
+            outer: Sensor::new(),
+            module_control: ModuleControl(0),
+            control: Control(0),
+            trimming_start: 0,
+            trimming_end: 0,
+            i2c_control: I2cControl(0),
+            i2c_data: 0,
+            i2c_device: None,
+            i2c_reading: false,
+            streaming: [false; 2],
+            frame: Vec::new(),
+            frame_pos: 0,
+        }
+    }
+
+    /// Whether the camera registers are mapped at all (i.e. whether the emulated model is a DSi).
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    #[inline]
+    pub fn module_control(&self) -> ModuleControl {
+        self.module_control
+    }
+
+    #[inline]
+    pub fn write_module_control(&mut self, value: ModuleControl) {
+        self.module_control.0 = (value.0 & 0x0022)
+            | ((value.reset_released() && value.clock_enabled()) as u16) << 7;
+        self.update_streaming();
+    }
+
+    #[inline]
+    pub fn control(&self) -> Control {
+        self.control
+    }
+
+    #[inline]
+    pub fn write_control(&mut self, value: Control) {
+        if value.flush() {
+            self.frame.clear();
+            self.frame_pos = 0;
+        }
+        let overrun = self.control.overrun() && !value.overrun();
+        self.control.0 = (value.0 & 0xE80F) | (overrun as u16) << 4;
+    }
+
+    #[inline]
+    pub fn trimming_start(&self) -> u32 {
+        self.trimming_start
+    }
+
+    #[inline]
+    pub fn write_trimming_start(&mut self, value: u32) {
+        self.trimming_start = value & 0x01FF_03FE;
+    }
+
+    #[inline]
+    pub fn trimming_end(&self) -> u32 {
+        self.trimming_end
+    }
+
+    #[inline]
+    pub fn write_trimming_end(&mut self, value: u32) {
+        self.trimming_end = value & 0x01FF_03FE;
+    }
+
+    #[inline]
+    pub fn i2c_control(&self) -> I2cControl {
+        self.i2c_control
+    }
+
+    #[inline]
+    pub fn i2c_data(&self) -> u8 {
+        self.i2c_data
+    }
+
+    #[inline]
+    pub fn write_i2c_data(&mut self, value: u8) {
+        self.i2c_data = value;
+    }
+
+    fn sensor_mut(&mut self, index: CameraIndex) -> &mut Sensor {
+        match index {
+            CameraIndex::Inner => &mut self.inner,
+            CameraIndex::Outer => &mut self.outer,
+        }
+    }
+
+    pub fn write_i2c_control(&mut self, value: I2cControl) {
+        self.i2c_control.0 = value.0 & 0x7F;
+        if !value.busy() {
+            return;
+        }
+
+        if value.start() {
+            let device_addr = self.i2c_data & 0xFE;
+            self.i2c_reading = self.i2c_data & 1 != 0;
+            self.i2c_device = match device_addr {
+                INNER_I2C_ADDR => Some(CameraIndex::Inner),
+                OUTER_I2C_ADDR => Some(CameraIndex::Outer),
+                _ => {
+                    #[cfg(feature = "log")]
+                    slog::warn!(self.logger, "Unknown I2C device: {:#04X}", device_addr);
+                    None
+                }
+            };
+            if let Some(device) = self.i2c_device {
+                let reading = self.i2c_reading;
+                self.sensor_mut(device).i2c_start(reading);
+            }
+            self.i2c_control.set_ack(self.i2c_device.is_some());
+        } else if let Some(device) = self.i2c_device {
+            if value.read() {
+                self.i2c_data = self.sensor_mut(device).i2c_read();
+            } else {
+                let data = self.i2c_data;
+                self.sensor_mut(device).i2c_write(data);
+                self.i2c_control.set_ack(true);
+            }
+        } else {
+            self.i2c_data = 0xFF;
+            self.i2c_control.set_ack(false);
+        }
+
+        if value.stop() {
+            self.i2c_device = None;
+        }
+        self.update_streaming();
+    }
+
+    fn update_streaming(&mut self) {
+        let module_ready = self.module_control.ready();
+        for (i, index) in [CameraIndex::Inner, CameraIndex::Outer]
+            .into_iter()
+            .enumerate()
+        {
+            let streaming = module_ready && self.sensor_mut(index).is_active();
+            if streaming != self.streaming[i] {
+                self.streaming[i] = streaming;
+                if streaming {
+                    self.backend.start(index);
+                } else {
+                    self.backend.stop(index);
+                }
+            }
+        }
+    }
+
+    fn capture_frame(&mut self) -> bool {
+        let index = if self.inner.is_streaming() {
+            CameraIndex::Inner
+        } else if self.outer.is_streaming() {
+            CameraIndex::Outer
+        } else {
+            return false;
+        };
+        let (width, height) = self.sensor_mut(index).output_size();
+        let mut pixels = vec![0; width as usize * height as usize];
+        self.backend.capture_frame(index, width, height, &mut pixels);
+
+        let (start_x, start_y, end_x, end_y) = if self.control.trimming_enabled() {
+            (
+                (self.trimming_start & 0x3FE) as u16 >> 1,
+                (self.trimming_start >> 16) as u16,
+                (self.trimming_end & 0x3FE) as u16 >> 1,
+                (self.trimming_end >> 16) as u16,
+            )
+        } else {
+            (0, 0, width / 2 - 1, height - 1)
+        };
+
+        self.frame.clear();
+        self.frame_pos = 0;
+        for y in start_y..=end_y.min(height - 1) {
+            let line = &pixels[y as usize * width as usize..(y as usize + 1) * width as usize];
+            for x in start_x..=end_x.min(width / 2 - 1) {
+                let a = line[x as usize * 2];
+                let b = line[x as usize * 2 + 1];
+                self.frame.push(if self.control.rgb_output() {
+                    (0x8000 | a as u32) | (0x8000 | b as u32) << 16
+                } else {
+                    rgb555_pair_to_yuv422(a, b)
+                });
+            }
+        }
+        !self.frame.is_empty()
+    }
+
+    /// Whether frame data can currently be read, i.e. whether transfers are enabled and one of the
+    /// sensors is streaming.
+    pub fn is_capturing(&self) -> bool {
+        self.control.transfer_enabled()
+            && self.module_control.ready()
+            && (self.inner.is_streaming() || self.outer.is_streaming())
+    }
+
+    /// Whether all data for the current frame has been read.
+    #[inline]
+    pub fn frame_finished(&self) -> bool {
+        self.frame_pos >= self.frame.len()
+    }
+
+    pub fn peek_data(&self) -> u32 {
+        self.frame.get(self.frame_pos).copied().unwrap_or(0)
+    }
+
+    pub fn read_data(&mut self, irqs: &mut arm9::Irqs, schedule: &mut arm9::Schedule) -> u32 {
+        if !self.control.transfer_enabled() || !self.module_control.ready() {
+            return 0;
+        }
+        if self.frame_finished() && !self.capture_frame() {
+            return 0;
+        }
+        let value = self.frame[self.frame_pos];
+        self.frame_pos += 1;
+        if self.frame_finished() && self.control.irq_enabled() {
+            irqs.write_requested(irqs.requested().with_dsi_camera(true), schedule);
+        }
+        value
+    }
+}
+
+fn rgb555_to_yuv(color: u16) -> (u8, u8, u8) {
+    let r = (color & 0x1F) as i32 * 255 / 31;
+    let g = (color >> 5 & 0x1F) as i32 * 255 / 31;
+    let b = (color >> 10 & 0x1F) as i32 * 255 / 31;
+    let y = (77 * r + 150 * g + 29 * b) >> 8;
+    let u = ((-43 * r - 85 * g + 128 * b) >> 8) + 128;
+    let v = ((128 * r - 107 * g - 21 * b) >> 8) + 128;
+    (
+        y.clamp(0, 255) as u8,
+        u.clamp(0, 255) as u8,
+        v.clamp(0, 255) as u8,
+    )
+}
+
+fn rgb555_pair_to_yuv422(a: u16, b: u16) -> u32 {
+    let (y0, u0, v0) = rgb555_to_yuv(a);
+    let (y1, u1, v1) = rgb555_to_yuv(b);
+    let u = ((u0 as u16 + u1 as u16) >> 1) as u32;
+    let v = ((v0 as u16 + v1 as u16) >> 1) as u32;
+    y0 as u32 | u << 8 | (y1 as u32) << 16 | v << 24
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cpu::{arm9::bus, bus::CpuAccess, interpreter::Interpreter},
+        emu::{testing, Emu},
+        ndma::{self, Ndma},
+        Model,
+    };
+
+    /// Builds a DSi with the inner camera streaming RGB frames of the given size, with the frame
+    /// end IRQ enabled.
+    fn streaming_emu(width: u16, height: u16) -> Emu<Interpreter> {
+        let mut builder = testing::builder();
+        builder.model = Model::Dsi;
+        let mut emu = testing::build_from(builder);
+
+        let sensor = &mut emu.camera.inner;
+        sensor.write_reg(0x0018, 0);
+        sensor.write_mcu_var(MODE_OUTPUT_WIDTH_A_VAR, width, false);
+        sensor.write_mcu_var(MODE_OUTPUT_HEIGHT_A_VAR, height, false);
+        sensor.run_seq_cmd(1);
+
+        bus::write_16::<CpuAccess, _>(&mut emu, 0x0400_4200, 0x0022);
+        bus::write_16::<CpuAccess, _>(&mut emu, 0x0400_4202, 0xA800);
+        assert!(emu.camera.is_capturing());
+        emu
+    }
+
+    const PIXEL_PAIR: u32 = 0xC210_C210;
+
+    #[test]
+    fn minimum_output_width() {
+        let mut emu = streaming_emu(1, 1);
+        assert_eq!(emu.camera.inner.output_size(), (2, 1));
+
+        assert_eq!(
+            bus::read_32::<CpuAccess, _, false>(&mut emu, 0x0400_4204),
+            PIXEL_PAIR
+        );
+        assert!(emu.camera.frame_finished());
+        assert!(emu.arm9.irqs.requested().dsi_camera());
+    }
+
+    #[test]
+    fn ndma_camera_transfer() {
+        let mut emu = streaming_emu(4, 2);
+        for (offset, value) in [
+            (0x104, 0x0400_4204),
+            (0x108, 0x0200_0000),
+            (0x10C, 4),
+            (0x110, 2),
+            (
+                0x11C,
+                0xC000_0000 | (ndma::TIMING_CAMERA as u32) << 24 | 2 << 13,
+            ),
+        ] {
+            bus::write_32::<CpuAccess, _>(&mut emu, 0x0400_4000 | offset, value);
+        }
+
+        Ndma::run_camera_transfers(&mut emu);
+        for i in 0..4 {
+            assert_eq!(
+                bus::read_32::<CpuAccess, _, false>(&mut emu, 0x0200_0000 + i * 4),
+                PIXEL_PAIR
+            );
+        }
+        assert!(!emu.ndma.channels[0].control().enabled());
+        let requested = emu.arm9.irqs.requested();
+        assert!(requested.dsi_camera() && requested.dsi_ndma0());
+
+        // Once the channel is done, no more frames are transferred
+        bus::write_32::<CpuAccess, _>(&mut emu, 0x0200_0000, 0);
+        Ndma::run_camera_transfers(&mut emu);
+        assert_eq!(
+            bus::read_32::<CpuAccess, _, false>(&mut emu, 0x0200_0000),
+            0
+        );
+    }
+}
@@ -7,6 +7,13 @@ pub(crate) use irqs::Irqs;
 #[cfg(any(feature = "debugger-hooks", doc))]
 #[macro_use]
 pub mod debug;
+#[cfg(feature = "lockstep-trace")]
+#[macro_use]
+pub mod trace;
+#[cfg(not(feature = "lockstep-trace"))]
+macro_rules! trace_record {
+    ($($args: tt)*) => {};
+}
 pub mod arm7;
 pub mod arm9;
 #[cfg(feature = "disasm")]
@@ -25,7 +32,13 @@ use crate::{
 };
 use psr::Psr;
 
-pub trait Engine: Sized + LoadableInPlace + Storable {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Core {
+    Arm7,
+    Arm9,
+}
+
+pub trait Engine: Sized + LoadableInPlace + Storable + 'static {
     type GlobalData: LoadableInPlace + Storable;
     type Arm7Data: Arm7Data + CoreData<Engine = Self> + LoadableInPlace + Storable;
     type Arm9Data: Arm9Data + CoreData<Engine = Self> + LoadableInPlace + Storable;
@@ -105,6 +118,8 @@ pub trait CoreData {
             );
             #[doc(cfg(feature = "debugger-hooks"))]
             fn clear_mem_watchpoints(&mut self);
+            #[doc(cfg(feature = "debugger-hooks"))]
+            fn set_irq_hook(&mut self, hook: &Option<debug::IrqHook<Self::Engine>>);
         }
     }
 }
@@ -115,6 +115,10 @@ impl<E: Engine> Arm7<E> {
                 ],
                 cur_channel: None,
                 running_channels: 0,
+                #[cfg(all(feature = "fault-injection", debug_assertions))]
+                completion_delays: [None; 4],
+                #[cfg(all(feature = "fault-injection", debug_assertions))]
+                delayed_completions: [None; 4],
             },
             last_dma_words: [0; 4],
             #[cfg(feature = "debugger-hooks")]
@@ -244,6 +248,19 @@ impl<E: Engine> Arm7<E> {
                 self.engine_data.set_mem_watchpoint_hook(&self.debug.mem_watchpoint_hook);
             }
 
+            #[doc(cfg(feature = "debugger-hooks"))]
+            #[inline]
+            pub fn irq_hook(&self) -> &Option<debug::IrqHook<E>> {
+                &self.debug.irq_hook
+            }
+
+            #[doc(cfg(feature = "debugger-hooks"))]
+            #[inline]
+            pub fn set_irq_hook(&mut self, value: Option<debug::IrqHook<E>>) {
+                self.debug.irq_hook = value;
+                self.engine_data.set_irq_hook(&self.debug.irq_hook);
+            }
+
             #[doc(cfg(feature = "debugger-hooks"))]
             #[inline]
             pub fn mem_watchpoints(&self) -> &debug::MemWatchpointRootTable {
@@ -302,6 +319,29 @@ impl<E: Engine> Arm7<E> {
                 }
             }
 
+            /// Adds memory watchpoints covering the inclusive address range `start..=end`.
+            #[doc(cfg(feature = "debugger-hooks"))]
+            pub fn add_mem_watchpoint_range(
+                &mut self,
+                (start, end): (u32, u32),
+                rw: debug::MemWatchpointRwMask,
+            ) {
+                debug::for_each_watchpoint_chunk((start, end), |addr, size| {
+                    self.add_mem_watchpoint(addr, size, rw);
+                });
+            }
+
+            #[doc(cfg(feature = "debugger-hooks"))]
+            pub fn remove_mem_watchpoint_range(
+                &mut self,
+                (start, end): (u32, u32),
+                rw: debug::MemWatchpointRwMask,
+            ) {
+                debug::for_each_watchpoint_chunk((start, end), |addr, size| {
+                    self.remove_mem_watchpoint(addr, size, rw);
+                });
+            }
+
             #[doc(cfg(feature = "debugger-hooks"))]
             #[inline]
             pub fn clear_mem_watchpoints(&mut self) {
@@ -74,6 +74,17 @@ pub fn read_8<A: AccessType, E: Engine>(emu: &mut Emu<E>, addr: u32) -> u8 {
                     0x006 => emu.gpu.vcount() as u8,
                     0x007 => (emu.gpu.vcount() >> 8) as u8,
 
+                    0x0B0..=0x0DF => {
+                        let offset = (addr & 0xFF) - 0xB0;
+                        let channel = &emu.arm7.dma.channels[(offset / 12) as usize];
+                        let value = match (offset % 12) >> 2 {
+                            0 => channel.src_addr,
+                            1 => channel.dst_addr,
+                            _ => channel.control.0,
+                        };
+                        (value >> ((addr & 3) << 3)) as u8
+                    }
+
                     0x100 => emu.arm7.timers.read_counter(
                         timers::Index::new(0),
                         &mut emu.arm7.schedule,
@@ -177,16 +188,21 @@ pub fn read_8<A: AccessType, E: Engine>(emu: &mut Emu<E>, addr: u32) -> u8 {
 
                     0x204 => emu.arm7.local_ex_mem_control.0 | emu.global_ex_mem_control().0 as u8,
                     0x205 => (emu.global_ex_mem_control().0 >> 8) as u8,
+                    0x206..=0x207 => 0,
 
                     0x208 => emu.arm7.irqs.master_enable() as u8,
                     0x209..=0x20B => 0,
 
+                    0x210..=0x213 => (emu.arm7.irqs.enabled().0 >> ((addr & 3) << 3)) as u8,
+                    0x214..=0x217 => (emu.arm7.irqs.requested().0 >> ((addr & 3) << 3)) as u8,
+
                     0x240 => emu.gpu.vram.arm7_status().0,
 
                     0x241 => emu.swram.control().0,
+                    0x242..=0x243 => 0,
 
                     0x300 => emu.arm7.post_boot_flag as u8,
-                    0x302..=0x303 => 0,
+                    0x301..=0x303 => 0,
 
                     0x304 => emu.audio_wifi_power_control().0,
                     0x305..=0x307 => 0,
@@ -225,7 +241,9 @@ pub fn read_8<A: AccessType, E: Engine>(emu: &mut Emu<E>, addr: u32) -> u8 {
 
         0x08 | 0x09 => {
             if emu.global_ex_mem_control().arm7_gba_slot_access() {
-                (emu.arm7.local_ex_mem_control().gba_rom_halfword(addr) >> ((addr & 1) << 3)) as u8
+                (emu.gba_slot
+                    .read_rom_halfword(addr, emu.arm7.local_ex_mem_control())
+                    >> ((addr & 1) << 3)) as u8
             } else {
                 0
             }
@@ -233,7 +251,7 @@ pub fn read_8<A: AccessType, E: Engine>(emu: &mut Emu<E>, addr: u32) -> u8 {
 
         0x0A => {
             if emu.global_ex_mem_control().arm7_gba_slot_access() {
-                0xFF
+                emu.gba_slot.read_sram(addr)
             } else {
                 0
             }
@@ -391,6 +409,7 @@ pub fn read_16<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32) -> u16
                     0x1C2 => emu.spi.read_data() as u16,
 
                     0x204 => emu.arm7.local_ex_mem_control.0 as u16 | emu.global_ex_mem_control().0,
+                    0x206 => 0,
 
                     0x208 => emu.arm7.irqs.master_enable() as u16,
                     0x20A => 0,
@@ -404,6 +423,7 @@ pub fn read_16<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32) -> u16
                     0x240 => {
                         emu.gpu.vram.arm7_status().0 as u16 | (emu.swram.control().0 as u16) << 8
                     }
+                    0x242 => 0,
 
                     0x300 => emu.arm7.post_boot_flag as u16,
                     0x302 => 0,
@@ -444,7 +464,8 @@ pub fn read_16<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32) -> u16
 
         0x08 | 0x09 => {
             if emu.global_ex_mem_control().arm7_gba_slot_access() {
-                emu.arm7.local_ex_mem_control().gba_rom_halfword(addr)
+                emu.gba_slot
+                    .read_rom_halfword(addr, emu.arm7.local_ex_mem_control())
             } else {
                 0
             }
@@ -452,7 +473,7 @@ pub fn read_16<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32) -> u16
 
         0x0A => {
             if emu.global_ex_mem_control().arm7_gba_slot_access() {
-                0xFFFF
+                emu.gba_slot.read_sram(addr) as u16 * 0x0101
             } else {
                 0
             }
@@ -618,13 +639,21 @@ pub fn read_32<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32) -> u32
 
                     0x304 => emu.audio_wifi_power_control().0 as u32,
 
+                    0x308 => emu.arm7.bios_prot as u32,
+
                     0x400..=0x51C => emu.audio.read_32::<A>(addr),
 
                     0x10_0000 => {
                         if A::IS_DEBUG {
                             emu.ipc.peek_7()
                         } else {
-                            emu.ipc.recv_7(&mut emu.arm9.irqs)
+                            let value = emu.ipc.recv_7(&mut emu.arm9.irqs);
+                            #[cfg(all(feature = "fault-injection", debug_assertions))]
+                            let value = emu
+                                .fault_injector
+                                .corrupt_ipc_recv(crate::cpu::Core::Arm7, value);
+                            trace_record!(emu, Arm7, IpcFifoRecv { value });
+                            value
                         }
                     }
 
@@ -670,7 +699,8 @@ pub fn read_32<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32) -> u32
 
         0x08 | 0x09 => {
             if emu.global_ex_mem_control().arm7_gba_slot_access() {
-                emu.arm7.local_ex_mem_control().gba_rom_word(addr)
+                emu.gba_slot
+                    .read_rom_word(addr, emu.arm7.local_ex_mem_control())
             } else {
                 0
             }
@@ -678,7 +708,7 @@ pub fn read_32<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32) -> u32
 
         0x0A => {
             if emu.global_ex_mem_control().arm7_gba_slot_access() {
-                0xFFFF_FFFF
+                emu.gba_slot.read_sram(addr) as u32 * 0x0101_0101
             } else {
                 0
             }
@@ -750,22 +780,43 @@ pub fn write_8<A: AccessType, E: Engine>(emu: &mut Emu<E>, addr: u32, value: u8)
                     )),
 
                     0x180 | 0x182 | 0x183 => {}
-                    0x181 => emu.ipc.write_sync_7(
-                        ipc::Sync((emu.ipc.sync_7().0 & 0x00FF) | (value as u16) << 8),
-                        &mut emu.arm9.irqs,
-                    ),
-                    0x184 => emu.ipc.write_fifo_control_7(
-                        ipc::FifoControl((emu.ipc.fifo_control_7().0 & 0xBF00) | value as u16),
-                        &mut emu.arm7.irqs,
-                        &mut emu.arm7.schedule,
-                    ),
-                    0x185 => emu.ipc.write_fifo_control_7(
-                        ipc::FifoControl(
-                            (emu.ipc.fifo_control_7().0 & 0x00FF) | (value as u16) << 8,
-                        ),
-                        &mut emu.arm7.irqs,
-                        &mut emu.arm7.schedule,
-                    ),
+                    0x181 => {
+                        emu.ipc.write_sync_7(
+                            ipc::Sync((emu.ipc.sync_7().0 & 0x00FF) | (value as u16) << 8),
+                            &mut emu.arm9.irqs,
+                        );
+                        trace_record!(emu, Arm7, IpcSyncWrite { value: emu.ipc.sync_7().0 });
+                    }
+                    0x184 => {
+                        emu.ipc.write_fifo_control_7(
+                            ipc::FifoControl((emu.ipc.fifo_control_7().0 & 0xBF00) | value as u16),
+                            &mut emu.arm7.irqs,
+                            &mut emu.arm7.schedule,
+                        );
+                        trace_record!(
+                            emu,
+                            Arm7,
+                            IpcFifoControlWrite {
+                                value: emu.ipc.fifo_control_7().0
+                            }
+                        );
+                    }
+                    0x185 => {
+                        emu.ipc.write_fifo_control_7(
+                            ipc::FifoControl(
+                                (emu.ipc.fifo_control_7().0 & 0x00FF) | (value as u16) << 8,
+                            ),
+                            &mut emu.arm7.irqs,
+                            &mut emu.arm7.schedule,
+                        );
+                        trace_record!(
+                            emu,
+                            Arm7,
+                            IpcFifoControlWrite {
+                                value: emu.ipc.fifo_control_7().0
+                            }
+                        );
+                    }
                     0x186 | 0x187 => {}
 
                     0x1A0 => {
@@ -855,6 +906,7 @@ pub fn write_8<A: AccessType, E: Engine>(emu: &mut Emu<E>, addr: u32, value: u8)
                             &mut emu.arm7.schedule,
                             &mut emu.schedule,
                             &mut emu.input.status,
+                            &mut emu.noise,
                         );
                     }
                     0x1C3 => {}
@@ -920,6 +972,12 @@ pub fn write_8<A: AccessType, E: Engine>(emu: &mut Emu<E>, addr: u32, value: u8)
 
         0x06 => emu.gpu.vram.write_arm7(addr, value),
 
+        0x0A => {
+            if emu.global_ex_mem_control().arm7_gba_slot_access() {
+                emu.gba_slot.write_sram(addr, value);
+            }
+        }
+
         _ =>
         {
             #[cfg(feature = "log")]
@@ -1113,13 +1171,25 @@ pub fn write_16<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32, value
 
                     0x138 => emu.rtc.write_control(rtc::Control(value)),
 
-                    0x180 => emu.ipc.write_sync_7(ipc::Sync(value), &mut emu.arm9.irqs),
+                    0x180 => {
+                        emu.ipc.write_sync_7(ipc::Sync(value), &mut emu.arm9.irqs);
+                        trace_record!(emu, Arm7, IpcSyncWrite { value: emu.ipc.sync_7().0 });
+                    }
                     0x182 => {}
-                    0x184 => emu.ipc.write_fifo_control_7(
-                        ipc::FifoControl(value),
-                        &mut emu.arm7.irqs,
-                        &mut emu.arm7.schedule,
-                    ),
+                    0x184 => {
+                        emu.ipc.write_fifo_control_7(
+                            ipc::FifoControl(value),
+                            &mut emu.arm7.irqs,
+                            &mut emu.arm7.schedule,
+                        );
+                        trace_record!(
+                            emu,
+                            Arm7,
+                            IpcFifoControlWrite {
+                                value: emu.ipc.fifo_control_7().0
+                            }
+                        );
+                    }
                     0x186 => {}
 
                     0x1A0 => {
@@ -1217,6 +1287,7 @@ pub fn write_16<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32, value
                         &mut emu.arm7.schedule,
                         &mut emu.schedule,
                         &mut emu.input.status,
+                        &mut emu.noise,
                     ),
 
                     0x204 => emu
@@ -1270,6 +1341,19 @@ pub fn write_16<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32, value
 
         0x06 => emu.gpu.vram.write_arm7(addr, value),
 
+        0x08 | 0x09 => {
+            if emu.global_ex_mem_control().arm7_gba_slot_access() {
+                emu.gba_slot.write_rom_halfword(addr, value);
+            }
+        }
+
+        0x0A => {
+            if emu.global_ex_mem_control().arm7_gba_slot_access() {
+                emu.gba_slot
+                    .write_sram(addr, (value >> ((addr & 1) << 3)) as u8);
+            }
+        }
+
         _ =>
         {
             #[cfg(feature = "log")]
@@ -1385,15 +1469,28 @@ pub fn write_32<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32, value
 
                     0x138 => emu.rtc.write_control(rtc::Control(value as u16)),
 
-                    0x180 => emu
-                        .ipc
-                        .write_sync_7(ipc::Sync(value as u16), &mut emu.arm9.irqs),
-                    0x184 => emu.ipc.write_fifo_control_7(
-                        ipc::FifoControl(value as u16),
-                        &mut emu.arm7.irqs,
-                        &mut emu.arm7.schedule,
-                    ),
-                    0x188 => emu.ipc.send_7(value, &mut emu.arm9.irqs),
+                    0x180 => {
+                        emu.ipc.write_sync_7(ipc::Sync(value as u16), &mut emu.arm9.irqs);
+                        trace_record!(emu, Arm7, IpcSyncWrite { value: emu.ipc.sync_7().0 });
+                    }
+                    0x184 => {
+                        emu.ipc.write_fifo_control_7(
+                            ipc::FifoControl(value as u16),
+                            &mut emu.arm7.irqs,
+                            &mut emu.arm7.schedule,
+                        );
+                        trace_record!(
+                            emu,
+                            Arm7,
+                            IpcFifoControlWrite {
+                                value: emu.ipc.fifo_control_7().0
+                            }
+                        );
+                    }
+                    0x188 => {
+                        emu.ipc.send_7(value, &mut emu.arm9.irqs);
+                        trace_record!(emu, Arm7, IpcFifoSend { value });
+                    }
 
                     0x1A0 => {
                         if emu.ds_slot.arm7_access() {
@@ -1461,6 +1558,7 @@ pub fn write_32<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32, value
                             &mut emu.arm7.schedule,
                             &mut emu.schedule,
                             &mut emu.input.status,
+                            &mut emu.noise,
                         );
                     }
 
@@ -1491,6 +1589,16 @@ pub fn write_32<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32, value
 
                     0x400..=0x51C => emu.audio.write_32::<A>(addr, value),
 
+                    0x10_0010 => {
+                        if emu.ds_slot.arm7_access() {
+                            emu.ds_slot.write_rom_data_arm7(
+                                value,
+                                &mut emu.arm7.irqs,
+                                &mut emu.arm7.schedule,
+                            );
+                        }
+                    }
+
                     _ =>
                     {
                         #[cfg(feature = "log")]
@@ -1521,6 +1629,21 @@ pub fn write_32<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32, value
 
         0x06 => emu.gpu.vram.write_arm7(addr, value),
 
+        0x08 | 0x09 => {
+            if emu.global_ex_mem_control().arm7_gba_slot_access() {
+                emu.gba_slot.write_rom_halfword(addr, value as u16);
+                emu.gba_slot
+                    .write_rom_halfword(addr | 2, (value >> 16) as u16);
+            }
+        }
+
+        0x0A => {
+            if emu.global_ex_mem_control().arm7_gba_slot_access() {
+                emu.gba_slot
+                    .write_sram(addr, (value >> ((addr & 3) << 3)) as u8);
+            }
+        }
+
         _ =>
         {
             #[cfg(feature = "log")]
@@ -32,6 +32,10 @@ impl<E: Engine> Arm7<E> {
             // be written to again.
 
             channel.timing = Timing::Disabled;
+            #[cfg(all(feature = "fault-injection", debug_assertions))]
+            {
+                self.dma.delayed_completions[i.get() as usize] = None;
+            }
             if prev_value.enabled() {
                 // Handle self-disabling DMAs
                 self.dma.running_channels &= !(1 << i.get());
@@ -125,11 +129,14 @@ impl<E: Engine> Arm7<E> {
             }
             channel.remaining_units = channel.unit_count;
         } else {
-            channel.control.set_enabled(false);
             channel.timing = Timing::Disabled;
         }
-        if channel.control.fire_irq() {
-            self.irqs.request_dma(i);
+        #[cfg(all(feature = "fault-injection", debug_assertions))]
+        let delayed = self.dma.delay_completion(i, self.schedule.cur_time().0);
+        #[cfg(not(all(feature = "fault-injection", debug_assertions)))]
+        let delayed = false;
+        if !delayed {
+            self.complete_dma_transfer(i);
         }
         self.dma.running_channels &= !(1 << i.get());
         if self.dma.cur_channel == Some(i) {
@@ -137,6 +144,16 @@ impl<E: Engine> Arm7<E> {
         }
     }
 
+    pub(crate) fn complete_dma_transfer(&mut self, i: Index) {
+        let channel = &mut self.dma.channels[i.get() as usize];
+        if !channel.repeat {
+            channel.control.set_enabled(false);
+        }
+        if channel.control.fire_irq() {
+            self.irqs.request_dma(i);
+        }
+    }
+
     pub(in super::super) fn run_dma_transfer(emu: &mut Emu<E>, i: Index) {
         let channel = &mut emu.arm7.dma.channels[i.get() as usize];
         let src_timings = emu.arm7.bus_timings.get(channel.cur_src_addr);
@@ -17,6 +17,7 @@ use crate::{
     cpu::{self, hle_bios},
     emu::{swram::Swram, Emu, LocalExMemControl},
     utils::{Bytes, OwnedBytesCellPtr, Savestate},
+    Model,
 };
 use cp15::Cp15;
 use div_engine::DivEngine;
@@ -72,6 +73,7 @@ impl<E: Engine> Arm9<E> {
     pub(crate) fn new(
         engine_data: E::Arm9Data,
         bios: Option<OwnedBytesCellPtr<BIOS_BUFFER_SIZE>>,
+        model: Model,
         #[cfg(feature = "log")] logger: slog::Logger,
     ) -> Self {
         let mut schedule = Schedule::new();
@@ -95,7 +97,7 @@ impl<E: Engine> Arm9<E> {
             bus_ptrs: bus::ptrs::Ptrs::new_boxed(),
             bus_timings: bus::timings::Timings::new_boxed(),
             cp15: Cp15::new(),
-            irqs: Irqs::new(),
+            irqs: Irqs::new(model == Model::Dsi),
             timers,
             local_ex_mem_control: LocalExMemControl(0),
             post_boot_flag: PostBootFlag(0),
@@ -132,6 +134,10 @@ impl<E: Engine> Arm9<E> {
                 ],
                 cur_channel: None,
                 running_channels: 0,
+                #[cfg(all(feature = "fault-injection", debug_assertions))]
+                completion_delays: [None; 4],
+                #[cfg(all(feature = "fault-injection", debug_assertions))]
+                delayed_completions: [None; 4],
             },
             dma_fill: Bytes::new([0; 16]),
             div_engine,
@@ -290,6 +296,19 @@ impl<E: Engine> Arm9<E> {
                 self.engine_data.set_mem_watchpoint_hook(&self.debug.mem_watchpoint_hook);
             }
 
+            #[doc(cfg(feature = "debugger-hooks"))]
+            #[inline]
+            pub fn irq_hook(&self) -> &Option<debug::IrqHook<E>> {
+                &self.debug.irq_hook
+            }
+
+            #[doc(cfg(feature = "debugger-hooks"))]
+            #[inline]
+            pub fn set_irq_hook(&mut self, value: Option<debug::IrqHook<E>>) {
+                self.debug.irq_hook = value;
+                self.engine_data.set_irq_hook(&self.debug.irq_hook);
+            }
+
             #[doc(cfg(feature = "debugger-hooks"))]
             #[inline]
             pub fn mem_watchpoints(&self) -> &debug::MemWatchpointRootTable {
@@ -374,6 +393,29 @@ impl<E: Engine> Arm9<E> {
                 }
             }
 
+            /// Adds memory watchpoints covering the inclusive address range `start..=end`.
+            #[doc(cfg(feature = "debugger-hooks"))]
+            pub fn add_mem_watchpoint_range(
+                &mut self,
+                (start, end): (u32, u32),
+                rw: debug::MemWatchpointRwMask,
+            ) {
+                debug::for_each_watchpoint_chunk((start, end), |addr, size| {
+                    self.add_mem_watchpoint(addr, size, rw);
+                });
+            }
+
+            #[doc(cfg(feature = "debugger-hooks"))]
+            pub fn remove_mem_watchpoint_range(
+                &mut self,
+                (start, end): (u32, u32),
+                rw: debug::MemWatchpointRwMask,
+            ) {
+                debug::for_each_watchpoint_chunk((start, end), |addr, size| {
+                    self.remove_mem_watchpoint(addr, size, rw);
+                });
+            }
+
             #[doc(cfg(feature = "debugger-hooks"))]
             #[inline]
             pub fn clear_mem_watchpoints(&mut self) {
@@ -1,6 +1,7 @@
 use super::super::{Engine, IrqFlags};
 
 use crate::{
+    camera,
     cpu::{
         arm9::{div_engine, sqrt_engine},
         bus::AccessType,
@@ -10,6 +11,7 @@ use crate::{
     emu::{input::KeyIrqControl, swram, Emu, GlobalExMemControl, LocalExMemControl},
     gpu::{self, engine_3d},
     ipc,
+    ndma::Ndma,
     utils::mem_prelude::*,
 };
 
@@ -36,6 +38,10 @@ pub fn read_8<A: AccessType, E: Engine>(emu: &mut Emu<E>, addr: u32) -> u8 {
                 .read()
         },
 
+        // Only reached when no shared WRAM is allocated to the ARM9, in which case it reads zeros
+        #[cfg(not(feature = "bft-r"))]
+        0x03 => 0,
+
         #[allow(clippy::match_same_arms)]
         0x04 => match addr & 0x00FF_FFFF {
             0x000..=0x003 | 0x008..=0x057 | 0x064..=0x067 | 0x06C..=0x06D => {
@@ -53,6 +59,17 @@ pub fn read_8<A: AccessType, E: Engine>(emu: &mut Emu<E>, addr: u32) -> u8 {
             0x061 => (emu.gpu.engine_3d.rendering_control().0 >> 8) as u8,
             0x062..=0x063 => 0,
 
+            0x0B0..=0x0DF => {
+                let offset = (addr & 0xFF) - 0xB0;
+                let channel = &emu.arm9.dma.channels[(offset / 12) as usize];
+                let value = match (offset % 12) >> 2 {
+                    0 => channel.src_addr,
+                    1 => channel.dst_addr,
+                    _ => channel.control.0,
+                };
+                (value >> ((addr & 3) << 3)) as u8
+            }
+
             0x0E0..=0x0EF => emu.arm9.dma_fill[addr as usize & 0xF],
 
             0x100 => emu.arm9.timers.read_counter(
@@ -147,6 +164,9 @@ pub fn read_8<A: AccessType, E: Engine>(emu: &mut Emu<E>, addr: u32) -> u8 {
             0x208 => emu.arm9.irqs.master_enable() as u8,
             0x209..=0x20B => 0,
 
+            0x210..=0x213 => (emu.arm9.irqs.enabled().0 >> ((addr & 3) << 3)) as u8,
+            0x214..=0x217 => (emu.arm9.irqs.requested().0 >> ((addr & 3) << 3)) as u8,
+
             0x240 => emu.gpu.vram.bank_control()[0].0,
             0x241 => emu.gpu.vram.bank_control()[1].0,
             0x242 => emu.gpu.vram.bank_control()[2].0,
@@ -157,14 +177,21 @@ pub fn read_8<A: AccessType, E: Engine>(emu: &mut Emu<E>, addr: u32) -> u8 {
             0x247 => emu.swram.control().0,
             0x248 => emu.gpu.vram.bank_control()[7].0,
             0x249 => emu.gpu.vram.bank_control()[8].0,
+            0x24A..=0x24B => 0,
 
             0x280 => emu.arm9.div_engine.control().0 as u8,
             0x281 => (emu.arm9.div_engine.control().0 >> 8) as u8,
             0x282..=0x283 => 0,
+            0x290..=0x297 => (emu.arm9.div_engine.num() >> ((addr & 7) << 3)) as u8,
+            0x298..=0x29F => (emu.arm9.div_engine.denom() >> ((addr & 7) << 3)) as u8,
+            0x2A0..=0x2A7 => (emu.arm9.div_engine.quot() >> ((addr & 7) << 3)) as u8,
+            0x2A8..=0x2AF => (emu.arm9.div_engine.rem() >> ((addr & 7) << 3)) as u8,
 
             0x2B0 => emu.arm9.sqrt_engine.control().0 as u8,
             0x2B1 => (emu.arm9.sqrt_engine.control().0 >> 8) as u8,
             0x2B2..=0x2B3 => 0,
+            0x2B4..=0x2B7 => (emu.arm9.sqrt_engine.result() >> ((addr & 3) << 3)) as u8,
+            0x2B8..=0x2BF => (emu.arm9.sqrt_engine.input() >> ((addr & 7) << 3)) as u8,
 
             0x300 => emu.arm9.post_boot_flag.0,
             0x301..=0x303 => 0,
@@ -178,6 +205,10 @@ pub fn read_8<A: AccessType, E: Engine>(emu: &mut Emu<E>, addr: u32) -> u8 {
             0x1000..=0x1003 | 0x1008..=0x1057 | 0x106C..=0x106D => {
                 emu.gpu.engine_2d_b.read_8::<A>(addr)
             }
+            0x106E..=0x106F => 0,
+
+            0x4500 if emu.camera.is_enabled() => emu.camera.i2c_data(),
+            0x4501 if emu.camera.is_enabled() => emu.camera.i2c_control().0,
 
             _ => {
                 #[cfg(feature = "log")]
@@ -209,7 +240,9 @@ pub fn read_8<A: AccessType, E: Engine>(emu: &mut Emu<E>, addr: u32) -> u8 {
             if emu.global_ex_mem_control().arm7_gba_slot_access() {
                 0
             } else {
-                (emu.arm9.local_ex_mem_control().gba_rom_halfword(addr) >> ((addr & 1) << 3)) as u8
+                (emu.gba_slot
+                    .read_rom_halfword(addr, emu.arm9.local_ex_mem_control())
+                    >> ((addr & 1) << 3)) as u8
             }
         }
 
@@ -217,7 +250,7 @@ pub fn read_8<A: AccessType, E: Engine>(emu: &mut Emu<E>, addr: u32) -> u8 {
             if emu.global_ex_mem_control().arm7_gba_slot_access() {
                 0
             } else {
-                0xFF
+                emu.gba_slot.read_sram(addr)
             }
         }
 
@@ -260,6 +293,10 @@ pub fn read_16<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32) -> u16
             )
         },
 
+        // Only reached when no shared WRAM is allocated to the ARM9, in which case it reads zeros
+        #[cfg(not(feature = "bft-r"))]
+        0x03 => 0,
+
         #[allow(clippy::match_same_arms)]
         0x04 => match addr & 0x00FF_FFFE {
             0x000..=0x002 | 0x008..=0x056 | 0x064 | 0x066 | 0x06C => {
@@ -374,12 +411,20 @@ pub fn read_16<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32) -> u16
                 emu.gpu.vram.bank_control()[7].0 as u16
                     | (emu.gpu.vram.bank_control()[8].0 as u16) << 8
             }
+            0x24A => 0,
 
             0x280 => emu.arm9.div_engine.control().0,
             0x282 => 0,
+            0x290..=0x296 => (emu.arm9.div_engine.num() >> ((addr & 6) << 3)) as u16,
+            0x298..=0x29E => (emu.arm9.div_engine.denom() >> ((addr & 6) << 3)) as u16,
+            0x2A0..=0x2A6 => (emu.arm9.div_engine.quot() >> ((addr & 6) << 3)) as u16,
+            0x2A8..=0x2AE => (emu.arm9.div_engine.rem() >> ((addr & 6) << 3)) as u16,
 
             0x2B0 => emu.arm9.sqrt_engine.control().0,
             0x2B2 => 0,
+            0x2B4 => emu.arm9.sqrt_engine.result() as u16,
+            0x2B6 => (emu.arm9.sqrt_engine.result() >> 16) as u16,
+            0x2B8..=0x2BE => (emu.arm9.sqrt_engine.input() >> ((addr & 6) << 3)) as u16,
 
             0x300 => emu.arm9.post_boot_flag.0 as u16,
             0x302 => 0,
@@ -390,6 +435,10 @@ pub fn read_16<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32) -> u16
             0x320..=0x6A2 => emu.gpu.engine_3d.read_16::<A>(addr as u16),
 
             0x1000..=0x1002 | 0x1008..=0x1056 | 0x106C => emu.gpu.engine_2d_b.read_16::<A>(addr),
+            0x106E => 0,
+
+            0x4200 if emu.camera.is_enabled() => emu.camera.module_control().0,
+            0x4202 if emu.camera.is_enabled() => emu.camera.control().0,
 
             _ => {
                 #[cfg(feature = "log")]
@@ -421,7 +470,8 @@ pub fn read_16<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32) -> u16
             if emu.global_ex_mem_control().arm7_gba_slot_access() {
                 0
             } else {
-                emu.arm9.local_ex_mem_control().gba_rom_halfword(addr)
+                emu.gba_slot
+                    .read_rom_halfword(addr, emu.arm9.local_ex_mem_control())
             }
         }
 
@@ -429,7 +479,7 @@ pub fn read_16<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32) -> u16
             if emu.global_ex_mem_control().arm7_gba_slot_access() {
                 0
             } else {
-                0xFFFF
+                emu.gba_slot.read_sram(addr) as u16 * 0x0101
             }
         }
 
@@ -472,6 +522,10 @@ pub fn read_32<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32) -> u32
             )
         },
 
+        // Only reached when no shared WRAM is allocated to the ARM9, in which case it reads zeros
+        #[cfg(not(feature = "bft-r"))]
+        0x03 => 0,
+
         0x04 => match addr & 0x00FF_FFFC {
             0x000 | 0x008..=0x054 | 0x064 | 0x06C => emu.gpu.engine_2d_a.read_32::<A>(addr),
 
@@ -582,15 +636,42 @@ pub fn read_32<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32) -> u32
             0x2B8 => emu.arm9.sqrt_engine.input() as u32,
             0x2BC => (emu.arm9.sqrt_engine.input() >> 32) as u32,
 
+            0x300 => emu.arm9.post_boot_flag.0 as u32,
+
+            0x304 => emu.gpu.power_control().0 as u32,
+
             0x320..=0x6A0 => emu.gpu.engine_3d.read_32::<A>(addr as u16),
 
             0x1000 | 0x1008..=0x1054 | 0x106C => emu.gpu.engine_2d_b.read_32::<A>(addr),
 
+            0x4100..=0x416C if emu.ndma.is_enabled() => {
+                emu.ndma.read_32((addr & 0xFFFC) as u16 - 0x4100)
+            }
+
+            0x4200 if emu.camera.is_enabled() => {
+                emu.camera.module_control().0 as u32 | (emu.camera.control().0 as u32) << 16
+            }
+            0x4204 if emu.camera.is_enabled() => {
+                if A::IS_DEBUG {
+                    emu.camera.peek_data()
+                } else {
+                    emu.camera.read_data(&mut emu.arm9.irqs, &mut emu.arm9.schedule)
+                }
+            }
+            0x4210 if emu.camera.is_enabled() => emu.camera.trimming_start(),
+            0x4214 if emu.camera.is_enabled() => emu.camera.trimming_end(),
+
             0x10_0000 => {
                 if A::IS_DEBUG {
                     emu.ipc.peek_9()
                 } else {
-                    emu.ipc.recv_9(&mut emu.arm7.irqs)
+                    let value = emu.ipc.recv_9(&mut emu.arm7.irqs);
+                    #[cfg(all(feature = "fault-injection", debug_assertions))]
+                    let value = emu
+                        .fault_injector
+                        .corrupt_ipc_recv(crate::cpu::Core::Arm9, value);
+                    trace_record!(emu, Arm9, IpcFifoRecv { value });
+                    value
                 }
             }
 
@@ -637,7 +718,8 @@ pub fn read_32<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32) -> u32
             if emu.global_ex_mem_control().arm7_gba_slot_access() {
                 0
             } else {
-                emu.arm9.local_ex_mem_control().gba_rom_word(addr)
+                emu.gba_slot
+                    .read_rom_word(addr, emu.arm9.local_ex_mem_control())
             }
         }
 
@@ -645,7 +727,7 @@ pub fn read_32<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32) -> u32
             if emu.global_ex_mem_control().arm7_gba_slot_access() {
                 0
             } else {
-                0xFFFF_FFFF
+                emu.gba_slot.read_sram(addr) as u32 * 0x0101_0101
             }
         }
 
@@ -686,6 +768,10 @@ pub fn write_8<A: AccessType, E: Engine>(emu: &mut Emu<E>, addr: u32, value: u8)
                 .write(value);
         },
 
+        // Only reached when no shared WRAM is allocated to the ARM9, which ignores the write
+        #[cfg(not(feature = "bft-w"))]
+        0x03 => {}
+
         #[allow(clippy::match_same_arms)]
         0x04 => match addr & 0x00FF_FFFF {
             0x000..=0x003 | 0x008..=0x057 | 0x064..=0x06D => {
@@ -703,13 +789,13 @@ pub fn write_8<A: AccessType, E: Engine>(emu: &mut Emu<E>, addr: u32, value: u8)
                 .gpu
                 .engine_3d
                 .write_rendering_control(engine_3d::RenderingControl(
-                    (emu.gpu.engine_3d.rendering_control().0 & 0x4F00) | value as u16,
+                    (emu.gpu.engine_3d.rendering_state().control.0 & 0x4F00) | value as u16,
                 )),
             0x061 => emu
                 .gpu
                 .engine_3d
                 .write_rendering_control(engine_3d::RenderingControl(
-                    (emu.gpu.engine_3d.rendering_control().0 & 0xFF) | (value as u16) << 8,
+                    (emu.gpu.engine_3d.rendering_state().control.0 & 0xFF) | (value as u16) << 8,
                 )),
             0x062 | 0x063 => {}
 
@@ -723,20 +809,41 @@ pub fn write_8<A: AccessType, E: Engine>(emu: &mut Emu<E>, addr: u32, value: u8)
             )),
 
             0x180 | 0x182 | 0x183 => {}
-            0x181 => emu.ipc.write_sync_9(
-                ipc::Sync((emu.ipc.sync_9().0 & 0x00FF) | (value as u16) << 8),
-                &mut emu.arm7.irqs,
-            ),
-            0x184 => emu.ipc.write_fifo_control_9(
-                ipc::FifoControl((emu.ipc.fifo_control_9().0 & 0xBF00) | value as u16),
-                &mut emu.arm9.irqs,
-                &mut emu.arm9.schedule,
-            ),
-            0x185 => emu.ipc.write_fifo_control_9(
-                ipc::FifoControl((emu.ipc.fifo_control_9().0 & 0x00FF) | (value as u16) << 8),
-                &mut emu.arm9.irqs,
-                &mut emu.arm9.schedule,
-            ),
+            0x181 => {
+                emu.ipc.write_sync_9(
+                    ipc::Sync((emu.ipc.sync_9().0 & 0x00FF) | (value as u16) << 8),
+                    &mut emu.arm7.irqs,
+                );
+                trace_record!(emu, Arm9, IpcSyncWrite { value: emu.ipc.sync_9().0 });
+            }
+            0x184 => {
+                emu.ipc.write_fifo_control_9(
+                    ipc::FifoControl((emu.ipc.fifo_control_9().0 & 0xBF00) | value as u16),
+                    &mut emu.arm9.irqs,
+                    &mut emu.arm9.schedule,
+                );
+                trace_record!(
+                    emu,
+                    Arm9,
+                    IpcFifoControlWrite {
+                        value: emu.ipc.fifo_control_9().0
+                    }
+                );
+            }
+            0x185 => {
+                emu.ipc.write_fifo_control_9(
+                    ipc::FifoControl((emu.ipc.fifo_control_9().0 & 0x00FF) | (value as u16) << 8),
+                    &mut emu.arm9.irqs,
+                    &mut emu.arm9.schedule,
+                );
+                trace_record!(
+                    emu,
+                    Arm9,
+                    IpcFifoControlWrite {
+                        value: emu.ipc.fifo_control_9().0
+                    }
+                );
+            }
             0x186 | 0x187 => {}
 
             0x1A0 => {
@@ -900,6 +1007,11 @@ pub fn write_8<A: AccessType, E: Engine>(emu: &mut Emu<E>, addr: u32, value: u8)
                 emu.gpu.engine_2d_b.write_8::<A>(addr, value);
             }
 
+            0x4500 if emu.camera.is_enabled() => emu.camera.write_i2c_data(value),
+            0x4501 if emu.camera.is_enabled() => {
+                emu.camera.write_i2c_control(camera::I2cControl(value));
+            }
+
             _ =>
             {
                 #[cfg(feature = "log")]
@@ -914,6 +1026,12 @@ pub fn write_8<A: AccessType, E: Engine>(emu: &mut Emu<E>, addr: u32, value: u8)
             }
         },
 
+        0x0A => {
+            if !emu.global_ex_mem_control().arm7_gba_slot_access() {
+                emu.gba_slot.write_sram(addr, value);
+            }
+        }
+
         _ =>
         {
             #[cfg(feature = "log")]
@@ -949,6 +1067,10 @@ pub fn write_16<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32, value
             );
         },
 
+        // Only reached when no shared WRAM is allocated to the ARM9, which ignores the write
+        #[cfg(not(feature = "bft-w"))]
+        0x03 => {}
+
         0x04 => {
             #[allow(clippy::match_same_arms)]
             match addr & 0x00FF_FFFE {
@@ -1105,13 +1227,25 @@ pub fn write_16<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32, value
 
                 0x132 => emu.write_arm9_key_irq_control(KeyIrqControl(value)),
 
-                0x180 => emu.ipc.write_sync_9(ipc::Sync(value), &mut emu.arm7.irqs),
+                0x180 => {
+                    emu.ipc.write_sync_9(ipc::Sync(value), &mut emu.arm7.irqs);
+                    trace_record!(emu, Arm9, IpcSyncWrite { value: emu.ipc.sync_9().0 });
+                }
                 0x182 => {}
-                0x184 => emu.ipc.write_fifo_control_9(
-                    ipc::FifoControl(value),
-                    &mut emu.arm9.irqs,
-                    &mut emu.arm9.schedule,
-                ),
+                0x184 => {
+                    emu.ipc.write_fifo_control_9(
+                        ipc::FifoControl(value),
+                        &mut emu.arm9.irqs,
+                        &mut emu.arm9.schedule,
+                    );
+                    trace_record!(
+                        emu,
+                        Arm9,
+                        IpcFifoControlWrite {
+                            value: emu.ipc.fifo_control_9().0
+                        }
+                    );
+                }
                 0x186 => {}
 
                 0x1A0 => {
@@ -1301,6 +1435,13 @@ pub fn write_16<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32, value
                     emu.gpu.engine_2d_b.write_16::<A>(addr, value);
                 }
 
+                0x4200 if emu.camera.is_enabled() => {
+                    emu.camera.write_module_control(camera::ModuleControl(value));
+                }
+                0x4202 if emu.camera.is_enabled() => {
+                    emu.camera.write_control(camera::Control(value));
+                }
+
                 _ =>
                 {
                     #[cfg(feature = "log")]
@@ -1328,6 +1469,19 @@ pub fn write_16<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32, value
 
         0x07 => emu.gpu.vram.write_oam(addr & 0x7FE, value),
 
+        0x08 | 0x09 => {
+            if !emu.global_ex_mem_control().arm7_gba_slot_access() {
+                emu.gba_slot.write_rom_halfword(addr, value);
+            }
+        }
+
+        0x0A => {
+            if !emu.global_ex_mem_control().arm7_gba_slot_access() {
+                emu.gba_slot
+                    .write_sram(addr, (value >> ((addr & 1) << 3)) as u8);
+            }
+        }
+
         _ =>
         {
             #[cfg(feature = "log")]
@@ -1363,6 +1517,10 @@ pub fn write_32<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32, mut v
             );
         },
 
+        // Only reached when no shared WRAM is allocated to the ARM9, which ignores the write
+        #[cfg(not(feature = "bft-w"))]
+        0x03 => {}
+
         0x04 => {
             match addr & 0x00FF_FFFC {
                 0x000 | 0x008..=0x054 | 0x064..=0x06C => {
@@ -1444,15 +1602,28 @@ pub fn write_32<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32, mut v
 
                 0x130 => emu.write_arm9_key_irq_control(KeyIrqControl((value >> 16) as u16)),
 
-                0x180 => emu
-                    .ipc
-                    .write_sync_9(ipc::Sync(value as u16), &mut emu.arm7.irqs),
-                0x184 => emu.ipc.write_fifo_control_9(
-                    ipc::FifoControl(value as u16),
-                    &mut emu.arm9.irqs,
-                    &mut emu.arm9.schedule,
-                ),
-                0x188 => emu.ipc.send_9(value, &mut emu.arm7.irqs),
+                0x180 => {
+                    emu.ipc.write_sync_9(ipc::Sync(value as u16), &mut emu.arm7.irqs);
+                    trace_record!(emu, Arm9, IpcSyncWrite { value: emu.ipc.sync_9().0 });
+                }
+                0x184 => {
+                    emu.ipc.write_fifo_control_9(
+                        ipc::FifoControl(value as u16),
+                        &mut emu.arm9.irqs,
+                        &mut emu.arm9.schedule,
+                    );
+                    trace_record!(
+                        emu,
+                        Arm9,
+                        IpcFifoControlWrite {
+                            value: emu.ipc.fifo_control_9().0
+                        }
+                    );
+                }
+                0x188 => {
+                    emu.ipc.send_9(value, &mut emu.arm7.irqs);
+                    trace_record!(emu, Arm9, IpcFifoSend { value });
+                }
 
                 0x1A0 => {
                     if emu.ds_slot.arm9_access() {
@@ -1642,6 +1813,27 @@ pub fn write_32<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32, mut v
                     emu.gpu.engine_2d_b.write_32::<A>(addr, value);
                 }
 
+                0x4100..=0x416C if emu.ndma.is_enabled() => {
+                    Ndma::write_32(emu, (addr & 0xFFFC) as u16 - 0x4100, value);
+                }
+
+                0x4200 if emu.camera.is_enabled() => {
+                    emu.camera.write_module_control(camera::ModuleControl(value as u16));
+                    emu.camera.write_control(camera::Control((value >> 16) as u16));
+                }
+                0x4210 if emu.camera.is_enabled() => emu.camera.write_trimming_start(value),
+                0x4214 if emu.camera.is_enabled() => emu.camera.write_trimming_end(value),
+
+                0x10_0010 => {
+                    if emu.ds_slot.arm9_access() {
+                        emu.ds_slot.write_rom_data_arm9(
+                            value,
+                            &mut emu.arm9.irqs,
+                            &mut emu.arm9.schedule,
+                        );
+                    }
+                }
+
                 _ =>
                 {
                     #[cfg(feature = "log")]
@@ -1669,6 +1861,21 @@ pub fn write_32<A: AccessType, E: Engine>(emu: &mut Emu<E>, mut addr: u32, mut v
 
         0x07 => emu.gpu.vram.write_oam(addr & 0x7FC, value),
 
+        0x08 | 0x09 => {
+            if !emu.global_ex_mem_control().arm7_gba_slot_access() {
+                emu.gba_slot.write_rom_halfword(addr, value as u16);
+                emu.gba_slot
+                    .write_rom_halfword(addr | 2, (value >> 16) as u16);
+            }
+        }
+
+        0x0A => {
+            if !emu.global_ex_mem_control().arm7_gba_slot_access() {
+                emu.gba_slot
+                    .write_sram(addr, (value >> ((addr & 3) << 3)) as u8);
+            }
+        }
+
         _ =>
         {
             #[cfg(feature = "log")]
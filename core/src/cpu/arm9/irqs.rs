@@ -40,11 +40,19 @@ proc_bitfield::bitfield! {
         pub ds_slot_transfer_complete: bool @ 19,   // x
         pub ds_slot_ext: bool @ 20,                 // -
         pub gx_fifo: bool @ 21,                     // x
+        pub dsi_camera: bool @ 25,                  // x
+        pub dsi_ndma0: bool @ 28,                   // x
+        pub dsi_ndma1: bool @ 29,                   // x
+        pub dsi_ndma2: bool @ 30,                   // x
+        pub dsi_ndma3: bool @ 31,                   // x
     }
 }
 
 #[derive(Savestate)]
+#[load(in_place_only)]
 pub struct Irqs {
+    #[savestate(skip)]
+    mask: u32,
     enabled: IrqFlags,
     requested: IrqFlags,
     master_enable: bool,
@@ -55,8 +63,9 @@ pub struct Irqs {
 }
 
 impl Irqs {
-    pub(super) fn new() -> Self {
+    pub(super) fn new(dsi: bool) -> Self {
         Irqs {
+            mask: if dsi { 0xF23F_3F7F } else { 0x003F_3F7F },
             enabled: IrqFlags(0),
             requested: IrqFlags(0),
             master_enable: false,
@@ -143,13 +152,13 @@ impl Irqs {
 
     #[inline]
     pub fn write_enabled<S: ScheduleUpdate>(&mut self, value: IrqFlags, schedule: S) {
-        self.enabled = IrqFlags(value.0 & 0x003F_3F7F);
+        self.enabled = IrqFlags(value.0 & self.mask);
         self.update_pending(schedule);
     }
 
     #[inline]
     pub fn write_requested<S: ScheduleUpdate>(&mut self, value: IrqFlags, schedule: S) {
-        self.requested = IrqFlags(value.0 & 0x003F_3F7F);
+        self.requested = IrqFlags(value.0 & self.mask);
         self.update_pending(schedule);
     }
 
@@ -1,6 +1,6 @@
 use crate::{
     cpu::{self, timers},
-    emu,
+    emu, ndma,
     utils::{def_event_slot_index, def_event_slots, def_timestamp, schedule, Savestate},
 };
 
@@ -38,12 +38,13 @@ impl From<Timestamp> for timers::Timestamp {
 pub enum Event {
     #[default]
     DsSlotRomDataReady, // Max 1
-    DsSlotSpiDataReady,      // Max 1
-    DivResultReady,          // Max 1
-    SqrtResultReady,         // Max 1
-    Timer(timers::Index),    // Max 4
-    GxFifoStall,             // Max 1
-    Engine3dCommandFinished, // Max 1
+    DsSlotSpiDataReady,        // Max 1
+    DivResultReady,            // Max 1
+    SqrtResultReady,           // Max 1
+    Timer(timers::Index),      // Max 4
+    GxFifoStall,               // Max 1
+    Engine3dCommandFinished,   // Max 1
+    NdmaTransfer(ndma::Index), // Max 4
 }
 
 def_event_slots! {
@@ -56,6 +57,7 @@ def_event_slots! {
     TIMERS_START..TIMERS_END 4,
     GX_FIFO,
     ENGINE_3D,
+    NDMA_START..NDMA_END 4,
 }
 
 def_event_slot_index!(bounded_esi, event_slots, pub struct EventSlotIndex(u8));
@@ -53,3 +53,6 @@ impl AccessType for DebugDmaAccess {
     const IS_DMA: bool = true;
     const IS_DEBUG: bool = true;
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,226 @@
+use super::CpuAccess;
+use crate::{
+    cpu::{arm7, arm9, interpreter::Interpreter, Core},
+    emu::{testing, Emu},
+};
+
+fn read(emu: &mut Emu<Interpreter>, core: Core, size: u8, addr: u32) -> u32 {
+    match (core, size) {
+        (Core::Arm7, 1) => arm7::bus::read_8::<CpuAccess, _>(emu, addr) as u32,
+        (Core::Arm7, 2) => arm7::bus::read_16::<CpuAccess, _>(emu, addr) as u32,
+        (Core::Arm7, _) => arm7::bus::read_32::<CpuAccess, _>(emu, addr),
+        (Core::Arm9, 1) => arm9::bus::read_8::<CpuAccess, _>(emu, addr) as u32,
+        (Core::Arm9, 2) => arm9::bus::read_16::<CpuAccess, _>(emu, addr) as u32,
+        (Core::Arm9, _) => arm9::bus::read_32::<CpuAccess, _, false>(emu, addr),
+    }
+}
+
+fn write_32(emu: &mut Emu<Interpreter>, core: Core, addr: u32, value: u32) {
+    match core {
+        Core::Arm7 => arm7::bus::write_32::<CpuAccess, _>(emu, addr, value),
+        Core::Arm9 => arm9::bus::write_32::<CpuAccess, _>(emu, addr, value),
+    }
+}
+
+fn write_io_8(emu: &mut Emu<Interpreter>, addr: u32, value: u8) {
+    arm9::bus::write_8::<CpuAccess, _>(emu, addr, value);
+}
+
+/// Checks that a 32-bit value is read back at all widths from each of the given addresses.
+#[track_caller]
+fn check_word(emu: &mut Emu<Interpreter>, core: Core, addrs: &[u32], value: u32) {
+    for &addr in addrs {
+        assert_eq!(
+            read(emu, core, 4, addr),
+            value,
+            "{core:?} read32 @ {addr:#010X}"
+        );
+        for offset in [0, 2] {
+            assert_eq!(
+                read(emu, core, 2, addr | offset),
+                value >> (offset << 3) & 0xFFFF,
+                "{core:?} read16 @ {:#010X}",
+                addr | offset,
+            );
+        }
+        for offset in 0..4 {
+            assert_eq!(
+                read(emu, core, 1, addr | offset),
+                value >> (offset << 3) & 0xFF,
+                "{core:?} read8 @ {:#010X}",
+                addr | offset,
+            );
+        }
+    }
+}
+
+#[test]
+fn main_mem_mirroring() {
+    let mut emu = testing::build();
+    for (core, addr, value) in [
+        (Core::Arm9, 0x0200_1000, 0x1234_5678),
+        (Core::Arm7, 0x02BF_FFFC, 0x9ABC_DEF0),
+    ] {
+        write_32(&mut emu, core, addr, value);
+        let offset = addr & 0x3F_FFFF;
+        let mirrors =
+            [0x0200_0000, 0x0240_0000, 0x0280_0000, 0x02C0_0000].map(|base| base | offset);
+        check_word(&mut emu, Core::Arm7, &mirrors, value);
+        check_word(&mut emu, Core::Arm9, &mirrors, value);
+    }
+}
+
+#[test]
+fn wram_mirroring() {
+    const A: u32 = 0x1111_1111;
+    const B: u32 = 0x2222_2222;
+    const C: u32 = 0x3333_3333;
+
+    let mut emu = testing::build();
+    // Fill both halves of the shared WRAM while the ARM9 has all of it, and the ARM7 WRAM
+    write_io_8(&mut emu, 0x0400_0247, 0);
+    write_32(&mut emu, Core::Arm9, 0x0300_0000, A);
+    write_32(&mut emu, Core::Arm9, 0x0300_4000, B);
+    write_32(&mut emu, Core::Arm7, 0x0380_0000, C);
+
+    // For each WRAMCNT value, the values read by each CPU from 0x0300_0000, 0x0300_4000,
+    // 0x0300_8000 and the last 16 KiB of the shared WRAM region; the ARM7 sees its own WRAM there
+    // if it has no shared WRAM allocated
+    for (layout, arm9_values, arm7_values) in [
+        (0, [A, B, A, B], [C, 0, 0, 0]),
+        (1, [B, B, B, B], [A, A, A, A]),
+        (2, [A, A, A, A], [B, B, B, B]),
+        (3, [0, 0, 0, 0], [A, B, A, B]),
+    ] {
+        write_io_8(&mut emu, 0x0400_0247, layout);
+        for (core, values) in [(Core::Arm9, arm9_values), (Core::Arm7, arm7_values)] {
+            for (addr, value) in [0x0300_0000, 0x0300_4000, 0x0300_8000, 0x037F_C000]
+                .into_iter()
+                .zip(values)
+            {
+                check_word(&mut emu, core, &[addr], value);
+            }
+        }
+        // The ARM7 WRAM is always mapped above the shared WRAM region, mirrored every 64 KiB
+        check_word(
+            &mut emu,
+            Core::Arm7,
+            &[0x0380_0000, 0x0381_0000, 0x03FF_0000],
+            C,
+        );
+        // The ARM9 sees its own shared WRAM mapping there instead
+        check_word(&mut emu, Core::Arm9, &[0x0380_0000], arm9_values[0]);
+    }
+}
+
+#[test]
+fn vram_mirroring() {
+    const X: u32 = 0xCAFE_BABE;
+    const Y: u32 = 0x0BAD_F00D;
+
+    let mut emu = testing::build();
+
+    // Bank A in LCDC mode is mirrored every 1 MiB, with no other banks mapped
+    write_io_8(&mut emu, 0x0400_0240, 0x80);
+    write_32(&mut emu, Core::Arm9, 0x0680_0000, X);
+    check_word(
+        &mut emu,
+        Core::Arm9,
+        &[0x0680_0000, 0x0690_0000, 0x06F0_0000],
+        X,
+    );
+    check_word(
+        &mut emu,
+        Core::Arm9,
+        &[0x0682_0000, 0x068A_0000, 0x068B_0000],
+        0,
+    );
+
+    // As engine A BG VRAM, 512 KiB are mirrored in a 2 MiB region
+    write_io_8(&mut emu, 0x0400_0240, 0x81);
+    check_word(
+        &mut emu,
+        Core::Arm9,
+        &[0x0600_0000, 0x0608_0000, 0x0618_0000],
+        X,
+    );
+    check_word(&mut emu, Core::Arm9, &[0x0680_0000, 0x0620_0000], 0);
+
+    // With an offset of 1, it moves to the second 128 KiB block
+    write_io_8(&mut emu, 0x0400_0240, 0x89);
+    check_word(&mut emu, Core::Arm9, &[0x0602_0000, 0x060A_0000], X);
+    check_word(&mut emu, Core::Arm9, &[0x0600_0000], 0);
+
+    // As engine A OBJ VRAM, 256 KiB are mirrored in a 2 MiB region
+    write_io_8(&mut emu, 0x0400_0240, 0x82);
+    check_word(
+        &mut emu,
+        Core::Arm9,
+        &[0x0640_0000, 0x0644_0000, 0x065C_0000],
+        X,
+    );
+
+    // Banks C and D mapped to the ARM7 are mirrored every 256 KiB in the whole VRAM region
+    write_io_8(&mut emu, 0x0400_0242, 0x80);
+    write_32(&mut emu, Core::Arm9, 0x0684_0000, Y);
+    write_io_8(&mut emu, 0x0400_0242, 0x82);
+    check_word(
+        &mut emu,
+        Core::Arm7,
+        &[0x0600_0000, 0x0604_0000, 0x06FC_0000],
+        Y,
+    );
+    check_word(&mut emu, Core::Arm7, &[0x0602_0000, 0x0606_0000], 0);
+}
+
+#[test]
+fn palette_oam_mirroring() {
+    let mut emu = testing::build();
+    for (addr, mirrors, value) in [
+        (0x0500_0000, [0x0500_0800, 0x05FF_F800], 0x7FFF_001F),
+        (0x0500_0400, [0x0500_0C00, 0x0540_0400], 0x03E0_7C00),
+        (0x0700_0000, [0x0700_0800, 0x07FF_F800], 0x0123_4567),
+        (0x0700_0400, [0x0700_0C00, 0x0740_0400], 0x89AB_CDEF),
+    ] {
+        write_32(&mut emu, Core::Arm9, addr, value);
+        check_word(&mut emu, Core::Arm9, &mirrors, value);
+        // The ARM7 can't access either
+        check_word(&mut emu, Core::Arm7, &mirrors, 0);
+    }
+}
+
+#[test]
+fn gba_slot_open_bus() {
+    let mut emu = testing::build();
+
+    // Without a cartridge, the ROM region returns the halfword address on the data bus (with some
+    // bits pulled high at the default access time) and SRAM reads return all ones; the CPU the
+    // slot isn't allocated to reads zeros
+    for (arm7_gba_slot_access, arm9_values, arm7_values) in [
+        (false, [0xFF1B_FF1A, 0xFFFF_FFFF], [0, 0]),
+        (true, [0, 0], [0xFF1B_FF1A, 0xFFFF_FFFF]),
+    ] {
+        write_io_8(&mut emu, 0x0400_0204, (arm7_gba_slot_access as u8) << 7);
+        for (core, values) in [(Core::Arm9, arm9_values), (Core::Arm7, arm7_values)] {
+            check_word(&mut emu, core, &[0x0800_1234, 0x0900_1234], values[0]);
+            check_word(&mut emu, core, &[0x0A00_0000, 0x0A00_FFF0], values[1]);
+        }
+    }
+}
+
+#[test]
+fn unmapped_regions() {
+    let mut emu = testing::build();
+    for (core, addr) in [
+        (Core::Arm9, 0x0B00_0000),
+        (Core::Arm9, 0x1000_0000),
+        (Core::Arm9, 0xFF00_0000),
+        (Core::Arm9, 0xFFFF_1000),
+        (Core::Arm7, 0x0000_4000),
+        (Core::Arm7, 0x0100_0000),
+        (Core::Arm7, 0x0B00_0000),
+        (Core::Arm7, 0xFFFF_0000),
+    ] {
+        check_word(&mut emu, core, &[addr], 0);
+    }
+}
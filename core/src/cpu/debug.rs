@@ -1,5 +1,8 @@
 use crate::{cpu::Engine, emu::Emu};
 use bitflags::bitflags;
+use std::{cell::RefCell, rc::Rc};
+
+pub use super::Core;
 
 #[repr(transparent)]
 pub struct MemWatchpointRootTable(pub [Option<Box<MemWatchpointSubTable>>; 0x800]);
@@ -155,6 +158,23 @@ impl MemWatchpointRootTable {
     }
 }
 
+/// Splits the inclusive address range `start..=end` into naturally aligned chunks small enough to
+/// be used as single memory watchpoints; reversed ranges are swapped.
+pub(super) fn for_each_watchpoint_chunk((start, end): (u32, u32), mut f: impl FnMut(u32, u8)) {
+    let (start, end) = (start.min(end), start.max(end));
+    let mut addr = start;
+    loop {
+        let remaining = (end - addr) as u64 + 1;
+        let size = (1_u64 << addr.trailing_zeros().min(MWLT_BYTES_PER_ENTRY_SHIFT))
+            .min(1 << remaining.ilog2());
+        f(addr, size as u8);
+        match addr.checked_add(size as u32) {
+            Some(next_addr) if next_addr <= end => addr = next_addr,
+            _ => break,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MemWatchpointTriggerCause {
     Read,
@@ -189,6 +209,89 @@ pub type DataAbortHook<E> = Hook<dyn FnMut(&mut Emu<E>, u32) -> bool>;
 pub type BreakpointHook<E> = Hook<dyn FnMut(&mut Emu<E>, u32) -> bool>;
 pub type MemWatchpointHook<E> =
     Hook<dyn FnMut(&mut Emu<E>, u32, u8, MemWatchpointTriggerCause) -> bool>;
+pub type IrqHook<E> = Hook<dyn FnMut(&mut Emu<E>, u32) -> bool>;
+
+/// Receives breakpoint, memory watchpoint and IRQ entry events from both CPUs, for frontends that
+/// prefer handling them in one place rather than installing each hook separately.
+///
+/// Returning `true` from any of the methods stops the CPU that triggered the event, the same way
+/// returning `true` from the corresponding hook would. The handler stays borrowed while its methods
+/// are running, so they can't install a different handler.
+pub trait EventHandler<E: Engine> {
+    fn breakpoint(&mut self, _emu: &mut Emu<E>, _core: Core, _addr: u32) -> bool {
+        true
+    }
+
+    fn mem_watchpoint(
+        &mut self,
+        _emu: &mut Emu<E>,
+        _core: Core,
+        _addr: u32,
+        _size: u8,
+        _cause: MemWatchpointTriggerCause,
+    ) -> bool {
+        true
+    }
+
+    /// Called right after the CPU enters the IRQ handler, with the address it will return to.
+    fn irq(&mut self, _emu: &mut Emu<E>, _core: Core, _return_addr: u32) -> bool {
+        false
+    }
+}
+
+impl<E: Engine> Emu<E> {
+    /// Installs breakpoint, memory watchpoint and IRQ hooks on both CPUs forwarding events to
+    /// `handler`, or removes them if `None`.
+    pub fn set_debug_event_handler(&mut self, handler: Option<Rc<RefCell<dyn EventHandler<E>>>>) {
+        let Some(handler) = handler else {
+            self.arm7.set_breakpoint_hook(None);
+            self.arm7.set_mem_watchpoint_hook(None);
+            self.arm7.set_irq_hook(None);
+            self.arm9.set_breakpoint_hook(None);
+            self.arm9.set_mem_watchpoint_hook(None);
+            self.arm9.set_irq_hook(None);
+            return;
+        };
+
+        macro_rules! install_hooks {
+            ($core: ident, $core_enum: ident) => {{
+                let handler_ = Rc::clone(&handler);
+                self.$core
+                    .set_breakpoint_hook(Some(BreakpointHook::new(Box::new(
+                        move |emu: &mut Emu<E>, addr| {
+                            handler_
+                                .borrow_mut()
+                                .breakpoint(emu, Core::$core_enum, addr)
+                        },
+                    ))));
+                let handler_ = Rc::clone(&handler);
+                self.$core
+                    .set_mem_watchpoint_hook(Some(MemWatchpointHook::new(Box::new(
+                        move |emu: &mut Emu<E>, addr, size, cause| {
+                            handler_.borrow_mut().mem_watchpoint(
+                                emu,
+                                Core::$core_enum,
+                                addr,
+                                size,
+                                cause,
+                            )
+                        },
+                    ))));
+                let handler_ = Rc::clone(&handler);
+                self.$core.set_irq_hook(Some(IrqHook::new(Box::new(
+                    move |emu: &mut Emu<E>, return_addr| {
+                        handler_
+                            .borrow_mut()
+                            .irq(emu, Core::$core_enum, return_addr)
+                    },
+                ))));
+            }};
+        }
+
+        install_hooks!(arm7, Arm7);
+        install_hooks!(arm9, Arm9);
+    }
+}
 
 pub(super) struct Arm7Data<E: Engine> {
     pub swi_hook: Option<SwiHook<E>>,
@@ -197,6 +300,7 @@ pub(super) struct Arm7Data<E: Engine> {
     pub breakpoint_hook: Option<BreakpointHook<E>>,
     pub mem_watchpoint_hook: Option<MemWatchpointHook<E>>,
     pub mem_watchpoints: Box<MemWatchpointRootTable>,
+    pub irq_hook: Option<IrqHook<E>>,
 }
 
 impl<E: Engine> Arm7Data<E> {
@@ -208,6 +312,7 @@ impl<E: Engine> Arm7Data<E> {
             breakpoint_hook: None,
             mem_watchpoint_hook: None,
             mem_watchpoints: unsafe { Box::new_zeroed().assume_init() },
+            irq_hook: None,
         }
     }
 }
@@ -221,6 +326,7 @@ pub(super) struct Arm9Data<E: Engine> {
     pub mem_watchpoints: Box<MemWatchpointRootTable>,
     pub prefetch_abort_hook: Option<PrefetchAbortHook<E>>,
     pub data_abort_hook: Option<DataAbortHook<E>>,
+    pub irq_hook: Option<IrqHook<E>>,
 }
 
 impl<E: Engine> Arm9Data<E> {
@@ -234,6 +340,105 @@ impl<E: Engine> Arm9Data<E> {
             mem_watchpoints: unsafe { Box::new_zeroed().assume_init() },
             prefetch_abort_hook: None,
             data_abort_hook: None,
+            irq_hook: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cpu::{arm7, arm9, bus::CpuAccess, interpreter::Interpreter},
+        emu::testing,
+    };
+
+    fn watchpoint_chunks(range: (u32, u32)) -> Vec<(u32, u8)> {
+        let mut chunks = Vec::new();
+        for_each_watchpoint_chunk(range, |addr, size| chunks.push((addr, size)));
+        chunks
+    }
+
+    #[test]
+    fn watchpoint_range_splitting() {
+        assert_eq!(
+            watchpoint_chunks((0x01, 0x1E)),
+            [
+                (0x01, 1),
+                (0x02, 2),
+                (0x04, 4),
+                (0x08, 8),
+                (0x10, 8),
+                (0x18, 4),
+                (0x1C, 2),
+                (0x1E, 1)
+            ],
+        );
+        assert_eq!(
+            watchpoint_chunks((0x0200_0004, 0x0200_0004)),
+            [(0x0200_0004, 1)]
+        );
+        // The end of the address space is reached without overflowing
+        assert_eq!(
+            watchpoint_chunks((0xFFFF_FFFE, 0xFFFF_FFFF)),
+            [(0xFFFF_FFFE, 2)]
+        );
+    }
+
+    #[test]
+    fn reversed_watchpoint_range() {
+        assert_eq!(
+            watchpoint_chunks((0x10, 0x0F)),
+            watchpoint_chunks((0x0F, 0x10))
+        );
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        mem_watchpoints: Vec<(Core, u32, u8, MemWatchpointTriggerCause)>,
+    }
+
+    impl EventHandler<Interpreter> for Recorder {
+        fn mem_watchpoint(
+            &mut self,
+            _emu: &mut Emu<Interpreter>,
+            core: Core,
+            addr: u32,
+            size: u8,
+            cause: MemWatchpointTriggerCause,
+        ) -> bool {
+            self.mem_watchpoints.push((core, addr, size, cause));
+            true
         }
     }
+
+    #[test]
+    fn event_handler_delivery() {
+        let mut emu = testing::build();
+        let recorder = Rc::new(RefCell::new(Recorder::default()));
+        let handler: Rc<RefCell<dyn EventHandler<Interpreter>>> = Rc::clone(&recorder);
+        emu.set_debug_event_handler(Some(handler));
+        emu.arm7
+            .add_mem_watchpoint_range((0x0200_0107, 0x0200_0100), MemWatchpointRwMask::WRITE);
+        emu.arm9
+            .add_mem_watchpoint_range((0x0200_0200, 0x0200_0203), MemWatchpointRwMask::READ);
+
+        arm7::bus::write_32::<CpuAccess, _>(&mut emu, 0x0200_0104, 1);
+        arm7::bus::read_32::<CpuAccess, _>(&mut emu, 0x0200_0104);
+        arm9::bus::write_32::<CpuAccess, _>(&mut emu, 0x0200_0200, 1);
+        arm9::bus::read_16::<CpuAccess, _>(&mut emu, 0x0200_0202);
+        assert_eq!(
+            recorder.borrow().mem_watchpoints,
+            [
+                (Core::Arm7, 0x0200_0104, 4, MemWatchpointTriggerCause::Write),
+                (Core::Arm9, 0x0200_0202, 2, MemWatchpointTriggerCause::Read),
+            ],
+        );
+        assert!(emu.arm7.is_stopped && emu.arm9.is_stopped);
+
+        // Removing the handler stops events from being delivered
+        emu.set_debug_event_handler(None);
+        arm7::bus::write_32::<CpuAccess, _>(&mut emu, 0x0200_0104, 1);
+        assert_eq!(recorder.borrow().mem_watchpoints.len(), 2);
+    }
 }
@@ -1,3 +1,5 @@
+#[cfg(all(feature = "fault-injection", debug_assertions))]
+use crate::utils::schedule::RawTimestamp;
 use crate::utils::{ReadSavestate, Savestate, WriteSavestate};
 
 proc_bitfield::bitfield! {
@@ -152,6 +154,14 @@ pub struct Controller<T: Copy, BU> {
     #[store(with = "store_optional_index(*cur_channel, save)")]
     pub(crate) cur_channel: Option<Index>,
     pub(crate) running_channels: u8,
+    /// The delay to apply to each channel's next transfer completion, if one was injected.
+    #[cfg(all(feature = "fault-injection", debug_assertions))]
+    #[savestate(skip)]
+    pub(crate) completion_delays: [Option<RawTimestamp>; 4],
+    /// The time each channel's delayed transfer completion is due at, if any.
+    #[cfg(all(feature = "fault-injection", debug_assertions))]
+    #[savestate(skip)]
+    pub(crate) delayed_completions: [Option<RawTimestamp>; 4],
 }
 
 impl<T: Copy, BU> Controller<T, BU> {
@@ -165,6 +175,30 @@ impl<T: Copy, BU> Controller<T, BU> {
         self.running_channels
     }
 
+    /// Consumes the completion delay injected for the given channel, if any, returning whether the
+    /// completion of its current transfer should be postponed.
+    #[cfg(all(feature = "fault-injection", debug_assertions))]
+    pub(crate) fn delay_completion(&mut self, i: Index, cur_time: RawTimestamp) -> bool {
+        let Some(delay) = self.completion_delays[i.get() as usize].take() else {
+            return false;
+        };
+        self.delayed_completions[i.get() as usize] = Some(cur_time + delay);
+        true
+    }
+
+    /// Returns the channels whose delayed transfer completions are due, as a bitmask.
+    #[cfg(all(feature = "fault-injection", debug_assertions))]
+    pub(crate) fn take_due_completions(&mut self, cur_time: RawTimestamp) -> u8 {
+        let mut due = 0;
+        for (i, completion) in self.delayed_completions.iter_mut().enumerate() {
+            if completion.is_some_and(|time| time <= cur_time) {
+                *completion = None;
+                due |= 1 << i;
+            }
+        }
+        due
+    }
+
     #[inline]
     pub(crate) fn switch_to_max_priority_running_channel(&mut self) {
         let trailing_zeros = self.running_channels.trailing_zeros() as u8;
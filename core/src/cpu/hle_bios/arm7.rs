@@ -3,7 +3,7 @@ use crate::{
     cpu::{
         arm7::{self, bus},
         bus::CpuAccess,
-        CoreData, Engine, Schedule,
+        Core, CoreData, Engine, Schedule,
     },
     emu::Emu,
     utils::{mem_prelude::*, Savestate},
@@ -166,60 +166,32 @@ pub static BIOS: [u8; arm7::BIOS_SIZE] = {
     bytes
 };
 
-fn bit_unpack<E: Engine>(
-    _emu: &mut Emu<E>,
-    _src_addr: u32,
-    _dst_addr: u32,
-    _unpack_data_addr: u32,
-) {
-    // TODO
-    #[cfg(feature = "log")]
-    slog::error!(_emu.arm7.logger, "Unimplemented BitUnPack SWI");
-}
+struct Mem;
 
-fn huff_uncomp_read_callback<E: Engine>(_emu: &mut Emu<E>) {
-    // TODO
-    #[cfg(feature = "log")]
-    slog::error!(
-        _emu.arm7.logger,
-        "Unimplemented HuffUnCompReadByCallback SWI"
-    );
-}
+impl<E: Engine> common::Mem<E> for Mem {
+    fn read_8(emu: &mut Emu<E>, addr: u32) -> u8 {
+        bus::read_8::<CpuAccess, _>(emu, addr)
+    }
 
-fn lz77_uncomp_read_normal_write_8<E: Engine>(_emu: &mut Emu<E>) {
-    // TODO
-    #[cfg(feature = "log")]
-    slog::error!(
-        _emu.arm7.logger,
-        "Unimplemented LZ77UnCompReadNormalWrite8bit SWI"
-    );
-}
+    fn read_16(emu: &mut Emu<E>, addr: u32) -> u16 {
+        bus::read_16::<CpuAccess, _>(emu, addr)
+    }
 
-fn lz77_uncomp_read_callback_write_16<E: Engine>(_emu: &mut Emu<E>) {
-    // TODO
-    #[cfg(feature = "log")]
-    slog::error!(
-        _emu.arm7.logger,
-        "Unimplemented LZ77UnCompReadByCallbackWrite16bit SWI"
-    );
-}
+    fn read_32(emu: &mut Emu<E>, addr: u32) -> u32 {
+        bus::read_32::<CpuAccess, _>(emu, addr)
+    }
 
-fn rl_uncomp_read_normal_write_8<E: Engine>(_emu: &mut Emu<E>) {
-    // TODO
-    #[cfg(feature = "log")]
-    slog::error!(
-        _emu.arm7.logger,
-        "Unimplemented RLUnCompReadNormalWrite8bit SWI"
-    );
-}
+    fn write_8(emu: &mut Emu<E>, addr: u32, value: u8) {
+        bus::write_8::<CpuAccess, _>(emu, addr, value);
+    }
 
-fn rl_uncomp_read_callback_write_16<E: Engine>(_emu: &mut Emu<E>) {
-    // TODO
-    #[cfg(feature = "log")]
-    slog::error!(
-        _emu.arm7.logger,
-        "Unimplemented RLUnCompReadByCallbackWrite16bit SWI"
-    );
+    fn write_16(emu: &mut Emu<E>, addr: u32, value: u16) {
+        bus::write_16::<CpuAccess, _>(emu, addr, value);
+    }
+
+    fn write_32(emu: &mut Emu<E>, addr: u32, value: u32) {
+        bus::write_32::<CpuAccess, _>(emu, addr, value);
+    }
 }
 
 fn cpu_set<E: Engine>(emu: &mut Emu<E>, r0_3: [u32; 4]) -> [u32; 2] {
@@ -410,6 +382,20 @@ fn get_boot_procs<E: Engine>(_emu: &mut Emu<E>) {
     slog::error!(_emu.arm7.logger, "Unimplemented GetBootProcs SWI");
 }
 
+/// The `ReadByCallback` SWIs read their source through guest functions listed in the table at
+/// r3, which can't be called from HLE code; rather than guessing where the data is, the call is
+/// refused and reported, as only the original BIOS can run it.
+fn unsupported_read_by_callback<E: Engine>(emu: &mut Emu<E>, number: u8) {
+    #[cfg(feature = "log")]
+    slog::error!(
+        emu.arm7.logger,
+        "{} SWI reads data through guest callbacks, which the HLE BIOS doesn't support; the \
+         original BIOS files are required",
+        SWI_NAMES[number as usize]
+    );
+    emu.report_unsupported_hle_swi(Core::Arm7, number);
+}
+
 #[derive(Savestate)]
 pub struct State {
     pub enabled: bool,
@@ -514,17 +500,17 @@ pub fn handle_swi<E: Engine>(emu: &mut Emu<E>, number: u8, mut r0_3: [u32; 4]) {
 
         0x0F => (r0_3[0], r0_3[1], r0_3[3]) = common::is_debugger::<_, 0x7F_FFFA>(emu),
 
-        0x10 => bit_unpack(emu, r0_3[0], r0_3[1], r0_3[2]),
-
-        0x11 => lz77_uncomp_read_normal_write_8(emu),
-
-        0x12 => lz77_uncomp_read_callback_write_16(emu),
+        0x10 => common::bit_unpack::<_, Mem>(emu, r0_3[0], r0_3[1], r0_3[2]),
 
-        0x13 => huff_uncomp_read_callback(emu),
+        0x11 => {
+            common::lz77_uncomp::<_, Mem>(emu, r0_3[0], r0_3[1]);
+        }
 
-        0x14 => rl_uncomp_read_normal_write_8(emu),
+        0x12 | 0x13 | 0x15 => unsupported_read_by_callback(emu, number),
 
-        0x15 => rl_uncomp_read_callback_write_16(emu),
+        0x14 => {
+            common::rl_uncomp::<_, Mem>(emu, r0_3[0], r0_3[1]);
+        }
 
         // TODO: r3 value
         0x1A => (r0_3[0], r0_3[1]) = get_sine_table(r0_3[0]),
@@ -3,7 +3,7 @@ use crate::{
     cpu::{
         arm9::{self, bus},
         bus::CpuAccess,
-        CoreData, Engine, Schedule,
+        Core, CoreData, Engine, Schedule,
     },
     emu::Emu,
     utils::Savestate,
@@ -42,78 +42,32 @@ pub static BIOS: [u8; arm9::BIOS_SIZE] = {
     bytes
 };
 
-fn bit_unpack<E: Engine>(
-    _emu: &mut Emu<E>,
-    _src_addr: u32,
-    _dst_addr: u32,
-    _unpack_data_addr: u32,
-) {
-    // TODO
-    #[cfg(feature = "log")]
-    slog::error!(_emu.arm9.logger, "Unimplemented BitUnPack SWI");
-}
-
-fn diff_8_unfilter_write_8<E: Engine>(_emu: &mut Emu<E>) {
-    // TODO
-    #[cfg(feature = "log")]
-    slog::error!(
-        _emu.arm9.logger,
-        "Unimplemented Diff8bitUnFilterWrite8bit SWI"
-    );
-}
+struct Mem;
 
-fn diff_16_unfilter<E: Engine>(_emu: &mut Emu<E>) {
-    // TODO
-    #[cfg(feature = "log")]
-    slog::error!(
-        _emu.arm9.logger,
-        "Unimplemented Diff8bitUnFilterWrite16bit SWI"
-    );
-}
+impl<E: Engine> common::Mem<E> for Mem {
+    fn read_8(emu: &mut Emu<E>, addr: u32) -> u8 {
+        bus::read_8::<CpuAccess, _>(emu, addr)
+    }
 
-fn huff_uncomp_read_callback<E: Engine>(_emu: &mut Emu<E>) {
-    // TODO
-    #[cfg(feature = "log")]
-    slog::error!(
-        _emu.arm9.logger,
-        "Unimplemented HuffUnCompReadByCallback SWI"
-    );
-}
+    fn read_16(emu: &mut Emu<E>, addr: u32) -> u16 {
+        bus::read_16::<CpuAccess, _>(emu, addr)
+    }
 
-fn lz77_uncomp_read_normal_write_8<E: Engine>(_emu: &mut Emu<E>) {
-    // TODO
-    #[cfg(feature = "log")]
-    slog::error!(
-        _emu.arm9.logger,
-        "Unimplemented LZ77UnCompReadNormalWrite8bit SWI"
-    );
-}
+    fn read_32(emu: &mut Emu<E>, addr: u32) -> u32 {
+        bus::read_32::<CpuAccess, _, false>(emu, addr)
+    }
 
-fn lz77_uncomp_read_callback_write_16<E: Engine>(_emu: &mut Emu<E>) {
-    // TODO
-    #[cfg(feature = "log")]
-    slog::error!(
-        _emu.arm9.logger,
-        "Unimplemented LZ77UnCompReadByCallbackWrite16bit SWI"
-    );
-}
+    fn write_8(emu: &mut Emu<E>, addr: u32, value: u8) {
+        bus::write_8::<CpuAccess, _>(emu, addr, value);
+    }
 
-fn rl_uncomp_read_normal_write_8<E: Engine>(_emu: &mut Emu<E>) {
-    // TODO
-    #[cfg(feature = "log")]
-    slog::error!(
-        _emu.arm9.logger,
-        "Unimplemented RLUnCompReadNormalWrite8bit SWI"
-    );
-}
+    fn write_16(emu: &mut Emu<E>, addr: u32, value: u16) {
+        bus::write_16::<CpuAccess, _>(emu, addr, value);
+    }
 
-fn rl_uncomp_read_callback_write_16<E: Engine>(_emu: &mut Emu<E>) {
-    // TODO
-    #[cfg(feature = "log")]
-    slog::error!(
-        _emu.arm9.logger,
-        "Unimplemented RLUnCompReadByCallbackWrite16bit SWI"
-    );
+    fn write_32(emu: &mut Emu<E>, addr: u32, value: u32) {
+        bus::write_32::<CpuAccess, _>(emu, addr, value);
+    }
 }
 
 fn cpu_set<E: Engine>(emu: &mut Emu<E>, r0_3: [u32; 4]) -> [u32; 2] {
@@ -241,6 +195,20 @@ fn wait_by_loop<E: Engine>(emu: &mut Emu<E>, iterations: i32) -> u32 {
     (iterations - 1).min(0) as u32
 }
 
+/// The `ReadByCallback` SWIs read their source through guest functions listed in the table at
+/// r3, which can't be called from HLE code; rather than guessing where the data is, the call is
+/// refused and reported, as only the original BIOS can run it.
+fn unsupported_read_by_callback<E: Engine>(emu: &mut Emu<E>, number: u8) {
+    #[cfg(feature = "log")]
+    slog::error!(
+        emu.arm9.logger,
+        "{} SWI reads data through guest callbacks, which the HLE BIOS doesn't support; the \
+         original BIOS files are required",
+        SWI_NAMES[number as usize]
+    );
+    emu.report_unsupported_hle_swi(Core::Arm9, number);
+}
+
 #[derive(Savestate)]
 pub struct State {
     pub enabled: bool,
@@ -343,21 +311,21 @@ pub fn handle_swi<E: Engine>(emu: &mut Emu<E>, number: u8, mut r0_3: [u32; 4]) {
 
         0x0F => (r0_3[0], r0_3[1], r0_3[3]) = common::is_debugger::<_, 0x7F_FFF8>(emu),
 
-        0x10 => bit_unpack(emu, r0_3[0], r0_3[1], r0_3[2]),
-
-        0x11 => lz77_uncomp_read_normal_write_8(emu),
+        0x10 => common::bit_unpack::<_, Mem>(emu, r0_3[0], r0_3[1], r0_3[2]),
 
-        0x12 => lz77_uncomp_read_callback_write_16(emu),
-
-        0x13 => huff_uncomp_read_callback(emu),
+        0x11 => {
+            common::lz77_uncomp::<_, Mem>(emu, r0_3[0], r0_3[1]);
+        }
 
-        0x14 => rl_uncomp_read_normal_write_8(emu),
+        0x12 | 0x13 | 0x15 => unsupported_read_by_callback(emu, number),
 
-        0x15 => rl_uncomp_read_callback_write_16(emu),
+        0x14 => {
+            common::rl_uncomp::<_, Mem>(emu, r0_3[0], r0_3[1]);
+        }
 
-        0x16 => diff_8_unfilter_write_8(emu),
+        0x16 => common::diff_unfilter::<_, Mem, false>(emu, r0_3[0], r0_3[1]),
 
-        0x18 => diff_16_unfilter(emu),
+        0x18 => common::diff_unfilter::<_, Mem, true>(emu, r0_3[0], r0_3[1]),
 
         0x1F => {
             bus::write_32::<CpuAccess, _>(emu, 0x0400_0300, r0_3[0]);
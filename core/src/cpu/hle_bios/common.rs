@@ -58,3 +58,306 @@ pub fn is_debugger<E: Engine, const ADDR: usize>(emu: &Emu<E>) -> (u32, u32, u32
         0x027F_FFE0,
     )
 }
+
+/// Memory accesses performed by the decompression and unpacking SWIs on behalf of either CPU.
+pub trait Mem<E: Engine> {
+    fn read_8(emu: &mut Emu<E>, addr: u32) -> u8;
+    fn read_16(emu: &mut Emu<E>, addr: u32) -> u16;
+    fn read_32(emu: &mut Emu<E>, addr: u32) -> u32;
+    fn write_8(emu: &mut Emu<E>, addr: u32, value: u8);
+    fn write_16(emu: &mut Emu<E>, addr: u32, value: u16);
+    fn write_32(emu: &mut Emu<E>, addr: u32, value: u32);
+}
+
+/// Accumulates `width`-bit units into words, writing each one out once it's full.
+struct WordWriter {
+    addr: u32,
+    value: u32,
+    shift: u32,
+}
+
+impl WordWriter {
+    fn new(addr: u32) -> Self {
+        WordWriter {
+            addr,
+            value: 0,
+            shift: 0,
+        }
+    }
+
+    fn write<E: Engine, M: Mem<E>>(&mut self, emu: &mut Emu<E>, value: u32, width: u32) {
+        self.value |= value << self.shift;
+        self.shift += width;
+        if self.shift >= 32 {
+            M::write_32(emu, self.addr, self.value);
+            self.addr = self.addr.wrapping_add(4);
+            self.value = 0;
+            self.shift = 0;
+        }
+    }
+}
+
+fn read_8_inc<E: Engine, M: Mem<E>>(emu: &mut Emu<E>, addr: &mut u32) -> u8 {
+    let value = M::read_8(emu, *addr);
+    *addr = addr.wrapping_add(1);
+    value
+}
+
+fn write_8_inc<E: Engine, M: Mem<E>>(emu: &mut Emu<E>, addr: &mut u32, value: u8) {
+    M::write_8(emu, *addr, value);
+    *addr = addr.wrapping_add(1);
+}
+
+pub fn bit_unpack<E: Engine, M: Mem<E>>(
+    emu: &mut Emu<E>,
+    mut src_addr: u32,
+    dst_addr: u32,
+    unpack_data_addr: u32,
+) {
+    let src_len = M::read_16(emu, unpack_data_addr) as u32;
+    let src_width = M::read_8(emu, unpack_data_addr.wrapping_add(2)) as u32;
+    let dst_width = M::read_8(emu, unpack_data_addr.wrapping_add(3)) as u32;
+    let data_offset = M::read_32(emu, unpack_data_addr.wrapping_add(4));
+    let offset_zero = data_offset & 1 << 31 != 0;
+    let data_offset = data_offset & 0x7FFF_FFFF;
+
+    if !matches!(src_width, 1 | 2 | 4 | 8) || !matches!(dst_width, 1 | 2 | 4 | 8 | 16 | 32) {
+        return;
+    }
+
+    let src_mask = (1 << src_width) - 1;
+    let dst_mask = if dst_width == 32 {
+        u32::MAX
+    } else {
+        (1 << dst_width) - 1
+    };
+    let mut writer = WordWriter::new(dst_addr);
+    for _ in 0..src_len {
+        let byte = read_8_inc::<E, M>(emu, &mut src_addr) as u32;
+        for shift in (0..8).step_by(src_width as usize) {
+            let mut value = byte >> shift & src_mask;
+            if value != 0 || offset_zero {
+                value = value.wrapping_add(data_offset);
+            }
+            writer.write::<E, M>(emu, value & dst_mask, dst_width);
+        }
+    }
+}
+
+/// Decompresses LZ77-compressed data (type `0x10`), returning the decompressed size.
+pub fn lz77_uncomp<E: Engine, M: Mem<E>>(
+    emu: &mut Emu<E>,
+    mut src_addr: u32,
+    mut dst_addr: u32,
+) -> u32 {
+    let header = M::read_32(emu, src_addr);
+    src_addr = src_addr.wrapping_add(4);
+    let size = header >> 8;
+
+    let mut remaining = size;
+    while remaining > 0 {
+        let flags = read_8_inc::<E, M>(emu, &mut src_addr);
+        for i in (0..8).rev() {
+            if remaining == 0 {
+                break;
+            }
+            if flags & 1 << i == 0 {
+                let value = read_8_inc::<E, M>(emu, &mut src_addr);
+                write_8_inc::<E, M>(emu, &mut dst_addr, value);
+                remaining -= 1;
+            } else {
+                let hi = read_8_inc::<E, M>(emu, &mut src_addr) as u32;
+                let lo = read_8_inc::<E, M>(emu, &mut src_addr) as u32;
+                let len = (hi >> 4) + 3;
+                let disp = ((hi & 0xF) << 8 | lo) + 1;
+                for _ in 0..len.min(remaining) {
+                    let value = M::read_8(emu, dst_addr.wrapping_sub(disp));
+                    write_8_inc::<E, M>(emu, &mut dst_addr, value);
+                }
+                remaining = remaining.saturating_sub(len);
+            }
+        }
+    }
+    size
+}
+
+/// Decompresses run-length-encoded data (type `0x30`), returning the decompressed size.
+pub fn rl_uncomp<E: Engine, M: Mem<E>>(
+    emu: &mut Emu<E>,
+    mut src_addr: u32,
+    mut dst_addr: u32,
+) -> u32 {
+    let header = M::read_32(emu, src_addr);
+    src_addr = src_addr.wrapping_add(4);
+    let size = header >> 8;
+
+    let mut remaining = size;
+    while remaining > 0 {
+        let flag = read_8_inc::<E, M>(emu, &mut src_addr);
+        if flag & 0x80 != 0 {
+            let len = ((flag & 0x7F) as u32 + 3).min(remaining);
+            let value = read_8_inc::<E, M>(emu, &mut src_addr);
+            for _ in 0..len {
+                write_8_inc::<E, M>(emu, &mut dst_addr, value);
+            }
+            remaining -= len;
+        } else {
+            let len = ((flag & 0x7F) as u32 + 1).min(remaining);
+            for _ in 0..len {
+                let value = read_8_inc::<E, M>(emu, &mut src_addr);
+                write_8_inc::<E, M>(emu, &mut dst_addr, value);
+            }
+            remaining -= len;
+        }
+    }
+    size
+}
+
+/// Reverses 8- or 16-bit differential filtering (types `0x81` and `0x82`).
+pub fn diff_unfilter<E: Engine, M: Mem<E>, const UNITS_16: bool>(
+    emu: &mut Emu<E>,
+    mut src_addr: u32,
+    mut dst_addr: u32,
+) {
+    let header = M::read_32(emu, src_addr);
+    src_addr = src_addr.wrapping_add(4);
+    let size = header >> 8;
+
+    if UNITS_16 {
+        let mut value = 0_u16;
+        for _ in 0..size >> 1 {
+            value = value.wrapping_add(M::read_16(emu, src_addr));
+            M::write_16(emu, dst_addr, value);
+            src_addr = src_addr.wrapping_add(2);
+            dst_addr = dst_addr.wrapping_add(2);
+        }
+    } else {
+        let mut value = 0_u8;
+        for _ in 0..size {
+            value = value.wrapping_add(read_8_inc::<E, M>(emu, &mut src_addr));
+            M::write_8(emu, dst_addr, value);
+            dst_addr = dst_addr.wrapping_add(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cpu::interpreter::Interpreter, emu::testing};
+
+    const SRC_ADDR: u32 = 0x0200_0000;
+    const DST_ADDR: u32 = 0x0200_1000;
+
+    struct MainMem;
+
+    impl MainMem {
+        fn offset(emu: &Emu<Interpreter>, addr: u32) -> usize {
+            (addr & emu.main_mem_mask().get()) as usize
+        }
+    }
+
+    impl Mem<Interpreter> for MainMem {
+        fn read_8(emu: &mut Emu<Interpreter>, addr: u32) -> u8 {
+            emu.main_mem().read(Self::offset(emu, addr))
+        }
+
+        fn read_16(emu: &mut Emu<Interpreter>, addr: u32) -> u16 {
+            emu.main_mem().read_le(Self::offset(emu, addr & !1))
+        }
+
+        fn read_32(emu: &mut Emu<Interpreter>, addr: u32) -> u32 {
+            emu.main_mem().read_le(Self::offset(emu, addr & !3))
+        }
+
+        fn write_8(emu: &mut Emu<Interpreter>, addr: u32, value: u8) {
+            emu.main_mem().write(Self::offset(emu, addr), value);
+        }
+
+        fn write_16(emu: &mut Emu<Interpreter>, addr: u32, value: u16) {
+            emu.main_mem().write_le(Self::offset(emu, addr & !1), value);
+        }
+
+        fn write_32(emu: &mut Emu<Interpreter>, addr: u32, value: u32) {
+            emu.main_mem().write_le(Self::offset(emu, addr & !3), value);
+        }
+    }
+
+    fn emu_with_src(src: &[u8]) -> Emu<Interpreter> {
+        let mut emu = testing::build();
+        for (i, &byte) in src.iter().enumerate() {
+            MainMem::write_8(&mut emu, SRC_ADDR + i as u32, byte);
+        }
+        emu
+    }
+
+    fn read_dst(emu: &mut Emu<Interpreter>, len: usize) -> Vec<u8> {
+        (0..len as u32)
+            .map(|i| MainMem::read_8(emu, DST_ADDR + i))
+            .collect()
+    }
+
+    #[test]
+    fn bit_unpack_offsets_nonzero_units() {
+        let mut emu = emu_with_src(&[
+            0xE4, 0xE4, 0, 0, // Data
+            2, 0, 2, 4, 1, 0, 0, 0, // Unpack info: 2 bytes, 2 -> 4 bits, offset 1
+        ]);
+        bit_unpack::<_, MainMem>(&mut emu, SRC_ADDR, DST_ADDR, SRC_ADDR + 4);
+        assert_eq!(MainMem::read_32(&mut emu, DST_ADDR), 0x4320_4320);
+    }
+
+    #[test]
+    fn bit_unpack_offset_zero_units() {
+        let mut emu = emu_with_src(&[
+            0xE4, 0xE4, 0, 0, // Data
+            2, 0, 2, 4, 1, 0, 0, 0x80, // Unpack info: as above, but offsetting zero units
+        ]);
+        bit_unpack::<_, MainMem>(&mut emu, SRC_ADDR, DST_ADDR, SRC_ADDR + 4);
+        assert_eq!(MainMem::read_32(&mut emu, DST_ADDR), 0x4321_4321);
+    }
+
+    #[test]
+    fn lz77_literals_and_overlapping_refs() {
+        // 3 literals, a 6-byte reference 3 bytes back (overlapping its own output), 1 literal
+        let mut emu = emu_with_src(&[0x10, 10, 0, 0, 0x10, b'a', b'b', b'c', 0x30, 0x02, b'!']);
+        assert_eq!(lz77_uncomp::<_, MainMem>(&mut emu, SRC_ADDR, DST_ADDR), 10);
+        assert_eq!(read_dst(&mut emu, 11), b"abcabcabc!\0");
+    }
+
+    #[test]
+    fn lz77_odd_size_truncates_ref() {
+        let mut emu = emu_with_src(&[0x10, 5, 0, 0, 0x10, b'a', b'b', b'c', 0x30, 0x02]);
+        assert_eq!(lz77_uncomp::<_, MainMem>(&mut emu, SRC_ADDR, DST_ADDR), 5);
+        assert_eq!(read_dst(&mut emu, 6), b"abcab\0");
+    }
+
+    #[test]
+    fn rl_uncompressed_and_compressed_runs() {
+        let mut emu = emu_with_src(&[0x30, 7, 0, 0, 0x02, b'x', b'y', b'z', 0x81, b'q']);
+        assert_eq!(rl_uncomp::<_, MainMem>(&mut emu, SRC_ADDR, DST_ADDR), 7);
+        assert_eq!(read_dst(&mut emu, 8), b"xyzqqqq\0");
+    }
+
+    #[test]
+    fn rl_odd_size_truncates_run() {
+        let mut emu = emu_with_src(&[0x30, 3, 0, 0, 0x81, b'q']);
+        assert_eq!(rl_uncomp::<_, MainMem>(&mut emu, SRC_ADDR, DST_ADDR), 3);
+        assert_eq!(read_dst(&mut emu, 4), b"qqq\0");
+    }
+
+    #[test]
+    fn diff_unfilter_8() {
+        let mut emu = emu_with_src(&[0x81, 4, 0, 0, 1, 1, 1, 0xFE]);
+        diff_unfilter::<_, MainMem, false>(&mut emu, SRC_ADDR, DST_ADDR);
+        assert_eq!(read_dst(&mut emu, 4), [1, 2, 3, 1]);
+    }
+
+    #[test]
+    fn diff_unfilter_16() {
+        let mut emu = emu_with_src(&[0x82, 4, 0, 0, 0x00, 0x01, 0xFF, 0xFF]);
+        diff_unfilter::<_, MainMem, true>(&mut emu, SRC_ADDR, DST_ADDR);
+        assert_eq!(MainMem::read_16(&mut emu, DST_ADDR), 0x0100);
+        assert_eq!(MainMem::read_16(&mut emu, DST_ADDR + 2), 0x00FF);
+    }
+}
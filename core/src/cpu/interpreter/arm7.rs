@@ -12,9 +12,9 @@ use crate::{
         bus::CpuAccess,
         hle_bios,
         psr::{Mode, Psr},
-        Arm7Data, CoreData, Schedule as _,
+        Arm7Data, Core, CoreData, Schedule as _,
     },
-    emu::Emu,
+    emu::{crash::Exception, Emu},
     utils::{schedule::RawTimestamp, Savestate},
 };
 
@@ -197,6 +197,12 @@ fn handle_undefined<const THUMB: bool>(emu: &mut Emu<Interpreter>, _instr: u32)
             emu.arm7.is_stopped = true;
         }
     }
+    emu.report_exception(
+        Core::Arm7,
+        Exception::UndefinedInstr,
+        reg!(emu.arm7, 15).wrapping_sub(8 >> THUMB as u8),
+        THUMB,
+    );
     let prev_cpsr = emu.arm7.engine_data.regs.cpsr;
     emu.arm7.engine_data.regs.cpsr = emu
         .arm7
@@ -538,6 +544,9 @@ impl CoreData for EngineData {
 
             #[inline]
             fn clear_mem_watchpoints(&mut self) {}
+
+            #[inline]
+            fn set_irq_hook(&mut self, _hook: &Option<debug::IrqHook<Interpreter>>) {}
         }
     }
 }
@@ -607,6 +616,17 @@ impl Arm7Data for EngineData {
                     reg!(emu.arm7, 15) = 0x0000_0018;
                     reload_pipeline::<{ StateSource::Arm }>(emu);
                 }
+                #[cfg(feature = "debugger-hooks")]
+                if let Some(irq_hook) = emu.arm7.irq_hook() {
+                    if unsafe { irq_hook.get()(emu, return_addr) } {
+                        emu.arm7
+                            .schedule
+                            .set_target_time(emu.arm7.schedule.cur_time());
+                        emu.arm7.was_stopped_by_debug_hook = true;
+                        emu.arm7.is_stopped = true;
+                        return;
+                    }
+                }
             } else if emu.arm7.irqs.halted() {
                 emu.arm7
                     .schedule
@@ -631,6 +651,15 @@ impl Arm7Data for EngineData {
                         }
                     }
                 }
+                trace_record!(
+                    emu,
+                    Arm7,
+                    Exec {
+                        pc: reg!(emu.arm7, 15)
+                            .wrapping_sub(8 >> emu.arm7.engine_data.regs.cpsr.thumb_state() as u8),
+                        thumb: emu.arm7.engine_data.regs.cpsr.thumb_state(),
+                    }
+                );
                 #[cfg(feature = "interp-pipeline")]
                 {
                     let addr = reg!(emu.arm7, 15);
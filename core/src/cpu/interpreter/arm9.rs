@@ -14,11 +14,12 @@ use crate::{
         bus::CpuAccess,
         hle_bios,
         psr::{Mode, Psr},
-        Arm9Data, CoreData, Schedule as _,
+        Arm9Data, Core, CoreData, Schedule as _,
     },
     ds_slot::DsSlot,
-    emu::Emu,
+    emu::{crash::Exception, Emu},
     gpu::engine_3d::Engine3d,
+    ndma::Ndma,
     utils::{schedule::RawTimestamp, Savestate},
 };
 use core::intrinsics::unlikely;
@@ -554,6 +555,12 @@ fn handle_undefined<const THUMB: bool>(emu: &mut Emu<Interpreter>, _instr: u32)
             emu.arm9.is_stopped = true;
         }
     }
+    emu.report_exception(
+        Core::Arm9,
+        Exception::UndefinedInstr,
+        reg!(emu.arm9, 15).wrapping_sub(8 >> THUMB as u8),
+        THUMB,
+    );
     if THUMB {
         prefetch_thumb::<false, false>(emu);
     } else {
@@ -657,6 +664,12 @@ fn handle_prefetch_abort<const THUMB: bool>(emu: &mut Emu<Interpreter>) {
             emu.arm9.is_stopped = true;
         }
     }
+    emu.report_exception(
+        Core::Arm9,
+        Exception::PrefetchAbort,
+        reg!(emu.arm9, 15).wrapping_sub(8 >> THUMB as u8),
+        THUMB,
+    );
     if THUMB {
         prefetch_thumb::<false, false>(emu);
     } else {
@@ -687,7 +700,7 @@ fn handle_prefetch_abort<const THUMB: bool>(emu: &mut Emu<Interpreter>) {
     reload_pipeline::<{ StateSource::Arm }>(emu);
 }
 
-fn handle_data_abort<const THUMB: bool>(emu: &mut Emu<Interpreter>, _addr: u32) {
+fn handle_data_abort<const THUMB: bool>(emu: &mut Emu<Interpreter>, addr: u32) {
     // r15 is assumed to be PC + 3i, and not PC + 2i (where i = instr size)
     #[cfg(feature = "log")]
     slog::warn!(
@@ -695,11 +708,11 @@ fn handle_data_abort<const THUMB: bool>(emu: &mut Emu<Interpreter>, _addr: u32)
         "Data abort @ {:#X} ({} state) accessing {:#X}",
         reg!(emu.arm9, 15).wrapping_sub(12 >> THUMB as u8),
         if THUMB { "Thumb" } else { "ARM" },
-        _addr,
+        addr,
     );
     #[cfg(feature = "debugger-hooks")]
     if let Some(data_abort_hook) = emu.arm9.data_abort_hook() {
-        if unsafe { data_abort_hook.get()(emu, _addr) } {
+        if unsafe { data_abort_hook.get()(emu, addr) } {
             emu.arm9
                 .schedule
                 .set_target_time(emu.arm9.schedule.cur_time());
@@ -707,6 +720,12 @@ fn handle_data_abort<const THUMB: bool>(emu: &mut Emu<Interpreter>, _addr: u32)
             emu.arm9.is_stopped = true;
         }
     }
+    emu.report_exception(
+        Core::Arm9,
+        Exception::DataAbort { addr },
+        reg!(emu.arm9, 15).wrapping_sub(12 >> THUMB as u8),
+        THUMB,
+    );
     let prev_cpsr = emu.arm9.engine_data.regs.cpsr;
     emu.arm9.engine_data.regs.cpsr = prev_cpsr
         .with_mode(Mode::ABORT)
@@ -1050,6 +1069,9 @@ impl CoreData for EngineData {
 
             #[inline]
             fn clear_mem_watchpoints(&mut self) {}
+
+            #[inline]
+            fn set_irq_hook(&mut self, _hook: &Option<debug::IrqHook<Interpreter>>) {}
         }
     }
 }
@@ -1070,6 +1092,7 @@ macro_rules! handle_pending_events {
                 ),
                 Event::GxFifoStall => $handle_gx_fifo_stall,
                 Event::Engine3dCommandFinished => Engine3d::process_next_command($emu),
+                Event::NdmaTransfer(i) => Ndma::handle_transfer_event($emu, i),
             }
         }
     };
@@ -1163,6 +1186,17 @@ impl Arm9Data for EngineData {
                         reg!(emu.arm9, 15) = emu.arm9.engine_data.exc_vectors_start | 0x18;
                         reload_pipeline::<{ StateSource::Arm }>(emu);
                     }
+                    #[cfg(feature = "debugger-hooks")]
+                    if let Some(irq_hook) = emu.arm9.irq_hook() {
+                        if unsafe { irq_hook.get()(emu, return_addr) } {
+                            emu.arm9
+                                .schedule
+                                .set_target_time(emu.arm9.schedule.cur_time());
+                            emu.arm9.was_stopped_by_debug_hook = true;
+                            emu.arm9.is_stopped = true;
+                            return;
+                        }
+                    }
                 } else if emu.arm9.irqs.halted() {
                     emu.arm9
                         .schedule
@@ -1187,6 +1221,15 @@ impl Arm9Data for EngineData {
                             }
                         }
                     }
+                    trace_record!(
+                        emu,
+                        Arm9,
+                        Exec {
+                            pc: reg!(emu.arm9, 15)
+                                .wrapping_sub(8 >> emu.arm9.engine_data.regs.cpsr.thumb_state() as u8),
+                            thumb: emu.arm9.engine_data.regs.cpsr.thumb_state(),
+                        }
+                    );
                     #[cfg(feature = "interp-pipeline")]
                     {
                         #[cfg(not(feature = "interp-arm9-interlocks"))]
@@ -0,0 +1,164 @@
+use super::Core;
+use crate::emu::Timestamp;
+use core::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntryKind {
+    Exec { pc: u32, thumb: bool },
+    IpcSyncWrite { value: u16 },
+    IpcFifoControlWrite { value: u16 },
+    IpcFifoSend { value: u32 },
+    IpcFifoRecv { value: u32 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub time: Timestamp,
+    pub core: Core,
+    pub kind: EntryKind,
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>12} {} ",
+            self.time.0,
+            match self.core {
+                Core::Arm7 => "ARM7",
+                Core::Arm9 => "ARM9",
+            }
+        )?;
+        match self.kind {
+            EntryKind::Exec { pc, thumb } => {
+                write!(f, "exec {pc:#010X} ({})", if thumb { "Thumb" } else { "ARM" })
+            }
+            EntryKind::IpcSyncWrite { value } => write!(f, "IPCSYNC <- {value:#06X}"),
+            EntryKind::IpcFifoControlWrite { value } => write!(f, "IPCFIFOCNT <- {value:#06X}"),
+            EntryKind::IpcFifoSend { value } => write!(f, "IPCFIFOSEND <- {value:#010X}"),
+            EntryKind::IpcFifoRecv { value } => write!(f, "IPCFIFORECV -> {value:#010X}"),
+        }
+    }
+}
+
+pub const DEFAULT_CAPACITY: usize = 0x1_0000;
+
+/// A bounded ring buffer recording both CPUs' executed instructions and their IPC register
+/// interactions, to allow reconstructing a unified timeline after the fact (i.e. to diagnose
+/// inter-processor deadlocks).
+///
+/// As the two CPUs are run in batches one after the other, entries aren't recorded in
+/// chronological order; [`Trace::entries`] sorts them by timestamp before returning them.
+pub struct Trace {
+    enabled: bool,
+    entries: Box<[Entry]>,
+    next_i: usize,
+    len: usize,
+}
+
+impl Trace {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Trace {
+            enabled: false,
+            entries: vec![
+                Entry {
+                    time: Timestamp(0),
+                    core: Core::Arm7,
+                    kind: EntryKind::Exec {
+                        pc: 0,
+                        thumb: false,
+                    },
+                };
+                capacity.max(1)
+            ]
+            .into_boxed_slice(),
+            next_i: 0,
+            len: 0,
+        }
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    #[inline]
+    pub fn set_enabled(&mut self, value: bool) {
+        self.enabled = value;
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.next_i = 0;
+        self.len = 0;
+    }
+
+    #[inline]
+    pub(crate) fn record(&mut self, time: Timestamp, core: Core, kind: EntryKind) {
+        if !self.enabled {
+            return;
+        }
+        self.entries[self.next_i] = Entry { time, core, kind };
+        self.next_i += 1;
+        if self.next_i == self.entries.len() {
+            self.next_i = 0;
+        }
+        self.len = (self.len + 1).min(self.entries.len());
+    }
+
+    /// Returns the currently recorded entries, in chronological order.
+    pub fn entries(&self) -> Vec<Entry> {
+        let mut entries = Vec::with_capacity(self.len);
+        if self.len == self.entries.len() {
+            entries.extend_from_slice(&self.entries[self.next_i..]);
+            entries.extend_from_slice(&self.entries[..self.next_i]);
+        } else {
+            entries.extend_from_slice(&self.entries[..self.len]);
+        }
+        // NOTE: A stable sort is needed to preserve the order of entries recorded by the same CPU
+        // with the same timestamp.
+        entries.sort_by_key(|entry| entry.time.0);
+        entries
+    }
+
+    /// Writes all currently recorded entries, in chronological order, one per line.
+    pub fn dump(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        for entry in self.entries() {
+            writeln!(f, "{entry}")?;
+        }
+        Ok(())
+    }
+}
+
+macro_rules! trace_record {
+    (@time $emu: expr, Arm7) => {
+        $emu.arm7.schedule.cur_time()
+    };
+    (@time $emu: expr, Arm9) => {
+        $emu.arm9.schedule.cur_time()
+    };
+    ($emu: expr, $core: ident, $kind: ident $($fields: tt)*) => {
+        if $emu.trace.is_enabled() {
+            let time = $crate::emu::Timestamp::from(trace_record!(@time $emu, $core));
+            $emu.trace.record(
+                time,
+                $crate::cpu::Core::$core,
+                $crate::cpu::trace::EntryKind::$kind $($fields)*,
+            );
+        }
+    };
+}
@@ -21,6 +21,7 @@ macro_rules! impl_from_variants {
     }
 }
 
+pub mod ir;
 pub mod rom;
 pub mod spi;
 
@@ -81,6 +82,8 @@ pub use bounded::{RomOutputLen, RomOutputPos};
 pub struct DsSlot {
     pub rom: rom::Rom,
     pub spi: spi::Spi,
+    #[savestate(skip)]
+    pub ir_backend: Box<dyn ir::Backend>,
     spi_control: AuxSpiControl,
     rom_control: RomControl,
     pub rom_cmd: Bytes<8>,
@@ -100,6 +103,7 @@ impl DsSlot {
     pub(crate) fn new(
         rom: rom::Rom,
         spi: spi::Spi,
+        ir_backend: Box<dyn ir::Backend>,
         arm7_schedule: &mut arm7::Schedule,
         arm9_schedule: &mut arm9::Schedule,
     ) -> Self {
@@ -122,6 +126,7 @@ impl DsSlot {
         DsSlot {
             rom,
             spi,
+            ir_backend,
             spi_control: AuxSpiControl(0),
             rom_control: RomControl(0),
             rom_cmd: Bytes::new([0; 8]),
@@ -183,6 +188,7 @@ impl DsSlot {
             self.rom_cmd.clone(),
             &mut self.rom_output_buffer,
             self.rom_output_len,
+            &mut self.spi,
         );
         // The command itself takes 8 CLK pulses to transfer, while every data byte takes 4 pulses
         // (the DS game card slot can only transfer 8 bits on every CLK cycle)
@@ -266,6 +272,28 @@ impl DsSlot {
         self.rom_data_out
     }
 
+    /// Sends a data word to the cartridge during a ROM transfer with writes enabled (i.e. when
+    /// writing to NAND save memory); once all data has been sent, it's handed to the ROM device.
+    pub(crate) fn write_rom_data_arm7(
+        &mut self,
+        value: u32,
+        irqs: &mut arm7::Irqs,
+        schedule: &mut arm7::Schedule,
+    ) {
+        if !self.rom_control.write_enabled() || !self.rom_control.data_ready() {
+            return;
+        }
+        let pos = self.rom_output_pos.get() as usize;
+        self.rom_output_buffer.write_le(pos, value);
+        let len = self.rom_output_len.get() as usize;
+        if pos + 4 >= len {
+            self.rom
+                .handle_rom_write(&self.rom_cmd, &self.rom_output_buffer[..len], &mut self.spi);
+        }
+        // Advance the transfer the same way reads do
+        self.read_rom_data_arm7(irqs, schedule);
+    }
+
     pub(crate) fn read_rom_data_arm7(
         &mut self,
         irqs: &mut arm7::Irqs,
@@ -298,6 +326,28 @@ impl DsSlot {
         self.rom_data_out
     }
 
+    /// Sends a data word to the cartridge during a ROM transfer with writes enabled (i.e. when
+    /// writing to NAND save memory); once all data has been sent, it's handed to the ROM device.
+    pub(crate) fn write_rom_data_arm9(
+        &mut self,
+        value: u32,
+        irqs: &mut arm9::Irqs,
+        schedule: &mut arm9::Schedule,
+    ) {
+        if !self.rom_control.write_enabled() || !self.rom_control.data_ready() {
+            return;
+        }
+        let pos = self.rom_output_pos.get() as usize;
+        self.rom_output_buffer.write_le(pos, value);
+        let len = self.rom_output_len.get() as usize;
+        if pos + 4 >= len {
+            self.rom
+                .handle_rom_write(&self.rom_cmd, &self.rom_output_buffer[..len], &mut self.spi);
+        }
+        // Advance the transfer the same way reads do
+        self.read_rom_data_arm9(irqs, schedule);
+    }
+
     pub(crate) fn read_rom_data_arm9(
         &mut self,
         irqs: &mut arm9::Irqs,
@@ -349,7 +399,9 @@ impl DsSlot {
         let first = !self.spi_last_hold;
         self.spi_last_hold = self.spi_control.spi_hold();
         let last = !self.spi_last_hold;
-        self.spi_data_out = self.spi.write_data(value, first, last);
+        self.spi_data_out = self
+            .spi
+            .write_data(value, first, last, &mut *self.ir_backend);
         // 8 bits at 33 / (8 << baud_rate) MHz (each bit takes 8 << baud_rate cycles to be
         // transferred)
         let byte_delay_cycles = Timestamp(64 << self.spi_control.spi_baud_rate());
@@ -1,16 +1,17 @@
 mod empty;
-mod key1;
+pub mod key1;
 pub use empty::Empty;
 pub mod header;
 pub mod icon_title;
 pub mod normal;
+pub mod patch;
 
-use super::RomOutputLen;
+use super::{spi::Spi, RomOutputLen};
 use crate::{
     utils::{mem_prelude::*, Savestate},
     Model,
 };
-use core::any::Any;
+use core::{any::Any, fmt};
 
 #[allow(clippy::len_without_is_empty)]
 pub trait Contents: Sync {
@@ -80,14 +81,37 @@ impl Contents for BoxedByteSlice {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SetupError {
+    /// The ROM's secure area needs to be decrypted (for direct boot) or encrypted (for firmware
+    /// boot) using keys derived from the KEY1 table, but neither an ARM7 BIOS nor a standalone
+    /// copy of the table was provided.
+    SecureAreaNeedsArm7Bios,
+}
+
+impl fmt::Display for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetupError::SecureAreaNeedsArm7Bios => f.write_str(
+                "the ROM's secure area is encrypted and can't be decrypted without an ARM7 BIOS or \
+                 KEY1 table",
+            ),
+        }
+    }
+}
+
+impl core::error::Error for SetupError {}
+
 trait RomDevice {
-    fn setup(&mut self, direct_boot: bool) -> Result<(), ()>;
+    fn setup(&mut self, direct_boot: bool) -> Result<(), SetupError>;
     fn handle_rom_command(
         &mut self,
         cmd: Bytes<8>,
         output: &mut Bytes<0x4000>,
         output_len: RomOutputLen,
+        spi: &mut Spi,
     );
+    fn handle_rom_write(&mut self, cmd: &Bytes<8>, data: &[u8], spi: &mut Spi);
 }
 
 #[derive(Savestate)]
@@ -98,7 +122,7 @@ pub enum Rom {
 }
 
 impl Rom {
-    pub(crate) fn setup(&mut self, direct_boot: bool) -> Result<(), ()> {
+    pub(crate) fn setup(&mut self, direct_boot: bool) -> Result<(), SetupError> {
         forward_to_variants!(Rom; Normal, Empty; self, setup(direct_boot))
     }
 
@@ -107,8 +131,19 @@ impl Rom {
         cmd: Bytes<8>,
         output: &mut Bytes<0x4000>,
         output_len: RomOutputLen,
+        spi: &mut Spi,
     ) {
-        forward_to_variants!(Rom; Normal, Empty; self, handle_rom_command(cmd, output, output_len));
+        forward_to_variants!(
+            Rom;
+            Normal, Empty;
+            self, handle_rom_command(cmd, output, output_len, spi)
+        );
+    }
+
+    /// Handles the data sent to the cartridge by a ROM command once the transfer ends (i.e. to
+    /// write to NAND save memory).
+    pub fn handle_rom_write(&mut self, cmd: &Bytes<8>, data: &[u8], spi: &mut Spi) {
+        forward_to_variants!(Rom; Normal, Empty; self, handle_rom_write(cmd, data, spi));
     }
 
     pub fn into_contents(self) -> Option<Box<dyn Contents>> {
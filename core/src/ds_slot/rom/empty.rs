@@ -1,4 +1,7 @@
-use super::super::RomOutputLen;
+use super::{
+    super::{spi::Spi, RomOutputLen},
+    SetupError,
+};
 use crate::utils::mem_prelude::*;
 use crate::utils::Savestate;
 
@@ -26,7 +29,7 @@ impl Empty {
 }
 
 impl super::RomDevice for Empty {
-    fn setup(&mut self, _direct_boot: bool) -> Result<(), ()> {
+    fn setup(&mut self, _direct_boot: bool) -> Result<(), SetupError> {
         Ok(())
     }
 
@@ -36,6 +39,7 @@ impl super::RomDevice for Empty {
         _cmd: Bytes<8>,
         output: &mut Bytes<0x4000>,
         output_len: RomOutputLen,
+        _spi: &mut Spi,
     ) {
         #[cfg(feature = "log")]
         slog::trace!(self.logger, "{:016X}", _cmd.read_be::<u64>(0));
@@ -43,4 +47,6 @@ impl super::RomDevice for Empty {
         // the data bus or does it get filled with 0xFF? GBATEK seems to imply the latter.
         output[..output_len.get() as usize].fill(0xFF);
     }
+
+    fn handle_rom_write(&mut self, _cmd: &Bytes<8>, _data: &[u8], _spi: &mut Spi) {}
 }
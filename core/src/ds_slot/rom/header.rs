@@ -211,7 +211,7 @@ impl<'a> Header<'a> {
 
     #[inline]
     pub fn nand_raw_rw_start(&self) -> u16 {
-        self.0.read_le::<u16>(0x98)
+        self.0.read_le::<u16>(0x96)
     }
 
     #[inline]
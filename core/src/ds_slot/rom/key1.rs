@@ -1,5 +1,16 @@
 use crate::{cpu::arm7, utils::mem_prelude::*};
 
+/// The size of the KEY1 Blowfish table, in bytes.
+pub const TABLE_SIZE: usize = 0x1048;
+const ARM7_BIOS_TABLE_OFFSET: usize = 0x30;
+
+/// Extracts the KEY1 Blowfish table stored in the ARM7 BIOS.
+pub fn table_from_arm7_bios(arm7_bios: &Bytes<{ arm7::BIOS_SIZE }>) -> Box<Bytes<TABLE_SIZE>> {
+    let mut table = Box::new(Bytes::new([0; TABLE_SIZE]));
+    table.copy_from_slice(&arm7_bios[ARM7_BIOS_TABLE_OFFSET..ARM7_BIOS_TABLE_OFFSET + TABLE_SIZE]);
+    table
+}
+
 #[derive(Clone)]
 pub struct KeyBuffer<const LEVEL_3: bool> {
     key_buf: [u32; 0x412],
@@ -7,14 +18,11 @@ pub struct KeyBuffer<const LEVEL_3: bool> {
 }
 
 impl<const LEVEL_3: bool> KeyBuffer<LEVEL_3> {
-    pub fn new_boxed<const MODULO: usize>(
-        id_code: u32,
-        arm7_bios: &Bytes<{ arm7::BIOS_SIZE }>,
-    ) -> Box<Self> {
+    pub fn new_boxed<const MODULO: usize>(id_code: u32, table: &Bytes<TABLE_SIZE>) -> Box<Self> {
         let mut result = unsafe { Box::<Self>::new_zeroed().assume_init() };
         result.key_code = [id_code, id_code >> 1, id_code << 1];
         for (i, word) in result.key_buf.iter_mut().enumerate() {
-            *word = arm7_bios.read_le(0x30 + (i << 2));
+            *word = table.read_le(i << 2);
         }
         result.apply_key_code::<MODULO>();
         result.apply_key_code::<MODULO>();
@@ -1,15 +1,33 @@
-use super::{super::RomOutputLen, is_valid_size, key1, Contents};
+use super::{
+    super::{spi::Spi, RomOutputLen},
+    is_valid_size, key1, min_size_for_model, Contents, SetupError,
+};
 use crate::{
-    cpu::arm7,
     utils::{make_zero, mem_prelude::*, zero, Savestate},
     Model,
 };
+use core::fmt;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CreationError {
-    InvalidSize,
+    /// The ROM contents' size is either not a power of two or smaller than the minimum size for
+    /// the emulated model; both are given in bytes.
+    InvalidSize { len: u64, min_len: u64 },
 }
 
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CreationError::InvalidSize { len, min_len } => write!(
+                f,
+                "invalid ROM size: {len} B (expected a power of two, at least {min_len} B)"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for CreationError {}
+
 #[derive(Clone, Copy, PartialEq, Eq, Savestate)]
 enum Stage {
     Initial,
@@ -32,22 +50,31 @@ pub struct Normal {
     chip_id: u32,
     #[savestate(skip)]
     key_buf: Option<Box<key1::KeyBuffer<false>>>, // Always at level 2
+    #[savestate(skip)]
+    nand_save_start: u32,
     stage: Stage,
 }
 
 impl Normal {
+    /// The KEY1 table, used to encrypt or decrypt the secure area, can be taken from the ARM7
+    /// BIOS through [`key1::table_from_arm7_bios`], or provided separately to boot encrypted ROMs
+    /// without one.
+    ///
     /// # Errors
     /// - [`CreationError::InvalidSize`](CreationError::InvalidSize): the ROM contents' size is
     ///   either not a power of two or too small.
     pub fn new(
         contents: Box<dyn Contents>,
-        arm7_bios: Option<&Bytes<{ arm7::BIOS_SIZE }>>,
+        key1_table: Option<&Bytes<{ key1::TABLE_SIZE }>>,
         model: Model,
         #[cfg(feature = "log")] logger: slog::Logger,
     ) -> Result<Self, CreationError> {
         let len = contents.len();
         if !is_valid_size(len, model) {
-            return Err(CreationError::InvalidSize);
+            return Err(CreationError::InvalidSize {
+                len,
+                min_len: min_size_for_model(model),
+            });
         }
         let rom_mask = (len - 1) as u32;
         let chip_id = 0x0000_00C2
@@ -57,13 +84,20 @@ impl Normal {
                 len @ 0x1000_0000..=0xFFFF_FFFF => 0x100 - (len >> 28),
             };
         let game_code = contents.game_code();
+        let nand_save_start = {
+            let mut header = zero();
+            contents.read_header(&mut header);
+            // Given in 128 KiB units, only meaningful for cartridges with NAND save memory
+            (header.read_le::<u16>(0x96) as u32) << 17
+        };
         Ok(Normal {
             #[cfg(feature = "log")]
             logger,
             contents,
             rom_mask,
             chip_id,
-            key_buf: arm7_bios.map(|bios| key1::KeyBuffer::new_boxed::<2>(game_code, bios)),
+            key_buf: key1_table.map(|table| key1::KeyBuffer::new_boxed::<2>(game_code, table)),
+            nand_save_start,
             stage: Stage::Initial,
         })
     }
@@ -94,7 +128,7 @@ impl Normal {
 }
 
 impl super::RomDevice for Normal {
-    fn setup(&mut self, direct_boot: bool) -> Result<(), ()> {
+    fn setup(&mut self, direct_boot: bool) -> Result<(), SetupError> {
         let mut buf = zero();
         self.contents.read_header(&mut buf);
         let secure_area_start = buf.read_le::<u32>(0x20);
@@ -110,7 +144,7 @@ impl super::RomDevice for Normal {
             };
             if secure_area.read_le::<u64>(0) != 0xE7FF_DEFF_E7FF_DEFF {
                 let Some(key_buf) = self.key_buf.as_ref() else {
-                    return Err(());
+                    return Err(SetupError::SecureAreaNeedsArm7Bios);
                 };
 
                 let res = key_buf.decrypt_64_bit([secure_area.read_le(0), secure_area.read_le(4)]);
@@ -132,7 +166,7 @@ impl super::RomDevice for Normal {
             let key_buf = self
                 .key_buf
                 .as_ref()
-                .expect("key_buf should be initialized");
+                .ok_or(SetupError::SecureAreaNeedsArm7Bios)?;
             if secure_area.read_le::<u64>(0) == 0xE7FF_DEFF_E7FF_DEFF {
                 secure_area[..8].copy_from_slice(b"encryObj");
                 let level_3_key_buf = key_buf.level_3::<2>();
@@ -155,6 +189,7 @@ impl super::RomDevice for Normal {
         mut cmd: Bytes<8>,
         output: &mut Bytes<0x4000>,
         output_len: RomOutputLen,
+        spi: &mut Spi,
     ) {
         match self.stage {
             Stage::Initial => {
@@ -283,6 +318,11 @@ impl super::RomDevice for Normal {
             Stage::Key2 => {
                 #[cfg(feature = "log")]
                 slog::trace!(self.logger, "KEY2: {:016X}", cmd.read_be::<u64>(0));
+                if let Spi::Nand(nand) = spi {
+                    if nand.handle_rom_command(&cmd, output, output_len, self.nand_save_start) {
+                        return;
+                    }
+                }
                 match cmd[0] {
                     0xB7 => {
                         // if cmd.read_be::<u32>(4) & 0x00FF_FFFF == 0 {
@@ -336,4 +376,13 @@ impl super::RomDevice for Normal {
               // }
         }
     }
+
+    fn handle_rom_write(&mut self, cmd: &Bytes<8>, data: &[u8], spi: &mut Spi) {
+        if self.stage != Stage::Key2 {
+            return;
+        }
+        if let Spi::Nand(nand) = spi {
+            nand.handle_rom_write(cmd, data);
+        }
+    }
 }
@@ -0,0 +1,493 @@
+//! Soft-patching of ROM contents with IPS, UPS and BPS patches, as used by ROM hacks and fan
+//! translations; the original ROM is left untouched and a patched copy is returned instead.
+
+use core::fmt;
+
+/// The file extensions of the supported patch formats.
+pub const EXTENSIONS: [&str; 3] = ["ips", "ups", "bps"];
+
+/// The largest output size a patch can request (the size of the biggest DS cartridges, 512 MiB);
+/// anything larger is treated as corruption instead of being allocated.
+pub const MAX_TARGET_LEN: usize = 0x2000_0000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Ips,
+    Ups,
+    Bps,
+}
+
+impl Format {
+    pub fn detect(patch: &[u8]) -> Option<Self> {
+        if patch.starts_with(b"PATCH") {
+            Some(Format::Ips)
+        } else if patch.starts_with(b"UPS1") {
+            Some(Format::Ups)
+        } else if patch.starts_with(b"BPS1") {
+            Some(Format::Bps)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Ips => "IPS",
+            Format::Ups => "UPS",
+            Format::Bps => "BPS",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    UnknownFormat,
+    /// The patch ended unexpectedly, or referenced data outside of the ROM or its output.
+    Corrupted,
+    PatchChecksumMismatch,
+    /// The patch was made for a different ROM (its size or checksum doesn't match).
+    SourceMismatch,
+    TargetChecksumMismatch,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Error::UnknownFormat => "unknown patch format",
+            Error::Corrupted => "the patch file is corrupted",
+            Error::PatchChecksumMismatch => "the patch file's checksum doesn't match",
+            Error::SourceMismatch => "the patch was made for a different ROM",
+            Error::TargetChecksumMismatch => "the patched ROM's checksum doesn't match",
+        })
+    }
+}
+
+impl core::error::Error for Error {}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0_u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = crc >> 1 ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8], pos: usize) -> Self {
+        Reader { bytes, pos }
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos.checked_add(len).ok_or(Error::Corrupted)?)
+            .ok_or(Error::Corrupted)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16_be(&mut self) -> Result<u16, Error> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u24_be(&mut self) -> Result<u32, Error> {
+        let bytes = self.bytes(3)?;
+        Ok(u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]))
+    }
+
+    /// Reads a variable-length number, as used by UPS and BPS patches.
+    fn varint(&mut self) -> Result<usize, Error> {
+        let mut value = 0_usize;
+        let mut shift = 1_usize;
+        loop {
+            let byte = self.u8()?;
+            value = (byte as usize & 0x7F)
+                .checked_mul(shift)
+                .and_then(|digit| value.checked_add(digit))
+                .ok_or(Error::Corrupted)?;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            shift = shift.checked_shl(7).ok_or(Error::Corrupted)?;
+            value = value.checked_add(shift).ok_or(Error::Corrupted)?;
+        }
+    }
+}
+
+/// Splits off and checks the 12-byte footer shared by UPS and BPS patches, returning the patch
+/// body and the expected source and target checksums.
+fn split_footer(patch: &[u8]) -> Result<(&[u8], u32, u32), Error> {
+    if patch.len() < 16 {
+        return Err(Error::Corrupted);
+    }
+    let (body, footer) = patch.split_at(patch.len() - 12);
+    let read_u32 = |i: usize| u32::from_le_bytes(footer[i..i + 4].try_into().unwrap());
+    if crc32(&patch[..patch.len() - 4]) != read_u32(8) {
+        return Err(Error::PatchChecksumMismatch);
+    }
+    Ok((body, read_u32(0), read_u32(4)))
+}
+
+fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut output = rom.to_vec();
+    let mut reader = Reader::new(patch, 5);
+    loop {
+        let offset = reader.u24_be()?;
+        if offset == 0x45_4F46 {
+            // "EOF", optionally followed by the length to truncate the output to
+            if let Ok(len) = reader.u24_be() {
+                output.truncate(len as usize);
+            }
+            return Ok(output);
+        }
+        let offset = offset as usize;
+        let len = reader.u16_be()? as usize;
+        if len == 0 {
+            let len = reader.u16_be()? as usize;
+            let value = reader.u8()?;
+            if output.len() < offset + len {
+                output.resize(offset + len, 0);
+            }
+            output[offset..offset + len].fill(value);
+        } else {
+            let data = reader.bytes(len)?;
+            if output.len() < offset + len {
+                output.resize(offset + len, 0);
+            }
+            output[offset..offset + len].copy_from_slice(data);
+        }
+    }
+}
+
+fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    let (body, source_crc, target_crc) = split_footer(patch)?;
+    let mut reader = Reader::new(body, 4);
+    let source_len = reader.varint()?;
+    let target_len = reader.varint()?;
+    if target_len > MAX_TARGET_LEN {
+        return Err(Error::Corrupted);
+    }
+    if source_len != rom.len() || crc32(rom) != source_crc {
+        return Err(Error::SourceMismatch);
+    }
+
+    let mut output = vec![0; target_len];
+    let copy_len = rom.len().min(target_len);
+    output[..copy_len].copy_from_slice(&rom[..copy_len]);
+    let mut offset = 0_usize;
+    while !reader.is_at_end() {
+        offset = offset
+            .checked_add(reader.varint()?)
+            .ok_or(Error::Corrupted)?;
+        loop {
+            let xor = reader.u8()?;
+            if xor == 0 {
+                offset += 1;
+                break;
+            }
+            *output.get_mut(offset).ok_or(Error::Corrupted)? ^= xor;
+            offset += 1;
+        }
+    }
+
+    if crc32(&output) != target_crc {
+        return Err(Error::TargetChecksumMismatch);
+    }
+    Ok(output)
+}
+
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    let (body, source_crc, target_crc) = split_footer(patch)?;
+    let mut reader = Reader::new(body, 4);
+    let source_len = reader.varint()?;
+    let target_len = reader.varint()?;
+    if target_len > MAX_TARGET_LEN {
+        return Err(Error::Corrupted);
+    }
+    let metadata_len = reader.varint()?;
+    reader.bytes(metadata_len)?;
+    if source_len != rom.len() || crc32(rom) != source_crc {
+        return Err(Error::SourceMismatch);
+    }
+
+    let mut output = Vec::with_capacity(target_len);
+    let mut source_offset = 0_usize;
+    let mut target_offset = 0_usize;
+    let apply_rel_offset = |offset: usize, value: usize| {
+        let delta = value >> 1;
+        if value & 1 == 0 {
+            offset.checked_add(delta)
+        } else {
+            offset.checked_sub(delta)
+        }
+        .ok_or(Error::Corrupted)
+    };
+    while !reader.is_at_end() {
+        let action = reader.varint()?;
+        let len = (action >> 2) + 1;
+        if output.len() + len > target_len {
+            return Err(Error::Corrupted);
+        }
+        match action & 3 {
+            // SourceRead
+            0 => {
+                let start = output.len();
+                output.extend_from_slice(rom.get(start..start + len).ok_or(Error::Corrupted)?);
+            }
+            // TargetRead
+            1 => output.extend_from_slice(reader.bytes(len)?),
+            // SourceCopy
+            2 => {
+                source_offset = apply_rel_offset(source_offset, reader.varint()?)?;
+                output.extend_from_slice(
+                    rom.get(source_offset..source_offset + len)
+                        .ok_or(Error::Corrupted)?,
+                );
+                source_offset += len;
+            }
+            // TargetCopy
+            _ => {
+                target_offset = apply_rel_offset(target_offset, reader.varint()?)?;
+                // The source and destination ranges can overlap, repeating previously written
+                // data, so bytes have to be copied one at a time
+                for _ in 0..len {
+                    let byte = *output.get(target_offset).ok_or(Error::Corrupted)?;
+                    output.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    if output.len() != target_len {
+        return Err(Error::Corrupted);
+    }
+    if crc32(&output) != target_crc {
+        return Err(Error::TargetChecksumMismatch);
+    }
+    Ok(output)
+}
+
+/// Applies the given patch (in any of the supported formats, detected from its header) to `rom`,
+/// returning the patched contents.
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    match Format::detect(patch).ok_or(Error::UnknownFormat)? {
+        Format::Ips => apply_ips(rom, patch),
+        Format::Ups => apply_ups(rom, patch),
+        Format::Bps => apply_bps(rom, patch),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varint(mut value: usize, output: &mut Vec<u8>) {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                output.push(byte | 0x80);
+                return;
+            }
+            output.push(byte);
+            value -= 1;
+        }
+    }
+
+    /// Builds a UPS or BPS patch from its magic, header fields and body, appending a footer with
+    /// the checksums of `source` and `target` and of the patch itself.
+    fn build_patch(
+        magic: &[u8],
+        header: &[usize],
+        body: &[u8],
+        source: &[u8],
+        target: &[u8],
+    ) -> Vec<u8> {
+        let mut patch = magic.to_vec();
+        for &value in header {
+            varint(value, &mut patch);
+        }
+        patch.extend_from_slice(body);
+        patch.extend_from_slice(&crc32(source).to_le_bytes());
+        patch.extend_from_slice(&crc32(target).to_le_bytes());
+        patch.extend_from_slice(&crc32(&patch).to_le_bytes());
+        patch
+    }
+
+    const ROM: [u8; 4] = [1, 2, 3, 4];
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn varint_round_trip() {
+        for value in [0, 1, 0x7F, 0x80, 0x3FFF, 0x4000, 0x1234_5678] {
+            let mut bytes = Vec::new();
+            varint(value, &mut bytes);
+            assert_eq!(Reader::new(&bytes, 0).varint(), Ok(value));
+        }
+    }
+
+    #[test]
+    fn unknown_format() {
+        assert_eq!(apply(&ROM, b"NOT A PATCH"), Err(Error::UnknownFormat));
+    }
+
+    #[test]
+    fn ips() {
+        let patch = b"PATCH\0\0\x01\0\x02\x09\x09\0\0\x05\0\0\0\x02\x07EOF";
+        assert_eq!(apply(&ROM, patch), Ok(vec![1, 9, 9, 4, 0, 7, 7]));
+    }
+
+    #[test]
+    fn ips_truncation() {
+        let patch = b"PATCH\0\0\0\0\x01\x05EOF\0\0\x02";
+        assert_eq!(apply(&ROM, patch), Ok(vec![5, 2]));
+    }
+
+    #[test]
+    fn ips_truncated() {
+        assert_eq!(
+            apply(&ROM, b"PATCH\0\0\x01\0\x02\x09"),
+            Err(Error::Corrupted)
+        );
+        assert_eq!(apply(&ROM, b"PATCH\0\0\x01"), Err(Error::Corrupted));
+    }
+
+    const UPS_TARGET: [u8; 5] = [1, 2, 5, 4, 9];
+
+    fn ups_patch(target_len: usize, body: &[u8], target: &[u8]) -> Vec<u8> {
+        build_patch(b"UPS1", &[ROM.len(), target_len], body, &ROM, target)
+    }
+
+    // Skip 2 bytes and XOR the 3 with 6 (giving 5), then XOR the byte the output is extended by
+    const UPS_BODY: [u8; 6] = [0x82, 6, 0, 0x80, 9, 0];
+
+    #[test]
+    fn ups() {
+        let patch = ups_patch(UPS_TARGET.len(), &UPS_BODY, &UPS_TARGET);
+        assert_eq!(apply(&ROM, &patch), Ok(UPS_TARGET.to_vec()));
+    }
+
+    #[test]
+    fn ups_source_mismatch() {
+        let patch = ups_patch(UPS_TARGET.len(), &UPS_BODY, &UPS_TARGET);
+        assert_eq!(apply(&[1, 2, 3, 5], &patch), Err(Error::SourceMismatch));
+        assert_eq!(apply(&[1, 2, 3], &patch), Err(Error::SourceMismatch));
+    }
+
+    #[test]
+    fn ups_checksum_mismatch() {
+        let mut patch = ups_patch(UPS_TARGET.len(), &UPS_BODY, &UPS_TARGET);
+        patch[7] ^= 1;
+        assert_eq!(apply(&ROM, &patch), Err(Error::PatchChecksumMismatch));
+
+        let patch = ups_patch(UPS_TARGET.len(), &UPS_BODY, &[1, 2, 5, 4, 8]);
+        assert_eq!(apply(&ROM, &patch), Err(Error::TargetChecksumMismatch));
+    }
+
+    #[test]
+    fn ups_truncated() {
+        let patch = ups_patch(UPS_TARGET.len(), &UPS_BODY[..5], &UPS_TARGET);
+        assert_eq!(apply(&ROM, &patch), Err(Error::Corrupted));
+        assert_eq!(apply(&ROM, &patch[..15]), Err(Error::Corrupted));
+    }
+
+    #[test]
+    fn ups_out_of_bounds() {
+        let patch = ups_patch(UPS_TARGET.len(), &[0x85, 1, 0], &UPS_TARGET);
+        assert_eq!(apply(&ROM, &patch), Err(Error::Corrupted));
+    }
+
+    #[test]
+    fn ups_target_too_large() {
+        let patch = ups_patch(MAX_TARGET_LEN + 1, &[], &[]);
+        assert_eq!(apply(&ROM, &patch), Err(Error::Corrupted));
+        let patch = ups_patch(usize::MAX >> 8, &[], &[]);
+        assert_eq!(apply(&ROM, &patch), Err(Error::Corrupted));
+    }
+
+    const BPS_TARGET: [u8; 7] = [1, 2, 9, 4, 9, 4, 9];
+
+    fn bps_patch(target_len: usize, body: &[u8], target: &[u8]) -> Vec<u8> {
+        build_patch(b"BPS1", &[ROM.len(), target_len, 0], body, &ROM, target)
+    }
+
+    // Read 2 bytes from the source, write a 9, copy 1 byte from source offset 3, then copy 3
+    // bytes from target offset 2, overlapping the data being written
+    const BPS_BODY: [u8; 7] = [0x84, 0x81, 9, 0x82, 0x86, 0x8B, 0x84];
+
+    #[test]
+    fn bps() {
+        let patch = bps_patch(BPS_TARGET.len(), &BPS_BODY, &BPS_TARGET);
+        assert_eq!(apply(&ROM, &patch), Ok(BPS_TARGET.to_vec()));
+    }
+
+    #[test]
+    fn bps_source_mismatch() {
+        let patch = bps_patch(BPS_TARGET.len(), &BPS_BODY, &BPS_TARGET);
+        assert_eq!(apply(&[1, 2, 3, 5], &patch), Err(Error::SourceMismatch));
+    }
+
+    #[test]
+    fn bps_checksum_mismatch() {
+        let mut patch = bps_patch(BPS_TARGET.len(), &BPS_BODY, &BPS_TARGET);
+        let last = patch.len() - 1;
+        patch[last] ^= 1;
+        assert_eq!(apply(&ROM, &patch), Err(Error::PatchChecksumMismatch));
+
+        let patch = bps_patch(BPS_TARGET.len(), &BPS_BODY, &[1, 2, 9, 4, 9, 4, 8]);
+        assert_eq!(apply(&ROM, &patch), Err(Error::TargetChecksumMismatch));
+    }
+
+    #[test]
+    fn bps_truncated() {
+        // The last action is cut off, leaving the output shorter than expected
+        let patch = bps_patch(BPS_TARGET.len(), &BPS_BODY[..5], &BPS_TARGET);
+        assert_eq!(apply(&ROM, &patch), Err(Error::Corrupted));
+        // A TargetRead action runs past the end of the patch
+        let patch = bps_patch(BPS_TARGET.len(), &[0x85, 9], &BPS_TARGET);
+        assert_eq!(apply(&ROM, &patch), Err(Error::Corrupted));
+    }
+
+    #[test]
+    fn bps_out_of_bounds() {
+        // SourceCopy from before the start of the ROM
+        let patch = bps_patch(BPS_TARGET.len(), &[0x82, 0x83], &BPS_TARGET);
+        assert_eq!(apply(&ROM, &patch), Err(Error::Corrupted));
+        // TargetCopy of bytes that haven't been written yet
+        let patch = bps_patch(BPS_TARGET.len(), &[0x83, 0x80], &BPS_TARGET);
+        assert_eq!(apply(&ROM, &patch), Err(Error::Corrupted));
+        // Writing past the target length
+        let patch = bps_patch(2, &[0x88], &BPS_TARGET);
+        assert_eq!(apply(&ROM, &patch), Err(Error::Corrupted));
+    }
+
+    #[test]
+    fn bps_target_too_large() {
+        let patch = bps_patch(MAX_TARGET_LEN + 1, &[], &[]);
+        assert_eq!(apply(&ROM, &patch), Err(Error::Corrupted));
+    }
+}
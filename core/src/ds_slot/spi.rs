@@ -3,7 +3,9 @@ pub use empty::Empty;
 pub mod eeprom_4k;
 pub mod eeprom_fram;
 pub mod flash;
+pub mod nand;
 
+use super::ir;
 use crate::{utils::Savestate, SaveReloadContents};
 
 trait SpiDevice {
@@ -22,6 +24,7 @@ pub enum Spi {
     Eeprom4k(eeprom_4k::Eeprom4k),
     EepromFram(eeprom_fram::EepromFram),
     Flash(flash::Flash),
+    Nand(nand::Nand),
     Empty(Empty),
 }
 
@@ -33,13 +36,14 @@ impl Spi {
             Spi::Eeprom4k(device) => Spi::Eeprom4k(device.reset()),
             Spi::EepromFram(device) => Spi::EepromFram(device.reset()),
             Spi::Flash(device) => Spi::Flash(device.reset()),
+            Spi::Nand(device) => Spi::Nand(device.reset()),
         }
     }
 
     pub fn contents(&self) -> &[u8] {
         forward_to_variants!(
             Spi;
-            Eeprom4k, EepromFram, Flash, Empty;
+            Eeprom4k, EepromFram, Flash, Nand, Empty;
             self, contents()
         )
     }
@@ -47,7 +51,7 @@ impl Spi {
     pub fn contents_mut(&mut self) -> &mut [u8] {
         forward_to_variants!(
             Spi;
-            Eeprom4k, EepromFram, Flash, Empty;
+            Eeprom4k, EepromFram, Flash, Nand, Empty;
             self, contents_mut()
         )
     }
@@ -55,7 +59,7 @@ impl Spi {
     pub fn reload_contents(&mut self, contents: SaveReloadContents) {
         forward_to_variants!(
             Spi;
-            Eeprom4k, EepromFram, Flash, Empty;
+            Eeprom4k, EepromFram, Flash, Nand, Empty;
             self, reload_contents(contents)
         );
     }
@@ -63,7 +67,7 @@ impl Spi {
     pub fn contents_dirty(&self) -> bool {
         forward_to_variants!(
             Spi;
-            Eeprom4k, EepromFram, Flash, Empty;
+            Eeprom4k, EepromFram, Flash, Nand, Empty;
             self, contents_dirty()
         )
     }
@@ -71,7 +75,7 @@ impl Spi {
     pub fn mark_contents_dirty(&mut self) {
         forward_to_variants!(
             Spi;
-            Eeprom4k, EepromFram, Flash, Empty;
+            Eeprom4k, EepromFram, Flash, Nand, Empty;
             self, mark_contents_dirty()
         );
     }
@@ -79,15 +83,26 @@ impl Spi {
     pub fn mark_contents_flushed(&mut self) {
         forward_to_variants!(
             Spi;
-            Eeprom4k, EepromFram, Flash, Empty;
+            Eeprom4k, EepromFram, Flash, Nand, Empty;
             self, mark_contents_flushed()
         );
     }
 
-    pub fn write_data(&mut self, data: u8, first: bool, last: bool) -> u8 {
+    pub fn write_data(
+        &mut self,
+        data: u8,
+        first: bool,
+        last: bool,
+        ir_backend: &mut dyn ir::Backend,
+    ) -> u8 {
+        if let Spi::Flash(device) = self {
+            if device.has_ir() {
+                return device.write_data_ir(data, first, last, ir_backend);
+            }
+        }
         forward_to_variants!(
             Spi;
-            Eeprom4k, EepromFram, Flash, Empty;
+            Eeprom4k, EepromFram, Flash, Nand, Empty;
             self, write_data(data, first, last)
         )
     }
@@ -95,6 +110,6 @@ impl Spi {
 
 impl_from_variants!(
     Spi;
-    Eeprom4k, EepromFram, Flash, Empty;
-    eeprom_4k::Eeprom4k, eeprom_fram::EepromFram, flash::Flash, Empty
+    Eeprom4k, EepromFram, Flash, Nand, Empty;
+    eeprom_4k::Eeprom4k, eeprom_fram::EepromFram, flash::Flash, nand::Nand, Empty
 );
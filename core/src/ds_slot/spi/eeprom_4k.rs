@@ -2,6 +2,7 @@ use crate::{
     utils::{mem_prelude::*, Savestate},
     SaveContents, SaveReloadContents,
 };
+use core::fmt;
 
 proc_bitfield::bitfield! {
     #[derive(Clone, Copy, PartialEq, Eq)]
@@ -41,9 +42,22 @@ pub struct Eeprom4k {
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CreationError {
-    InvalidSize,
+    /// The contents' size, given in bytes, isn't 512 B.
+    InvalidSize(usize),
 }
 
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CreationError::InvalidSize(len) => {
+                write!(f, "invalid 4 Kib EEPROM size: {len} B (expected 512 B)")
+            }
+        }
+    }
+}
+
+impl core::error::Error for CreationError {}
+
 impl Eeprom4k {
     pub fn new(
         contents: SaveContents,
@@ -51,7 +65,7 @@ impl Eeprom4k {
         #[cfg(feature = "log")] logger: slog::Logger,
     ) -> Result<Self, CreationError> {
         if contents.len() != 512 {
-            return Err(CreationError::InvalidSize);
+            return Err(CreationError::InvalidSize(contents.len()));
         }
         let mut result = Eeprom4k {
             #[cfg(feature = "log")]
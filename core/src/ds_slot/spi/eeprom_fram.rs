@@ -2,6 +2,7 @@ use crate::{
     utils::{mem_prelude::*, Savestate},
     SaveContents, SaveReloadContents,
 };
+use core::fmt;
 
 proc_bitfield::bitfield! {
     #[derive(Clone, Copy, PartialEq, Eq, Savestate)]
@@ -51,9 +52,23 @@ pub struct EepromFram {
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CreationError {
-    InvalidSize,
+    /// The contents' size, given in bytes, isn't one of 8 KiB, 64 KiB or 128 KiB.
+    InvalidSize(usize),
 }
 
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CreationError::InvalidSize(len) => write!(
+                f,
+                "invalid EEPROM/FRAM size: {len} B (expected 8 KiB, 64 KiB or 128 KiB)"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for CreationError {}
+
 pub enum CreationContents {}
 
 impl EepromFram {
@@ -63,7 +78,7 @@ impl EepromFram {
         #[cfg(feature = "log")] logger: slog::Logger,
     ) -> Result<Self, CreationError> {
         if !matches!(contents.len(), 0x2000 | 0x1_0000 | 0x2_0000) {
-            return Err(CreationError::InvalidSize);
+            return Err(CreationError::InvalidSize(contents.len()));
         }
         let contents_len_mask = (contents.len() - 1) as u32;
         let page_mask = match contents.len().trailing_zeros() {
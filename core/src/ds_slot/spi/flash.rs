@@ -1,4 +1,10 @@
-use crate::{flash, utils::Savestate, SaveContents, SaveReloadContents};
+use super::super::ir;
+use crate::{
+    flash,
+    utils::{mem_prelude::*, zeroed_box, Savestate},
+    SaveContents, SaveReloadContents,
+};
+use core::fmt;
 
 pub type Status = flash::Status;
 
@@ -13,11 +19,38 @@ pub struct Flash {
     has_ir: bool,
     ir_cmd: u8,
     first_ir_data_byte: bool,
+    ir_buffer: Box<Bytes<0x100>>,
+    ir_len: u8,
+    ir_pos: u8,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CreationError {
-    InvalidSize,
+    /// The contents' size, given in bytes, isn't one of 256 KiB, 512 KiB or 1 MiB.
+    InvalidSize(usize),
+    /// The underlying FLASH chip couldn't be created.
+    Contents(flash::CreationError),
+}
+
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CreationError::InvalidSize(len) => write!(
+                f,
+                "invalid FLASH save size: {len} B (expected 256 KiB, 512 KiB or 1 MiB)"
+            ),
+            CreationError::Contents(err) => fmt::Display::fmt(err, f),
+        }
+    }
+}
+
+impl core::error::Error for CreationError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            CreationError::InvalidSize(_) => None,
+            CreationError::Contents(err) => Some(err),
+        }
+    }
 }
 
 pub enum CreationContents {}
@@ -30,7 +63,7 @@ impl Flash {
         #[cfg(feature = "log")] logger: slog::Logger,
     ) -> Result<Self, CreationError> {
         if !matches!(contents.len(), 0x4_0000 | 0x8_0000 | 0x10_0000) {
-            return Err(CreationError::InvalidSize);
+            return Err(CreationError::InvalidSize(contents.len()));
         }
         Ok(Flash {
             contents: flash::Flash::new(
@@ -39,11 +72,13 @@ impl Flash {
                 #[cfg(feature = "log")]
                 logger.new(slog::o!("contents" => "")),
             )
-            // NOTE: The contents' length was just checked above, this should never occur.
-            .expect("couldn't create SPI FLASH device"),
+            .map_err(CreationError::Contents)?,
             has_ir,
             ir_cmd: 0,
             first_ir_data_byte: false,
+            ir_buffer: zeroed_box(),
+            ir_len: 0,
+            ir_pos: 0,
             #[cfg(feature = "log")]
             logger,
         })
@@ -55,6 +90,8 @@ impl Flash {
             contents: self.contents.reset(),
             ir_cmd: 0,
             first_ir_data_byte: false,
+            ir_len: 0,
+            ir_pos: 0,
             ..self
         }
     }
@@ -93,6 +130,86 @@ impl Flash {
     pub fn cur_command_pos(&self) -> u8 {
         self.contents.cur_command_pos()
     }
+
+    /// Handles a byte written to a cartridge with an IR transceiver, where the first byte of each
+    /// transfer selects whether to access the FLASH chip or the IR link.
+    pub(super) fn write_data_ir(
+        &mut self,
+        value: u8,
+        first: bool,
+        last: bool,
+        ir_backend: &mut dyn ir::Backend,
+    ) -> u8 {
+        if first {
+            self.ir_cmd = value;
+            self.first_ir_data_byte = true;
+            return 0;
+        }
+        let first = self.first_ir_data_byte;
+        self.first_ir_data_byte = false;
+        match self.ir_cmd {
+            0x00 => {
+                // Pass-through to FLASH chip
+                self.contents.handle_byte(value, first, last)
+            }
+
+            0x01 => {
+                // Receive; the first byte returned is the received packet's length, 0 if nothing
+                // was received
+                if first {
+                    self.ir_len = ir_backend
+                        .receive(&mut self.ir_buffer[..ir::MAX_PACKET_LEN])
+                        .min(ir::MAX_PACKET_LEN) as u8;
+                    self.ir_pos = 0;
+                    self.ir_len
+                } else if self.ir_pos < self.ir_len {
+                    let result = self.ir_buffer[self.ir_pos as usize];
+                    self.ir_pos += 1;
+                    result
+                } else {
+                    0
+                }
+            }
+
+            0x02 => {
+                // Send; the packet is transmitted once the transfer ends
+                if first {
+                    self.ir_len = 0;
+                }
+                if (self.ir_len as usize) < ir::MAX_PACKET_LEN {
+                    self.ir_buffer[self.ir_len as usize] = value;
+                    self.ir_len += 1;
+                }
+                if last {
+                    ir_backend.send(&self.ir_buffer[..self.ir_len as usize]);
+                    self.ir_len = 0;
+                }
+                0
+            }
+
+            0x08 => {
+                // Read ID
+                0xAA
+            }
+
+            _command => {
+                #[cfg(feature = "log")]
+                slog::warn!(
+                    self.logger,
+                    "Unknown IR byte (command {:#04X}): {:#04X}{}",
+                    _command,
+                    value,
+                    match (first, last) {
+                        (false, false) => "",
+                        (true, false) => " (first)",
+                        (false, true) => " (last)",
+                        (true, true) => " (first, last)",
+                    }
+                );
+                0
+            }
+        }
+    }
 }
 
 impl super::SpiDevice for Flash {
@@ -128,45 +245,6 @@ impl super::SpiDevice for Flash {
     }
 
     fn write_data(&mut self, value: u8, first: bool, last: bool) -> u8 {
-        if self.has_ir {
-            if first {
-                self.ir_cmd = value;
-                self.first_ir_data_byte = true;
-                0
-            } else {
-                let first = self.first_ir_data_byte;
-                self.first_ir_data_byte = false;
-                match self.ir_cmd {
-                    0x00 => {
-                        // Pass-through to FLASH chip
-                        self.contents.handle_byte(value, first, last)
-                    }
-
-                    0x08 => {
-                        // Read ID
-                        0xAA
-                    }
-
-                    _command => {
-                        #[cfg(feature = "log")]
-                        slog::warn!(
-                            self.logger,
-                            "Unknown IR byte (command {:#04X}): {:#04X}{}",
-                            _command,
-                            value,
-                            match (first, last) {
-                                (false, false) => "",
-                                (true, false) => " (first)",
-                                (false, true) => " (last)",
-                                (true, true) => " (first, last)",
-                            }
-                        );
-                        0
-                    }
-                }
-            }
-        } else {
-            self.contents.handle_byte(value, first, last)
-        }
+        self.contents.handle_byte(value, first, last)
     }
 }
@@ -0,0 +1,276 @@
+use crate::{
+    ds_slot::RomOutputLen,
+    utils::{mem_prelude::*, zeroed_box, Savestate},
+    SaveContents, SaveReloadContents,
+};
+use core::fmt;
+
+// The save area can only be accessed through 128 KiB windows
+const WINDOW_MASK: u32 = !0x1_FFFF;
+const PAGE_SIZE: usize = 0x200;
+const WRITE_BUFFER_SIZE: usize = 0x800;
+
+// The ID returned by the Samsung NAND chips found in these cartridges; the rest of the response is
+// zero-filled
+const CHIP_ID: [u8; 5] = [0xEC, 0xF1, 0x00, 0x95, 0x40];
+
+proc_bitfield::bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq, Savestate)]
+    pub struct Status(pub u8): Debug {
+        pub write_enabled: bool @ 4,
+        pub ready: bool @ 5,
+    }
+}
+
+/// The NAND save memory found in some larger cartridges (i.e. WarioWare D.I.Y. and Jam with the
+/// Band).
+///
+/// Unlike the other save chips, it's not connected to the SPI bus; instead, it's accessed through
+/// dedicated ROM commands, handled by [`Nand::handle_rom_command`] and
+/// [`Nand::handle_rom_write`], while the save area is mapped into the ROM's address space.
+#[derive(Clone, Savestate)]
+#[load(in_place_only)]
+pub struct Nand {
+    #[cfg(feature = "log")]
+    #[savestate(skip)]
+    logger: slog::Logger,
+
+    #[savestate(skip)]
+    contents: BoxedByteSlice,
+    #[savestate(skip)]
+    contents_dirty: bool,
+
+    status: Status,
+    /// The offset inside the save area of the currently selected window, or `None` if the ROM is
+    /// being accessed instead.
+    window: Option<u32>,
+    write_addr: u32,
+    write_buffer: Box<Bytes<WRITE_BUFFER_SIZE>>,
+    write_buffer_len: u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CreationError {
+    /// The contents' size, given in bytes, isn't one of 8 MiB, 16 MiB or 32 MiB.
+    InvalidSize(usize),
+}
+
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CreationError::InvalidSize(len) => write!(
+                f,
+                "invalid NAND save size: {len} B (expected 8 MiB, 16 MiB or 32 MiB)"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for CreationError {}
+
+impl Nand {
+    pub fn new(
+        contents: SaveContents,
+        #[cfg(feature = "log")] logger: slog::Logger,
+    ) -> Result<Self, CreationError> {
+        if !matches!(contents.len(), 0x80_0000 | 0x100_0000 | 0x200_0000) {
+            return Err(CreationError::InvalidSize(contents.len()));
+        }
+        Ok(Nand {
+            #[cfg(feature = "log")]
+            logger,
+
+            contents: contents.get_or_create(|len| {
+                let mut contents = BoxedByteSlice::new_zeroed(len);
+                contents.fill(0xFF);
+                contents
+            }),
+            contents_dirty: false,
+
+            status: Status(0).with_ready(true),
+            window: None,
+            write_addr: 0,
+            write_buffer: zeroed_box(),
+            write_buffer_len: 0,
+        })
+    }
+
+    #[must_use]
+    pub fn reset(self) -> Self {
+        Nand {
+            status: Status(0).with_ready(true),
+            window: None,
+            write_addr: 0,
+            write_buffer_len: 0,
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    #[inline]
+    pub fn window(&self) -> Option<u32> {
+        self.window
+    }
+
+    /// Handles a KEY2-stage ROM command if it concerns the save area, given the ROM address the
+    /// save area starts at; returns whether the command was handled.
+    pub fn handle_rom_command(
+        &mut self,
+        cmd: &Bytes<8>,
+        output: &mut Bytes<0x4000>,
+        output_len: RomOutputLen,
+        save_start: u32,
+    ) -> bool {
+        let output = &mut output[..output_len.get() as usize];
+        match cmd[0] {
+            0x81 => {
+                // Write data; the data is only received once the transfer ends
+            }
+
+            0x82 => {
+                // Commit the write buffer
+                if self.status.write_enabled() {
+                    let len = self.write_buffer_len as usize;
+                    let start = self.write_addr as usize;
+                    if start < self.contents.len() {
+                        let len = len.min(self.contents.len() - start);
+                        self.contents[start..start + len]
+                            .copy_from_slice(&self.write_buffer[..len]);
+                        self.contents_dirty = true;
+                    }
+                    self.write_addr = self.write_addr.wrapping_add(len as u32);
+                    self.write_buffer_len = 0;
+                    self.status.set_write_enabled(false);
+                }
+                output.fill(0);
+            }
+
+            0x84 => {
+                // Discard the write buffer
+                self.write_buffer_len = 0;
+                self.status.set_write_enabled(false);
+                output.fill(0);
+            }
+
+            0x85 => {
+                // Write enable
+                if self.window.is_some() {
+                    self.status.set_write_enabled(true);
+                    self.write_buffer_len = 0;
+                }
+                output.fill(0);
+            }
+
+            0x8B => {
+                // Go back to accessing the ROM
+                self.window = None;
+                self.status.set_write_enabled(false);
+                output.fill(0);
+            }
+
+            0x94 => {
+                // Read ID
+                output.fill(0);
+                let len = CHIP_ID.len().min(output.len());
+                output[..len].copy_from_slice(&CHIP_ID[..len]);
+            }
+
+            0xB2 => {
+                // Select the save area window to access
+                let addr = cmd.read_be::<u32>(1) & WINDOW_MASK;
+                match addr.checked_sub(save_start) {
+                    Some(offset) if (offset as usize) < self.contents.len() => {
+                        self.window = Some(offset);
+                        self.write_addr = offset;
+                    }
+                    _ => {
+                        #[cfg(feature = "log")]
+                        slog::warn!(
+                            self.logger,
+                            "Tried to select out-of-bounds save window @ {:#010X}",
+                            addr
+                        );
+                    }
+                }
+                output.fill(0);
+            }
+
+            0xB7 => {
+                // Read data, only handled here while a save area window is selected
+                if self.window.is_none() {
+                    return false;
+                }
+                let addr = cmd.read_be::<u32>(1);
+                match addr.checked_sub(save_start) {
+                    Some(offset) if (offset as usize) < self.contents.len() => {
+                        let start = offset as usize;
+                        let len = output.len().min(self.contents.len() - start);
+                        output[..len].copy_from_slice(&self.contents[start..start + len]);
+                        output[len..].fill(0xFF);
+                    }
+                    _ => output.fill(0xFF),
+                }
+            }
+
+            0xD6 => {
+                // Read status
+                output.fill(self.status.0);
+            }
+
+            _ => return false,
+        }
+        true
+    }
+
+    /// Receives the data sent along with a ROM command once the transfer ends.
+    pub fn handle_rom_write(&mut self, cmd: &Bytes<8>, data: &[u8]) {
+        if cmd[0] != 0x81 || !self.status.write_enabled() {
+            return;
+        }
+        let start = self.write_buffer_len as usize;
+        let len = data.len().min(PAGE_SIZE).min(WRITE_BUFFER_SIZE - start);
+        self.write_buffer[start..start + len].copy_from_slice(&data[..len]);
+        self.write_buffer_len += len as u16;
+    }
+}
+
+impl super::SpiDevice for Nand {
+    fn contents(&self) -> &[u8] {
+        &self.contents
+    }
+
+    fn contents_mut(&mut self) -> &mut [u8] {
+        &mut self.contents
+    }
+
+    fn reload_contents(&mut self, contents: SaveReloadContents) {
+        match contents {
+            SaveReloadContents::Existing(contents) => {
+                self.contents[..contents.len()].copy_from_slice(&contents);
+                self.contents[contents.len()..].fill(0xFF);
+            }
+            SaveReloadContents::New => self.contents.fill(0xFF),
+        }
+    }
+
+    fn contents_dirty(&self) -> bool {
+        self.contents_dirty
+    }
+
+    fn mark_contents_dirty(&mut self) {
+        self.contents_dirty = true;
+    }
+
+    fn mark_contents_flushed(&mut self) {
+        self.contents_dirty = false;
+    }
+
+    fn write_data(&mut self, _data: u8, _first: bool, _last: bool) -> u8 {
+        // No device is connected to the SPI bus
+        0xFF
+    }
+}
@@ -2,11 +2,24 @@ mod schedule;
 pub use schedule::{
     event_slots, Event, EventSlotIndex, Schedule, Timestamp, DEFAULT_BATCH_DURATION,
 };
+pub mod crash;
+#[cfg(all(feature = "fault-injection", debug_assertions))]
+pub mod fault_injection;
+#[cfg(feature = "frame-limiter")]
+pub mod frame_limiter;
 pub mod input;
+#[cfg(feature = "link")]
+pub mod link;
+pub mod mem_regions;
+pub mod noise;
+pub mod savestate;
 pub mod swram;
+#[cfg(test)]
+pub(crate) mod testing;
 
 use crate::{
     audio::{self, Audio},
+    camera::{self, Camera},
     cpu::{
         self,
         arm7::{self, Arm7},
@@ -17,17 +30,28 @@ use crate::{
     dldi::{self, Dldi},
     ds_slot::{self, DsSlot},
     flash::Flash,
+    gba_slot::{self, GbaSlot},
     gpu::{self, engine_3d::Engine3d, Gpu},
     ipc::Ipc,
+    ndma::Ndma,
     rtc::{self, Rtc},
     spi,
     utils::{mem_prelude::*, schedule::RawTimestamp, ReadSavestate, Savestate, WriteSavestate},
     wifi::WiFi,
     Model,
 };
+use core::fmt;
 #[cfg(feature = "xq-audio")]
 use core::num::NonZeroU32;
 use input::Input;
+use noise::Noise;
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 use swram::Swram;
 
 proc_bitfield::bitfield! {
@@ -85,6 +109,34 @@ mod bounded {
 }
 pub use bounded::*;
 
+/// A handle that can be used from any thread to make [`Emu::run`] (and
+/// [`Emu::run_with_cycles`]) return [`RunOutput::Cancelled`] as soon as the current batch of
+/// cycles is over, even if the current frame hasn't finished yet.
+#[derive(Clone, Default)]
+pub struct RunCancelToken(Arc<AtomicBool>);
+
+impl RunCancelToken {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    fn take(&self) -> bool {
+        self.is_cancelled() && self.0.swap(false, Ordering::Relaxed)
+    }
+}
+
 #[derive(Savestate)]
 #[load(in_place_only, post = "self.post_load(save)?")]
 #[store(post = "self.post_store(save)?")]
@@ -102,10 +154,17 @@ pub struct Emu<E: cpu::Engine> {
     global_ex_mem_control: GlobalExMemControl,
     pub ipc: Ipc,
     pub ds_slot: DsSlot,
+    #[savestate(skip)]
+    pub gba_slot: GbaSlot,
     pub spi: spi::Controller,
     pub rtc: Rtc,
+    pub camera: Camera,
+    pub ndma: Ndma,
     pub gpu: Gpu,
     pub input: Input,
+    pub noise: Noise,
+    #[savestate(skip)]
+    queued_input_changes: VecDeque<(u32, input::Change)>,
     pub audio_wifi_power_control: AudioWifiPowerControl,
     pub audio: Audio,
     pub wifi: WiFi,
@@ -116,10 +175,24 @@ pub struct Emu<E: cpu::Engine> {
     #[cfg(feature = "debugger-hooks")]
     #[savestate(skip)]
     frame_finished: bool,
+    #[cfg(feature = "lockstep-trace")]
+    #[savestate(skip)]
+    pub trace: cpu::trace::Trace,
+    #[cfg(all(feature = "fault-injection", debug_assertions))]
+    #[savestate(skip)]
+    pub(crate) fault_injector: fault_injection::FaultInjector<E>,
+    #[savestate(skip)]
+    crash_hook: Option<crash::Hook<E>>,
+    #[savestate(skip)]
+    run_cancel_token: RunCancelToken,
+    #[savestate(skip)]
+    frame_cancelled: bool,
 }
 
 impl<E: cpu::Engine> Emu<E> {
     fn post_load<S: ReadSavestate>(&mut self, save: &mut S) -> Result<(), S::Error> {
+        self.queued_input_changes.clear();
+
         save.start_field(b"main_mem")?;
         if self.is_debugger {
             self.main_mem_mask = MainMemMask::new(0x7F_FFFF);
@@ -161,32 +234,76 @@ pub struct Builder {
     pub firmware: Flash,
     pub ds_rom: Option<Box<dyn ds_slot::rom::Contents>>,
     pub ds_spi: ds_slot::spi::Spi,
+    pub gba_cart: Option<gba_slot::Cart>,
     pub audio_backend: Box<dyn audio::Backend>,
     pub mic_backend: Option<Box<dyn spi::tsc::MicBackend>>,
     pub rtc_backend: Box<dyn rtc::Backend>,
     pub renderer_2d: Box<dyn gpu::engine_2d::Renderer>,
     pub renderer_3d_tx: Box<dyn gpu::engine_3d::RendererTx>,
     pub dldi_provider: Option<Box<dyn dldi::Provider>>,
+    pub camera_backend: Box<dyn camera::Backend>,
+    pub ir_backend: Box<dyn ds_slot::ir::Backend>,
 
     pub arm7_bios: Option<Box<Bytes<{ arm7::BIOS_SIZE }>>>,
     pub arm9_bios: Option<Box<Bytes<{ arm9::BIOS_SIZE }>>>,
+    /// A standalone copy of the KEY1 table, only used to handle encrypted ROMs when no ARM7 BIOS
+    /// was provided.
+    pub key1_table: Option<Box<Bytes<{ ds_slot::rom::key1::TABLE_SIZE }>>>,
     pub model: Model,
     pub is_debugger: bool,
     pub direct_boot: bool,
+    /// Whether to offload the 3D geometry engine's vertex transformation, clipping and polygon
+    /// setup to a worker thread; results are identical either way, as the emulation thread waits
+    /// for the worker whenever they're observable (i.e. on `SwapBuffers`).
+    pub threaded_3d_geometry: bool,
     pub batch_duration: u32,
     pub first_launch: bool,
+    pub noise_seed: u64,
+    /// Whether to add noise to analog readings (i.e. jitter to touchscreen coordinates and
+    /// dithering to microphone samples), as the hardware does; disabled by default, as exact
+    /// readings are easier to work with and to reproduce.
+    pub analog_noise: bool,
     pub audio_sample_chunk_size: u16,
     #[cfg(feature = "xq-audio")]
     pub audio_custom_sample_rate: Option<NonZeroU32>,
     #[cfg(feature = "xq-audio")]
     pub audio_channel_interp_method: audio::ChannelInterpMethod,
+    #[cfg(feature = "lockstep-trace")]
+    pub trace_capacity: usize,
+    pub run_cancel_token: RunCancelToken,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BuildError {
+    /// Direct boot was requested, but no DS slot ROM was provided.
     MissingRom,
+    /// Booting through the firmware was requested, but the BIOS files weren't provided.
     MissingSysFiles,
     RomCreation(ds_slot::rom::normal::CreationError),
-    RomNeedsDecryptionButNoBiosProvided,
+    RomSetup(ds_slot::rom::SetupError),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::MissingRom => f.write_str("direct boot requires a DS slot ROM"),
+            BuildError::MissingSysFiles => {
+                f.write_str("booting through the firmware requires both the ARM7 and ARM9 BIOS")
+            }
+            BuildError::RomCreation(err) => fmt::Display::fmt(err, f),
+            BuildError::RomSetup(err) => fmt::Display::fmt(err, f),
+        }
+    }
+}
+
+impl core::error::Error for BuildError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            BuildError::MissingRom | BuildError::MissingSysFiles => None,
+            BuildError::RomCreation(err) => Some(err),
+            BuildError::RomSetup(err) => Some(err),
+        }
+    }
 }
 
 impl Builder {
@@ -211,25 +328,35 @@ impl Builder {
             firmware,
             ds_rom,
             ds_spi,
+            gba_cart: None,
             audio_backend,
             mic_backend,
             rtc_backend,
             renderer_2d,
             renderer_3d_tx,
             dldi_provider,
+            camera_backend: Box::new(camera::DummyBackend),
+            ir_backend: Box::new(ds_slot::ir::DummyBackend),
 
             arm7_bios: None,
             arm9_bios: None,
+            key1_table: None,
             model: Model::Ds,
             is_debugger: false,
             direct_boot: true,
+            threaded_3d_geometry: false,
             batch_duration: DEFAULT_BATCH_DURATION,
             first_launch: false,
+            noise_seed: noise::DEFAULT_SEED,
+            analog_noise: false,
             audio_sample_chunk_size: audio::DEFAULT_OUTPUT_SAMPLE_CHUNK_SIZE,
             #[cfg(feature = "xq-audio")]
             audio_custom_sample_rate: None,
             #[cfg(feature = "xq-audio")]
             audio_channel_interp_method: audio::ChannelInterpMethod::Nearest,
+            #[cfg(feature = "lockstep-trace")]
+            trace_capacity: cpu::trace::DEFAULT_CAPACITY,
+            run_cancel_token: RunCancelToken::new(),
         }
     }
 
@@ -244,11 +371,17 @@ impl Builder {
             .zip(self.dldi_provider)
             .and_then(|(ds_rom, dldi_provider)| Dldi::new_if_supported(ds_rom, dldi_provider));
 
+        let key1_table = self
+            .arm7_bios
+            .as_deref()
+            .map(ds_slot::rom::key1::table_from_arm7_bios)
+            .or(self.key1_table);
+
         let mut ds_rom = match self.ds_rom {
             Some(contents) => ds_slot::rom::Rom::Normal(
                 ds_slot::rom::normal::Normal::new(
                     contents,
-                    self.arm7_bios.as_deref(),
+                    key1_table.as_deref(),
                     self.model,
                     #[cfg(feature = "log")]
                     self.logger.new(slog::o!("ds_rom" => "normal")),
@@ -263,7 +396,7 @@ impl Builder {
 
         ds_rom
             .setup(self.direct_boot)
-            .map_err(|()| BuildError::RomNeedsDecryptionButNoBiosProvided)?;
+            .map_err(BuildError::RomSetup)?;
 
         let (global_engine_data, arm7_engine_data, arm9_engine_data) = engine.into_data();
         let mut arm7 = Arm7::new(
@@ -279,6 +412,7 @@ impl Builder {
                 (unsafe { buf.as_mut_arr() })[..arm9::BIOS_SIZE].copy_from_slice(&**bios);
                 buf
             }),
+            self.model,
             #[cfg(feature = "log")]
             self.logger.new(slog::o!("cpu" => "arm9")),
         );
@@ -294,7 +428,14 @@ impl Builder {
             swram: Swram::new(),
             global_ex_mem_control: GlobalExMemControl(0x6000),
             ipc: Ipc::new(),
-            ds_slot: DsSlot::new(ds_rom, self.ds_spi, &mut arm7.schedule, &mut arm9.schedule),
+            ds_slot: DsSlot::new(
+                ds_rom,
+                self.ds_spi,
+                self.ir_backend,
+                &mut arm7.schedule,
+                &mut arm9.schedule,
+            ),
+            gba_slot: GbaSlot::new(self.gba_cart),
             spi: spi::Controller::new(
                 self.model,
                 self.firmware,
@@ -307,18 +448,29 @@ impl Builder {
             rtc: Rtc::new(
                 self.rtc_backend,
                 self.first_launch,
+                &mut global_schedule,
                 #[cfg(feature = "log")]
                 self.logger.new(slog::o!("rtc" => "")),
             ),
+            camera: Camera::new(
+                self.camera_backend,
+                self.model == Model::Dsi,
+                #[cfg(feature = "log")]
+                self.logger.new(slog::o!("camera" => "")),
+            ),
+            ndma: Ndma::new(self.model == Model::Dsi, &mut arm9.schedule),
             gpu: Gpu::new(
                 self.renderer_2d,
                 self.renderer_3d_tx,
+                self.threaded_3d_geometry,
                 &mut arm9.schedule,
                 &mut global_schedule,
                 #[cfg(feature = "log")]
                 &self.logger.new(slog::o!("gpu" => "")),
             ),
             input: Input::new(),
+            noise: Noise::new(self.noise_seed, self.analog_noise),
+            queued_input_changes: VecDeque::new(),
             audio_wifi_power_control: AudioWifiPowerControl(0),
             audio: Audio::new(
                 self.audio_backend,
@@ -340,6 +492,13 @@ impl Builder {
             is_debugger: self.is_debugger,
             #[cfg(feature = "debugger-hooks")]
             frame_finished: true,
+            #[cfg(feature = "lockstep-trace")]
+            trace: cpu::trace::Trace::new(self.trace_capacity),
+            #[cfg(all(feature = "fault-injection", debug_assertions))]
+            fault_injector: fault_injection::FaultInjector::new(),
+            crash_hook: None,
+            run_cancel_token: self.run_cancel_token,
+            frame_cancelled: false,
         };
         Arm7::setup(&mut emu);
         Arm9::setup(&mut emu);
@@ -367,10 +526,15 @@ bitflags::bitflags! {
 pub enum RunOutput {
     FrameFinished,
     Shutdown,
+    Cancelled,
     #[cfg(feature = "debugger-hooks")]
     StoppedByDebugHook,
     #[cfg(feature = "debugger-hooks")]
     CyclesOver(CoreMask),
+    /// Emulated time reached [`Schedule::time_limit`]; the interrupted frame will be resumed by the
+    /// next call.
+    #[cfg(feature = "virtual-time")]
+    TimeLimitReached,
 }
 
 impl<E: cpu::Engine> Emu<E> {
@@ -428,7 +592,8 @@ impl<E: cpu::Engine> Emu<E> {
         // RTC status (0 == OK)
         write_main_mem!(0x7F_F816, 0_u16);
         // "Random LSB from SIO debug detect handshake"
-        write_main_mem!(0x7F_F818, 0);
+        let sio_debug_random_lsb = (self.noise.next_u32() & 1) as u8;
+        write_main_mem!(0x7F_F818, sio_debug_random_lsb);
         // NDS7 BIOS CRC
         write_main_mem!(0x7F_F850, 0x5835_u16);
         // Copy of NDS7 RAM address (?)
@@ -472,7 +637,7 @@ impl<E: cpu::Engine> Emu<E> {
         // RTC status (0 == OK)
         write_main_mem!(0x7F_FC16, 0_u8);
         // "Random LSB from SIO debug detect handshake"
-        write_main_mem!(0x7F_FC17, 0);
+        write_main_mem!(0x7F_FC17, sio_debug_random_lsb);
 
         // TODO: GBA cart header data at 0x7F_FC30..0x7F_FC3C
 
@@ -573,7 +738,13 @@ impl<E: cpu::Engine> Emu<E> {
 
     #[inline]
     pub fn rcnt(&self) -> u16 {
-        self.rcnt
+        // In general-purpose mode, the RTC's /INT output can be read through SI when it's set as an
+        // input
+        if self.rcnt >> 14 == 2 && self.rcnt & 1 << 6 == 0 {
+            (self.rcnt & !(1 << 2)) | (!self.rtc.int_active() as u16) << 2
+        } else {
+            self.rcnt
+        }
     }
 
     #[inline]
@@ -663,8 +834,11 @@ macro_rules! run {
                     return RunOutput::Shutdown;
                 }
                 Event::Engine3dCommandFinished => Engine3d::process_next_command($emu),
+                Event::RtcTick => Rtc::handle_tick($emu, time),
             }
         }
+        #[cfg(all(feature = "fault-injection", debug_assertions))]
+        $emu.run_fault_injection();
         #[cfg(feature = "debugger-hooks")]
         {
             if $emu.arm7.was_stopped_by_debug_hook || $emu.arm9.was_stopped_by_debug_hook {
@@ -674,6 +848,15 @@ macro_rules! run {
                 return RunOutput::FrameFinished;
             }
         }
+        #[cfg(feature = "virtual-time")]
+        if $emu.schedule.cur_time() >= $emu.schedule.time_limit {
+            $emu.frame_cancelled = true;
+            return RunOutput::TimeLimitReached;
+        }
+        if $emu.run_cancel_token.take() {
+            $emu.frame_cancelled = true;
+            return RunOutput::Cancelled;
+        }
     };
 }
 
@@ -699,12 +882,15 @@ impl<E: cpu::Engine> Emu<E> {
     #[cfg(feature = "debugger-hooks")]
     #[inline(never)]
     pub fn run_with_cycles(&mut self, cycles: &mut [RawTimestamp; 2]) -> RunOutput {
+        // Partial frames are already tracked through `frame_finished`
+        self.frame_cancelled = false;
         if core::mem::replace(&mut self.frame_finished, false) {
+            self.noise.start_frame();
             self.spi.tsc.start_frame(self.schedule.cur_time());
         }
+        self.arm7.was_stopped_by_debug_hook = false;
+        self.arm9.was_stopped_by_debug_hook = false;
         if (cycles[0] != 0 && !self.arm7.is_stopped) || (cycles[1] != 0 && !self.arm9.is_stopped) {
-            self.arm7.was_stopped_by_debug_hook = false;
-            self.arm9.was_stopped_by_debug_hook = false;
             let mut new_cycles = cycles.map(|c| if c == 0 { RawTimestamp::MAX } else { c });
             let output = self.run_for_cycles(&mut new_cycles);
             for i in 0..2 {
@@ -719,9 +905,18 @@ impl<E: cpu::Engine> Emu<E> {
         }
     }
 
+    #[inline]
+    pub fn run_cancel_token(&self) -> &RunCancelToken {
+        &self.run_cancel_token
+    }
+
     #[inline(never)]
     pub fn run(&mut self) -> RunOutput {
-        self.spi.tsc.start_frame(self.schedule.cur_time());
+        // If the last call was cancelled, resume the same frame instead of starting a new one
+        if !core::mem::replace(&mut self.frame_cancelled, false) {
+            self.noise.start_frame();
+            self.spi.tsc.start_frame(self.schedule.cur_time());
+        }
         loop {
             run!(self, E);
         }
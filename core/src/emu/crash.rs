@@ -0,0 +1,122 @@
+//! Detection of CPU exceptions the emulated software has no handler for (i.e. undefined
+//! instructions or data aborts in homebrew under development), which would otherwise make the
+//! BIOS loop forever in its default handler, so that frontends can report them as crashes instead
+//! of a silent hang.
+//!
+//! Exceptions are only considered unhandled when they'd be dispatched through the BIOS (which
+//! is always the case for the ARM7, and when the ARM9's exception vectors are high) and no user
+//! handler is installed at the address the BIOS jumps through.
+
+use super::Emu;
+use crate::cpu::{psr::Psr, Core, Engine, Regs};
+
+/// The address in main RAM the ARM9 BIOS reads the user exception handler's address from.
+const ARM9_HANDLER_ADDR: u32 = 0x027F_FD9C;
+/// The offset in ARM7 WRAM the ARM7 BIOS reads the user exception handler's address from.
+const ARM7_HANDLER_WRAM_OFFSET: usize = 0xFFDC;
+
+/// The cause of a crash; besides actual CPU exceptions, calls to SWIs the HLE BIOS can't emulate
+/// are reported too, regardless of any user exception handler (which wouldn't be invoked anyway).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exception {
+    UndefinedInstr,
+    PrefetchAbort,
+    DataAbort { addr: u32 },
+    UnsupportedHleSwi { number: u8 },
+}
+
+/// The state of a CPU right before it entered an unhandled exception.
+#[derive(Clone, Debug)]
+pub struct Crash {
+    pub core: Core,
+    pub exception: Exception,
+    /// The address of the instruction that caused the exception.
+    pub instr_addr: u32,
+    pub thumb: bool,
+    pub regs: Regs,
+    pub cpsr: Psr,
+}
+
+/// A hook called whenever a CPU enters an unhandled exception; emulation continues normally
+/// afterwards, so it's up to the hook to stop it through the CPU's schedule or a
+/// [`RunCancelToken`](super::RunCancelToken) if needed.
+pub type Hook<E> = Box<dyn FnMut(&mut Emu<E>, &Crash)>;
+
+impl<E: Engine> Emu<E> {
+    /// Sets the hook called whenever a CPU enters an unhandled exception; the hook can replace
+    /// itself by calling this function, but not remove itself.
+    pub fn set_crash_hook(&mut self, hook: Option<Hook<E>>) {
+        self.crash_hook = hook;
+    }
+
+    fn is_exception_handled(&self, core: Core) -> bool {
+        match core {
+            Core::Arm7 => self.arm7.wram.read_le::<u32>(ARM7_HANDLER_WRAM_OFFSET) != 0,
+            Core::Arm9 => {
+                !self.arm9.cp15.control().high_exc_vectors()
+                    || self
+                        .main_mem
+                        .read_le::<u32>((ARM9_HANDLER_ADDR & self.main_mem_mask.get()) as usize)
+                        != 0
+            }
+        }
+    }
+
+    /// Called by CPU engines right before entering an exception mode, with the registers still
+    /// holding the faulting state.
+    pub(crate) fn report_exception(
+        &mut self,
+        core: Core,
+        exception: Exception,
+        instr_addr: u32,
+        thumb: bool,
+    ) {
+        if self.crash_hook.is_none() || self.is_exception_handled(core) {
+            return;
+        }
+        self.call_crash_hook(core, exception, instr_addr, thumb);
+    }
+
+    /// Called by the HLE BIOS when it's asked to run a SWI it doesn't support, before returning
+    /// to the caller without doing anything.
+    pub(crate) fn report_unsupported_hle_swi(&mut self, core: Core, number: u8) {
+        if self.crash_hook.is_none() {
+            return;
+        }
+        // The return address and the caller's CPSR are banked in supervisor mode by the time the
+        // HLE BIOS handles the SWI
+        let regs = match core {
+            Core::Arm7 => self.arm7.regs(),
+            Core::Arm9 => self.arm9.regs(),
+        };
+        let thumb = regs.spsr_svc.thumb_state();
+        let instr_addr = regs.r13_14_svc[1].wrapping_sub(if thumb { 2 } else { 4 });
+        self.call_crash_hook(
+            core,
+            Exception::UnsupportedHleSwi { number },
+            instr_addr,
+            thumb,
+        );
+    }
+
+    fn call_crash_hook(&mut self, core: Core, exception: Exception, instr_addr: u32, thumb: bool) {
+        let (regs, cpsr) = match core {
+            Core::Arm7 => (self.arm7.regs(), self.arm7.cpsr()),
+            Core::Arm9 => (self.arm9.regs(), self.arm9.cpsr()),
+        };
+        let crash = Crash {
+            core,
+            exception,
+            instr_addr,
+            thumb,
+            regs,
+            cpsr,
+        };
+        if let Some(mut hook) = self.crash_hook.take() {
+            hook(self, &crash);
+            if self.crash_hook.is_none() {
+                self.crash_hook = Some(hook);
+            }
+        }
+    }
+}
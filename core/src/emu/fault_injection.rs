@@ -0,0 +1,137 @@
+//! Injection of controlled hardware faults (delayed DMA completions, spurious IRQs and corrupted
+//! IPC FIFO entries), to exercise error paths and check that both the emulated software and the
+//! emulator itself fail gracefully instead of hanging.
+//!
+//! Faults can be injected directly through [`Emu::inject_fault`], or from a script hook that's
+//! called after every batch of emulated cycles and can decide when to inject them based on the
+//! emulator's state. Only available in debug builds.
+
+use super::Emu;
+use crate::{
+    cpu::{arm7, arm9, dma, Core, Engine, Schedule as _},
+    utils::schedule::RawTimestamp,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// Delays the next completion of a transfer on the given DMA channel by `delay` cycles of the
+    /// CPU's clock: until then, the channel's enable bit stays set (for non-repeating transfers)
+    /// and its IRQ isn't requested.
+    DelayDmaCompletion {
+        core: Core,
+        channel: dma::Index,
+        delay: RawTimestamp,
+    },
+    /// Requests the given IRQs (as a mask of IF bits) without any hardware having raised them.
+    SpuriousIrq { core: Core, irqs: u32 },
+    /// XORs the next word the given CPU reads from its IPC receive FIFO with `mask`.
+    CorruptIpcFifoEntry { core: Core, mask: u32 },
+}
+
+/// A hook called after every batch of emulated cycles, which can inject faults through
+/// [`Emu::inject_fault`].
+pub type Script<E> = Box<dyn FnMut(&mut Emu<E>)>;
+
+pub struct FaultInjector<E: Engine> {
+    script: Option<Script<E>>,
+    /// The masks to XOR the next word received from the IPC FIFO with, for the ARM7 and ARM9
+    /// respectively.
+    ipc_recv_corruption: [u32; 2],
+    injected_faults: u64,
+}
+
+impl<E: Engine> FaultInjector<E> {
+    pub(crate) fn new() -> Self {
+        FaultInjector {
+            script: None,
+            ipc_recv_corruption: [0; 2],
+            injected_faults: 0,
+        }
+    }
+
+    /// The total number of faults injected so far.
+    #[inline]
+    pub fn injected_faults(&self) -> u64 {
+        self.injected_faults
+    }
+
+    pub(crate) fn corrupt_ipc_recv(&mut self, core: Core, value: u32) -> u32 {
+        let mask = &mut self.ipc_recv_corruption[core as usize];
+        let value = value ^ *mask;
+        *mask = 0;
+        value
+    }
+}
+
+impl<E: Engine> Emu<E> {
+    pub fn inject_fault(&mut self, fault: Fault) {
+        self.fault_injector.injected_faults += 1;
+        match fault {
+            Fault::DelayDmaCompletion {
+                core,
+                channel,
+                delay,
+            } => {
+                let delays = match core {
+                    Core::Arm7 => &mut self.arm7.dma.completion_delays,
+                    Core::Arm9 => &mut self.arm9.dma.completion_delays,
+                };
+                delays[channel.get() as usize] = Some(delay);
+            }
+
+            Fault::SpuriousIrq { core, irqs } => match core {
+                Core::Arm7 => self.arm7.irqs.write_requested(
+                    arm7::IrqFlags(self.arm7.irqs.requested().0 | irqs),
+                    &mut self.arm7.schedule,
+                ),
+                Core::Arm9 => self.arm9.irqs.write_requested(
+                    arm9::IrqFlags(self.arm9.irqs.requested().0 | irqs),
+                    &mut self.arm9.schedule,
+                ),
+            },
+
+            Fault::CorruptIpcFifoEntry { core, mask } => {
+                self.fault_injector.ipc_recv_corruption[core as usize] ^= mask;
+            }
+        }
+    }
+
+    /// Sets the script hook called after every batch of emulated cycles; the script can replace
+    /// itself by calling this function, but not remove itself.
+    pub fn set_fault_script(&mut self, script: Option<Script<E>>) {
+        self.fault_injector.script = script;
+    }
+
+    #[inline]
+    pub fn fault_injector(&self) -> &FaultInjector<E> {
+        &self.fault_injector
+    }
+
+    pub(super) fn run_fault_injection(&mut self) {
+        if let Some(mut script) = self.fault_injector.script.take() {
+            script(self);
+            if self.fault_injector.script.is_none() {
+                self.fault_injector.script = Some(script);
+            }
+        }
+
+        let due = self
+            .arm7
+            .dma
+            .take_due_completions(self.arm7.schedule.cur_time().0);
+        for i in 0..4 {
+            if due & 1 << i != 0 {
+                self.arm7.complete_dma_transfer(dma::Index::new(i));
+            }
+        }
+        let due = self
+            .arm9
+            .dma
+            .take_due_completions(self.arm9.schedule.cur_time().0);
+        for i in 0..4 {
+            if due & 1 << i != 0 {
+                self.arm9.complete_dma_transfer(dma::Index::new(i));
+            }
+        }
+    }
+}
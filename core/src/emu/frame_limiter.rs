@@ -0,0 +1,230 @@
+//! Frame pacing for frontends embedding the core, limiting emulation to a target framerate.
+//!
+//! [`FramePacer`] only does the bookkeeping, working on timestamps read from any monotonic clock
+//! the embedder has access to (i.e. `performance.now()` on the web), and tells it how long to wait
+//! before the next frame; [`Limiter`] wraps it around [`std::time::Instant`] and does the waiting
+//! itself, for platforms where that's available.
+//!
+//! Frame deadlines are accumulated instead of being computed from the time each wait ended, so
+//! oversleeping on one frame is made up for on the next ones and the average framerate doesn't
+//! drift from the target; if emulation falls too far behind, though, the missed frames are dropped
+//! rather than being run as fast as possible to catch up.
+
+use core::time::Duration;
+
+/// The framerate of the DS' LCDs, ~59.8261 FPS (33.513982 MHz / 6 cycles per dot, 355 dots per
+/// scanline, 263 scanlines per frame).
+pub const NATIVE_FRAME_RATE: f64 = 33_513_982.0 / (6.0 * 355.0 * 263.0);
+
+/// The default number of frames emulation can fall behind by before the missed ones are dropped.
+pub const DEFAULT_MAX_LAG_FRAMES: u32 = 2;
+
+#[derive(Clone, Debug)]
+pub struct FramePacer {
+    frame_interval: Option<Duration>,
+    max_lag_frames: u32,
+    /// The time the next frame is due to start at, if any frame was paced since the last reset.
+    next_frame_time: Option<Duration>,
+    last_time: Duration,
+}
+
+impl FramePacer {
+    /// Creates a pacer running a frame every `frame_interval`, or as fast as possible if `None`.
+    pub fn new(frame_interval: Option<Duration>) -> Self {
+        FramePacer {
+            frame_interval,
+            max_lag_frames: DEFAULT_MAX_LAG_FRAMES,
+            next_frame_time: None,
+            last_time: Duration::ZERO,
+        }
+    }
+
+    /// Creates a pacer targeting `fps` frames per second; non-finite or non-positive values
+    /// disable limiting.
+    pub fn with_target_fps(fps: f64) -> Self {
+        Self::new(frame_interval_for_fps(fps))
+    }
+
+    #[inline]
+    pub fn frame_interval(&self) -> Option<Duration> {
+        self.frame_interval
+    }
+
+    pub fn set_frame_interval(&mut self, value: Option<Duration>) {
+        self.frame_interval = value;
+        self.next_frame_time = None;
+    }
+
+    pub fn set_target_fps(&mut self, fps: f64) {
+        self.set_frame_interval(frame_interval_for_fps(fps));
+    }
+
+    #[inline]
+    pub fn max_lag_frames(&self) -> u32 {
+        self.max_lag_frames
+    }
+
+    #[inline]
+    pub fn set_max_lag_frames(&mut self, value: u32) {
+        self.max_lag_frames = value;
+    }
+
+    /// Forgets the current frame deadline, so that the next frame is paced from the time it ends
+    /// at; should be called after emulation was paused or otherwise stopped for a while.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.next_frame_time = None;
+    }
+
+    /// Marks the end of a frame at time `now`, as read from a monotonic clock, returning how long
+    /// to wait for before starting the next one (zero if it's already due).
+    ///
+    /// If `now` is earlier than the last time passed to this function (i.e. the clock was reset),
+    /// pacing restarts from `now`.
+    pub fn end_frame(&mut self, now: Duration) -> Duration {
+        if now < self.last_time {
+            self.next_frame_time = None;
+        }
+        self.last_time = now;
+
+        let Some(frame_interval) = self.frame_interval else {
+            return Duration::ZERO;
+        };
+
+        let next_frame_time = match self.next_frame_time {
+            Some(prev_frame_time) => {
+                let next_frame_time = prev_frame_time + frame_interval;
+                if now > next_frame_time + frame_interval * self.max_lag_frames {
+                    now
+                } else {
+                    next_frame_time
+                }
+            }
+            None => now + frame_interval,
+        };
+        self.next_frame_time = Some(next_frame_time);
+        next_frame_time.saturating_sub(now)
+    }
+}
+
+fn frame_interval_for_fps(fps: f64) -> Option<Duration> {
+    (fps.is_finite() && fps > 0.0).then(|| Duration::from_secs_f64(1.0 / fps))
+}
+
+/// A [`FramePacer`] driven by [`std::time::Instant`], blocking the current thread until the next
+/// frame is due.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+#[derive(Clone, Debug)]
+pub struct Limiter {
+    pacer: FramePacer,
+    epoch: std::time::Instant,
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl Limiter {
+    /// How long before the deadline to stop sleeping and start spinning, to make up for the
+    /// coarse granularity of OS sleeps.
+    const SPIN_DURATION: Duration = Duration::from_millis(1);
+
+    pub fn new(pacer: FramePacer) -> Self {
+        Limiter {
+            pacer,
+            epoch: std::time::Instant::now(),
+        }
+    }
+
+    #[inline]
+    pub fn pacer(&self) -> &FramePacer {
+        &self.pacer
+    }
+
+    #[inline]
+    pub fn pacer_mut(&mut self) -> &mut FramePacer {
+        &mut self.pacer
+    }
+
+    /// Marks the end of a frame and blocks until the next one is due.
+    pub fn end_frame(&mut self) {
+        let wait_duration = self.pacer.end_frame(self.epoch.elapsed());
+        if wait_duration.is_zero() {
+            return;
+        }
+        let deadline = std::time::Instant::now() + wait_duration;
+        let sleep_duration = wait_duration.saturating_sub(Self::SPIN_DURATION);
+        if !sleep_duration.is_zero() {
+            std::thread::sleep(sleep_duration);
+        }
+        while std::time::Instant::now() < deadline {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(10);
+
+    fn ms(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    #[test]
+    fn first_frame_waits_full_interval() {
+        let mut pacer = FramePacer::new(Some(INTERVAL));
+        assert_eq!(pacer.end_frame(ms(5)), INTERVAL);
+    }
+
+    #[test]
+    fn drift_is_carried_over() {
+        let mut pacer = FramePacer::new(Some(INTERVAL));
+        assert_eq!(pacer.end_frame(ms(0)), ms(10));
+        // Oversleeping by 2 ms shortens the next wait
+        assert_eq!(pacer.end_frame(ms(12)), ms(8));
+        assert_eq!(pacer.end_frame(ms(20)), ms(10));
+        // Falling behind by less than the lag limit is caught up on by not waiting, keeping the
+        // original deadlines
+        assert_eq!(pacer.end_frame(ms(45)), ms(0));
+        assert_eq!(pacer.end_frame(ms(46)), ms(4));
+    }
+
+    #[test]
+    fn lag_beyond_limit_is_dropped() {
+        let mut pacer = FramePacer::new(Some(INTERVAL));
+        pacer.end_frame(ms(0));
+        // 80 ms late, more than the default 2 frames allowed
+        assert_eq!(pacer.end_frame(ms(100)), ms(0));
+        // Pacing restarts from the late frame instead of running the missed ones
+        assert_eq!(pacer.end_frame(ms(101)), ms(9));
+
+        pacer.set_max_lag_frames(10);
+        assert_eq!(pacer.end_frame(ms(190)), ms(0));
+        assert_eq!(pacer.end_frame(ms(191)), ms(0));
+    }
+
+    #[test]
+    fn clock_reset_restarts_pacing() {
+        let mut pacer = FramePacer::new(Some(INTERVAL));
+        pacer.end_frame(ms(100));
+        assert_eq!(pacer.end_frame(ms(5)), INTERVAL);
+        assert_eq!(pacer.end_frame(ms(15)), INTERVAL);
+    }
+
+    #[test]
+    fn manual_reset_restarts_pacing() {
+        let mut pacer = FramePacer::new(Some(INTERVAL));
+        pacer.end_frame(ms(0));
+        pacer.reset();
+        assert_eq!(pacer.end_frame(ms(1000)), INTERVAL);
+    }
+
+    #[test]
+    fn unlimited() {
+        let mut pacer = FramePacer::with_target_fps(0.0);
+        assert_eq!(pacer.frame_interval(), None);
+        assert_eq!(pacer.end_frame(ms(0)), Duration::ZERO);
+        pacer.set_target_fps(f64::INFINITY);
+        assert_eq!(pacer.end_frame(ms(1)), Duration::ZERO);
+    }
+}
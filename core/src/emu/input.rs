@@ -61,6 +61,16 @@ proc_bitfield::bitfield! {
     }
 }
 
+/// A change to the console's inputs, which can either be applied immediately or queued to be
+/// applied at a specific scanline using [`Emu::queue_input_change`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    PressKeys(Keys),
+    ReleaseKeys(Keys),
+    SetTouchPos([u16; 2]),
+    EndTouch,
+}
+
 #[derive(Savestate)]
 pub struct Input {
     pub(crate) status: Status,
@@ -82,6 +92,12 @@ impl Input {
         self.status
     }
 
+    /// Returns the keys currently held down, as seen by the emulated software.
+    #[inline]
+    pub fn pressed_keys(&self) -> Keys {
+        Keys::from_bits_truncate(!self.status.0)
+    }
+
     #[inline]
     pub fn arm7_key_irq_control(&self) -> KeyIrqControl {
         self.key_irq_control[0]
@@ -155,4 +171,39 @@ impl<E: cpu::Engine> Emu<E> {
         self.spi.tsc.clear_y_pos();
         self.spi.tsc.set_pen_down(false, &mut self.input.status);
     }
+
+    pub fn apply_input_change(&mut self, change: Change) {
+        match change {
+            Change::PressKeys(keys) => self.press_keys(keys),
+            Change::ReleaseKeys(keys) => self.release_keys(keys),
+            Change::SetTouchPos(pos) => self.set_touch_pos(pos),
+            Change::EndTouch => self.end_touch(),
+        }
+    }
+
+    /// Queues an input change to be applied when the given scanline of the current frame
+    /// (counting from the start of the frame, regardless of VCOUNT writes) starts, allowing input to
+    /// be latched with sub-frame precision.
+    ///
+    /// Changes need to be queued in chronological order; if the scanline has already been reached,
+    /// the change is applied immediately, and any changes still queued when the frame ends are
+    /// applied at that point.
+    pub fn queue_input_change(&mut self, scanline: u32, change: Change) {
+        if self.queued_input_changes.is_empty() && scanline <= self.gpu.cur_scanline() {
+            self.apply_input_change(change);
+        } else {
+            self.queued_input_changes.push_back((scanline, change));
+        }
+    }
+
+    #[inline]
+    pub(crate) fn apply_queued_input_changes(&mut self, flush: bool) {
+        while let Some(&(scanline, change)) = self.queued_input_changes.front() {
+            if !flush && scanline > self.gpu.cur_scanline() {
+                break;
+            }
+            self.queued_input_changes.pop_front();
+            self.apply_input_change(change);
+        }
+    }
 }
@@ -0,0 +1,301 @@
+//! An in-process harness running multiple emulated consoles in lockstep on a shared virtual clock,
+//! with their WiFi hardware connected through a loopback transport, to test local wireless
+//! features deterministically.
+//!
+//! Consoles are run one after another in slices of emulated time (quanta), in a fixed order; WiFi
+//! frames sent during a quantum are delivered to every other console at its end, so the outcome
+//! only depends on the consoles' initial state, their input and the quantum length. Frames sent
+//! through the emulated MAC's TX slots (or [`WiFi::queue_tx_frame`]) are written to the receivers'
+//! RX buffers if they have reception enabled, and dropped otherwise.
+
+use super::{Emu, RunOutput, Timestamp};
+use crate::{cpu, utils::schedule::RawTimestamp, wifi::WiFi};
+
+/// The default quantum length, in ARM7 cycles (~0.5 ms).
+pub const DEFAULT_QUANTUM: RawTimestamp = 0x4000;
+
+/// Why [`Link::run_until`] returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// All consoles reached the requested time.
+    TimeReached,
+    /// A console stopped for a reason other than the end of a quantum or of a frame; calling
+    /// [`Link::run_until`] again will resume the current quantum from the same console.
+    Stopped { console: usize, output: RunOutput },
+}
+
+pub struct Link<E: cpu::Engine> {
+    consoles: Vec<Emu<E>>,
+    /// The length of the time slices consoles are run for, in ARM7 cycles; this is also the
+    /// maximum latency of WiFi frames sent between them.
+    pub quantum: RawTimestamp,
+    cur_time: Timestamp,
+    next_console: usize,
+}
+
+impl<E: cpu::Engine> Link<E> {
+    /// Connects the given consoles, which need to be at the same point in emulated time (i.e.
+    /// freshly built).
+    ///
+    /// # Panics
+    /// Panics if the consoles' current times differ.
+    pub fn new(mut consoles: Vec<Emu<E>>, quantum: RawTimestamp) -> Self {
+        let cur_time = consoles
+            .first()
+            .map_or(Timestamp(0), |emu| emu.schedule.cur_time());
+        for emu in &mut consoles {
+            assert_eq!(
+                emu.schedule.cur_time(),
+                cur_time,
+                "linked consoles need to start at the same time"
+            );
+            emu.schedule.time_limit = cur_time;
+        }
+        Link {
+            consoles,
+            quantum: quantum.max(1),
+            cur_time,
+            next_console: 0,
+        }
+    }
+
+    #[inline]
+    pub fn consoles(&self) -> &[Emu<E>] {
+        &self.consoles
+    }
+
+    #[inline]
+    pub fn consoles_mut(&mut self) -> &mut [Emu<E>] {
+        &mut self.consoles
+    }
+
+    /// Disconnects the consoles, removing the time limit imposed on them.
+    pub fn into_consoles(mut self) -> Vec<Emu<E>> {
+        for emu in &mut self.consoles {
+            emu.schedule.time_limit = Timestamp(RawTimestamp::MAX);
+        }
+        self.consoles
+    }
+
+    /// The time all consoles have been synchronized up to.
+    #[inline]
+    pub fn cur_time(&self) -> Timestamp {
+        self.cur_time
+    }
+
+    fn exchange_frames(&mut self) {
+        for emu in &mut self.consoles {
+            WiFi::transmit(emu);
+        }
+        for sender_i in 0..self.consoles.len() {
+            let frames = self.consoles[sender_i]
+                .wifi
+                .take_tx_frames()
+                .collect::<Vec<_>>();
+            for frame in frames {
+                for (receiver_i, receiver) in self.consoles.iter_mut().enumerate() {
+                    if receiver_i != sender_i {
+                        receiver.wifi.push_rx_frame(frame.clone());
+                    }
+                }
+            }
+        }
+        for emu in &mut self.consoles {
+            WiFi::receive(emu);
+        }
+    }
+
+    /// Runs all consoles until the given time, one quantum at a time; `frame_finished` is called
+    /// with a console's index whenever it finishes a frame.
+    pub fn run_until(
+        &mut self,
+        end_time: Timestamp,
+        mut frame_finished: impl FnMut(usize, &mut Emu<E>),
+    ) -> StopReason {
+        while self.cur_time < end_time {
+            let quantum_end_time =
+                Timestamp(self.cur_time.0.saturating_add(self.quantum).min(end_time.0));
+
+            while let Some(emu) = self.consoles.get_mut(self.next_console) {
+                emu.schedule.time_limit = quantum_end_time;
+                loop {
+                    match emu.run() {
+                        RunOutput::TimeLimitReached => break,
+                        RunOutput::FrameFinished => frame_finished(self.next_console, emu),
+                        output => {
+                            return StopReason::Stopped {
+                                console: self.next_console,
+                                output,
+                            };
+                        }
+                    }
+                }
+                self.next_console += 1;
+            }
+
+            self.next_console = 0;
+            self.cur_time = quantum_end_time;
+            self.exchange_frames();
+        }
+        StopReason::TimeReached
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cpu::{arm7, bus::CpuAccess, interpreter::Interpreter},
+        emu::testing,
+        utils::mem_prelude::*,
+        wifi::Frame,
+    };
+
+    const WIFI_BASE: u32 = 0x0480_0000;
+    const RX_BUF_BEGIN: u16 = 0x4C00;
+    const RX_BUF_END: u16 = 0x5F60;
+
+    fn link(consoles: usize) -> Link<Interpreter> {
+        Link::new(
+            (0..consoles).map(|_| testing::build()).collect(),
+            DEFAULT_QUANTUM,
+        )
+    }
+
+    fn read_wifi_16(emu: &mut Emu<Interpreter>, addr: u32) -> u16 {
+        arm7::bus::read_16::<CpuAccess, _>(emu, WIFI_BASE | addr)
+    }
+
+    fn write_wifi_16(emu: &mut Emu<Interpreter>, addr: u32, value: u16) {
+        arm7::bus::write_16::<CpuAccess, _>(emu, WIFI_BASE | addr, value);
+    }
+
+    fn enable_rx(emu: &mut Emu<Interpreter>, begin: u16, end: u16) {
+        write_wifi_16(emu, 0x012, 0x0003);
+        write_wifi_16(emu, 0x050, begin);
+        write_wifi_16(emu, 0x052, end);
+        write_wifi_16(emu, 0x054, (begin & 0x1FFE) >> 1);
+        write_wifi_16(emu, 0x030, 0x8000);
+    }
+
+    fn run_quanta(link: &mut Link<Interpreter>, quanta: RawTimestamp) {
+        let end_time = Timestamp(link.cur_time().0 + quanta * link.quantum);
+        assert_eq!(link.run_until(end_time, |_, _| {}), StopReason::TimeReached);
+        assert_eq!(link.cur_time(), end_time);
+    }
+
+    fn wifi_irq_requested(emu: &Emu<Interpreter>) -> bool {
+        emu.arm7.irqs.requested().wifi()
+    }
+
+    #[test]
+    fn mac_frames_are_delivered_to_other_consoles() {
+        const DATA: [u8; 6] = [0x08, 0x01, 0xDE, 0xAD, 0xBE, 0xEF];
+
+        let mut link = link(3);
+        for emu in link.consoles_mut() {
+            enable_rx(emu, RX_BUF_BEGIN, RX_BUF_END);
+        }
+
+        // Set up a frame in the sender's LOC1 slot, at the start of its WiFi RAM
+        let sender = &mut link.consoles_mut()[0];
+        sender.wifi.ram.write_le(0x8, 0x0014_u16);
+        sender.wifi.ram.write_le(0xA, DATA.len() as u16 + 4);
+        sender.wifi.ram[0xC..0xC + DATA.len()].copy_from_slice(&DATA);
+        write_wifi_16(sender, 0x0A0, 0x8000);
+        write_wifi_16(sender, 0x0AE, 0x0001);
+        assert_eq!(read_wifi_16(sender, 0x0B0), 0x0001);
+
+        run_quanta(&mut link, 1);
+
+        // The sender's slot is marked as done, and it doesn't receive its own frame
+        let sender = &mut link.consoles_mut()[0];
+        assert_eq!(sender.wifi.ram.read_le::<u16>(0), 1);
+        assert_eq!(read_wifi_16(sender, 0x0A0), 0);
+        assert_eq!(read_wifi_16(sender, 0x010), 0x0002);
+        assert_eq!(read_wifi_16(sender, 0x054), (RX_BUF_BEGIN & 0x1FFE) >> 1);
+        assert!(wifi_irq_requested(sender));
+
+        let rx_start = (RX_BUF_BEGIN & 0x1FFE) as usize;
+        for receiver in &mut link.consoles_mut()[1..] {
+            assert_eq!(read_wifi_16(receiver, 0x010), 0x0001);
+            assert!(wifi_irq_requested(receiver));
+            assert_eq!(
+                receiver.wifi.ram.read_le::<u16>(rx_start + 8),
+                DATA.len() as u16
+            );
+            assert_eq!(
+                &receiver.wifi.ram[rx_start + 0xC..rx_start + 0xC + DATA.len()],
+                &DATA
+            );
+            // The write cursor is advanced past the header and the data, word-aligned
+            assert_eq!(read_wifi_16(receiver, 0x054), (rx_start as u16 + 0x14) >> 1);
+
+            // Writing 1 to an interrupt flag acknowledges it
+            write_wifi_16(receiver, 0x010, 0xFFFF);
+            assert_eq!(read_wifi_16(receiver, 0x010), 0);
+        }
+
+        // Nothing is sent again until the slot is re-enabled
+        run_quanta(&mut link, 1);
+        assert_eq!(read_wifi_16(&mut link.consoles_mut()[1], 0x010), 0);
+    }
+
+    #[test]
+    fn frames_are_received_in_order_around_the_rx_buffer() {
+        let mut link = link(2);
+        // A 64-byte RX buffer, fitting two 20-byte frames along with their headers
+        enable_rx(&mut link.consoles_mut()[1], 0x4100, 0x4140);
+
+        let frames = (0..3_u8)
+            .map(|i| Frame {
+                time: Timestamp(0),
+                data: vec![i; 20],
+            })
+            .collect::<Vec<_>>();
+        for frame in &frames {
+            link.consoles_mut()[0].wifi.queue_tx_frame(frame.clone());
+        }
+        // Frames are dropped by consoles that don't have reception enabled
+        run_quanta(&mut link, 1);
+
+        let receiver = &mut link.consoles_mut()[1];
+        // The third frame wraps around to overwrite the first one
+        assert_eq!(read_wifi_16(receiver, 0x054), 0x120 >> 1);
+        for (addr, frame) in [(0x100, &frames[2]), (0x120, &frames[1])] {
+            assert_eq!(receiver.wifi.ram.read_le::<u16>(addr + 8), 20);
+            assert_eq!(&receiver.wifi.ram[addr + 0xC..addr + 0x20], &frame.data[..]);
+        }
+        assert_eq!(read_wifi_16(&mut link.consoles_mut()[0], 0x010), 0);
+    }
+
+    #[test]
+    fn consoles_run_in_lockstep() {
+        // Long enough for each console to finish its first frame
+        const QUANTA: RawTimestamp = 40;
+
+        let mut frames_finished = Vec::new();
+        for _ in 0..2 {
+            let mut link = link(2);
+            let end_time = Timestamp(link.cur_time().0 + QUANTA * link.quantum);
+            let mut finished = [0; 2];
+            assert_eq!(
+                link.run_until(end_time, |i, _| finished[i] += 1),
+                StopReason::TimeReached
+            );
+            for emu in link.consoles() {
+                assert!(emu.schedule.cur_time() >= end_time);
+            }
+            assert_eq!(finished[0], finished[1]);
+            assert_ne!(finished[0], 0);
+            frames_finished.push((
+                finished,
+                link.consoles()
+                    .iter()
+                    .map(|emu| emu.schedule.cur_time())
+                    .collect::<Vec<_>>(),
+            ));
+        }
+        assert_eq!(frames_finished[0], frames_finished[1]);
+    }
+}
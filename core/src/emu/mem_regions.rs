@@ -0,0 +1,141 @@
+//! Export and import of the emulated system's memory as raw binary data, for reverse engineering
+//! or moving work between emulators.
+//!
+//! Whole regions are read from their backing storage directly, regardless of how they're currently
+//! mapped; address ranges are accessed through either CPU's bus like a debugger would, so imports
+//! go through the same paths as writes by the emulated program (and thus e.g. keep the renderers'
+//! view of VRAM up to date).
+
+use super::Emu;
+use crate::cpu::{self, arm7, arm9, bus::DebugCpuAccess};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Region {
+    MainMem,
+    SharedWram,
+    Arm7Wram,
+    Itcm,
+    Dtcm,
+    /// A VRAM bank, from 0 for bank A to 8 for bank I.
+    VramBank(u8),
+}
+
+impl Region {
+    pub const ALL: [Region; 14] = [
+        Region::MainMem,
+        Region::SharedWram,
+        Region::Arm7Wram,
+        Region::Itcm,
+        Region::Dtcm,
+        Region::VramBank(0),
+        Region::VramBank(1),
+        Region::VramBank(2),
+        Region::VramBank(3),
+        Region::VramBank(4),
+        Region::VramBank(5),
+        Region::VramBank(6),
+        Region::VramBank(7),
+        Region::VramBank(8),
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Region::MainMem => "Main RAM",
+            Region::SharedWram => "Shared WRAM",
+            Region::Arm7Wram => "ARM7 WRAM",
+            Region::Itcm => "ITCM",
+            Region::Dtcm => "DTCM",
+            Region::VramBank(i) => [
+                "VRAM bank A",
+                "VRAM bank B",
+                "VRAM bank C",
+                "VRAM bank D",
+                "VRAM bank E",
+                "VRAM bank F",
+                "VRAM bank G",
+                "VRAM bank H",
+                "VRAM bank I",
+            ][i as usize],
+        }
+    }
+
+    /// Returns a short identifier for the region, suitable for use in file names.
+    pub fn id(self) -> &'static str {
+        match self {
+            Region::MainMem => "main_ram",
+            Region::SharedWram => "shared_wram",
+            Region::Arm7Wram => "arm7_wram",
+            Region::Itcm => "itcm",
+            Region::Dtcm => "dtcm",
+            Region::VramBank(i) => [
+                "vram_a", "vram_b", "vram_c", "vram_d", "vram_e", "vram_f", "vram_g", "vram_h",
+                "vram_i",
+            ][i as usize],
+        }
+    }
+}
+
+impl<E: cpu::Engine> Emu<E> {
+    /// Returns a copy of the contents of a memory region; main RAM is 4 MiB large, or 8 MiB in
+    /// debugger mode.
+    pub fn export_mem_region(&mut self, region: Region) -> Vec<u8> {
+        match region {
+            Region::MainMem => {
+                self.main_mem.as_arr()[..=self.main_mem_mask.get() as usize].to_vec()
+            }
+            Region::SharedWram => self.swram.contents().as_arr().to_vec(),
+            Region::Arm7Wram => self.arm7.wram.as_arr().to_vec(),
+            Region::Itcm => self.arm9.cp15.itcm().as_arr().to_vec(),
+            Region::Dtcm => self.arm9.cp15.dtcm().as_arr().to_vec(),
+            Region::VramBank(i) => self.gpu.vram.bank_contents(i).to_vec(),
+        }
+    }
+
+    /// Reads `len` bytes starting at `addr` through the ARM9's or ARM7's bus.
+    pub fn export_mem_range(&mut self, arm9: bool, addr: u32, len: u32) -> Vec<u8> {
+        (0..len)
+            .map(|i| {
+                let addr = addr.wrapping_add(i);
+                if arm9 {
+                    arm9::bus::read_8::<DebugCpuAccess, E>(self, addr)
+                } else {
+                    arm7::bus::read_8::<DebugCpuAccess, E>(self, addr)
+                }
+            })
+            .collect()
+    }
+
+    /// Writes `data` starting at `addr` through the ARM9's or ARM7's bus, using the widest aligned
+    /// accesses possible (as 8-bit writes are ignored by some regions, like VRAM).
+    pub fn import_mem_range(&mut self, arm9: bool, addr: u32, data: &[u8]) {
+        let mut offset = 0;
+        while offset < data.len() {
+            let cur_addr = addr.wrapping_add(offset as u32);
+            let remaining = &data[offset..];
+            if cur_addr & 3 == 0 && remaining.len() >= 4 {
+                let value = u32::from_le_bytes(remaining[..4].try_into().unwrap());
+                if arm9 {
+                    arm9::bus::write_32::<DebugCpuAccess, E>(self, cur_addr, value);
+                } else {
+                    arm7::bus::write_32::<DebugCpuAccess, E>(self, cur_addr, value);
+                }
+                offset += 4;
+            } else if cur_addr & 1 == 0 && remaining.len() >= 2 {
+                let value = u16::from_le_bytes([remaining[0], remaining[1]]);
+                if arm9 {
+                    arm9::bus::write_16::<DebugCpuAccess, E>(self, cur_addr, value);
+                } else {
+                    arm7::bus::write_16::<DebugCpuAccess, E>(self, cur_addr, value);
+                }
+                offset += 2;
+            } else {
+                if arm9 {
+                    arm9::bus::write_8::<DebugCpuAccess, E>(self, cur_addr, remaining[0]);
+                } else {
+                    arm7::bus::write_8::<DebugCpuAccess, E>(self, cur_addr, remaining[0]);
+                }
+                offset += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! A deterministic source of noise for the parts of the hardware whose outputs vary in ways that
+//! can't be modeled exactly, like analog readings from the touchscreen controller.
+//!
+//! The generator is reseeded at the start of every frame from the seed and the frame number, so
+//! the values it produces only depend on those and on the order of accesses within the frame; as
+//! its state is also stored in savestates, runs with the same inputs stay reproducible even after
+//! loading one.
+//!
+//! Analog noise is disabled by default (see [`Builder::analog_noise`]), in which case readings are
+//! exact.
+//!
+//! [`Builder::analog_noise`]: super::Builder::analog_noise
+
+use crate::utils::Savestate;
+
+/// The seed used when none is specified.
+pub const DEFAULT_SEED: u64 = 0x6475_7374_6E6F_6973;
+
+#[derive(Clone, Savestate)]
+#[load(in_place_only)]
+pub struct Noise {
+    #[savestate(skip)]
+    analog_enabled: bool,
+    seed: u64,
+    frame: u64,
+    state: u64,
+}
+
+fn mix(mut value: u64) -> u64 {
+    // SplitMix64 finalizer
+    value = (value ^ value >> 30).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ value >> 27).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^ value >> 31
+}
+
+impl Noise {
+    pub(crate) fn new(seed: u64, analog_enabled: bool) -> Self {
+        Noise {
+            analog_enabled,
+            seed,
+            frame: 0,
+            state: mix(seed),
+        }
+    }
+
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    #[inline]
+    pub fn analog_enabled(&self) -> bool {
+        self.analog_enabled
+    }
+
+    /// Enables or disables noise in analog readings.
+    #[inline]
+    pub fn set_analog_enabled(&mut self, value: bool) {
+        self.analog_enabled = value;
+    }
+
+    /// Changes the seed, taking effect from the start of the next frame.
+    #[inline]
+    pub fn set_seed(&mut self, value: u64) {
+        self.seed = value;
+    }
+
+    /// Returns the number of frames started since the emulator was created.
+    #[inline]
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub(crate) fn start_frame(&mut self) {
+        self.frame = self.frame.wrapping_add(1);
+        self.state = mix(self.seed ^ mix(self.frame));
+    }
+
+    pub(crate) fn next_u32(&mut self) -> u32 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        (mix(self.state) >> 32) as u32
+    }
+
+    /// Returns a uniformly distributed value in `-amplitude..=amplitude` to add to an analog
+    /// reading, or 0 if analog noise is disabled.
+    pub(crate) fn next_analog_offset(&mut self, amplitude: u16) -> i32 {
+        if !self.analog_enabled {
+            return 0;
+        }
+        let range = 2 * amplitude as u64 + 1;
+        ((self.next_u32() as u64 * range) >> 32) as i32 - amplitude as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offsets(noise: &mut Noise) -> Vec<i32> {
+        (0..64).map(|_| noise.next_analog_offset(2)).collect()
+    }
+
+    #[test]
+    fn analog_noise_is_disabled_by_default() {
+        let emu = crate::emu::testing::build();
+        assert!(!emu.noise.analog_enabled());
+    }
+
+    #[test]
+    fn analog_offsets() {
+        let mut noise = Noise::new(DEFAULT_SEED, false);
+        noise.start_frame();
+        assert!(offsets(&mut noise).iter().all(|&offset| offset == 0));
+
+        noise.set_analog_enabled(true);
+        let values = offsets(&mut noise);
+        assert!(values.iter().all(|offset| (-2..=2).contains(offset)));
+        assert!(values.iter().any(|&offset| offset != 0));
+
+        // Disabled noise doesn't advance the generator, so the sequence only depends on the seed,
+        // the frame number and the number of noisy readings
+        let mut other = Noise::new(DEFAULT_SEED, true);
+        other.start_frame();
+        assert_eq!(offsets(&mut other), values);
+    }
+}
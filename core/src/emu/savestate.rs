@@ -0,0 +1,387 @@
+//! A versioned container for savestates, recording which format version and console model they
+//! were created with, so that incompatible ones can be rejected with a meaningful error instead of
+//! failing to load (or loading into a corrupted state).
+//!
+//! The layout is as follows (all integers are little-endian):
+//! - Magic (8 bytes, `DUSTSST\0`)
+//! - Format version (`u16`)
+//! - Console model (`u8`, `0xFF` if unknown)
+//! - Flags (`u8`; bit 0: whether a save file is included)
+//! - Core version string length (`u8`), followed by the UTF-8 core version string
+//! - Emulator state, save file and extra data lengths (`u32` each)
+//! - Emulator state, save file and extra data
+//!
+//! The extra data is opaque to the core, and can be used by frontends to store things like a
+//! screenshot.
+
+use crate::Model;
+use core::fmt;
+
+pub const MAGIC: [u8; 8] = *b"DUSTSST\0";
+
+// Format version history:
+// - 1: Initial version
+// - 2: Added the touchscreen and microphone noise source
+// - 3: Added NAND save memory as a DS slot SPI device, before the empty device
+// - 4: Moved vertex and polygon RAM and the polygon assembly state out of the 3D engine into its
+//      geometry stage
+// - 5: Added the DSi's NDMA controller
+
+/// The current format version; it needs to be bumped whenever the emulator state's layout changes
+/// in a way that makes older savestates unloadable, adding a migration to [`read`] if possible.
+pub const FORMAT_VERSION: u16 = 5;
+
+/// The oldest format version savestates can still be loaded from.
+pub const MIN_FORMAT_VERSION: u16 = 5;
+
+/// The format version of savestates from before versioning was introduced, which share the first
+/// versioned format's layout.
+pub const LEGACY_FORMAT_VERSION: u16 = 1;
+
+pub const CORE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const UNKNOWN_MODEL: u8 = 0xFF;
+const HAS_SAVE: u8 = 1 << 0;
+
+fn model_to_raw(model: Option<Model>) -> u8 {
+    match model {
+        Some(Model::Ds) => 0,
+        Some(Model::Lite) => 1,
+        Some(Model::Ique) => 2,
+        Some(Model::IqueLite) => 3,
+        Some(Model::Dsi) => 4,
+        None => UNKNOWN_MODEL,
+    }
+}
+
+fn model_from_raw(raw: u8) -> Option<Model> {
+    match raw {
+        0 => Some(Model::Ds),
+        1 => Some(Model::Lite),
+        2 => Some(Model::Ique),
+        3 => Some(Model::IqueLite),
+        4 => Some(Model::Dsi),
+        _ => None,
+    }
+}
+
+fn model_name(model: Model) -> &'static str {
+    match model {
+        Model::Ds => "DS",
+        Model::Lite => "DS Lite",
+        Model::Ique => "iQue DS",
+        Model::IqueLite => "iQue DS Lite",
+        Model::Dsi => "DSi",
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub format_version: u16,
+    pub core_version: String,
+    /// The console model the savestate was created with, if known.
+    pub model: Option<Model>,
+}
+
+impl Header {
+    /// Returns the header for a savestate created by this version of the core.
+    pub fn new(model: Model) -> Self {
+        Header {
+            format_version: FORMAT_VERSION,
+            core_version: CORE_VERSION.to_owned(),
+            model: Some(model),
+        }
+    }
+
+    /// Checks whether the savestate's format version is supported by this version of the core.
+    pub fn check_format_version(&self) -> Result<(), Error> {
+        if self.format_version > FORMAT_VERSION {
+            return Err(Error::TooNew {
+                format_version: self.format_version,
+                core_version: self.core_version.clone(),
+            });
+        }
+        if self.format_version < MIN_FORMAT_VERSION {
+            return Err(Error::TooOld {
+                format_version: self.format_version,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks whether the savestate can be loaded into an emulator instance emulating the given
+    /// model.
+    pub fn check_compatible(&self, model: Model) -> Result<(), Error> {
+        match self.model {
+            Some(savestate_model) if savestate_model != model => Err(Error::ModelMismatch {
+                savestate: savestate_model,
+                current: model,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub struct Contents<'a> {
+    pub header: Header,
+    pub state: &'a [u8],
+    pub save: Option<&'a [u8]>,
+    pub extra: &'a [u8],
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    InvalidMagic,
+    Truncated,
+    TooOld {
+        format_version: u16,
+    },
+    TooNew {
+        format_version: u16,
+        core_version: String,
+    },
+    ModelMismatch {
+        savestate: Model,
+        current: Model,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidMagic => f.write_str("not a savestate file"),
+            Error::Truncated => f.write_str("the savestate file is truncated"),
+            Error::TooOld { format_version } => write!(
+                f,
+                "the savestate uses format version {format_version}, which is no longer supported \
+                 (the oldest supported one is {MIN_FORMAT_VERSION})"
+            ),
+            Error::TooNew {
+                format_version,
+                core_version,
+            } => write!(
+                f,
+                "the savestate was created by a newer version of the emulator ({core_version}, \
+                 format version {format_version}); update the emulator to load it"
+            ),
+            Error::ModelMismatch { savestate, current } => write!(
+                f,
+                "the savestate was created while emulating a {}, but a {} is being emulated",
+                model_name(*savestate),
+                model_name(*current),
+            ),
+        }
+    }
+}
+
+/// Returns whether the given data starts with a savestate container header, as opposed to being
+/// from before savestates were versioned.
+pub fn is_container(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC)
+}
+
+/// Creates a savestate container with the given contents.
+pub fn write(header: &Header, state: &[u8], save: Option<&[u8]>, extra: &[u8]) -> Vec<u8> {
+    let core_version = &header.core_version.as_bytes()[..header.core_version.len().min(0xFF)];
+    let save_len = save.map_or(0, <[u8]>::len);
+    let mut result = Vec::with_capacity(
+        MAGIC.len() + 17 + core_version.len() + state.len() + save_len + extra.len(),
+    );
+    result.extend_from_slice(&MAGIC);
+    result.extend_from_slice(&header.format_version.to_le_bytes());
+    result.push(model_to_raw(header.model));
+    result.push(if save.is_some() { HAS_SAVE } else { 0 });
+    result.push(core_version.len() as u8);
+    result.extend_from_slice(core_version);
+    for len in [state.len(), save_len, extra.len()] {
+        result.extend_from_slice(&(len as u32).to_le_bytes());
+    }
+    result.extend_from_slice(state);
+    if let Some(save) = save {
+        result.extend_from_slice(save);
+    }
+    result.extend_from_slice(extra);
+    result
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.data.len() < len {
+            return Err(Error::Truncated);
+        }
+        let (result, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(result)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+}
+
+/// Parses a savestate container, rejecting ones whose format version isn't supported.
+pub fn read(data: &[u8]) -> Result<Contents<'_>, Error> {
+    if !is_container(data) {
+        return Err(Error::InvalidMagic);
+    }
+    let mut reader = Reader {
+        data: &data[MAGIC.len()..],
+    };
+
+    let format_version = reader.u16()?;
+    let model = model_from_raw(reader.u8()?);
+    let flags = reader.u8()?;
+    let core_version_len = reader.u8()? as usize;
+    let core_version = String::from_utf8_lossy(reader.bytes(core_version_len)?).into_owned();
+    let header = Header {
+        format_version,
+        core_version,
+        model,
+    };
+
+    header.check_format_version()?;
+    // NOTE: Migrations from older supported format versions should be applied here, once there are
+    //       any; the header always keeps the layout above.
+
+    let state_len = reader.u32()? as usize;
+    let save_len = reader.u32()? as usize;
+    let extra_len = reader.u32()? as usize;
+    let state = reader.bytes(state_len)?;
+    let save = reader.bytes(save_len)?;
+    let extra = reader.bytes(extra_len)?;
+
+    Ok(Contents {
+        header,
+        state,
+        save: (flags & HAS_SAVE != 0).then_some(save),
+        extra,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> Header {
+        Header::new(Model::Lite)
+    }
+
+    fn container() -> Vec<u8> {
+        write(&header(), b"state", Some(b"save data"), b"extra")
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = container();
+        let contents = read(&data).unwrap();
+        assert_eq!(contents.header, header());
+        assert_eq!(contents.state, b"state");
+        assert_eq!(contents.save, Some(&b"save data"[..]));
+        assert_eq!(contents.extra, b"extra");
+
+        let data = write(&header(), b"state", None, &[]);
+        let contents = read(&data).unwrap();
+        assert_eq!(contents.state, b"state");
+        assert_eq!(contents.save, None);
+        assert_eq!(contents.extra, b"");
+    }
+
+    #[test]
+    fn unknown_model_round_trip() {
+        let header = Header {
+            model: None,
+            ..header()
+        };
+        let data = write(&header, b"", None, b"");
+        assert_eq!(read(&data).unwrap().header, header);
+    }
+
+    #[test]
+    fn long_core_version_is_truncated() {
+        let header = Header {
+            core_version: "1".repeat(300),
+            ..header()
+        };
+        let data = write(&header, b"state", None, b"");
+        let contents = read(&data).unwrap();
+        assert_eq!(contents.header.core_version, "1".repeat(0xFF));
+        assert_eq!(contents.state, b"state");
+    }
+
+    #[test]
+    fn truncated() {
+        let data = container();
+        for len in MAGIC.len()..data.len() {
+            assert_eq!(
+                read(&data[..len]).err(),
+                Some(Error::Truncated),
+                "length {len}"
+            );
+        }
+    }
+
+    #[test]
+    fn bad_magic() {
+        let mut data = container();
+        data[0] ^= 1;
+        assert!(!is_container(&data));
+        assert_eq!(read(&data).err(), Some(Error::InvalidMagic));
+        assert_eq!(read(&MAGIC[..4]).err(), Some(Error::InvalidMagic));
+        assert_eq!(read(&[]).err(), Some(Error::InvalidMagic));
+    }
+
+    #[test]
+    fn unsupported_versions() {
+        for format_version in [LEGACY_FORMAT_VERSION, MIN_FORMAT_VERSION - 1] {
+            let header = Header {
+                format_version,
+                ..header()
+            };
+            let data = write(&header, b"state", None, b"");
+            assert_eq!(read(&data).err(), Some(Error::TooOld { format_version }));
+        }
+
+        let header = Header {
+            format_version: FORMAT_VERSION + 1,
+            core_version: "99.0.0".to_owned(),
+            ..header()
+        };
+        let data = write(&header, b"state", None, b"");
+        assert_eq!(
+            read(&data).err(),
+            Some(Error::TooNew {
+                format_version: FORMAT_VERSION + 1,
+                core_version: "99.0.0".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn model_compatibility() {
+        assert_eq!(header().check_compatible(Model::Lite), Ok(()));
+        assert_eq!(
+            header().check_compatible(Model::Dsi),
+            Err(Error::ModelMismatch {
+                savestate: Model::Lite,
+                current: Model::Dsi,
+            })
+        );
+        let header = Header {
+            model: None,
+            ..header()
+        };
+        assert_eq!(header.check_compatible(Model::Dsi), Ok(()));
+    }
+}
@@ -13,6 +13,7 @@ pub enum Event {
     #[default]
     Shutdown, // Max 1
     Engine3dCommandFinished, // Max 1
+    RtcTick,         // Max 1
 }
 
 def_event_slots! {
@@ -21,6 +22,7 @@ def_event_slots! {
     GPU,
     SHUTDOWN,
     ENGINE_3D,
+    RTC,
 }
 
 def_event_slot_index!(bounded_esi, event_slots, pub struct EventSlotIndex(u8));
@@ -34,6 +36,13 @@ pub struct Schedule {
     cur_time: Timestamp,
     #[savestate(skip)]
     pub batch_cycles: Timestamp,
+    /// The time emulation will stop at, returning [`RunOutput::TimeLimitReached`]; emulated time
+    /// will never advance past it.
+    ///
+    /// [`RunOutput::TimeLimitReached`]: super::RunOutput::TimeLimitReached
+    #[cfg(feature = "virtual-time")]
+    #[savestate(skip)]
+    pub time_limit: Timestamp,
     schedule: RawSchedule,
 }
 
@@ -42,6 +51,8 @@ impl Schedule {
         Schedule {
             cur_time: Timestamp(0),
             batch_cycles,
+            #[cfg(feature = "virtual-time")]
+            time_limit: Timestamp(schedule::RawTimestamp::MAX),
             schedule: schedule::Schedule::new(),
         }
     }
@@ -62,10 +73,15 @@ impl Schedule {
     }
 
     #[inline]
+    #[allow(clippy::let_and_return)]
     pub(super) fn batch_end_time(&self) -> Timestamp {
-        self.schedule
+        let end_time = self
+            .schedule
             .next_event_time()
-            .min(self.cur_time + self.batch_cycles)
+            .min(self.cur_time + self.batch_cycles);
+        #[cfg(feature = "virtual-time")]
+        let end_time = end_time.min(self.time_limit);
+        end_time
     }
 
     #[inline]
@@ -0,0 +1,127 @@
+//! Minimal emulator instances for unit tests, running without any ROM, system files or renderers.
+
+use super::{Builder, Emu};
+use crate::{
+    audio,
+    cpu::interpreter::Interpreter,
+    ds_slot,
+    flash::Flash,
+    gpu::{
+        engine_2d::{self, Engine2d, EngineA, EngineB},
+        engine_3d::{self, GxSnapshot, RenderingState},
+        vram::Vram,
+        Framebuffer,
+    },
+    rtc,
+    spi::firmware,
+    utils::{zeroed_box, Bytes},
+    Model, SaveContents,
+};
+use std::sync::Arc;
+
+struct Renderer2d(Box<Framebuffer>);
+
+impl engine_2d::Renderer for Renderer2d {
+    fn uses_bg_obj_vram_tracking(&self) -> bool {
+        false
+    }
+
+    fn uses_lcdc_vram_tracking(&self) -> bool {
+        false
+    }
+
+    fn framebuffer(&self) -> &Framebuffer {
+        &self.0
+    }
+
+    fn start_prerendering_objs(
+        &mut self,
+        _engines: (&mut Engine2d<EngineA>, &mut Engine2d<EngineB>),
+        _vram: &mut Vram,
+    ) {
+    }
+
+    fn start_scanline(
+        &mut self,
+        _line: u8,
+        _vcount: u8,
+        _engines: (&mut Engine2d<EngineA>, &mut Engine2d<EngineB>),
+        _vram: &mut Vram,
+    ) {
+    }
+
+    fn finish_scanline(
+        &mut self,
+        _line: u8,
+        _vcount: u8,
+        _engines: (&mut Engine2d<EngineA>, &mut Engine2d<EngineB>),
+        _vram: &mut Vram,
+    ) {
+    }
+}
+
+struct Renderer3dTx;
+
+impl engine_3d::RendererTx for Renderer3dTx {
+    fn set_capture_enabled(&mut self, _capture_enabled: bool) {}
+
+    fn swap_buffers(&mut self, _gx: &Arc<GxSnapshot>, _state: &RenderingState) {}
+
+    fn repeat_last_frame(&mut self, _state: &RenderingState) {}
+
+    fn start_rendering(
+        &mut self,
+        _texture: &Bytes<0x8_0000>,
+        _tex_pal: &Bytes<0x1_8000>,
+        _state: &RenderingState,
+    ) {
+    }
+
+    fn skip_rendering(&mut self) {}
+}
+
+#[cfg(feature = "log")]
+fn logger() -> slog::Logger {
+    slog::Logger::root(slog::Discard, slog::o!())
+}
+
+/// Returns a builder for a DS with the default firmware, no DS or GBA slot cartridges and all-zero
+/// BIOS files, so that nothing runs until the test sets up the CPUs' state.
+pub fn builder() -> Builder {
+    let mut builder = Builder::new(
+        Flash::new(
+            SaveContents::Existing(firmware::default(Model::Ds)),
+            firmware::id_for_model(Model::Ds),
+            #[cfg(feature = "log")]
+            logger(),
+        )
+        .expect("couldn't create firmware flash"),
+        None,
+        ds_slot::spi::Empty::new(
+            #[cfg(feature = "log")]
+            logger(),
+        )
+        .into(),
+        Box::new(audio::DummyBackend),
+        None,
+        Box::new(rtc::DummyBackend),
+        Box::new(Renderer2d(zeroed_box())),
+        Box::new(Renderer3dTx),
+        None,
+        #[cfg(feature = "log")]
+        logger(),
+    );
+    builder.arm7_bios = Some(zeroed_box());
+    builder.arm9_bios = Some(zeroed_box());
+    builder
+}
+
+pub fn build_from(builder: Builder) -> Emu<Interpreter> {
+    builder
+        .build(Interpreter)
+        .unwrap_or_else(|err| panic!("couldn't build emulator: {err}"))
+}
+
+pub fn build() -> Emu<Interpreter> {
+    build_from(builder())
+}
@@ -2,6 +2,7 @@ use crate::{
     utils::{mem_prelude::*, zeroed_box, Savestate},
     SaveContents,
 };
+use core::fmt;
 
 proc_bitfield::bitfield! {
     #[derive(Clone, Copy, PartialEq, Eq, Savestate)]
@@ -42,9 +43,24 @@ pub struct Flash {
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CreationError {
-    InvalidSize,
+    /// The contents' size, given in bytes, is either not a power of two or smaller than
+    /// 128 KiB.
+    InvalidSize(usize),
 }
 
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CreationError::InvalidSize(len) => write!(
+                f,
+                "invalid FLASH size: {len} B (expected a power of two, at least 128 KiB)"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for CreationError {}
+
 impl Flash {
     pub fn new(
         contents: SaveContents,
@@ -52,7 +68,7 @@ impl Flash {
         #[cfg(feature = "log")] logger: slog::Logger,
     ) -> Result<Self, CreationError> {
         if !contents.len().is_power_of_two() || contents.len() < 0x2_0000 {
-            return Err(CreationError::InvalidSize);
+            return Err(CreationError::InvalidSize(contents.len()));
         }
         let contents_len_mask = (contents.len() - 1) as u32;
         Ok(Flash {
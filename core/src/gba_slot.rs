@@ -0,0 +1,303 @@
+//! GBA slot (slot-2) cartridge passthrough.
+//!
+//! Only as much of a GBA cartridge is emulated as DS games need to detect and read one: the ROM
+//! image (usually just enough of it to cover the header), its battery-backed SRAM and the solar
+//! sensor some carts have. Flash and EEPROM saves aren't supported, as no DS game is known to
+//! write to them, and games that only check a cart's header don't read them either.
+
+use crate::{emu::LocalExMemControl, utils::BoxedByteSlice};
+use core::fmt;
+
+/// The maximum size of a GBA ROM image, mapped at `0x0800_0000`.
+pub const MAX_ROM_SIZE: usize = 0x200_0000;
+/// The maximum size of a GBA cart's SRAM, mapped at `0x0A00_0000`.
+pub const MAX_SRAM_SIZE: usize = 0x1_0000;
+
+const GPIO_DATA_ADDR: u32 = 0xC4;
+const GPIO_DIRECTION_ADDR: u32 = 0xC6;
+const GPIO_CONTROL_ADDR: u32 = 0xC8;
+
+/// The light sensor of the Boktai carts, connected through the cart's GPIO pins: bit 0 is the
+/// clock, bit 1 resets the counter, bit 2 is the (active low) chip select and bit 3 is set once
+/// the counter has reached a value depending on the light level.
+#[derive(Clone, Debug)]
+pub struct SolarSensor {
+    light_level: u8,
+    counter: u8,
+    threshold: u8,
+    prev_clock: bool,
+}
+
+impl SolarSensor {
+    fn new() -> Self {
+        SolarSensor {
+            light_level: 0,
+            counter: 0,
+            threshold: Self::threshold(0),
+            prev_clock: false,
+        }
+    }
+
+    /// Returns the current light level, from 0 (darkest) to 255 (brightest).
+    #[inline]
+    pub fn light_level(&self) -> u8 {
+        self.light_level
+    }
+
+    /// Sets the light level, from 0 (darkest) to 255 (brightest); it's sampled when the game next
+    /// resets the sensor's counter.
+    #[inline]
+    pub fn set_light_level(&mut self, value: u8) {
+        self.light_level = value;
+    }
+
+    fn threshold(light_level: u8) -> u8 {
+        // The carts' ADC counter reaches the sensor's voltage after ~0xE8 clocks in complete
+        // darkness, and after ~0x50 in direct sunlight
+        (0xE8 - (light_level as u16 * (0xE8 - 0x50) / 0xFF)) as u8
+    }
+
+    fn write_pins(&mut self, pins: u8) -> u8 {
+        if pins & 4 != 0 {
+            return 0;
+        }
+        if pins & 2 != 0 {
+            self.counter = 0;
+            self.threshold = Self::threshold(self.light_level);
+        }
+        let clock = pins & 1 != 0;
+        if clock && !self.prev_clock {
+            self.counter = self.counter.saturating_add(1);
+        }
+        self.prev_clock = clock;
+        ((self.counter >= self.threshold) as u8) << 3
+    }
+}
+
+/// A GBA cartridge inserted in the GBA slot.
+#[derive(Clone)]
+pub struct Cart {
+    rom: BoxedByteSlice,
+    sram: Option<BoxedByteSlice>,
+    sram_mask: u32,
+    sram_dirty: bool,
+    solar_sensor: Option<SolarSensor>,
+    gpio_data: u8,
+    gpio_input: u8,
+    gpio_direction: u8,
+    gpio_readable: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CreationError {
+    InvalidRomSize,
+    InvalidSramSize,
+}
+
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CreationError::InvalidRomSize => {
+                f.write_str("the GBA ROM is either too small to contain a header or over 32 MiB")
+            }
+            CreationError::InvalidSramSize => {
+                f.write_str("the GBA cart SRAM size is either not a power of two or over 64 KiB")
+            }
+        }
+    }
+}
+
+impl core::error::Error for CreationError {}
+
+impl Cart {
+    /// Creates a cart from a (possibly partial) GBA ROM image and its SRAM contents, if it has
+    /// any; reads past the end of the ROM image return open bus values, as on an empty slot.
+    ///
+    /// Carts known to have a solar sensor are detected from the game code in their header.
+    pub fn new(rom: BoxedByteSlice, sram: Option<BoxedByteSlice>) -> Result<Self, CreationError> {
+        if rom.len() < 0xC0 || rom.len() > MAX_ROM_SIZE {
+            return Err(CreationError::InvalidRomSize);
+        }
+        let sram_len = sram.as_ref().map_or(0, |sram| sram.len());
+        if sram.is_some() && (!sram_len.is_power_of_two() || sram_len > MAX_SRAM_SIZE) {
+            return Err(CreationError::InvalidSramSize);
+        }
+        let sram_mask = (sram_len as u32).wrapping_sub(1);
+        let solar_sensor =
+            matches!(&rom[0xAC..0xAF], b"U3I" | b"U32" | b"U33").then(SolarSensor::new);
+        Ok(Cart {
+            rom,
+            sram,
+            sram_mask,
+            sram_dirty: false,
+            solar_sensor,
+            gpio_data: 0,
+            gpio_input: 0,
+            gpio_direction: 0,
+            gpio_readable: false,
+        })
+    }
+
+    /// Returns the 4-character game code from the cart's header.
+    #[inline]
+    pub fn game_code(&self) -> [u8; 4] {
+        self.rom[0xAC..0xB0].try_into().unwrap()
+    }
+
+    #[inline]
+    pub fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    #[inline]
+    pub fn sram(&self) -> Option<&[u8]> {
+        self.sram.as_deref()
+    }
+
+    #[inline]
+    pub fn sram_dirty(&self) -> bool {
+        self.sram_dirty
+    }
+
+    #[inline]
+    pub fn mark_sram_flushed(&mut self) {
+        self.sram_dirty = false;
+    }
+
+    #[inline]
+    pub fn solar_sensor(&self) -> Option<&SolarSensor> {
+        self.solar_sensor.as_ref()
+    }
+
+    #[inline]
+    pub fn solar_sensor_mut(&mut self) -> Option<&mut SolarSensor> {
+        self.solar_sensor.as_mut()
+    }
+
+    fn has_gpio(&self) -> bool {
+        self.solar_sensor.is_some()
+    }
+
+    fn read_rom_halfword(&self, addr: u32) -> Option<u16> {
+        let offset = addr & 0x1FF_FFFE;
+        if self.gpio_readable && self.has_gpio() {
+            match offset {
+                GPIO_DATA_ADDR => {
+                    return Some(
+                        ((self.gpio_data & self.gpio_direction)
+                            | (self.gpio_input & !self.gpio_direction))
+                            as u16,
+                    )
+                }
+                GPIO_DIRECTION_ADDR => return Some(self.gpio_direction as u16),
+                GPIO_CONTROL_ADDR => return Some(self.gpio_readable as u16),
+                _ => {}
+            }
+        }
+        let offset = offset as usize;
+        self.rom
+            .get(offset..offset + 2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn write_rom_halfword(&mut self, addr: u32, value: u16) {
+        if !self.has_gpio() {
+            return;
+        }
+        match addr & 0x1FF_FFFE {
+            GPIO_DATA_ADDR => {
+                self.gpio_data = value as u8 & 0xF;
+                let pins = self.gpio_data & self.gpio_direction;
+                if let Some(solar_sensor) = &mut self.solar_sensor {
+                    self.gpio_input = solar_sensor.write_pins(pins);
+                }
+            }
+            GPIO_DIRECTION_ADDR => self.gpio_direction = value as u8 & 0xF,
+            GPIO_CONTROL_ADDR => self.gpio_readable = value & 1 != 0,
+            _ => {}
+        }
+    }
+
+    fn read_sram(&self, addr: u32) -> Option<u8> {
+        self.sram
+            .as_ref()
+            .map(|sram| sram[(addr & self.sram_mask) as usize])
+    }
+
+    fn write_sram(&mut self, addr: u32, value: u8) {
+        if let Some(sram) = &mut self.sram {
+            sram[(addr & self.sram_mask) as usize] = value;
+            self.sram_dirty = true;
+        }
+    }
+}
+
+/// The GBA slot, possibly containing a [`Cart`]; accessories aren't emulated yet, so an empty
+/// slot returns open bus values.
+pub struct GbaSlot {
+    cart: Option<Cart>,
+}
+
+impl GbaSlot {
+    pub(crate) fn new(cart: Option<Cart>) -> Self {
+        GbaSlot { cart }
+    }
+
+    #[inline]
+    pub fn cart(&self) -> Option<&Cart> {
+        self.cart.as_ref()
+    }
+
+    #[inline]
+    pub fn cart_mut(&mut self) -> Option<&mut Cart> {
+        self.cart.as_mut()
+    }
+
+    /// Inserts a cart, returning the previously inserted one, if any.
+    #[inline]
+    pub fn insert(&mut self, cart: Cart) -> Option<Cart> {
+        self.cart.replace(cart)
+    }
+
+    #[inline]
+    pub fn eject(&mut self) -> Option<Cart> {
+        self.cart.take()
+    }
+
+    pub(crate) fn read_rom_halfword(&self, addr: u32, ex_mem_control: LocalExMemControl) -> u16 {
+        self.cart
+            .as_ref()
+            .and_then(|cart| cart.read_rom_halfword(addr))
+            .unwrap_or_else(|| ex_mem_control.gba_rom_halfword(addr))
+    }
+
+    pub(crate) fn read_rom_word(&self, addr: u32, ex_mem_control: LocalExMemControl) -> u32 {
+        if self.cart.is_none() {
+            return ex_mem_control.gba_rom_word(addr);
+        }
+        let addr = addr & !3;
+        self.read_rom_halfword(addr, ex_mem_control) as u32
+            | (self.read_rom_halfword(addr | 2, ex_mem_control) as u32) << 16
+    }
+
+    pub(crate) fn write_rom_halfword(&mut self, addr: u32, value: u16) {
+        if let Some(cart) = &mut self.cart {
+            cart.write_rom_halfword(addr, value);
+        }
+    }
+
+    /// Reads a byte from the SRAM region; its data bus is 8 bits wide, so wider reads return the
+    /// same byte repeated.
+    pub(crate) fn read_sram(&self, addr: u32) -> u8 {
+        self.cart
+            .as_ref()
+            .and_then(|cart| cart.read_sram(addr))
+            .unwrap_or(0xFF)
+    }
+
+    pub(crate) fn write_sram(&mut self, addr: u32, value: u8) {
+        if let Some(cart) = &mut self.cart {
+            cart.write_sram(addr, value);
+        }
+    }
+}
@@ -5,6 +5,7 @@ pub mod vram;
 use crate::{
     cpu::{arm7, arm9, Engine},
     emu::{self, event_slots, Emu, Timestamp},
+    ndma::Ndma,
     utils::{schedule::RawTimestamp, Savestate},
 };
 use engine_2d::Engine2d;
@@ -30,6 +31,14 @@ proc_bitfield::bitfield! {
     }
 }
 
+/// Identifies one of the two 2D engines at runtime, i.e. when reporting which screen each one is
+/// displayed on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Engine2dId {
+    A,
+    B,
+}
+
 proc_bitfield::bitfield! {
     #[derive(Clone, Copy, PartialEq, Eq, Savestate)]
     pub struct DispStatus(pub u16): Debug {
@@ -88,6 +97,7 @@ impl Gpu {
     pub(crate) fn new(
         renderer_2d: Box<dyn engine_2d::Renderer>,
         renderer_3d_tx: Box<dyn engine_3d::RendererTx>,
+        threaded_3d_geometry: bool,
         schedule: &mut arm9::Schedule,
         emu_schedule: &mut emu::Schedule,
         #[cfg(feature = "log")] logger: &slog::Logger,
@@ -116,6 +126,7 @@ impl Gpu {
             ),
             engine_3d: Engine3d::new(
                 renderer_3d_tx,
+                threaded_3d_geometry,
                 schedule,
                 emu_schedule,
                 #[cfg(feature = "log")]
@@ -166,6 +177,18 @@ impl Gpu {
         self.power_control
     }
 
+    /// Returns the 2D engines whose output is currently sent to the upper and lower screens, in
+    /// that order, as selected by POWCNT1's display swap bit; the touchscreen is always the lower
+    /// one.
+    #[inline]
+    pub fn screen_engines(&self) -> [Engine2dId; 2] {
+        if self.power_control.swap_screens() {
+            [Engine2dId::A, Engine2dId::B]
+        } else {
+            [Engine2dId::B, Engine2dId::A]
+        }
+    }
+
     #[inline]
     pub fn write_power_control(&mut self, value: PowerControl) {
         // TODO: What to do with bit 0? The current handling code is just a guess
@@ -248,6 +271,14 @@ impl Gpu {
         self.vcount_compare_9 = value.vcount_compare();
     }
 
+    /// Writes a color to texture palette memory for debugging purposes (see
+    /// [`Vram::write_tex_pal`]), invalidating the 3D renderer's copy of the written slot.
+    pub fn write_tex_pal(&mut self, addr: u32, value: u16) {
+        self.vram.write_tex_pal(addr, value);
+        self.engine_3d
+            .set_tex_pal_dirty(1 << (addr as usize % 0x1_8000 >> 14));
+    }
+
     pub(crate) fn end_hdraw(emu: &mut Emu<impl Engine>, time: Timestamp) {
         if emu.gpu.power_control.display_enabled() {
             emu.gpu.disp_status_7.set_hblank(true);
@@ -306,6 +337,7 @@ impl Gpu {
         emu.gpu.next_vcount = None;
 
         if emu.gpu.vcount == TOTAL_SCANLINES as u16 {
+            emu.apply_queued_input_changes(true);
             emu.gpu.vcount = 0;
             emu.gpu.cur_scanline = 0;
             emu.gpu.engine_2d_a.end_vblank();
@@ -319,6 +351,8 @@ impl Gpu {
         emu.gpu.engine_2d_a.update_windows(emu.gpu.vcount as u8);
         emu.gpu.engine_2d_b.update_windows(emu.gpu.vcount as u8);
 
+        emu.apply_queued_input_changes(false);
+
         if emu.gpu.power_control.display_enabled() {
             emu.gpu.disp_status_7.set_hblank(false);
             emu.gpu.disp_status_9.set_hblank(false);
@@ -379,6 +413,9 @@ impl Gpu {
                 emu.arm9
                     .start_dma_transfers_with_timing::<{ arm9::dma::Timing::VBlank }>();
             }
+            if emu.ndma.is_enabled() {
+                Ndma::run_camera_transfers(emu);
+            }
         } else if emu.gpu.vcount == (TOTAL_SCANLINES - 48) as u16 {
             emu.gpu.engine_3d.start_rendering(&emu.gpu.vram);
         } else if emu.gpu.vcount == (TOTAL_SCANLINES - 1) as u16
@@ -3,7 +3,7 @@ mod renderer;
 pub use renderer::Renderer;
 
 use crate::utils::{LoadableInPlace, Savestate, Storable};
-use core::marker::PhantomData;
+use core::{marker::PhantomData, ops::Range};
 
 pub trait Role: LoadableInPlace + Storable + Send + Sync + 'static {
     const IS_A: bool;
@@ -295,9 +295,33 @@ proc_bitfield::bitfield! {
     pub struct WindowsActive(pub u8): Debug {
         pub win0: bool @ 0,
         pub win1: bool @ 1,
+        // Whether each window's horizontal range is still active at the start of the line, which
+        // happens when its X1 coordinate is greater than X2 and it was vertically active in a
+        // previous line, making it wrap around to the left edge of the screen
+        pub win0_x: bool @ 2,
+        pub win1_x: bool @ 3,
     }
 }
 
+/// Returns the horizontal spans of pixels covered by a window on the current line.
+///
+/// The window is activated when reaching its X1 coordinate and deactivated when reaching X2 (which
+/// takes priority), so if X1 is greater than X2 it extends to the right edge of the screen and,
+/// if `active_at_line_start` is set, starts from the left edge too.
+pub fn window_x_spans(x_range: (u8, u8), active_at_line_start: bool) -> [Range<usize>; 2] {
+    let (x_start, x_end) = (x_range.0 as usize, x_range.1 as usize);
+    [
+        0..if active_at_line_start { x_end } else { 0 },
+        x_start..if x_end > x_start {
+            x_end
+        } else if x_end < x_start {
+            256
+        } else {
+            x_start
+        },
+    ]
+}
+
 #[derive(Savestate)]
 pub struct Engine2d<R: Role> {
     #[cfg(feature = "log")]
@@ -317,6 +341,7 @@ pub struct Engine2d<R: Role> {
     pub window_x_ranges: [(u8, u8); 2],
     pub window_y_ranges: [(u8, u8); 2],
     windows_active: WindowsActive,
+    windows_y_latched: u8,
     window_control: [WindowControl; 4],
     color_effects_control: ColorEffectsControl,
     blend_coeffs_raw: BlendCoeffsRaw,
@@ -325,6 +350,8 @@ pub struct Engine2d<R: Role> {
     capture_control: CaptureControl,
     capture_enabled_in_frame: bool,
     capture_height: u8,
+    #[savestate(skip)]
+    forced_disabled_layers: u8,
 }
 
 impl<R: Role> Engine2d<R> {
@@ -357,6 +384,7 @@ impl<R: Role> Engine2d<R> {
             window_x_ranges: [(0, 0), (0, 0)],
             window_y_ranges: [(0, 0), (0, 0)],
             windows_active: WindowsActive(0),
+            windows_y_latched: 0,
             window_control: [
                 WindowControl(0),
                 WindowControl(0),
@@ -370,6 +398,7 @@ impl<R: Role> Engine2d<R> {
             capture_control: CaptureControl(0),
             capture_enabled_in_frame: false,
             capture_height: 128,
+            forced_disabled_layers: 0,
         }
     }
 
@@ -411,6 +440,40 @@ impl<R: Role> Engine2d<R> {
         }
     }
 
+    /// Returns the mask of layers renderers are forced to hide regardless of DISPCNT, for debugging;
+    /// bits 0-3 correspond to BG0-3 and bit 4 to OBJs.
+    #[inline]
+    pub fn forced_disabled_layers(&self) -> u8 {
+        self.forced_disabled_layers
+    }
+
+    #[inline]
+    pub fn set_forced_disabled_layers(&mut self, value: u8) {
+        self.forced_disabled_layers = value & 0x1F;
+    }
+
+    /// Returns the priority renderers should draw the given BG with, or 4 if it's either disabled
+    /// or forced to be hidden.
+    #[inline]
+    pub fn bg_render_priority(&self, i: BgIndex) -> u8 {
+        if self.forced_disabled_layers & 1 << i.get() != 0 {
+            4
+        } else {
+            self.bgs[i.get() as usize].priority
+        }
+    }
+
+    /// Returns the value of DISPCNT as seen by renderers, with OBJs disabled if they're forced to
+    /// be hidden.
+    #[inline]
+    pub fn render_control(&self) -> Control {
+        if self.forced_disabled_layers & 1 << 4 != 0 {
+            self.control.with_objs_enabled(false)
+        } else {
+            self.control
+        }
+    }
+
     #[inline]
     pub fn master_brightness_control(&self) -> BrightnessControl {
         self.master_brightness_control
@@ -539,27 +602,135 @@ impl<R: Role> Engine2d<R> {
         }
     }
 
+    // Called for every line including the VBlank ones, with VCOUNT truncated to 8 bits; both the
+    // horizontal and the vertical latches persist across lines (and frames), so windows with
+    // X1 > X2 or Y1 > Y2 wrap around the screen edges.
     pub(super) fn update_windows(&mut self, vcount: u8) {
         for i in 0..2 {
             let mask = 1 << i;
-
-            if self.control.win01_enabled() & mask == 0 {
-                self.windows_active.0 &= !mask;
-                continue;
+            let x_mask = 4 << i;
+
+            // The horizontal latch only gets updated on lines the window was active on; since X2
+            // takes priority, it ends up set at the end of the line only if X1 > X2
+            if self.windows_active.0 & mask != 0 {
+                let x_range = &self.window_x_ranges[i];
+                if x_range.0 > x_range.1 {
+                    self.windows_active.0 |= x_mask;
+                } else {
+                    self.windows_active.0 &= !x_mask;
+                }
             }
 
             let y_range = &self.window_y_ranges[i];
-            let y_start = y_range.0;
-            let mut y_end = y_range.1;
-            if y_end < y_start {
-                y_end = 192;
+            if vcount == y_range.1 {
+                self.windows_y_latched &= !mask;
+            } else if vcount == y_range.0 {
+                self.windows_y_latched |= mask;
             }
-            if vcount == y_start {
+
+            if self.control.win01_enabled() & mask != 0 && self.windows_y_latched & mask != 0 {
                 self.windows_active.0 |= mask;
-            }
-            if vcount == y_end {
+            } else {
                 self.windows_active.0 &= !mask;
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine(win0_y_range: (u8, u8), win0_x_range: (u8, u8)) -> Engine2d<EngineA> {
+        let mut engine = Engine2d::new(
+            #[cfg(feature = "log")]
+            slog::Logger::root(slog::Discard, slog::o!()),
+        );
+        engine.control = Control(0).with_win01_enabled(1);
+        engine.window_y_ranges[0] = win0_y_range;
+        engine.window_x_ranges[0] = win0_x_range;
+        engine
+    }
+
+    // Runs a frame's worth of window updates, returning the lines WIN0 was active on and the ones
+    // its horizontal range started out active on
+    fn run_frame(engine: &mut Engine2d<EngineA>) -> (Vec<u16>, Vec<u16>) {
+        let (mut active, mut active_x) = (Vec::new(), Vec::new());
+        for vcount in 0..263_u16 {
+            engine.update_windows(vcount as u8);
+            if engine.windows_active().win0() {
+                active.push(vcount);
+                if engine.windows_active().win0_x() {
+                    active_x.push(vcount);
+                }
+            }
+        }
+        (active, active_x)
+    }
+
+    #[test]
+    fn x_spans() {
+        for (x_range, active_at_line_start, expected) in [
+            ((10, 20), false, [0..0, 10..20]),
+            ((0, 255), false, [0..0, 0..255]),
+            // X1 > X2 extends to the right edge, and wraps around if active at the line's start
+            ((200, 50), false, [0..0, 200..256]),
+            ((200, 50), true, [0..50, 200..256]),
+            ((50, 0), true, [0..0, 50..256]),
+            // X1 == X2 is empty, as X2 takes priority
+            ((100, 100), false, [0..0, 100..100]),
+            ((0, 0), false, [0..0, 0..0]),
+        ] {
+            assert_eq!(
+                window_x_spans(x_range, active_at_line_start),
+                expected,
+                "{x_range:?}, {active_at_line_start}",
+            );
+        }
+    }
+
+    #[test]
+    fn y_latch() {
+        let mut engine = engine((10, 20), (0, 255));
+        assert_eq!(run_frame(&mut engine).0, (10..20).collect::<Vec<_>>());
+        assert_eq!(run_frame(&mut engine).0, (10..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn y_latch_wrapping() {
+        // Y1 > Y2 stays active through VBlank and into the next frame
+        let mut engine = engine((150, 30), (0, 255));
+        assert_eq!(run_frame(&mut engine).0, (150..263).collect::<Vec<_>>());
+        assert_eq!(
+            run_frame(&mut engine).0,
+            (0..30).chain(150..263).collect::<Vec<_>>(),
+        );
+    }
+
+    #[test]
+    fn y_latch_empty() {
+        // Y1 == Y2 never activates the window, as Y2 takes priority
+        let mut engine = engine((50, 50), (0, 255));
+        assert!(run_frame(&mut engine).0.is_empty());
+    }
+
+    #[test]
+    fn y_latch_truncated_vcount() {
+        // VCOUNT is truncated to 8 bits, so Y1 can match on VBlank lines 256-262 too
+        let mut engine = engine((2, 4), (0, 255));
+        assert_eq!(run_frame(&mut engine).0, vec![2, 3, 258, 259]);
+    }
+
+    #[test]
+    fn x_latch() {
+        // X1 > X2 only wraps around from the second line the window is active on
+        let mut engine = engine((10, 20), (200, 50));
+        let (active, active_x) = run_frame(&mut engine);
+        assert_eq!(active, (10..20).collect::<Vec<_>>());
+        assert_eq!(active_x, (11..20).collect::<Vec<_>>());
+
+        // X1 <= X2 never does
+        let mut engine = engine((10, 20), (50, 50));
+        assert!(run_frame(&mut engine).1.is_empty());
+    }
+}
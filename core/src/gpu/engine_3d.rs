@@ -1,9 +1,11 @@
+mod geometry;
+use geometry::{Command as GeometryCommand, Geometry, Stage as GeometryStage};
 mod io;
 mod matrix;
 mod vertex;
 pub use vertex::{Color, InterpColor, ScreenCoords, ScreenVertex, TexCoords};
 mod renderer;
-pub use renderer::{AccelRendererRx, RendererTx, SoftRendererRx};
+pub use renderer::{AccelRendererRx, GxSnapshot, RendererTx, SoftRendererRx};
 
 use crate::{
     cpu::{
@@ -13,19 +15,20 @@ use crate::{
     },
     emu::{self, Emu},
     gpu::vram::Vram,
-    utils::{load_slice_in_place, schedule::RawTimestamp, store_slice, Fifo, Savestate},
+    utils::{schedule::RawTimestamp, Fifo, Savestate},
 };
 use core::{
     intrinsics::simd::simd_div,
-    mem::{replace, transmute, MaybeUninit},
+    mem::{replace, swap, transmute, MaybeUninit},
     simd::{
         cmp::SimdOrd,
         i32x4, i64x4,
         num::{SimdInt, SimdUint},
-        u32x2, u64x2,
+        u32x2,
     },
 };
 use matrix::{Matrix, MatrixBuffer};
+use std::sync::Arc;
 use vertex::Vertex;
 
 proc_bitfield::bitfield! {
@@ -271,9 +274,6 @@ pub struct Engine3d {
     tex_params: TextureParams,
     tex_palette_base: u16,
 
-    viewport_origin: u32x2,
-    viewport_size: u64x2,
-
     vert_color: Color,
     vert_normal: [i16; 3],
     tex_coords: TexCoords,
@@ -292,26 +292,11 @@ pub struct Engine3d {
     next_poly_attrs: PolygonAttrs,
     cur_poly_attrs: PolygonAttrs,
 
-    cur_prim_type: PrimitiveType,
-    cur_prim_verts: [Vertex; 4],
-    last_strip_prim_vert_indices: [VertexAddr; 2],
-    cur_prim_max_verts: PrimMaxVerts,
-    cur_prim_vert_index: PrimVertIndex,
-    cur_strip_prim_is_odd: bool,
-    connect_to_last_strip_prim: bool,
-
-    vert_ram_level: u16,
-    poly_ram_level: u16,
-    #[load(
-        with_in_place = "load_slice_in_place(&mut vert_ram[..*vert_ram_level as usize], save)?"
-    )]
-    #[store(with = "store_slice(&mut vert_ram[..*vert_ram_level as usize], save)?")]
-    vert_ram: Box<[ScreenVertex; 6144]>,
-    #[load(
-        with_in_place = "load_slice_in_place(&mut poly_ram[..*poly_ram_level as usize], save)?"
-    )]
-    #[store(with = "store_slice(&mut poly_ram[..*poly_ram_level as usize], save)?")]
-    poly_ram: Box<[Polygon; 2048]>,
+    geometry: GeometryStage,
+    /// Snapshots previously sent to the renderer, whose buffers get swapped with the vertex and
+    /// polygon RAM on `SwapBuffers` once the renderer doesn't reference them anymore.
+    #[savestate(skip)]
+    gx_snapshots: Vec<Arc<GxSnapshot>>,
 
     rendering_state: RenderingState,
 }
@@ -417,9 +402,157 @@ impl Clip for i32x4 {
     fn output(_clipped_verts_len: usize, _clipped: bool) -> Self::Output {}
 }
 
+#[inline]
+fn clip_polygon<V: Clip + Copy>(
+    verts: &[V],
+    shared_verts_len: usize,
+    clip_far_plane: bool,
+    clip_buffer: &mut [MaybeUninit<V>; 10],
+) -> Option<V::Output> {
+    // If the last polygon wasn't clipped, then the shared vertices won't need clipping either
+    // TODO:
+    // - Maybe use the Cohen-Sutherland algorithm? It'd basically be the same but without
+    //   grouping passes, and instead running until there are no points outside the frustum
+
+    let mut clipped_verts_len = verts.len();
+    let mut clipped = false;
+
+    macro_rules! interpolate {
+        (
+            $axis_i: expr,
+            $output: expr,
+            ($vert: expr, $coord: expr, $w: expr, $sign: expr),
+            $other: expr,
+            |$other_coord: ident, $other_w: ident|
+            ($compare: expr, $numer: expr, $coord_diff: expr,),
+        ) => {
+            let other = $other;
+            let $other_coord = other.coords()[$axis_i] as i64;
+            let $other_w = other.coords()[3] as i64;
+            if $compare {
+                // For the positive side of the frustum:
+                //          w0 - x0
+                // t = -----------------
+                //     x1 - x0 - w1 + w0
+                // for the negative side:
+                //          w0 + x0
+                // t = -----------------
+                //     x0 - x1 - w1 + w0
+                // Both can be summed up by:
+                //           w0 ∓ x0                  $numer
+                // t = --------------------- = ---------------------
+                //     ±(x1 - x0) - w1 + w0    $coord_diff - w1 + w0
+                let denom = $coord_diff + $w - $other_w;
+                #[allow(clippy::neg_multiply)]
+                if denom != 0 {
+                    let mut vert = $vert.interpolate($other, $numer, denom);
+                    vert.coords_mut()[$axis_i] = $sign * vert.coords()[3];
+                    *$output.get_unchecked_mut(clipped_verts_len) = MaybeUninit::new(vert);
+                    clipped_verts_len += 1;
+                }
+            }
+        };
+    }
+
+    macro_rules! run_clip_pass {
+        ($axis_i: expr, $clip_far: expr, $input: expr$(, $assume_init: ident)? => $output: expr) => {
+            let input_len = replace(&mut clipped_verts_len, shared_verts_len);
+            for (i, vert) in $input
+                .get_unchecked(..input_len)
+                .iter()
+                .enumerate()
+                .skip(shared_verts_len)
+            {
+                $(let vert = vert.$assume_init();)*
+                let coord = vert.coords()[$axis_i] as i64;
+                let w = vert.coords()[3] as i64;
+                if coord > w {
+                    if !$clip_far {
+                        return None;
+                    }
+                    clipped = true;
+                    interpolate!(
+                        $axis_i,
+                        $output,
+                        (vert, coord, w, 1),
+                        $input.get_unchecked(if i == 0 { input_len - 1 } else { i - 1 })
+                            $(.$assume_init())*,
+                        |other_coord, other_w| (
+                            other_coord <= other_w,
+                            w - coord,
+                            other_coord - coord,
+                        ),
+                    );
+                    interpolate!(
+                        $axis_i,
+                        $output,
+                        (vert, coord, w, 1),
+                        $input.get_unchecked(if i + 1 == input_len { 0 } else { i + 1 })
+                            $(.$assume_init())*,
+                        |other_coord, other_w| (
+                            other_coord <= other_w,
+                            w - coord,
+                            other_coord - coord,
+                        ),
+                    );
+                } else if coord < -w {
+                    clipped = true;
+                    interpolate!(
+                        $axis_i,
+                        $output,
+                        (vert, coord, w, -1),
+                        $input.get_unchecked(if i == 0 { input_len - 1 } else { i - 1 })
+                            $(.$assume_init())*,
+                        |other_coord, other_w| (
+                            other_coord >= -other_w,
+                            w + coord,
+                            coord - other_coord,
+                        ),
+                    );
+                    interpolate!(
+                        $axis_i,
+                        $output,
+                        (vert, coord, w, -1),
+                        $input.get_unchecked(if i + 1 == input_len { 0 } else { i + 1 })
+                            $(.$assume_init())*,
+                        |other_coord, other_w| (
+                            other_coord >= -other_w,
+                            w + coord,
+                            coord - other_coord,
+                        ),
+                    );
+                } else {
+                    *$output.get_unchecked_mut(clipped_verts_len) = MaybeUninit::new(*vert);
+                    clipped_verts_len += 1;
+                }
+            }
+            if clipped_verts_len == 0 {
+                return None;
+            }
+        };
+    }
+
+    // Safety:
+    // - Assumes that shared_verts_len == 0 or 2
+    // - Assumes that verts.len() == 3 or 4
+    // - Assumes that the clipped vertices will not exceed 10 (guaranteed geometrically)
+    let mut buffer_1 = MaybeUninit::uninit_array::<10>();
+    unsafe {
+        for i in 0..shared_verts_len {
+            *clip_buffer.get_unchecked_mut(i) = MaybeUninit::new(*verts.get_unchecked(i));
+            *buffer_1.get_unchecked_mut(i) = MaybeUninit::new(*verts.get_unchecked(i));
+        }
+        run_clip_pass!(2, clip_far_plane, verts => clip_buffer);
+        run_clip_pass!(1, true, clip_buffer, assume_init_ref => buffer_1);
+        run_clip_pass!(0, true, buffer_1, assume_init_ref => clip_buffer);
+    }
+    Some(V::output(clipped_verts_len, clipped))
+}
+
 impl Engine3d {
     pub(super) fn new(
         renderer_tx: Box<dyn RendererTx>,
+        threaded_geometry: bool,
         schedule: &mut arm9::Schedule,
         emu_schedule: &mut emu::Schedule,
         #[cfg(feature = "log")] logger: slog::Logger,
@@ -473,9 +606,6 @@ impl Engine3d {
             tex_params: TextureParams(0),
             tex_palette_base: 0,
 
-            viewport_origin: u32x2::splat(0),
-            viewport_size: u64x2::splat(0),
-
             vert_color: Color::splat(0),
             vert_normal: [0; 3],
             tex_coords: TexCoords::splat(0),
@@ -496,18 +626,8 @@ impl Engine3d {
             next_poly_attrs: PolygonAttrs(0),
             cur_poly_attrs: PolygonAttrs(0),
 
-            cur_prim_type: PrimitiveType::Triangles,
-            cur_prim_verts: [Vertex::new(); 4],
-            last_strip_prim_vert_indices: [VertexAddr::new(0); 2],
-            cur_prim_max_verts: PrimMaxVerts::new(0),
-            cur_prim_vert_index: PrimVertIndex::new(0),
-            cur_strip_prim_is_odd: false,
-            connect_to_last_strip_prim: false,
-
-            vert_ram_level: 0,
-            poly_ram_level: 0,
-            vert_ram: unsafe { Box::new_zeroed().assume_init() },
-            poly_ram: unsafe { Box::new_zeroed().assume_init() },
+            geometry: GeometryStage::new(threaded_geometry),
+            gx_snapshots: Vec::new(),
 
             rendering_state: RenderingState {
                 control: RenderingControl(0),
@@ -576,26 +696,19 @@ impl Engine3d {
     }
 
     pub fn vert_ram_level(&self) -> u16 {
-        self.vert_ram_level
-    }
-
-    pub fn vert_ram(&self) -> &[ScreenVertex; 6144] {
-        &self.vert_ram
+        self.geometry.ram_status().vert_ram_level
     }
 
     pub fn poly_ram_level(&self) -> u16 {
-        self.poly_ram_level
-    }
-
-    pub fn poly_ram(&self) -> &[Polygon; 2048] {
-        &self.poly_ram
+        self.geometry.ram_status().poly_ram_level
     }
 
     #[inline]
     pub fn poly_vert_ram_level(&self) -> PolyVertRamLevel {
+        let status = self.geometry.ram_status();
         PolyVertRamLevel(0)
-            .with_poly_ram_level(self.poly_ram_level)
-            .with_vert_ram_level(self.vert_ram_level)
+            .with_poly_ram_level(status.poly_ram_level)
+            .with_vert_ram_level(status.vert_ram_level)
     }
 
     #[inline]
@@ -615,13 +728,19 @@ impl Engine3d {
 
     #[inline]
     pub fn rendering_control(&self) -> RenderingControl {
-        self.rendering_state.control
+        // The overflow flag is only tracked by the polygon assembly stage
+        self.rendering_state
+            .control
+            .with_poly_vert_ram_overflow(self.geometry.ram_status().ram_overflow)
     }
 
     #[inline]
     pub fn write_rendering_control(&mut self, value: RenderingControl) {
+        if value.poly_vert_ram_overflow() {
+            self.geometry.acknowledge_ram_overflow();
+        }
         self.rendering_state.control.0 =
-            (self.rendering_state.control.0 & 0x3000 & !value.0) | (value.0 & 0x4FFF);
+            (self.rendering_state.control.0 & 0x1000 & !value.0) | (value.0 & 0x4FFF);
     }
 
     #[inline]
@@ -811,6 +930,8 @@ impl Engine3d {
     fn update_clip_mtx(&mut self) {
         self.clip_mtx_needs_recalculation = false;
         self.cur_clip_mtx = self.cur_pos_vec_mtxs[0] * self.cur_proj_mtx;
+        self.geometry
+            .run(GeometryCommand::ClipMtx(self.cur_clip_mtx));
     }
 
     fn load_matrix(&mut self, matrix: Matrix) {
@@ -882,21 +1003,14 @@ impl Engine3d {
     }
 
     fn add_vert(&mut self, coords: [i16; 3]) {
-        if self.poly_ram_level as usize == self.poly_ram.len() {
-            self.rendering_state
-                .control
-                .set_poly_vert_ram_overflow(true);
-            return;
-        }
-
+        // NOTE: The last vertex's coordinates and texture coordinates are updated even if polygon
+        // RAM is full, as only the polygon assembly stage knows about that.
         self.last_vtx_coords = coords;
 
         if self.clip_mtx_needs_recalculation {
             self.update_clip_mtx();
         }
 
-        let transformed_coords = self.cur_clip_mtx.mul_left_vec3::<i16, i32>(coords);
-
         if self.tex_params.coord_transform_mode() == 3 {
             let [u, v, ..] = self
                 .cur_tex_mtx
@@ -905,387 +1019,11 @@ impl Engine3d {
             self.transformed_tex_coords = self.tex_coords + TexCoords::from_array([u, v]);
         }
 
-        self.cur_prim_verts[self.cur_prim_vert_index.get() as usize] = Vertex {
-            coords: transformed_coords,
+        self.geometry.run(GeometryCommand::Vertex {
+            coords,
             uv: self.transformed_tex_coords,
             color: self.vert_color,
-        };
-
-        let new_vert_index = self.cur_prim_vert_index.get() + 1;
-        if new_vert_index < self.cur_prim_max_verts.get() {
-            self.cur_prim_vert_index = PrimVertIndex::new(new_vert_index);
-            return;
-        }
-
-        if self.cur_prim_type == PrimitiveType::QuadStrip {
-            self.cur_prim_verts.swap(2, 3);
-        }
-
-        self.clip_and_submit_polygon();
-
-        match self.cur_prim_type {
-            PrimitiveType::Triangles | PrimitiveType::Quads => {
-                self.cur_prim_vert_index = PrimVertIndex::new(0);
-            }
-
-            PrimitiveType::TriangleStrip => {
-                self.cur_prim_verts[self.cur_strip_prim_is_odd as usize] = self.cur_prim_verts[2];
-                self.cur_prim_vert_index = PrimVertIndex::new(2);
-                self.cur_strip_prim_is_odd = !self.cur_strip_prim_is_odd;
-            }
-
-            PrimitiveType::QuadStrip => {
-                self.cur_prim_verts.copy_within(2.., 0);
-                self.cur_prim_verts.swap(0, 1);
-                self.cur_prim_vert_index = PrimVertIndex::new(2);
-            }
-        }
-    }
-
-    #[inline]
-    fn clip_polygon<V: Clip + Copy>(
-        &self,
-        verts: &[V],
-        shared_verts_len: usize,
-        clip_buffer: &mut [MaybeUninit<V>; 10],
-    ) -> Option<V::Output> {
-        // If the last polygon wasn't clipped, then the shared vertices won't need clipping either
-        // TODO:
-        // - Maybe use the Cohen-Sutherland algorithm? It'd basically be the same but without
-        //   grouping passes, and instead running until there are no points outside the frustum
-
-        let mut clipped_verts_len = verts.len();
-        let mut clipped = false;
-
-        macro_rules! interpolate {
-            (
-                $axis_i: expr,
-                $output: expr,
-                ($vert: expr, $coord: expr, $w: expr, $sign: expr),
-                $other: expr,
-                |$other_coord: ident, $other_w: ident|
-                ($compare: expr, $numer: expr, $coord_diff: expr,),
-            ) => {
-                let other = $other;
-                let $other_coord = other.coords()[$axis_i] as i64;
-                let $other_w = other.coords()[3] as i64;
-                if $compare {
-                    // For the positive side of the frustum:
-                    //          w0 - x0
-                    // t = -----------------
-                    //     x1 - x0 - w1 + w0
-                    // for the negative side:
-                    //          w0 + x0
-                    // t = -----------------
-                    //     x0 - x1 - w1 + w0
-                    // Both can be summed up by:
-                    //           w0 ∓ x0                  $numer
-                    // t = --------------------- = ---------------------
-                    //     ±(x1 - x0) - w1 + w0    $coord_diff - w1 + w0
-                    let denom = $coord_diff + $w - $other_w;
-                    #[allow(clippy::neg_multiply)]
-                    if denom != 0 {
-                        let mut vert = $vert.interpolate($other, $numer, denom);
-                        vert.coords_mut()[$axis_i] = $sign * vert.coords()[3];
-                        *$output.get_unchecked_mut(clipped_verts_len) = MaybeUninit::new(vert);
-                        clipped_verts_len += 1;
-                    }
-                }
-            };
-        }
-
-        macro_rules! run_clip_pass {
-            ($axis_i: expr, $clip_far: expr, $input: expr$(, $assume_init: ident)? => $output: expr) => {
-                let input_len = replace(&mut clipped_verts_len, shared_verts_len);
-                for (i, vert) in $input
-                    .get_unchecked(..input_len)
-                    .iter()
-                    .enumerate()
-                    .skip(shared_verts_len)
-                {
-                    $(let vert = vert.$assume_init();)*
-                    let coord = vert.coords()[$axis_i] as i64;
-                    let w = vert.coords()[3] as i64;
-                    if coord > w {
-                        if !$clip_far {
-                            return None;
-                        }
-                        clipped = true;
-                        interpolate!(
-                            $axis_i,
-                            $output,
-                            (vert, coord, w, 1),
-                            $input.get_unchecked(if i == 0 { input_len - 1 } else { i - 1 })
-                                $(.$assume_init())*,
-                            |other_coord, other_w| (
-                                other_coord <= other_w,
-                                w - coord,
-                                other_coord - coord,
-                            ),
-                        );
-                        interpolate!(
-                            $axis_i,
-                            $output,
-                            (vert, coord, w, 1),
-                            $input.get_unchecked(if i + 1 == input_len { 0 } else { i + 1 })
-                                $(.$assume_init())*,
-                            |other_coord, other_w| (
-                                other_coord <= other_w,
-                                w - coord,
-                                other_coord - coord,
-                            ),
-                        );
-                    } else if coord < -w {
-                        clipped = true;
-                        interpolate!(
-                            $axis_i,
-                            $output,
-                            (vert, coord, w, -1),
-                            $input.get_unchecked(if i == 0 { input_len - 1 } else { i - 1 })
-                                $(.$assume_init())*,
-                            |other_coord, other_w| (
-                                other_coord >= -other_w,
-                                w + coord,
-                                coord - other_coord,
-                            ),
-                        );
-                        interpolate!(
-                            $axis_i,
-                            $output,
-                            (vert, coord, w, -1),
-                            $input.get_unchecked(if i + 1 == input_len { 0 } else { i + 1 })
-                                $(.$assume_init())*,
-                            |other_coord, other_w| (
-                                other_coord >= -other_w,
-                                w + coord,
-                                coord - other_coord,
-                            ),
-                        );
-                    } else {
-                        *$output.get_unchecked_mut(clipped_verts_len) = MaybeUninit::new(*vert);
-                        clipped_verts_len += 1;
-                    }
-                }
-                if clipped_verts_len == 0 {
-                    return None;
-                }
-            };
-        }
-
-        // Safety:
-        // - Assumes that shared_verts_len == 0 or 2
-        // - Assumes that verts.len() == 3 or 4
-        // - Assumes that the clipped vertices will not exceed 10 (guaranteed geometrically)
-        let mut buffer_1 = MaybeUninit::uninit_array::<10>();
-        unsafe {
-            for i in 0..shared_verts_len {
-                *clip_buffer.get_unchecked_mut(i) = MaybeUninit::new(*verts.get_unchecked(i));
-                *buffer_1.get_unchecked_mut(i) = MaybeUninit::new(*verts.get_unchecked(i));
-            }
-            run_clip_pass!(2, self.cur_poly_attrs.clip_far_plane(), verts => clip_buffer);
-            run_clip_pass!(1, true, clip_buffer, assume_init_ref => buffer_1);
-            run_clip_pass!(0, true, buffer_1, assume_init_ref => clip_buffer);
-        }
-        Some(V::output(clipped_verts_len, clipped))
-    }
-
-    fn clip_and_submit_polygon(&mut self) {
-        // TODO:
-        // - Check whether </> or <=/>= should be used for the frustum checks
-        // - Check what happens for vertices where the divisor ends up being 0
-
-        let (culled, is_front_facing) = vertex::culled(
-            &self.cur_prim_verts[0],
-            &self.cur_prim_verts[1],
-            &self.cur_prim_verts[2],
-            self.cur_poly_attrs.show_front(),
-            self.cur_poly_attrs.show_back(),
-        );
-        if culled {
-            self.connect_to_last_strip_prim = false;
-            return;
-        }
-
-        let shared_verts_len = (self.connect_to_last_strip_prim as usize) << 1;
-        let mut clip_buffer = MaybeUninit::uninit_array::<10>();
-        let Some((clipped_verts_len, clipped)) = self.clip_polygon(
-            &self.cur_prim_verts[..self.cur_prim_max_verts.get() as usize],
-            shared_verts_len,
-            &mut clip_buffer,
-        ) else {
-            self.connect_to_last_strip_prim = false;
-            return;
-        };
-        let clipped_verts = unsafe {
-            MaybeUninit::slice_assume_init_mut(&mut clip_buffer[..clipped_verts_len.get() as usize])
-        };
-
-        if self.vert_ram_level as usize
-            > self.vert_ram.len() - (clipped_verts_len.get() as usize - shared_verts_len)
-        {
-            self.rendering_state
-                .control
-                .set_poly_vert_ram_overflow(true);
-            self.connect_to_last_strip_prim = false;
-            return;
-        }
-
-        let connect_to_last_strip_prim = replace(
-            &mut self.connect_to_last_strip_prim,
-            matches!(
-                self.cur_prim_type,
-                PrimitiveType::TriangleStrip | PrimitiveType::QuadStrip
-            ) && !clipped,
-        );
-
-        let is_translucent = matches!(self.cur_poly_attrs.alpha(), 1..=30)
-            || (matches!(self.cur_poly_attrs.mode(), 0 | 2)
-                && matches!(self.tex_params.format(), 1 | 6));
-
-        let poly = &mut self.poly_ram[self.poly_ram_level as usize];
-        self.poly_ram_level += 1;
-        poly.tex_palette_base = self.tex_palette_base;
-        poly.tex_params = self.tex_params;
-        poly.attrs = RenderingPolygonAttrs(self.cur_poly_attrs.0)
-            .with_verts_len(clipped_verts_len)
-            .with_is_front_facing(is_front_facing)
-            .with_is_translucent(is_translucent);
-
-        if connect_to_last_strip_prim {
-            poly.verts[..2].copy_from_slice(&self.last_strip_prim_vert_indices);
-        }
-
-        let mut top_y = 0xFF;
-        let mut bot_y = 0;
-
-        let viewport_origin = self.viewport_origin;
-        let viewport_size = self.viewport_size;
-        for (vert, vert_addr) in clipped_verts[shared_verts_len..]
-            .iter_mut()
-            .zip(&mut poly.verts[shared_verts_len..clipped_verts_len.get() as usize])
-        {
-            vert.coords[3] &= 0x00FF_FFFF;
-            let w = vert.coords[3] as u32;
-            let coords = if w == 0 {
-                // TODO: What should actually happen for W == 0?
-                ScreenCoords::splat(0)
-            } else {
-                let mut w = w;
-                let mut coords = u32x2::from_array([
-                    (vert.coords[0] + w as i32) as u32,
-                    (-vert.coords[1] + w as i32) as u32,
-                ]);
-                if w > 0xFFFF {
-                    w >>= 1;
-                    coords >>= 1;
-                }
-                ((unsafe {
-                    // Safety: w != 0
-                    simd_div(
-                        coords.cast::<u64>() * viewport_size,
-                        u64x2::splat((w << 1) as u64),
-                    )
-                }
-                .cast::<u32>()
-                    + viewport_origin)
-                    & u32x2::from_array([0x1FF, 0xFF]))
-                .cast::<u16>()
-            };
-            let y = coords[1] as u8;
-            top_y = top_y.min(y);
-            bot_y = bot_y.max(y);
-            self.vert_ram[self.vert_ram_level as usize] = ScreenVertex {
-                coords,
-                #[cfg(feature = "3d-hi-res-coords")]
-                hi_res_coords: if w == 0 {
-                    // TODO: What should actually happen for W == 0?
-                    ScreenCoords::splat(0)
-                } else {
-                    let mut w = w;
-                    let mut coords = u32x2::from_array([
-                        (vert.coords[0] + w as i32) as u32,
-                        (-vert.coords[1] + w as i32) as u32,
-                    ]);
-                    if w > 0xFFFF {
-                        w >>= 1;
-                        coords >>= 1;
-                    }
-                    ((unsafe {
-                        // Safety: w != 0
-                        simd_div(
-                            (coords.cast::<u64>() << 4) * viewport_size,
-                            u64x2::splat((w << 1) as u64),
-                        )
-                    }
-                    .cast::<u32>()
-                        + (viewport_origin << 4))
-                        & u32x2::from_array([0x1FFF, 0xFFF]))
-                    .cast::<u16>()
-                },
-                uv: vert.uv,
-                color: vert.color.cast::<u16>() << 3 | vert.color.cast::<u16>() >> 3,
-            };
-            *vert_addr = VertexAddr::new(self.vert_ram_level);
-            self.vert_ram_level += 1;
-        }
-
-        for &vert_addr in &poly.verts[..shared_verts_len] {
-            let y = self.vert_ram[vert_addr.get() as usize].coords[1] as u8;
-            top_y = top_y.min(y);
-            bot_y = bot_y.max(y);
-        }
-
-        poly.top_y = top_y;
-        poly.bot_y = bot_y;
-
-        let mut w_leading_zeros = 32;
-        for vert in clipped_verts.iter() {
-            w_leading_zeros = w_leading_zeros.min(vert.coords[3].leading_zeros());
-        }
-        w_leading_zeros &= !3;
-
-        if w_leading_zeros >= 16 {
-            let shift = w_leading_zeros - 16;
-            for (i, vert) in clipped_verts.iter().enumerate() {
-                poly.w_values[i] = (vert.coords[3] << shift) as u16;
-            }
-        } else {
-            let shift = 16 - w_leading_zeros;
-            for (i, vert) in clipped_verts.iter().enumerate() {
-                poly.w_values[i] = (vert.coords[3] >> shift) as u16;
-            }
-        }
-
-        for (i, vert) in clipped_verts.iter().enumerate() {
-            let w = vert.coords[3] as u32;
-            poly.depth_values[i] = if self.rendering_state.w_buffering {
-                w & !((((1_u64 << (32 - w_leading_zeros)) - 1) as u32) >> 16)
-            } else if w != 0 {
-                ((((((vert.coords[2] as i64) << 14) / w as i64) + 0x3FFF) << 9) as i32)
-                    .clamp(0, 0xFF_FFFF) as u32
-            } else {
-                // TODO: What should this value be? This is using 0 as (z << 14) / w
-                0x7F_FE00
-            };
-        }
-
-        if self.connect_to_last_strip_prim {
-            match self.cur_prim_type {
-                PrimitiveType::TriangleStrip => {
-                    self.last_strip_prim_vert_indices = if self.cur_strip_prim_is_odd {
-                        [poly.verts[0], poly.verts[2]]
-                    } else {
-                        [poly.verts[2], poly.verts[1]]
-                    };
-                }
-
-                PrimitiveType::QuadStrip => {
-                    self.last_strip_prim_vert_indices = [poly.verts[3], poly.verts[2]];
-                }
-
-                _ => {}
-            }
-        }
+        });
     }
 
     fn box_test(&mut self, param0: u32, param1: u32, param2: u32) -> bool {
@@ -1315,51 +1053,52 @@ impl Engine3d {
         }
 
         let mut clip_buffer = MaybeUninit::uninit_array::<10>();
+        let clip_far_plane = self.cur_poly_attrs.clip_far_plane();
 
         for x in 0..2 {
-            if self
-                .clip_polygon(
-                    &[
-                        coords[x << 2],
-                        coords[x << 2 | 1],
-                        coords[x << 2 | 3],
-                        coords[x << 2 | 2],
-                    ],
-                    0,
-                    &mut clip_buffer,
-                )
-                .is_some()
+            if clip_polygon(
+                &[
+                    coords[x << 2],
+                    coords[x << 2 | 1],
+                    coords[x << 2 | 3],
+                    coords[x << 2 | 2],
+                ],
+                0,
+                clip_far_plane,
+                &mut clip_buffer,
+            )
+            .is_some()
             {
                 return true;
             }
         }
 
         for y in 0..2 {
-            if self
-                .clip_polygon(
-                    &[
-                        coords[y << 1],
-                        coords[y << 1 | 1],
-                        coords[y << 1 | 5],
-                        coords[y << 1 | 4],
-                    ],
-                    0,
-                    &mut clip_buffer,
-                )
-                .is_some()
+            if clip_polygon(
+                &[
+                    coords[y << 1],
+                    coords[y << 1 | 1],
+                    coords[y << 1 | 5],
+                    coords[y << 1 | 4],
+                ],
+                0,
+                clip_far_plane,
+                &mut clip_buffer,
+            )
+            .is_some()
             {
                 return true;
             }
         }
 
         for z in 0..2 {
-            if self
-                .clip_polygon(
-                    &[coords[z], coords[z | 2], coords[z | 6], coords[z | 4]],
-                    0,
-                    &mut clip_buffer,
-                )
-                .is_some()
+            if clip_polygon(
+                &[coords[z], coords[z | 2], coords[z | 6], coords[z | 4]],
+                0,
+                clip_far_plane,
+                &mut clip_buffer,
+            )
+            .is_some()
             {
                 return true;
             }
@@ -1378,46 +1117,50 @@ impl Engine3d {
         }
     }
 
+    /// Moves the current vertex and polygon RAM contents into a snapshot to be sent to the
+    /// renderer, without copying them; afterwards, the geometry stage's buffers hold stale data,
+    /// which is fine as both RAM levels get reset.
+    fn take_gx_snapshot(
+        gx_snapshots: &mut Vec<Arc<GxSnapshot>>,
+        geometry: &mut Geometry,
+    ) -> Arc<GxSnapshot> {
+        let mut gx = match gx_snapshots
+            .iter_mut()
+            .position(|gx| Arc::get_mut(gx).is_some())
+        {
+            Some(i) => gx_snapshots.swap_remove(i),
+            None => Arc::new(GxSnapshot::new_zeroed()),
+        };
+        let gx_mut = Arc::get_mut(&mut gx).unwrap();
+        swap(&mut gx_mut.vert_ram, &mut geometry.vert_ram);
+        swap(&mut gx_mut.poly_ram, &mut geometry.poly_ram);
+        gx_mut.vert_ram_level = geometry.vert_ram_level;
+        gx_mut.poly_ram_level = geometry.poly_ram_level;
+        gx_snapshots.push(Arc::clone(&gx));
+        gx
+    }
+
     pub(super) fn swap_buffers(emu: &mut Emu<impl cpu::Engine>) {
-        if emu.gpu.engine_3d.rendering_enabled {
-            // According to melonDS, the sort order is determined by these things, in order of
-            // decreasing priority:
-            // - Being translucent/opaque (opaque polygons always come first, GBATEK says this too)
-            // - Bottom Y (lower first)
-            // - Top Y (lower first)
-            // - Submit order (thus needing a stable sort)
-            if emu
-                .gpu
-                .engine_3d
-                .swap_buffers_attrs
-                .translucent_auto_sort_disabled()
-            {
-                emu.gpu.engine_3d.poly_ram[..emu.gpu.engine_3d.poly_ram_level as usize]
-                    .sort_by_key(|poly| {
-                        if poly.attrs.is_translucent() {
-                            0x1_0000
-                        } else {
-                            (poly.bot_y as u32) << 8 | poly.top_y as u32
-                        }
-                    });
-            } else {
-                emu.gpu.engine_3d.poly_ram[..emu.gpu.engine_3d.poly_ram_level as usize]
-                    .sort_by_key(|poly| {
-                        (poly.attrs.is_translucent() as u32) << 16
-                            | (poly.bot_y as u32) << 8
-                            | poly.top_y as u32
-                    });
+        let engine_3d = &mut emu.gpu.engine_3d;
+        {
+            // Waits for the geometry worker thread (if any) to finish the frame's polygons
+            let mut geometry = engine_3d.geometry.get_mut();
+            if engine_3d.rendering_enabled {
+                geometry.sort_polys(
+                    engine_3d
+                        .swap_buffers_attrs
+                        .translucent_auto_sort_disabled(),
+                );
+                let gx = Self::take_gx_snapshot(&mut engine_3d.gx_snapshots, &mut geometry);
+                engine_3d
+                    .renderer_tx
+                    .swap_buffers(&gx, &engine_3d.rendering_state);
             }
-            emu.gpu.engine_3d.renderer_tx.swap_buffers(
-                &emu.gpu.engine_3d.vert_ram[..emu.gpu.engine_3d.vert_ram_level as usize],
-                &emu.gpu.engine_3d.poly_ram[..emu.gpu.engine_3d.poly_ram_level as usize],
-                &emu.gpu.engine_3d.rendering_state,
-            );
+            engine_3d.rendering_state.w_buffering = engine_3d.swap_buffers_attrs.w_buffering();
+            geometry.w_buffering = engine_3d.rendering_state.w_buffering;
+            geometry.vert_ram_level = 0;
+            geometry.poly_ram_level = 0;
         }
-        emu.gpu.engine_3d.rendering_state.w_buffering =
-            emu.gpu.engine_3d.swap_buffers_attrs.w_buffering();
-        emu.gpu.engine_3d.vert_ram_level = 0;
-        emu.gpu.engine_3d.poly_ram_level = 0;
         Self::process_next_command(emu);
     }
 
@@ -1932,11 +1675,21 @@ impl Engine3d {
                 0x2A => {
                     // TEXIMAGE_PARAM
                     emu.gpu.engine_3d.tex_params = TextureParams(first_param);
+                    emu.gpu
+                        .engine_3d
+                        .geometry
+                        .run(GeometryCommand::TexParams(emu.gpu.engine_3d.tex_params));
                 }
 
                 0x2B => {
                     // PLTT_BASE
                     emu.gpu.engine_3d.tex_palette_base = first_param as u16 & 0x1FFF;
+                    emu.gpu
+                        .engine_3d
+                        .geometry
+                        .run(GeometryCommand::TexPaletteBase(
+                            emu.gpu.engine_3d.tex_palette_base,
+                        ));
                 }
 
                 0x30 => {
@@ -2001,17 +1754,10 @@ impl Engine3d {
                 0x40 => {
                     // BEGIN_VTXS
                     emu.gpu.engine_3d.cur_poly_attrs = emu.gpu.engine_3d.next_poly_attrs;
-                    emu.gpu.engine_3d.cur_prim_type =
-                        unsafe { transmute::<u8, PrimitiveType>(first_param as u8 & 3) };
-                    emu.gpu.engine_3d.cur_prim_vert_index = PrimVertIndex::new(0);
-                    emu.gpu.engine_3d.cur_prim_max_verts = match emu.gpu.engine_3d.cur_prim_type {
-                        PrimitiveType::Triangles | PrimitiveType::TriangleStrip => {
-                            PrimMaxVerts::new(3)
-                        }
-                        PrimitiveType::Quads | PrimitiveType::QuadStrip => PrimMaxVerts::new(4),
-                    };
-                    emu.gpu.engine_3d.cur_strip_prim_is_odd = false;
-                    emu.gpu.engine_3d.connect_to_last_strip_prim = false;
+                    emu.gpu.engine_3d.geometry.run(GeometryCommand::BeginVtxs {
+                        attrs: emu.gpu.engine_3d.cur_poly_attrs,
+                        prim_type: unsafe { transmute::<u8, PrimitiveType>(first_param as u8 & 3) },
+                    });
                 }
 
                 0x41 => {
@@ -2038,13 +1784,14 @@ impl Engine3d {
                     let x1 = first_param >> 16 & 0xFF;
                     let y1 = first_param >> 24;
 
-                    emu.gpu.engine_3d.viewport_origin =
-                        u32x2::from_array([x0, 191_u32.wrapping_sub(y1) & 0xFF]);
-                    emu.gpu.engine_3d.viewport_size = u32x2::from_array([
-                        x1.wrapping_sub(x0).wrapping_add(1) & 0x1FF,
-                        y1.wrapping_sub(y0_unmasked).wrapping_add(1) & 0xFF,
-                    ])
-                    .cast();
+                    emu.gpu.engine_3d.geometry.run(GeometryCommand::Viewport {
+                        origin: u32x2::from_array([x0, 191_u32.wrapping_sub(y1) & 0xFF]),
+                        size: u32x2::from_array([
+                            x1.wrapping_sub(x0).wrapping_add(1) & 0x1FF,
+                            y1.wrapping_sub(y0_unmasked).wrapping_add(1) & 0xFF,
+                        ])
+                        .cast(),
+                    });
                 }
 
                 0x70 => {
@@ -0,0 +1,727 @@
+//! The polygon assembly stage of the geometry engine, which transforms vertices into clip space,
+//! assembles them into primitives, culls and clips those, and stores the results in vertex and
+//! polygon RAM.
+//!
+//! This is the most expensive part of processing geometry commands, and none of its results are
+//! visible to the CPU until it reads the RAM levels or overflow flag (or a new frame is started by
+//! `SwapBuffers`), so it can optionally run on a worker thread: commands are then queued up in
+//! batches, and the worker is waited for at those points, keeping results deterministic.
+
+use super::{
+    clip_polygon, vertex::Vertex, Color, Matrix, Polygon, PolygonAttrs, PrimMaxVerts,
+    PrimVertIndex, PrimitiveType, RenderingPolygonAttrs, ScreenCoords, ScreenVertex, TexCoords,
+    TextureParams, VertexAddr,
+};
+use crate::utils::{
+    load_slice_in_place, store_slice, LoadableInPlace, ReadSavestate, Savestate, Storable,
+    WriteSavestate,
+};
+use core::{
+    cell::{Cell, RefCell},
+    intrinsics::simd::simd_div,
+    mem::{replace, MaybeUninit},
+    ops::{Deref, DerefMut},
+    simd::{num::SimdUint, u32x2, u64x2},
+};
+use std::{
+    sync::{mpsc, Arc, Mutex, MutexGuard},
+    thread,
+};
+
+/// A geometry command forwarded to the polygon assembly stage, carrying the part of the geometry
+/// engine's state it needs.
+#[derive(Clone, Copy)]
+pub(super) enum Command {
+    ClipMtx(Matrix),
+    TexParams(TextureParams),
+    TexPaletteBase(u16),
+    Viewport {
+        origin: u32x2,
+        size: u64x2,
+    },
+    BeginVtxs {
+        attrs: PolygonAttrs,
+        prim_type: PrimitiveType,
+    },
+    Vertex {
+        coords: [i16; 3],
+        uv: TexCoords,
+        color: Color,
+    },
+}
+
+#[derive(Savestate)]
+#[load(in_place_only)]
+pub(super) struct Geometry {
+    clip_mtx: Matrix,
+    tex_params: TextureParams,
+    tex_palette_base: u16,
+
+    viewport_origin: u32x2,
+    viewport_size: u64x2,
+
+    // Latched on BEGIN_VTXS
+    cur_poly_attrs: PolygonAttrs,
+
+    cur_prim_type: PrimitiveType,
+    cur_prim_verts: [Vertex; 4],
+    last_strip_prim_vert_indices: [VertexAddr; 2],
+    cur_prim_max_verts: PrimMaxVerts,
+    cur_prim_vert_index: PrimVertIndex,
+    cur_strip_prim_is_odd: bool,
+    connect_to_last_strip_prim: bool,
+
+    /// Latched on `SwapBuffers`, like the rendering engine's copy.
+    pub(super) w_buffering: bool,
+    /// Whether vertex or polygon RAM overflowed since the CPU last acknowledged it, reported in
+    /// bit 13 of `DISP3DCNT`.
+    pub(super) ram_overflow: bool,
+
+    pub(super) vert_ram_level: u16,
+    pub(super) poly_ram_level: u16,
+    #[load(
+        with_in_place = "load_slice_in_place(&mut vert_ram[..*vert_ram_level as usize], save)?"
+    )]
+    #[store(with = "store_slice(&mut vert_ram[..*vert_ram_level as usize], save)?")]
+    pub(super) vert_ram: Box<[ScreenVertex; 6144]>,
+    #[load(
+        with_in_place = "load_slice_in_place(&mut poly_ram[..*poly_ram_level as usize], save)?"
+    )]
+    #[store(with = "store_slice(&mut poly_ram[..*poly_ram_level as usize], save)?")]
+    pub(super) poly_ram: Box<[Polygon; 2048]>,
+}
+
+impl Geometry {
+    pub(super) fn new() -> Self {
+        Geometry {
+            clip_mtx: Matrix::zero(),
+            tex_params: TextureParams(0),
+            tex_palette_base: 0,
+
+            viewport_origin: u32x2::splat(0),
+            viewport_size: u64x2::splat(0),
+
+            cur_poly_attrs: PolygonAttrs(0),
+
+            cur_prim_type: PrimitiveType::Triangles,
+            cur_prim_verts: [Vertex::new(); 4],
+            last_strip_prim_vert_indices: [VertexAddr::new(0); 2],
+            cur_prim_max_verts: PrimMaxVerts::new(0),
+            cur_prim_vert_index: PrimVertIndex::new(0),
+            cur_strip_prim_is_odd: false,
+            connect_to_last_strip_prim: false,
+
+            w_buffering: false,
+            ram_overflow: false,
+
+            vert_ram_level: 0,
+            poly_ram_level: 0,
+            vert_ram: unsafe { Box::new_zeroed().assume_init() },
+            poly_ram: unsafe { Box::new_zeroed().assume_init() },
+        }
+    }
+
+    fn run(&mut self, command: Command) {
+        match command {
+            Command::ClipMtx(mtx) => self.clip_mtx = mtx,
+            Command::TexParams(tex_params) => self.tex_params = tex_params,
+            Command::TexPaletteBase(tex_palette_base) => self.tex_palette_base = tex_palette_base,
+            Command::Viewport { origin, size } => {
+                self.viewport_origin = origin;
+                self.viewport_size = size;
+            }
+            Command::BeginVtxs { attrs, prim_type } => {
+                self.cur_poly_attrs = attrs;
+                self.cur_prim_type = prim_type;
+                self.cur_prim_vert_index = PrimVertIndex::new(0);
+                self.cur_prim_max_verts = match prim_type {
+                    PrimitiveType::Triangles | PrimitiveType::TriangleStrip => PrimMaxVerts::new(3),
+                    PrimitiveType::Quads | PrimitiveType::QuadStrip => PrimMaxVerts::new(4),
+                };
+                self.cur_strip_prim_is_odd = false;
+                self.connect_to_last_strip_prim = false;
+            }
+            Command::Vertex { coords, uv, color } => self.add_vert(coords, uv, color),
+        }
+    }
+
+    fn add_vert(&mut self, coords: [i16; 3], uv: TexCoords, color: Color) {
+        if self.poly_ram_level as usize == self.poly_ram.len() {
+            self.ram_overflow = true;
+            return;
+        }
+
+        self.cur_prim_verts[self.cur_prim_vert_index.get() as usize] = Vertex {
+            coords: self.clip_mtx.mul_left_vec3::<i16, i32>(coords),
+            uv,
+            color,
+        };
+
+        let new_vert_index = self.cur_prim_vert_index.get() + 1;
+        if new_vert_index < self.cur_prim_max_verts.get() {
+            self.cur_prim_vert_index = PrimVertIndex::new(new_vert_index);
+            return;
+        }
+
+        if self.cur_prim_type == PrimitiveType::QuadStrip {
+            self.cur_prim_verts.swap(2, 3);
+        }
+
+        self.clip_and_submit_polygon();
+
+        match self.cur_prim_type {
+            PrimitiveType::Triangles | PrimitiveType::Quads => {
+                self.cur_prim_vert_index = PrimVertIndex::new(0);
+            }
+
+            PrimitiveType::TriangleStrip => {
+                self.cur_prim_verts[self.cur_strip_prim_is_odd as usize] = self.cur_prim_verts[2];
+                self.cur_prim_vert_index = PrimVertIndex::new(2);
+                self.cur_strip_prim_is_odd = !self.cur_strip_prim_is_odd;
+            }
+
+            PrimitiveType::QuadStrip => {
+                self.cur_prim_verts.copy_within(2.., 0);
+                self.cur_prim_verts.swap(0, 1);
+                self.cur_prim_vert_index = PrimVertIndex::new(2);
+            }
+        }
+    }
+
+    fn clip_and_submit_polygon(&mut self) {
+        // TODO:
+        // - Check whether </> or <=/>= should be used for the frustum checks
+        // - Check what happens for vertices where the divisor ends up being 0
+
+        let (culled, is_front_facing) = vertex::culled(
+            &self.cur_prim_verts[0],
+            &self.cur_prim_verts[1],
+            &self.cur_prim_verts[2],
+            self.cur_poly_attrs.show_front(),
+            self.cur_poly_attrs.show_back(),
+        );
+        if culled {
+            self.connect_to_last_strip_prim = false;
+            return;
+        }
+
+        let shared_verts_len = (self.connect_to_last_strip_prim as usize) << 1;
+        let mut clip_buffer = MaybeUninit::uninit_array::<10>();
+        let Some((clipped_verts_len, clipped)) = clip_polygon(
+            &self.cur_prim_verts[..self.cur_prim_max_verts.get() as usize],
+            shared_verts_len,
+            self.cur_poly_attrs.clip_far_plane(),
+            &mut clip_buffer,
+        ) else {
+            self.connect_to_last_strip_prim = false;
+            return;
+        };
+        let clipped_verts = unsafe {
+            MaybeUninit::slice_assume_init_mut(&mut clip_buffer[..clipped_verts_len.get() as usize])
+        };
+
+        if self.vert_ram_level as usize
+            > self.vert_ram.len() - (clipped_verts_len.get() as usize - shared_verts_len)
+        {
+            self.ram_overflow = true;
+            self.connect_to_last_strip_prim = false;
+            return;
+        }
+
+        let connect_to_last_strip_prim = replace(
+            &mut self.connect_to_last_strip_prim,
+            matches!(
+                self.cur_prim_type,
+                PrimitiveType::TriangleStrip | PrimitiveType::QuadStrip
+            ) && !clipped,
+        );
+
+        let is_translucent = matches!(self.cur_poly_attrs.alpha(), 1..=30)
+            || (matches!(self.cur_poly_attrs.mode(), 0 | 2)
+                && matches!(self.tex_params.format(), 1 | 6));
+
+        let poly = &mut self.poly_ram[self.poly_ram_level as usize];
+        self.poly_ram_level += 1;
+        poly.tex_palette_base = self.tex_palette_base;
+        poly.tex_params = self.tex_params;
+        poly.attrs = RenderingPolygonAttrs(self.cur_poly_attrs.0)
+            .with_verts_len(clipped_verts_len)
+            .with_is_front_facing(is_front_facing)
+            .with_is_translucent(is_translucent);
+
+        if connect_to_last_strip_prim {
+            poly.verts[..2].copy_from_slice(&self.last_strip_prim_vert_indices);
+        }
+
+        let mut top_y = 0xFF;
+        let mut bot_y = 0;
+
+        let viewport_origin = self.viewport_origin;
+        let viewport_size = self.viewport_size;
+        for (vert, vert_addr) in clipped_verts[shared_verts_len..]
+            .iter_mut()
+            .zip(&mut poly.verts[shared_verts_len..clipped_verts_len.get() as usize])
+        {
+            vert.coords[3] &= 0x00FF_FFFF;
+            let w = vert.coords[3] as u32;
+            let coords = if w == 0 {
+                // TODO: What should actually happen for W == 0?
+                ScreenCoords::splat(0)
+            } else {
+                let mut w = w;
+                let mut coords = u32x2::from_array([
+                    (vert.coords[0] + w as i32) as u32,
+                    (-vert.coords[1] + w as i32) as u32,
+                ]);
+                if w > 0xFFFF {
+                    w >>= 1;
+                    coords >>= 1;
+                }
+                ((unsafe {
+                    // Safety: w != 0
+                    simd_div(
+                        coords.cast::<u64>() * viewport_size,
+                        u64x2::splat((w << 1) as u64),
+                    )
+                }
+                .cast::<u32>()
+                    + viewport_origin)
+                    & u32x2::from_array([0x1FF, 0xFF]))
+                .cast::<u16>()
+            };
+            let y = coords[1] as u8;
+            top_y = top_y.min(y);
+            bot_y = bot_y.max(y);
+            self.vert_ram[self.vert_ram_level as usize] = ScreenVertex {
+                coords,
+                #[cfg(feature = "3d-hi-res-coords")]
+                hi_res_coords: if w == 0 {
+                    // TODO: What should actually happen for W == 0?
+                    ScreenCoords::splat(0)
+                } else {
+                    let mut w = w;
+                    let mut coords = u32x2::from_array([
+                        (vert.coords[0] + w as i32) as u32,
+                        (-vert.coords[1] + w as i32) as u32,
+                    ]);
+                    if w > 0xFFFF {
+                        w >>= 1;
+                        coords >>= 1;
+                    }
+                    ((unsafe {
+                        // Safety: w != 0
+                        simd_div(
+                            (coords.cast::<u64>() << 4) * viewport_size,
+                            u64x2::splat((w << 1) as u64),
+                        )
+                    }
+                    .cast::<u32>()
+                        + (viewport_origin << 4))
+                        & u32x2::from_array([0x1FFF, 0xFFF]))
+                    .cast::<u16>()
+                },
+                uv: vert.uv,
+                color: vert.color.cast::<u16>() << 3 | vert.color.cast::<u16>() >> 3,
+            };
+            *vert_addr = VertexAddr::new(self.vert_ram_level);
+            self.vert_ram_level += 1;
+        }
+
+        for &vert_addr in &poly.verts[..shared_verts_len] {
+            let y = self.vert_ram[vert_addr.get() as usize].coords[1] as u8;
+            top_y = top_y.min(y);
+            bot_y = bot_y.max(y);
+        }
+
+        poly.top_y = top_y;
+        poly.bot_y = bot_y;
+
+        let mut w_leading_zeros = 32;
+        for vert in clipped_verts.iter() {
+            w_leading_zeros = w_leading_zeros.min(vert.coords[3].leading_zeros());
+        }
+        w_leading_zeros &= !3;
+
+        if w_leading_zeros >= 16 {
+            let shift = w_leading_zeros - 16;
+            for (i, vert) in clipped_verts.iter().enumerate() {
+                poly.w_values[i] = (vert.coords[3] << shift) as u16;
+            }
+        } else {
+            let shift = 16 - w_leading_zeros;
+            for (i, vert) in clipped_verts.iter().enumerate() {
+                poly.w_values[i] = (vert.coords[3] >> shift) as u16;
+            }
+        }
+
+        for (i, vert) in clipped_verts.iter().enumerate() {
+            let w = vert.coords[3] as u32;
+            poly.depth_values[i] = if self.w_buffering {
+                w & !((((1_u64 << (32 - w_leading_zeros)) - 1) as u32) >> 16)
+            } else if w != 0 {
+                ((((((vert.coords[2] as i64) << 14) / w as i64) + 0x3FFF) << 9) as i32)
+                    .clamp(0, 0xFF_FFFF) as u32
+            } else {
+                // TODO: What should this value be? This is using 0 as (z << 14) / w
+                0x7F_FE00
+            };
+        }
+
+        if self.connect_to_last_strip_prim {
+            match self.cur_prim_type {
+                PrimitiveType::TriangleStrip => {
+                    self.last_strip_prim_vert_indices = if self.cur_strip_prim_is_odd {
+                        [poly.verts[0], poly.verts[2]]
+                    } else {
+                        [poly.verts[2], poly.verts[1]]
+                    };
+                }
+
+                PrimitiveType::QuadStrip => {
+                    self.last_strip_prim_vert_indices = [poly.verts[3], poly.verts[2]];
+                }
+
+                _ => {}
+            }
+        }
+    }
+
+    pub(super) fn sort_polys(&mut self, translucent_auto_sort_disabled: bool) {
+        // According to melonDS, the sort order is determined by these things, in order of
+        // decreasing priority:
+        // - Being translucent/opaque (opaque polygons always come first, GBATEK says this too)
+        // - Bottom Y (lower first)
+        // - Top Y (lower first)
+        // - Submit order (thus needing a stable sort)
+        let polys = &mut self.poly_ram[..self.poly_ram_level as usize];
+        if translucent_auto_sort_disabled {
+            polys.sort_by_key(|poly| {
+                if poly.attrs.is_translucent() {
+                    0x1_0000
+                } else {
+                    (poly.bot_y as u32) << 8 | poly.top_y as u32
+                }
+            });
+        } else {
+            polys.sort_by_key(|poly| {
+                (poly.attrs.is_translucent() as u32) << 16
+                    | (poly.bot_y as u32) << 8
+                    | poly.top_y as u32
+            });
+        }
+    }
+}
+
+/// The parts of the polygon assembly stage's state visible to the CPU.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct RamStatus {
+    pub(super) vert_ram_level: u16,
+    pub(super) poly_ram_level: u16,
+    pub(super) ram_overflow: bool,
+}
+
+impl RamStatus {
+    fn new(geometry: &Geometry) -> Self {
+        RamStatus {
+            vert_ram_level: geometry.vert_ram_level,
+            poly_ram_level: geometry.poly_ram_level,
+            ram_overflow: geometry.ram_overflow,
+        }
+    }
+}
+
+/// The number of commands queued up before they're sent to the worker thread.
+const BATCH_LEN: usize = 128;
+
+enum Message {
+    Batch(Vec<Command>),
+    Sync,
+}
+
+/// A worker thread running the polygon assembly stage.
+pub(super) struct Worker {
+    geometry: Arc<Mutex<Box<Geometry>>>,
+    pending: RefCell<Vec<Command>>,
+    /// The worker's RAM status as of the last sync, if no commands have been issued since then;
+    /// games tend to poll it in a loop, which would otherwise wait for the worker every time.
+    status: Cell<Option<RamStatus>>,
+    tx: Option<mpsc::Sender<Message>>,
+    synced_rx: mpsc::Receiver<()>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Worker {
+    fn new(geometry: Box<Geometry>) -> Self {
+        let geometry = Arc::new(Mutex::new(geometry));
+        let (tx, rx) = mpsc::channel();
+        let (synced_tx, synced_rx) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("GX geometry".to_owned())
+            .spawn({
+                let geometry = Arc::clone(&geometry);
+                move || {
+                    for message in rx {
+                        match message {
+                            Message::Batch(commands) => {
+                                let mut geometry = geometry.lock().unwrap();
+                                for command in commands {
+                                    geometry.run(command);
+                                }
+                            }
+                            Message::Sync => {
+                                if synced_tx.send(()).is_err() {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                }
+            })
+            .expect("couldn't spawn GX geometry thread");
+        Worker {
+            geometry,
+            pending: RefCell::new(Vec::with_capacity(BATCH_LEN)),
+            status: Cell::new(None),
+            tx: Some(tx),
+            synced_rx,
+            thread: Some(thread),
+        }
+    }
+
+    fn send(&self, message: Message) {
+        self.tx
+            .as_ref()
+            .unwrap()
+            .send(message)
+            .expect("GX geometry thread exited unexpectedly");
+    }
+
+    fn flush(&self) {
+        let mut pending = self.pending.borrow_mut();
+        if !pending.is_empty() {
+            self.send(Message::Batch(replace(
+                &mut *pending,
+                Vec::with_capacity(BATCH_LEN),
+            )));
+        }
+    }
+
+    fn run(&mut self, command: Command) {
+        *self.status.get_mut() = None;
+        let pending = self.pending.get_mut();
+        pending.push(command);
+        if pending.len() >= BATCH_LEN {
+            self.flush();
+        }
+    }
+
+    /// Waits for the worker to process all queued commands, then locks its state.
+    fn sync(&self) -> MutexGuard<'_, Box<Geometry>> {
+        self.flush();
+        self.send(Message::Sync);
+        self.synced_rx
+            .recv()
+            .expect("GX geometry thread exited unexpectedly");
+        self.geometry.lock().unwrap()
+    }
+
+    fn ram_status(&self) -> RamStatus {
+        if let Some(status) = self.status.get() {
+            return status;
+        }
+        let status = RamStatus::new(&self.sync());
+        self.status.set(Some(status));
+        status
+    }
+
+    /// Waits for the worker to process all queued commands, then locks its state for
+    /// modification, invalidating the cached RAM status.
+    fn sync_mut(&mut self) -> MutexGuard<'_, Box<Geometry>> {
+        *self.status.get_mut() = None;
+        self.sync()
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        drop(self.tx.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The polygon assembly stage, either running inline on the emulation thread or on a worker.
+pub(super) enum Stage {
+    Inline(Box<Geometry>),
+    Threaded(Worker),
+}
+
+pub(super) enum GeometryMut<'a> {
+    Inline(&'a mut Geometry),
+    Threaded(MutexGuard<'a, Box<Geometry>>),
+}
+
+impl Deref for GeometryMut<'_> {
+    type Target = Geometry;
+
+    fn deref(&self) -> &Geometry {
+        match self {
+            GeometryMut::Inline(geometry) => geometry,
+            GeometryMut::Threaded(geometry) => geometry,
+        }
+    }
+}
+
+impl DerefMut for GeometryMut<'_> {
+    fn deref_mut(&mut self) -> &mut Geometry {
+        match self {
+            GeometryMut::Inline(geometry) => geometry,
+            GeometryMut::Threaded(geometry) => geometry,
+        }
+    }
+}
+
+impl Stage {
+    pub(super) fn new(threaded: bool) -> Self {
+        let geometry = Box::new(Geometry::new());
+        if threaded {
+            Stage::Threaded(Worker::new(geometry))
+        } else {
+            Stage::Inline(geometry)
+        }
+    }
+
+    #[inline]
+    pub(super) fn run(&mut self, command: Command) {
+        match self {
+            Stage::Inline(geometry) => geometry.run(command),
+            Stage::Threaded(worker) => worker.run(command),
+        }
+    }
+
+    /// Returns the RAM levels and overflow flag once all previously issued commands have been
+    /// processed.
+    pub(super) fn ram_status(&self) -> RamStatus {
+        match self {
+            Stage::Inline(geometry) => RamStatus::new(geometry),
+            Stage::Threaded(worker) => worker.ram_status(),
+        }
+    }
+
+    pub(super) fn acknowledge_ram_overflow(&mut self) {
+        if self.ram_status().ram_overflow {
+            self.get_mut().ram_overflow = false;
+        }
+    }
+
+    /// Returns the stage's state once all previously issued commands have been processed.
+    pub(super) fn get_mut(&mut self) -> GeometryMut<'_> {
+        match self {
+            Stage::Inline(geometry) => GeometryMut::Inline(geometry),
+            Stage::Threaded(worker) => GeometryMut::Threaded(worker.sync_mut()),
+        }
+    }
+}
+
+impl LoadableInPlace for Stage {
+    fn load_in_place<S: ReadSavestate>(&mut self, save: &mut S) -> Result<(), S::Error> {
+        self.get_mut().load_in_place(save)
+    }
+}
+
+impl Storable for Stage {
+    fn store<S: WriteSavestate>(&mut self, save: &mut S) -> Result<(), S::Error> {
+        self.get_mut().store(save)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Generates a deterministic command stream mixing all primitive types, with vertices
+    /// scattered both inside and outside of the view volume (so that some polygons get culled or
+    /// clipped), long enough to overflow vertex or polygon RAM.
+    fn commands() -> Vec<Command> {
+        let mut seed = 0x1234_5678_u32;
+        let mut next = move || {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            seed >> 16
+        };
+        let mut commands = vec![
+            Command::ClipMtx(Matrix::identity()),
+            Command::Viewport {
+                origin: u32x2::splat(0),
+                size: u64x2::from_array([256, 192]),
+            },
+        ];
+        for i in 0..10_000 {
+            if i % 16 == 0 {
+                commands.push(Command::TexParams(TextureParams(next() << 26)));
+                commands.push(Command::TexPaletteBase(next() as u16 & 0x1FFF));
+                commands.push(Command::BeginVtxs {
+                    attrs: PolygonAttrs(0xC0 | (next() & 0x1F) << 16 | (next() & 1) << 12),
+                    prim_type: match next() & 3 {
+                        0 => PrimitiveType::Triangles,
+                        1 => PrimitiveType::Quads,
+                        2 => PrimitiveType::TriangleStrip,
+                        _ => PrimitiveType::QuadStrip,
+                    },
+                });
+            }
+            commands.push(Command::Vertex {
+                coords: [(), (), ()].map(|_| (next() & 0x3FFF) as i16 - 0x2000),
+                uv: TexCoords::from_array([next() as i16, next() as i16]),
+                color: Color::from_array([(), (), (), ()].map(|_| next() as u8 & 0x1F)),
+            });
+        }
+        commands
+    }
+
+    fn ram_contents(stage: &mut Stage) -> (RamStatus, Vec<ScreenVertex>, Vec<Polygon>) {
+        let status = stage.ram_status();
+        let geometry = stage.get_mut();
+        (
+            status,
+            geometry.vert_ram[..geometry.vert_ram_level as usize].to_vec(),
+            geometry.poly_ram[..geometry.poly_ram_level as usize].to_vec(),
+        )
+    }
+
+    #[test]
+    fn threaded_matches_inline() {
+        let mut inline = Stage::new(false);
+        let mut threaded = Stage::new(true);
+        for (i, &command) in commands().iter().enumerate() {
+            inline.run(command);
+            threaded.run(command);
+            if i % 97 == 0 {
+                assert_eq!(threaded.ram_status(), inline.ram_status());
+                // Cached until more commands are issued
+                assert_eq!(threaded.ram_status(), inline.ram_status());
+            }
+        }
+        let inline = ram_contents(&mut inline);
+        assert!(inline.0.poly_ram_level > 0);
+        assert_eq!(ram_contents(&mut threaded), inline);
+    }
+
+    #[test]
+    fn overflow_is_acknowledged() {
+        for threaded in [false, true] {
+            let mut stage = Stage::new(threaded);
+            for command in commands() {
+                stage.run(command);
+            }
+            stage.run(Command::Vertex {
+                coords: [0; 3],
+                uv: TexCoords::splat(0),
+                color: Color::splat(0),
+            });
+            assert!(stage.ram_status().ram_overflow);
+            stage.acknowledge_ram_overflow();
+            assert!(!stage.ram_status().ram_overflow);
+        }
+    }
+}
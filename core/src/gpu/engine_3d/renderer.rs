@@ -1,14 +1,53 @@
 use super::{Polygon, RenderingState, ScreenVertex};
 use crate::{gpu::Scanline, utils::mem_prelude::*};
+use std::sync::Arc;
+
+/// The vertices and (sorted) polygons submitted for a frame, produced once per `SwapBuffers` and
+/// shared between all renderers consuming them instead of being copied into each.
+///
+/// Snapshots are immutable once sent; the 3D engine only reuses a snapshot's buffers after all
+/// renderers have dropped their references to it.
+pub struct GxSnapshot {
+    pub(super) vert_ram: Box<[ScreenVertex; 6144]>,
+    pub(super) vert_ram_level: u16,
+    pub(super) poly_ram: Box<[Polygon; 2048]>,
+    pub(super) poly_ram_level: u16,
+}
+
+impl GxSnapshot {
+    pub(super) fn new_zeroed() -> Self {
+        GxSnapshot {
+            vert_ram: unsafe { Box::new_zeroed().assume_init() },
+            vert_ram_level: 0,
+            poly_ram: unsafe { Box::new_zeroed().assume_init() },
+            poly_ram_level: 0,
+        }
+    }
+
+    #[inline]
+    pub fn vert_ram_level(&self) -> u16 {
+        self.vert_ram_level
+    }
+
+    #[inline]
+    pub fn vert_ram(&self) -> &[ScreenVertex; 6144] {
+        &self.vert_ram
+    }
+
+    #[inline]
+    pub fn poly_ram_level(&self) -> u16 {
+        self.poly_ram_level
+    }
+
+    #[inline]
+    pub fn poly_ram(&self) -> &[Polygon; 2048] {
+        &self.poly_ram
+    }
+}
 
 pub trait RendererTx {
     fn set_capture_enabled(&mut self, capture_enabled: bool);
-    fn swap_buffers(
-        &mut self,
-        vert_ram: &[ScreenVertex],
-        poly_ram: &[Polygon],
-        state: &RenderingState,
-    );
+    fn swap_buffers(&mut self, gx: &Arc<GxSnapshot>, state: &RenderingState);
     fn repeat_last_frame(&mut self, state: &RenderingState);
     fn start_rendering(
         &mut self,
@@ -3,7 +3,7 @@ mod bank_cnt;
 
 use crate::{
     cpu::{arm7, arm9, Engine},
-    utils::{OwnedBytesCellPtr, Savestate},
+    utils::{Bytes, OwnedBytesCellPtr, Savestate},
 };
 use core::{
     cell::{Cell, UnsafeCell},
@@ -144,7 +144,7 @@ impl Vram {
         let zero_buffer = OwnedBytesCellPtr::new_zeroed();
         let ignore_buffer = OwnedBytesCellPtr::new_zeroed();
 
-        Vram {
+        let mut result = Vram {
             bank_control: [BankControl(0); 9],
             arm7_status: Arm7Status(0),
             banks,
@@ -172,7 +172,11 @@ impl Vram {
 
             zero_buffer,
             ignore_buffer,
-        }
+        };
+        // The 2D renderer may be reused from a previous run (i.e. after a reset), in which case it
+        // could still hold copies of the old VRAM contents
+        result.mark_all_vram_updated();
+        result
     }
 
     fn post_load(&mut self) {
@@ -224,6 +228,29 @@ impl Vram {
         self.arm7_status
     }
 
+    /// Returns the texture and texture palette memory as seen by the 3D engine when rendering.
+    #[inline]
+    pub fn texture_contents(&self) -> (&Bytes<0x8_0000>, &Bytes<0x1_8000>) {
+        unsafe { (self.texture.as_bytes(), self.tex_pal.as_bytes()) }
+    }
+
+    /// Returns the contents of a VRAM bank (from 0 for A to 8 for I), after flushing any pending
+    /// writes made through its current mappings.
+    pub fn bank_contents(&mut self, index: u8) -> &[u8] {
+        self.flush_writeback();
+        match index {
+            0 => self.banks.a.as_arr(),
+            1 => self.banks.b.as_arr(),
+            2 => self.banks.c.as_arr(),
+            3 => self.banks.d.as_arr(),
+            4 => self.banks.e.as_arr(),
+            5 => self.banks.f.as_arr(),
+            6 => self.banks.g.as_arr(),
+            7 => &self.banks.h.as_arr()[..0x8000],
+            _ => self.banks.i.as_arr(),
+        }
+    }
+
     pub(crate) fn setup_arm7_bus_ptrs(&mut self, ptrs: &mut arm7::bus::ptrs::Ptrs) {
         unsafe {
             ptrs.map_range(
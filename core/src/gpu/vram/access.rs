@@ -682,6 +682,38 @@ impl Vram {
         );
     }
 
+    /// Writes to texture palette memory through its mapping, updating all banks mapped to the
+    /// written address; this isn't possible on hardware, and is only meant for debugging tools.
+    ///
+    /// The 3D engine's texture palette slots need to be marked as dirty afterwards, which
+    /// [`Gpu::write_tex_pal`](crate::gpu::Gpu::write_tex_pal) takes care of.
+    #[inline]
+    pub fn write_tex_pal<T: MemValue>(&mut self, addr: u32, value: T) {
+        let addr = addr as usize % 0x1_8000 & !(mem::size_of::<T>() - 1);
+        let mapped = self.map.tex_pal[addr >> 14].get();
+        if mapped == 0 {
+            return;
+        }
+        unsafe {
+            self.tex_pal.write_le_aligned_unchecked(addr, value);
+            if mapped & 1 << 0 != 0 {
+                self.banks
+                    .e
+                    .write_le_aligned_unchecked(addr & 0xFFFF, value);
+            }
+            if mapped & 1 << 1 != 0 {
+                self.banks
+                    .f
+                    .write_le_aligned_unchecked(addr & 0x3FFF, value);
+            }
+            if mapped & 1 << 2 != 0 {
+                self.banks
+                    .g
+                    .write_le_aligned_unchecked(addr & 0x3FFF, value);
+            }
+        }
+    }
+
     #[inline]
     pub fn read_arm7<T: MemValue>(&self, addr: u32) -> T {
         unsafe {
@@ -38,13 +38,17 @@
 pub extern crate emu_utils as utils;
 
 pub mod audio;
+pub mod camera;
 pub mod cpu;
 pub mod dldi;
 pub mod ds_slot;
 pub mod emu;
 pub mod flash;
+pub mod gba_slot;
 pub mod gpu;
 pub mod ipc;
+pub mod ndma;
+pub mod profiling;
 pub mod rtc;
 pub mod spi;
 pub mod wifi;
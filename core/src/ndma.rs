@@ -0,0 +1,360 @@
+use crate::{
+    cpu::{
+        arm9::{bus, event_slots, Event, EventSlotIndex, IrqFlags, Schedule, Timestamp},
+        bus::DmaAccess,
+        Engine, Schedule as _,
+    },
+    emu::Emu,
+    utils::{schedule::RawTimestamp, Savestate},
+};
+
+// TODO: Only the ARM9's NDMA controller is emulated, and only its immediate and camera start
+//       modes; transfers use a fixed per-word cost, ignoring bus timings, the block interval timer
+//       and the arbitration settings in NDMAGCNT.
+
+mod bounded {
+    use crate::utils::{bounded_int_lit, bounded_int_savestate};
+    bounded_int_lit!(pub struct Index(u8), max 3);
+    bounded_int_savestate!(Index(u8));
+}
+pub use bounded::*;
+
+/// The maximum number of words an immediate transfer runs at once before scheduling the rest, so
+/// that long transfers (up to `0x1000_0000` words) don't stall emulation.
+const IMMEDIATE_CHUNK_LEN: u32 = 0x400;
+/// The approximate time taken to transfer a single word, in ARM9 cycles.
+const WORD_CYCLES: RawTimestamp = 4;
+
+fn event_slot(i: Index) -> EventSlotIndex {
+    EventSlotIndex::new(event_slots::NDMA_START.get() + i.get())
+}
+
+proc_bitfield::bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq, Savestate)]
+    pub struct Control(pub u32): Debug {
+        pub dst_addr_control: u8 @ 10..=11,
+        pub dst_addr_reload: bool @ 12,
+        pub src_addr_control: u8 @ 13..=14,
+        pub src_addr_reload: bool @ 15,
+        pub physical_block_size_shift: u8 @ 16..=19,
+        pub timing: u8 @ 24..=28,
+        pub repeat: bool @ 29,
+        pub irq_enabled: bool @ 30,
+        pub enabled: bool @ 31,
+    }
+}
+
+impl Control {
+    #[inline]
+    pub fn is_immediate(self) -> bool {
+        self.timing() & 0x10 != 0
+    }
+}
+
+pub const TIMING_CAMERA: u8 = 0x0B;
+
+#[derive(Clone, Copy, Savestate)]
+pub struct Channel {
+    pub src_addr: u32,
+    pub dst_addr: u32,
+    pub total_len: u32,
+    pub block_len: u32,
+    pub block_interval: u32,
+    pub fill_data: u32,
+    control: Control,
+    cur_src_addr: u32,
+    cur_dst_addr: u32,
+    remaining_len: u32,
+}
+
+impl Channel {
+    const fn new() -> Self {
+        Channel {
+            src_addr: 0,
+            dst_addr: 0,
+            total_len: 0,
+            block_len: 0,
+            block_interval: 0,
+            fill_data: 0,
+            control: Control(0),
+            cur_src_addr: 0,
+            cur_dst_addr: 0,
+            remaining_len: 0,
+        }
+    }
+
+    #[inline]
+    pub fn control(&self) -> Control {
+        self.control
+    }
+}
+
+/// The DSi's new DMA controller, as seen from the ARM9.
+#[derive(Savestate)]
+#[load(in_place_only)]
+pub struct Ndma {
+    #[savestate(skip)]
+    enabled: bool,
+    global_control: u32,
+    pub channels: [Channel; 4],
+}
+
+impl Ndma {
+    pub(crate) fn new(enabled: bool, schedule: &mut Schedule) -> Self {
+        for i in 0..4 {
+            let i = Index::new(i);
+            schedule.set_event(event_slot(i), Event::NdmaTransfer(i));
+        }
+        Ndma {
+            enabled,
+            global_control: 0,
+            channels: [Channel::new(); 4],
+        }
+    }
+
+    /// Whether the NDMA registers are mapped at all (i.e. whether the emulated model is a DSi).
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    #[inline]
+    pub fn global_control(&self) -> u32 {
+        self.global_control
+    }
+
+    /// Reads a 32-bit register, given its offset from NDMAGCNT.
+    pub fn read_32(&self, offset: u16) -> u32 {
+        if offset < 4 {
+            return self.global_control;
+        }
+        let channel = &self.channels[((offset - 4) / 0x1C) as usize & 3];
+        match (offset - 4) % 0x1C {
+            0x00 => channel.src_addr,
+            0x04 => channel.dst_addr,
+            0x08 => channel.total_len,
+            0x0C => channel.block_len,
+            0x10 => channel.block_interval,
+            0x14 => channel.fill_data,
+            _ => channel.control.0,
+        }
+    }
+
+    /// Writes a 32-bit register, given its offset from NDMAGCNT.
+    pub(crate) fn write_32<E: Engine>(emu: &mut Emu<E>, offset: u16, value: u32) {
+        if offset < 4 {
+            emu.ndma.global_control = value & 0x800F_0000;
+            return;
+        }
+        let i = ((offset - 4) / 0x1C) as usize & 3;
+        let channel = &mut emu.ndma.channels[i];
+        match (offset - 4) % 0x1C {
+            0x00 => channel.src_addr = value & !3,
+            0x04 => channel.dst_addr = value & !3,
+            0x08 => channel.total_len = value & 0x0FFF_FFFF,
+            0x0C => channel.block_len = value & 0x00FF_FFFF,
+            0x10 => channel.block_interval = value & 0x0003_FFFF,
+            0x14 => channel.fill_data = value,
+            _ => {
+                let prev_value = channel.control;
+                channel.control = Control(value & 0xFF0F_FC00);
+                if !channel.control.enabled() {
+                    if prev_value.enabled() && prev_value.is_immediate() {
+                        emu.arm9
+                            .schedule
+                            .cancel_event(event_slot(Index::new(i as u8)));
+                    }
+                    return;
+                }
+                if prev_value.enabled() {
+                    return;
+                }
+                channel.cur_src_addr = channel.src_addr;
+                channel.cur_dst_addr = channel.dst_addr;
+                channel.remaining_len = if channel.total_len == 0 {
+                    0x1000_0000
+                } else {
+                    channel.total_len
+                };
+                if channel.control.is_immediate() {
+                    // Immediate transfers ignore the block length, and are run in chunks as soon as
+                    // the current instruction finishes
+                    let time = emu.arm9.schedule.cur_time();
+                    emu.arm9
+                        .schedule
+                        .schedule_event(event_slot(Index::new(i as u8)), time);
+                }
+            }
+        }
+    }
+
+    /// Runs the next chunk of an immediate transfer, scheduling the following one if it's not
+    /// finished yet.
+    pub(crate) fn handle_transfer_event<E: Engine>(emu: &mut Emu<E>, i: Index) {
+        let channel = &emu.ndma.channels[i.get() as usize];
+        if !channel.control.enabled() {
+            return;
+        }
+        let len = if channel.control.repeat() {
+            IMMEDIATE_CHUNK_LEN
+        } else {
+            channel.remaining_len.min(IMMEDIATE_CHUNK_LEN)
+        };
+        if Self::run_block(emu, i.get() as usize, len) {
+            emu.arm9.irqs.write_requested(
+                IrqFlags(emu.arm9.irqs.requested().0 | 1 << (28 + i.get())),
+                &mut emu.arm9.schedule,
+            );
+        }
+        if emu.ndma.channels[i.get() as usize].control.enabled() {
+            let time = emu.arm9.schedule.cur_time() + Timestamp(len as RawTimestamp * WORD_CYCLES);
+            emu.arm9.schedule.schedule_event(event_slot(i), time);
+        }
+    }
+
+    /// Runs a block of `len` words for the given channel, then disables it if it reached the end
+    /// of its total length, returning whether its IRQ should be requested.
+    fn run_block<E: Engine>(emu: &mut Emu<E>, i: usize, len: u32) -> bool {
+        let channel = emu.ndma.channels[i];
+        let len = if channel.control.repeat() {
+            len
+        } else {
+            len.min(channel.remaining_len)
+        };
+        let addr_incr = |addr_control| match addr_control {
+            0 => 4_u32,
+            1 => 4_u32.wrapping_neg(),
+            _ => 0,
+        };
+        let src_incr = addr_incr(channel.control.src_addr_control());
+        let dst_incr = addr_incr(channel.control.dst_addr_control());
+
+        let mut src_addr = channel.cur_src_addr;
+        let mut dst_addr = channel.cur_dst_addr;
+        for _ in 0..len {
+            let value = if channel.control.src_addr_control() == 3 {
+                channel.fill_data
+            } else {
+                bus::read_32::<DmaAccess, _, false>(emu, src_addr)
+            };
+            bus::write_32::<DmaAccess, _>(emu, dst_addr, value);
+            src_addr = src_addr.wrapping_add(src_incr);
+            dst_addr = dst_addr.wrapping_add(dst_incr);
+        }
+
+        let channel = &mut emu.ndma.channels[i];
+        channel.cur_src_addr = if channel.control.src_addr_reload() {
+            channel.src_addr
+        } else {
+            src_addr
+        };
+        channel.cur_dst_addr = if channel.control.dst_addr_reload() {
+            channel.dst_addr
+        } else {
+            dst_addr
+        };
+        if channel.control.repeat() {
+            return false;
+        }
+        channel.remaining_len -= len;
+        if channel.remaining_len != 0 {
+            return false;
+        }
+        channel.control.set_enabled(false);
+        channel.control.irq_enabled()
+    }
+
+    /// Runs the enabled camera NDMA channel with the lowest index until the camera finishes its
+    /// current frame; as frames are captured on demand, this is done once per VBlank.
+    pub(crate) fn run_camera_transfers<E: Engine>(emu: &mut Emu<E>) {
+        while emu.camera.is_capturing() {
+            let Some(i) = emu.ndma.channels.iter().position(|channel| {
+                channel.control.enabled() && channel.control.timing() == TIMING_CAMERA
+            }) else {
+                break;
+            };
+            let block_len = match emu.ndma.channels[i].block_len {
+                0 => 0x100_0000,
+                len => len,
+            };
+            if Self::run_block(emu, i, block_len) {
+                // Camera transfers run outside of the ARM9's execution, so there's no need to stop
+                // it here
+                emu.arm9
+                    .irqs
+                    .write_requested(IrqFlags(emu.arm9.irqs.requested().0 | 1 << (28 + i)), ());
+            }
+            if emu.camera.frame_finished() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cpu::{bus::CpuAccess, interpreter::Interpreter},
+        emu::testing,
+        Model,
+    };
+
+    const FILL_DATA: u32 = 0x1234_5678;
+
+    /// Builds a DSi and starts an immediate fill transfer of `total_len` words to main RAM on
+    /// NDMA channel 0, with its IRQ enabled.
+    fn emu_with_immediate_fill(total_len: u32) -> Emu<Interpreter> {
+        let mut builder = testing::builder();
+        builder.model = Model::Dsi;
+        let mut emu = testing::build_from(builder);
+        for (offset, value) in [
+            (0x108, 0x0200_0000),
+            (0x10C, total_len),
+            (0x118, FILL_DATA),
+            (0x11C, 0xD000_0000 | 3 << 13),
+        ] {
+            bus::write_32::<CpuAccess, _>(&mut emu, 0x0400_4000 | offset, value);
+        }
+        emu
+    }
+
+    fn read_dst(emu: &mut Emu<Interpreter>, word: u32) -> u32 {
+        bus::read_32::<CpuAccess, _, false>(emu, 0x0200_0000 + word * 4)
+    }
+
+    #[test]
+    fn immediate_transfer_is_scheduled() {
+        let mut emu = emu_with_immediate_fill(0);
+        // Even the maximum length doesn't run synchronously when the control register is written
+        assert_eq!(read_dst(&mut emu, 0), 0);
+        assert_eq!(emu.ndma.channels[0].remaining_len, 0x1000_0000);
+    }
+
+    #[test]
+    fn immediate_transfer_runs_in_chunks() {
+        let len = IMMEDIATE_CHUNK_LEN + 0x100;
+        let mut emu = emu_with_immediate_fill(len);
+
+        Ndma::handle_transfer_event(&mut emu, Index::new(0));
+        assert_eq!(read_dst(&mut emu, IMMEDIATE_CHUNK_LEN - 1), FILL_DATA);
+        assert_eq!(read_dst(&mut emu, IMMEDIATE_CHUNK_LEN), 0);
+        assert!(emu.ndma.channels[0].control().enabled());
+        assert!(!emu.arm9.irqs.requested().dsi_ndma0());
+
+        Ndma::handle_transfer_event(&mut emu, Index::new(0));
+        assert_eq!(read_dst(&mut emu, len - 1), FILL_DATA);
+        assert_eq!(read_dst(&mut emu, len), 0);
+        assert!(!emu.ndma.channels[0].control().enabled());
+        assert!(emu.arm9.irqs.requested().dsi_ndma0());
+    }
+
+    #[test]
+    fn disabled_channel_stops_transferring() {
+        let mut emu = emu_with_immediate_fill(0x10);
+        bus::write_32::<CpuAccess, _>(&mut emu, 0x0400_411C, 0);
+        Ndma::handle_transfer_event(&mut emu, Index::new(0));
+        assert_eq!(read_dst(&mut emu, 0), 0);
+        assert!(!emu.arm9.irqs.requested().dsi_ndma0());
+    }
+}
@@ -0,0 +1,65 @@
+//! Lightweight instrumentation used to measure how long different parts of the emulator (and the
+//! renderers driven by it) take per frame.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+#[derive(Default)]
+struct TimeCounterData {
+    enabled: AtomicBool,
+    nanos: AtomicU64,
+}
+
+/// Accumulates the time spent inside an instrumented section of code, possibly across multiple
+/// threads, until it's taken by the frontend.
+///
+/// Time is only measured while the counter is enabled, so that disabled instrumentation only costs
+/// an atomic load per section; counters start out disabled.
+#[derive(Clone, Default)]
+pub struct TimeCounter(Arc<TimeCounterData>);
+
+impl TimeCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.0.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, value: bool) {
+        self.0.enabled.store(value, Ordering::Relaxed);
+    }
+
+    /// Starts measuring an instrumented section, returning the instant it started at if the
+    /// counter is enabled; the result should be passed to [`stop`](Self::stop) once the section
+    /// ends.
+    #[inline]
+    pub fn start(&self) -> Option<Instant> {
+        self.is_enabled().then(Instant::now)
+    }
+
+    #[inline]
+    pub fn stop(&self, start: Option<Instant>) {
+        if let Some(start) = start {
+            self.add(start.elapsed());
+        }
+    }
+
+    pub fn add(&self, duration: Duration) {
+        self.0
+            .nanos
+            .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Returns the total time measured since the last call, resetting it.
+    pub fn take(&self) -> Duration {
+        Duration::from_nanos(self.0.nanos.swap(0, Ordering::Relaxed))
+    }
+}
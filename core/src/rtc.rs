@@ -1,7 +1,14 @@
-use crate::utils::Savestate;
+use crate::{
+    cpu::Engine,
+    emu::{self, event_slots, Emu, Timestamp},
+    utils::{schedule::RawTimestamp, Savestate},
+};
 use core::any::Any;
 
-// TODO: Implement INT1 and INT2 (and also expose them)
+// How often the interrupt outputs are updated; this is enough to resolve the 16 Hz selected
+// frequency interrupt and the shortest (~7.9 ms) per-minute steady interrupt
+const TICKS_PER_SECOND: u8 = 64;
+const TICK_INTERVAL: Timestamp = Timestamp(33_554_432 / TICKS_PER_SECOND as RawTimestamp);
 
 proc_bitfield::bitfield! {
     #[derive(Clone, Copy, PartialEq, Eq, Savestate)]
@@ -61,6 +68,46 @@ pub struct Time {
     pub second: u8,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Int1Mode {
+    Disabled,
+    SelectedFrequency,
+    PerMinuteEdge,
+    PerMinuteSteady30s,
+    Alarm,
+    PerMinuteSteady7_9ms,
+    Clock32KHz,
+}
+
+impl Int1Mode {
+    pub fn from_raw(value: u8) -> Self {
+        match value & 0xF {
+            0 => Int1Mode::Disabled,
+            0x8..=0xF => Int1Mode::Clock32KHz,
+            0x1 | 0x5 => Int1Mode::SelectedFrequency,
+            0x2 | 0x6 => Int1Mode::PerMinuteEdge,
+            0x3 => Int1Mode::PerMinuteSteady30s,
+            0x4 => Int1Mode::Alarm,
+            _ => Int1Mode::PerMinuteSteady7_9ms,
+        }
+    }
+}
+
+/// An alarm's settings; each field is only compared against the current date and time if it's
+/// `Some`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Alarm {
+    pub days_from_sunday: Option<u8>,
+    pub hour: Option<u8>,
+    pub minute: Option<u8>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interrupt {
+    Int1(Int1Mode),
+    Alarm2,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Date {
     pub years_since_2000: u8,
@@ -76,6 +123,10 @@ pub trait Backend {
     fn get_time(&mut self) -> Time;
     fn get_date_time(&mut self) -> (Date, Time);
     fn set_date_time(&mut self, value: (Date, Time));
+
+    /// Called whenever one of the RTC's interrupt outputs is asserted, i.e. when an alarm goes off
+    /// or a periodic interrupt fires.
+    fn interrupt_triggered(&mut self, _interrupt: Interrupt) {}
 }
 
 pub struct DummyBackend;
@@ -140,6 +191,9 @@ pub struct Rtc {
     int2: [u8; 3],
     pub clock_adjust: u8,
     pub free_reg: u8,
+    int_active: [bool; 2],
+    last_second: u8,
+    subsecond_ticks: u8,
 }
 
 fn from_bcd(value: u8) -> u8 {
@@ -154,8 +208,12 @@ impl Rtc {
     pub(crate) fn new(
         backend: Box<dyn Backend>,
         first_launch: bool,
+        emu_schedule: &mut emu::Schedule,
         #[cfg(feature = "log")] logger: slog::Logger,
     ) -> Self {
+        emu_schedule.set_event(event_slots::RTC, emu::Event::RtcTick);
+        emu_schedule.schedule_event(event_slots::RTC, TICK_INTERVAL);
+
         Rtc {
             #[cfg(feature = "log")]
             logger,
@@ -176,6 +234,9 @@ impl Rtc {
             int2: [0; 3],
             clock_adjust: 0,
             free_reg: 0,
+            int_active: [false; 2],
+            last_second: u8::MAX,
+            subsecond_ticks: 0,
         }
     }
 
@@ -244,17 +305,133 @@ impl Rtc {
             #[cfg(feature = "log")]
             slog::warn!(self.logger, "Tried to enter unimplemented test mode");
         }
-        if value.int1_mode() != 0 {
-            #[cfg(feature = "log")]
-            slog::warn!(self.logger, "Tried to enable unimplemented alarm 1");
-        }
-        if value.int2_enabled() {
+        if Int1Mode::from_raw(value.int1_mode()) == Int1Mode::Clock32KHz {
             #[cfg(feature = "log")]
-            slog::warn!(self.logger, "Tried to enable unimplemented alarm 2");
+            slog::warn!(
+                self.logger,
+                "Tried to enable unimplemented 32 kHz clock output"
+            );
         }
         self.status2 = value;
     }
 
+    #[inline]
+    pub fn int1_mode(&self) -> Int1Mode {
+        Int1Mode::from_raw(self.status2.int1_mode())
+    }
+
+    fn alarm_from_regs(regs: &[u8; 3], is_in_24_hour_mode: bool) -> Alarm {
+        Alarm {
+            days_from_sunday: (regs[0] & 0x80 != 0).then_some(regs[0] & 7),
+            hour: (regs[1] & 0x80 != 0).then(|| {
+                from_bcd(regs[1] & 0x3F)
+                    + if is_in_24_hour_mode {
+                        0
+                    } else {
+                        12 * (regs[1] >> 6 & 1)
+                    }
+            }),
+            minute: (regs[2] & 0x80 != 0).then_some(from_bcd(regs[2] & 0x7F)),
+        }
+    }
+
+    /// Returns the settings for alarm 1, if INT1 is in alarm mode.
+    pub fn alarm1(&self) -> Option<Alarm> {
+        (self.int1_mode() == Int1Mode::Alarm)
+            .then(|| Self::alarm_from_regs(&self.int1, self.status1.is_in_24_hour_mode()))
+    }
+
+    /// Returns the settings for alarm 2, if it's enabled.
+    pub fn alarm2(&self) -> Option<Alarm> {
+        self.status2
+            .int2_enabled()
+            .then(|| Self::alarm_from_regs(&self.int2, self.status1.is_in_24_hour_mode()))
+    }
+
+    /// Returns whether the /INT output (shared by INT1 and INT2) is currently asserted (low).
+    #[inline]
+    pub fn int_active(&self) -> bool {
+        self.int_active[0] || self.int_active[1]
+    }
+
+    fn alarm_matches(alarm: Alarm, date: Date, time: Time) -> bool {
+        (alarm.days_from_sunday.is_some() || alarm.hour.is_some() || alarm.minute.is_some())
+            && alarm
+                .days_from_sunday
+                .map_or(true, |value| value == date.days_from_sunday)
+            && alarm.hour.map_or(true, |value| value == time.hour)
+            && alarm.minute.map_or(true, |value| value == time.minute)
+    }
+
+    pub(crate) fn handle_tick(emu: &mut Emu<impl Engine>, time: Timestamp) {
+        emu.schedule
+            .schedule_event(event_slots::RTC, time + TICK_INTERVAL);
+
+        let rtc = &mut emu.rtc;
+        let int1_mode = rtc.int1_mode();
+        let alarm2 = rtc.alarm2();
+
+        let mut int_active = [false; 2];
+        if !matches!(int1_mode, Int1Mode::Disabled | Int1Mode::Clock32KHz) || alarm2.is_some() {
+            let (date, cur_time) = rtc.backend.get_date_time();
+
+            let minute_started = rtc.last_second != u8::MAX && cur_time.second < rtc.last_second;
+            if cur_time.second != rtc.last_second {
+                rtc.last_second = cur_time.second;
+                rtc.subsecond_ticks = 0;
+            } else if rtc.subsecond_ticks < TICKS_PER_SECOND - 1 {
+                rtc.subsecond_ticks += 1;
+            }
+
+            int_active[0] = match int1_mode {
+                Int1Mode::SelectedFrequency => {
+                    // Bits 0-4 select 1, 2, 4, 8 and 16 Hz square waves respectively; /INT is
+                    // asserted while all selected ones are in their first half-period
+                    let selected = rtc.int1[2] & 0x1F;
+                    selected != 0
+                        && (0..5).all(|i| {
+                            selected & 1 << i == 0 || rtc.subsecond_ticks >> (5 - i) & 1 == 0
+                        })
+                }
+                // Held until STATUS1 is read
+                Int1Mode::PerMinuteEdge => minute_started || rtc.status1.int1_flag(),
+                Int1Mode::PerMinuteSteady30s => cur_time.second < 30,
+                Int1Mode::Alarm => {
+                    Self::alarm_matches(rtc.alarm1().unwrap(), date, cur_time)
+                        || rtc.int_active[0] && rtc.status1.int1_flag()
+                }
+                Int1Mode::PerMinuteSteady7_9ms => cur_time.second == 0 && rtc.subsecond_ticks == 0,
+                Int1Mode::Disabled | Int1Mode::Clock32KHz => false,
+            };
+            int_active[1] = alarm2.is_some_and(|alarm2| {
+                Self::alarm_matches(alarm2, date, cur_time)
+                    || rtc.int_active[1] && rtc.status1.int2_flag()
+            });
+        } else {
+            rtc.last_second = u8::MAX;
+        }
+
+        let was_active = rtc.int_active();
+        for (i, &active) in int_active.iter().enumerate() {
+            if active && !rtc.int_active[i] {
+                rtc.status1.0 |= 0x10 << i;
+                rtc.backend.interrupt_triggered(if i == 0 {
+                    Interrupt::Int1(int1_mode)
+                } else {
+                    Interrupt::Alarm2
+                });
+            }
+        }
+        rtc.int_active = int_active;
+
+        // /INT is connected to SI, which requests an IRQ on its falling edge if enabled in RCNT
+        if !was_active && rtc.int_active() && emu.rcnt() & 1 << 8 != 0 {
+            emu.arm7
+                .irqs
+                .write_requested(emu.arm7.irqs.requested().with_sio_rtc(true), ());
+        }
+    }
+
     fn latch_date(&mut self, date: Date) {
         self.latched_date_time[0] = to_bcd(date.years_since_2000);
         self.latched_date_time[1] = to_bcd(date.month);
@@ -4,7 +4,7 @@ pub mod tsc;
 
 use crate::{
     cpu::{arm7, Schedule as _},
-    emu::{self, input},
+    emu::{self, input, noise::Noise},
     flash::Flash,
     utils::Savestate,
     Model,
@@ -112,6 +112,7 @@ impl Controller {
         arm7_schedule: &mut arm7::Schedule,
         emu_schedule: &mut emu::Schedule,
         input_status: &mut input::Status,
+        noise: &mut Noise,
     ) {
         // TODO: What happens if SPICNT bit 11 is set before changing the device?
         if self.control.busy() || !self.control.enabled() {
@@ -142,6 +143,7 @@ impl Controller {
                     arm7_schedule.cur_time().into(),
                     &self.power,
                     input_status,
+                    noise,
                 )
             }
 
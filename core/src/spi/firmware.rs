@@ -130,8 +130,8 @@ pub fn verify(firmware: &[u8], model: Model) -> Result<(), VerificationError> {
             firmware,
             VerificationRegion::User0IQue,
             0xFFFF,
-            0x7_FE00 & mask..0x7_FEFD & mask,
-            0x7_FE72 & mask,
+            0x7_FE74 & mask..0x7_FEFE & mask,
+            0x7_FEFE & mask,
         )?;
     }
     check_crc(
@@ -146,8 +146,8 @@ pub fn verify(firmware: &[u8], model: Model) -> Result<(), VerificationError> {
             firmware,
             VerificationRegion::User1IQue,
             0xFFFF,
-            0x7_FF00 & mask..0x7_FF70 & mask,
-            0x7_FF72 & mask,
+            0x7_FF74 & mask..0x7_FFFE & mask,
+            0x7_FFFE & mask,
         )?;
     }
     Ok(())
@@ -1,6 +1,11 @@
 use super::crc16;
 use crate::{utils::mem_prelude::*, Model};
 
+/// The screen positions (in pixels) of the two touchscreen calibration points stored in the
+/// user settings; the corresponding ADC values are derived from them using the same 12-bit scale
+/// the emulated TSC uses for touch positions.
+const CALIBRATION_POINTS: [(u8, u8); 2] = [(0x20, 0x20), (0xE0, 0xA0)];
+
 pub fn default(model: Model) -> BoxedByteSlice {
     let len = match model {
         Model::Dsi => 0x2_0000,
@@ -63,12 +68,15 @@ pub fn default(model: Model) -> BoxedByteSlice {
         user_settings[0x56] = 0;
         user_settings[0x57] = 0;
 
-        user_settings.write_le(0x58, 0x0000_u16);
-        user_settings.write_le(0x5A, 0x0000_u16);
-        user_settings.write_le(0x5C, 0x00_00_u16);
-        user_settings.write_le(0x5E, 0x0FF0_u16);
-        user_settings.write_le(0x60, 0x0BF0_u16);
-        user_settings.write_le(0x62, 0xBF_FF_u16);
+        // Touchscreen calibration; zeroed or degenerate points would make games that validate
+        // them (or that detect an uncalibrated console) get stuck prompting for calibration
+        for (i, (x, y)) in CALIBRATION_POINTS.into_iter().enumerate() {
+            let base = 0x58 + i * 6;
+            user_settings.write_le(base, (x as u16) << 4);
+            user_settings.write_le(base + 2, (y as u16) << 4);
+            user_settings[base + 4] = x;
+            user_settings[base + 5] = y;
+        }
 
         user_settings.write_le(
             0x64,
@@ -1,6 +1,6 @@
 use super::Power;
 use crate::{
-    emu::{input, Timestamp},
+    emu::{input, noise::Noise, Timestamp},
     utils::{zeroed_box, Savestate},
 };
 
@@ -17,6 +17,16 @@ proc_bitfield::bitfield! {
 
 pub const MIC_SAMPLES_PER_FRAME: usize = (6 * 355 * 263 + 128) / 128;
 
+// Maximum deviations of the ADC readings from the sampled values, in 12-bit units; the touch
+// panel's readings jitter slightly while it's being pressed, and the mic's output isn't perfectly
+// flat even in silence
+const TOUCH_NOISE_AMPLITUDE: u16 = 2;
+const MIC_NOISE_AMPLITUDE: u16 = 1;
+
+fn add_noise(value: u16, amplitude: u16, noise: &mut Noise) -> u16 {
+    (value as i32 + noise.next_analog_offset(amplitude)).clamp(0, 0xFFF) as u16
+}
+
 pub trait MicBackend {
     fn start_frame(&mut self);
     fn read_frame_samples(&mut self, offset: usize, samples: &mut [i16]);
@@ -55,6 +65,8 @@ pub struct Tsc {
     data_out: u16,
     x_pos: u16,
     y_pos: u16,
+    #[savestate(skip)]
+    pressure: u8,
 }
 
 impl Tsc {
@@ -75,6 +87,7 @@ impl Tsc {
             data_out: 0,
             x_pos: 0,
             y_pos: 0,
+            pressure: 0xFF,
         }
     }
 
@@ -108,6 +121,38 @@ impl Tsc {
         self.y_pos = 0xFFF;
     }
 
+    #[inline]
+    pub fn pressure(&self) -> u8 {
+        self.pressure
+    }
+
+    /// Sets the pressure reported through the Z1/Z2 channels while the pen is down, from 0 (the
+    /// lightest touch) to 255 (the firmest one).
+    #[inline]
+    pub fn set_pressure(&mut self, value: u8) {
+        self.pressure = value;
+    }
+
+    fn z_pos(&self) -> (u16, u16) {
+        if !self.pen_down {
+            return (0, 0xFFF);
+        }
+        // The touch resistance is calculated as R_x * X / 4096 * (Z2 / Z1 - 1), so pick Z2 to make
+        // it inversely proportional to the pressure (between R_x / 256 and R_x) regardless of X
+        const Z1: u32 = 0x400;
+        let resistance = (0x100 - self.pressure as u32) << 4;
+        let z2 = Z1 + Z1 * resistance / (self.x_pos as u32).max(1);
+        (Z1 as u16, z2.min(0xFFF) as u16)
+    }
+
+    fn touch_reading(&self, value: u16, noise: &mut Noise) -> u16 {
+        if self.pen_down {
+            add_noise(value, TOUCH_NOISE_AMPLITUDE, noise)
+        } else {
+            value
+        }
+    }
+
     #[inline]
     pub fn pen_down(&self) -> bool {
         self.pen_down
@@ -134,6 +179,7 @@ impl Tsc {
         time: Timestamp,
         _power: &Power,
         input_status: &mut input::Status,
+        noise: &mut Noise,
     ) -> u16 {
         if value.power_down_mode() & 1 == 0 {
             input_status.set_pen_down(!self.pen_down);
@@ -157,7 +203,7 @@ impl Tsc {
                 }
                 0xFFF
             }
-            1 => self.y_pos,
+            1 => self.touch_reading(self.y_pos, noise),
             2 => {
                 #[cfg(feature = "log")]
                 if !value.single_ended_mode() {
@@ -168,23 +214,9 @@ impl Tsc {
                 }
                 0xFFF
             }
-            3 => {
-                #[cfg(feature = "log")]
-                slog::debug!(
-                    self.logger,
-                    "Reading from unimplemented channel 3 (Z1-position)"
-                );
-                0xFFF
-            }
-            4 => {
-                #[cfg(feature = "log")]
-                slog::debug!(
-                    self.logger,
-                    "Reading from unimplemented channel 4 (Z2-position)"
-                );
-                0xFFF
-            }
-            5 => self.x_pos,
+            3 => self.touch_reading(self.z_pos().0, noise),
+            4 => self.touch_reading(self.z_pos().1, noise),
+            5 => self.touch_reading(self.x_pos, noise),
             6 => {
                 if value.single_ended_mode() {
                     let sample = if let Some(mic_data) = &mut self.mic_data {
@@ -210,7 +242,11 @@ impl Tsc {
                     //         )
                     //         .clamp(-0x8000, 0x7FFF) as i16;
                     // }
-                    (sample as u16).wrapping_add(0x8000) >> 4
+                    add_noise(
+                        (sample as u16).wrapping_add(0x8000) >> 4,
+                        MIC_NOISE_AMPLITUDE,
+                        noise,
+                    )
                 } else {
                     if !self.is_ds_lite {
                         #[cfg(feature = "log")]
@@ -252,6 +288,7 @@ impl Tsc {
         time: Timestamp,
         power: &Power,
         input_status: &mut input::Status,
+        noise: &mut Noise,
     ) -> u8 {
         if is_first {
             self.pos = 0;
@@ -260,7 +297,7 @@ impl Tsc {
             if ControlByte(value).start() {
                 self.pos = 1;
                 self.data_out =
-                    self.handle_control_byte(ControlByte(value), time, power, input_status);
+                    self.handle_control_byte(ControlByte(value), time, power, input_status, noise);
             }
             0
         } else {
@@ -269,8 +306,13 @@ impl Tsc {
             if self.pos == 2 {
                 if ControlByte(value).start() {
                     self.pos = 1;
-                    self.data_out =
-                        self.handle_control_byte(ControlByte(value), time, power, input_status);
+                    self.data_out = self.handle_control_byte(
+                        ControlByte(value),
+                        time,
+                        power,
+                        input_status,
+                        noise,
+                    );
                 }
             } else {
                 self.pos = 2;
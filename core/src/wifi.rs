@@ -1,12 +1,38 @@
 mod io;
 
 use crate::utils::{zeroed_box, Bytes, Savestate};
+#[cfg(feature = "link")]
+use crate::{
+    cpu::{self, arm7::Arm7},
+    emu::{Emu, Timestamp},
+    utils::mem_prelude::*,
+};
+#[cfg(feature = "link")]
+use std::collections::VecDeque;
+
+/// A raw 802.11 frame exchanged between consoles connected through a [`Link`].
+///
+/// [`Link`]: crate::emu::link::Link
+#[cfg(feature = "link")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// The time the frame was sent at, in the sender's ARM7 cycles.
+    pub time: Timestamp,
+    pub data: Vec<u8>,
+}
 
 #[derive(Savestate)]
+#[load(in_place_only)]
 pub struct WiFi {
     pub mmio: Box<Bytes<0x1000>>,
     pub ram: Box<Bytes<0x2000>>,
     bb_regs: [u8; 0x100],
+    #[cfg(feature = "link")]
+    #[savestate(skip)]
+    tx_frames: VecDeque<Frame>,
+    #[cfg(feature = "link")]
+    #[savestate(skip)]
+    rx_frames: VecDeque<Frame>,
 }
 
 impl WiFi {
@@ -24,6 +50,128 @@ impl WiFi {
             mmio,
             ram: zeroed_box(),
             bb_regs,
+            #[cfg(feature = "link")]
+            tx_frames: VecDeque::new(),
+            #[cfg(feature = "link")]
+            rx_frames: VecDeque::new(),
+        }
+    }
+
+    /// Queues a frame to be delivered to all other consoles connected to the same link.
+    #[cfg(feature = "link")]
+    pub fn queue_tx_frame(&mut self, frame: Frame) {
+        self.tx_frames.push_back(frame);
+    }
+
+    #[cfg(feature = "link")]
+    pub(crate) fn take_tx_frames(&mut self) -> impl Iterator<Item = Frame> + '_ {
+        self.tx_frames.drain(..)
+    }
+
+    #[cfg(feature = "link")]
+    pub(crate) fn push_rx_frame(&mut self, frame: Frame) {
+        self.rx_frames.push_back(frame);
+    }
+
+    #[cfg(feature = "link")]
+    pub(crate) fn pop_rx_frame(&mut self) -> Option<Frame> {
+        self.rx_frames.pop_front()
+    }
+}
+
+// The MAC is only emulated as far as needed to exchange frames through a link: frames are sent and
+// received at the end of each quantum, without any timing, retransmission or acknowledgement
+// handling, and the CMD slot is treated like the other TX slots.
+// TODO: Emulate the MAC outside of links, and the CMD slot's multiplayer replies.
+#[cfg(feature = "link")]
+impl WiFi {
+    fn mmio_16(&self, addr: usize) -> u16 {
+        self.mmio.read_le(addr)
+    }
+
+    fn set_mmio_16(&mut self, addr: usize, value: u16) {
+        self.mmio.write_le(addr, value);
+    }
+
+    fn request_irq(&mut self, mask: u16, arm7: &mut Arm7<impl cpu::Engine>) {
+        let r#if = self.mmio_16(io::IF) | mask;
+        self.set_mmio_16(io::IF, r#if);
+        if r#if & self.mmio_16(io::IE) != 0 {
+            arm7.irqs
+                .write_requested(arm7.irqs.requested().with_wifi(true), &mut arm7.schedule);
+        }
+    }
+
+    /// Sends the frames in all enabled TX slots whose transmission was requested through
+    /// `W_TXREQ_SET`, queueing them to be delivered through the link.
+    pub(crate) fn transmit(emu: &mut Emu<impl cpu::Engine>) {
+        let time = emu.schedule.cur_time();
+        let wifi = &mut emu.wifi;
+        let mut sent = false;
+        // Slots in order of priority, along with their bit in `W_TXREQ_READ`
+        for (loc_addr, req_bit) in [
+            (io::TX_BUF_LOC3, 3),
+            (io::TX_BUF_LOC2, 2),
+            (io::TX_BUF_CMD, 1),
+            (io::TX_BUF_LOC1, 0),
+        ] {
+            let loc = wifi.mmio_16(loc_addr);
+            if wifi.mmio_16(io::TX_REQ_READ) & 1 << req_bit == 0 || loc & 0x8000 == 0 {
+                continue;
+            }
+            let header_addr = (loc as usize & 0xFFF) << 1;
+            // The length includes the 4-byte FCS, which is generated by the hardware
+            let len: u16 = wifi.ram.read_le((header_addr + 0xA) & 0x1FFE);
+            let len = (len as usize).saturating_sub(4);
+            let data = (0..len)
+                .map(|i| wifi.ram[(header_addr + 0xC + i) & 0x1FFF])
+                .collect();
+            wifi.queue_tx_frame(Frame { time, data });
+            wifi.ram.write_le(header_addr, 1_u16);
+            wifi.set_mmio_16(loc_addr, loc & 0x7FFF);
+            sent = true;
+        }
+        if sent {
+            wifi.set_mmio_16(io::TX_STAT, 1);
+            wifi.request_irq(1 << 1, &mut emu.arm7);
+        }
+    }
+
+    /// Writes all frames received from the link to the RX circular buffer if reception is
+    /// enabled in `W_RXCNT`, dropping them otherwise.
+    pub(crate) fn receive(emu: &mut Emu<impl cpu::Engine>) {
+        let wifi = &mut emu.wifi;
+        let mut received = false;
+        while let Some(frame) = wifi.pop_rx_frame() {
+            let begin = wifi.mmio_16(io::RX_BUF_BEGIN) as usize & 0x1FFE;
+            let end = wifi.mmio_16(io::RX_BUF_END) as usize & 0x1FFE;
+            if wifi.mmio_16(io::RX_CNT) & 0x8000 == 0 || begin >= end {
+                continue;
+            }
+            let mut cur_addr = (wifi.mmio_16(io::RX_BUF_WR_CSR) as usize & 0xFFF) << 1;
+            if !(begin..end).contains(&cur_addr) {
+                cur_addr = begin;
+            }
+            let buf_len = end - begin;
+            let len = 0xC + frame.data.len();
+            if (len + 3) & !3 >= buf_len {
+                continue;
+            }
+
+            // The header's flags aren't derived from the frame's contents; the rate is always
+            // reported as 2 Mbit/s
+            let [len_low, len_high] = (frame.data.len() as u16).to_le_bytes();
+            let header = [0x10, 0, 0, 0, 0, 0, 0x14, 0, len_low, len_high, 0, 0];
+            for (i, byte) in header.into_iter().chain(frame.data).enumerate() {
+                wifi.ram[begin + (cur_addr - begin + i) % buf_len] = byte;
+            }
+
+            cur_addr = begin + (cur_addr - begin + ((len + 3) & !3)) % buf_len;
+            wifi.set_mmio_16(io::RX_BUF_WR_CSR, (cur_addr >> 1) as u16);
+            received = true;
+        }
+        if received {
+            wifi.request_irq(1 << 0, &mut emu.arm7);
         }
     }
 }
@@ -1,6 +1,31 @@
 use super::WiFi;
 use crate::{cpu::bus::AccessType, utils::mem_prelude::*};
 
+pub(super) const IF: usize = 0x010;
+#[cfg(feature = "link")]
+pub(super) const IE: usize = 0x012;
+#[cfg(feature = "link")]
+pub(super) const RX_CNT: usize = 0x030;
+#[cfg(feature = "link")]
+pub(super) const RX_BUF_BEGIN: usize = 0x050;
+#[cfg(feature = "link")]
+pub(super) const RX_BUF_END: usize = 0x052;
+#[cfg(feature = "link")]
+pub(super) const RX_BUF_WR_CSR: usize = 0x054;
+#[cfg(feature = "link")]
+pub(super) const TX_BUF_CMD: usize = 0x090;
+#[cfg(feature = "link")]
+pub(super) const TX_BUF_LOC1: usize = 0x0A0;
+#[cfg(feature = "link")]
+pub(super) const TX_BUF_LOC2: usize = 0x0A4;
+#[cfg(feature = "link")]
+pub(super) const TX_BUF_LOC3: usize = 0x0A8;
+pub(super) const TX_REQ_RESET: usize = 0x0AC;
+pub(super) const TX_REQ_SET: usize = 0x0AE;
+pub(super) const TX_REQ_READ: usize = 0x0B0;
+#[cfg(feature = "link")]
+pub(super) const TX_STAT: usize = 0x0B8;
+
 #[allow(clippy::extra_unused_type_parameters)]
 impl WiFi {
     fn read_io<A: AccessType>(&mut self, addr: u16) -> u8 {
@@ -10,9 +35,27 @@ impl WiFi {
     fn write_io<A: AccessType>(&mut self, mut addr: u16, value: u8) {
         addr &= 0xFFF;
         #[allow(clippy::match_same_arms)]
-        match addr {
+        match addr as usize {
+            // Writing 1 to an interrupt flag acknowledges it
+            IF | 0x011 => {
+                self.mmio[addr as usize] &= !value;
+                return;
+            }
+
             0x03D => return,
 
+            TX_REQ_RESET | 0x0AD => {
+                self.mmio[TX_REQ_READ | (addr & 1) as usize] &= !value;
+                return;
+            }
+
+            TX_REQ_SET | 0x0AF => {
+                self.mmio[TX_REQ_READ | (addr & 1) as usize] |= value;
+                return;
+            }
+
+            TX_REQ_READ | 0x0B1 => return,
+
             0x159 => {
                 let index = self.mmio[0x158];
                 match value >> 4 {
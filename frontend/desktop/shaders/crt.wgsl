@@ -0,0 +1,19 @@
+// Example post-processing shader imitating a CRT, with horizontal scanlines and a slight
+// horizontal blur; copy it to the shader directory to use it.
+
+//! scale: 4
+
+const SCANLINE_DARKNESS: f32 = 0.4;
+
+@fragment
+fn fs_main(in: VertOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / params.source_size;
+    let color = textureSample(t_source, s_linear, in.uv) * 0.5
+        + textureSample(t_source, s_nearest, in.uv - vec2<f32>(texel.x, 0.0)) * 0.25
+        + textureSample(t_source, s_nearest, in.uv + vec2<f32>(texel.x, 0.0)) * 0.25;
+
+    // Darken the space between source lines, following a cosine so the lines stay smooth
+    let line_pos = fract(in.uv.y * params.source_size.y);
+    let scanline = 1.0 - SCANLINE_DARKNESS * (0.5 - 0.5 * cos(line_pos * 6.2831853));
+    return vec4<f32>(color.rgb * scanline, 1.0);
+}
@@ -0,0 +1,19 @@
+// Example post-processing shader approximating the colors of the DS's LCDs, which are darker and
+// less saturated than modern displays; copy it to the shader directory to use it.
+
+// How strongly to apply the color profile, from 0 (no change) to 1
+const STRENGTH: f32 = 1.0;
+const GAMMA: f32 = 1.4;
+
+@fragment
+fn fs_main(in: VertOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_source, s_nearest, in.uv).rgb;
+    let linear = pow(color, vec3<f32>(GAMMA));
+    let corrected = mat3x3<f32>(
+        vec3<f32>(0.80, 0.10, 0.10),
+        vec3<f32>(0.15, 0.75, 0.15),
+        vec3<f32>(0.05, 0.15, 0.75),
+    ) * linear;
+    let result = pow(corrected, vec3<f32>(1.0 / 2.2));
+    return vec4<f32>(mix(color, result, STRENGTH), 1.0);
+}
@@ -1,5 +1,7 @@
 mod cpal;
 pub use self::cpal::*;
+mod profile;
+pub use profile::OutputProfile;
 
 use super::{InterpMethod, SYS_CLOCK_RATE};
 use dust_core::audio::OutputSample;
@@ -10,14 +12,30 @@ use parking_lot::RwLock;
 use std::num::NonZeroU32;
 use std::{
     marker::PhantomData,
-    sync::atomic::{AtomicUsize, Ordering},
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
     sync::Arc,
     thread::{self, Thread},
+    time::{Duration, Instant},
 };
 
 pub const DEFAULT_INPUT_SAMPLE_RATE: u32 = SYS_CLOCK_RATE >> 10;
 
-const BUFFER_BASE_CAPACITY: usize = 0x800;
+pub const MIN_BUFFER_LEN: usize = 0x200;
+pub const MAX_BUFFER_LEN: usize = 0x4000;
+
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Counters for the times the output buffer ran empty or full, which are heard as gaps or skips
+/// respectively.
+#[derive(Default)]
+pub struct Stats {
+    /// How many times the output device asked for more samples than were buffered (because
+    /// emulation was either running too slowly or paused).
+    pub underruns: AtomicU32,
+    /// How many times samples had to be discarded because the buffer was full; this only happens
+    /// when not syncing to audio.
+    pub overruns: AtomicU32,
+}
 
 struct Buffer {
     read_pos: AtomicUsize,
@@ -32,17 +50,18 @@ unsafe impl Sync for Buffer {}
 impl Buffer {
     fn new_arc(
         thread: Thread,
+        base_capacity: usize,
         #[cfg(feature = "xq-audio")] custom_sample_rate: Option<NonZeroU32>,
     ) -> Arc<Self> {
         #[cfg(not(feature = "xq-audio"))]
-        let capacity = BUFFER_BASE_CAPACITY;
+        let capacity = base_capacity;
         #[cfg(feature = "xq-audio")]
         let capacity = match custom_sample_rate {
             Some(sample_rate) => {
-                BUFFER_BASE_CAPACITY
+                base_capacity
                     * ((sample_rate.get() / DEFAULT_INPUT_SAMPLE_RATE) as usize).next_power_of_two()
             }
-            None => BUFFER_BASE_CAPACITY,
+            None => base_capacity,
         };
 
         Arc::new(Buffer {
@@ -52,6 +71,14 @@ impl Buffer {
             thread: Mutex::new(thread),
         })
     }
+
+    fn buffered_samples(&self) -> usize {
+        self.write_pos
+            .load(Ordering::Relaxed)
+            .wrapping_sub(self.read_pos.load(Ordering::Relaxed))
+            .wrapping_sub(1)
+            & (self.data.len() - 1)
+    }
 }
 
 impl Drop for Buffer {
@@ -66,12 +93,28 @@ pub struct SenderData {
     buffer_ptr: Arc<RwLock<Arc<Buffer>>>,
     #[cfg(not(feature = "xq-audio"))]
     buffer: Arc<Buffer>,
+    stats: Arc<Stats>,
+}
+
+impl SenderData {
+    fn receiver(&self) -> Receiver {
+        Receiver {
+            #[cfg(feature = "xq-audio")]
+            buffer_ptr: Arc::clone(&self.buffer_ptr),
+            #[cfg(feature = "xq-audio")]
+            buffer: Arc::clone(&self.buffer_ptr.read()),
+            #[cfg(not(feature = "xq-audio"))]
+            buffer: Arc::clone(&self.buffer),
+            stats: Arc::clone(&self.stats),
+        }
+    }
 }
 
 pub struct Sender {
     #[cfg(feature = "xq-audio")]
     buffer_ptr: Arc<RwLock<Arc<Buffer>>>,
     buffer: Arc<Buffer>,
+    stats: Arc<Stats>,
     write_pos: usize,
     sync: bool,
     _not_send: PhantomData<*const ()>,
@@ -89,6 +132,7 @@ impl Sender {
             buffer_ptr: Arc::clone(&data.buffer_ptr),
             write_pos: buffer.write_pos.load(Ordering::Relaxed),
             buffer,
+            stats: Arc::clone(&data.stats),
             sync,
             _not_send: PhantomData,
         }
@@ -99,7 +143,7 @@ impl dust_core::audio::Backend for Sender {
     fn handle_sample_chunk(&mut self, samples: &mut Vec<[OutputSample; 2]>) {
         while !samples.is_empty() {
             #[cfg(not(feature = "xq-audio"))]
-            let buffer_mask = BUFFER_BASE_CAPACITY - 1;
+            let buffer_mask = self.buffer.data.len() - 1;
             #[cfg(feature = "xq-audio")]
             let mut buffer_mask = {
                 let buffer = self.buffer_ptr.read();
@@ -138,17 +182,20 @@ impl dust_core::audio::Backend for Sender {
             } else {
                 // Overwrite the oldest samples, attempt to move the read position to the start of the
                 // oldest remaining ones
-                let _ = self.buffer.read_pos.fetch_update(
-                    Ordering::Relaxed,
-                    Ordering::Relaxed,
-                    |read_pos| {
+                if self
+                    .buffer
+                    .read_pos
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |read_pos| {
                         if read_pos.wrapping_sub(self.write_pos) & buffer_mask <= len {
                             Some((self.write_pos + len + 1) & buffer_mask)
                         } else {
                             None
                         }
-                    },
-                );
+                    })
+                    .is_ok()
+                {
+                    self.stats.overruns.fetch_add(1, Ordering::Relaxed);
+                }
             }
             for sample in samples.drain(..len) {
                 unsafe {
@@ -167,6 +214,7 @@ struct Receiver {
     #[cfg(feature = "xq-audio")]
     buffer_ptr: Arc<RwLock<Arc<Buffer>>>,
     buffer: Arc<Buffer>,
+    stats: Arc<Stats>,
 }
 
 impl Receiver {
@@ -181,9 +229,6 @@ impl Receiver {
     }
 
     fn read_sample(&mut self) -> Option<[f64; 2]> {
-        #[cfg(not(feature = "xq-audio"))]
-        let buffer_mask = BUFFER_BASE_CAPACITY - 1;
-        #[cfg(feature = "xq-audio")]
         let buffer_mask = self.buffer.data.len() - 1;
 
         if let Ok(read_pos) =
@@ -218,52 +263,158 @@ impl Receiver {
     fn finish_reading(&mut self) {
         self.buffer.thread.lock().unpark();
     }
+
+    fn underrun(&self) {
+        self.stats.underruns.fetch_add(1, Ordering::Relaxed);
+    }
 }
 
 pub struct Channel {
     pub tx_data: SenderData,
     pub output_stream: OutputStream,
+    buffer_len: usize,
+    latency_ms: u16,
+    device_config: DeviceConfig,
+    #[cfg(feature = "xq-audio")]
+    custom_sample_rate: Option<NonZeroU32>,
+    last_reconnect_attempt: Option<Instant>,
 }
 
 impl Channel {
+    /// Creates an output channel playing on the device described by `device_config`, whose buffer
+    /// holds `buffer_len` samples at the default sample rate (rounded up to a power of two), and
+    /// which asks the output device to request samples every `latency_ms` milliseconds (or as
+    /// often as it prefers, if 0).
     pub fn new(
+        device_config: DeviceConfig,
         interp_method: InterpMethod,
         volume: f32,
+        profile: OutputProfile,
+        buffer_len: usize,
+        latency_ms: u16,
         #[cfg(feature = "xq-audio")] custom_sample_rate: Option<NonZeroU32>,
     ) -> Option<Self> {
+        let buffer_len = buffer_len
+            .clamp(MIN_BUFFER_LEN, MAX_BUFFER_LEN)
+            .next_power_of_two();
         let buffer = Buffer::new_arc(
             thread::current(),
+            buffer_len,
             #[cfg(feature = "xq-audio")]
             custom_sample_rate,
         );
         #[cfg(feature = "xq-audio")]
         let buffer_ptr = Arc::new(RwLock::new(Arc::clone(&buffer)));
+        let stats = Arc::new(Stats::default());
+        let tx_data = SenderData {
+            #[cfg(feature = "xq-audio")]
+            buffer_ptr,
+            #[cfg(not(feature = "xq-audio"))]
+            buffer,
+            stats,
+        };
+        let output_stream = OutputStream::new(
+            tx_data.receiver(),
+            &device_config,
+            interp_method,
+            volume,
+            profile,
+            latency_ms,
+            #[cfg(feature = "xq-audio")]
+            custom_sample_rate,
+        )
+        .map_err(|err| error!("Audio output error", "Couldn't set up audio output: {err}"))
+        .ok()?;
         Some(Channel {
-            tx_data: SenderData {
-                #[cfg(feature = "xq-audio")]
-                buffer_ptr: Arc::clone(&buffer_ptr),
-                #[cfg(not(feature = "xq-audio"))]
-                buffer: Arc::clone(&buffer),
-            },
-            output_stream: OutputStream::new(
-                Receiver {
-                    #[cfg(feature = "xq-audio")]
-                    buffer_ptr,
-                    buffer,
-                },
-                interp_method,
-                volume,
-                #[cfg(feature = "xq-audio")]
-                custom_sample_rate,
-            )?,
+            tx_data,
+            output_stream,
+            buffer_len,
+            latency_ms,
+            device_config,
+            #[cfg(feature = "xq-audio")]
+            custom_sample_rate,
+            last_reconnect_attempt: None,
         })
     }
 
+    fn recreate_output_stream(&mut self) -> Result<(), Error> {
+        self.output_stream = OutputStream::new(
+            self.tx_data.receiver(),
+            &self.device_config,
+            self.output_stream.interp_method(),
+            self.output_stream.volume(),
+            self.output_stream.profile(),
+            self.latency_ms,
+            #[cfg(feature = "xq-audio")]
+            self.custom_sample_rate,
+        )?;
+        Ok(())
+    }
+
+    /// Switches playback to a different output device or sample rate, keeping any samples that
+    /// are already buffered; if the new device can't be set up, the current one is kept.
+    pub fn set_device_config(&mut self, device_config: DeviceConfig) -> Result<(), Error> {
+        if device_config == self.device_config {
+            return Ok(());
+        }
+        let prev_device_config = std::mem::replace(&mut self.device_config, device_config);
+        self.recreate_output_stream().inspect_err(|_| {
+            self.device_config = prev_device_config;
+        })
+    }
+
+    /// If the output stream stopped working (usually because its device was disconnected), tries
+    /// to recreate it on the same device (or the default one, if it's gone), at most once every
+    /// [`RECONNECT_INTERVAL`]; returns whether the stream was recreated.
+    pub fn reconnect_if_disconnected(&mut self) -> bool {
+        if !self.output_stream.is_disconnected() {
+            return false;
+        }
+        let now = Instant::now();
+        if self
+            .last_reconnect_attempt
+            .is_some_and(|last_attempt| now - last_attempt < RECONNECT_INTERVAL)
+        {
+            return false;
+        }
+        self.last_reconnect_attempt = Some(now);
+        self.recreate_output_stream().is_ok()
+    }
+
+    /// Returns whether the channel was created with the given buffer length and latency, i.e.
+    /// whether it needs to be recreated for them to apply.
+    pub fn matches_buffer_config(&self, buffer_len: usize, latency_ms: u16) -> bool {
+        buffer_len
+            .clamp(MIN_BUFFER_LEN, MAX_BUFFER_LEN)
+            .next_power_of_two()
+            == self.buffer_len
+            && latency_ms == self.latency_ms
+    }
+
+    #[inline]
+    pub fn stats(&self) -> &Stats {
+        &self.tx_data.stats
+    }
+
+    /// Returns how many samples are currently buffered, and how many the buffer can hold.
+    pub fn buffer_fill(&self) -> (usize, usize) {
+        #[cfg(feature = "xq-audio")]
+        let buffer = self.tx_data.buffer_ptr.read();
+        #[cfg(not(feature = "xq-audio"))]
+        let buffer = &self.tx_data.buffer;
+        (buffer.buffered_samples(), buffer.data.len())
+    }
+
     #[cfg(feature = "xq-audio")]
     pub fn set_custom_sample_rate(&mut self, custom_sample_rate: Option<NonZeroU32>) {
         let mut buffer = self.tx_data.buffer_ptr.write();
-        let new_buffer = Buffer::new_arc(buffer.thread.lock().clone(), custom_sample_rate);
+        let new_buffer = Buffer::new_arc(
+            buffer.thread.lock().clone(),
+            self.buffer_len,
+            custom_sample_rate,
+        );
         *buffer = new_buffer;
+        self.custom_sample_rate = custom_sample_rate;
         self.output_stream
             .set_custom_sample_rate(custom_sample_rate);
     }
@@ -1,27 +1,140 @@
 use super::{
     super::{Interp, InterpMethod, SAMPLE_RATE_ADJUSTMENT_RATIO},
+    profile::{OutputProfile, ProfileFilter},
     Receiver, DEFAULT_INPUT_SAMPLE_RATE,
 };
 use cpal::{
     default_host,
-    platform::Stream,
+    platform::{Device, Host, Stream},
     traits::{DeviceTrait, HostTrait, StreamTrait},
-    Sample, SampleFormat, SupportedStreamConfigRange,
+    BufferSize, BuildStreamError, PlayStreamError, Sample, SampleFormat, SampleRate,
+    SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
+    SupportedStreamConfigsError,
 };
 use std::{
-    iter,
+    fmt, iter,
     sync::{
-        atomic::{AtomicU32, Ordering},
+        atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering},
         Arc,
     },
 };
 #[cfg(feature = "xq-audio")]
 use std::{num::NonZeroU32, sync::atomic::AtomicU64};
 
+// How long it takes to fade the output in or out when samples stop or start coming in (i.e. when
+// pausing, resuming or advancing frames), to avoid pops
+const FADE_DURATION_SECS: f32 = 0.005;
+
+// The sample rate to pick when the device doesn't report a usable default one, if supported
+const PREFERRED_SAMPLE_RATE: u32 = 48000;
+
+/// Which output device to play audio on, and at which sample rate.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeviceConfig {
+    /// The name of the output device, or `None` to use the system's default one; if no device
+    /// with the given name exists, the default one is used instead.
+    pub name: Option<String>,
+    /// The sample rate to run the output device at, or `None` to pick one automatically.
+    pub sample_rate: Option<u32>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    NoDevice,
+    NoSupportedConfig,
+    UnsupportedSampleFormat(SampleFormat),
+    SupportedConfigs(SupportedStreamConfigsError),
+    BuildStream(BuildStreamError),
+    PlayStream(PlayStreamError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoDevice => f.write_str("no audio output device available"),
+            Error::NoSupportedConfig => {
+                f.write_str("the audio output device doesn't support stereo output")
+            }
+            Error::UnsupportedSampleFormat(format) => {
+                write!(f, "unsupported audio output sample format: {format}")
+            }
+            Error::SupportedConfigs(err) => {
+                write!(
+                    f,
+                    "couldn't query the audio output device's configurations: {err}"
+                )
+            }
+            Error::BuildStream(err) => write!(f, "couldn't set up the audio output stream: {err}"),
+            Error::PlayStream(err) => write!(f, "couldn't start the audio output stream: {err}"),
+        }
+    }
+}
+
+/// Lists the names of all available audio output devices.
+pub fn output_device_names() -> Vec<String> {
+    default_host()
+        .output_devices()
+        .map(|devices| devices.filter_map(|device| device.name().ok()).collect())
+        .unwrap_or_default()
+}
+
+fn find_device(host: &Host, name: Option<&str>) -> Option<Device> {
+    if let Some(name) = name {
+        let device = host.output_devices().ok().and_then(|mut devices| {
+            devices.find(|device| device.name().ok().as_deref() == Some(name))
+        });
+        if device.is_some() {
+            return device;
+        }
+    }
+    host.default_output_device()
+}
+
+fn stream_config(
+    device: &Device,
+    sample_rate: Option<u32>,
+) -> Result<SupportedStreamConfig, Error> {
+    let configs = device
+        .supported_output_configs()
+        .map_err(Error::SupportedConfigs)?
+        .filter(|config| config.channels() == 2)
+        .collect::<Vec<_>>();
+
+    if let Some(sample_rate) = sample_rate {
+        if let Some(config) = configs
+            .iter()
+            .filter(|config| {
+                (config.min_sample_rate().0..=config.max_sample_rate().0).contains(&sample_rate)
+            })
+            .max_by(|a, b| a.cmp_default_heuristics(b))
+        {
+            return Ok(config.clone().with_sample_rate(SampleRate(sample_rate)));
+        }
+    }
+
+    // Prefer the device's default configuration, as the highest supported sample rate is often
+    // unusable (e.g. PipeWire reports rates that it can't actually sustain)
+    if let Ok(config) = device.default_output_config() {
+        if config.channels() == 2 {
+            return Ok(config);
+        }
+    }
+
+    let config = configs
+        .into_iter()
+        .max_by(SupportedStreamConfigRange::cmp_default_heuristics)
+        .ok_or(Error::NoSupportedConfig)?;
+    let sample_rate =
+        PREFERRED_SAMPLE_RATE.clamp(config.min_sample_rate().0, config.max_sample_rate().0);
+    Ok(config.with_sample_rate(SampleRate(sample_rate)))
+}
+
 struct SharedData {
     volume: AtomicU32,
+    profile: AtomicU8,
     #[cfg(feature = "xq-audio")]
     sample_rate_ratio: AtomicU64,
+    disconnected: AtomicBool,
 }
 
 pub struct OutputStream {
@@ -44,52 +157,66 @@ fn sample_rate_ratio(custom_sample_rate: Option<NonZeroU32>, output_sample_rate:
 impl OutputStream {
     pub(super) fn new(
         rx: Receiver,
+        device_config: &DeviceConfig,
         interp_method: InterpMethod,
         volume: f32,
+        profile: OutputProfile,
+        latency_ms: u16,
         #[cfg(feature = "xq-audio")] custom_sample_rate: Option<NonZeroU32>,
-    ) -> Option<Self> {
-        let output_device = default_host().default_output_device()?;
-        let supported_output_config = output_device
-            .supported_output_configs()
-            .map_err(|e| {
-                error!(
-                    "Audio output error",
-                    "Error setting up audio output device: {e}"
-                );
-            })
-            .ok()?
-            .filter(|config| config.channels() == 2)
-            .max_by(SupportedStreamConfigRange::cmp_default_heuristics)?
-            .with_max_sample_rate();
+    ) -> Result<Self, Error> {
+        let output_device =
+            find_device(&default_host(), device_config.name.as_deref()).ok_or(Error::NoDevice)?;
+        let supported_output_config = stream_config(&output_device, device_config.sample_rate)?;
 
         let output_sample_rate = supported_output_config.sample_rate().0;
 
+        let mut stream_config = supported_output_config.config();
+        if latency_ms != 0 {
+            let frames = (output_sample_rate as u64 * latency_ms as u64 / 1000) as u32;
+            stream_config.buffer_size = match *supported_output_config.buffer_size() {
+                SupportedBufferSize::Range { min, max } => {
+                    BufferSize::Fixed(frames.clamp(min, max))
+                }
+                SupportedBufferSize::Unknown => BufferSize::Fixed(frames),
+            };
+        }
+
         let (interp_tx, interp_rx) = crossbeam_channel::unbounded();
         let shared_data = Arc::new(SharedData {
             volume: AtomicU32::new(volume.to_bits()),
+            profile: AtomicU8::new(profile as u8),
             #[cfg(feature = "xq-audio")]
             sample_rate_ratio: AtomicU64::new(
                 sample_rate_ratio(custom_sample_rate, output_sample_rate).to_bits(),
             ),
+            disconnected: AtomicBool::new(false),
         });
 
         let mut output_data = OutputData {
             rx,
             interp_rx,
             interp: interp_method.create_interp(),
+            profile_filter: ProfileFilter::new(profile, output_sample_rate),
+            output_sample_rate,
             shared_data: Arc::clone(&shared_data),
             #[cfg(not(feature = "xq-audio"))]
             sample_rate_ratio: DEFAULT_INPUT_SAMPLE_RATE as f64 * SAMPLE_RATE_ADJUSTMENT_RATIO
                 / output_sample_rate as f64,
             fract: 0.0,
+            gain: 0.0,
+            starved: false,
+            gain_step: 1.0 / (output_sample_rate as f32 * FADE_DURATION_SECS),
         };
 
-        let err_callback = |err| panic!("Error in default audio output device stream: {err}");
+        // Errors can't be recovered from within the stream (they usually mean the device was
+        // disconnected), so they're just flagged for the stream to be recreated
+        let err_shared_data = Arc::clone(&shared_data);
+        let err_callback = move |_| err_shared_data.disconnected.store(true, Ordering::Relaxed);
 
         macro_rules! build_output_stream {
             ($t: ty) => {
                 output_device.build_output_stream(
-                    &supported_output_config.config(),
+                    &stream_config,
                     move |data: &mut [$t], _| output_data.fill(data),
                     err_callback,
                     None,
@@ -108,26 +235,12 @@ impl OutputStream {
             SampleFormat::I64 => build_output_stream!(i64),
             SampleFormat::F32 => build_output_stream!(f32),
             SampleFormat::F64 => build_output_stream!(f64),
-            _ => panic!("Unsupported audio output sample format"),
+            sample_format => return Err(Error::UnsupportedSampleFormat(sample_format)),
         }
-        .map_err(|e| {
-            error!(
-                "Audio output error",
-                "Error setting up audio output stream: {e}"
-            );
-        })
-        .ok()?;
-        stream
-            .play()
-            .map_err(|e| {
-                error!(
-                    "Audio output error",
-                    "Error starting audio output stream: {e}"
-                );
-            })
-            .ok()?;
+        .map_err(Error::BuildStream)?;
+        stream.play().map_err(Error::PlayStream)?;
 
-        Some(OutputStream {
+        Ok(OutputStream {
             _stream: stream,
             interp_method,
             interp_tx,
@@ -141,11 +254,17 @@ impl OutputStream {
         if value == self.interp_method {
             return;
         }
+        self.interp_method = value;
         self.interp_tx
             .send(value.create_interp())
             .expect("couldn't send new interpolator to audio output thread");
     }
 
+    #[inline]
+    pub fn interp_method(&self) -> InterpMethod {
+        self.interp_method
+    }
+
     #[cfg(feature = "xq-audio")]
     pub(super) fn set_custom_sample_rate(&mut self, value: Option<NonZeroU32>) {
         self.shared_data.sample_rate_ratio.store(
@@ -154,21 +273,47 @@ impl OutputStream {
         );
     }
 
+    pub fn volume(&self) -> f32 {
+        f32::from_bits(self.shared_data.volume.load(Ordering::Relaxed))
+    }
+
     pub fn set_volume(&mut self, volume: f32) {
         self.shared_data
             .volume
             .store(volume.to_bits(), Ordering::Relaxed);
     }
+
+    pub fn profile(&self) -> OutputProfile {
+        OutputProfile::from_u8(self.shared_data.profile.load(Ordering::Relaxed))
+    }
+
+    pub fn set_profile(&mut self, profile: OutputProfile) {
+        self.shared_data
+            .profile
+            .store(profile as u8, Ordering::Relaxed);
+    }
+
+    /// Returns whether the stream stopped working (usually because its device was disconnected)
+    /// and needs to be recreated.
+    pub fn is_disconnected(&self) -> bool {
+        self.shared_data.disconnected.load(Ordering::Relaxed)
+    }
 }
 
 struct OutputData {
     rx: Receiver,
     interp_rx: crossbeam_channel::Receiver<Box<dyn Interp<2>>>,
     interp: Box<dyn Interp<2>>,
+    profile_filter: ProfileFilter,
+    output_sample_rate: u32,
     shared_data: Arc<SharedData>,
     #[cfg(not(feature = "xq-audio"))]
     sample_rate_ratio: f64,
     fract: f64,
+    gain: f32,
+    gain_step: f32,
+    /// Whether the last call ran out of input samples, to only count consecutive underruns once.
+    starved: bool,
 }
 
 impl OutputData {
@@ -177,7 +322,14 @@ impl OutputData {
             self.interp = interp;
         }
 
+        let profile = OutputProfile::from_u8(self.shared_data.profile.load(Ordering::Relaxed));
+        if profile != self.profile_filter.profile() {
+            self.profile_filter
+                .set_profile(profile, self.output_sample_rate);
+        }
+
         let mut fract = self.fract;
+        let mut gain = self.gain;
         let mut output_i = 0;
         let mut volume = f32::from_bits(self.shared_data.volume.load(Ordering::Relaxed));
         volume *= volume;
@@ -191,16 +343,28 @@ impl OutputData {
             (((data.len()) >> 1) as f64 * sample_rate_ratio + fract).ceil() as usize;
 
         macro_rules! push_output_samples {
-            () => {
+            ($fade_in: expr) => {
                 while fract < 1.0 {
                     if output_i >= data.len() {
                         self.fract = fract;
+                        self.gain = gain;
+                        if $fade_in {
+                            self.starved = false;
+                        }
                         self.rx.finish_reading();
                         return;
                     }
+                    gain = if $fade_in {
+                        (gain + self.gain_step).min(1.0)
+                    } else {
+                        (gain - self.gain_step).max(0.0)
+                    };
                     let result = self.interp.get_output_sample(fract);
-                    data[output_i] = T::from_sample(result[0] as f32 * volume);
-                    data[output_i + 1] = T::from_sample(result[1] as f32 * volume);
+                    let result = self
+                        .profile_filter
+                        .process([result[0] as f32, result[1] as f32]);
+                    data[output_i] = T::from_sample(result[0] * volume * gain);
+                    data[output_i + 1] = T::from_sample(result[1] * volume * gain);
                     fract += sample_rate_ratio;
                     output_i += 2;
                 }
@@ -212,12 +376,18 @@ impl OutputData {
 
         for input_sample in iter::from_fn(|| self.rx.read_sample()).take(max_input_samples) {
             self.interp.push_input_sample(input_sample);
-            push_output_samples!();
+            push_output_samples!(true);
         }
 
+        // No samples are available (either because emulation is paused or because it's running too
+        // slowly), fade the last one out instead of abruptly cutting it off
+        if !self.starved {
+            self.starved = true;
+            self.rx.underrun();
+        }
         loop {
             self.interp.copy_last_input_sample();
-            push_output_samples!();
+            push_output_samples!(false);
         }
     }
 }
@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+/// The kind of output to imitate: the DS's headphone jack outputs a nearly flat signal, while its
+/// small built-in speakers can't reproduce low frequencies, roll off the highest ones and, being
+/// close together, blend the two channels somewhat.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputProfile {
+    Headphones,
+    Speakers,
+}
+
+impl OutputProfile {
+    pub(super) fn from_u8(value: u8) -> Self {
+        match value {
+            0 => OutputProfile::Headphones,
+            _ => OutputProfile::Speakers,
+        }
+    }
+}
+
+// Approximate response of the built-in speakers
+const SPEAKER_HIGH_PASS_CUTOFF: f32 = 400.0;
+const SPEAKER_LOW_PASS_CUTOFF: f32 = 9000.0;
+const SPEAKER_CROSSFEED: f32 = 0.3;
+// Makes up for the energy lost to the high-pass filter, so that switching profiles doesn't cause a
+// large change in perceived loudness
+const SPEAKER_GAIN: f32 = 1.25;
+
+#[derive(Clone, Copy)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    fn new(b: [f32; 3], a: [f32; 3]) -> Self {
+        Biquad {
+            b0: b[0] / a[0],
+            b1: b[1] / a[0],
+            b2: b[2] / a[0],
+            a1: a[1] / a[0],
+            a2: a[2] / a[0],
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    fn coefficients(sample_rate: f32, cutoff: f32) -> (f32, f32) {
+        let omega = 2.0 * PI * cutoff.min(sample_rate * 0.45) / sample_rate;
+        // Butterworth response (Q = 1/sqrt(2))
+        let alpha = omega.sin() * FRAC_1_SQRT_2;
+        (omega.cos(), alpha)
+    }
+
+    fn high_pass(sample_rate: f32, cutoff: f32) -> Self {
+        let (cos, alpha) = Self::coefficients(sample_rate, cutoff);
+        Self::new(
+            [(1.0 + cos) * 0.5, -(1.0 + cos), (1.0 + cos) * 0.5],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    fn low_pass(sample_rate: f32, cutoff: f32) -> Self {
+        let (cos, alpha) = Self::coefficients(sample_rate, cutoff);
+        Self::new(
+            [(1.0 - cos) * 0.5, 1.0 - cos, (1.0 - cos) * 0.5],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let output = self.b0 * input + self.z1;
+        self.z1 = self.b1 * input - self.a1 * output + self.z2;
+        self.z2 = self.b2 * input - self.a2 * output;
+        output
+    }
+}
+
+/// Applies an [`OutputProfile`] to samples at the output device's sample rate.
+pub(super) struct ProfileFilter {
+    profile: OutputProfile,
+    // Per-channel high-pass and low-pass stages, only used for the speaker profile
+    stages: [[Biquad; 2]; 2],
+}
+
+impl ProfileFilter {
+    pub fn new(profile: OutputProfile, sample_rate: u32) -> Self {
+        let sample_rate = sample_rate as f32;
+        let stages = [
+            Biquad::high_pass(sample_rate, SPEAKER_HIGH_PASS_CUTOFF),
+            Biquad::low_pass(sample_rate, SPEAKER_LOW_PASS_CUTOFF),
+        ];
+        ProfileFilter {
+            profile,
+            stages: [stages; 2],
+        }
+    }
+
+    #[inline]
+    pub fn profile(&self) -> OutputProfile {
+        self.profile
+    }
+
+    /// Changes the profile being applied, clearing the filters' history.
+    pub fn set_profile(&mut self, profile: OutputProfile, sample_rate: u32) {
+        *self = Self::new(profile, sample_rate);
+    }
+
+    pub fn process(&mut self, [l, r]: [f32; 2]) -> [f32; 2] {
+        match self.profile {
+            OutputProfile::Headphones => [l, r],
+            OutputProfile::Speakers => {
+                let mixed = [
+                    l + (r - l) * SPEAKER_CROSSFEED,
+                    r + (l - r) * SPEAKER_CROSSFEED,
+                ];
+                let [l_stages, r_stages] = &mut self.stages;
+                [(l_stages, mixed[0]), (r_stages, mixed[1])].map(|(stages, sample)| {
+                    stages
+                        .iter_mut()
+                        .fold(sample, |sample, stage| stage.process(sample))
+                        * SPEAKER_GAIN
+                })
+            }
+        }
+    }
+}
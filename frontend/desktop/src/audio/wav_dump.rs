@@ -0,0 +1,241 @@
+use dust_core::audio::{Backend as AudioBackend, ChannelAudioCaptureData, OutputSample};
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+// Individual channels are always sampled at the console's native rate
+const CHANNEL_SAMPLE_RATE: u32 = 32768;
+
+pub struct Settings {
+    pub output_path: PathBuf,
+    /// Whether to also dump each of the 16 channels to a separate file, next to the mixed output.
+    pub channels: bool,
+}
+
+#[derive(Clone, Copy)]
+enum SampleFormat {
+    Pcm16,
+    Float32,
+}
+
+impl SampleFormat {
+    fn bits_per_sample(self) -> u16 {
+        match self {
+            SampleFormat::Pcm16 => 16,
+            SampleFormat::Float32 => 32,
+        }
+    }
+}
+
+/// A WAV file whose header is completed once the total length of the audio data is known.
+struct WavWriter {
+    file: BufWriter<File>,
+    data_len: u32,
+    error: Option<io::Error>,
+}
+
+impl WavWriter {
+    fn create(
+        path: &Path,
+        channels: u16,
+        sample_rate: u32,
+        sample_format: SampleFormat,
+    ) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        let block_align = channels * sample_format.bits_per_sample() / 8;
+        file.write_all(b"RIFF\0\0\0\0WAVEfmt ")?;
+        file.write_all(&16_u32.to_le_bytes())?;
+        file.write_all(
+            &match sample_format {
+                SampleFormat::Pcm16 => 1_u16,
+                SampleFormat::Float32 => 3,
+            }
+            .to_le_bytes(),
+        )?;
+        file.write_all(&channels.to_le_bytes())?;
+        file.write_all(&sample_rate.to_le_bytes())?;
+        file.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+        file.write_all(&block_align.to_le_bytes())?;
+        file.write_all(&sample_format.bits_per_sample().to_le_bytes())?;
+        file.write_all(b"data\0\0\0\0")?;
+        Ok(WavWriter {
+            file,
+            data_len: 0,
+            error: None,
+        })
+    }
+
+    fn write(&mut self, data: &[u8]) {
+        if self.error.is_some() {
+            return;
+        }
+        // WAV files can't be larger than 4 GiB, drop anything past that
+        let len = data.len().min((u32::MAX - 36 - self.data_len) as usize);
+        match self.file.write_all(&data[..len]) {
+            Ok(()) => self.data_len += len as u32,
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        self.file.seek(SeekFrom::Start(4))?;
+        self.file.write_all(&(self.data_len + 36).to_le_bytes())?;
+        self.file.seek(SeekFrom::Start(40))?;
+        self.file.write_all(&self.data_len.to_le_bytes())?;
+        self.file.flush()
+    }
+}
+
+/// A shared slot that, while a dump is active, receives every audio sample the emulator outputs.
+#[derive(Clone, Default)]
+pub struct MixedCapture(Rc<RefCell<Option<WavWriter>>>);
+
+impl MixedCapture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wraps an audio backend so that the samples it receives are also dumped.
+    pub fn wrap(&self, inner: Box<dyn AudioBackend>) -> Box<dyn AudioBackend> {
+        Box::new(DumpingAudioBackend {
+            inner,
+            capture: self.clone(),
+        })
+    }
+}
+
+struct DumpingAudioBackend {
+    inner: Box<dyn AudioBackend>,
+    capture: MixedCapture,
+}
+
+impl AudioBackend for DumpingAudioBackend {
+    fn handle_sample_chunk(&mut self, samples: &mut Vec<[OutputSample; 2]>) {
+        if let Some(writer) = &mut *self.capture.0.borrow_mut() {
+            let mut bytes = Vec::with_capacity(samples.len() * 8);
+            for sample in samples.iter().flatten() {
+                #[cfg(not(feature = "xq-audio"))]
+                bytes.extend_from_slice(&((*sample as i16 - 0x200) << 6).to_le_bytes());
+                #[cfg(feature = "xq-audio")]
+                bytes.extend_from_slice(&sample.to_le_bytes());
+            }
+            writer.write(&bytes);
+        }
+        self.inner.handle_sample_chunk(samples);
+    }
+}
+
+/// Dumps the emulator's mixed audio output to a WAV file, optionally alongside the output of
+/// each individual channel (before panning and mixing) as captured by the core.
+pub struct Dumper {
+    mixed: MixedCapture,
+    channels: Option<Box<[WavWriter; 16]>>,
+}
+
+impl Dumper {
+    pub fn start(
+        settings: Settings,
+        mixed_sample_rate: u32,
+        mixed: &MixedCapture,
+    ) -> io::Result<Self> {
+        let channels = if settings.channels {
+            let stem = settings
+                .output_path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            let mut writers = Vec::with_capacity(16);
+            for i in 0..16 {
+                let path = settings
+                    .output_path
+                    .with_file_name(format!("{stem}_ch{i}.wav"));
+                writers.push(WavWriter::create(
+                    &path,
+                    1,
+                    CHANNEL_SAMPLE_RATE,
+                    SampleFormat::Pcm16,
+                )?);
+            }
+            Some(writers.into_boxed_slice().try_into().ok().unwrap())
+        } else {
+            None
+        };
+
+        *mixed.0.borrow_mut() = Some(WavWriter::create(
+            &settings.output_path,
+            2,
+            mixed_sample_rate,
+            if cfg!(feature = "xq-audio") {
+                SampleFormat::Float32
+            } else {
+                SampleFormat::Pcm16
+            },
+        )?);
+
+        Ok(Dumper {
+            mixed: mixed.clone(),
+            channels,
+        })
+    }
+
+    /// Makes sure the core is capturing the output of individual channels, if needed.
+    pub fn prepare_channel_capture(&self, capture_data: &mut ChannelAudioCaptureData) {
+        if self.channels.is_some() {
+            capture_data.dump_mask = 0xFFFF;
+        }
+    }
+
+    /// Writes the channel samples captured since the last call to
+    /// [`clear_channel_samples`](Self::clear_channel_samples); the capture buffers are shared with
+    /// the debug views, so this needs to be called before they're drained.
+    pub fn write_channel_samples(&mut self, capture_data: &ChannelAudioCaptureData) {
+        let Some(channels) = &mut self.channels else {
+            return;
+        };
+        for (writer, buffer) in channels.iter_mut().zip(&capture_data.buffers) {
+            let bytes = buffer
+                .iter()
+                .flat_map(|sample| sample.to_le_bytes())
+                .collect::<Vec<_>>();
+            writer.write(&bytes);
+        }
+    }
+
+    pub fn clear_channel_samples(&self, capture_data: &mut ChannelAudioCaptureData) {
+        if self.channels.is_none() {
+            return;
+        }
+        for buffer in &mut capture_data.buffers {
+            buffer.clear();
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        let mixed_result = self
+            .mixed
+            .0
+            .borrow_mut()
+            .take()
+            .map_or(Ok(()), WavWriter::finish);
+        if let Some(channels) = self.channels.take() {
+            for writer in *channels {
+                writer.finish()?;
+            }
+        }
+        mixed_result
+    }
+}
+
+impl Drop for Dumper {
+    fn drop(&mut self) {
+        self.mixed.0.borrow_mut().take();
+    }
+}
@@ -1,18 +1,28 @@
+pub mod diff;
+pub mod game_bundle;
+pub mod play_stats;
 pub mod saves;
 #[allow(dead_code)]
 mod setting;
+pub mod sys_files;
 pub use setting::{
     NonOverridable, Origin as SettingOrigin, Overridable, OverridableTypes, Resolvable, Setting,
     Tracked, Untracked,
 };
 
 use crate::{
-    audio, input,
+    audio,
+    game_db::SaveType,
+    input,
     utils::{base_dirs, double_option, HomePathBuf},
 };
+use chrono::NaiveDateTime;
 use dust_core::{
-    audio::ChannelInterpMethod as AudioChannelInterpMethod,
+    audio::{ChannelInterpMethod as AudioChannelInterpMethod, StereoMode as AudioStereoMode},
     cpu::{arm7, arm9},
+    ds_slot::rom::key1,
+    emu::DEFAULT_BATCH_DURATION,
+    gpu::{Engine2dId, SCREEN_HEIGHT, SCREEN_WIDTH},
     spi::firmware,
     utils::{zeroed_box, BoxedByteSlice, Bytes},
     Model,
@@ -203,7 +213,7 @@ macro_rules! def_config {
 
             pub fn unset_game(&mut self) {
                 $(self.$uo_ident.inner_mut().unset_game();)*
-                $(self.$uga_ident.set($lga_default);)*
+                $(self.$uga_ident.set($uga_default);)*
                 $(self.$to_ident.inner_mut().unset_game();)*
                 $(self.$tga_ident.set($tga_default);)*
             }
@@ -213,6 +223,89 @@ macro_rules! def_config {
                 $(self.$to_ident.clear_updates();)*
                 $(self.$tga_ident.clear_updates();)*
             }
+
+            /// Lists the global settings that differ from their defaults, along with the ones
+            /// overridden by the current game's configuration.
+            pub fn diff(&self) -> diff::Diff {
+                let mut diff = diff::Diff::default();
+                $(diff.push_global(
+                    stringify!($ug_ident),
+                    self.$ug_ident.get(),
+                    self.$ug_ident.inner().default(),
+                );)*
+                $(diff.push_global(
+                    stringify!($uo_ident),
+                    self.$uo_ident.inner().global(),
+                    self.$uo_ident.inner().default_global(),
+                );)*
+                $(diff.push_global(
+                    stringify!($tg_ident),
+                    self.$tg_ident.get(),
+                    self.$tg_ident.inner().default(),
+                );)*
+                $(diff.push_global(
+                    stringify!($to_ident),
+                    self.$to_ident.inner().global(),
+                    self.$to_ident.inner().default_global(),
+                );)*
+                $(diff.push_game(
+                    stringify!($uo_ident),
+                    self.$uo_ident.inner().game(),
+                    &$uo_game_unset,
+                );)*
+                $(diff.push_game(
+                    stringify!($uga_ident),
+                    self.$uga_ident.get(),
+                    self.$uga_ident.inner().default(),
+                );)*
+                $(diff.push_game(
+                    stringify!($to_ident),
+                    self.$to_ident.inner().game(),
+                    &$to_game_unset,
+                );)*
+                $(diff.push_game(
+                    stringify!($tga_ident),
+                    self.$tga_ident.get(),
+                    self.$tga_ident.inner().default(),
+                );)*
+                diff
+            }
+
+            /// Resets the global value of a setting, identified by its name in the configuration
+            /// file, to its default.
+            pub fn reset_global_setting(&mut self, name: &str) {
+                let ident = name.replace('-', "_");
+                $(if ident == stringify!($ug_ident) {
+                    self.$ug_ident.set_default();
+                })*
+                $(if ident == stringify!($uo_ident) {
+                    self.$uo_ident.inner_mut().set_default_global();
+                })*
+                $(if ident == stringify!($tg_ident) {
+                    self.$tg_ident.set_default();
+                })*
+                $(if ident == stringify!($to_ident) {
+                    self.$to_ident.inner_mut().set_default_global();
+                })*
+            }
+
+            /// Removes the current game's override for a setting, identified by its name in the
+            /// configuration file.
+            pub fn reset_game_setting(&mut self, name: &str) {
+                let ident = name.replace('-', "_");
+                $(if ident == stringify!($uo_ident) {
+                    self.$uo_ident.inner_mut().unset_game();
+                })*
+                $(if ident == stringify!($uga_ident) {
+                    self.$uga_ident.set_default();
+                })*
+                $(if ident == stringify!($to_ident) {
+                    self.$to_ident.inner_mut().unset_game();
+                })*
+                $(if ident == stringify!($tga_ident) {
+                    self.$tga_ident.set_default();
+                })*
+            }
         }
     };
 }
@@ -258,6 +351,72 @@ macro_rules! toggle_config {
     }};
 }
 
+/// The position and size of a screen inside a [`CustomScreenLayout`], in DS pixels.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ScreenRect {
+    pub pos: [f32; 2],
+    pub size: [f32; 2],
+}
+
+/// A named, user-defined arrangement of the screens, in which each of them can be freely
+/// positioned and scaled (possibly overlapping the other one) inside a canvas of arbitrary size.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct CustomScreenLayout {
+    pub name: String,
+    pub size: [f32; 2],
+    /// The area covered by each screen (top, then bottom), or `None` if it's hidden.
+    pub screens: [Option<ScreenRect>; 2],
+    /// Whether the top screen should be drawn over the bottom one where they overlap.
+    pub top_screen_above: bool,
+}
+
+impl Default for CustomScreenLayout {
+    fn default() -> Self {
+        let screen_size = [SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32];
+        CustomScreenLayout {
+            name: String::new(),
+            size: [screen_size[0], screen_size[1] * 2.0],
+            screens: [
+                Some(ScreenRect {
+                    pos: [0.0; 2],
+                    size: screen_size,
+                }),
+                Some(ScreenRect {
+                    pos: [0.0, screen_size[1]],
+                    size: screen_size,
+                }),
+            ],
+            top_screen_above: false,
+        }
+    }
+}
+
+/// A named group of system files (i.e. a DS retail, DS debug, iQue or DSi dump) that can be
+/// selected to boot games with instead of the default system paths.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SysPathSet {
+    pub name: String,
+    pub dir: Option<HomePathBuf>,
+    pub arm7_bios: Option<HomePathBuf>,
+    pub arm9_bios: Option<HomePathBuf>,
+    pub firmware: Option<HomePathBuf>,
+}
+
+impl SysPathSet {
+    pub fn resolve(&self) -> ResolvedSysPaths {
+        ResolvedSysPaths::resolve_paths(
+            &self.dir,
+            &self.arm7_bios,
+            &self.arm9_bios,
+            &self.firmware,
+            Some(self.name.clone()),
+        )
+    }
+}
+
 #[derive(Clone, Default, Serialize, Deserialize)]
 #[serde(default, rename_all = "kebab-case")]
 pub struct GlobalSysPaths {
@@ -265,6 +424,16 @@ pub struct GlobalSysPaths {
     pub arm7_bios: Option<HomePathBuf>,
     pub arm9_bios: Option<HomePathBuf>,
     pub firmware: Option<HomePathBuf>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sets: Vec<SysPathSet>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub set: Option<String>,
+}
+
+impl GlobalSysPaths {
+    pub fn find_set(&self, name: &str) -> Option<&SysPathSet> {
+        self.sets.iter().find(|set| set.name == name)
+    }
 }
 
 #[derive(Clone, Default, Serialize, Deserialize)]
@@ -278,6 +447,8 @@ pub struct GameSysPaths {
     pub arm9_bios: Option<Option<HomePathBuf>>,
     #[serde(skip_serializing_if = "Option::is_none", with = "double_option")]
     pub firmware: Option<Option<HomePathBuf>>,
+    #[serde(skip_serializing_if = "Option::is_none", with = "double_option")]
+    pub set: Option<Option<String>>,
 }
 
 impl GameSysPaths {
@@ -287,6 +458,7 @@ impl GameSysPaths {
             arm7_bios: Some(None),
             arm9_bios: Some(None),
             firmware: Some(None),
+            set: Some(None),
         }
     }
 }
@@ -296,14 +468,54 @@ pub struct ResolvedSysPaths {
     pub arm7_bios: Option<HomePathBuf>,
     pub arm9_bios: Option<HomePathBuf>,
     pub firmware: Option<HomePathBuf>,
+    /// An optional standalone copy of the KEY1 table, only looked up in the system files directory
+    /// and used to handle encrypted ROMs when no ARM7 BIOS is loaded.
+    pub key1_table: Option<HomePathBuf>,
+    /// The name of the system file set the paths were taken from, if any.
+    pub set: Option<String>,
 }
 
 impl ResolvedSysPaths {
+    fn resolve_paths(
+        dir: &Option<HomePathBuf>,
+        arm7_bios: &Option<HomePathBuf>,
+        arm9_bios: &Option<HomePathBuf>,
+        firmware: &Option<HomePathBuf>,
+        set: Option<String>,
+    ) -> Self {
+        macro_rules! path {
+            ($field: ident, $path_in_sys_dir: expr) => {
+                $field.clone().or_else(|| {
+                    dir.as_ref()
+                        .map(|dir_path| HomePathBuf(dir_path.0.join($path_in_sys_dir)))
+                })
+            };
+        }
+
+        ResolvedSysPaths {
+            arm7_bios: path!(arm7_bios, "biosnds7.bin"),
+            arm9_bios: path!(arm9_bios, "biosnds9.bin"),
+            firmware: path!(firmware, "firmware.bin"),
+            key1_table: dir
+                .as_ref()
+                .map(|dir_path| HomePathBuf(dir_path.0.join("key1.bin"))),
+            set,
+        }
+    }
+
     fn resolve(global: &GlobalSysPaths, game: &GameSysPaths) -> (Self, SettingOrigin) {
+        let set_name = game.set.as_ref().unwrap_or(&global.set);
+        // An unknown set name (i.e. one that was since removed or renamed) falls back to the
+        // default paths
+        let set = set_name.as_deref().and_then(|name| global.find_set(name));
+
         macro_rules! override_paths {
             ($($field: ident),*) => {
                 $(
-                    let mut $field = &global.$field;
+                    let mut $field = match set {
+                        Some(set) => &set.$field,
+                        None => &global.$field,
+                    };
                     if let Some(path) = &game.$field {
                         $field = path;
                     }
@@ -313,21 +525,14 @@ impl ResolvedSysPaths {
 
         override_paths!(dir, arm7_bios, arm9_bios, firmware);
 
-        macro_rules! path {
-            ($field: ident, $path_in_sys_dir: expr) => {
-                $field.clone().or_else(|| {
-                    dir.as_ref()
-                        .map(|dir_path| HomePathBuf(dir_path.0.join($path_in_sys_dir)))
-                })
-            };
-        }
-
         (
-            ResolvedSysPaths {
-                arm7_bios: path!(arm7_bios, "biosnds7.bin"),
-                arm9_bios: path!(arm9_bios, "biosnds9.bin"),
-                firmware: path!(firmware, "firmware.bin"),
-            },
+            Self::resolve_paths(
+                dir,
+                arm7_bios,
+                arm9_bios,
+                firmware,
+                set.map(|set| set.name.clone()),
+            ),
             SettingOrigin::Game,
         )
     }
@@ -366,6 +571,242 @@ pub enum Renderer3dKind {
     Wgpu,
 }
 
+/// Which physical screen each 2D engine's output should be displayed on, regardless of the one the
+/// game sends it to.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EngineScreenAssignment {
+    Auto,
+    EngineAOnTop,
+    EngineBOnTop,
+}
+
+impl EngineScreenAssignment {
+    pub fn name(self) -> &'static str {
+        match self {
+            EngineScreenAssignment::Auto => "Follow game",
+            EngineScreenAssignment::EngineAOnTop => "Engine A on top",
+            EngineScreenAssignment::EngineBOnTop => "Engine B on top",
+        }
+    }
+
+    /// Returns whether the screens need to be swapped on display to honor the assignment, given
+    /// the engines the game is currently sending to the upper and lower screens.
+    pub fn swaps_screens(self, screen_engines: [Engine2dId; 2]) -> bool {
+        match self {
+            EngineScreenAssignment::Auto => false,
+            EngineScreenAssignment::EngineAOnTop => screen_engines[0] != Engine2dId::A,
+            EngineScreenAssignment::EngineBOnTop => screen_engines[0] != Engine2dId::B,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScreenFilter {
+    Nearest,
+    Bilinear,
+    Scale2x,
+    Scanlines,
+    LcdGrid,
+}
+
+impl ScreenFilter {
+    pub fn name(self) -> &'static str {
+        match self {
+            ScreenFilter::Nearest => "Nearest",
+            ScreenFilter::Bilinear => "Bilinear",
+            ScreenFilter::Scale2x => "Scale2x",
+            ScreenFilter::Scanlines => "Scanlines",
+            ScreenFilter::LcdGrid => "LCD grid",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TextureFilter {
+    Nearest,
+    Bilinear,
+}
+
+impl TextureFilter {
+    pub fn name(self) -> &'static str {
+        match self {
+            TextureFilter::Nearest => "Nearest",
+            TextureFilter::Bilinear => "Bilinear",
+        }
+    }
+}
+
+impl From<TextureFilter> for dust_wgpu_3d::TextureFilter {
+    fn from(value: TextureFilter) -> Self {
+        match value {
+            TextureFilter::Nearest => dust_wgpu_3d::TextureFilter::Nearest,
+            TextureFilter::Bilinear => dust_wgpu_3d::TextureFilter::Bilinear,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TextureCacheMode {
+    Combined,
+    PaletteSeparated,
+    Auto,
+}
+
+impl TextureCacheMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            TextureCacheMode::Combined => "Combined",
+            TextureCacheMode::PaletteSeparated => "Separate palettes",
+            TextureCacheMode::Auto => "Auto",
+        }
+    }
+}
+
+impl From<TextureCacheMode> for dust_wgpu_3d::TextureCacheMode {
+    fn from(value: TextureCacheMode) -> Self {
+        match value {
+            TextureCacheMode::Combined => dust_wgpu_3d::TextureCacheMode::Combined,
+            TextureCacheMode::PaletteSeparated => dust_wgpu_3d::TextureCacheMode::PaletteSeparated,
+            TextureCacheMode::Auto => dust_wgpu_3d::TextureCacheMode::Auto,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LcdColorProfile {
+    None,
+    Ds,
+    DsLite,
+}
+
+impl LcdColorProfile {
+    pub fn name(self) -> &'static str {
+        match self {
+            LcdColorProfile::None => "None",
+            LcdColorProfile::Ds => "DS",
+            LcdColorProfile::DsLite => "DS Lite",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct AccuracySettings {
+    pub batch_duration: u32,
+    pub prefer_hle_bios: bool,
+}
+
+macro_rules! accuracy_settings {
+    ($($field: ident),*) => {
+        impl AccuracySettings {
+            pub fn global(config: &Config) -> Self {
+                AccuracySettings {
+                    $($field: *config.$field.inner().global(),)*
+                }
+            }
+
+            /// Returns the settings that apply to the current game, taking the global value for
+            /// those that aren't overridden.
+            pub fn game(config: &Config) -> Self {
+                AccuracySettings {
+                    $($field: config
+                        .$field
+                        .inner()
+                        .game()
+                        .unwrap_or(*config.$field.inner().global()),)*
+                }
+            }
+
+            pub fn is_overridden(config: &Config) -> bool {
+                $(config.$field.inner().game().is_some())||*
+            }
+
+            pub fn set_global(self, config: &mut Config) {
+                $(config.$field.inner_mut().set_global(self.$field);)*
+            }
+
+            pub fn set_game(self, config: &mut Config) {
+                $(config.$field.inner_mut().set_game(Some(self.$field));)*
+            }
+
+            pub fn unset_game(config: &mut Config) {
+                $(config.$field.inner_mut().set_game(None);)*
+            }
+
+            pub fn set_default_global(config: &mut Config) {
+                $(config.$field.inner_mut().set_default_global();)*
+            }
+
+            pub fn set_default_game(config: &mut Config) {
+                $(config.$field.inner_mut().set_default_game();)*
+            }
+        }
+    };
+}
+
+accuracy_settings!(batch_duration, prefer_hle_bios);
+
+/// A coherent set of values for the emulation settings that trade off accuracy for performance, so
+/// that they don't need to be tuned individually; renderers are left to the user's choice.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AccuracyPreset {
+    Fast,
+    Balanced,
+    Accurate,
+    CycleSynced,
+}
+
+impl AccuracyPreset {
+    pub const ALL: [AccuracyPreset; 4] = [
+        AccuracyPreset::Fast,
+        AccuracyPreset::Balanced,
+        AccuracyPreset::Accurate,
+        AccuracyPreset::CycleSynced,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AccuracyPreset::Fast => "Fast",
+            AccuracyPreset::Balanced => "Balanced",
+            AccuracyPreset::Accurate => "Accurate",
+            AccuracyPreset::CycleSynced => "Cycle-synced",
+        }
+    }
+
+    pub fn settings(self) -> AccuracySettings {
+        match self {
+            AccuracyPreset::Fast => AccuracySettings {
+                batch_duration: 256,
+                prefer_hle_bios: true,
+            },
+            AccuracyPreset::Balanced => AccuracySettings {
+                batch_duration: DEFAULT_BATCH_DURATION,
+                prefer_hle_bios: false,
+            },
+            AccuracyPreset::Accurate => AccuracySettings {
+                batch_duration: 16,
+                prefer_hle_bios: false,
+            },
+            AccuracyPreset::CycleSynced => AccuracySettings {
+                batch_duration: 1,
+                prefer_hle_bios: false,
+            },
+        }
+    }
+
+    /// Returns the preset that the given settings correspond to, or `None` if they have been
+    /// customized.
+    pub fn matching(settings: &AccuracySettings) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.settings() == *settings)
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum TitleBarMode {
@@ -390,6 +831,172 @@ impl TitleBarMode {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RecordingContainer {
+    Mp4,
+    #[serde(rename = "webm")]
+    WebM,
+    Matroska,
+}
+
+impl RecordingContainer {
+    pub fn name(self) -> &'static str {
+        match self {
+            RecordingContainer::Mp4 => "MP4",
+            RecordingContainer::WebM => "WebM",
+            RecordingContainer::Matroska => "Matroska",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            RecordingContainer::Mp4 => "mp4",
+            RecordingContainer::WebM => "webm",
+            RecordingContainer::Matroska => "mkv",
+        }
+    }
+
+    pub fn supports(self, video_codec: RecordingVideoCodec) -> bool {
+        match self {
+            RecordingContainer::WebM => {
+                matches!(
+                    video_codec,
+                    RecordingVideoCodec::Vp9 | RecordingVideoCodec::Av1
+                )
+            }
+            RecordingContainer::Mp4 | RecordingContainer::Matroska => true,
+        }
+    }
+
+    pub fn ffmpeg_audio_encoder(self) -> &'static str {
+        match self {
+            RecordingContainer::Mp4 => "aac",
+            RecordingContainer::WebM | RecordingContainer::Matroska => "libopus",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RecordingVideoCodec {
+    H264,
+    H265,
+    Vp9,
+    Av1,
+}
+
+impl RecordingVideoCodec {
+    pub fn name(self) -> &'static str {
+        match self {
+            RecordingVideoCodec::H264 => "H.264",
+            RecordingVideoCodec::H265 => "H.265",
+            RecordingVideoCodec::Vp9 => "VP9",
+            RecordingVideoCodec::Av1 => "AV1",
+        }
+    }
+
+    pub fn ffmpeg_encoder(self) -> &'static str {
+        match self {
+            RecordingVideoCodec::H264 => "libx264",
+            RecordingVideoCodec::H265 => "libx265",
+            RecordingVideoCodec::Vp9 => "libvpx-vp9",
+            RecordingVideoCodec::Av1 => "libsvtav1",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FrameDumpFormat {
+    Png,
+    Raw,
+}
+
+impl FrameDumpFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            FrameDumpFormat::Png => "PNG sequence",
+            FrameDumpFormat::Raw => "Raw RGBA stream",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            FrameDumpFormat::Png => "png",
+            FrameDumpFormat::Raw => "rgba",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CaptureLayout {
+    MatchScreen,
+    RawStacked,
+}
+
+impl CaptureLayout {
+    pub fn name(self) -> &'static str {
+        match self {
+            CaptureLayout::MatchScreen => "Match screen layout",
+            CaptureLayout::RawStacked => "Raw dual-screen stacked",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RemoteDisplayEncoding {
+    Rgb565,
+    Mjpeg,
+}
+
+impl RemoteDisplayEncoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            RemoteDisplayEncoding::Rgb565 => "Raw RGB565",
+            RemoteDisplayEncoding::Mjpeg => "MJPEG",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RemoteDisplayScreens {
+    Both,
+    Top,
+    Bottom,
+}
+
+impl RemoteDisplayScreens {
+    pub fn name(self) -> &'static str {
+        match self {
+            RemoteDisplayScreens::Both => "Both",
+            RemoteDisplayScreens::Top => "Top screen",
+            RemoteDisplayScreens::Bottom => "Bottom screen",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PresentMode {
+    Vsync,
+    Mailbox,
+    Immediate,
+}
+
+impl PresentMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            PresentMode::Vsync => "Vsync",
+            PresentMode::Mailbox => "Low-latency vsync",
+            PresentMode::Immediate => "No vsync",
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum GameIconMode {
@@ -435,32 +1042,110 @@ def_config! {
                 HomePathBuf(base_dirs().config.join("imgui.ini"))
             ),
             screen_integer_scale: bool = false,
+            show_frame_counter: bool = false,
+            show_input_overlay: bool = false,
+            show_perf_overlay: bool = false,
+            play_stats_enabled: bool = true,
+            idle_when_paused: bool = true,
+            audio_output_buffer_len: u16 = 2048,
+            audio_output_latency_ms: u16 = 0,
+            detached_bottom_screen: bool = false,
+            bottom_screen_integer_scale: bool = false,
             reset_on_save_slot_switch: bool = true,
+            auto_savestate_enabled: bool = false,
+            auto_savestate_interval_mins: u16 = 5,
+            auto_savestate_count: u8 = 3,
+            screen_layouts: Vec<CustomScreenLayout> = Vec::new(),
             gdb_server_addr: SocketAddr = ([127_u8, 0, 0, 1], 12345_u16).into(),
+            virtual_time_server_addr: SocketAddr = ([127_u8, 0, 0, 1], 12346_u16).into(),
+            ir_link_enabled: bool = false,
+            ir_link_local_addr: SocketAddr = ([127_u8, 0, 0, 1], 12348_u16).into(),
+            ir_link_peer_addr: SocketAddr = ([127_u8, 0, 0, 1], 12349_u16).into(),
+            crash_screen_enabled: bool = true,
+            ffmpeg_path: Option<HomePathBuf> = None,
+            recording_container: RecordingContainer = RecordingContainer::Mp4,
+            recording_video_codec: RecordingVideoCodec = RecordingVideoCodec::H264,
+            recording_video_bitrate_kbps: u32 = 8000,
+            recording_audio_bitrate_kbps: u32 = 192,
+            frame_dump_dir_path: HomePathBuf = HomePathBuf(base_dirs().data.join("frame_dumps")),
+            frame_dump_name_template: String = "{title}/{frame}_{layer}".to_owned(),
+            frame_dump_format: FrameDumpFormat = FrameDumpFormat::Png,
+            frame_dump_3d_layer: bool = false,
+            frame_dump_2d_layers: bool = false,
+            capture_layout: CaptureLayout = CaptureLayout::MatchScreen,
+            wav_dump_channels: bool = false,
+            remote_display_addr: SocketAddr = ([127_u8, 0, 0, 1], 12347_u16).into(),
+            remote_display_encoding: RemoteDisplayEncoding = RemoteDisplayEncoding::Mjpeg,
+            remote_display_jpeg_quality: u8 = 80,
+            remote_display_screens: RemoteDisplayScreens = RemoteDisplayScreens::Both,
+            adaptive_resolution_lower_threshold: u8 = 90,
+            adaptive_resolution_raise_threshold: u8 = 20,
+            adaptive_resolution_hold_frames: u16 = 30,
         }
         overridable {
             ds_slot_rom_in_memory_max_size: u32 = 32 * 1024 * 1024, Some(32 * 1024 * 1024), None,
                 resolve resolve_option, set set_option,
             screen_rot: u16 = 0, Some(0), None,
                 resolve resolve_option, set set_option,
+            bottom_screen_rot: u16 = 0, Some(0), None,
+                resolve resolve_option, set set_option,
+            screen_gap: u16 = 0, Some(0), None,
+                resolve resolve_option, set set_option,
+            swap_screens: bool = false, Some(false), None,
+                resolve resolve_option, set set_option,
+            engine_screen_assignment: EngineScreenAssignment = EngineScreenAssignment::Auto,
+                Some(EngineScreenAssignment::Auto), None,
+                resolve resolve_option, set set_option,
+            single_screen: bool = false, Some(false), None,
+                resolve resolve_option, set set_option,
+            magnified_screen: bool = false, Some(false), None,
+                resolve resolve_option, set set_option,
+            magnified_screen_is_top: bool = false, Some(false), None,
+                resolve resolve_option, set set_option,
+            small_screen_scale: f32 = 0.35, Some(0.35), None,
+                resolve resolve_option, set set_option,
+            screen_layout: String = String::new(), Some(String::new()), None,
+                resolve resolve_option, set set_option,
             sys_paths: ResolvedSysPaths, GlobalSysPaths, GameSysPaths, ()
                 = Default::default(), GameSysPaths::empty(), GameSysPaths::default(),
                 resolve ResolvedSysPaths::resolve, set set_unreachable,
             skip_firmware: bool = true, Some(true), None,
                 resolve resolve_option, set set_option,
+            return_to_menu_on_shutdown: bool = false, Some(false), None,
+                resolve resolve_option, set set_option,
             pause_on_launch: bool = false, Some(false), None,
                 resolve resolve_option, set set_option,
             model: ModelConfig = ModelConfig::Auto, Some(ModelConfig::Auto), None,
                 resolve resolve_option, set set_option,
             prefer_hle_bios: bool = false, Some(false), None,
                 resolve resolve_option, set set_option,
+            batch_duration: u32 = DEFAULT_BATCH_DURATION, Some(DEFAULT_BATCH_DURATION), None,
+                resolve resolve_option, set set_option,
+            threaded_3d_geometry: bool = false, Some(false), None,
+                resolve resolve_option, set set_option,
             input_map: input::Map, input::GlobalMap, input::Map, ()
                 = Default::default(), Default::default(), input::Map::empty(),
                 resolve input::Map::resolve, set set_unreachable,
             include_save_in_savestates: bool = true, Some(true), None,
                 resolve resolve_option, set set_option,
+            fast_forward_speed_limit: (bool, f32) = (true, 4.0), Some((true, 4.0)), None,
+                resolve resolve_option, set set_option,
+            turbo_speed_limit: (bool, f32) = (true, 2.0), Some((true, 2.0)), None,
+                resolve resolve_option, set set_option,
+            slow_motion_speed: f32 = 0.5, Some(0.5), None,
+                resolve resolve_option, set set_option,
+            hang_timeout_secs: (bool, f32) = (true, 5.0), Some((true, 5.0)), None,
+                resolve resolve_option, set set_option,
+            run_frames_count: u32 = 60, Some(60), None,
+                resolve resolve_option, set set_option,
+            gba_slot_rom_path: Option<HomePathBuf> = None, Some(None), None,
+                resolve resolve_option, set set_option,
+            watch_folder_patch_path: Option<HomePathBuf> = None, Some(None), None,
+                resolve resolve_option, set set_option,
+        }
+        game {
+            save_type_override: Option<SaveType> = None,
         }
-        game {}
     }
     tracked {
         global {
@@ -472,6 +1157,17 @@ def_config! {
             logging_kind: LoggingKind = LoggingKind::Imgui,
             save_dir_path: HomePathBuf = HomePathBuf(base_dirs().data.join("saves")),
             savestate_dir_path: HomePathBuf = HomePathBuf(base_dirs().data.join("states")),
+            shader_dir_path: HomePathBuf = HomePathBuf(base_dirs().config.join("shaders")),
+            texture_dump_dir_path: HomePathBuf
+                = HomePathBuf(base_dirs().data.join("texture_dumps")),
+            texture_pack_dir_path: HomePathBuf
+                = HomePathBuf(base_dirs().data.join("texture_packs")),
+            watch_folder_dir_path: HomePathBuf
+                = HomePathBuf(base_dirs().data.join("watch_folders")),
+            audio_output_device: String = String::new(),
+            audio_output_sample_rate: u32 = 0,
+            present_mode: PresentMode = PresentMode::Vsync,
+            max_frame_latency: u8 = 2,
         }
         overridable {
             full_window_screen: bool = true, Some(true), None,
@@ -486,6 +1182,24 @@ def_config! {
                 resolve resolve_option, set set_option,
             sync_to_audio: bool = true, Some(true), None,
                 resolve resolve_option, set set_option,
+            sub_frame_input: bool = false, Some(false), None,
+                resolve resolve_option, set set_option,
+            fast_pointer: bool = false, Some(false), None,
+                resolve resolve_option, set set_option,
+            fast_pointer_sensitivity: f32 = 0.25, Some(0.25), None,
+                resolve resolve_option, set set_option,
+            fast_pointer_acceleration: f32 = 0.0, Some(0.0), None,
+                resolve resolve_option, set set_option,
+            touch_requires_hotkey: bool = false, Some(false), None,
+                resolve resolve_option, set set_option,
+            stylus_speed: f32 = 128.0, Some(128.0), None,
+                resolve resolve_option, set set_option,
+            touch_pressure: u8 = 255, Some(255), None,
+                resolve resolve_option, set set_option,
+            solar_sensor_level: u8 = 128, Some(128), None,
+                resolve resolve_option, set set_option,
+            gamepad_deadzone: f32 = 0.3, Some(0.3), None,
+                resolve resolve_option, set set_option,
             audio_volume: f32 = 1.0, Some(1.0), None,
                 resolve resolve_option, set set_option,
             audio_sample_chunk_size: u16 = 512, Some(512), None,
@@ -493,6 +1207,13 @@ def_config! {
             audio_output_interp_method: audio::InterpMethod
                 = audio::InterpMethod::Nearest, Some(audio::InterpMethod::Nearest), None,
                 resolve resolve_option, set set_option,
+            audio_output_profile: audio::output::OutputProfile
+                = audio::output::OutputProfile::Headphones,
+                    Some(audio::output::OutputProfile::Headphones), None,
+                resolve resolve_option, set set_option,
+            audio_stereo_mode: AudioStereoMode
+                = AudioStereoMode::Stereo, Some(AudioStereoMode::Stereo), None,
+                resolve resolve_option, set set_option,
             audio_input_enabled: bool = false, Some(false), None,
                 resolve resolve_option, set set_option,
             audio_input_interp_method: audio::InterpMethod
@@ -507,6 +1228,8 @@ def_config! {
                 resolve resolve_option, set set_option,
             rtc_time_offset_seconds: i64 = 0, Some(0), None,
                 resolve resolve_option, set set_option,
+            rtc_frozen_date_time: Option<NaiveDateTime> = None, Some(None), None,
+                resolve resolve_option, set set_option,
             renderer_2d_kind: Renderer2dKind
                 = Renderer2dKind::SoftLockstepScanlines,
                     Some(Renderer2dKind::SoftLockstepScanlines), None,
@@ -514,8 +1237,37 @@ def_config! {
             renderer_3d_kind: Renderer3dKind
                 = Renderer3dKind::Soft, Some(Renderer3dKind::Soft), None,
                 resolve resolve_option, set set_option,
+            soft_renderer_3d_threads: u8 = 1, Some(1), None,
+                resolve resolve_option, set set_option,
             resolution_scale_shift: u8 = 0, Some(0), None,
                 resolve resolve_option, set set_option,
+            adaptive_resolution_scale: bool = false, Some(false), None,
+                resolve resolve_option, set set_option,
+            hi_res_2d_bgs: bool = false, Some(false), None,
+                resolve resolve_option, set set_option,
+            texture_filter: TextureFilter
+                = TextureFilter::Nearest, Some(TextureFilter::Nearest), None,
+                resolve resolve_option, set set_option,
+            texture_anisotropy_shift: u8 = 0, Some(0), None,
+                resolve resolve_option, set set_option,
+            msaa_3d: bool = false, Some(false), None,
+                resolve resolve_option, set set_option,
+            compute_rasterizer_3d: bool = false, Some(false), None,
+                resolve resolve_option, set set_option,
+            texture_cache_mode: TextureCacheMode
+                = TextureCacheMode::Auto, Some(TextureCacheMode::Auto), None,
+                resolve resolve_option, set set_option,
+            dump_3d_textures: bool = false, Some(false), None,
+                resolve resolve_option, set set_option,
+            replace_3d_textures: bool = false, Some(false), None,
+                resolve resolve_option, set set_option,
+            screen_filter: ScreenFilter = ScreenFilter::Nearest, Some(ScreenFilter::Nearest), None,
+                resolve resolve_option, set set_option,
+            lcd_color_profile: LcdColorProfile
+                = LcdColorProfile::None, Some(LcdColorProfile::None), None,
+                resolve resolve_option, set set_option,
+            post_process_shader: String = String::new(), Some(String::new()), None,
+                resolve resolve_option, set set_option,
         }
         game {
             save_path_config: Option<saves::PathConfig> = Some(Default::default()),
@@ -523,15 +1275,62 @@ def_config! {
     }
     ui {
         window_size: (u32, u32) = (1300, 800),
+        bottom_screen_window_size: (u32, u32) = (512, 384),
     }
 }
 
+/// The contents of a game's configuration file: its settings, along with statistics about it that
+/// aren't settings themselves (and as such are left alone when importing, exporting or resetting
+/// them).
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct GameFile {
+    #[serde(flatten)]
+    pub settings: Game,
+    #[serde(skip_serializing_if = "play_stats::PlayStats::is_empty")]
+    pub play_stats: play_stats::PlayStats,
+}
+
 impl Config {
     pub fn save_path(&self, game_title: &str) -> Option<PathBuf> {
         config!(self, &save_path_config)
             .as_ref()
             .and_then(|config| config.path(&config!(self, &save_dir_path).0, game_title))
     }
+
+    /// Returns the custom screen layout currently in use, if any.
+    pub fn custom_screen_layout(&self) -> Option<&CustomScreenLayout> {
+        let name = config!(self, &screen_layout);
+        if name.is_empty() {
+            return None;
+        }
+        config!(self, &screen_layouts)
+            .iter()
+            .find(|layout| layout.name == *name)
+    }
+
+    /// Switches to the next (or previous) screen layout, cycling through the default one and all
+    /// custom ones in order.
+    pub fn cycle_screen_layout(&mut self, forward: bool) {
+        let names = std::iter::once("")
+            .chain(
+                config!(self, &screen_layouts)
+                    .iter()
+                    .map(|layout| layout.name.as_str()),
+            )
+            .collect::<Vec<_>>();
+        let cur_i = names
+            .iter()
+            .position(|name| *name == config!(self, &screen_layout).as_str())
+            .unwrap_or(0);
+        let new_i = if forward {
+            (cur_i + 1) % names.len()
+        } else {
+            (cur_i + names.len() - 1) % names.len()
+        };
+        let new_name = names[new_i].to_owned();
+        set_config!(self, screen_layout, new_name);
+    }
 }
 
 #[derive(Default)]
@@ -604,6 +1403,7 @@ pub struct SysFiles {
     pub arm7_bios: Option<Box<Bytes<{ arm7::BIOS_SIZE }>>>,
     pub arm9_bios: Option<Box<Bytes<{ arm9::BIOS_SIZE }>>>,
     pub firmware: Option<BoxedByteSlice>,
+    pub key1_table: Option<Box<Bytes<{ key1::TABLE_SIZE }>>>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -611,6 +1411,7 @@ pub enum SystemFile {
     Arm7Bios,
     Arm9Bios,
     Firmware,
+    Key1Table,
 }
 
 pub enum LaunchWarning {
@@ -667,7 +1468,7 @@ pub enum LaunchError {
 
 impl fmt::Display for LaunchError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        const SYS_FILE_NAMES: [&str; 3] = ["ARM7 BIOS", "ARM9 BIOS", "firmware"];
+        const SYS_FILE_NAMES: [&str; 4] = ["ARM7 BIOS", "ARM9 BIOS", "firmware", "KEY1 table"];
 
         match self {
             LaunchError::MissingSysPath(file) => {
@@ -789,6 +1590,33 @@ impl Launch {
             }),
         );
 
+        // Only needed to handle encrypted ROMs without an ARM7 BIOS; as it's optional, it's fine
+        // for it not to exist
+        let key1_table = if arm7_bios.is_none() {
+            config
+                .sys_paths
+                .get()
+                .key1_table
+                .as_ref()
+                .and_then(|path| fs::File::open(&path.0).ok())
+                .and_then(|mut file| {
+                    let len = file.metadata().ok()?.len();
+                    if len != key1::TABLE_SIZE as u64 {
+                        errors.push(LaunchError::InvalidSysFileLength {
+                            file: SystemFile::Key1Table,
+                            expected: key1::TABLE_SIZE,
+                            got: len,
+                        });
+                        return None;
+                    }
+                    let mut buf = zeroed_box::<Bytes<{ key1::TABLE_SIZE }>>();
+                    file.read_exact(&mut **buf).ok()?;
+                    Some(buf)
+                })
+        } else {
+            None
+        };
+
         if let Some(firmware) = &firmware {
             if !firmware::is_valid_size(firmware.len()) {
                 errors.push(LaunchError::InvalidFirmwareFileLength {
@@ -829,6 +1657,7 @@ impl Launch {
                     arm7_bios,
                     arm9_bios,
                     firmware,
+                    key1_table,
                 },
                 skip_firmware,
                 model,
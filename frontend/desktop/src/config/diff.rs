@@ -0,0 +1,68 @@
+use serde::Serialize;
+use std::fmt::Write;
+
+/// A setting whose value differs from its default, with both values formatted as they'd appear in
+/// the configuration file.
+pub struct Entry {
+    /// The setting's name as used in the configuration file.
+    pub name: String,
+    pub value: String,
+    pub default: String,
+}
+
+/// The settings that were changed from their defaults, globally and for the current game.
+#[derive(Default)]
+pub struct Diff {
+    pub global: Vec<Entry>,
+    /// The settings overridden by the current game's configuration.
+    pub game: Vec<Entry>,
+}
+
+fn push_if_changed<T: Serialize>(entries: &mut Vec<Entry>, ident: &str, value: &T, default: &T) {
+    // Comparing the serialized representations avoids requiring all setting types to implement
+    // `PartialEq`, and gives a readable version of their values for free
+    let (Ok(value), Ok(default)) = (serde_json::to_value(value), serde_json::to_value(default))
+    else {
+        return;
+    };
+    if value != default {
+        entries.push(Entry {
+            name: ident.replace('_', "-"),
+            value: value.to_string(),
+            default: default.to_string(),
+        });
+    }
+}
+
+impl Diff {
+    pub(super) fn push_global<T: Serialize>(&mut self, ident: &str, value: &T, default: &T) {
+        push_if_changed(&mut self.global, ident, value, default);
+    }
+
+    pub(super) fn push_game<T: Serialize>(&mut self, ident: &str, value: &T, default: &T) {
+        push_if_changed(&mut self.game, ident, value, default);
+    }
+
+    /// Formats the changed settings as a plain-text list, to be attached to bug reports.
+    pub fn report(&self, game_title: Option<&str>) -> String {
+        let mut report = format!("Dust {} - changed settings\n", env!("CARGO_PKG_VERSION"));
+        let mut write_entries = |heading: &str, entries: &[Entry]| {
+            let _ = write!(report, "\n{heading}:\n");
+            if entries.is_empty() {
+                report.push_str("  (none)\n");
+            }
+            for entry in entries {
+                let _ = writeln!(
+                    report,
+                    "  {} = {} (default: {})",
+                    entry.name, entry.value, entry.default
+                );
+            }
+        };
+        write_entries("Global", &self.global);
+        if let Some(title) = game_title {
+            write_entries(&format!("Game overrides ({title})"), &self.game);
+        }
+        report
+    }
+}
@@ -0,0 +1,162 @@
+//! A shareable file containing the configuration overrides of multiple games, keyed by their
+//! titles (the ROM file names their configurations are stored under).
+
+use super::{File, FileError, Game, GameFile};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{collections::BTreeMap, fmt, fs, io, path::Path};
+
+const FORMAT: &str = "dust-game-configs";
+const VERSION: u32 = 1;
+
+/// Settings that refer to files on the machine they were set on, or that are otherwise personal,
+/// and so are neither exported nor replaced when importing a bundle.
+const LOCAL_SETTINGS: &[&str] = &[
+    "sys-paths",
+    "gba-slot-rom-path",
+    "watch-folder-patch-path",
+    "save-path-config",
+    "input-map",
+];
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Bundle {
+    format: String,
+    version: u32,
+    /// Each game's settings, as they'd appear in its configuration file; they're stored as raw
+    /// JSON objects so that settings unknown to this version are carried over untouched.
+    pub games: BTreeMap<String, Map<String, Value>>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Json(serde_json::Error),
+    UnsupportedFormat,
+    UnsupportedVersion(u32),
+    InvalidTitle(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {err}"),
+            Error::Json(err) => write!(f, "JSON serialization error: {err}"),
+            Error::UnsupportedFormat => f.write_str("not a game configuration bundle"),
+            Error::UnsupportedVersion(version) => {
+                write!(f, "unsupported bundle version {version}")
+            }
+            Error::InvalidTitle(title) => write!(f, "invalid game title: {title:?}"),
+        }
+    }
+}
+
+impl From<FileError> for Error {
+    fn from(err: FileError) -> Self {
+        match err {
+            FileError::Io(err) => Error::Io(err),
+            FileError::Json(err) => Error::Json(err),
+        }
+    }
+}
+
+/// Returns the titles of all games with a configuration file in the given directory, sorted
+/// alphabetically.
+pub fn list_games(games_dir: &Path) -> io::Result<Vec<String>> {
+    let mut titles = fs::read_dir(games_dir)?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+            path.file_stem()?.to_str().map(str::to_owned)
+        })
+        .collect::<Vec<_>>();
+    titles.sort_unstable();
+    Ok(titles)
+}
+
+/// Returns whether the given title can be safely used as a configuration file name, as titles
+/// coming from bundles can't be trusted not to point outside the configuration directory.
+fn is_valid_title(title: &str) -> bool {
+    !title.is_empty() && title != "." && title != ".." && !title.contains(['/', '\\', ':', '\0'])
+}
+
+impl Default for Bundle {
+    fn default() -> Self {
+        Bundle {
+            format: FORMAT.to_owned(),
+            version: VERSION,
+            games: BTreeMap::new(),
+        }
+    }
+}
+
+impl Bundle {
+    /// Adds the given settings for a game, leaving out the ones that wouldn't apply on another
+    /// machine and the ones that aren't overridden.
+    pub fn insert(&mut self, title: String, settings: &Game) -> Result<(), Error> {
+        let Value::Object(mut settings) = serde_json::to_value(settings).map_err(Error::Json)?
+        else {
+            unreachable!();
+        };
+        for name in LOCAL_SETTINGS {
+            settings.remove(*name);
+        }
+        settings.retain(|_, value| !value.is_null());
+        self.games.insert(title, settings);
+        Ok(())
+    }
+
+    /// Adds the settings stored in the configuration file of the game with the given title.
+    pub fn insert_from_dir(&mut self, games_dir: &Path, title: &str) -> Result<(), Error> {
+        let file = File::<GameFile>::read(&games_dir.join(format!("{title}.json")), false)?;
+        self.insert(title.to_owned(), &file.contents.settings)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let bundle: Bundle =
+            serde_json::from_slice(&fs::read(path).map_err(Error::Io)?).map_err(Error::Json)?;
+        if bundle.format != FORMAT {
+            return Err(Error::UnsupportedFormat);
+        }
+        if bundle.version > VERSION {
+            return Err(Error::UnsupportedVersion(bundle.version));
+        }
+        Ok(bundle)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        fs::write(path, serde_json::to_vec_pretty(self).map_err(Error::Json)?).map_err(Error::Io)
+    }
+
+    /// Applies the bundle's settings for the given game on top of the given ones, keeping the
+    /// local settings and any that the bundle doesn't specify.
+    pub fn merge(&self, title: &str, settings: &Game) -> Result<Game, Error> {
+        let Value::Object(mut merged) = serde_json::to_value(settings).map_err(Error::Json)? else {
+            unreachable!();
+        };
+        if let Some(imported) = self.games.get(title) {
+            for (name, value) in imported {
+                if !LOCAL_SETTINGS.contains(&name.as_str()) {
+                    merged.insert(name.clone(), value.clone());
+                }
+            }
+        }
+        serde_json::from_value(Value::Object(merged)).map_err(Error::Json)
+    }
+
+    /// Merges the bundle's settings for the game with the given title into its configuration
+    /// file, creating it if needed.
+    pub fn import_into_dir(&self, games_dir: &Path, title: &str) -> Result<(), Error> {
+        if !is_valid_title(title) {
+            return Err(Error::InvalidTitle(title.to_owned()));
+        }
+        let mut file = File::<GameFile>::read(&games_dir.join(format!("{title}.json")), true)?;
+        file.contents.settings = self.merge(title, &file.contents.settings)?;
+        file.write()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// How many of the most recent sessions are kept in a game's activity log.
+const MAX_LOGGED_SESSIONS: usize = 20;
+
+/// Sessions shorter than this (i.e. accidental launches) aren't recorded at all.
+const MIN_SESSION_DURATION: Duration = Duration::from_secs(10);
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LoggedSession {
+    pub started_at: DateTime<Utc>,
+    pub play_time_secs: u64,
+}
+
+/// Play time statistics for a single game, stored alongside its configuration.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PlayStats {
+    pub play_time_secs: u64,
+    pub sessions: u32,
+    pub last_played: Option<DateTime<Utc>>,
+    /// The most recent sessions, oldest first.
+    pub log: Vec<LoggedSession>,
+}
+
+impl PlayStats {
+    pub fn is_empty(&self) -> bool {
+        self.sessions == 0
+    }
+
+    pub fn record(&mut self, session: &Session) {
+        let play_time = session.play_time();
+        if play_time < MIN_SESSION_DURATION {
+            return;
+        }
+        let play_time_secs = play_time.as_secs();
+        self.play_time_secs = self.play_time_secs.saturating_add(play_time_secs);
+        self.sessions = self.sessions.saturating_add(1);
+        self.last_played = Some(Utc::now());
+        if self.log.len() >= MAX_LOGGED_SESSIONS {
+            self.log.drain(..=self.log.len() - MAX_LOGGED_SESSIONS);
+        }
+        self.log.push(LoggedSession {
+            started_at: session.started_at,
+            play_time_secs,
+        });
+    }
+
+    /// Returns user-facing descriptions of the statistics, as (name, value) pairs.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![
+            (
+                "Play time",
+                format_duration(Duration::from_secs(self.play_time_secs)),
+            ),
+            ("Sessions", self.sessions.to_string()),
+        ];
+        if let Some(last_played) = self.last_played {
+            fields.push(("Last played", format_date_time(last_played)));
+        }
+        fields
+    }
+}
+
+/// Tracks the time a game is actually running (i.e. not paused) for while it's loaded.
+pub struct Session {
+    started_at: DateTime<Utc>,
+    play_time: Duration,
+    last_update: Instant,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session {
+            started_at: Utc::now(),
+            play_time: Duration::ZERO,
+            last_update: Instant::now(),
+        }
+    }
+
+    /// Adds the time elapsed since the last update to the session's play time if the game was
+    /// running since then; should be called once per UI frame, and before recording the session.
+    pub fn update(&mut self, playing: bool) {
+        let now = Instant::now();
+        if playing {
+            self.play_time += now - self.last_update;
+        }
+        self.last_update = now;
+    }
+
+    pub fn play_time(&self) -> Duration {
+        self.play_time
+    }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let mins = duration.as_secs() / 60;
+    if mins < 60 {
+        format!("{mins}m")
+    } else {
+        format!("{}h {}m", mins / 60, mins % 60)
+    }
+}
+
+pub fn format_date_time(date_time: DateTime<Utc>) -> String {
+    date_time
+        .with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
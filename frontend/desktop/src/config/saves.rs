@@ -1,9 +1,17 @@
+pub mod convert;
+
 use crate::utils::HomePathBuf;
+use dust_core::ds_slot::spi::Spi;
 use serde::{Deserialize, Serialize};
 use std::{
     ffi::{OsStr, OsString},
-    fs, mem,
+    fmt, fs,
+    io::{self, Write},
+    mem,
+    ops::{Deref, DerefMut},
     path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard, PoisonError, TryLockError},
+    time::{Duration, Instant},
 };
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -233,6 +241,32 @@ impl PathConfig {
         false
     }
 
+    /// Switches to the slot after (or before) the current one, wrapping around at the ends of the
+    /// list.
+    pub fn switch_to_adjacent_slot(&mut self, forward: bool) -> bool {
+        let Slots::Multiple { current, slots } = &self.slots else {
+            return false;
+        };
+        let len = slots.len();
+        if len == 0 {
+            return false;
+        }
+        let new_i = match (*current, forward) {
+            (Some(i), true) => (i + 1) % len,
+            (Some(i), false) => (i + len - 1) % len,
+            (None, true) => 0,
+            (None, false) => len - 1,
+        };
+        self.switch_slot(new_i)
+    }
+
+    pub fn current_slot_name(&self) -> Option<&str> {
+        match &self.slots {
+            Slots::Single => None,
+            Slots::Multiple { current, slots } => Some(slots[(*current)?].as_str()),
+        }
+    }
+
     pub fn create_slot(&mut self, name: String) {
         if let Slots::Multiple { slots, .. } = &mut self.slots {
             if !slots.contains(&name) {
@@ -279,3 +313,222 @@ impl PathConfig {
         }
     }
 }
+
+/// An operation that may cause the save chip's current contents to be lost or replaced, and thus
+/// needs pending save writes to be flushed to disk first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlushReason {
+    SavestateLoad,
+    Reset,
+    SavePathChange,
+    SaveImport,
+    RendererSwitch,
+    Exit,
+}
+
+impl fmt::Display for FlushReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            FlushReason::SavestateLoad => "savestate load",
+            FlushReason::Reset => "reset",
+            FlushReason::SavePathChange => "save path change",
+            FlushReason::SaveImport => "save import",
+            FlushReason::RendererSwitch => "renderer switch",
+            FlushReason::Exit => "exit",
+        })
+    }
+}
+
+/// Writes `contents` to `path` through a temporary file next to it, which then replaces it; this
+/// way, the previous contents are kept intact if writing fails or is interrupted midway.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut tmp_file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?
+        .to_os_string();
+    tmp_file_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_file_name);
+
+    let result = fs::File::create(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&tmp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// The minimum time between copies of the save chip's contents kept around for the panic hook,
+/// bounding both the copying overhead and the progress that can be lost in a crash.
+pub const PANIC_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Writes the save chip's contents back to the save file, either periodically or right before
+/// operations that could discard them.
+pub struct Flusher {
+    path: Option<PathBuf>,
+    interval: Duration,
+    last_flush_time: Instant,
+    last_snapshot_time: Instant,
+}
+
+impl Flusher {
+    pub fn new(path: Option<PathBuf>, interval: Duration) -> Self {
+        Flusher {
+            path,
+            interval,
+            last_flush_time: Instant::now(),
+            last_snapshot_time: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn set_interval(&mut self, value: Duration) {
+        self.interval = value;
+    }
+
+    /// Writes the save chip's contents to disk if they were modified since the last flush.
+    /// Returns whether anything was written.
+    pub fn flush(&mut self, spi: &mut Spi) -> io::Result<bool> {
+        self.last_flush_time = Instant::now();
+        let Some(path) = &self.path else {
+            return Ok(false);
+        };
+        if !spi.contents_dirty() {
+            return Ok(false);
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomically(path, spi.contents())?;
+        spi.mark_contents_flushed();
+        *lock_panic_snapshot() = None;
+        Ok(true)
+    }
+
+    /// Copies the save chip's contents for [`flush_on_panic`] if they were modified since the last
+    /// flush, at most once every [`PANIC_SNAPSHOT_INTERVAL`]. Must only be called between frames,
+    /// when the contents are known to be consistent.
+    pub fn update_panic_snapshot(&mut self, spi: &Spi) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if !spi.contents_dirty() || self.last_snapshot_time.elapsed() < PANIC_SNAPSHOT_INTERVAL {
+            return;
+        }
+        self.last_snapshot_time = Instant::now();
+        let contents = spi.contents();
+        let mut snapshot = lock_panic_snapshot();
+        match &mut *snapshot {
+            Some(snapshot)
+                if snapshot.path == *path && snapshot.contents.len() == contents.len() =>
+            {
+                snapshot.contents.copy_from_slice(contents);
+            }
+            _ => {
+                *snapshot = Some(PanicSnapshot {
+                    path: path.clone(),
+                    contents: contents.into(),
+                });
+            }
+        }
+    }
+
+    /// Flushes the save chip's contents if the save interval has elapsed since the last flush.
+    /// Returns whether anything was written.
+    ///
+    /// Failures will be retried on the next interval, and reported again before any operation that
+    /// would lose the data.
+    pub fn flush_if_due(&mut self, spi: &mut Spi) -> io::Result<bool> {
+        if self.last_flush_time.elapsed() >= self.interval {
+            self.flush(spi)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Starts an operation that could discard the save chip's current contents, making sure any
+    /// pending writes are on disk first; the periodic flush timer restarts once the returned
+    /// transaction is dropped.
+    ///
+    /// A failed flush is reported to the user, but doesn't prevent the operation.
+    pub fn begin(&mut self, spi: &mut Spi, reason: FlushReason) -> Transaction<'_> {
+        if let Err(err) = self.flush(spi) {
+            error!(
+                "Save file error",
+                "Couldn't write save file before {reason}, the latest in-game progress may be \
+                 lost: {err}"
+            );
+        }
+        Transaction { flusher: self }
+    }
+}
+
+/// An operation started through [`Flusher::begin`]; allows the save path to be changed safely,
+/// as all previous writes have already reached the old one.
+pub struct Transaction<'a> {
+    flusher: &'a mut Flusher,
+}
+
+impl Transaction<'_> {
+    pub fn replace_path(&mut self, new: Option<PathBuf>) -> Option<PathBuf> {
+        mem::replace(&mut self.flusher.path, new)
+    }
+}
+
+impl Deref for Transaction<'_> {
+    type Target = Flusher;
+
+    fn deref(&self) -> &Flusher {
+        self.flusher
+    }
+}
+
+impl DerefMut for Transaction<'_> {
+    fn deref_mut(&mut self) -> &mut Flusher {
+        self.flusher
+    }
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        self.flusher.last_flush_time = Instant::now();
+    }
+}
+
+/// The save chip's contents as of the end of an emulated frame, if they hadn't been written back
+/// yet, along with the path they belong at.
+struct PanicSnapshot {
+    path: PathBuf,
+    contents: Box<[u8]>,
+}
+
+static PANIC_SNAPSHOT: Mutex<Option<PanicSnapshot>> = Mutex::new(None);
+
+fn lock_panic_snapshot() -> MutexGuard<'static, Option<PanicSnapshot>> {
+    PANIC_SNAPSHOT
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Writes the latest snapshot taken through [`Flusher::update_panic_snapshot`] that hasn't been
+/// superseded by a successful flush, if any; meant to be called from the panic hook, as the
+/// emulator quits right from it (and release builds abort on panic), so without this any
+/// progress since the last flush would be lost.
+///
+/// Returns `None` if there was nothing to write, or if the panic happened while the snapshot was
+/// being updated (in which case it can't be trusted).
+pub fn flush_on_panic() -> Option<io::Result<()>> {
+    let mut snapshot = match PANIC_SNAPSHOT.try_lock() {
+        Ok(snapshot) => snapshot,
+        Err(TryLockError::Poisoned(err)) => err.into_inner(),
+        Err(TryLockError::WouldBlock) => return None,
+    };
+    let snapshot = snapshot.take()?;
+    Some(write_atomically(&snapshot.path, &snapshot.contents))
+}
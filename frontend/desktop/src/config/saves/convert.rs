@@ -0,0 +1,371 @@
+//! Conversion from and to the save file formats used by other emulators, so that progress can be
+//! carried over when migrating.
+//!
+//! - DeSmuME's `.dsv` files contain the raw save data (possibly padded), followed by a footer
+//!   describing it.
+//! - no$gba's `.sav` files are either raw, or start with a header optionally followed by
+//!   RLE-compressed data.
+//! - Raw dumps made by other tools may contain multiple mirrored copies of the save data, or be
+//!   padded to a bigger size.
+
+use dust_core::utils::BoxedByteSlice;
+use std::{fmt, fs, io, path::Path};
+
+const DESMUME_FOOTER_TEXT: &[u8] =
+    b"|<--Snip above here to create a raw sav by excluding this DeSmuME savedata footer:";
+const DESMUME_COOKIE: &[u8] = b"|-DESMUME SAVE-|";
+const DESMUME_FOOTER_LEN: usize = DESMUME_FOOTER_TEXT.len() + 6 * 4 + DESMUME_COOKIE.len();
+
+/// The save sizes DeSmuME recognizes; the footer's type field is an index into this list.
+const DESMUME_SAVE_SIZES: &[usize] = &[
+    0x200, 0x2000, 0x1_0000, 0x8000, 0x4_0000, 0x8_0000, 0x10_0000, 0x20_0000, 0x40_0000,
+    0x80_0000, 0x100_0000, 0x200_0000, 0x400_0000,
+];
+
+const NO_CASH_MAGIC: &[u8] = b"NocashGbaBackupMediaSavDataFile\x1A";
+const NO_CASH_SRAM_ID: &[u8] = b"SRAM";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Raw,
+    Desmume,
+    NoCashGba,
+}
+
+impl Format {
+    pub fn detect(contents: &[u8]) -> Self {
+        if contents.starts_with(NO_CASH_MAGIC) {
+            Format::NoCashGba
+        } else if contents.ends_with(DESMUME_COOKIE) && contents.len() >= DESMUME_FOOTER_LEN {
+            Format::Desmume
+        } else {
+            Format::Raw
+        }
+    }
+
+    /// Picks the format to export to from the extension of the destination path.
+    pub fn from_export_path(path: &Path) -> Self {
+        if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("dsv"))
+        {
+            Format::Desmume
+        } else {
+            Format::Raw
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Format::Raw => "raw",
+            Format::Desmume => "DeSmuME",
+            Format::NoCashGba => "no$gba",
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(io::Error),
+    NoSaveMemory,
+    /// The file is in a known format, but its contents are inconsistent with it.
+    Malformed(Format),
+    /// The file contains a no$gba save for a kind of save memory other than the DS slot's.
+    UnsupportedNoCashMedia,
+    SizeMismatch {
+        len: usize,
+        expected: usize,
+    },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::Io(err) => write!(f, "I/O error: {err}"),
+            ImportError::NoSaveMemory => f.write_str("the current game has no save memory"),
+            ImportError::Malformed(format) => write!(f, "malformed {format} save file"),
+            ImportError::UnsupportedNoCashMedia => {
+                f.write_str("the no$gba save file doesn't contain DS slot save data")
+            }
+            ImportError::SizeMismatch { len, expected } => write!(
+                f,
+                "the save data is {len} B long, but the current game's save memory is {expected} B \
+                 long"
+            ),
+        }
+    }
+}
+
+fn read_u32_le(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().unwrap(),
+    ))
+}
+
+/// Returns the save data contained in a DeSmuME `.dsv` file, excluding the footer and any padding.
+fn strip_desmume_footer(contents: &[u8]) -> Option<&[u8]> {
+    let footer_start = contents.len().checked_sub(DESMUME_FOOTER_LEN)?;
+    let (data, footer) = contents.split_at(footer_start);
+    let fields = footer.strip_prefix(DESMUME_FOOTER_TEXT)?;
+    let used_len = read_u32_le(fields, 0)? as usize;
+    let padded_len = read_u32_le(fields, 4)? as usize;
+    let mem_len = read_u32_le(fields, 16)? as usize;
+    // The chip size is preferred when present, as the amount of data actually written by the game
+    // might not cover all of it
+    [mem_len, padded_len, used_len]
+        .into_iter()
+        .find(|&len| len != 0 && len <= data.len())
+        .map(|len| &data[..len])
+}
+
+/// Decodes the save data contained in a no$gba `.sav` file with a header, decompressing it if
+/// needed.
+///
+/// The data length stored in the file is checked against the expected one before decoding
+/// anything, so that a malformed file can't make it allocate an arbitrary amount of memory.
+fn unpack_no_cash(contents: &[u8], expected_len: usize) -> Result<Vec<u8>, ImportError> {
+    let malformed = || ImportError::Malformed(Format::NoCashGba);
+    let check_len = |len: u32| {
+        let len = len as usize;
+        if len == expected_len {
+            Ok(len)
+        } else {
+            Err(ImportError::SizeMismatch {
+                len,
+                expected: expected_len,
+            })
+        }
+    };
+    if contents.get(0x40..0x44) != Some(NO_CASH_SRAM_ID) {
+        return Err(ImportError::UnsupportedNoCashMedia);
+    }
+    match read_u32_le(contents, 0x44).ok_or_else(malformed)? {
+        0 => {
+            let len = check_len(read_u32_le(contents, 0x48).ok_or_else(malformed)?)?;
+            contents
+                .get(0x4C..0x4C + len)
+                .map(<[u8]>::to_vec)
+                .ok_or_else(malformed)
+        }
+        1 => {
+            let len = check_len(read_u32_le(contents, 0x4C).ok_or_else(malformed)?)?;
+            let mut result = Vec::with_capacity(len);
+            let mut data = contents.get(0x50..).ok_or_else(malformed)?;
+            loop {
+                match *data {
+                    [0, ..] => break,
+                    // Long run of a single byte, with a 16-bit length
+                    [0x80, len_low, len_high, value, ref rest @ ..] => {
+                        let run_len = u16::from_le_bytes([len_low, len_high]) as usize;
+                        result.resize(result.len() + run_len, value);
+                        data = rest;
+                    }
+                    // Short run of a single byte
+                    [control @ 0x81.., value, ref rest @ ..] => {
+                        result.resize(result.len() + (control - 0x80) as usize, value);
+                        data = rest;
+                    }
+                    // Literal bytes
+                    [control @ 1..=0x7F, ref rest @ ..] => {
+                        let literal_len = control as usize;
+                        let literal = rest.get(..literal_len).ok_or_else(malformed)?;
+                        result.extend_from_slice(literal);
+                        data = &rest[literal_len..];
+                    }
+                    _ => return Err(malformed()),
+                }
+                if result.len() > len {
+                    return Err(malformed());
+                }
+            }
+            if result.len() != len {
+                return Err(malformed());
+            }
+            Ok(result)
+        }
+        _ => Err(malformed()),
+    }
+}
+
+/// Fits raw save data to the expected save memory size, discarding extra data if it's only
+/// padding or mirrored copies of the actual contents (as some dumping tools produce).
+fn fit_raw(mut data: Vec<u8>, expected_len: usize) -> Result<Vec<u8>, ImportError> {
+    let len = data.len();
+    if len > expected_len {
+        let (contents, extra) = data.split_at(expected_len);
+        let is_padding = extra.iter().all(|&b| b == 0xFF) || extra.iter().all(|&b| b == 0);
+        let is_mirrored = extra
+            .chunks(expected_len)
+            .all(|chunk| chunk == &contents[..chunk.len()]);
+        if !is_padding && !is_mirrored {
+            return Err(ImportError::SizeMismatch {
+                len,
+                expected: expected_len,
+            });
+        }
+        data.truncate(expected_len);
+    } else if len < expected_len {
+        return Err(ImportError::SizeMismatch {
+            len,
+            expected: expected_len,
+        });
+    }
+    Ok(data)
+}
+
+/// Reads a save file in any of the supported formats, returning its format and the contained save
+/// data, fitted to the size of the current game's save memory.
+pub fn import(path: &Path, expected_len: usize) -> Result<(Format, BoxedByteSlice), ImportError> {
+    if expected_len == 0 {
+        return Err(ImportError::NoSaveMemory);
+    }
+    let contents = fs::read(path).map_err(ImportError::Io)?;
+    let format = Format::detect(&contents);
+    let data = match format {
+        Format::Raw => contents,
+        Format::Desmume => strip_desmume_footer(&contents)
+            .ok_or(ImportError::Malformed(Format::Desmume))?
+            .to_vec(),
+        Format::NoCashGba => unpack_no_cash(&contents, expected_len)?,
+    };
+    let data = fit_raw(data, expected_len)?;
+    let mut result = BoxedByteSlice::new_zeroed(data.len());
+    result.copy_from_slice(&data);
+    Ok((format, result))
+}
+
+/// Converts raw save data to the given format; no$gba files are exported as raw ones, which it
+/// can read as well.
+pub fn export(data: &[u8], format: Format) -> Vec<u8> {
+    let mut result = data.to_vec();
+    if format == Format::Desmume {
+        let len = data.len() as u32;
+        let type_ = DESMUME_SAVE_SIZES
+            .iter()
+            .position(|&size| size == data.len())
+            .map_or(0xFF, |i| i as u32);
+        // Address size in bytes used by the save chip's commands, as DeSmuME expects it
+        let addr_len: u32 = match data.len() {
+            0x200 => 1,
+            0x2000..=0x1_0000 => 2,
+            _ => 3,
+        };
+        result.extend_from_slice(DESMUME_FOOTER_TEXT);
+        for field in [len, len, type_, addr_len, len, 0] {
+            result.extend_from_slice(&field.to_le_bytes());
+        }
+        result.extend_from_slice(DESMUME_COOKIE);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_cash_file(method: u32, len: u32, data: &[u8]) -> Vec<u8> {
+        let mut file = NO_CASH_MAGIC.to_vec();
+        file.resize(0x40, 0);
+        file.extend_from_slice(NO_CASH_SRAM_ID);
+        file.extend_from_slice(&method.to_le_bytes());
+        if method == 1 {
+            // Compressed files have an additional unused word before the length
+            file.extend_from_slice(&0_u32.to_le_bytes());
+        }
+        file.extend_from_slice(&len.to_le_bytes());
+        file.extend_from_slice(data);
+        file
+    }
+
+    #[test]
+    fn detect_formats() {
+        assert_eq!(Format::detect(&[0xFF; 0x200]), Format::Raw);
+        assert_eq!(Format::detect(&[]), Format::Raw);
+        assert_eq!(
+            Format::detect(&no_cash_file(0, 4, &[1, 2, 3, 4])),
+            Format::NoCashGba
+        );
+        assert_eq!(
+            Format::detect(&export(&[0; 0x200], Format::Desmume)),
+            Format::Desmume
+        );
+        // A raw save that happens to end with the cookie, but is too short to contain a footer
+        assert_eq!(Format::detect(DESMUME_COOKIE), Format::Raw);
+    }
+
+    #[test]
+    fn desmume_round_trip() {
+        let data = (0..0x2000).map(|i| i as u8).collect::<Vec<_>>();
+        let file = export(&data, Format::Desmume);
+        assert_eq!(file.len(), data.len() + DESMUME_FOOTER_LEN);
+        assert_eq!(strip_desmume_footer(&file), Some(&data[..]));
+    }
+
+    #[test]
+    fn no_cash_raw() {
+        let file = no_cash_file(0, 4, &[1, 2, 3, 4]);
+        assert_eq!(unpack_no_cash(&file, 4).unwrap(), [1, 2, 3, 4]);
+        // Truncated data
+        let file = no_cash_file(0, 8, &[1, 2, 3, 4]);
+        assert!(matches!(
+            unpack_no_cash(&file, 8),
+            Err(ImportError::Malformed(Format::NoCashGba))
+        ));
+    }
+
+    #[test]
+    fn no_cash_rle() {
+        let data = [
+            &[3, 0xA, 0xB, 0xC][..],   // Literal bytes
+            &[0x84, 0x11],             // Short run
+            &[0x80, 0x00, 0x01, 0x22], // Long run
+            &[0],                      // End
+        ]
+        .concat();
+        let file = no_cash_file(1, 3 + 4 + 0x100, &data);
+        let mut expected = vec![0xA, 0xB, 0xC, 0x11, 0x11, 0x11, 0x11];
+        expected.resize(expected.len() + 0x100, 0x22);
+        assert_eq!(unpack_no_cash(&file, expected.len()).unwrap(), expected);
+    }
+
+    #[test]
+    fn no_cash_rle_malformed() {
+        // Decompressed data shorter than the stored length
+        let file = no_cash_file(1, 4, &[0x82, 0x11, 0]);
+        assert!(matches!(
+            unpack_no_cash(&file, 4),
+            Err(ImportError::Malformed(Format::NoCashGba))
+        ));
+        // Runs going past the stored length
+        let file = no_cash_file(1, 2, &[0x80, 0xFF, 0xFF, 0x11, 0]);
+        assert!(matches!(
+            unpack_no_cash(&file, 2),
+            Err(ImportError::Malformed(Format::NoCashGba))
+        ));
+        // Missing terminator
+        let file = no_cash_file(1, 2, &[0x82, 0x11]);
+        assert!(matches!(
+            unpack_no_cash(&file, 2),
+            Err(ImportError::Malformed(Format::NoCashGba))
+        ));
+        // Lengths not matching the save chip are rejected before decoding anything
+        let file = no_cash_file(1, u32::MAX, &[0x80, 0xFF, 0xFF, 0x11, 0]);
+        assert!(matches!(
+            unpack_no_cash(&file, 0x200),
+            Err(ImportError::SizeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn fit_mirrored_and_padded() {
+        let data = vec![1, 2, 3, 4];
+        let mirrored = [&data[..], &data[..]].concat();
+        assert_eq!(fit_raw(mirrored, 4).unwrap(), data);
+        let padded = [&data[..], &[0xFF; 4][..]].concat();
+        assert_eq!(fit_raw(padded, 4).unwrap(), data);
+        assert!(fit_raw(vec![1, 2, 3, 4, 5, 6, 7, 8], 4).is_err());
+        assert!(fit_raw(vec![1, 2], 4).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use super::{LaunchWarning, ResolvedSysPaths, SystemFile};
+use crate::utils::HomePathBuf;
+use dust_core::{
+    cpu::{arm7, arm9},
+    ds_slot::rom::key1,
+    spi::firmware,
+    Model,
+};
+use std::{fmt, fs, io};
+
+// CRC32s of the known good retail DS BIOS dumps
+const RETAIL_ARM7_BIOS_CRC32: u32 = 0x1280_F0D5;
+const RETAIL_ARM9_BIOS_CRC32: u32 = 0x2AB2_3573;
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0_u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = crc >> 1 ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+pub enum Status {
+    /// The file matches a known good dump.
+    Known,
+    /// The file is valid, but doesn't match any known dump (i.e. because it comes from a debug or
+    /// DSi unit, or because it was modified).
+    Unknown,
+    Invalid(String),
+}
+
+pub struct FileInfo {
+    pub len: u64,
+    pub crc32: u32,
+    pub status: Status,
+    /// The console model detected from the file's contents, only available for firmware files.
+    pub model: Option<Model>,
+}
+
+pub enum FileError {
+    MissingPath,
+    Io(io::Error),
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileError::MissingPath => write!(f, "No path specified"),
+            FileError::Io(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+pub type FileResult = Result<FileInfo, FileError>;
+
+/// Reads the given system file and checks its contents, returning its size, its CRC32 hash and
+/// whether it looks like a valid dump.
+pub fn inspect(path: Option<&HomePathBuf>, file: SystemFile) -> FileResult {
+    let contents = fs::read(&path.ok_or(FileError::MissingPath)?.0).map_err(FileError::Io)?;
+    let len = contents.len() as u64;
+    let crc32 = crc32(&contents);
+    let mut model = None;
+
+    let status = match file {
+        SystemFile::Arm7Bios | SystemFile::Arm9Bios => {
+            let (expected_len, retail_crc32) = if file == SystemFile::Arm7Bios {
+                (arm7::BIOS_SIZE, RETAIL_ARM7_BIOS_CRC32)
+            } else {
+                (arm9::BIOS_SIZE, RETAIL_ARM9_BIOS_CRC32)
+            };
+            if contents.len() != expected_len {
+                Status::Invalid(format!(
+                    "Invalid size: expected {expected_len} bytes, got {len} bytes"
+                ))
+            } else if crc32 == retail_crc32 {
+                Status::Known
+            } else {
+                Status::Unknown
+            }
+        }
+
+        SystemFile::Firmware => match firmware::detect_model(&contents) {
+            Ok(detected_model) => {
+                model = detected_model;
+                match firmware::verify(&contents, detected_model.unwrap_or_default()) {
+                    Ok(()) => Status::Unknown,
+                    Err(err) => Status::Invalid(LaunchWarning::InvalidFirmware(err).to_string()),
+                }
+            }
+            Err(_) => Status::Invalid(format!(
+                "Invalid size: expected 131072, 262144 or 524288 bytes, got {len} bytes"
+            )),
+        },
+
+        SystemFile::Key1Table => {
+            if contents.len() != key1::TABLE_SIZE {
+                Status::Invalid(format!(
+                    "Invalid size: expected {} bytes, got {len} bytes",
+                    key1::TABLE_SIZE
+                ))
+            } else {
+                Status::Unknown
+            }
+        }
+    };
+
+    Ok(FileInfo {
+        len,
+        crc32,
+        status,
+        model,
+    })
+}
+
+pub struct SetInfo {
+    pub arm7_bios: FileResult,
+    pub arm9_bios: FileResult,
+    pub firmware: FileResult,
+    /// Only present if the optional KEY1 table file exists.
+    pub key1_table: Option<FileResult>,
+}
+
+impl SetInfo {
+    pub fn new(paths: &ResolvedSysPaths) -> Self {
+        SetInfo {
+            arm7_bios: inspect(paths.arm7_bios.as_ref(), SystemFile::Arm7Bios),
+            arm9_bios: inspect(paths.arm9_bios.as_ref(), SystemFile::Arm9Bios),
+            firmware: inspect(paths.firmware.as_ref(), SystemFile::Firmware),
+            key1_table: paths
+                .key1_table
+                .as_ref()
+                .filter(|path| path.0.exists())
+                .map(|path| inspect(Some(path), SystemFile::Key1Table)),
+        }
+    }
+
+    pub fn model(&self) -> Option<Model> {
+        self.firmware.as_ref().ok().and_then(|info| info.model)
+    }
+}
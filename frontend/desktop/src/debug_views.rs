@@ -8,12 +8,20 @@ mod cpu_memory;
 use cpu_memory::CpuMemory;
 mod cpu_disasm;
 use cpu_disasm::CpuDisasm;
-mod palettes_2d;
-use palettes_2d::Palettes2d;
+mod palettes;
+use palettes::Palettes;
 mod bg_maps_2d;
 use bg_maps_2d::BgMaps2d;
+mod layers_2d;
+use layers_2d::Layers2d;
+mod oam_2d;
+use oam_2d::Oam2d;
+mod vram_banks;
+use vram_banks::VramBanks;
 mod audio_channels;
 use audio_channels::AudioChannels;
+mod audio_mixer;
+use audio_mixer::AudioMixer;
 mod ds_rom_info;
 use ds_rom_info::DsRomInfo;
 mod fs;
@@ -1085,15 +1093,19 @@ macro_rules! declare_structs {
 declare_structs!(
     [
         (arm7_state, CpuState<false>, InitArm7State, DestroyArm7State, Arm7StateVisibility, Arm7StateCustom),
-        (arm9_state, CpuState<true>, InitArm9State, DestroyArm9State, Arm9StateVisibility, Arm9StateCustom)
+        (arm9_state, CpuState<true>, InitArm9State, DestroyArm9State, Arm9StateVisibility, Arm9StateCustom),
+        (audio_mixer, AudioMixer, InitAudioMixer, DestroyAudioMixer, AudioMixerVisibility, AudioMixerCustom)
     ],
     [
         (arm7_memory, CpuMemory<false>, InitArm7Memory, DestroyArm7Memory, Arm7MemoryVisibility, Arm7MemoryCustom),
         (arm9_memory, CpuMemory<true>, InitArm9Memory, DestroyArm9Memory, Arm9MemoryVisibility, Arm9MemoryCustom),
         (arm7_disasm, CpuDisasm<false>, InitArm7Disasm, DestroyArm7Disasm, Arm7DisasmVisibility, Arm7DisasmCustom),
         (arm9_disasm, CpuDisasm<true>, InitArm9Disasm, DestroyArm9Disasm, Arm9DisasmVisibility, Arm9DisasmCustom),
-        (palettes_2d, Palettes2d, InitPalettes2d, DestroyPalettes2d, Palettes2dVisibility, Palettes2dCustom),
+        (palettes, Palettes, InitPalettes, DestroyPalettes, PalettesVisibility, PalettesCustom),
         (bg_maps_2d, BgMaps2d, InitBgMaps2d, DestroyBgMaps2d, BgMaps2dVisibility, BgMaps2dCustom),
+        (layers_2d, Layers2d, InitLayers2d, DestroyLayers2d, Layers2dVisibility, Layers2dCustom),
+        (oam_2d, Oam2d, InitOam2d, DestroyOam2d, Oam2dVisibility, Oam2dCustom),
+        (vram_banks, VramBanks, InitVramBanks, DestroyVramBanks, VramBanksVisibility, VramBanksCustom),
         (audio_channels, AudioChannels, InitAudioChannels, DestroyAudioChannels, AudioChannelsVisibility, AudioChannelsCustom)
     ],
     [
@@ -1103,3 +1115,16 @@ declare_structs!(
         (fs, Fs, InitFs, DestroyFs, FsVisibility, FsMessage, FsNotif)
     ]
 );
+
+impl UiState {
+    pub fn open_arm9_disasm(&mut self, window: &mut Window, mut messages: impl Messages) {
+        let mut key = 1;
+        while self.arm9_disasm.contains_key(&key) {
+            key += 1;
+        }
+        let view = CpuDisasm::<true>::new(window);
+        let data = view.emu_state();
+        messages.push(Message::InitArm9Disasm(key, data, true));
+        self.arm9_disasm.insert(key, (Some(view), true));
+    }
+}
@@ -0,0 +1,221 @@
+use super::{BaseView, FrameDataSlot, FrameView, FrameViewMessages, SingletonView};
+use crate::ui::window::Window;
+use dust_core::{
+    audio::channel::{Control, Format, Index as ChannelIndex, RepeatMode},
+    cpu,
+    emu::Emu,
+};
+use imgui::{StyleColor, TableFlags};
+use std::array;
+
+// The channel timers are clocked at half the ARM7 bus clock
+const CHANNEL_TIMER_CLOCK_RATE: u32 = 1 << 24;
+
+#[derive(Clone, Copy)]
+pub struct ChannelData {
+    control: Control,
+    timer_reload: u16,
+    cur_addr: u32,
+}
+
+pub struct EmuState;
+
+impl super::FrameViewEmuState for EmuState {
+    type InitData = ();
+    type Message = u16;
+    type FrameData = [ChannelData; 16];
+
+    fn new<E: cpu::Engine>(_data: Self::InitData, _visible: bool, _emu: &mut Emu<E>) -> Self {
+        EmuState
+    }
+
+    fn destroy<E: cpu::Engine>(self, emu: &mut Emu<E>) {
+        // Channels muted from this view shouldn't stay silent once it's closed
+        emu.audio.set_muted_channels(0);
+    }
+
+    fn handle_message<E: cpu::Engine>(&mut self, muted_channels: Self::Message, emu: &mut Emu<E>) {
+        emu.audio.set_muted_channels(muted_channels);
+    }
+
+    fn prepare_frame_data<'a, E: cpu::Engine, S: FrameDataSlot<'a, Self::FrameData>>(
+        &mut self,
+        emu: &mut Emu<E>,
+        frame_data: S,
+    ) {
+        frame_data.insert(array::from_fn(|i| {
+            let channel = &emu.audio.channels[i];
+            ChannelData {
+                control: channel.control(),
+                timer_reload: channel.timer_reload(),
+                cur_addr: channel.cur_addr(),
+            }
+        }));
+    }
+}
+
+fn format_name(format: Format) -> &'static str {
+    match format {
+        Format::Pcm8 => "PCM8",
+        Format::Pcm16 => "PCM16",
+        Format::Adpcm => "IMA-ADPCM",
+        Format::PsgWave => "PSG wave",
+        Format::PsgNoise => "PSG noise",
+        Format::Silence => "Invalid",
+    }
+}
+
+/// Lists the state of all 16 sound channels at a glance, and allows muting or soloing any of them
+/// in the mixer (i.e. to isolate individual instruments when ripping music); the waveform of a
+/// single channel can be inspected in the audio channel viewer.
+pub struct AudioMixer {
+    muted: u16,
+    soloed: u16,
+    data: Option<[ChannelData; 16]>,
+}
+
+impl AudioMixer {
+    fn effective_muted_channels(&self) -> u16 {
+        if self.soloed != 0 {
+            self.muted | !self.soloed
+        } else {
+            self.muted
+        }
+    }
+}
+
+impl SingletonView for AudioMixer {}
+
+impl BaseView for AudioMixer {
+    const MENU_NAME: &'static str = "Audio mixer";
+}
+
+impl FrameView for AudioMixer {
+    type EmuState = EmuState;
+
+    fn new(_window: &mut Window) -> Self {
+        AudioMixer {
+            muted: 0,
+            soloed: 0,
+            data: None,
+        }
+    }
+
+    fn emu_state(&self) -> <Self::EmuState as super::FrameViewEmuState>::InitData {}
+
+    fn update_from_frame_data(
+        &mut self,
+        frame_data: &<Self::EmuState as super::FrameViewEmuState>::FrameData,
+        _window: &mut Window,
+    ) {
+        self.data = Some(*frame_data);
+    }
+
+    fn draw(
+        &mut self,
+        ui: &imgui::Ui,
+        window: &mut Window,
+        mut messages: impl FrameViewMessages<Self>,
+    ) {
+        let Some(data) = self.data.as_ref() else {
+            return;
+        };
+
+        let prev_muted_channels = self.effective_muted_channels();
+        let mut muted = self.muted;
+        let mut soloed = self.soloed;
+
+        if ui.button("Unmute all") {
+            muted = 0;
+        }
+        ui.same_line();
+        if ui.button("Clear solo") {
+            soloed = 0;
+        }
+
+        if let Some(_token) = ui.begin_table_with_flags(
+            "channels",
+            9,
+            TableFlags::BORDERS | TableFlags::ROW_BG | TableFlags::SIZING_FIXED_FIT,
+        ) {
+            for name in [
+                "Channel",
+                "Mute",
+                "Solo",
+                "Format",
+                "Frequency",
+                "Volume",
+                "Pan",
+                "Address",
+                "Repeat",
+            ] {
+                ui.table_setup_column(name);
+            }
+            ui.table_headers_row();
+
+            for (i, channel) in data.iter().enumerate() {
+                let control = channel.control;
+
+                ui.table_next_row();
+                ui.table_next_column();
+                if control.running() {
+                    ui.text(format!("{i}"));
+                } else {
+                    ui.text_colored(ui.style_color(StyleColor::TextDisabled), format!("{i}"));
+                }
+
+                ui.table_next_column();
+                let mut is_muted = muted & 1 << i != 0;
+                if ui.checkbox(&format!("##mute_{i}"), &mut is_muted) {
+                    muted ^= 1 << i;
+                }
+
+                ui.table_next_column();
+                let mut is_soloed = soloed & 1 << i != 0;
+                if ui.checkbox(&format!("##solo_{i}"), &mut is_soloed) {
+                    soloed ^= 1 << i;
+                }
+
+                let _mono_font_token = ui.push_font(window.imgui.mono_font);
+
+                ui.table_next_column();
+                ui.text(format_name(control.format(ChannelIndex::new(i as u8))));
+
+                ui.table_next_column();
+                ui.text(format!(
+                    "{} Hz",
+                    CHANNEL_TIMER_CLOCK_RATE / (0x1_0000 - channel.timer_reload as u32)
+                ));
+
+                ui.table_next_column();
+                ui.text(format!(
+                    "{} >> {}",
+                    control.volume(),
+                    control.volume_shift()
+                ));
+
+                ui.table_next_column();
+                ui.text(format!("{}", control.pan() as i16 - 64));
+
+                ui.table_next_column();
+                ui.text(format!("{:#09X}", channel.cur_addr));
+
+                ui.table_next_column();
+                ui.text(match control.repeat_mode() {
+                    RepeatMode::Manual => "Manual",
+                    RepeatMode::OneShot => "One-shot",
+                    RepeatMode::LoopInfinite => "Loop",
+                });
+            }
+        }
+
+        if (muted, soloed) != (self.muted, self.soloed) {
+            self.muted = muted;
+            self.soloed = soloed;
+            let muted_channels = self.effective_muted_channels();
+            if muted_channels != prev_muted_channels {
+                messages.push(muted_channels);
+            }
+        }
+    }
+}
@@ -289,6 +289,11 @@ impl DisassemblyView {
         }
     }
 
+    #[inline]
+    pub fn selected_addr(&self) -> Addr {
+        self.selected_addr
+    }
+
     pub fn set_selected_addr(&mut self, addr: Addr) {
         self.selected_addr = addr.clamp(self.addr_range.start, self.addr_range.end);
         self.selected_addr -= (self.selected_addr - self.addr_range.start) % self.bytes_per_line;
@@ -7,14 +7,37 @@ use super::{
     InstanceableView,
 };
 use crate::ui::window::Window;
+#[cfg(feature = "lockstep-trace")]
+use dust_core::cpu::{
+    disasm::disassemble_single,
+    trace::{Core as TraceCore, EntryKind as TraceEntryKind},
+};
 use dust_core::{
     cpu::{
-        self,
+        self, debug,
         disasm::{disassemble_range, Instr},
+        Schedule,
     },
     emu::Emu,
+    utils::schedule::RawTimestamp,
 };
-use imgui::StyleColor;
+use imgui::{MouseButton, StyleColor};
+use std::{cell::RefCell, rc::Rc};
+
+const BREAKPOINT_COLOR: [f32; 4] = [0.9, 0.25, 0.25, 1.0];
+const PC_COLOR: [f32; 4] = [1.0, 0.85, 0.3, 1.0];
+
+/// How many of the last instructions executed by the CPU are shown in the trace.
+#[cfg(feature = "lockstep-trace")]
+const TRACE_LEN: usize = 0x400;
+
+#[cfg(feature = "lockstep-trace")]
+#[derive(Clone)]
+pub struct TraceInstr {
+    time: u64,
+    thumb: bool,
+    instr: Instr,
+}
 
 pub struct DisassemblyResults {
     visible_addrs: RangeInclusive<Addr>,
@@ -22,16 +45,116 @@ pub struct DisassemblyResults {
     cpu_thumb: bool,
     thumb: bool,
     instrs: Vec<Instr>,
+    breakpoints: Vec<u32>,
+    /// The last executed instructions, if both the trace is shown and the emulator is recording
+    /// it.
+    #[cfg(feature = "lockstep-trace")]
+    trace: Option<Vec<TraceInstr>>,
+}
+
+impl DisassemblyResults {
+    fn new() -> Self {
+        DisassemblyResults {
+            visible_addrs: (0, 0).into(),
+            cpu_pc: 0,
+            cpu_thumb: false,
+            thumb: false,
+            instrs: Vec::new(),
+            breakpoints: Vec::new(),
+            #[cfg(feature = "lockstep-trace")]
+            trace: None,
+        }
+    }
+
+    fn cur_instr_addr(&self) -> u32 {
+        self.cpu_pc.wrapping_sub(8 >> self.cpu_thumb as u8)
+    }
+}
+
+pub enum Message {
+    UpdateView {
+        visible_addrs: RangeInclusive<Addr>,
+        thumb: bool,
+    },
+    ToggleBreakpoint(u32),
+    /// Resumes the emulator until the CPU reaches the given address.
+    RunTo(u32),
+    #[cfg(feature = "lockstep-trace")]
+    ShowTrace(bool),
 }
 
 pub struct EmuState<const ARM9: bool> {
     visible_addrs: RangeInclusive<Addr>,
     thumb: bool,
+    /// A breakpoint only added to implement run-to-cursor, removed once hit.
+    temp_breakpoint: Option<u32>,
+    #[cfg(feature = "lockstep-trace")]
+    show_trace: bool,
+}
+
+/// Stops the CPUs at breakpoints, keeping track of the last one each CPU stopped at.
+struct BreakpointHandler {
+    last_stops: [Option<(u32, RawTimestamp)>; 2],
+}
+
+impl<E: cpu::Engine> debug::EventHandler<E> for BreakpointHandler {
+    fn breakpoint(&mut self, emu: &mut Emu<E>, core: cpu::Core, addr: u32) -> bool {
+        let (last_stop, cur_time) = match core {
+            cpu::Core::Arm7 => (&mut self.last_stops[0], emu.arm7.schedule.cur_time().0),
+            cpu::Core::Arm9 => (&mut self.last_stops[1], emu.arm9.schedule.cur_time().0),
+        };
+        // After being resumed, the CPU checks the breakpoint it stopped at again before executing
+        // its instruction; let it through once.
+        let stop = (addr, cur_time);
+        if *last_stop == Some(stop) {
+            *last_stop = None;
+            return false;
+        }
+        *last_stop = Some(stop);
+        true
+    }
+}
+
+macro_rules! cpu {
+    ($emu: expr, $cpu: ident => $e: expr) => {
+        if ARM9 {
+            let $cpu = &mut $emu.arm9;
+            $e
+        } else {
+            let $cpu = &mut $emu.arm7;
+            $e
+        }
+    };
+}
+
+impl<const ARM9: bool> EmuState<ARM9> {
+    fn cur_instr_addr<E: cpu::Engine>(emu: &mut Emu<E>) -> u32 {
+        cpu!(emu, cpu => cpu.r15().wrapping_sub(8 >> cpu.cpsr().thumb_state() as u8))
+    }
+
+    /// Installs a debug event handler stopping the CPUs at breakpoints, unless one was already set
+    /// (i.e. by the GDB server or another disassembly view).
+    fn ensure_event_handler<E: cpu::Engine>(emu: &mut Emu<E>) {
+        if cpu!(emu, cpu => cpu.breakpoint_hook().is_some()) {
+            return;
+        }
+        let handler: Rc<RefCell<dyn debug::EventHandler<E>>> =
+            Rc::new(RefCell::new(BreakpointHandler {
+                last_stops: [None; 2],
+            }));
+        emu.set_debug_event_handler(Some(handler));
+    }
+
+    fn remove_temp_breakpoint<E: cpu::Engine>(&mut self, emu: &mut Emu<E>) {
+        if let Some(addr) = self.temp_breakpoint.take() {
+            cpu!(emu, cpu => cpu.remove_breakpoint(addr));
+        }
+    }
 }
 
 impl<const ARM9: bool> super::FrameViewEmuState for EmuState<ARM9> {
     type InitData = RangeInclusive<Addr>;
-    type Message = (RangeInclusive<Addr>, bool);
+    type Message = Message;
     type FrameData = DisassemblyResults;
 
     fn new<E: cpu::Engine>(
@@ -42,16 +165,57 @@ impl<const ARM9: bool> super::FrameViewEmuState for EmuState<ARM9> {
         EmuState {
             visible_addrs,
             thumb: false,
+            temp_breakpoint: None,
+            #[cfg(feature = "lockstep-trace")]
+            show_trace: false,
         }
     }
 
-    fn handle_message<E: cpu::Engine>(
-        &mut self,
-        (visible_addrs, thumb): Self::Message,
-        _emu: &mut Emu<E>,
-    ) {
-        self.visible_addrs = visible_addrs;
-        self.thumb = thumb;
+    fn destroy<E: cpu::Engine>(mut self, emu: &mut Emu<E>) {
+        self.remove_temp_breakpoint(emu);
+    }
+
+    fn handle_message<E: cpu::Engine>(&mut self, message: Self::Message, emu: &mut Emu<E>) {
+        match message {
+            Message::UpdateView {
+                visible_addrs,
+                thumb,
+            } => {
+                self.visible_addrs = visible_addrs;
+                self.thumb = thumb;
+            }
+
+            Message::ToggleBreakpoint(addr) => {
+                if self.temp_breakpoint == Some(addr) {
+                    // Keep the run-to-cursor breakpoint as a regular one
+                    self.temp_breakpoint = None;
+                } else if cpu!(emu, cpu => cpu.breakpoints().binary_search(&addr).is_ok()) {
+                    cpu!(emu, cpu => cpu.remove_breakpoint(addr));
+                } else {
+                    Self::ensure_event_handler(emu);
+                    cpu!(emu, cpu => cpu.add_breakpoint(addr));
+                }
+            }
+
+            Message::RunTo(addr) => {
+                self.remove_temp_breakpoint(emu);
+                if Self::cur_instr_addr(emu) != addr {
+                    Self::ensure_event_handler(emu);
+                    if cpu!(emu, cpu => cpu.breakpoints().binary_search(&addr).is_err()) {
+                        cpu!(emu, cpu => cpu.add_breakpoint(addr));
+                        self.temp_breakpoint = Some(addr);
+                    }
+                    // Clearing the stopped flags resumes the emulator if it was paused
+                    emu.arm7.is_stopped = false;
+                    emu.arm9.is_stopped = false;
+                }
+            }
+
+            #[cfg(feature = "lockstep-trace")]
+            Message::ShowTrace(show_trace) => {
+                self.show_trace = show_trace;
+            }
+        }
     }
 
     fn prepare_frame_data<'a, E: cpu::Engine, S: FrameDataSlot<'a, Self::FrameData>>(
@@ -59,13 +223,13 @@ impl<const ARM9: bool> super::FrameViewEmuState for EmuState<ARM9> {
         emu: &mut Emu<E>,
         frame_data: S,
     ) {
-        let frame_data = frame_data.get_or_insert_with(|| DisassemblyResults {
-            visible_addrs: (0, 0).into(),
-            cpu_pc: 0,
-            cpu_thumb: false,
-            thumb: false,
-            instrs: Vec::new(),
-        });
+        if let Some(addr) = self.temp_breakpoint {
+            if addr == Self::cur_instr_addr(emu) {
+                self.remove_temp_breakpoint(emu);
+            }
+        }
+
+        let frame_data = frame_data.get_or_insert_with(DisassemblyResults::new);
         let (r15, cpsr) = if ARM9 {
             (emu.arm9.r15(), emu.arm9.cpsr())
         } else {
@@ -85,6 +249,43 @@ impl<const ARM9: bool> super::FrameViewEmuState for EmuState<ARM9> {
             self.thumb,
             &mut frame_data.instrs,
         );
+        frame_data.breakpoints.clear();
+        cpu!(emu, cpu => frame_data.breakpoints.extend_from_slice(cpu.breakpoints()));
+
+        #[cfg(feature = "lockstep-trace")]
+        {
+            frame_data.trace = (self.show_trace && emu.trace.is_enabled()).then(|| {
+                let core = if ARM9 {
+                    TraceCore::Arm9
+                } else {
+                    TraceCore::Arm7
+                };
+                let mut execs = emu
+                    .trace
+                    .entries()
+                    .into_iter()
+                    .rev()
+                    .filter_map(|entry| match entry.kind {
+                        TraceEntryKind::Exec { pc, thumb } if entry.core == core => {
+                            Some((entry.time.0, pc, thumb))
+                        }
+                        _ => None,
+                    })
+                    .take(TRACE_LEN)
+                    .collect::<Vec<_>>();
+                execs.reverse();
+                // NOTE: Instructions are decoded from the current memory contents, which could
+                // differ from what was executed for self-modifying code.
+                execs
+                    .into_iter()
+                    .map(|(time, pc, thumb)| TraceInstr {
+                        time,
+                        thumb,
+                        instr: disassemble_single::<_, ARM9>(emu, pc, thumb),
+                    })
+                    .collect()
+            });
+        }
     }
 }
 
@@ -96,6 +297,8 @@ pub struct CpuDisasm<const ARM9: bool> {
     last_visible_addrs: RangeInclusive<Addr>,
     last_bytes_per_line: u8,
     disasm_results: DisassemblyResults,
+    #[cfg(feature = "lockstep-trace")]
+    show_trace: bool,
 }
 
 impl<const ARM9: bool> BaseView for CpuDisasm<ARM9> {
@@ -118,13 +321,9 @@ impl<const ARM9: bool> FrameView for CpuDisasm<ARM9> {
             last_visible_addrs: (0, 0).into(),
             last_bytes_per_line: 4,
             thumb: false,
-            disasm_results: DisassemblyResults {
-                visible_addrs: (0, 0).into(),
-                cpu_pc: 0,
-                cpu_thumb: false,
-                thumb: false,
-                instrs: Vec::new(),
-            },
+            disasm_results: DisassemblyResults::new(),
+            #[cfg(feature = "lockstep-trace")]
+            show_trace: false,
         }
     }
 
@@ -145,6 +344,12 @@ impl<const ARM9: bool> FrameView for CpuDisasm<ARM9> {
         self.disasm_results
             .instrs
             .extend_from_slice(&frame_data.instrs);
+        self.disasm_results.breakpoints.clear();
+        self.disasm_results
+            .breakpoints
+            .extend_from_slice(&frame_data.breakpoints);
+        #[cfg(feature = "lockstep-trace")]
+        self.disasm_results.trace.clone_from(&frame_data.trace);
     }
 
     fn draw(
@@ -165,17 +370,65 @@ impl<const ARM9: bool> FrameView for CpuDisasm<ARM9> {
         ui.same_line();
 
         if ui.button("Disassemble at PC") {
-            self.view.set_selected_addr(
-                self.disasm_results
-                    .cpu_pc
-                    .wrapping_sub(8 >> self.disasm_results.cpu_thumb as u8) as Addr,
-            );
+            self.view
+                .set_selected_addr(self.disasm_results.cur_instr_addr() as Addr);
             self.thumb = self.disasm_results.cpu_thumb;
             emu_state_changed = true;
         }
 
+        ui.same_line();
+
+        if ui.button("Run to cursor") {
+            messages.push(Message::RunTo(self.view.selected_addr() as u32));
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text("Resume until the CPU reaches the selected instruction");
+        }
+
+        #[cfg(feature = "lockstep-trace")]
+        {
+            ui.same_line();
+            if ui.checkbox("Trace", &mut self.show_trace) {
+                messages.push(Message::ShowTrace(self.show_trace));
+            }
+        }
+
         ui.separator();
 
+        #[cfg(feature = "lockstep-trace")]
+        if self.show_trace {
+            let disabled_color = ui.style_color(StyleColor::TextDisabled);
+            ui.child_window("trace")
+                .size([0.0, ui.text_line_height_with_spacing() * 10.0])
+                .build(|| {
+                    let Some(trace) = &self.disasm_results.trace else {
+                        ui.text_colored(
+                            disabled_color,
+                            "Enable the lockstep trace from the Debug menu to record executed \
+                             instructions.",
+                        );
+                        return;
+                    };
+                    let follow = ui.scroll_y() >= ui.scroll_max_y();
+                    for entry in trace {
+                        ui.text_colored(
+                            disabled_color,
+                            format!("{:>12} {:08X}:", entry.time, entry.instr.addr),
+                        );
+                        ui.same_line();
+                        ui.text(if entry.thumb {
+                            format!("{:04X}     {}", entry.instr.raw, entry.instr.opcode)
+                        } else {
+                            format!("{:08X} {}", entry.instr.raw, entry.instr.opcode)
+                        });
+                    }
+                    if follow {
+                        ui.set_scroll_here_y_with_ratio(1.0);
+                    }
+                });
+            ui.separator();
+        }
+
         self.view.handle_options_right_click(ui);
 
         let instr_size_shift = 2 - self.disasm_results.thumb as u8;
@@ -185,7 +438,60 @@ impl<const ARM9: bool> FrameView for CpuDisasm<ARM9> {
             self.last_bytes_per_line = bytes_per_line;
             self.view.set_bytes_per_line(bytes_per_line as Addr);
         }
+        let cur_instr_addr = self.disasm_results.cur_instr_addr();
         self.view.draw_callbacks(ui, None, &mut (), |ui, _, addr| {
+            // Breakpoint and current instruction markers, clicking toggles a breakpoint
+            let marker_size = ui.text_line_height();
+            let marker_start = ui.cursor_screen_pos();
+            let marker_end = [marker_start[0] + marker_size, marker_start[1] + marker_size];
+            let draw_list = ui.get_window_draw_list();
+            if self
+                .disasm_results
+                .breakpoints
+                .binary_search(&(addr as u32))
+                .is_ok()
+            {
+                draw_list
+                    .add_circle(
+                        [
+                            marker_start[0] + marker_size * 0.5,
+                            marker_start[1] + marker_size * 0.5,
+                        ],
+                        marker_size * 0.3,
+                        BREAKPOINT_COLOR,
+                    )
+                    .filled(true)
+                    .build();
+            }
+            if addr as u32 == cur_instr_addr {
+                draw_list
+                    .add_triangle(
+                        [
+                            marker_start[0] + marker_size * 0.25,
+                            marker_start[1] + marker_size * 0.2,
+                        ],
+                        [
+                            marker_start[0] + marker_size * 0.25,
+                            marker_start[1] + marker_size * 0.8,
+                        ],
+                        [
+                            marker_start[0] + marker_size * 0.8,
+                            marker_start[1] + marker_size * 0.5,
+                        ],
+                        PC_COLOR,
+                    )
+                    .filled(true)
+                    .build();
+            }
+            if ui.is_window_hovered()
+                && ui.is_mouse_hovering_rect(marker_start, marker_end)
+                && ui.is_mouse_clicked(MouseButton::Left)
+            {
+                messages.push(Message::ToggleBreakpoint(addr as u32));
+            }
+            ui.dummy([marker_size, marker_size]);
+            ui.same_line_with_spacing(0.0, 0.0);
+
             if self.disasm_results.visible_addrs.contains(&addr) {
                 let offset = (addr - self.disasm_results.visible_addrs.start) as usize;
                 if offset < self.disasm_results.instrs.len() << instr_size_shift {
@@ -201,7 +507,14 @@ impl<const ARM9: bool> FrameView for CpuDisasm<ARM9> {
                     );
 
                     ui.same_line_with_spacing(0.0, 0.0);
-                    ui.text(&instr.opcode);
+                    ui.text_colored(
+                        if addr as u32 == cur_instr_addr {
+                            PC_COLOR
+                        } else {
+                            ui.style_color(StyleColor::Text)
+                        },
+                        &instr.opcode,
+                    );
 
                     if !instr.comment.is_empty() {
                         ui.same_line_with_spacing(0.0, 0.0);
@@ -214,7 +527,10 @@ impl<const ARM9: bool> FrameView for CpuDisasm<ARM9> {
         let visible_addrs = self.view.visible_addrs(1);
         if emu_state_changed || visible_addrs != self.last_visible_addrs {
             self.last_visible_addrs = visible_addrs;
-            messages.push((visible_addrs, self.thumb));
+            messages.push(Message::UpdateView {
+                visible_addrs,
+                thumb: self.thumb,
+            });
         }
     }
 }
@@ -0,0 +1,258 @@
+use super::{
+    BaseView, FrameDataSlot, FrameView, FrameViewMessages, InstanceableFrameViewEmuState,
+    InstanceableView,
+};
+use crate::ui::{utils::combo_value, window::Window};
+use dust_core::{
+    cpu,
+    emu::Emu,
+    gpu::engine_2d::{self, BgControl, BgIndex, Control, Role},
+};
+use imgui::{StyleColor, TableFlags};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Engine2d {
+    A,
+    B,
+}
+
+impl AsRef<str> for Engine2d {
+    fn as_ref(&self) -> &str {
+        match self {
+            Engine2d::A => "Engine A",
+            Engine2d::B => "Engine B",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct BgData {
+    control: BgControl,
+    scroll: [u16; 2],
+}
+
+#[derive(Clone, Copy)]
+pub struct LayersData {
+    engine: Engine2d,
+    control: Control,
+    forced_disabled_layers: u8,
+    bgs: [BgData; 4],
+}
+
+pub enum Message {
+    UpdateEngine(Engine2d),
+    SetForcedDisabledLayers(Engine2d, u8),
+}
+
+pub struct EmuState {
+    engine: Engine2d,
+}
+
+impl super::FrameViewEmuState for EmuState {
+    type InitData = Engine2d;
+    type Message = Message;
+    type FrameData = LayersData;
+
+    fn new<E: cpu::Engine>(engine: Self::InitData, _visible: bool, _emu: &mut Emu<E>) -> Self {
+        EmuState { engine }
+    }
+
+    fn destroy<E: cpu::Engine>(self, emu: &mut Emu<E>) {
+        // Layers hidden from this view shouldn't stay hidden once it's closed
+        emu.gpu.engine_2d_a.set_forced_disabled_layers(0);
+        emu.gpu.engine_2d_b.set_forced_disabled_layers(0);
+    }
+
+    fn handle_message<E: cpu::Engine>(&mut self, message: Self::Message, emu: &mut Emu<E>) {
+        match message {
+            Message::UpdateEngine(engine) => self.engine = engine,
+            Message::SetForcedDisabledLayers(engine, value) => match engine {
+                Engine2d::A => emu.gpu.engine_2d_a.set_forced_disabled_layers(value),
+                Engine2d::B => emu.gpu.engine_2d_b.set_forced_disabled_layers(value),
+            },
+        }
+    }
+
+    fn prepare_frame_data<'a, E: cpu::Engine, S: FrameDataSlot<'a, Self::FrameData>>(
+        &mut self,
+        emu: &mut Emu<E>,
+        frame_data: S,
+    ) {
+        fn layers_data<R: Role>(engine: &engine_2d::Engine2d<R>, id: Engine2d) -> LayersData {
+            LayersData {
+                engine: id,
+                control: engine.control(),
+                forced_disabled_layers: engine.forced_disabled_layers(),
+                bgs: [0, 1, 2, 3].map(|i| BgData {
+                    control: engine.bgs[i].control(),
+                    scroll: engine.bgs[i].scroll,
+                }),
+            }
+        }
+
+        frame_data.insert(match self.engine {
+            Engine2d::A => layers_data(&emu.gpu.engine_2d_a, Engine2d::A),
+            Engine2d::B => layers_data(&emu.gpu.engine_2d_b, Engine2d::B),
+        });
+    }
+}
+
+impl InstanceableFrameViewEmuState for EmuState {}
+
+fn bg_mode_name(control: Control, is_a: bool, i: usize) -> &'static str {
+    match (i, control.bg_mode()) {
+        (0, _) if is_a && control.bg0_3d() => "3D",
+        (0 | 1, _) | (2, 0..=1 | 3 | 7) | (3, 0 | 6..=7) => "Text",
+        (2, 2 | 4) | (3, 1..=2) => "Affine",
+        (2, 5) | (3, 3..=5) => "Extended",
+        _ => "Large bitmap",
+    }
+}
+
+/// Shows the state of each layer of a 2D engine, and allows forcing any of them to be hidden in
+/// the renderer's output regardless of what the emulated software enabled, to check which layer a
+/// given graphic belongs to. Individual BGs can be inspected in the BG map viewer.
+pub struct Layers2d {
+    engine: Engine2d,
+    data: Option<LayersData>,
+}
+
+impl BaseView for Layers2d {
+    const MENU_NAME: &'static str = "2D engine layers";
+}
+
+impl FrameView for Layers2d {
+    type EmuState = EmuState;
+
+    fn new(_window: &mut Window) -> Self {
+        Layers2d {
+            engine: Engine2d::A,
+            data: None,
+        }
+    }
+
+    fn emu_state(&self) -> <Self::EmuState as super::FrameViewEmuState>::InitData {
+        self.engine
+    }
+
+    fn update_from_frame_data(
+        &mut self,
+        frame_data: &<Self::EmuState as super::FrameViewEmuState>::FrameData,
+        _window: &mut Window,
+    ) {
+        self.data = Some(*frame_data);
+    }
+
+    fn draw(
+        &mut self,
+        ui: &imgui::Ui,
+        _window: &mut Window,
+        mut messages: impl FrameViewMessages<Self>,
+    ) {
+        if combo_value(
+            ui,
+            "##engine",
+            &mut self.engine,
+            &[Engine2d::A, Engine2d::B],
+            |engine| engine.as_ref().into(),
+        ) {
+            messages.push(Message::UpdateEngine(self.engine));
+        }
+
+        let Some(data) = self.data.as_ref().filter(|data| data.engine == self.engine) else {
+            return;
+        };
+        let is_a = data.engine == Engine2d::A;
+
+        ui.text(format!(
+            "BG mode {}, display mode {}{}",
+            data.control.bg_mode(),
+            if is_a {
+                data.control.display_mode_a()
+            } else {
+                data.control.display_mode_b()
+            },
+            if data.control.forced_blank() {
+                ", forced blank"
+            } else {
+                ""
+            },
+        ));
+
+        let mut forced_disabled_layers = data.forced_disabled_layers;
+
+        if let Some(_token) = ui.begin_table_with_flags(
+            "layers",
+            8,
+            TableFlags::BORDERS | TableFlags::ROW_BG | TableFlags::SIZING_FIXED_FIT,
+        ) {
+            for name in [
+                "Layer", "Shown", "Mode", "Priority", "Size", "Scroll", "Tiles", "Map",
+            ] {
+                ui.table_setup_column(name);
+            }
+            ui.table_headers_row();
+
+            for (i, bg) in data.bgs.iter().enumerate() {
+                ui.table_next_row();
+                ui.table_next_column();
+                if data.control.bg_enabled(BgIndex::new(i as u8)) {
+                    ui.text(format!("BG{i}"));
+                } else {
+                    ui.text_colored(
+                        ui.style_color(StyleColor::TextDisabled),
+                        format!("BG{i} (off)"),
+                    );
+                }
+                ui.table_next_column();
+                let mut shown = forced_disabled_layers & 1 << i == 0;
+                if ui.checkbox(&format!("##bg{i}_shown"), &mut shown) {
+                    forced_disabled_layers ^= 1 << i;
+                }
+                ui.table_next_column();
+                ui.text(bg_mode_name(data.control, is_a, i));
+                ui.table_next_column();
+                ui.text(format!("{}", bg.control.priority()));
+                ui.table_next_column();
+                ui.text(format!("{}", bg.control.size_key()));
+                ui.table_next_column();
+                ui.text(format!("{}, {}", bg.scroll[0], bg.scroll[1]));
+                ui.table_next_column();
+                ui.text(format!(
+                    "{:#07X}",
+                    (bg.control.tile_base_raw() as u32) << 14
+                ));
+                ui.table_next_column();
+                ui.text(format!("{:#07X}", (bg.control.map_base_raw() as u32) << 11));
+            }
+
+            ui.table_next_row();
+            ui.table_next_column();
+            if data.control.objs_enabled() {
+                ui.text("OBJ");
+            } else {
+                ui.text_colored(ui.style_color(StyleColor::TextDisabled), "OBJ (off)");
+            }
+            ui.table_next_column();
+            let mut shown = forced_disabled_layers & 1 << 4 == 0;
+            if ui.checkbox("##obj_shown", &mut shown) {
+                forced_disabled_layers ^= 1 << 4;
+            }
+            ui.table_next_column();
+            ui.text(if data.control.obj_tile_1d_mapping() {
+                "1D tiles"
+            } else {
+                "2D tiles"
+            });
+        }
+
+        if forced_disabled_layers != data.forced_disabled_layers {
+            messages.push(Message::SetForcedDisabledLayers(
+                self.engine,
+                forced_disabled_layers,
+            ));
+        }
+    }
+}
+
+impl InstanceableView for Layers2d {}
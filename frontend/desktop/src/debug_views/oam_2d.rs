@@ -0,0 +1,440 @@
+use super::{
+    common::rgb5_to_rgba8, BaseView, FrameDataSlot, FrameView, FrameViewMessages,
+    InstanceableFrameViewEmuState, InstanceableView,
+};
+use crate::ui::{utils::combo_value, window::Window};
+use dust_core::{
+    cpu,
+    emu::Emu,
+    gpu::{
+        engine_2d::{self, Control, OamAttr0, OamAttr1, OamAttr2, Role},
+        vram::Vram,
+    },
+    utils::{mem_prelude::*, zeroed_box},
+};
+use imgui::{Image, SelectableFlags, TableFlags, TextureId};
+use std::slice;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Engine2d {
+    A,
+    B,
+}
+
+impl AsRef<str> for Engine2d {
+    fn as_ref(&self) -> &str {
+        match self {
+            Engine2d::A => "Engine A",
+            Engine2d::B => "Engine B",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    engine: Engine2d,
+    obj_index: u8,
+}
+
+// Shape 3 is invalid and behaves like an 8x8 OBJ
+#[rustfmt::skip]
+static OBJ_SIZE_SHIFT: [(u8, u8); 16] = [
+    (0, 0), (1, 0), (0, 1), (0, 0),
+    (1, 1), (2, 0), (0, 2), (0, 0),
+    (2, 2), (2, 1), (1, 2), (0, 0),
+    (3, 3), (3, 2), (2, 3), (0, 0),
+];
+
+fn obj_size(attr_0: OamAttr0, attr_1: OamAttr1) -> [usize; 2] {
+    let (width_shift, height_shift) =
+        OBJ_SIZE_SHIFT[((attr_1.0 >> 12 & 0xC) | attr_0.0 >> 14) as usize];
+    [8 << width_shift, 8 << height_shift]
+}
+
+fn obj_attrs(oam: &[u8], i: usize) -> (OamAttr0, OamAttr1, OamAttr2) {
+    (
+        OamAttr0(oam.read_le::<u16>(i << 3)),
+        OamAttr1(oam.read_le::<u16>(i << 3 | 2)),
+        OamAttr2(oam.read_le::<u16>(i << 3 | 4)),
+    )
+}
+
+pub struct ObjsData {
+    selection: Option<Selection>,
+    control: Control,
+    oam: Box<Bytes<0x400>>,
+    /// The RGBA8 pixels of the selected OBJ, without any flipping or rotation/scaling applied,
+    /// with a stride of 64 pixels.
+    preview: Box<[u32; 64 * 64]>,
+}
+
+impl Default for ObjsData {
+    fn default() -> Self {
+        ObjsData {
+            selection: None,
+            control: Control(0),
+            oam: zeroed_box(),
+            preview: zeroed_box(),
+        }
+    }
+}
+
+pub struct EmuState {
+    selection: Selection,
+}
+
+impl super::FrameViewEmuState for EmuState {
+    type InitData = Selection;
+    type Message = Selection;
+    type FrameData = ObjsData;
+
+    fn new<E: cpu::Engine>(selection: Self::InitData, _visible: bool, _emu: &mut Emu<E>) -> Self {
+        EmuState { selection }
+    }
+
+    fn handle_message<E: cpu::Engine>(&mut self, selection: Self::Message, _emu: &mut Emu<E>) {
+        self.selection = selection;
+    }
+
+    fn prepare_frame_data<'a, E: cpu::Engine, S: FrameDataSlot<'a, Self::FrameData>>(
+        &mut self,
+        emu: &mut Emu<E>,
+        frame_data: S,
+    ) {
+        fn read_obj_color<R: Role>(
+            control: Control,
+            vram: &Vram,
+            attr_2: OamAttr2,
+            color_index: u16,
+            use_256_colors: bool,
+        ) -> u32 {
+            if color_index == 0 {
+                return 0;
+            }
+            let color = if use_256_colors && control.obj_ext_pal_enabled() {
+                let addr = ((attr_2.palette_number() as u32) << 8 | color_index as u32) << 1;
+                if R::IS_A {
+                    vram.read_a_obj_ext_pal::<u16>(addr)
+                } else {
+                    vram.read_b_obj_ext_pal::<u16>(addr)
+                }
+            } else {
+                let pal_base = if use_256_colors {
+                    0
+                } else {
+                    (attr_2.palette_number() as usize) << 4
+                };
+                let base = (!R::IS_A as usize) << 10 | 0x200;
+                vram.palette
+                    .read_le::<u16>(base | (pal_base | color_index as usize) << 1)
+            };
+            rgb5_to_rgba8(color)
+        }
+
+        fn render_preview<R: Role>(
+            engine: &engine_2d::Engine2d<R>,
+            vram: &Vram,
+            oam: &[u8],
+            obj_index: usize,
+            preview: &mut [u32; 64 * 64],
+        ) {
+            let read_obj = |addr: u32| {
+                let addr = addr & R::OBJ_VRAM_MASK;
+                if R::IS_A {
+                    vram.read_a_obj::<u8>(addr)
+                } else {
+                    vram.read_b_obj::<u8>(addr)
+                }
+            };
+
+            let control = engine.control();
+            let (attr_0, attr_1, attr_2) = obj_attrs(oam, obj_index);
+            let [width, height] = obj_size(attr_0, attr_1);
+            let tile_number = attr_2.tile_number() as u32;
+
+            if attr_0.mode() == 3 {
+                let (base, stride) = if control.obj_bitmap_1d_mapping() {
+                    let boundary_shift = if R::IS_A {
+                        7 + control.a_obj_bitmap_1d_boundary()
+                    } else {
+                        7
+                    };
+                    (tile_number << boundary_shift, width as u32 * 2)
+                } else if control.bitmap_objs_256x256() {
+                    (
+                        ((tile_number & 0x1F) << 4) + ((tile_number & !0x1F) << 7),
+                        512,
+                    )
+                } else {
+                    (
+                        ((tile_number & 0xF) << 4) + ((tile_number & !0xF) << 7),
+                        256,
+                    )
+                };
+                for y in 0..height {
+                    for x in 0..width {
+                        let addr = base + y as u32 * stride + (x as u32) * 2;
+                        let color = read_obj(addr) as u16 | (read_obj(addr + 1) as u16) << 8;
+                        preview[y << 6 | x] = if color & 0x8000 == 0 {
+                            0
+                        } else {
+                            rgb5_to_rgba8(color)
+                        };
+                    }
+                }
+                return;
+            }
+
+            let use_256_colors = attr_0.use_256_colors();
+            let tile_bytes_shift = 5 + use_256_colors as u32;
+            for y in 0..height {
+                for x in 0..width {
+                    let tile_offset = if control.obj_tile_1d_mapping() {
+                        (tile_number << (5 + control.obj_tile_1d_boundary()))
+                            + ((((y >> 3) * (width >> 3) + (x >> 3)) as u32) << tile_bytes_shift)
+                    } else {
+                        (tile_number << 5)
+                            + ((y >> 3) as u32) * 0x400
+                            + (((x >> 3) as u32) << tile_bytes_shift)
+                    };
+                    let pixel_offset = (y & 7) * 8 + (x & 7);
+                    let color_index = if use_256_colors {
+                        read_obj(tile_offset + pixel_offset as u32) as u16
+                    } else {
+                        let byte = read_obj(tile_offset + (pixel_offset >> 1) as u32);
+                        (byte >> ((pixel_offset & 1) << 2) & 0xF) as u16
+                    };
+                    preview[y << 6 | x] =
+                        read_obj_color::<R>(control, vram, attr_2, color_index, use_256_colors);
+                }
+            }
+        }
+
+        let data = frame_data.get_or_insert_with(Default::default);
+        data.selection = Some(self.selection);
+        let oam_base = ((self.selection.engine == Engine2d::B) as usize) << 10;
+        data.oam
+            .copy_from_slice(&emu.gpu.vram.oam.as_arr()[oam_base..oam_base + 0x400]);
+        let obj_index = self.selection.obj_index as usize;
+        match self.selection.engine {
+            Engine2d::A => {
+                data.control = emu.gpu.engine_2d_a.control();
+                render_preview(
+                    &emu.gpu.engine_2d_a,
+                    &emu.gpu.vram,
+                    &**data.oam,
+                    obj_index,
+                    &mut data.preview,
+                );
+            }
+            Engine2d::B => {
+                data.control = emu.gpu.engine_2d_b.control();
+                render_preview(
+                    &emu.gpu.engine_2d_b,
+                    &emu.gpu.vram,
+                    &**data.oam,
+                    obj_index,
+                    &mut data.preview,
+                );
+            }
+        }
+    }
+}
+
+impl InstanceableFrameViewEmuState for EmuState {}
+
+/// Lists the attributes of all 128 OBJs of a 2D engine, with a preview of the selected one (as
+/// stored in VRAM, before any flipping or rotation/scaling).
+pub struct Oam2d {
+    tex_id: TextureId,
+    selection: Selection,
+    data: ObjsData,
+}
+
+impl BaseView for Oam2d {
+    const MENU_NAME: &'static str = "2D engine OAM";
+}
+
+impl FrameView for Oam2d {
+    type EmuState = EmuState;
+
+    fn new(window: &mut Window) -> Self {
+        let tex_id = window.imgui_gfx.create_and_add_owned_texture(
+            Some("OBJ preview".into()),
+            imgui_wgpu::TextureDescriptor {
+                width: 64,
+                height: 64,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                ..Default::default()
+            },
+            imgui_wgpu::SamplerDescriptor {
+                mag_filter: wgpu::FilterMode::Nearest,
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            },
+        );
+        Oam2d {
+            tex_id,
+            selection: Selection {
+                engine: Engine2d::A,
+                obj_index: 0,
+            },
+            data: ObjsData::default(),
+        }
+    }
+
+    fn destroy(self, window: &mut Window) {
+        window.imgui_gfx.remove_texture(self.tex_id);
+    }
+
+    fn emu_state(&self) -> <Self::EmuState as super::FrameViewEmuState>::InitData {
+        self.selection
+    }
+
+    fn update_from_frame_data(
+        &mut self,
+        frame_data: &<Self::EmuState as super::FrameViewEmuState>::FrameData,
+        window: &mut Window,
+    ) {
+        self.data.selection = frame_data.selection;
+        self.data.control = frame_data.control;
+        self.data.oam.copy_from_slice(&**frame_data.oam);
+        self.data.preview.copy_from_slice(&*frame_data.preview);
+        window
+            .imgui_gfx
+            .texture(self.tex_id)
+            .unwrap_owned_ref()
+            .set_data(
+                window.gfx_device(),
+                window.gfx_queue(),
+                unsafe {
+                    slice::from_raw_parts(self.data.preview.as_ptr() as *const u8, 64 * 64 * 4)
+                },
+                Default::default(),
+            );
+    }
+
+    fn draw(
+        &mut self,
+        ui: &imgui::Ui,
+        _window: &mut Window,
+        mut messages: impl FrameViewMessages<Self>,
+    ) {
+        if combo_value(
+            ui,
+            "##engine",
+            &mut self.selection.engine,
+            &[Engine2d::A, Engine2d::B],
+            |engine| engine.as_ref().into(),
+        ) {
+            messages.push(self.selection);
+        }
+
+        if self.data.selection.map(|selection| selection.engine) != Some(self.selection.engine) {
+            return;
+        }
+
+        {
+            let (attr_0, attr_1, attr_2) =
+                obj_attrs(&**self.data.oam, self.selection.obj_index as usize);
+            let [width, height] = obj_size(attr_0, attr_1);
+            let preview_scale = ui.current_font_size() * 0.25;
+            Image::new(
+                self.tex_id,
+                [width as f32 * preview_scale, height as f32 * preview_scale],
+            )
+            .uv1([width as f32 / 64.0, height as f32 / 64.0])
+            .build(ui);
+            ui.same_line();
+            ui.group(|| {
+                ui.text(format!("OBJ {}", self.selection.obj_index));
+                ui.text(format!("Tile number: {:#05X}", attr_2.tile_number()));
+                if attr_0.rot_scale() {
+                    ui.text(format!(
+                        "Rot/scale parameters: {}{}",
+                        attr_1.rot_scale_params_index(),
+                        if attr_0.double_size() {
+                            ", double size"
+                        } else {
+                            ""
+                        }
+                    ));
+                } else {
+                    ui.text(format!(
+                        "Flip: {}{}",
+                        if attr_1.x_flip() { "X" } else { "-" },
+                        if attr_1.y_flip() { "Y" } else { "-" },
+                    ));
+                }
+                ui.text(format!(
+                    "Mosaic: {}",
+                    if attr_0.mosaic_enabled() { "on" } else { "off" }
+                ));
+            });
+        }
+
+        ui.separator();
+
+        if let Some(_token) = ui.begin_table_with_flags(
+            "objs",
+            8,
+            TableFlags::BORDERS
+                | TableFlags::ROW_BG
+                | TableFlags::SIZING_FIXED_FIT
+                | TableFlags::SCROLL_Y,
+        ) {
+            ui.table_setup_scroll_freeze(0, 1);
+            for name in [
+                "#", "Position", "Size", "Mode", "Colors", "Tile", "Palette", "Priority",
+            ] {
+                ui.table_setup_column(name);
+            }
+            ui.table_headers_row();
+
+            for i in 0..128 {
+                let (attr_0, attr_1, attr_2) = obj_attrs(&**self.data.oam, i);
+                let hidden = !attr_0.rot_scale() && attr_0.disabled();
+                let [width, height] = obj_size(attr_0, attr_1);
+
+                ui.table_next_row();
+                ui.table_next_column();
+                if ui
+                    .selectable_config(format!("{i}{}", if hidden { " (off)" } else { "" }))
+                    .selected(self.selection.obj_index == i as u8)
+                    .flags(SelectableFlags::SPAN_ALL_COLUMNS)
+                    .build()
+                {
+                    self.selection.obj_index = i as u8;
+                    messages.push(self.selection);
+                }
+                ui.table_next_column();
+                ui.text(format!("{}, {}", attr_1.x_start(), attr_0.y_start()));
+                ui.table_next_column();
+                ui.text(format!("{width}x{height}"));
+                ui.table_next_column();
+                ui.text(match attr_0.mode() {
+                    0 => "Normal",
+                    1 => "Semi-transparent",
+                    2 => "Window",
+                    _ => "Bitmap",
+                });
+                ui.table_next_column();
+                ui.text(if attr_0.mode() == 3 {
+                    "Direct"
+                } else if attr_0.use_256_colors() {
+                    "256"
+                } else {
+                    "16"
+                });
+                ui.table_next_column();
+                ui.text(format!("{:#05X}", attr_2.tile_number()));
+                ui.table_next_column();
+                ui.text(format!("{}", attr_2.palette_number()));
+                ui.table_next_column();
+                ui.text(format!("{}", attr_2.bg_priority()));
+            }
+        }
+    }
+}
+
+impl InstanceableView for Oam2d {}
@@ -0,0 +1,364 @@
+use super::{
+    common::rgb5_to_rgba8, BaseView, FrameDataSlot, FrameView, FrameViewMessages,
+    InstanceableFrameViewEmuState, InstanceableView,
+};
+use crate::ui::{utils::combo_value, window::Window};
+use dust_core::{
+    cpu,
+    emu::Emu,
+    gpu::vram::BankControl,
+    utils::{mem_prelude::*, zeroed_box},
+};
+use imgui::{Image, SliderFlags, TextureId};
+use std::slice;
+
+const BANK_LENS: [usize; 9] = [
+    0x2_0000, 0x2_0000, 0x2_0000, 0x2_0000, 0x1_0000, 0x4000, 0x4000, 0x8000, 0x4000,
+];
+
+/// The width of the visualization in pixels, which fits 32 tiles per row.
+const WIDTH: usize = 256;
+/// The maximum height of the visualization in pixels, reached when showing a 128 KiB bank as
+/// 16-color tiles.
+const MAX_HEIGHT: usize = 0x2_0000 * 2 / WIDTH;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Tiles16,
+    Tiles256,
+    Bitmap,
+}
+
+impl Format {
+    fn height(self, bank_len: usize) -> usize {
+        match self {
+            Format::Tiles16 => bank_len * 2 / WIDTH,
+            Format::Tiles256 => bank_len / WIDTH,
+            Format::Bitmap => bank_len / 2 / WIDTH,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Palette {
+    Grayscale,
+    EngineABg,
+    EngineAObj,
+    EngineBBg,
+    EngineBObj,
+}
+
+impl Palette {
+    fn base(self) -> Option<usize> {
+        match self {
+            Palette::Grayscale => None,
+            Palette::EngineABg => Some(0),
+            Palette::EngineAObj => Some(0x200),
+            Palette::EngineBBg => Some(0x400),
+            Palette::EngineBObj => Some(0x600),
+        }
+    }
+}
+
+pub struct BankData {
+    bank_index: Option<u8>,
+    bank_control: BankControl,
+    data: Box<Bytes<0x2_0000>>,
+    palette: Box<Bytes<0x800>>,
+}
+
+impl Default for BankData {
+    fn default() -> Self {
+        BankData {
+            bank_index: None,
+            bank_control: BankControl(0),
+            data: zeroed_box(),
+            palette: zeroed_box(),
+        }
+    }
+}
+
+pub struct EmuState {
+    bank_index: u8,
+}
+
+impl super::FrameViewEmuState for EmuState {
+    type InitData = u8;
+    type Message = u8;
+    type FrameData = BankData;
+
+    fn new<E: cpu::Engine>(bank_index: Self::InitData, _visible: bool, _emu: &mut Emu<E>) -> Self {
+        EmuState { bank_index }
+    }
+
+    fn handle_message<E: cpu::Engine>(&mut self, bank_index: Self::Message, _emu: &mut Emu<E>) {
+        self.bank_index = bank_index;
+    }
+
+    fn prepare_frame_data<'a, E: cpu::Engine, S: FrameDataSlot<'a, Self::FrameData>>(
+        &mut self,
+        emu: &mut Emu<E>,
+        frame_data: S,
+    ) {
+        let bank_data = frame_data.get_or_insert_with(Default::default);
+        bank_data.bank_index = Some(self.bank_index);
+        bank_data.bank_control = emu.gpu.vram.bank_control()[self.bank_index as usize];
+        let contents = emu.gpu.vram.bank_contents(self.bank_index);
+        bank_data.data[..contents.len()].copy_from_slice(contents);
+        bank_data
+            .palette
+            .copy_from_slice(&emu.gpu.vram.palette.as_arr()[..0x800]);
+    }
+}
+
+impl InstanceableFrameViewEmuState for EmuState {}
+
+/// Shows the raw contents of a VRAM bank, regardless of where it's mapped, interpreted as either
+/// 16-color tiles, 256-color tiles or a direct color bitmap.
+pub struct VramBanks {
+    tex_id: TextureId,
+    bank_index: u8,
+    format: Format,
+    palette: Palette,
+    pal_index: u8,
+    data: BankData,
+    pixel_buffer: Box<[u32; WIDTH * MAX_HEIGHT]>,
+}
+
+impl BaseView for VramBanks {
+    const MENU_NAME: &'static str = "VRAM banks";
+}
+
+impl FrameView for VramBanks {
+    type EmuState = EmuState;
+
+    fn new(window: &mut Window) -> Self {
+        let tex_id = window.imgui_gfx.create_and_add_owned_texture(
+            Some("VRAM bank".into()),
+            imgui_wgpu::TextureDescriptor {
+                width: WIDTH as u32,
+                height: MAX_HEIGHT as u32,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                ..Default::default()
+            },
+            imgui_wgpu::SamplerDescriptor {
+                mag_filter: wgpu::FilterMode::Nearest,
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            },
+        );
+        VramBanks {
+            tex_id,
+            bank_index: 0,
+            format: Format::Tiles16,
+            palette: Palette::Grayscale,
+            pal_index: 0,
+            data: BankData::default(),
+            pixel_buffer: zeroed_box(),
+        }
+    }
+
+    fn destroy(self, window: &mut Window) {
+        window.imgui_gfx.remove_texture(self.tex_id);
+    }
+
+    fn emu_state(&self) -> <Self::EmuState as super::FrameViewEmuState>::InitData {
+        self.bank_index
+    }
+
+    fn update_from_frame_data(
+        &mut self,
+        frame_data: &<Self::EmuState as super::FrameViewEmuState>::FrameData,
+        _window: &mut Window,
+    ) {
+        self.data.bank_index = frame_data.bank_index;
+        self.data.bank_control = frame_data.bank_control;
+        if let Some(bank_index) = frame_data.bank_index {
+            let len = BANK_LENS[bank_index as usize];
+            self.data.data[..len].copy_from_slice(&frame_data.data[..len]);
+        }
+        self.data.palette.copy_from_slice(&**frame_data.palette);
+    }
+
+    fn draw(
+        &mut self,
+        ui: &imgui::Ui,
+        window: &mut Window,
+        mut messages: impl FrameViewMessages<Self>,
+    ) {
+        let content_width = ui.content_region_avail()[0];
+        let three_width = content_width - 2.0 * style!(ui, item_spacing)[0];
+
+        ui.set_next_item_width(three_width * (1.0 / 3.0));
+        if ui
+            .slider_config("##bank", 0_u8, 8)
+            .display_format(format!("Bank {}", (b'A' + self.bank_index) as char))
+            .flags(SliderFlags::NO_INPUT)
+            .build(&mut self.bank_index)
+        {
+            messages.push(self.bank_index);
+        }
+
+        ui.same_line();
+        ui.set_next_item_width(three_width * (1.0 / 3.0));
+        combo_value(
+            ui,
+            "##format",
+            &mut self.format,
+            &[Format::Tiles16, Format::Tiles256, Format::Bitmap],
+            |format| {
+                match format {
+                    Format::Tiles16 => "Tiles, 16 colors",
+                    Format::Tiles256 => "Tiles, 256 colors",
+                    Format::Bitmap => "Bitmap, direct color",
+                }
+                .into()
+            },
+        );
+
+        ui.same_line();
+        ui.set_next_item_width(three_width * (1.0 / 3.0));
+        ui.disabled(self.format == Format::Bitmap, || {
+            combo_value(
+                ui,
+                "##palette",
+                &mut self.palette,
+                &[
+                    Palette::Grayscale,
+                    Palette::EngineABg,
+                    Palette::EngineAObj,
+                    Palette::EngineBBg,
+                    Palette::EngineBObj,
+                ],
+                |palette| {
+                    match palette {
+                        Palette::Grayscale => "Grayscale",
+                        Palette::EngineABg => "Engine A BG palette",
+                        Palette::EngineAObj => "Engine A OBJ palette",
+                        Palette::EngineBBg => "Engine B BG palette",
+                        Palette::EngineBObj => "Engine B OBJ palette",
+                    }
+                    .into()
+                },
+            );
+        });
+
+        if self.format == Format::Tiles16 && self.palette != Palette::Grayscale {
+            ui.slider("Palette index", 0_u8, 15, &mut self.pal_index);
+        }
+
+        let Some(bank_index) = self.data.bank_index.filter(|i| *i == self.bank_index) else {
+            return;
+        };
+
+        let bank_control = self.data.bank_control;
+        if bank_control.enabled() {
+            ui.text(format!(
+                "MST {}, offset {}",
+                bank_control.mst(),
+                bank_control.offset()
+            ));
+        } else {
+            ui.text("Disabled");
+        }
+
+        let bank_len = BANK_LENS[bank_index as usize];
+        let height = self.format.height(bank_len);
+
+        let color = |index: u16, pal_base: u16| -> u32 {
+            match self.palette.base() {
+                None => {
+                    let max = if self.format == Format::Tiles16 {
+                        15
+                    } else {
+                        255
+                    };
+                    let value = (index as u32 * 255 / max) & 0xFF;
+                    0xFF00_0000 | value << 16 | value << 8 | value
+                }
+                Some(base) => rgb5_to_rgba8(
+                    self.data
+                        .palette
+                        .read_le::<u16>(base | ((pal_base | index) as usize) << 1),
+                ),
+            }
+        };
+
+        match self.format {
+            Format::Tiles16 | Format::Tiles256 => {
+                let bpp_shift = (self.format == Format::Tiles256) as usize;
+                let pal_base = if self.format == Format::Tiles16 {
+                    (self.pal_index as u16) << 4
+                } else {
+                    0
+                };
+                for tile in 0..bank_len >> (5 + bpp_shift) {
+                    let src_base = tile << (5 + bpp_shift);
+                    let dst_base = (tile >> 5) << 11 | (tile & 0x1F) << 3;
+                    for y in 0..8 {
+                        for x in 0..8 {
+                            let i = y << 3 | x;
+                            let index = if bpp_shift == 1 {
+                                self.data.data[src_base + i] as u16
+                            } else {
+                                (self.data.data[src_base + (i >> 1)] >> ((i & 1) << 2) & 0xF) as u16
+                            };
+                            self.pixel_buffer[dst_base + y * WIDTH + x] = color(index, pal_base);
+                        }
+                    }
+                }
+            }
+
+            Format::Bitmap => {
+                for (i, pixel) in self.pixel_buffer[..bank_len >> 1].iter_mut().enumerate() {
+                    *pixel = rgb5_to_rgba8(self.data.data.read_le::<u16>(i << 1));
+                }
+            }
+        }
+
+        window
+            .imgui_gfx
+            .texture(self.tex_id)
+            .unwrap_owned_ref()
+            .set_data(
+                window.gfx_device(),
+                window.gfx_queue(),
+                unsafe {
+                    slice::from_raw_parts(
+                        self.pixel_buffer.as_ptr() as *const u8,
+                        WIDTH * height * 4,
+                    )
+                },
+                imgui_wgpu::TextureSetRange {
+                    width: Some(WIDTH as u32),
+                    height: Some(height as u32),
+                    ..Default::default()
+                },
+            );
+
+        ui.child_window("bank_contents").build(|| {
+            let width = ui.content_region_avail()[0];
+            Image::new(self.tex_id, [width, width * height as f32 / WIDTH as f32])
+                .uv1([1.0, height as f32 / MAX_HEIGHT as f32])
+                .build(ui);
+            if ui.is_item_hovered() {
+                let image_pos = ui.item_rect_min();
+                let mouse_pos = ui.io().mouse_pos;
+                let x = ((mouse_pos[0] - image_pos[0]) / width * WIDTH as f32) as usize;
+                let y = ((mouse_pos[1] - image_pos[1]) / width * WIDTH as f32) as usize;
+                let offset = match self.format {
+                    Format::Tiles16 | Format::Tiles256 => {
+                        let bpp_shift = (self.format == Format::Tiles256) as usize;
+                        ((y >> 3) << 5 | x >> 3) << (5 + bpp_shift)
+                    }
+                    Format::Bitmap => (y * WIDTH + x) << 1,
+                };
+                if offset < bank_len {
+                    ui.tooltip_text(format!("Offset {offset:#07X}"));
+                }
+            }
+        });
+    }
+}
+
+impl InstanceableView for VramBanks {}
@@ -1,24 +1,47 @@
+#[cfg(any(feature = "ffmpeg", feature = "frame-dump"))]
+pub mod capture;
 #[cfg(feature = "dldi")]
 mod dldi;
 pub mod ds_slot_rom;
+#[cfg(feature = "frame-dump")]
+pub mod frame_dump;
 #[cfg(feature = "gdb-server")]
 mod gdb_server;
+pub mod hang_detection;
+mod ir_link;
+#[cfg(feature = "ffmpeg")]
+pub mod recording;
+#[cfg(feature = "remote-display")]
+pub mod remote_display;
+pub mod rom_archive;
 mod rtc;
 pub mod soft_renderer_3d;
+#[cfg(feature = "virtual-time")]
+mod virtual_time;
 
 #[cfg(feature = "debug-views")]
 use super::debug_views;
-use crate::{audio, config::SysFiles, game_db::SaveType, input, FrameData};
+use crate::{
+    audio,
+    config::{saves, SysFiles},
+    game_db::{SaveType, SaveTypeSource},
+    input, notifications, FrameData, FrameInput,
+};
+use chrono::NaiveDateTime;
 use ds_slot_rom::DsSlotRom;
 #[cfg(feature = "xq-audio")]
-use dust_core::audio::{Audio, ChannelInterpMethod as AudioChannelInterpMethod};
+use dust_core::audio::{
+    Audio, ChannelInterpMethod as AudioChannelInterpMethod, StereoMode as AudioStereoMode,
+};
 use dust_core::{
-    audio::DummyBackend as DummyAudioBackend,
+    audio::{Backend as AudioBackend, DummyBackend as DummyAudioBackend},
     cpu::{self, interpreter::Interpreter},
     ds_slot,
-    emu::{self, RunOutput},
+    emu::{self, RunCancelToken, RunOutput},
     flash::Flash,
-    gpu::{engine_2d, engine_3d, Framebuffer},
+    gba_slot,
+    gpu::{engine_2d, engine_3d, Framebuffer, TOTAL_SCANLINES},
+    profiling::TimeCounter,
     spi::{self, firmware},
     utils::{
         BoxedByteSlice, PersistentReadSavestate, PersistentWriteSavestate, ReadSavestate,
@@ -27,15 +50,16 @@ use dust_core::{
     Model, SaveContents, SaveReloadContents,
 };
 use emu_utils::triple_buffer;
-#[cfg(feature = "gdb-server")]
-use std::net::SocketAddr;
+use hang_detection::{HangDetector, HangState};
 #[cfg(feature = "xq-audio")]
 use std::num::NonZeroU32;
 use std::{
     fs::{self, File},
     hint,
     io::{self, Read},
-    path::PathBuf,
+    mem,
+    net::SocketAddr,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -46,10 +70,46 @@ use std::{
 pub struct SharedState {
     // UI to emu
     pub playing: AtomicBool,
+    pub run_cancel_token: RunCancelToken,
 
     // Emu to UI
+    pub hang_state: HangState,
     #[cfg(feature = "gdb-server")]
     pub gdb_server_active: AtomicBool,
+    #[cfg(feature = "virtual-time")]
+    pub virtual_time_server_active: AtomicBool,
+    #[cfg(feature = "remote-display")]
+    pub remote_display_active: AtomicBool,
+    #[cfg(feature = "lockstep-trace")]
+    pub lockstep_trace_active: AtomicBool,
+
+    // Profiling, enabled by the UI
+    /// The time spent running the emulator, including any work done synchronously by the 2D
+    /// renderer and audio mixer.
+    pub emulation_time: TimeCounter,
+    pub audio_time: TimeCounter,
+}
+
+impl SharedState {
+    pub fn new(playing: bool) -> Self {
+        SharedState {
+            playing: AtomicBool::new(playing),
+            run_cancel_token: RunCancelToken::new(),
+
+            hang_state: HangState::new(),
+            #[cfg(feature = "gdb-server")]
+            gdb_server_active: AtomicBool::new(false),
+            #[cfg(feature = "virtual-time")]
+            virtual_time_server_active: AtomicBool::new(false),
+            #[cfg(feature = "remote-display")]
+            remote_display_active: AtomicBool::new(false),
+            #[cfg(feature = "lockstep-trace")]
+            lockstep_trace_active: AtomicBool::new(false),
+
+            emulation_time: TimeCounter::new(),
+            audio_time: TimeCounter::new(),
+        }
+    }
 }
 
 pub struct SavePathUpdate {
@@ -59,10 +119,31 @@ pub struct SavePathUpdate {
     pub reset: bool,
 }
 
+/// A temporary speed to run emulation at, overriding the configured framerate limit (used for
+/// fast-forward and slow motion).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpeedOverride {
+    Ratio(f32),
+    Unlimited,
+}
+
+impl SpeedOverride {
+    fn is_faster_than_native(self) -> bool {
+        match self {
+            SpeedOverride::Ratio(value) => value > 1.0,
+            SpeedOverride::Unlimited => true,
+        }
+    }
+}
+
 pub struct Savestate {
+    pub header: emu::savestate::Header,
     pub contents: Vec<u8>,
     pub save: Option<BoxedByteSlice>,
     pub framebuffer: Box<Framebuffer>,
+    /// The keys of the textures cached by the accelerated 3D renderer when the savestate was
+    /// created, to decode them again ahead of time when loading it.
+    pub texture_cache_keys: Vec<u64>,
 }
 
 pub enum Message {
@@ -80,20 +161,34 @@ pub enum Message {
 
     UpdateSavePath(SavePathUpdate),
     UpdateSaveIntervalMs(f32),
+    /// Replaces the save chip's contents with the ones of a save file in any supported format,
+    /// then resets the system.
+    ImportSave(PathBuf),
+    ExportSave(PathBuf, saves::convert::Format),
 
     UpdateRtcTimeOffsetSeconds(i64),
+    UpdateRtcFrozenDateTime(Option<NaiveDateTime>),
 
     UpdateRenderers {
         renderer_2d_is_accel: bool,
         renderer_2d: Box<dyn engine_2d::Renderer + Send>,
         renderer_3d_tx: Box<dyn engine_3d::RendererTx + Send>,
+        texture_cache: Option<dust_wgpu_3d::threaded::TextureCache>,
     },
 
     UpdateFramerateLimit(Option<f32>),
     UpdatePausedFramerateLimit(f32),
+    UpdateSpeedOverride(Option<SpeedOverride>),
+    /// Runs the given number of frames while paused, then pauses again.
+    AdvanceFrames(u32),
 
     UpdateSyncToAudio(bool),
+    UpdateSubFrameInput(bool),
+    UpdateTouchPressure(u8),
+    UpdateSolarSensorLevel(u8),
+    UpdateReturnToMenuOnShutdown(bool),
     UpdateAudioSampleChunkSize(u16),
+    UpdateAudioStereoMode(AudioStereoMode),
     #[cfg(feature = "xq-audio")]
     UpdateAudioCustomSampleRate(Option<NonZeroU32>),
     #[cfg(feature = "xq-audio")]
@@ -106,16 +201,76 @@ pub enum Message {
 
     #[cfg(feature = "gdb-server")]
     ToggleGdbServer(Option<SocketAddr>),
+
+    #[cfg(feature = "virtual-time")]
+    ToggleVirtualTimeServer(Option<SocketAddr>),
+
+    #[cfg(feature = "remote-display")]
+    ToggleRemoteDisplay(Option<(SocketAddr, remote_display::Settings)>),
+
+    #[cfg(feature = "lockstep-trace")]
+    ToggleLockstepTrace(bool),
+    #[cfg(feature = "lockstep-trace")]
+    DumpLockstepTrace(PathBuf),
+
+    #[cfg(all(feature = "fault-injection", debug_assertions))]
+    InjectFault(emu::fault_injection::Fault),
+    /// Injects a fault every given number of milliseconds of emulated time, or stops doing so.
+    #[cfg(all(feature = "fault-injection", debug_assertions))]
+    SetPeriodicFault(Option<(emu::fault_injection::Fault, u32)>),
+
+    #[cfg(feature = "debug-views")]
+    ExportMemRegion(emu::mem_regions::Region, PathBuf),
+    #[cfg(feature = "debug-views")]
+    ExportMemRange {
+        arm9: bool,
+        addr: u32,
+        len: u32,
+        path: PathBuf,
+    },
+    #[cfg(feature = "debug-views")]
+    ImportMemRange {
+        arm9: bool,
+        addr: u32,
+        path: PathBuf,
+    },
+
+    #[cfg(feature = "ffmpeg")]
+    StartRecording(recording::Settings),
+    #[cfg(feature = "ffmpeg")]
+    StopRecording,
+
+    #[cfg(feature = "frame-dump")]
+    StartFrameDump(frame_dump::Settings),
+    #[cfg(feature = "frame-dump")]
+    StopFrameDump,
+
+    #[cfg(feature = "wav-dump")]
+    StartWavDump(audio::wav_dump::Settings),
+    #[cfg(feature = "wav-dump")]
+    StopWavDump,
 }
 
 pub enum Notification {
     Stopped,
     #[cfg(feature = "debug-views")]
     DebugViews(debug_views::Notification),
+    /// The emulator was paused by a breakpoint, or resumed by a debug view.
+    #[cfg(feature = "debug-views")]
+    PlayingChanged(bool),
 
+    /// A CPU hit an exception the game doesn't handle; emulation was paused.
+    Crashed(Box<emu::crash::Crash>),
     RtcTimeOffsetSecondsUpdated(i64),
+    RtcFrozenDateTimeUpdated(Option<NaiveDateTime>),
     SavestateCreated(String, Savestate),
     SavestateFailed(String),
+    #[cfg(feature = "ffmpeg")]
+    RecordingStopped,
+    #[cfg(feature = "frame-dump")]
+    FrameDumpStopped,
+    #[cfg(feature = "wav-dump")]
+    WavDumpStopped,
 }
 
 #[cfg(feature = "debug-views")]
@@ -136,17 +291,43 @@ impl debug_views::Notifications for &crossbeam_channel::Sender<Notification> {
 
 pub struct DsSlot {
     pub rom: DsSlotRom,
-    pub save_type: Option<SaveType>,
+    /// The save type the game is expected to use, before taking the existing save file (if any)
+    /// into account.
+    pub save_type: Option<(SaveType, SaveTypeSource)>,
     pub has_ir: bool,
 }
 
+pub struct GbaSlot {
+    pub cart: gba_slot::Cart,
+    /// Where the cart's SRAM contents get written back to when the emulator stops, if it has any.
+    pub sram_path: Option<PathBuf>,
+}
+
+fn flush_gba_sram(gba_slot: &mut gba_slot::GbaSlot, path: Option<&Path>) {
+    let (Some(cart), Some(path)) = (gba_slot.cart_mut(), path) else {
+        return;
+    };
+    let Some(sram) = cart.sram().filter(|_| cart.sram_dirty()) else {
+        return;
+    };
+    if let Err(err) = saves::write_atomically(path, sram) {
+        error!(
+            "GBA SRAM error",
+            "Couldn't write GBA cart SRAM to `{}`: {err}",
+            path.display()
+        );
+    } else {
+        cart.mark_sram_flushed();
+    }
+}
+
 #[cfg(feature = "dldi")]
 pub struct Dldi {
     pub root_path: PathBuf,
     pub skip_path: PathBuf,
 }
 
-fn read_save_file_contents(save_path: &PathBuf) -> io::Result<Option<BoxedByteSlice>> {
+fn read_save_file_contents(save_path: &Path) -> io::Result<Option<BoxedByteSlice>> {
     let mut save_file = match File::open(save_path) {
         Ok(save_file) => save_file,
         Err(err) => match err.kind() {
@@ -160,6 +341,60 @@ fn read_save_file_contents(save_path: &PathBuf) -> io::Result<Option<BoxedByteSl
     Ok(Some(save_contents))
 }
 
+/// Loads the GBA ROM image at the given path (which may only contain its first part, such as the
+/// header) as a cart to insert into the GBA slot, along with its SRAM contents from a `.sav` file
+/// next to it, if present.
+pub fn load_gba_slot(rom_path: &Path) -> Option<GbaSlot> {
+    let rom = match File::open(rom_path).and_then(|file| {
+        let mut rom = Vec::new();
+        file.take(gba_slot::MAX_ROM_SIZE as u64)
+            .read_to_end(&mut rom)?;
+        Ok(rom)
+    }) {
+        Ok(rom) => rom,
+        Err(err) => {
+            error!(
+                "GBA ROM error",
+                "Couldn't read GBA ROM at `{}`: {err}",
+                rom_path.display()
+            );
+            return None;
+        }
+    };
+    let mut rom_contents = BoxedByteSlice::new_zeroed(rom.len());
+    rom_contents.copy_from_slice(&rom);
+
+    let sram_path = rom_path.with_extension("sav");
+    let sram = read_save_file_contents(&sram_path).unwrap_or_else(|err| {
+        error!("GBA SRAM error", "Couldn't read GBA cart SRAM: {err}");
+        None
+    });
+    let has_sram = sram.is_some();
+
+    match gba_slot::Cart::new(rom_contents, sram) {
+        Ok(cart) => Some(GbaSlot {
+            cart,
+            sram_path: has_sram.then_some(sram_path),
+        }),
+        Err(gba_slot::CreationError::InvalidRomSize) => {
+            error!(
+                "GBA ROM error",
+                "The GBA ROM at `{}` is too small to contain a header.",
+                rom_path.display()
+            );
+            None
+        }
+        Err(gba_slot::CreationError::InvalidSramSize) => {
+            error!(
+                "GBA SRAM error",
+                "The GBA cart SRAM at `{}` is too large, it can be at most 64 KiB.",
+                sram_path.display()
+            );
+            None
+        }
+    }
+}
+
 fn setup_ds_slot(
     ds_slot: Option<DsSlot>,
     save_path: &Option<PathBuf>,
@@ -178,25 +413,29 @@ fn setup_ds_slot(
         };
 
         let save_type = if let Some(save_contents) = &save_contents {
-            if let Some(save_type) = ds_slot.save_type {
+            if let Some((save_type, source)) = ds_slot.save_type {
                 let expected_len = save_type.expected_len();
                 if expected_len != Some(save_contents.len()) {
-                    let (chosen_save_type, chosen) = if let Some(detected_save_type) =
-                        SaveType::from_save_len(save_contents.len())
-                    {
-                        (detected_save_type, "existing save file")
-                    } else {
-                        (save_type, "database entry")
-                    };
+                    // A save type set by the user is respected even if it doesn't match the file,
+                    // as it's most likely meant to fix a misdetection
+                    let (chosen_save_type, chosen) =
+                        match SaveType::from_save_len(save_contents.len()) {
+                            Some(detected_save_type) if source != SaveTypeSource::Override => {
+                                (detected_save_type, SaveTypeSource::SaveFile)
+                            }
+                            _ => (save_type, source),
+                        };
                     warning!(
                         "Save file size mismatch",
-                        "Unexpected save file size: expected {}, got {} B; respecting {chosen}.",
+                        "Unexpected save file size: expected {}, got {} B; respecting the save \
+                         type from {}.",
                         if let Some(expected_len) = expected_len {
                             format!("{expected_len} B")
                         } else {
                             "no file".to_owned()
                         },
                         save_contents.len(),
+                        chosen.description(),
                     );
                     chosen_save_type
                 } else {
@@ -214,14 +453,17 @@ fn setup_ds_slot(
                 })
             }
         } else {
-            ds_slot.save_type.unwrap_or_else(|| {
-                error!(
+            ds_slot
+                .save_type
+                .map(|(save_type, _)| save_type)
+                .unwrap_or_else(|| {
+                    error!(
                     "Unknown save type",
                     "No existing save file present and no database entry found, defaulting to an \
                      empty save.",
                 );
-                SaveType::None
-            })
+                    SaveType::None
+                })
         };
 
         let spi = if save_type == SaveType::None {
@@ -245,7 +487,7 @@ fn setup_ds_slot(
                 }
                 None => SaveContents::New(expected_len),
             };
-            match save_type {
+            let spi: Result<ds_slot::spi::Spi, String> = match save_type {
                 SaveType::None => unreachable!(),
                 SaveType::Eeprom4k => ds_slot::spi::eeprom_4k::Eeprom4k::new(
                     save_contents,
@@ -253,9 +495,8 @@ fn setup_ds_slot(
                     #[cfg(feature = "log")]
                     logger.new(slog::o!("ds_spi" => "eeprom_4k")),
                 )
-                // NOTE: The save contents' size is ensured beforehand, this should never occur.
-                .expect("couldn't create 4 Kib EEPROM DS slot SPI device")
-                .into(),
+                .map(Into::into)
+                .map_err(|err| err.to_string()),
                 SaveType::EepromFram64k | SaveType::EepromFram512k | SaveType::EepromFram1m => {
                     ds_slot::spi::eeprom_fram::EepromFram::new(
                         save_contents,
@@ -263,9 +504,8 @@ fn setup_ds_slot(
                         #[cfg(feature = "log")]
                         logger.new(slog::o!("ds_spi" => "eeprom_fram")),
                     )
-                    // NOTE: The save contents' size is ensured beforehand, this should never occur.
-                    .expect("couldn't create EEPROM/FRAM DS slot SPI device")
-                    .into()
+                    .map(Into::into)
+                    .map_err(|err| err.to_string())
                 }
                 SaveType::Flash2m | SaveType::Flash4m | SaveType::Flash8m => {
                     ds_slot::spi::flash::Flash::new(
@@ -274,25 +514,33 @@ fn setup_ds_slot(
                         ds_slot.has_ir,
                         #[cfg(feature = "log")]
                         logger.new(
-                            slog::o!("ds_spi" => if ds_slot.has_ir { "flash" } else { "flash_ir" }),
+                            slog::o!("ds_spi" => if ds_slot.has_ir { "flash_ir" } else { "flash" }),
                         ),
                     )
-                    // NOTE: The save contents' size is ensured beforehand, this should never occur.
-                    .expect("couldn't create FLASH DS slot SPI device")
-                    .into()
+                    .map(Into::into)
+                    .map_err(|err| err.to_string())
                 }
                 SaveType::Nand64m | SaveType::Nand128m | SaveType::Nand256m => {
-                    error!(
-                        "Save file unsupported",
-                        "TODO: NAND saves are currently unsupported, falling back to no save file.",
-                    );
-                    ds_slot::spi::Empty::new(
+                    ds_slot::spi::nand::Nand::new(
+                        save_contents,
                         #[cfg(feature = "log")]
-                        logger.new(slog::o!("ds_spi" => "nand_todo")),
+                        logger.new(slog::o!("ds_spi" => "nand")),
                     )
-                    .into()
+                    .map(Into::into)
+                    .map_err(|err| err.to_string())
                 }
-            }
+            };
+            spi.unwrap_or_else(|err| {
+                error!(
+                    "Save file error",
+                    "Couldn't create the save chip: {err}; falling back to no save file.",
+                );
+                ds_slot::spi::Empty::new(
+                    #[cfg(feature = "log")]
+                    logger.new(slog::o!("ds_spi" => "empty")),
+                )
+                .into()
+            })
         };
 
         (Some(rom), spi)
@@ -308,36 +556,43 @@ fn setup_ds_slot(
     }
 }
 
+/// Creates a crash hook pausing emulation and reporting unhandled CPU exceptions to the UI thread.
+fn crash_hook(
+    to_ui: &crossbeam_channel::Sender<Notification>,
+    shared_state: &Arc<SharedState>,
+) -> emu::crash::Hook<Interpreter> {
+    let to_ui = to_ui.clone();
+    let shared_state = Arc::clone(shared_state);
+    Box::new(move |emu, crash| {
+        shared_state.playing.store(false, Ordering::Relaxed);
+        // The BIOS would only loop forever from here on, so there's no point in finishing the
+        // current frame
+        emu.run_cancel_token().cancel();
+        let _ = to_ui.send(Notification::Crashed(Box::new(crash.clone())));
+    })
+}
+
 fn build_emu<E: cpu::Engine>(emu_builder: emu::Builder, engine: E) -> Option<emu::Emu<E>> {
     match emu_builder.build(engine) {
         Ok(emu) => Some(emu),
-        Err(err) => match err {
-            emu::BuildError::MissingRom => unreachable!("Missing DS slot ROM"),
-            emu::BuildError::MissingSysFiles => unreachable!("Missing emulator system files"),
-            emu::BuildError::RomCreation(err) => match err {
-                ds_slot::rom::normal::CreationError::InvalidSize => {
-                    unreachable!("Invalid DS slot ROM file size")
-                }
-            },
-            emu::BuildError::RomNeedsDecryptionButNoBiosProvided => {
-                error!(
-                    "Emulator error",
-                    "Couldn't start emulator: ROM needs decryption but no BIOS provided."
-                );
-                None
-            }
-        },
+        Err(err) => {
+            error!("Emulator error", "Couldn't start emulator: {err}.");
+            None
+        }
     }
 }
 
 pub struct LaunchData {
     pub sys_files: SysFiles,
     pub ds_slot: Option<DsSlot>,
+    pub gba_slot: Option<GbaSlot>,
     #[cfg(feature = "dldi")]
     pub dldi: Option<Dldi>,
 
     pub model: Model,
     pub skip_firmware: bool,
+    pub batch_duration: u32,
+    pub threaded_3d_geometry: bool,
 
     pub save_path: Option<PathBuf>,
     pub save_interval_ms: f32,
@@ -345,6 +600,7 @@ pub struct LaunchData {
     pub shared_state: Arc<SharedState>,
     pub from_ui: crossbeam_channel::Receiver<Message>,
     pub to_ui: crossbeam_channel::Sender<Notification>,
+    pub notifications: notifications::Sender,
 
     pub audio_tx_data: Option<audio::output::SenderData>,
     pub mic_rx: Option<audio::input::Receiver>,
@@ -354,39 +610,69 @@ pub struct LaunchData {
     pub paused_framerate_limit: f32,
 
     pub sync_to_audio: bool,
+    pub sub_frame_input: bool,
+    pub touch_pressure: u8,
+    pub solar_sensor_level: u8,
+    pub return_to_menu_on_shutdown: bool,
     pub audio_sample_chunk_size: u16,
+    pub audio_stereo_mode: AudioStereoMode,
     #[cfg(feature = "xq-audio")]
     pub audio_custom_sample_rate: Option<NonZeroU32>,
     #[cfg(feature = "xq-audio")]
     pub audio_channel_interp_method: AudioChannelInterpMethod,
 
     pub rtc_time_offset_seconds: i64,
+    pub rtc_frozen_date_time: Option<NaiveDateTime>,
+    /// The local and peer addresses of the IR link, if enabled.
+    pub ir_link_addrs: Option<(SocketAddr, SocketAddr)>,
+    pub crash_screen_enabled: bool,
 
     pub renderer_2d_is_accel: bool,
     pub renderer_2d: Box<dyn engine_2d::Renderer + Send>,
     pub renderer_3d_tx: Box<dyn engine_3d::RendererTx + Send>,
+    pub texture_cache: Option<dust_wgpu_3d::threaded::TextureCache>,
 
     #[cfg(feature = "logging")]
     pub logger: slog::Logger,
 }
 
+/// Maps the time an input change happened at to the scanline it should be latched at in the frame
+/// that's about to be emulated, based on its position within the previous frame's interval; this
+/// delays input by up to a frame, but preserves the timing between changes.
+fn sub_frame_input_scanline(
+    time: Instant,
+    frame_interval: Duration,
+    cur_frame_start_time: Instant,
+) -> u32 {
+    let Some(prev_frame_start_time) = cur_frame_start_time.checked_sub(frame_interval) else {
+        return 0;
+    };
+    let offset = time.saturating_duration_since(prev_frame_start_time);
+    ((offset.as_secs_f64() / frame_interval.as_secs_f64() * TOTAL_SCANLINES as f64) as u32)
+        .min(TOTAL_SCANLINES as u32 - 1)
+}
+
 #[allow(clippy::too_many_arguments)]
 pub(super) fn run(
     LaunchData {
         sys_files,
         ds_slot,
+        gba_slot,
         #[cfg(feature = "dldi")]
         dldi,
 
         model,
         skip_firmware,
+        batch_duration,
+        threaded_3d_geometry,
 
-        mut save_path,
+        save_path,
         save_interval_ms,
 
         shared_state,
         from_ui,
         to_ui,
+        notifications,
 
         audio_tx_data,
         mic_rx,
@@ -396,17 +682,26 @@ pub(super) fn run(
         paused_framerate_limit,
 
         mut sync_to_audio,
+        mut sub_frame_input,
+        mut touch_pressure,
+        solar_sensor_level,
+        mut return_to_menu_on_shutdown,
         audio_sample_chunk_size,
+        mut audio_stereo_mode,
         #[cfg(feature = "xq-audio")]
         audio_custom_sample_rate,
         #[cfg(feature = "xq-audio")]
         audio_channel_interp_method,
 
         mut rtc_time_offset_seconds,
+        mut rtc_frozen_date_time,
+        ir_link_addrs,
+        crash_screen_enabled,
 
         mut renderer_2d_is_accel,
         renderer_2d,
         renderer_3d_tx,
+        mut texture_cache,
 
         #[cfg(feature = "logging")]
         logger,
@@ -420,7 +715,17 @@ pub(super) fn run(
         };
     }
 
-    let firmware_flash = Flash::new(
+    macro_rules! toast {
+        ($kind: ident, $level: ident, $($message: tt)*) => {
+            notifications.post(notifications::Notification::new(
+                notifications::Kind::$kind,
+                notifications::Level::$level,
+                format!($($message)*),
+            ))
+        }
+    }
+
+    let firmware_flash = match Flash::new(
         SaveContents::Existing(
             sys_files
                 .firmware
@@ -429,9 +734,13 @@ pub(super) fn run(
         firmware::id_for_model(model),
         #[cfg(feature = "log")]
         logger.new(slog::o!("fw" => "")),
-    )
-    // NOTE: The firmware's size is checked before launch, this should never occur.
-    .expect("couldn't build firmware");
+    ) {
+        Ok(firmware_flash) => firmware_flash,
+        Err(err) => {
+            error!("Firmware error", "Couldn't load firmware: {err}.");
+            return frame_tx;
+        }
+    };
 
     let (ds_slot_rom, ds_slot_spi) = setup_ds_slot(
         ds_slot,
@@ -440,16 +749,43 @@ pub(super) fn run(
         &logger,
     );
 
+    #[cfg(feature = "ffmpeg")]
+    let audio_capture = recording::AudioCapture::new();
+    #[cfg(feature = "ffmpeg")]
+    let mut recorder: Option<recording::Recorder> = None;
+
+    #[cfg(feature = "frame-dump")]
+    let mut frame_dumper: Option<frame_dump::FrameDumper> = None;
+
+    #[cfg(feature = "wav-dump")]
+    let wav_dump_capture = audio::wav_dump::MixedCapture::new();
+    #[cfg(feature = "wav-dump")]
+    let mut wav_dumper: Option<audio::wav_dump::Dumper> = None;
+
+    macro_rules! audio_backend {
+        ($backend: expr) => {{
+            let backend: Box<dyn AudioBackend> = Box::new($backend);
+            #[cfg(feature = "ffmpeg")]
+            let backend = audio_capture.wrap(backend);
+            #[cfg(feature = "wav-dump")]
+            let backend = wav_dump_capture.wrap(backend);
+            backend
+        }};
+    }
+
     let mut emu_builder = emu::Builder::new(
         firmware_flash,
         ds_slot_rom,
         ds_slot_spi,
         match &audio_tx_data {
-            Some(data) => Box::new(audio::output::Sender::new(data, sync_to_audio)),
-            None => Box::new(DummyAudioBackend),
+            Some(data) => audio_backend!(audio::output::Sender::new(data, sync_to_audio)),
+            None => audio_backend!(DummyAudioBackend),
         },
         mic_rx.map(|mic_rx| Box::new(mic_rx) as Box<dyn spi::tsc::MicBackend>),
-        Box::new(rtc::Backend::new(rtc_time_offset_seconds)),
+        Box::new(rtc::Backend::new(
+            rtc_time_offset_seconds,
+            rtc_frozen_date_time,
+        )),
         renderer_2d,
         renderer_3d_tx,
         #[cfg(feature = "dldi")]
@@ -463,12 +799,30 @@ pub(super) fn run(
         logger.clone(),
     );
 
+    let gba_sram_path = gba_slot.as_ref().and_then(|slot| slot.sram_path.clone());
+    emu_builder.gba_cart = gba_slot.map(|slot| {
+        let mut cart = slot.cart;
+        if let Some(solar_sensor) = cart.solar_sensor_mut() {
+            solar_sensor.set_light_level(solar_sensor_level);
+        }
+        cart
+    });
     emu_builder.arm7_bios.clone_from(&sys_files.arm7_bios);
     emu_builder.arm9_bios.clone_from(&sys_files.arm9_bios);
+    emu_builder.key1_table.clone_from(&sys_files.key1_table);
+    emu_builder.run_cancel_token = shared_state.run_cancel_token.clone();
+    if let Some((local_addr, peer_addr)) = ir_link_addrs {
+        match ir_link::UdpBackend::new(local_addr, peer_addr) {
+            Ok(backend) => emu_builder.ir_backend = Box::new(backend),
+            Err(err) => error!("IR link error", "Couldn't set up the IR link: {err}."),
+        }
+    }
 
     emu_builder.model = model;
     emu_builder.direct_boot = skip_firmware;
-    // TODO: Set batch_duration and first_launch?
+    emu_builder.batch_duration = batch_duration;
+    emu_builder.threaded_3d_geometry = threaded_3d_geometry;
+    // TODO: Set first_launch?
     emu_builder.audio_sample_chunk_size = audio_sample_chunk_size;
     #[cfg(feature = "xq-audio")]
     {
@@ -479,9 +833,25 @@ pub(super) fn run(
     let Some(mut emu) = build_emu(emu_builder, Interpreter) else {
         return frame_tx;
     };
+    emu.spi.tsc.set_pressure(touch_pressure);
+    emu.audio.set_stereo_mode(audio_stereo_mode);
+    emu.audio.time_counter = shared_state.audio_time.clone();
+    if crash_screen_enabled {
+        emu.set_crash_hook(Some(crash_hook(&to_ui, &shared_state)));
+    }
 
     const FRAME_BASE_INTERVAL: Duration = Duration::from_nanos(1_000_000_000 / 60);
-    let mut frame_interval = framerate_ratio_limit.map(|value| FRAME_BASE_INTERVAL.div_f32(value));
+    let calc_frame_interval =
+        |framerate_ratio_limit: Option<f32>, speed_override: Option<SpeedOverride>| {
+            match speed_override {
+                Some(SpeedOverride::Ratio(value)) => Some(FRAME_BASE_INTERVAL.div_f32(value)),
+                Some(SpeedOverride::Unlimited) => None,
+                None => framerate_ratio_limit.map(|value| FRAME_BASE_INTERVAL.div_f32(value)),
+            }
+        };
+    let mut framerate_ratio_limit = framerate_ratio_limit;
+    let mut speed_override = None;
+    let mut frame_interval = calc_frame_interval(framerate_ratio_limit, speed_override);
     let mut paused_frame_interval = Duration::from_secs(1).div_f32(paused_framerate_limit);
     let mut last_frame_time = Instant::now();
 
@@ -490,44 +860,109 @@ pub(super) fn run(
     let mut last_fps_calc_time = last_frame_time;
     let mut fps = 0.0;
 
-    let mut save_interval = Duration::from_secs_f32(save_interval_ms);
-    let mut last_save_flush_time = last_frame_time;
+    let mut frame_count = 0;
+    let mut hang_detector = HangDetector::new();
+    let mut frames_to_advance = 0_u32;
+
+    let mut save_flusher = saves::Flusher::new(
+        save_path,
+        Duration::from_secs_f32(save_interval_ms / 1000.0),
+    );
 
     #[cfg(feature = "debug-views")]
     let mut debug_views = debug_views::EmuState::new();
+    // Whether both CPUs were marked as stopped because the emulator is paused; debug views can
+    // then clear the flags to resume it.
+    #[cfg(feature = "debug-views")]
+    let mut cpus_held_by_pause = false;
 
     #[cfg(feature = "gdb-server")]
     let mut gdb_server = None;
 
-    macro_rules! save {
+    #[cfg(feature = "virtual-time")]
+    let mut virtual_time_server: Option<virtual_time::VirtualTimeServer> = None;
+
+    #[cfg(feature = "remote-display")]
+    let mut remote_display: Option<remote_display::RemoteDisplayServer> = None;
+
+    #[cfg(feature = "ffmpeg")]
+    macro_rules! finish_recording {
+        () => {
+            if let Some(recorder) = recorder.take() {
+                if let Err(err) = recorder.finish() {
+                    error!("Recording error", "Couldn't finish recording: {err}");
+                }
+                notif!(Notification::RecordingStopped);
+            }
+        };
+    }
+
+    #[cfg(feature = "frame-dump")]
+    macro_rules! finish_frame_dump {
+        () => {
+            if let Some(frame_dumper) = frame_dumper.take() {
+                if let Err(err) = frame_dumper.finish() {
+                    error!("Frame dump error", "Couldn't finish frame dump: {err}");
+                }
+                notif!(Notification::FrameDumpStopped);
+            }
+        };
+    }
+
+    #[cfg(feature = "wav-dump")]
+    macro_rules! finish_wav_dump {
         () => {
-            if let Some(save_path) = &save_path {
-                if emu.ds_slot.spi.contents_dirty()
-                    && save_path
-                        .parent()
-                        .map(|parent| fs::create_dir_all(parent).is_ok())
-                        .unwrap_or(true)
-                    && fs::write(save_path, emu.ds_slot.spi.contents()).is_ok()
-                {
-                    emu.ds_slot.spi.mark_contents_flushed();
+            if let Some(wav_dumper) = wav_dumper.take() {
+                emu.audio.channel_audio_capture_data.dump_mask = 0;
+                if let Err(err) = wav_dumper.finish() {
+                    error!("WAV dump error", "Couldn't finish WAV dump: {err}");
                 }
+                notif!(Notification::WavDumpStopped);
             }
         };
     }
 
+    #[cfg(feature = "remote-display")]
+    macro_rules! stop_remote_display {
+        () => {
+            if let Some(remote_display) = remote_display.take() {
+                // Don't leave any input pressed by the remote client stuck
+                for change in remote_display.finish() {
+                    emu.apply_input_change(change);
+                }
+                shared_state
+                    .remote_display_active
+                    .store(false, Ordering::Relaxed);
+            }
+        };
+    }
+
+    // Set when the emulated system shuts down and should be booted again into the firmware menu
+    let mut restart_requested = false;
+
     'run_loop: loop {
-        let mut reset_triggered = false;
+        let mut reset_triggered = mem::take(&mut restart_requested);
 
         for message in from_ui.try_iter() {
             match message {
                 Message::UpdateInput(changes) => {
-                    emu.press_keys(changes.pressed);
-                    emu.release_keys(changes.released);
-                    if let Some(new_touch_pos) = changes.touch_pos {
-                        if let Some(touch_pos) = new_touch_pos {
-                            emu.set_touch_pos(touch_pos);
+                    let latch_scanline = match frame_interval {
+                        Some(frame_interval)
+                            if sub_frame_input && shared_state.playing.load(Ordering::Relaxed) =>
+                        {
+                            Some(sub_frame_input_scanline(
+                                changes.time,
+                                frame_interval,
+                                last_frame_time,
+                            ))
+                        }
+                        _ => None,
+                    };
+                    for change in changes.emu_changes() {
+                        if let Some(scanline) = latch_scanline {
+                            emu.queue_input_change(scanline, change);
                         } else {
-                            emu.end_touch();
+                            emu.apply_input_change(change);
                         }
                     }
                 }
@@ -554,6 +989,7 @@ pub(super) fn run(
                         notif!(Notification::SavestateCreated(
                             name,
                             Savestate {
+                                header: emu::savestate::Header::new(model),
                                 contents,
                                 save: if include_save {
                                     let spi_contents = emu.ds_slot.spi.contents();
@@ -576,6 +1012,9 @@ pub(super) fn run(
                                         framebuffer.assume_init()
                                     }
                                 },
+                                texture_cache_keys: texture_cache
+                                    .as_ref()
+                                    .map_or_else(Vec::new, |texture_cache| texture_cache.keys()),
                             }
                         ));
                     } else {
@@ -584,6 +1023,12 @@ pub(super) fn run(
                 }
 
                 Message::ApplySavestate(savestate) => {
+                    if let Err(err) = savestate.header.check_compatible(model) {
+                        toast!(SavestateFailed, Error, "Couldn't load savestate: {err}");
+                        continue;
+                    }
+                    let _transaction =
+                        save_flusher.begin(&mut emu.ds_slot.spi, saves::FlushReason::SavestateLoad);
                     if PersistentReadSavestate::new(&savestate.contents)
                         .and_then(|mut savestate| savestate.load_into(&mut emu).map_err(drop))
                         .is_ok()
@@ -593,6 +1038,26 @@ pub(super) fn run(
                                 .spi
                                 .reload_contents(SaveReloadContents::Existing(save));
                         }
+                        if let Some(texture_cache) = &texture_cache {
+                            let (texture, tex_pal) = emu.gpu.vram.texture_contents();
+                            texture_cache.prewarm(savestate.texture_cache_keys, texture, tex_pal);
+                        }
+                        toast!(SavestateLoaded, Info, "Savestate loaded");
+                    } else if savestate.header.core_version.is_empty() {
+                        toast!(
+                            SavestateFailed,
+                            Error,
+                            "Couldn't load savestate: it was created by an unknown older version \
+                             of the emulator, and is most likely incompatible"
+                        );
+                    } else {
+                        toast!(
+                            SavestateFailed,
+                            Error,
+                            "Couldn't load savestate: incompatible or corrupted data (created by \
+                             version {})",
+                            savestate.header.core_version
+                        );
                     }
                 }
 
@@ -602,22 +1067,22 @@ pub(super) fn run(
                     reload,
                     reset,
                 }) => {
-                    save!();
-                    last_save_flush_time = Instant::now();
+                    let mut transaction = save_flusher
+                        .begin(&mut emu.ds_slot.spi, saves::FlushReason::SavePathChange);
 
-                    if let Some((prev, new_prev)) = save_path.as_ref().zip(new_prev) {
+                    let prev = transaction.replace_path(new);
+                    if let Some((prev, new_prev)) = prev.zip(new_prev) {
                         if let Some(new_prev) = new_prev {
-                            if new_prev != *prev {
+                            if new_prev != prev {
                                 let _ = fs::rename(prev, new_prev);
                             }
                         } else {
                             let _ = fs::remove_file(prev);
                         }
                     }
-                    save_path = new;
 
                     if reload {
-                        if let Some(save_path) = save_path.as_ref() {
+                        if let Some(save_path) = transaction.path() {
                             let save_contents = match read_save_file_contents(save_path) {
                                 Ok(contents) => match contents {
                                     Some(contents) => SaveReloadContents::Existing(contents),
@@ -637,8 +1102,60 @@ pub(super) fn run(
                     }
                 }
 
+                Message::ImportSave(path) => {
+                    let mut transaction =
+                        save_flusher.begin(&mut emu.ds_slot.spi, saves::FlushReason::SaveImport);
+                    match saves::convert::import(&path, emu.ds_slot.spi.contents().len()) {
+                        Ok((format, contents)) => {
+                            emu.ds_slot
+                                .spi
+                                .reload_contents(SaveReloadContents::Existing(contents));
+                            emu.ds_slot.spi.mark_contents_dirty();
+                            if let Err(err) = transaction.flush(&mut emu.ds_slot.spi) {
+                                toast!(
+                                    SaveWriteFailed,
+                                    Error,
+                                    "Couldn't write save file, retrying later: {err}"
+                                );
+                            }
+                            toast!(Other, Info, "Imported {format} save file");
+                            reset_triggered = true;
+                        }
+                        Err(err) => {
+                            error!(
+                                "Save import error",
+                                "Couldn't import save file `{}`: {err}",
+                                path.display()
+                            );
+                        }
+                    }
+                }
+
+                Message::ExportSave(path, format) => {
+                    let contents = emu.ds_slot.spi.contents();
+                    if contents.is_empty() {
+                        toast!(
+                            Other,
+                            Error,
+                            "Couldn't export save file: the current game has no save memory"
+                        );
+                        continue;
+                    }
+                    let contents = saves::convert::export(contents, format);
+                    match saves::write_atomically(&path, &contents) {
+                        Ok(()) => toast!(Other, Info, "Exported {format} save file"),
+                        Err(err) => {
+                            error!(
+                                "Save export error",
+                                "Couldn't write save file to `{}`: {err}",
+                                path.display()
+                            );
+                        }
+                    }
+                }
+
                 Message::UpdateSaveIntervalMs(value) => {
-                    save_interval = Duration::from_secs_f32(value);
+                    save_flusher.set_interval(Duration::from_secs_f32(value / 1000.0));
                 }
 
                 Message::UpdateRtcTimeOffsetSeconds(value) => {
@@ -651,29 +1168,106 @@ pub(super) fn run(
                         .set_time_offset_seconds(value);
                 }
 
+                Message::UpdateRtcFrozenDateTime(value) => {
+                    rtc_frozen_date_time = value;
+                    emu.rtc
+                        .backend
+                        .as_any_mut()
+                        .downcast_mut::<rtc::Backend>()
+                        .unwrap()
+                        .set_frozen_date_time(value);
+                }
+
                 Message::UpdateRenderers {
                     renderer_2d_is_accel: new_renderer_2d_is_accel,
                     renderer_2d,
                     renderer_3d_tx,
+                    texture_cache: new_texture_cache,
                 } => {
+                    // Renderer setup is the most likely point for the frontend to crash, so make
+                    // sure no save data is lost if that happens
+                    let _transaction = save_flusher
+                        .begin(&mut emu.ds_slot.spi, saves::FlushReason::RendererSwitch);
                     renderer_2d_is_accel = new_renderer_2d_is_accel;
+                    // The accelerated 2D renderer's output can't be read back to be recorded
+                    #[cfg(feature = "ffmpeg")]
+                    if renderer_2d_is_accel {
+                        finish_recording!();
+                    }
+                    // The 3D layer capture belongs to the previous renderer, so the dump can't
+                    // continue either way
+                    #[cfg(feature = "frame-dump")]
+                    finish_frame_dump!();
+                    #[cfg(feature = "remote-display")]
+                    if renderer_2d_is_accel {
+                        stop_remote_display!();
+                    }
                     emu.gpu.engine_3d.set_renderer_tx(renderer_3d_tx);
                     emu.gpu.set_renderer_2d(renderer_2d, &mut emu.arm9);
+                    texture_cache = new_texture_cache;
                 }
 
                 Message::UpdateFramerateLimit(value) => {
-                    frame_interval = value.map(|value| FRAME_BASE_INTERVAL.div_f32(value));
+                    framerate_ratio_limit = value;
+                    frame_interval = calc_frame_interval(framerate_ratio_limit, speed_override);
                 }
 
                 Message::UpdatePausedFramerateLimit(value) => {
                     paused_frame_interval = Duration::from_secs(1).div_f32(value);
                 }
 
+                Message::AdvanceFrames(frames) => {
+                    frames_to_advance = frames_to_advance.saturating_add(frames);
+                }
+
+                Message::UpdateSpeedOverride(value) => {
+                    let was_fast =
+                        speed_override.map_or(false, SpeedOverride::is_faster_than_native);
+                    speed_override = value;
+                    frame_interval = calc_frame_interval(framerate_ratio_limit, speed_override);
+                    let is_fast =
+                        speed_override.map_or(false, SpeedOverride::is_faster_than_native);
+                    // Syncing to audio would prevent running faster than native speed
+                    if sync_to_audio && was_fast != is_fast {
+                        if let Some(data) = &audio_tx_data {
+                            emu.audio.backend =
+                                audio_backend!(audio::output::Sender::new(data, !is_fast));
+                        }
+                    }
+                }
+
+                Message::UpdateSubFrameInput(value) => {
+                    sub_frame_input = value;
+                }
+
+                Message::UpdateTouchPressure(value) => {
+                    touch_pressure = value;
+                    emu.spi.tsc.set_pressure(value);
+                }
+
+                Message::UpdateSolarSensorLevel(value) => {
+                    if let Some(solar_sensor) = emu
+                        .gba_slot
+                        .cart_mut()
+                        .and_then(|cart| cart.solar_sensor_mut())
+                    {
+                        solar_sensor.set_light_level(value);
+                    }
+                }
+
+                Message::UpdateReturnToMenuOnShutdown(value) => {
+                    return_to_menu_on_shutdown = value;
+                }
+
                 Message::UpdateSyncToAudio(value) => {
                     sync_to_audio = value;
                     if let Some(data) = &audio_tx_data {
-                        emu.audio.backend =
-                            Box::new(audio::output::Sender::new(data, sync_to_audio));
+                        emu.audio.backend = audio_backend!(audio::output::Sender::new(
+                            data,
+                            sync_to_audio
+                                && !speed_override
+                                    .map_or(false, SpeedOverride::is_faster_than_native),
+                        ));
                     }
                 }
 
@@ -681,6 +1275,11 @@ pub(super) fn run(
                     emu.audio.sample_chunk_size = value;
                 }
 
+                Message::UpdateAudioStereoMode(value) => {
+                    audio_stereo_mode = value;
+                    emu.audio.set_stereo_mode(value);
+                }
+
                 #[cfg(feature = "xq-audio")]
                 Message::UpdateAudioCustomSampleRate(value) => {
                     Audio::set_custom_sample_rate(&mut emu, value);
@@ -730,6 +1329,261 @@ pub(super) fn run(
                             .store(enabled, Ordering::Relaxed);
                     }
                 }
+
+                #[cfg(feature = "virtual-time")]
+                Message::ToggleVirtualTimeServer(addr) => {
+                    let mut enabled = addr.is_some();
+                    if virtual_time_server.is_some() != enabled {
+                        if let Some(addr) = addr {
+                            match virtual_time::VirtualTimeServer::new(addr) {
+                                Ok(mut server) => {
+                                    server.attach(&mut emu);
+                                    virtual_time_server = Some(server);
+                                }
+                                Err(err) => {
+                                    error!(
+                                        "Virtual time server not started",
+                                        "Couldn't start virtual time server: {err}"
+                                    );
+                                    enabled = false;
+                                }
+                            }
+                        } else {
+                            virtual_time_server.take().unwrap().detach(&mut emu);
+                        }
+                        shared_state
+                            .virtual_time_server_active
+                            .store(enabled, Ordering::Relaxed);
+                    }
+                }
+
+                #[cfg(feature = "remote-display")]
+                Message::ToggleRemoteDisplay(addr_and_settings) => {
+                    if let Some((addr, settings)) = addr_and_settings {
+                        if remote_display.is_none() {
+                            if renderer_2d_is_accel {
+                                error!(
+                                    "Remote display not started",
+                                    "The remote display requires the software 2D renderer, as the \
+                                     accelerated one's output can't be read back."
+                                );
+                            } else {
+                                match remote_display::RemoteDisplayServer::new(addr, settings) {
+                                    Ok(server) => {
+                                        remote_display = Some(server);
+                                        shared_state
+                                            .remote_display_active
+                                            .store(true, Ordering::Relaxed);
+                                    }
+                                    Err(err) => {
+                                        error!(
+                                            "Remote display not started",
+                                            "Couldn't start remote display server: {err}"
+                                        );
+                                    }
+                                }
+                            }
+                        }
+                    } else {
+                        stop_remote_display!();
+                    }
+                }
+
+                #[cfg(feature = "lockstep-trace")]
+                Message::ToggleLockstepTrace(enabled) => {
+                    emu.trace.set_enabled(enabled);
+                    shared_state
+                        .lockstep_trace_active
+                        .store(enabled, Ordering::Relaxed);
+                }
+
+                #[cfg(feature = "lockstep-trace")]
+                Message::DumpLockstepTrace(path) => {
+                    let mut dump = String::new();
+                    let _ = emu.trace.dump(&mut dump);
+                    if let Err(err) = fs::write(&path, dump) {
+                        error!(
+                            "Couldn't dump lockstep trace",
+                            "Couldn't write lockstep trace to {}: {err}",
+                            path.display()
+                        );
+                    }
+                }
+
+                #[cfg(feature = "debug-views")]
+                Message::ExportMemRegion(region, path) => {
+                    let contents = emu.export_mem_region(region);
+                    if let Err(err) = fs::write(&path, contents) {
+                        error!(
+                            "Couldn't export memory",
+                            "Couldn't write {} contents to {}: {err}",
+                            region.name(),
+                            path.display()
+                        );
+                    } else {
+                        toast!(
+                            Other,
+                            Info,
+                            "Exported {} to {}",
+                            region.name(),
+                            path.display()
+                        );
+                    }
+                }
+
+                #[cfg(all(feature = "fault-injection", debug_assertions))]
+                Message::InjectFault(fault) => emu.inject_fault(fault),
+
+                #[cfg(all(feature = "fault-injection", debug_assertions))]
+                Message::SetPeriodicFault(periodic) => {
+                    let script = periodic.map(|(fault, interval_ms)| {
+                        // Emulated time is measured in ARM7 cycles, at 2^25 Hz
+                        let interval = (u64::from(interval_ms) << 25) / 1000;
+                        let mut next_time = emu.schedule.cur_time().0 + interval;
+                        Box::new(move |emu: &mut emu::Emu<Interpreter>| {
+                            let cur_time = emu.schedule.cur_time().0;
+                            if cur_time >= next_time {
+                                emu.inject_fault(fault);
+                                next_time = cur_time + interval;
+                            }
+                        }) as emu::fault_injection::Script<Interpreter>
+                    });
+                    emu.set_fault_script(script);
+                }
+
+                #[cfg(feature = "debug-views")]
+                Message::ExportMemRange {
+                    arm9,
+                    addr,
+                    len,
+                    path,
+                } => {
+                    let contents = emu.export_mem_range(arm9, addr, len);
+                    if let Err(err) = fs::write(&path, contents) {
+                        error!(
+                            "Couldn't export memory",
+                            "Couldn't write memory contents to {}: {err}",
+                            path.display()
+                        );
+                    } else {
+                        toast!(
+                            Other,
+                            Info,
+                            "Exported {len:#X} bytes at {addr:#010X} to {}",
+                            path.display()
+                        );
+                    }
+                }
+
+                #[cfg(feature = "debug-views")]
+                Message::ImportMemRange { arm9, addr, path } => match fs::read(&path) {
+                    Ok(contents) => {
+                        emu.import_mem_range(arm9, addr, &contents);
+                        toast!(
+                            Other,
+                            Info,
+                            "Imported {:#X} bytes from {} at {addr:#010X}",
+                            contents.len(),
+                            path.display()
+                        );
+                    }
+                    Err(err) => {
+                        error!(
+                            "Couldn't import memory",
+                            "Couldn't read {}: {err}",
+                            path.display()
+                        );
+                    }
+                },
+
+                #[cfg(feature = "ffmpeg")]
+                Message::StartRecording(settings) => {
+                    finish_recording!();
+                    if renderer_2d_is_accel {
+                        error!(
+                            "Recording error",
+                            "Recording is only supported with the software 2D renderers."
+                        );
+                        notif!(Notification::RecordingStopped);
+                    } else {
+                        #[cfg(not(feature = "xq-audio"))]
+                        let audio_sample_rate = audio::output::DEFAULT_INPUT_SAMPLE_RATE;
+                        #[cfg(feature = "xq-audio")]
+                        let audio_sample_rate = emu
+                            .audio
+                            .custom_sample_rate()
+                            .map_or(audio::output::DEFAULT_INPUT_SAMPLE_RATE, NonZeroU32::get);
+                        match recording::Recorder::start(
+                            settings,
+                            audio_sample_rate,
+                            &audio_capture,
+                        ) {
+                            Ok(new_recorder) => recorder = Some(new_recorder),
+                            Err(err) => {
+                                error!("Recording error", "Couldn't start recording: {err}");
+                                notif!(Notification::RecordingStopped);
+                            }
+                        }
+                    }
+                }
+
+                #[cfg(feature = "ffmpeg")]
+                Message::StopRecording => {
+                    finish_recording!();
+                }
+
+                #[cfg(feature = "frame-dump")]
+                Message::StartFrameDump(settings) => {
+                    finish_frame_dump!();
+                    if renderer_2d_is_accel {
+                        error!(
+                            "Frame dump error",
+                            "Frame dumps are only supported with the software 2D renderers."
+                        );
+                        notif!(Notification::FrameDumpStopped);
+                    } else {
+                        match frame_dump::FrameDumper::start(settings) {
+                            Ok(new_frame_dumper) => frame_dumper = Some(new_frame_dumper),
+                            Err(err) => {
+                                error!("Frame dump error", "Couldn't start frame dump: {err}");
+                                notif!(Notification::FrameDumpStopped);
+                            }
+                        }
+                    }
+                }
+
+                #[cfg(feature = "frame-dump")]
+                Message::StopFrameDump => {
+                    finish_frame_dump!();
+                }
+
+                #[cfg(feature = "wav-dump")]
+                Message::StartWavDump(settings) => {
+                    finish_wav_dump!();
+                    #[cfg(not(feature = "xq-audio"))]
+                    let audio_sample_rate = audio::output::DEFAULT_INPUT_SAMPLE_RATE;
+                    #[cfg(feature = "xq-audio")]
+                    let audio_sample_rate = emu
+                        .audio
+                        .custom_sample_rate()
+                        .map_or(audio::output::DEFAULT_INPUT_SAMPLE_RATE, NonZeroU32::get);
+                    match audio::wav_dump::Dumper::start(
+                        settings,
+                        audio_sample_rate,
+                        &wav_dump_capture,
+                    ) {
+                        Ok(new_wav_dumper) => wav_dumper = Some(new_wav_dumper),
+                        Err(err) => {
+                            error!("WAV dump error", "Couldn't start WAV dump: {err}");
+                            notif!(Notification::WavDumpStopped);
+                        }
+                    }
+                }
+
+                #[cfg(feature = "wav-dump")]
+                Message::StopWavDump => {
+                    finish_wav_dump!();
+                }
             }
         }
 
@@ -741,12 +1595,39 @@ pub(super) fn run(
             playing &= gdb_server.is_running();
         }
 
+        #[cfg(feature = "virtual-time")]
+        if let Some(virtual_time_server) = &mut virtual_time_server {
+            virtual_time_server.poll(&mut emu);
+        }
+
+        #[cfg(feature = "remote-display")]
+        if let Some(remote_display) = &mut remote_display {
+            while let Some(change) = remote_display.poll_input() {
+                emu.apply_input_change(change);
+            }
+        }
+
         if reset_triggered {
+            frame_count = 0;
+            hang_detector.reset(&shared_state.hang_state);
+            frames_to_advance = 0;
+            #[cfg(feature = "debug-views")]
+            {
+                cpus_held_by_pause = false;
+            }
+
+            // Make sure the save file is up to date before the game gets relaunched, as it'll
+            // read it back from the save chip
+            drop(save_flusher.begin(&mut emu.ds_slot.spi, saves::FlushReason::Reset));
+
             #[cfg(feature = "xq-audio")]
             let audio_custom_sample_rate = emu.audio.custom_sample_rate();
             #[cfg(feature = "xq-audio")]
             let audio_channel_interp_method = emu.audio.channel_interp_method();
 
+            #[cfg(feature = "lockstep-trace")]
+            let lockstep_trace_enabled = emu.trace.is_enabled();
+
             let (renderer_2d, renderer_3d_tx) = emu.gpu.into_renderers();
 
             let mut emu_builder = emu::Builder::new(
@@ -763,12 +1644,19 @@ pub(super) fn run(
                 logger.clone(),
             );
 
+            emu_builder.camera_backend = emu.camera.backend;
+            emu_builder.ir_backend = emu.ds_slot.ir_backend;
+            emu_builder.gba_cart = emu.gba_slot.eject();
+            emu_builder.run_cancel_token = emu.run_cancel_token().clone();
             emu_builder.arm7_bios.clone_from(&sys_files.arm7_bios);
             emu_builder.arm9_bios.clone_from(&sys_files.arm9_bios);
+            emu_builder.key1_table.clone_from(&sys_files.key1_table);
 
             emu_builder.model = model;
             emu_builder.direct_boot = skip_firmware;
-            // TODO: Set batch_duration and first_launch?
+            emu_builder.batch_duration = batch_duration;
+            emu_builder.threaded_3d_geometry = threaded_3d_geometry;
+            // TODO: Set first_launch?
             emu_builder.audio_sample_chunk_size = emu.audio.sample_chunk_size;
             #[cfg(feature = "xq-audio")]
             {
@@ -778,29 +1666,121 @@ pub(super) fn run(
 
             if let Some(new_emu) = build_emu(emu_builder, Interpreter) {
                 emu = new_emu;
+                emu.spi.tsc.set_pressure(touch_pressure);
+                emu.audio.set_stereo_mode(audio_stereo_mode);
+                emu.audio.time_counter = shared_state.audio_time.clone();
+                if crash_screen_enabled {
+                    emu.set_crash_hook(Some(crash_hook(&to_ui, &shared_state)));
+                }
+                #[cfg(feature = "lockstep-trace")]
+                emu.trace.set_enabled(lockstep_trace_enabled);
+                #[cfg(feature = "virtual-time")]
+                if let Some(virtual_time_server) = &mut virtual_time_server {
+                    virtual_time_server.emu_reset(&mut emu);
+                }
             } else {
                 return frame_tx;
             };
         }
 
-        playing &= shared_state.playing.load(Ordering::Relaxed);
+        let playing_requested = shared_state.playing.load(Ordering::Relaxed);
+        if playing_requested {
+            // Frame advance requests only apply while paused
+            frames_to_advance = 0;
+        }
+        let advancing_frame = frames_to_advance != 0;
+        playing &= playing_requested || advancing_frame;
+
+        #[cfg(feature = "debug-views")]
+        {
+            #[cfg(feature = "gdb-server")]
+            let gdb_server_attached = gdb_server.is_some();
+            #[cfg(not(feature = "gdb-server"))]
+            let gdb_server_attached = false;
+            if gdb_server_attached {
+                // The GDB server manages the CPUs' stopped state by itself
+                cpus_held_by_pause = false;
+            } else if !playing {
+                if cpus_held_by_pause && !emu.arm7.is_stopped && !emu.arm9.is_stopped {
+                    // A disassembly view resumed the CPUs (i.e. to run to a specific instruction)
+                    cpus_held_by_pause = false;
+                    playing = true;
+                    shared_state.playing.store(true, Ordering::Relaxed);
+                    notif!(Notification::PlayingChanged(true));
+                } else {
+                    emu.arm7.is_stopped = true;
+                    emu.arm9.is_stopped = true;
+                    cpus_held_by_pause = true;
+                }
+            } else if cpus_held_by_pause {
+                emu.arm7.is_stopped = false;
+                emu.arm9.is_stopped = false;
+                cpus_held_by_pause = false;
+            }
+        }
+
+        #[cfg(feature = "virtual-time")]
+        if let Some(virtual_time_server) = &virtual_time_server {
+            playing &= virtual_time_server.has_time_left(&emu);
+        }
 
         let frame = frame_tx.current();
 
         if playing {
-            #[cfg(not(feature = "gdb-server"))]
+            #[cfg(feature = "wav-dump")]
+            if let Some(wav_dumper) = &wav_dumper {
+                wav_dumper.prepare_channel_capture(&mut emu.audio.channel_audio_capture_data);
+            }
+            let run_start = shared_state.emulation_time.start();
+            #[cfg(not(any(feature = "gdb-server", feature = "debug-views")))]
             let run_output = emu.run();
-            #[cfg(feature = "gdb-server")]
+            #[cfg(any(feature = "gdb-server", feature = "debug-views"))]
             let run_output = {
                 let mut run_forever = [0; 2];
-                emu.run_with_cycles(if let Some(gdb_server) = &mut gdb_server {
+                #[cfg(feature = "gdb-server")]
+                let cycles = if let Some(gdb_server) = &mut gdb_server {
                     &mut gdb_server.remaining_step_cycles
                 } else {
                     &mut run_forever
-                })
+                };
+                #[cfg(not(feature = "gdb-server"))]
+                let cycles = &mut run_forever;
+                emu.run_with_cycles(cycles)
             };
+            shared_state.emulation_time.stop(run_start);
             match run_output {
-                RunOutput::FrameFinished => {}
+                RunOutput::FrameFinished => {
+                    frame_count += 1;
+                    hang_detector.frame_finished(&emu, &shared_state.hang_state);
+                    if advancing_frame {
+                        frames_to_advance -= 1;
+                    }
+                    #[cfg(feature = "ffmpeg")]
+                    if let Some(recorder_) = &mut recorder {
+                        if recorder_.is_running() {
+                            recorder_.push_frame(emu.gpu.renderer_2d().framebuffer());
+                        } else {
+                            // FFmpeg exited early, retrieve the error
+                            finish_recording!();
+                        }
+                    }
+                    #[cfg(feature = "frame-dump")]
+                    if let Some(frame_dumper_) = &mut frame_dumper {
+                        if frame_dumper_.is_running() {
+                            frame_dumper_.push_frame(frame_count, &emu.gpu);
+                        } else {
+                            finish_frame_dump!();
+                        }
+                    }
+                }
+                // Process any pending messages before resuming the interrupted frame
+                RunOutput::Cancelled => continue,
+                RunOutput::Shutdown if return_to_menu_on_shutdown && !skip_firmware => {
+                    // The system will be booted again through the firmware, allowing the title to
+                    // be relaunched from its menu
+                    restart_requested = true;
+                    playing = false;
+                }
                 RunOutput::Shutdown => {
                     notif!(Notification::Stopped);
                     playing = false;
@@ -809,14 +1789,36 @@ pub(super) fn run(
                         gdb_server.emu_shutdown();
                     }
                 }
-                #[cfg(feature = "gdb-server")]
-                RunOutput::StoppedByDebugHook => {}
+                #[cfg(any(feature = "gdb-server", feature = "debug-views"))]
+                RunOutput::StoppedByDebugHook => {
+                    #[cfg(feature = "debug-views")]
+                    {
+                        #[cfg(feature = "gdb-server")]
+                        let stopped_by_gdb_server = gdb_server.is_some();
+                        #[cfg(not(feature = "gdb-server"))]
+                        let stopped_by_gdb_server = false;
+                        if !stopped_by_gdb_server {
+                            // Hit a breakpoint set from a disassembly view
+                            shared_state.playing.store(false, Ordering::Relaxed);
+                            notif!(Notification::PlayingChanged(false));
+                        }
+                    }
+                }
                 #[cfg(feature = "gdb-server")]
                 RunOutput::CyclesOver(core_mask) => {
                     if let Some(gdb_server) = &mut gdb_server {
                         gdb_server.cycles_over(&mut emu, core_mask);
                     }
                 }
+                #[cfg(all(feature = "debug-views", not(feature = "gdb-server")))]
+                RunOutput::CyclesOver(_) => {}
+                #[cfg(feature = "virtual-time")]
+                RunOutput::TimeLimitReached => {}
+            }
+
+            #[cfg(feature = "virtual-time")]
+            if let Some(virtual_time_server) = &mut virtual_time_server {
+                virtual_time_server.check_time_limit(&emu);
             }
         }
 
@@ -825,10 +1827,27 @@ pub(super) fn run(
                 .fb
                 .copy_from_slice(emu.gpu.renderer_2d().framebuffer());
         }
+        frame.screen_engines = emu.gpu.screen_engines();
+
+        #[cfg(feature = "remote-display")]
+        if let Some(remote_display) = &mut remote_display {
+            remote_display.push_frame(emu.gpu.renderer_2d().framebuffer());
+        }
+
+        // Needs to happen before the debug views drain the channel capture buffers
+        #[cfg(feature = "wav-dump")]
+        if let Some(wav_dumper) = &mut wav_dumper {
+            wav_dumper.write_channel_samples(&emu.audio.channel_audio_capture_data);
+        }
 
         #[cfg(feature = "debug-views")]
         debug_views.update(&mut emu, &mut frame.debug, &to_ui);
 
+        #[cfg(feature = "wav-dump")]
+        if let Some(wav_dumper) = &wav_dumper {
+            wav_dumper.clear_channel_samples(&mut emu.audio.channel_audio_capture_data);
+        }
+
         frames_since_last_fps_calc += 1;
         let now = Instant::now();
         let elapsed = now - last_fps_calc_time;
@@ -838,28 +1857,62 @@ pub(super) fn run(
             frames_since_last_fps_calc = 0;
         }
         frame.fps = fps;
+        frame.frame_count = frame_count;
+        frame.input = FrameInput {
+            keys: emu.input.pressed_keys(),
+            touch_pos: emu
+                .spi
+                .tsc
+                .pen_down()
+                .then(|| [emu.spi.tsc.x_pos(), emu.spi.tsc.y_pos()]),
+        };
 
         frame_tx.finish();
 
-        let now = Instant::now();
-        if now - last_save_flush_time >= save_interval {
-            last_save_flush_time = now;
-            save!();
+        save_flusher.update_panic_snapshot(&emu.ds_slot.spi);
+        match save_flusher.flush_if_due(&mut emu.ds_slot.spi) {
+            Ok(true) => toast!(SaveWritten, Info, "Save file written"),
+            Ok(false) => {}
+            Err(err) => toast!(
+                SaveWriteFailed,
+                Error,
+                "Couldn't write save file, retrying later: {err}"
+            ),
         }
 
-        let new_rtc_time_offset_seconds = emu
+        let rtc_backend = emu
             .rtc
             .backend
             .as_any()
             .downcast_ref::<rtc::Backend>()
-            .unwrap()
-            .time_offset_seconds();
+            .unwrap();
+        let new_rtc_time_offset_seconds = rtc_backend.time_offset_seconds();
         if new_rtc_time_offset_seconds != rtc_time_offset_seconds {
             rtc_time_offset_seconds = new_rtc_time_offset_seconds;
             notif!(Notification::RtcTimeOffsetSecondsUpdated(
                 new_rtc_time_offset_seconds,
             ));
         }
+        let new_rtc_frozen_date_time = rtc_backend.frozen_date_time();
+        if new_rtc_frozen_date_time != rtc_frozen_date_time {
+            rtc_frozen_date_time = new_rtc_frozen_date_time;
+            notif!(Notification::RtcFrozenDateTimeUpdated(
+                new_rtc_frozen_date_time,
+            ));
+        }
+
+        // Emulated time only advances inside the time slices granted by the virtual time
+        // controller, which are run as fast as possible
+        #[cfg(feature = "virtual-time")]
+        if let Some(virtual_time_server) = &mut virtual_time_server {
+            if playing {
+                continue;
+            }
+            if playing_requested && !virtual_time_server.has_time_left(&emu) {
+                virtual_time_server.wait_for_time(&mut emu, paused_frame_interval);
+                continue;
+            }
+        }
 
         if let Some(frame_interval) = if playing {
             frame_interval
@@ -884,7 +1937,17 @@ pub(super) fn run(
         }
     }
 
-    save!();
+    drop(save_flusher.begin(&mut emu.ds_slot.spi, saves::FlushReason::Exit));
+    flush_gba_sram(&mut emu.gba_slot, gba_sram_path.as_deref());
+
+    #[cfg(feature = "ffmpeg")]
+    finish_recording!();
+
+    #[cfg(feature = "frame-dump")]
+    finish_frame_dump!();
+
+    #[cfg(feature = "wav-dump")]
+    finish_wav_dump!();
 
     frame_tx
 }
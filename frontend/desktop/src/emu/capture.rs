@@ -0,0 +1,170 @@
+//! Frame layout and metadata shared by all features capturing the emulator's video output
+//! (recordings and frame dumps), so that captures agree with each other on how the screens are
+//! arranged, oriented and color-tagged.
+//!
+//! Captured pixels are always square and in the sRGB color space; orientation is stored as
+//! metadata rather than applied to the pixels, and only in quarter turns, as that's all container
+//! formats can represent.
+
+use dust_core::gpu::{Framebuffer, SCREEN_HEIGHT, SCREEN_WIDTH};
+use std::slice;
+
+/// A clockwise rotation to be applied by players and image viewers when displaying a capture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+impl Rotation {
+    /// Rounds a clockwise rotation in degrees (like the `screen_rot` setting) to the nearest
+    /// quarter turn.
+    pub fn from_degrees(degrees: u16) -> Self {
+        match (degrees as u32 + 45) / 90 % 4 {
+            0 => Rotation::None,
+            1 => Rotation::Cw90,
+            2 => Rotation::Cw180,
+            _ => Rotation::Cw270,
+        }
+    }
+
+    pub fn clockwise_degrees(self) -> u16 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Cw90 => 90,
+            Rotation::Cw180 => 180,
+            Rotation::Cw270 => 270,
+        }
+    }
+
+    /// Returns the value of the EXIF `Orientation` tag describing the rotation.
+    pub fn exif_orientation(self) -> u16 {
+        match self {
+            Rotation::None => 1,
+            Rotation::Cw90 => 6,
+            Rotation::Cw180 => 3,
+            Rotation::Cw270 => 8,
+        }
+    }
+
+    /// Returns a minimal big-endian EXIF block containing only the `Orientation` tag, as stored in
+    /// PNG `eXIf` chunks.
+    pub fn exif_block(self) -> [u8; 26] {
+        // A TIFF header with the first IFD right after it, containing a single entry for tag
+        // 0x0112 (Orientation) with type 3 (SHORT) and count 1, and no next IFD
+        let mut block = *b"MM\0\x2A\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01\0\0\0\0\0\0\0\0";
+        block[18..20].copy_from_slice(&self.exif_orientation().to_be_bytes());
+        block
+    }
+}
+
+/// How the screens are arranged in captured frames.
+#[derive(Clone, Debug)]
+pub struct Layout {
+    size: [usize; 2],
+    /// The area covered by each screen (top, then bottom), in pixels, as its top-left and
+    /// bottom-right corners.
+    screen_rects: [Option<[[usize; 2]; 2]>; 2],
+    top_screen_above: bool,
+    rotation: Rotation,
+}
+
+impl Layout {
+    /// The console's raw output: both screens stacked vertically without a gap, with the top
+    /// screen first and no rotation, regardless of how they're displayed.
+    pub fn raw_stacked() -> Self {
+        Layout {
+            size: [SCREEN_WIDTH, SCREEN_HEIGHT * 2],
+            screen_rects: [
+                Some([[0, 0], [SCREEN_WIDTH, SCREEN_HEIGHT]]),
+                Some([[0, SCREEN_HEIGHT], [SCREEN_WIDTH, SCREEN_HEIGHT * 2]]),
+            ],
+            top_screen_above: false,
+            rotation: Rotation::None,
+        }
+    }
+
+    /// Creates a layout matching the on-screen one, given its size in DS pixels and the fractions
+    /// of it each screen's corners are at (as in the UI's `ScreenLayout`).
+    pub fn new(
+        size: [f32; 2],
+        screen_rects: [Option<[[f32; 2]; 2]>; 2],
+        top_screen_above: bool,
+        rotation: Rotation,
+    ) -> Self {
+        let size = size.map(|size| (size.round() as usize).max(1));
+        Layout {
+            size,
+            screen_rects: screen_rects.map(|rect| {
+                rect.map(|rect| {
+                    rect.map(|corner| {
+                        [
+                            (corner[0] * size[0] as f32).round() as usize,
+                            (corner[1] * size[1] as f32).round() as usize,
+                        ]
+                    })
+                })
+                .filter(|[start, end]| start[0] < end[0] && start[1] < end[1])
+            }),
+            top_screen_above,
+            rotation,
+        }
+    }
+
+    /// The size of captured frames in pixels, before rotation.
+    #[inline]
+    pub fn size(&self) -> [usize; 2] {
+        self.size
+    }
+
+    #[inline]
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    fn is_raw_stacked(&self) -> bool {
+        self.size == [SCREEN_WIDTH, SCREEN_HEIGHT * 2]
+            && self.screen_rects == Layout::raw_stacked().screen_rects
+    }
+
+    /// Arranges the screens in `fb` according to the layout, returning the resulting RGBA8
+    /// pixels; areas not covered by any screen are opaque black.
+    pub fn compose(&self, fb: &Framebuffer) -> Box<[u8]> {
+        let raw = unsafe {
+            slice::from_raw_parts(
+                fb.as_ptr() as *const u8,
+                2 * 4 * SCREEN_WIDTH * SCREEN_HEIGHT,
+            )
+        };
+        if self.is_raw_stacked() {
+            return raw.into();
+        }
+
+        let mut data = [0, 0, 0, 0xFF].repeat(self.size[0] * self.size[1]);
+        let order = if self.top_screen_above {
+            [1, 0]
+        } else {
+            [0, 1]
+        };
+        for i in order {
+            let Some([start, end]) = self.screen_rects[i] else {
+                continue;
+            };
+            let screen = &fb[i];
+            let width = end[0] - start[0];
+            let height = end[1] - start[1];
+            for y in start[1]..end[1].min(self.size[1]) {
+                let src_y = (y - start[1]) * SCREEN_HEIGHT / height;
+                for x in start[0]..end[0].min(self.size[0]) {
+                    let src_x = (x - start[0]) * SCREEN_WIDTH / width;
+                    let pixel = screen[src_y * SCREEN_WIDTH + src_x];
+                    let dst = (y * self.size[0] + x) * 4;
+                    data[dst..dst + 4].copy_from_slice(&pixel.to_le_bytes());
+                }
+            }
+        }
+        data.into_boxed_slice()
+    }
+}
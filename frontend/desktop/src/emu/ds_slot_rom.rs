@@ -1,13 +1,56 @@
+use super::rom_archive;
 use dust_core::{
-    ds_slot::rom::{self, Contents},
+    ds_slot::rom::{self, patch, Contents},
     utils::{mem_prelude::*, zeroed_box},
     Model,
 };
-use std::{any::Any, io, path::Path, sync::Arc};
+use memmap2::Mmap;
+use std::{
+    any::Any,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use sync_file::{RandomAccessFile, ReadAt};
 
+/// Where a streamed ROM's contents are read from.
+enum Source {
+    /// A read-only memory mapping of the whole file, letting the OS page it in on demand and cache
+    /// it without duplicating it in the emulator's memory.
+    Mapped(Mmap),
+    /// Plain reads from the file, for platforms or filesystems that don't support memory mapping.
+    File(RandomAccessFile),
+}
+
+impl Source {
+    fn open(path: &Path) -> io::Result<(Self, u64)> {
+        let file = fs::File::open(path)?;
+        let len = file.metadata()?.len();
+        // NOTE: The mapping is only ever read from; the ROM file being truncated while the game is
+        // running can still fault, but modifying it isn't supported anyway.
+        Ok(match unsafe { Mmap::map(&file) } {
+            Ok(mmap) if mmap.len() as u64 == len => (Source::Mapped(mmap), len),
+            _ => (Source::File(RandomAccessFile::from(file)), len),
+        })
+    }
+
+    fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        match self {
+            Source::Mapped(mmap) => {
+                let src = usize::try_from(offset)
+                    .ok()
+                    .and_then(|start| mmap.get(start..start.checked_add(buf.len())?))
+                    .ok_or(io::ErrorKind::UnexpectedEof)?;
+                buf.copy_from_slice(src);
+                Ok(())
+            }
+            Source::File(file) => file.read_exact_at(buf, offset),
+        }
+    }
+}
+
 pub struct File {
-    file: RandomAccessFile,
+    source: Source,
     len: u64,
     game_code: u32,
     header_bytes: Box<Bytes<0x170>>,
@@ -40,7 +83,7 @@ impl Contents for File {
         self.secure_area
             .get_or_insert_with(|| {
                 let mut buf = zeroed_box::<Bytes<0x800>>();
-                self.file
+                self.source
                     .read_exact_at(&mut **buf, self.secure_area_start as u64)
                     .ok()
                     .map(|_| buf)
@@ -55,7 +98,7 @@ impl Contents for File {
                 self.dldi_area_start = addr;
                 self.dldi_area_end = (addr as u64) + len as u64;
                 let mut buf = BoxedByteSlice::new_zeroed(len);
-                self.file
+                self.source
                     .read_exact_at(&mut buf, self.dldi_area_start as u64)
                     .ok()
                     .map(|_| buf)
@@ -72,7 +115,7 @@ impl Contents for File {
         let read_len = (output.len() as u64).min(self.len.saturating_sub(addr as u64)) as usize;
         output[read_len..].fill(0);
         if read_len > 0 {
-            self.file
+            self.source
                 .read_exact_at(&mut output[..read_len], addr as u64)
                 .expect("couldn't read DS slot ROM data");
         }
@@ -112,6 +155,8 @@ pub enum DsSlotRom {
 pub enum CreationError {
     InvalidFileSize(u64),
     Io(io::Error),
+    Patch(patch::Error),
+    Archive(rom_archive::Error),
 }
 
 impl From<io::Error> for CreationError {
@@ -120,10 +165,40 @@ impl From<io::Error> for CreationError {
     }
 }
 
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CreationError::InvalidFileSize(got) => write!(f, "invalid ROM file size: {got} B"),
+            CreationError::Io(err) => write!(f, "I/O error: {err}"),
+            CreationError::Patch(err) => write!(f, "couldn't apply patch: {err}"),
+            CreationError::Archive(err) => write!(f, "couldn't extract ROM: {err}"),
+        }
+    }
+}
+
+/// Looks for an IPS, UPS or BPS patch next to the given ROM file and with the same name (i.e.
+/// `game.ups` for `game.nds`).
+pub fn find_patch(rom_path: &Path) -> Option<PathBuf> {
+    patch::EXTENSIONS
+        .iter()
+        .map(|extension| rom_path.with_extension(extension))
+        .find(|path| path.is_file())
+}
+
 impl DsSlotRom {
-    pub fn new(path: &Path, in_memory_max_size: u32, model: Model) -> Result<Self, CreationError> {
-        let file = RandomAccessFile::open(path)?;
-        let len = file.metadata()?.len();
+    /// Opens the ROM file at `path`; if `patch_path` is specified, the patch is applied to a copy
+    /// of the ROM in memory, leaving the file untouched.
+    pub fn new(
+        path: &Path,
+        patch_path: Option<&Path>,
+        in_memory_max_size: u32,
+        model: Model,
+    ) -> Result<Self, CreationError> {
+        if patch_path.is_some() {
+            return Self::from_bytes(fs::read(path)?, patch_path, model);
+        }
+
+        let (source, len) = Source::open(path)?;
         if !rom::is_valid_size(len.next_power_of_two(), model) {
             return Err(CreationError::InvalidFileSize(len));
         }
@@ -131,17 +206,17 @@ impl DsSlotRom {
 
         Ok(if read_to_memory {
             let mut bytes = BoxedByteSlice::new_zeroed(len.next_power_of_two() as usize);
-            file.read_exact_at(&mut bytes[..len as usize], 0)?;
+            source.read_exact_at(&mut bytes[..len as usize], 0)?;
             DsSlotRom::Memory(bytes)
         } else {
             let mut header_bytes = zeroed_box::<Bytes<0x170>>();
-            file.read_exact_at(&mut **header_bytes, 0)?;
+            source.read_exact_at(&mut **header_bytes, 0)?;
 
             let game_code = header_bytes.read_le::<u32>(0x0C);
             let secure_area_start = header_bytes.read_le::<u32>(0x20);
 
             DsSlotRom::File(File {
-                file,
+                source,
                 len,
                 game_code,
                 header_bytes,
@@ -154,6 +229,28 @@ impl DsSlotRom {
             })
         })
     }
+
+    /// Creates an in-memory ROM from the given contents (i.e. extracted from an archive), applying
+    /// the patch at `patch_path` to them if specified.
+    pub fn from_bytes(
+        contents: Vec<u8>,
+        patch_path: Option<&Path>,
+        model: Model,
+    ) -> Result<Self, CreationError> {
+        let contents = match patch_path {
+            Some(patch_path) => {
+                patch::apply(&contents, &fs::read(patch_path)?).map_err(CreationError::Patch)?
+            }
+            None => contents,
+        };
+        let len = contents.len() as u64;
+        if !rom::is_valid_size(len.next_power_of_two(), model) {
+            return Err(CreationError::InvalidFileSize(len));
+        }
+        let mut bytes = BoxedByteSlice::new_zeroed(len.next_power_of_two() as usize);
+        bytes[..contents.len()].copy_from_slice(&contents);
+        Ok(DsSlotRom::Memory(bytes))
+    }
 }
 
 macro_rules! forward_to_variants {
@@ -0,0 +1,496 @@
+use super::{
+    capture::{self, Rotation},
+    soft_renderer_3d::LayerCapture,
+};
+use crate::config::FrameDumpFormat;
+use dust_core::gpu::{
+    engine_2d::{Engine2d, Role},
+    Gpu, Scanline, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+use dust_soft_2d::layer_capture::{BgObjPixel, LayerCapture as LayerCapture2d};
+use serde::Serialize;
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    thread::{self, JoinHandle},
+};
+
+// Limits how far emulation can get ahead of the encoder before being throttled
+const MAX_QUEUED_FRAMES: usize = 8;
+
+pub struct Settings {
+    pub dir_path: PathBuf,
+    pub name_template: String,
+    pub format: FrameDumpFormat,
+    pub title: String,
+    /// The layout of the `screens` layer; the 3D layer always contains a single unrotated screen.
+    pub layout: capture::Layout,
+    /// Only available when using the software 3D renderer.
+    pub layer_3d: Option<LayerCapture>,
+    /// Only available when using the software 2D renderers.
+    pub layers_2d: Option<LayerCapture2d>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Png(png::EncodingError),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<png::EncodingError> for Error {
+    fn from(err: png::EncodingError) -> Self {
+        Error::Png(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {err}"),
+            Error::Png(err) => write!(f, "PNG encoding error: {err}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Layer2dKind {
+    /// The topmost BG/OBJ layer at each pixel.
+    Top,
+    /// The layer right below the topmost one, used as the second target for color effects.
+    Bottom,
+    /// The indices of the top and bottom layers (0-3 for BGs, 4 for OBJs, 5 for the backdrop),
+    /// in the red and green channels respectively.
+    Ids,
+}
+
+#[derive(Clone, Copy)]
+enum Layer {
+    Screens,
+    Layer3d,
+    Layer2d {
+        is_engine_b: bool,
+        kind: Layer2dKind,
+    },
+}
+
+impl Layer {
+    const COUNT: usize = 8;
+
+    fn index(self) -> usize {
+        match self {
+            Layer::Screens => 0,
+            Layer::Layer3d => 1,
+            Layer::Layer2d { is_engine_b, kind } => 2 + is_engine_b as usize * 3 + kind as usize,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        const NAMES: [&str; Layer::COUNT] = [
+            "screens",
+            "3d",
+            "2d_a_top",
+            "2d_a_bottom",
+            "2d_a_ids",
+            "2d_b_top",
+            "2d_b_bottom",
+            "2d_b_ids",
+        ];
+        NAMES[self.index()]
+    }
+}
+
+/// The 2D engine state needed to composite the dumped layers, as of the end of the frame.
+#[derive(Clone, Copy, Serialize)]
+struct EngineParams {
+    is_on_lower_screen: bool,
+    display_mode: u8,
+    bg0_3d: bool,
+    color_effect: u8,
+    target_1_mask: u8,
+    target_2_mask: u8,
+    /// The EVA and EVB coefficients used for alpha blending, out of 16.
+    blend_coeffs: (u8, u8),
+    /// The EVY coefficient used for brightness changes, out of 16.
+    brightness_coeff: u8,
+    master_brightness_mode: u8,
+    master_brightness_factor: u32,
+}
+
+impl EngineParams {
+    fn new<R: Role>(engine: &Engine2d<R>) -> Self {
+        let control = engine.control();
+        let color_effects_control = engine.color_effects_control();
+        EngineParams {
+            is_on_lower_screen: engine.is_on_lower_screen(),
+            display_mode: if R::IS_A {
+                control.display_mode_a()
+            } else {
+                control.display_mode_b()
+            },
+            bg0_3d: R::IS_A && control.bg0_3d(),
+            color_effect: color_effects_control.color_effect(),
+            target_1_mask: color_effects_control.target_1_mask(),
+            target_2_mask: color_effects_control.target_2_mask(),
+            blend_coeffs: engine.blend_coeffs(),
+            brightness_coeff: engine.brightness_coeff(),
+            master_brightness_mode: engine.master_brightness_control().mode(),
+            master_brightness_factor: engine.master_brightness_factor(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ManifestLayer {
+    layer: &'static str,
+    /// The path of the file the layer was written to, relative to the dump directory.
+    path: String,
+    /// The index of the frame inside the file, for raw dumps.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct ManifestEntry {
+    frame: u64,
+    layers: Vec<ManifestLayer>,
+    engines: [EngineParams; 2],
+}
+
+struct Frame {
+    number: u64,
+    screens: Box<[u8]>,
+    layer_3d: Option<Box<[Scanline<u32>; SCREEN_HEIGHT]>>,
+    layers_2d: Option<Box<[[Scanline<BgObjPixel>; SCREEN_HEIGHT]; 2]>>,
+    engines: [EngineParams; 2],
+}
+
+struct Writer {
+    dir_path: PathBuf,
+    name_template: String,
+    format: FrameDumpFormat,
+    title: String,
+    layout: capture::Layout,
+    raw_files: [Option<(PathBuf, BufWriter<File>)>; Layer::COUNT],
+    raw_frames_written: u64,
+    /// The manifest file, created next to the first dumped frame, and whether any entries were
+    /// written to it yet.
+    manifest: Option<(BufWriter<File>, bool)>,
+}
+
+impl Writer {
+    fn path(&self, frame_number: u64, layer: Layer) -> PathBuf {
+        let name = self
+            .name_template
+            .replace("{title}", &self.title)
+            .replace("{frame}", &format!("{frame_number:06}"))
+            .replace("{layer}", layer.name());
+        self.dir_path
+            .join(format!("{name}.{}", self.format.extension()))
+    }
+
+    fn create_file(path: &Path) -> io::Result<BufWriter<File>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        File::create(path).map(BufWriter::new)
+    }
+
+    fn relative_path(&self, path: &Path) -> String {
+        path.strip_prefix(&self.dir_path)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn write_layer(
+        &mut self,
+        frame_number: u64,
+        layer: Layer,
+        data: &[u8],
+    ) -> Result<ManifestLayer, Error> {
+        match self.format {
+            FrameDumpFormat::Png => {
+                let path = self.path(frame_number, layer);
+                let file = Self::create_file(&path)?;
+                let ([width, height], rotation) = match layer {
+                    Layer::Screens => (self.layout.size(), self.layout.rotation()),
+                    Layer::Layer3d | Layer::Layer2d { .. } => {
+                        ([SCREEN_WIDTH, SCREEN_HEIGHT], Rotation::None)
+                    }
+                };
+                let mut encoder = png::Encoder::new(file, width as u32, height as u32);
+                encoder.set_color(png::ColorType::Rgba);
+                encoder.set_depth(png::BitDepth::Eight);
+                encoder.set_compression(png::Compression::Fast);
+                encoder.set_srgb(png::SrgbRenderingIntent::Perceptual);
+                encoder.set_pixel_dims(Some(png::PixelDimensions {
+                    xppu: 1,
+                    yppu: 1,
+                    unit: png::Unit::Unspecified,
+                }));
+                let mut writer = encoder.write_header()?;
+                if rotation != Rotation::None {
+                    writer.write_chunk(png::chunk::ChunkType(*b"eXIf"), &rotation.exif_block())?;
+                }
+                writer.write_image_data(data)?;
+                writer.finish()?;
+                Ok(ManifestLayer {
+                    layer: layer.name(),
+                    path: self.relative_path(&path),
+                    index: None,
+                })
+            }
+
+            FrameDumpFormat::Raw => {
+                // All frames are appended to the same file, named after the first one
+                let raw_file = &mut self.raw_files[layer.index()];
+                if raw_file.is_none() {
+                    let path = self.path(frame_number, layer);
+                    *raw_file = Some((path.clone(), Self::create_file(&path)?));
+                }
+                let (path, file) = raw_file.as_mut().unwrap();
+                file.write_all(data)?;
+                let path = path.clone();
+                Ok(ManifestLayer {
+                    layer: layer.name(),
+                    path: self.relative_path(&path),
+                    index: Some(self.raw_frames_written),
+                })
+            }
+        }
+    }
+
+    fn write_manifest_entry(&mut self, entry: &ManifestEntry) -> io::Result<()> {
+        if self.manifest.is_none() {
+            let path = self.path(entry.frame, Layer::Screens);
+            let path = path
+                .parent()
+                .unwrap_or(&self.dir_path)
+                .join("manifest.json");
+            let mut file = Self::create_file(&path)?;
+            write!(file, "{{\"title\":")?;
+            serde_json::to_writer(&mut file, &self.title)?;
+            write!(
+                file,
+                ",\"format\":\"{}\",\"frames\":[",
+                self.format.extension()
+            )?;
+            self.manifest = Some((file, false));
+        }
+        let (file, has_entries) = self.manifest.as_mut().unwrap();
+        if *has_entries {
+            file.write_all(b",")?;
+        }
+        file.write_all(b"\n")?;
+        serde_json::to_writer(&mut *file, entry)?;
+        *has_entries = true;
+        Ok(())
+    }
+
+    fn write_frame(&mut self, frame: Frame) -> Result<(), Error> {
+        let mut layers = vec![self.write_layer(frame.number, Layer::Screens, &frame.screens)?];
+        if let Some(layer_3d) = &frame.layer_3d {
+            layers.push(self.write_layer(
+                frame.number,
+                Layer::Layer3d,
+                &layer_3d_to_rgba8(layer_3d),
+            )?);
+        }
+        if let Some(layers_2d) = &frame.layers_2d {
+            for (is_engine_b, scanlines) in [false, true].into_iter().zip(layers_2d.iter()) {
+                let [top, bottom, ids] = layers_2d_to_rgba8(scanlines);
+                for (kind, data) in [
+                    (Layer2dKind::Top, top),
+                    (Layer2dKind::Bottom, bottom),
+                    (Layer2dKind::Ids, ids),
+                ] {
+                    layers.push(self.write_layer(
+                        frame.number,
+                        Layer::Layer2d { is_engine_b, kind },
+                        &data,
+                    )?);
+                }
+            }
+        }
+        self.raw_frames_written += 1;
+        self.write_manifest_entry(&ManifestEntry {
+            frame: frame.number,
+            layers,
+            engines: frame.engines,
+        })?;
+        Ok(())
+    }
+
+    fn run(mut self, rx: crossbeam_channel::Receiver<Frame>) -> Result<(), Error> {
+        for frame in rx {
+            self.write_frame(frame)?;
+        }
+        for (_, file) in self.raw_files.iter_mut().flatten() {
+            file.flush()?;
+        }
+        if let Some((mut file, _)) = self.manifest.take() {
+            file.write_all(b"\n]}\n")?;
+            file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn rgb6_to_rgb8(rgb6: u32) -> [u8; 3] {
+    [rgb6 & 0x3F, rgb6 >> 6 & 0x3F, rgb6 >> 12 & 0x3F].map(|c| (c << 2 | c >> 4) as u8)
+}
+
+fn layer_3d_to_rgba8(scanlines: &[Scanline<u32>; SCREEN_HEIGHT]) -> Box<[u8]> {
+    let mut data = Vec::with_capacity(SCREEN_WIDTH * SCREEN_HEIGHT * 4);
+    for pixel in scanlines.iter().flat_map(|scanline| &scanline.0) {
+        let [r, g, b] = rgb6_to_rgb8(*pixel);
+        let a5 = (pixel >> 18 & 0x1F) as u8;
+        data.extend_from_slice(&[r, g, b, a5 << 3 | a5 >> 2]);
+    }
+    data.into_boxed_slice()
+}
+
+/// Converts a 2D engine's captured layers to RGBA8 images for the top layer, the bottom layer and
+/// their indices, in that order.
+fn layers_2d_to_rgba8(scanlines: &[Scanline<BgObjPixel>; SCREEN_HEIGHT]) -> [Box<[u8]>; 3] {
+    fn layer_index(color_effects_mask: u8) -> u8 {
+        if color_effects_mask == 0 {
+            0xFF
+        } else {
+            color_effects_mask.trailing_zeros() as u8
+        }
+    }
+
+    let mut data = [(); 3].map(|_| Vec::with_capacity(SCREEN_WIDTH * SCREEN_HEIGHT * 4));
+    for pixel in scanlines.iter().flat_map(|scanline| &scanline.0) {
+        let [r, g, b] = rgb6_to_rgb8(pixel.rgb());
+        data[0].extend_from_slice(&[r, g, b, 0xFF]);
+        let [r, g, b] = rgb6_to_rgb8(pixel.bot_rgb());
+        data[1].extend_from_slice(&[r, g, b, 0xFF]);
+        data[2].extend_from_slice(&[
+            layer_index(pixel.color_effects_mask()),
+            layer_index(pixel.bot_color_effects_mask()),
+            0,
+            0xFF,
+        ]);
+    }
+    data.map(Vec::into_boxed_slice)
+}
+
+fn copy_boxed<T: Copy>(value: &T) -> Box<T> {
+    let mut result = Box::<T>::new_uninit();
+    unsafe {
+        result.as_mut_ptr().copy_from_nonoverlapping(value, 1);
+        result.assume_init()
+    }
+}
+
+/// Writes every emulated frame losslessly to disk, optionally alongside the 3D layer and the 2D
+/// engines' layers as they were before being composited, and a manifest describing how to
+/// composite them.
+///
+/// Layers are only copied on the emulation thread, and converted and encoded on a separate one;
+/// if it falls behind, emulation is throttled instead of dropping frames.
+pub struct FrameDumper {
+    layout: capture::Layout,
+    layer_3d: Option<LayerCapture>,
+    layers_2d: Option<LayerCapture2d>,
+    tx: Option<crossbeam_channel::Sender<Frame>>,
+    thread: Option<JoinHandle<Result<(), Error>>>,
+}
+
+impl FrameDumper {
+    pub fn start(settings: Settings) -> Result<Self, Error> {
+        fs::create_dir_all(&settings.dir_path)?;
+
+        let writer = Writer {
+            dir_path: settings.dir_path,
+            name_template: settings.name_template,
+            format: settings.format,
+            title: settings.title,
+            layout: settings.layout.clone(),
+            raw_files: [(); Layer::COUNT].map(|_| None),
+            raw_frames_written: 0,
+            manifest: None,
+        };
+        let (tx, rx) = crossbeam_channel::bounded(MAX_QUEUED_FRAMES);
+        let thread = thread::Builder::new()
+            .name("frame dump".to_owned())
+            .spawn(move || writer.run(rx))?;
+
+        if let Some(layer_3d) = &settings.layer_3d {
+            layer_3d.set_enabled(true);
+        }
+        if let Some(layers_2d) = &settings.layers_2d {
+            layers_2d.set_enabled(true);
+        }
+
+        Ok(FrameDumper {
+            layout: settings.layout,
+            layer_3d: settings.layer_3d,
+            layers_2d: settings.layers_2d,
+            tx: Some(tx),
+            thread: Some(thread),
+        })
+    }
+
+    /// Whether the writer thread is still running; if it's not, dumping has failed and should be
+    /// finished to retrieve the error.
+    pub fn is_running(&self) -> bool {
+        self.thread
+            .as_ref()
+            .map_or(false, |thread| !thread.is_finished())
+    }
+
+    pub fn push_frame(&mut self, number: u64, gpu: &Gpu) {
+        let screens = self.layout.compose(gpu.renderer_2d().framebuffer());
+        let layer_3d = self
+            .layer_3d
+            .as_ref()
+            .map(|layer_3d| copy_boxed(&**layer_3d.scanlines()));
+        let layers_2d = self
+            .layers_2d
+            .as_ref()
+            .map(|layers_2d| copy_boxed(&**layers_2d.scanlines()));
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(Frame {
+                number,
+                screens,
+                layer_3d,
+                layers_2d,
+                engines: [
+                    EngineParams::new(&gpu.engine_2d_a),
+                    EngineParams::new(&gpu.engine_2d_b),
+                ],
+            });
+        }
+    }
+
+    pub fn finish(mut self) -> Result<(), Error> {
+        self.tx = None;
+        self.thread
+            .take()
+            .unwrap()
+            .join()
+            .expect("couldn't join frame dump thread")
+    }
+}
+
+impl Drop for FrameDumper {
+    fn drop(&mut self) {
+        if let Some(layer_3d) = &self.layer_3d {
+            layer_3d.set_enabled(false);
+        }
+        if let Some(layers_2d) = &self.layers_2d {
+            layers_2d.set_enabled(false);
+        }
+    }
+}
@@ -12,7 +12,7 @@ use dust_core::{
         self, arm7, arm9,
         bus::DebugCpuAccess,
         debug::{
-            BreakpointHook, DataAbortHook, MemWatchpointHook, MemWatchpointRwMask,
+            self, DataAbortHook, MemWatchpointRwMask,
             MemWatchpointTriggerCause as MemWatchpointCause, PrefetchAbortHook, UndefHook,
         },
     },
@@ -59,6 +59,15 @@ enum Core {
     Arm9,
 }
 
+impl From<cpu::Core> for Core {
+    fn from(core: cpu::Core) -> Self {
+        match core {
+            cpu::Core::Arm7 => Core::Arm7,
+            cpu::Core::Arm9 => Core::Arm9,
+        }
+    }
+}
+
 impl Core {
     fn other(&self) -> Core {
         match self {
@@ -141,6 +150,36 @@ enum VStoppedSequenceKind {
     Stopped { i: u8 },
 }
 
+/// Reports breakpoint and memory watchpoint hits on either CPU as stop causes.
+struct EventHandler {
+    stop_causes: Rc<RefCell<StopCauses>>,
+}
+
+impl<E: cpu::Engine> debug::EventHandler<E> for EventHandler {
+    fn breakpoint(&mut self, _emu: &mut Emu<E>, core: cpu::Core, _addr: u32) -> bool {
+        self.stop_causes.borrow_mut().push(StopCause::CoreStopped(
+            CoreStopCause::HwBreakpoint,
+            core.into(),
+        ));
+        true
+    }
+
+    fn mem_watchpoint(
+        &mut self,
+        _emu: &mut Emu<E>,
+        core: cpu::Core,
+        addr: u32,
+        _size: u8,
+        cause: MemWatchpointCause,
+    ) -> bool {
+        self.stop_causes.borrow_mut().push(StopCause::CoreStopped(
+            CoreStopCause::MemWatchpoint(addr, cause),
+            core.into(),
+        ));
+        true
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum EmuControlFlow {
     Continue,
@@ -257,32 +296,11 @@ impl GdbServer {
             }
         );
 
-        set_hook!(
-            self,
-            emu,
-            (set_breakpoint_hook, BreakpointHook<E>),
-            [(arm7, Arm7), (arm9, Arm9)],
-            |core, stop_causes| move |_emu, _addr| {
-                stop_causes
-                    .borrow_mut()
-                    .push(StopCause::CoreStopped(CoreStopCause::HwBreakpoint, core));
-                true
-            }
-        );
-
-        set_hook!(
-            self,
-            emu,
-            (set_mem_watchpoint_hook, MemWatchpointHook<E>),
-            [(arm7, Arm7), (arm9, Arm9)],
-            |core, stop_causes| move |_emu, addr, _size, cause| {
-                stop_causes.borrow_mut().push(StopCause::CoreStopped(
-                    CoreStopCause::MemWatchpoint(addr, cause),
-                    core,
-                ));
-                true
-            }
-        );
+        let event_handler: Rc<RefCell<dyn debug::EventHandler<E>>> =
+            Rc::new(RefCell::new(EventHandler {
+                stop_causes: Rc::clone(&self.stop_causes),
+            }));
+        emu.set_debug_event_handler(Some(event_handler));
     }
 
     pub fn detach<E: cpu::Engine>(&mut self, emu: &mut Emu<E>) {
@@ -301,10 +319,10 @@ impl GdbServer {
             stop_causes.cores = [None; 2];
         }
 
+        emu.set_debug_event_handler(None);
+
         emu.arm7.set_swi_hook(None);
         emu.arm7.set_undef_hook(None);
-        emu.arm7.set_breakpoint_hook(None);
-        emu.arm7.set_mem_watchpoint_hook(None);
         emu.arm7.clear_breakpoints();
         emu.arm7.clear_mem_watchpoints();
         emu.arm7.is_stopped = false;
@@ -313,8 +331,6 @@ impl GdbServer {
         emu.arm9.set_undef_hook(None);
         emu.arm9.set_prefetch_abort_hook(None);
         emu.arm9.set_data_abort_hook(None);
-        emu.arm9.set_breakpoint_hook(None);
-        emu.arm9.set_mem_watchpoint_hook(None);
         emu.arm9.clear_breakpoints();
         emu.arm9.clear_mem_watchpoints();
         emu.arm9.is_stopped = false;
@@ -411,7 +427,7 @@ impl GdbServer {
         &mut self,
         emu: &mut Emu<E>,
         addr: u32,
-        size: u8,
+        len: u32,
     ) {
         let mut mask = MemWatchpointRwMask::empty();
         if READ {
@@ -420,12 +436,13 @@ impl GdbServer {
         if WRITE {
             mask |= MemWatchpointRwMask::WRITE;
         }
+        let range = (addr, addr.saturating_add(len - 1));
         if SET {
-            emu.arm7.add_mem_watchpoint(addr, size, mask);
-            emu.arm9.add_mem_watchpoint(addr, size, mask);
+            emu.arm7.add_mem_watchpoint_range(range, mask);
+            emu.arm9.add_mem_watchpoint_range(range, mask);
         } else {
-            emu.arm7.remove_mem_watchpoint(addr, size, mask);
-            emu.arm9.remove_mem_watchpoint(addr, size, mask);
+            emu.arm7.remove_mem_watchpoint_range(range, mask);
+            emu.arm9.remove_mem_watchpoint_range(range, mask);
         }
     }
 
@@ -1010,10 +1010,8 @@ impl GdbServer {
                 let addr = parse_int!(addr);
                 let kind = parse_int!(kind);
 
-                if kind > u8::MAX as u32 {
-                    return Err(PacketError::InvalidParams.into());
-                }
-                if (2..5).contains(&ty) && (!kind.is_power_of_two() || addr & (kind - 1) != 0) {
+                // Watchpoints can cover any range, which gets split into aligned chunks
+                if (2..5).contains(&ty) && kind == 0 {
                     return Err(PacketError::InvalidParams.into());
                 }
 
@@ -1032,17 +1030,17 @@ impl GdbServer {
                     }
 
                     2 => {
-                        self.toggle_watchpoint::<_, false, true, false>(emu, addr, kind as u8);
+                        self.toggle_watchpoint::<_, false, true, false>(emu, addr, kind);
                         ok!();
                     }
 
                     3 => {
-                        self.toggle_watchpoint::<_, true, false, false>(emu, addr, kind as u8);
+                        self.toggle_watchpoint::<_, true, false, false>(emu, addr, kind);
                         ok!();
                     }
 
                     4 => {
-                        self.toggle_watchpoint::<_, true, true, false>(emu, addr, kind as u8);
+                        self.toggle_watchpoint::<_, true, true, false>(emu, addr, kind);
                         ok!();
                     }
 
@@ -1057,10 +1055,8 @@ impl GdbServer {
                 let addr = parse_int!(addr);
                 let kind = parse_int!(kind);
 
-                if kind > u8::MAX as u32 {
-                    return Err(PacketError::InvalidParams.into());
-                }
-                if (2..5).contains(&ty) && (!kind.is_power_of_two() || addr & (kind - 1) != 0) {
+                // Watchpoints can cover any range, which gets split into aligned chunks
+                if (2..5).contains(&ty) && kind == 0 {
                     return Err(PacketError::InvalidParams.into());
                 }
 
@@ -1079,17 +1075,17 @@ impl GdbServer {
                     }
 
                     2 => {
-                        self.toggle_watchpoint::<_, false, true, true>(emu, addr, kind as u8);
+                        self.toggle_watchpoint::<_, false, true, true>(emu, addr, kind);
                         ok!();
                     }
 
                     3 => {
-                        self.toggle_watchpoint::<_, true, false, true>(emu, addr, kind as u8);
+                        self.toggle_watchpoint::<_, true, false, true>(emu, addr, kind);
                         ok!();
                     }
 
                     4 => {
-                        self.toggle_watchpoint::<_, true, true, true>(emu, addr, kind as u8);
+                        self.toggle_watchpoint::<_, true, true, true>(emu, addr, kind);
                         ok!();
                     }
 
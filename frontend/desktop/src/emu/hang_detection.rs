@@ -0,0 +1,109 @@
+use dust_core::{cpu, emu::Emu};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// How far apart (in bytes) the ARM9's PC can be at the end of two frames for it to be considered
+/// stuck in the same loop.
+const STALLED_PC_RANGE: u32 = 0x20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HangKind {
+    /// The ARM9 is running (i.e. not halted waiting for an IRQ) but its PC hasn't left a small
+    /// range of addresses, while VBlank IRQs go unacknowledged; on its own, a stalled PC is also
+    /// what busy-waiting on a register looks like, so it's only considered alongside them.
+    StalledPc,
+    /// The VBlank IRQ is enabled, but hasn't been acknowledged.
+    UnacknowledgedVBlank,
+}
+
+/// The emulation thread's view of how long the game might have been hung for, in emulated frames;
+/// counting frames instead of wall-clock time avoids reporting slow but progressing emulation
+/// (e.g. in debug builds) as a hang.
+pub struct HangState {
+    stalled_pc_frames: AtomicU32,
+    unacknowledged_vblank_frames: AtomicU32,
+}
+
+impl HangState {
+    pub fn new() -> Self {
+        HangState {
+            stalled_pc_frames: AtomicU32::new(0),
+            unacknowledged_vblank_frames: AtomicU32::new(0),
+        }
+    }
+
+    /// Returns the longest-lasting sign of a hang, along with how many consecutive frames it's
+    /// lasted for; a stalled PC is preferred when it's lasted as long as the unacknowledged VBlank
+    /// IRQs, as it's more specific.
+    pub fn hung_frames(&self) -> Option<(HangKind, u32)> {
+        [
+            (
+                HangKind::UnacknowledgedVBlank,
+                self.unacknowledged_vblank_frames.load(Ordering::Relaxed),
+            ),
+            (
+                HangKind::StalledPc,
+                self.stalled_pc_frames.load(Ordering::Relaxed),
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, frames)| *frames != 0)
+        .max_by_key(|(_, frames)| *frames)
+    }
+}
+
+pub struct HangDetector {
+    last_arm9_pc: u32,
+    stalled_pc_frames: u32,
+    unacknowledged_vblank_frames: u32,
+}
+
+impl HangDetector {
+    pub fn new() -> Self {
+        HangDetector {
+            last_arm9_pc: 0,
+            stalled_pc_frames: 0,
+            unacknowledged_vblank_frames: 0,
+        }
+    }
+
+    pub fn reset(&mut self, state: &HangState) {
+        *self = Self::new();
+        self.publish(state);
+    }
+
+    fn publish(&self, state: &HangState) {
+        state
+            .stalled_pc_frames
+            .store(self.stalled_pc_frames, Ordering::Relaxed);
+        state
+            .unacknowledged_vblank_frames
+            .store(self.unacknowledged_vblank_frames, Ordering::Relaxed);
+    }
+
+    /// Samples the emulator's state at the end of a frame.
+    pub fn frame_finished<E: cpu::Engine>(&mut self, emu: &Emu<E>, state: &HangState) {
+        let irqs = &emu.arm9.irqs;
+
+        // The VBlank IRQ is requested at the start of VBlank, so if it's still pending at the end
+        // of the frame, the game spent all of VBlank without handling it
+        let vblank_unacknowledged = irqs.enabled().vblank() && irqs.requested().vblank();
+        if vblank_unacknowledged {
+            self.unacknowledged_vblank_frames += 1;
+        } else {
+            self.unacknowledged_vblank_frames = 0;
+        }
+
+        let pc = emu.arm9.r15();
+        if vblank_unacknowledged
+            && !irqs.halted()
+            && pc.abs_diff(self.last_arm9_pc) <= STALLED_PC_RANGE
+        {
+            self.stalled_pc_frames += 1;
+        } else {
+            self.stalled_pc_frames = 0;
+        }
+        self.last_arm9_pc = pc;
+
+        self.publish(state);
+    }
+}
@@ -0,0 +1,51 @@
+use dust_core::ds_slot::ir;
+use std::{
+    any::Any,
+    io::{self, ErrorKind},
+    net::{SocketAddr, UdpSocket},
+};
+
+/// An IR link to another emulator instance, exchanging each IR packet as a single UDP datagram.
+pub struct UdpBackend {
+    socket: UdpSocket,
+}
+
+impl UdpBackend {
+    pub fn new(local_addr: SocketAddr, peer_addr: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind(local_addr)?;
+        socket.connect(peer_addr)?;
+        socket.set_nonblocking(true)?;
+        Ok(UdpBackend { socket })
+    }
+}
+
+impl ir::Backend for UdpBackend {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn send(&mut self, data: &[u8]) {
+        // Packets are allowed to get lost, just like with actual IR
+        let _ = self.socket.send(data);
+    }
+
+    fn receive(&mut self, buffer: &mut [u8]) -> usize {
+        loop {
+            match self.socket.recv(buffer) {
+                Ok(len) => return len,
+                // Reported by some platforms for packets previously sent while the peer wasn't
+                // running yet
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset
+                    ) => {}
+                Err(_) => return 0,
+            }
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use super::capture::{self, Rotation};
+use crate::config::{RecordingContainer, RecordingVideoCodec};
+use dust_core::{
+    audio::{Backend as AudioBackend, OutputSample},
+    gpu::Framebuffer,
+};
+use std::{
+    cell::RefCell,
+    fmt,
+    io::{self, Read, Write},
+    net::{Ipv4Addr, TcpListener},
+    path::PathBuf,
+    process::{Child, ChildStderr, ChildStdin, Command, Stdio},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+// The console's native framerate, as a fraction (33.554432 MHz / (6 * 355 * 263) cycles)
+const FRAME_RATE: &str = "33554432/560190";
+const OUTPUT_AUDIO_SAMPLE_RATE: u32 = 48000;
+const AUDIO_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// Limits how far emulation can get ahead of the encoder before being throttled
+const MAX_QUEUED_FRAMES: usize = 8;
+// Custom capture layouts can have odd sizes, which 4:2:0 chroma subsampling doesn't support; the
+// conversion from sRGB is done explicitly to match the color space the output is tagged with
+const VIDEO_FILTERS: &str = concat!(
+    "pad=ceil(iw/2)*2:ceil(ih/2)*2,",
+    "scale=out_color_matrix=bt709:out_range=tv,format=yuv420p,setsar=1",
+);
+
+pub struct Settings {
+    pub ffmpeg_path: Option<PathBuf>,
+    pub output_path: PathBuf,
+    pub container: RecordingContainer,
+    pub video_codec: RecordingVideoCodec,
+    pub video_bitrate_kbps: u32,
+    pub audio_bitrate_kbps: u32,
+    /// Rotations other than [`Rotation::None`] are stored as display matrix metadata, which
+    /// requires FFmpeg 6.1 or later.
+    pub layout: capture::Layout,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    UnsupportedVideoCodec(RecordingContainer, RecordingVideoCodec),
+    Io(io::Error),
+    Ffmpeg(String),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnsupportedVideoCodec(container, video_codec) => write!(
+                f,
+                "{} videos can't be stored in {} files",
+                video_codec.name(),
+                container.name()
+            ),
+            Error::Io(err) => write!(f, "I/O error: {err}"),
+            Error::Ffmpeg(output) => write!(f, "FFmpeg error: {output}"),
+        }
+    }
+}
+
+/// A shared slot that, while a recording is active, receives a copy of every audio sample the
+/// emulator outputs.
+#[derive(Clone, Default)]
+pub struct AudioCapture(Rc<RefCell<Option<crossbeam_channel::Sender<Vec<u8>>>>>);
+
+impl AudioCapture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wraps an audio backend so that the samples it receives are also captured.
+    pub fn wrap(&self, inner: Box<dyn AudioBackend>) -> Box<dyn AudioBackend> {
+        Box::new(CapturingAudioBackend {
+            inner,
+            capture: self.clone(),
+        })
+    }
+}
+
+struct CapturingAudioBackend {
+    inner: Box<dyn AudioBackend>,
+    capture: AudioCapture,
+}
+
+impl AudioBackend for CapturingAudioBackend {
+    fn handle_sample_chunk(&mut self, samples: &mut Vec<[OutputSample; 2]>) {
+        if let Some(tx) = &*self.capture.0.borrow() {
+            let mut bytes = Vec::with_capacity(samples.len() * 4);
+            for sample in samples.iter().flatten() {
+                #[cfg(not(feature = "xq-audio"))]
+                bytes.extend_from_slice(&((*sample as i16 - 0x200) << 6).to_le_bytes());
+                #[cfg(feature = "xq-audio")]
+                bytes.extend_from_slice(&sample.to_le_bytes());
+            }
+            let _ = tx.send(bytes);
+        }
+        self.inner.handle_sample_chunk(samples);
+    }
+}
+
+/// Encodes the emulator's video and audio output to a file using an external FFmpeg process.
+///
+/// Frames are sent raw through FFmpeg's stdin, while audio is sent through a loopback TCP
+/// connection; both are captured from the emulation thread, so the result is in sync with
+/// emulated time regardless of the speed emulation is running at.
+pub struct Recorder {
+    child: Child,
+    layout: capture::Layout,
+    audio_capture: AudioCapture,
+    video_tx: Option<crossbeam_channel::Sender<Box<[u8]>>>,
+    video_thread: Option<JoinHandle<io::Result<()>>>,
+    audio_thread: Option<JoinHandle<io::Result<()>>>,
+    audio_thread_stop: Arc<AtomicBool>,
+    stderr_thread: Option<JoinHandle<String>>,
+}
+
+impl Recorder {
+    pub fn start(
+        settings: Settings,
+        audio_sample_rate: u32,
+        audio_capture: &AudioCapture,
+    ) -> Result<Self, Error> {
+        if !settings.container.supports(settings.video_codec) {
+            return Err(Error::UnsupportedVideoCodec(
+                settings.container,
+                settings.video_codec,
+            ));
+        }
+
+        let audio_listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        audio_listener.set_nonblocking(true)?;
+        let audio_port = audio_listener.local_addr()?.port();
+
+        let [width, height] = settings.layout.size();
+        let mut command = Command::new(
+            settings
+                .ffmpeg_path
+                .as_deref()
+                .unwrap_or_else(|| "ffmpeg".as_ref()),
+        );
+        command
+            .args(["-hide_banner", "-loglevel", "error", "-y"])
+            // Video input
+            .args(["-f", "rawvideo", "-pix_fmt", "rgba", "-video_size"])
+            .arg(format!("{width}x{height}"))
+            .args(["-framerate", FRAME_RATE]);
+        let rotation = settings.layout.rotation();
+        if rotation != Rotation::None {
+            // FFmpeg expects a counterclockwise angle
+            command
+                .arg("-display_rotation")
+                .arg(((360 - rotation.clockwise_degrees()) % 360).to_string());
+        }
+        let mut child = command
+            .args(["-i", "pipe:0"])
+            // Audio input
+            .args([
+                "-f",
+                if cfg!(feature = "xq-audio") {
+                    "f32le"
+                } else {
+                    "s16le"
+                },
+                "-ac",
+                "2",
+                "-ar",
+            ])
+            .arg(audio_sample_rate.to_string())
+            .arg("-i")
+            .arg(format!("tcp://127.0.0.1:{audio_port}"))
+            // Output
+            .args(["-c:v", settings.video_codec.ffmpeg_encoder()])
+            .args(["-vf", VIDEO_FILTERS])
+            .args([
+                "-colorspace",
+                "bt709",
+                "-color_primaries",
+                "bt709",
+                "-color_trc",
+                "iec61966-2-1",
+                "-color_range",
+                "tv",
+            ])
+            .args(["-pix_fmt", "yuv420p", "-b:v"])
+            .arg(format!("{}k", settings.video_bitrate_kbps))
+            .args(["-c:a", settings.container.ffmpeg_audio_encoder(), "-b:a"])
+            .arg(format!("{}k", settings.audio_bitrate_kbps))
+            .arg("-ar")
+            .arg(OUTPUT_AUDIO_SAMPLE_RATE.to_string())
+            .arg(&settings.output_path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let stdin = child.stdin.take().expect("couldn't get FFmpeg stdin");
+        let stderr = child.stderr.take().expect("couldn't get FFmpeg stderr");
+
+        // FFmpeg's error output has to be drained while it's running, as it would otherwise block
+        // once the pipe's buffer is full
+        let stderr_thread = thread::Builder::new()
+            .name("recording FFmpeg output".to_owned())
+            .spawn(move || Self::read_output(stderr))?;
+
+        let (video_tx, video_rx) = crossbeam_channel::bounded(MAX_QUEUED_FRAMES);
+        let video_thread = thread::Builder::new()
+            .name("recording video".to_owned())
+            .spawn(move || Self::write_video(stdin, video_rx))?;
+
+        let (audio_tx, audio_rx) = crossbeam_channel::unbounded();
+        let audio_thread_stop = Arc::new(AtomicBool::new(false));
+        let audio_thread = thread::Builder::new()
+            .name("recording audio".to_owned())
+            .spawn({
+                let stop = Arc::clone(&audio_thread_stop);
+                move || Self::write_audio(audio_listener, audio_rx, &stop)
+            })?;
+
+        *audio_capture.0.borrow_mut() = Some(audio_tx);
+
+        Ok(Recorder {
+            child,
+            layout: settings.layout,
+            audio_capture: audio_capture.clone(),
+            video_tx: Some(video_tx),
+            video_thread: Some(video_thread),
+            audio_thread: Some(audio_thread),
+            audio_thread_stop,
+            stderr_thread: Some(stderr_thread),
+        })
+    }
+
+    fn read_output(mut stderr: ChildStderr) -> String {
+        let mut output = String::new();
+        let _ = stderr.read_to_string(&mut output);
+        output
+    }
+
+    fn write_video(
+        mut stdin: ChildStdin,
+        rx: crossbeam_channel::Receiver<Box<[u8]>>,
+    ) -> io::Result<()> {
+        for frame in rx {
+            stdin.write_all(&frame)?;
+        }
+        Ok(())
+    }
+
+    fn write_audio(
+        listener: TcpListener,
+        rx: crossbeam_channel::Receiver<Vec<u8>>,
+        stop: &AtomicBool,
+    ) -> io::Result<()> {
+        let start_time = Instant::now();
+        let mut stream = loop {
+            match listener.accept() {
+                Ok((stream, _)) => break stream,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    if stop.load(Ordering::Relaxed) || start_time.elapsed() > AUDIO_CONNECT_TIMEOUT
+                    {
+                        return Err(io::ErrorKind::TimedOut.into());
+                    }
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(err) => return Err(err),
+            }
+        };
+        stream.set_nonblocking(false)?;
+        for samples in rx {
+            stream.write_all(&samples)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the FFmpeg process is still running; if it's not, the recording has failed and
+    /// should be finished to retrieve the error.
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    pub fn push_frame(&mut self, fb: &Framebuffer) {
+        if let Some(video_tx) = &self.video_tx {
+            let _ = video_tx.send(self.layout.compose(fb));
+        }
+    }
+
+    pub fn finish(mut self) -> Result<(), Error> {
+        *self.audio_capture.0.borrow_mut() = None;
+        self.video_tx = None;
+
+        let video_result = self
+            .video_thread
+            .take()
+            .unwrap()
+            .join()
+            .expect("couldn't join recording video thread");
+        // If FFmpeg never opened the audio input, the audio thread would otherwise keep waiting
+        // for it to connect until the timeout
+        if video_result.is_err() {
+            self.audio_thread_stop.store(true, Ordering::Relaxed);
+        }
+        let audio_result = self
+            .audio_thread
+            .take()
+            .unwrap()
+            .join()
+            .expect("couldn't join recording audio thread");
+
+        let status = self.child.wait()?;
+        let output = self
+            .stderr_thread
+            .take()
+            .unwrap()
+            .join()
+            .expect("couldn't join recording FFmpeg output thread");
+        if !status.success() {
+            return Err(Error::Ffmpeg(if output.is_empty() {
+                status.to_string()
+            } else {
+                output.trim_end().to_owned()
+            }));
+        }
+
+        video_result?;
+        audio_result?;
+        Ok(())
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        *self.audio_capture.0.borrow_mut() = None;
+        self.audio_thread_stop.store(true, Ordering::Relaxed);
+    }
+}
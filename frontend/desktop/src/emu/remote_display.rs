@@ -0,0 +1,388 @@
+//! A TCP server streaming the emulated screens to a remote device (i.e. a phone or tablet), and
+//! receiving touch and button input back from it, so that it can act as a handheld-like bottom
+//! screen.
+//!
+//! Only one client is served at a time; all integers are little-endian. After a client connects,
+//! the server sends a header made up of:
+//! - the magic `DUSTRD` followed by the protocol version (1), as a `u8`;
+//! - the encoding used for frames, as a `u8`: 0 for raw RGB565, 1 for MJPEG;
+//! - a `u8` mask of the streamed screens: bit 0 for the top screen, bit 1 for the bottom one;
+//! - the width and height of each screen, as `u16`s.
+//!
+//! Then, for every emulated frame, each streamed screen's image is sent (top screen first) as a
+//! `u32` byte length followed by either RGB565 pixels in row-major order or a JPEG image. Frames
+//! are dropped rather than queued if the client can't keep up, to keep latency low.
+//!
+//! The client can send 5-byte input packets back, starting with a command byte:
+//! - 0, followed by a `u32` with the state of all keys, using the same bit layout as
+//!   [`dust_core::emu::input::Keys`];
+//! - 1, followed by the `u16` X and Y coordinates of a touch on the bottom screen, in pixels;
+//! - 2, followed by 4 ignored bytes, to end the current touch.
+
+use crate::config::{RemoteDisplayEncoding, RemoteDisplayScreens};
+use dust_core::{
+    emu::input::{Change, Keys},
+    gpu::{Framebuffer, SCREEN_HEIGHT, SCREEN_WIDTH},
+};
+use std::{
+    fmt,
+    io::{self, ErrorKind, Read, Write},
+    mem,
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TryRecvError, TrySendError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+const PROTOCOL_VERSION: u8 = 1;
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+const INPUT_PACKET_LEN: usize = 5;
+
+pub struct Settings {
+    pub encoding: RemoteDisplayEncoding,
+    pub jpeg_quality: u8,
+    pub screens: RemoteDisplayScreens,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Jpeg(jpeg_encoder::EncodingError),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<jpeg_encoder::EncodingError> for Error {
+    fn from(err: jpeg_encoder::EncodingError) -> Self {
+        Error::Jpeg(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {err}"),
+            Error::Jpeg(err) => write!(f, "JPEG encoding error: {err}"),
+        }
+    }
+}
+
+fn screen_mask(screens: RemoteDisplayScreens) -> u8 {
+    match screens {
+        RemoteDisplayScreens::Both => 3,
+        RemoteDisplayScreens::Top => 1,
+        RemoteDisplayScreens::Bottom => 2,
+    }
+}
+
+fn encode_rgb565(pixels: &[u32], output: &mut Vec<u8>) {
+    output.reserve(pixels.len() * 2);
+    for &pixel in pixels {
+        let [r, g, b, _] = pixel.to_le_bytes();
+        let color = (r as u16 >> 3) << 11 | (g as u16 >> 2) << 5 | b as u16 >> 3;
+        output.extend_from_slice(&color.to_le_bytes());
+    }
+}
+
+fn encode_jpeg(pixels: &[u32], quality: u8, output: &mut Vec<u8>) -> Result<(), Error> {
+    let rgba =
+        unsafe { std::slice::from_raw_parts(pixels.as_ptr() as *const u8, pixels.len() * 4) };
+    jpeg_encoder::Encoder::new(output, quality.clamp(1, 100)).encode(
+        rgba,
+        SCREEN_WIDTH as u16,
+        SCREEN_HEIGHT as u16,
+        jpeg_encoder::ColorType::Rgba,
+    )?;
+    Ok(())
+}
+
+/// Converts a touch position in bottom screen pixels to touchscreen coordinates, which have 4
+/// fractional bits per pixel.
+fn touch_pos(x: u16, y: u16) -> [u16; 2] {
+    [
+        x.min(SCREEN_WIDTH as u16 - 1) << 4,
+        y.min(SCREEN_HEIGHT as u16 - 1) << 4,
+    ]
+}
+
+struct Client {
+    stream: TcpStream,
+    input_buffer: [u8; INPUT_PACKET_LEN],
+    input_len: usize,
+    keys: Keys,
+    touching: bool,
+}
+
+impl Client {
+    fn new(stream: TcpStream, settings: &Settings) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(b"DUSTRD");
+        header.push(PROTOCOL_VERSION);
+        header.push(match settings.encoding {
+            RemoteDisplayEncoding::Rgb565 => 0,
+            RemoteDisplayEncoding::Mjpeg => 1,
+        });
+        header.push(screen_mask(settings.screens));
+        header.extend_from_slice(&(SCREEN_WIDTH as u16).to_le_bytes());
+        header.extend_from_slice(&(SCREEN_HEIGHT as u16).to_le_bytes());
+        (&stream).write_all(&header)?;
+
+        // Input is polled in between frames, so reads shouldn't block
+        stream.set_nonblocking(true)?;
+
+        Ok(Client {
+            stream,
+            input_buffer: [0; INPUT_PACKET_LEN],
+            input_len: 0,
+            keys: Keys::empty(),
+            touching: false,
+        })
+    }
+
+    fn send_frame(&mut self, fb: &Framebuffer, settings: &Settings) -> Result<(), Error> {
+        let mask = screen_mask(settings.screens);
+        let mut data = Vec::new();
+        for (i, screen) in fb.iter().enumerate() {
+            if mask & 1 << i == 0 {
+                continue;
+            }
+            let len_pos = data.len();
+            data.extend_from_slice(&[0; 4]);
+            match settings.encoding {
+                RemoteDisplayEncoding::Rgb565 => encode_rgb565(screen, &mut data),
+                RemoteDisplayEncoding::Mjpeg => {
+                    encode_jpeg(screen, settings.jpeg_quality, &mut data)?
+                }
+            }
+            let len = (data.len() - len_pos - 4) as u32;
+            data[len_pos..len_pos + 4].copy_from_slice(&len.to_le_bytes());
+        }
+
+        // Writes need to block (with a timeout) to send whole frames
+        self.stream.set_nonblocking(false)?;
+        let result = (&self.stream).write_all(&data);
+        self.stream.set_nonblocking(true)?;
+        Ok(result?)
+    }
+
+    fn recv_input(&mut self, input_tx: &mpsc::Sender<Change>) -> io::Result<()> {
+        loop {
+            match (&self.stream).read(&mut self.input_buffer[self.input_len..]) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(len) => self.input_len += len,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+            if self.input_len < INPUT_PACKET_LEN {
+                continue;
+            }
+            self.input_len = 0;
+
+            let [command, args @ ..] = self.input_buffer;
+            let change = match command {
+                0 => {
+                    let keys = Keys::from_bits_truncate(u32::from_le_bytes(args));
+                    let pressed = keys - self.keys;
+                    let released = self.keys - keys;
+                    self.keys = keys;
+                    if !pressed.is_empty() {
+                        let _ = input_tx.send(Change::PressKeys(pressed));
+                    }
+                    if released.is_empty() {
+                        continue;
+                    }
+                    Change::ReleaseKeys(released)
+                }
+                1 => {
+                    self.touching = true;
+                    Change::SetTouchPos(touch_pos(
+                        u16::from_le_bytes([args[0], args[1]]),
+                        u16::from_le_bytes([args[2], args[3]]),
+                    ))
+                }
+                2 => {
+                    self.touching = false;
+                    Change::EndTouch
+                }
+                _ => return Err(io::Error::new(ErrorKind::InvalidData, "unknown command")),
+            };
+            let _ = input_tx.send(change);
+        }
+    }
+
+    /// Releases any input still held by the client, so it doesn't stay stuck after disconnecting.
+    fn release_input(&self, input_tx: &mpsc::Sender<Change>) {
+        if !self.keys.is_empty() {
+            let _ = input_tx.send(Change::ReleaseKeys(self.keys));
+        }
+        if self.touching {
+            let _ = input_tx.send(Change::EndTouch);
+        }
+    }
+}
+
+fn run_server(
+    listener: TcpListener,
+    settings: Settings,
+    frame_rx: Receiver<Box<Framebuffer>>,
+    free_tx: SyncSender<Box<Framebuffer>>,
+    input_tx: mpsc::Sender<Change>,
+    client_connected: Arc<AtomicBool>,
+) {
+    let mut client: Option<Client> = None;
+
+    macro_rules! disconnect {
+        ($client: expr) => {
+            $client.release_input(&input_tx);
+            client = None;
+            client_connected.store(false, Ordering::Relaxed);
+        };
+    }
+
+    loop {
+        if client.is_none() {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    if let Ok(new_client) = Client::new(stream, &settings) {
+                        client = Some(new_client);
+                        client_connected.store(true, Ordering::Relaxed);
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+                Err(_) => thread::sleep(POLL_INTERVAL),
+            }
+        }
+
+        if let Some(client_) = &mut client {
+            if client_.recv_input(&input_tx).is_err() {
+                disconnect!(client_);
+            }
+        }
+
+        let fb = match frame_rx.recv_timeout(POLL_INTERVAL) {
+            Ok(fb) => fb,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        if let Some(client_) = &mut client {
+            if client_.send_frame(&fb, &settings).is_err() {
+                disconnect!(client_);
+            }
+        }
+        let _ = free_tx.try_send(fb);
+    }
+
+    if let Some(client_) = &client {
+        client_.release_input(&input_tx);
+    }
+}
+
+pub struct RemoteDisplayServer {
+    frame_tx: Option<SyncSender<Box<Framebuffer>>>,
+    free_rx: Receiver<Box<Framebuffer>>,
+    spare_fb: Option<Box<Framebuffer>>,
+    input_rx: Receiver<Change>,
+    client_connected: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RemoteDisplayServer {
+    pub fn new(addr: impl ToSocketAddrs, settings: Settings) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+
+        // Only a single frame is buffered, so that the client always receives the latest one
+        let (frame_tx, frame_rx) = mpsc::sync_channel(1);
+        let (free_tx, free_rx) = mpsc::sync_channel(2);
+        let (input_tx, input_rx) = mpsc::channel();
+        let client_connected = Arc::new(AtomicBool::new(false));
+
+        let thread = thread::Builder::new()
+            .name("remote display".to_owned())
+            .spawn({
+                let client_connected = Arc::clone(&client_connected);
+                move || {
+                    run_server(
+                        listener,
+                        settings,
+                        frame_rx,
+                        free_tx,
+                        input_tx,
+                        client_connected,
+                    )
+                }
+            })?;
+
+        Ok(RemoteDisplayServer {
+            frame_tx: Some(frame_tx),
+            free_rx,
+            spare_fb: None,
+            input_rx,
+            client_connected,
+            thread: Some(thread),
+        })
+    }
+
+    /// Sends the given frame to the connected client, if any; the frame is dropped if the previous
+    /// one is still being sent.
+    pub fn push_frame(&mut self, fb: &Framebuffer) {
+        if !self.client_connected.load(Ordering::Relaxed) {
+            return;
+        }
+        let Some(frame_tx) = &self.frame_tx else {
+            return;
+        };
+        let mut buffer = self
+            .spare_fb
+            .take()
+            .or_else(|| self.free_rx.try_recv().ok())
+            .unwrap_or_else(|| unsafe { Box::new_zeroed().assume_init() });
+        buffer.copy_from_slice(fb);
+        if let Err(TrySendError::Full(buffer) | TrySendError::Disconnected(buffer)) =
+            frame_tx.try_send(buffer)
+        {
+            self.spare_fb = Some(buffer);
+        }
+    }
+
+    /// Returns the next input change received from the client, if any.
+    pub fn poll_input(&mut self) -> Option<Change> {
+        match self.input_rx.try_recv() {
+            Ok(change) => Some(change),
+            Err(TryRecvError::Empty | TryRecvError::Disconnected) => None,
+        }
+    }
+
+    fn stop_thread(&mut self) {
+        self.frame_tx = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+
+    /// Stops the server, returning any input changes the client didn't get to release yet.
+    pub fn finish(mut self) -> impl Iterator<Item = Change> {
+        self.stop_thread();
+        let input_rx = mem::replace(&mut self.input_rx, mpsc::channel().1);
+        input_rx.into_iter()
+    }
+}
+
+impl Drop for RemoteDisplayServer {
+    fn drop(&mut self) {
+        self.stop_thread();
+    }
+}
@@ -0,0 +1,128 @@
+use sevenz_rust::{Password, SevenZReader};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+use zip::{result::ZipError, ZipArchive};
+
+/// The file extensions of the supported archive formats.
+pub const EXTENSIONS: [&str; 2] = ["zip", "7z"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Zip,
+    SevenZ,
+}
+
+impl Kind {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        if extension.eq_ignore_ascii_case("zip") {
+            Some(Kind::Zip)
+        } else if extension.eq_ignore_ascii_case("7z") {
+            Some(Kind::SevenZ)
+        } else {
+            None
+        }
+    }
+}
+
+pub enum Error {
+    Io(io::Error),
+    Zip(ZipError),
+    SevenZ(sevenz_rust::Error),
+    NotFound,
+}
+
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        Error::Io(value)
+    }
+}
+
+impl From<ZipError> for Error {
+    fn from(value: ZipError) -> Self {
+        Error::Zip(value)
+    }
+}
+
+impl From<sevenz_rust::Error> for Error {
+    fn from(value: sevenz_rust::Error) -> Self {
+        Error::SevenZ(value)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {err}"),
+            Error::Zip(err) => write!(f, "invalid ZIP archive: {err}"),
+            Error::SevenZ(err) => write!(f, "invalid 7z archive: {err}"),
+            Error::NotFound => f.write_str("file not found in archive"),
+        }
+    }
+}
+
+fn is_rom_name(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("nds"))
+}
+
+/// Returns the names of all DS ROMs (`.nds` files) inside the archive at `path`, in the order they
+/// appear in.
+pub fn list_roms(path: &Path, kind: Kind) -> Result<Vec<String>, Error> {
+    Ok(match kind {
+        Kind::Zip => {
+            let archive = ZipArchive::new(File::open(path)?)?;
+            archive
+                .file_names()
+                .filter(|name| is_rom_name(name))
+                .map(str::to_owned)
+                .collect()
+        }
+        Kind::SevenZ => {
+            let reader = SevenZReader::open(path, Password::empty())?;
+            reader
+                .archive()
+                .files
+                .iter()
+                .filter(|entry| !entry.is_directory() && is_rom_name(entry.name()))
+                .map(|entry| entry.name().to_owned())
+                .collect()
+        }
+    })
+}
+
+/// Extracts the file called `name` from the archive at `path` into memory.
+pub fn read_rom(path: &Path, kind: Kind, name: &str) -> Result<Vec<u8>, Error> {
+    match kind {
+        Kind::Zip => {
+            let mut archive = ZipArchive::new(File::open(path)?)?;
+            let mut file = archive.by_name(name).map_err(|err| match err {
+                ZipError::FileNotFound => Error::NotFound,
+                err => Error::Zip(err),
+            })?;
+            let mut bytes = Vec::with_capacity(file.size() as usize);
+            file.read_to_end(&mut bytes)?;
+            Ok(bytes)
+        }
+        Kind::SevenZ => {
+            let mut reader = SevenZReader::open(path, Password::empty())?;
+            let mut bytes = None;
+            reader.for_each_entries(|entry, entry_reader| {
+                if entry.name() != name {
+                    return Ok(true);
+                }
+                let mut entry_bytes = Vec::with_capacity(entry.size() as usize);
+                entry_reader.read_to_end(&mut entry_bytes)?;
+                bytes = Some(entry_bytes);
+                Ok(false)
+            })?;
+            bytes.ok_or(Error::NotFound)
+        }
+    }
+}
@@ -2,14 +2,18 @@ use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, Tim
 use core::any::Any;
 use dust_core::rtc::{self, Date, Time};
 
+/// An RTC backend following the host's local time with a configurable offset, which can also be
+/// frozen at a fixed date and time (i.e. to make TAS runs deterministic).
 pub struct Backend {
     time_offset: Duration,
+    frozen_date_time: Option<NaiveDateTime>,
 }
 
 impl Backend {
-    pub fn new(time_offset_secondss: i64) -> Self {
+    pub fn new(time_offset_secondss: i64, frozen_date_time: Option<NaiveDateTime>) -> Self {
         Backend {
             time_offset: Duration::try_seconds(time_offset_secondss).unwrap(),
+            frozen_date_time,
         }
     }
 
@@ -20,6 +24,19 @@ impl Backend {
     pub fn set_time_offset_seconds(&mut self, value: i64) {
         self.time_offset = Duration::try_seconds(value).unwrap();
     }
+
+    pub fn frozen_date_time(&self) -> Option<NaiveDateTime> {
+        self.frozen_date_time
+    }
+
+    pub fn set_frozen_date_time(&mut self, value: Option<NaiveDateTime>) {
+        self.frozen_date_time = value;
+    }
+
+    fn date_time(&self) -> NaiveDateTime {
+        self.frozen_date_time
+            .unwrap_or_else(|| Local::now().naive_local() + self.time_offset)
+    }
 }
 
 impl rtc::Backend for Backend {
@@ -32,7 +49,7 @@ impl rtc::Backend for Backend {
     }
 
     fn get_time(&mut self) -> Time {
-        let date_time = Local::now() + self.time_offset;
+        let date_time = self.date_time();
         Time {
             hour: date_time.hour() as u8,
             minute: date_time.minute() as u8,
@@ -41,7 +58,7 @@ impl rtc::Backend for Backend {
     }
 
     fn get_date_time(&mut self) -> (Date, Time) {
-        let date_time = Local::now() + self.time_offset;
+        let date_time = self.date_time();
         (
             Date {
                 years_since_2000: (date_time.year() - 2000) as u8,
@@ -72,6 +89,12 @@ impl rtc::Backend for Backend {
                 Some(time) => time,
                 None => return,
             };
-        self.time_offset = NaiveDateTime::new(date, time) - Local::now().naive_local();
+        let date_time = NaiveDateTime::new(date, time);
+        // A frozen clock stays frozen, just at the time the game set
+        if let Some(frozen_date_time) = &mut self.frozen_date_time {
+            *frozen_date_time = date_time;
+        } else {
+            self.time_offset = date_time - Local::now().naive_local();
+        }
     }
 }
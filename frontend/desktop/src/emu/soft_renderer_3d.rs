@@ -1,16 +1,18 @@
 use dust_core::{
     gpu::{
-        engine_3d::{
-            Polygon, RendererTx, RenderingState as CoreRenderingState, ScreenVertex, SoftRendererRx,
-        },
+        engine_3d::{GxSnapshot, RendererTx, RenderingState as CoreRenderingState, SoftRendererRx},
         Scanline, SCREEN_HEIGHT,
     },
+    profiling::TimeCounter,
     utils::mem_prelude::*,
 };
-use dust_soft_3d::{Renderer, RenderingData};
+use dust_soft_3d::{threaded::Renderer, RenderingData};
+#[cfg(feature = "frame-dump")]
+use parking_lot::{Mutex, MutexGuard};
 use std::{
     cell::UnsafeCell,
     hint,
+    num::NonZeroUsize,
     sync::{
         atomic::{AtomicBool, AtomicU8, Ordering},
         Arc,
@@ -23,16 +25,57 @@ struct SharedData {
     scanline_buffer: Box<UnsafeCell<[Scanline<u32>; SCREEN_HEIGHT]>>,
     processing_scanline: AtomicU8,
     stopped: AtomicBool,
+    time_counter: TimeCounter,
 }
 
 unsafe impl Sync for SharedData {}
 
+#[cfg(feature = "frame-dump")]
+struct LayerCaptureData {
+    enabled: AtomicBool,
+    scanlines: Mutex<Box<[Scanline<u32>; SCREEN_HEIGHT]>>,
+}
+
+/// A copy of the 3D layer as read by the 2D renderer (before compositing), updated while enabled.
+#[cfg(feature = "frame-dump")]
+#[derive(Clone)]
+pub struct LayerCapture(Arc<LayerCaptureData>);
+
+#[cfg(feature = "frame-dump")]
+impl LayerCapture {
+    fn new() -> Self {
+        LayerCapture(Arc::new(LayerCaptureData {
+            enabled: AtomicBool::new(false),
+            scanlines: Mutex::new(unsafe { Box::new_zeroed().assume_init() }),
+        }))
+    }
+
+    pub fn set_enabled(&self, value: bool) {
+        self.0.enabled.store(value, Ordering::Relaxed);
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.0.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Returns the last captured frame; each pixel is stored as packed RGB6 and 5-bit alpha, in
+    /// the format returned by [`SoftRendererRx::read_scanline`].
+    pub fn scanlines(&self) -> MutexGuard<'_, Box<[Scanline<u32>; SCREEN_HEIGHT]>> {
+        self.0.scanlines.lock()
+    }
+}
+
 pub struct Tx {
     shared_data: Arc<SharedData>,
     thread: Option<thread::JoinHandle<()>>,
 }
 
 impl Tx {
+    /// Returns a counter measuring the time the rendering thread spends rendering frames.
+    pub fn time_counter(&self) -> TimeCounter {
+        self.shared_data.time_counter.clone()
+    }
+
     fn wait_for_frame_end(&self) {
         while {
             let processing_scanline = self.shared_data.processing_scanline.load(Ordering::Acquire);
@@ -46,14 +89,9 @@ impl Tx {
 impl RendererTx for Tx {
     fn set_capture_enabled(&mut self, _capture_enabled: bool) {}
 
-    fn swap_buffers(
-        &mut self,
-        vert_ram: &[ScreenVertex],
-        poly_ram: &[Polygon],
-        state: &CoreRenderingState,
-    ) {
+    fn swap_buffers(&mut self, gx: &Arc<GxSnapshot>, state: &CoreRenderingState) {
         self.wait_for_frame_end();
-        unsafe { &mut *self.shared_data.rendering_data.get() }.prepare(vert_ram, poly_ram, state);
+        unsafe { &mut *self.shared_data.rendering_data.get() }.prepare(gx, state);
     }
 
     fn repeat_last_frame(&mut self, state: &CoreRenderingState) {
@@ -95,9 +133,16 @@ impl Drop for Tx {
 pub struct Rx {
     next_scanline: u8,
     shared_data: Arc<SharedData>,
+    #[cfg(feature = "frame-dump")]
+    layer_capture: LayerCapture,
 }
 
 impl Rx {
+    #[cfg(feature = "frame-dump")]
+    pub fn layer_capture(&self) -> LayerCapture {
+        self.layer_capture.clone()
+    }
+
     fn wait_for_line(&self, line: u8) {
         while {
             let processing_scanline = self.shared_data.processing_scanline.load(Ordering::Acquire);
@@ -117,27 +162,38 @@ impl SoftRendererRx for Rx {
         self.wait_for_line(self.next_scanline);
         let result =
             unsafe { &(&*self.shared_data.scanline_buffer.get())[self.next_scanline as usize] };
+        #[cfg(feature = "frame-dump")]
+        if self.layer_capture.is_enabled() {
+            self.layer_capture.scanlines()[self.next_scanline as usize] = *result;
+        }
         self.next_scanline += 1;
         result
     }
 
     fn skip_scanline(&mut self) {
+        #[cfg(feature = "frame-dump")]
+        if self.layer_capture.is_enabled() {
+            self.layer_capture.scanlines()[self.next_scanline as usize] = Scanline([0; 256]);
+        }
         self.next_scanline += 1;
     }
 }
 
-pub fn init() -> (Tx, Rx) {
+pub fn init(threads: NonZeroUsize) -> (Tx, Rx) {
     let shared_data = Arc::new(unsafe {
         SharedData {
             rendering_data: Box::new_zeroed().assume_init(),
             scanline_buffer: Box::new_zeroed().assume_init(),
             processing_scanline: AtomicU8::new(SCREEN_HEIGHT as u8),
             stopped: AtomicBool::new(false),
+            time_counter: TimeCounter::new(),
         }
     });
     let rx = Rx {
         next_scanline: 0,
         shared_data: Arc::clone(&shared_data),
+        #[cfg(feature = "frame-dump")]
+        layer_capture: LayerCapture::new(),
     };
     (
         Tx {
@@ -146,7 +202,7 @@ pub fn init() -> (Tx, Rx) {
                 thread::Builder::new()
                     .name("3D rendering".to_owned())
                     .spawn(move || {
-                        let mut raw_renderer = Renderer::new();
+                        let mut raw_renderer = Renderer::new(threads);
                         loop {
                             if shared_data.stopped.load(Ordering::Relaxed) {
                                 return;
@@ -157,23 +213,20 @@ pub fn init() -> (Tx, Rx) {
                                 .is_ok()
                             {
                                 let rendering_data = unsafe { &*shared_data.rendering_data.get() };
-                                raw_renderer.start_frame(rendering_data);
-                                raw_renderer.render_line(0, rendering_data);
-                                for y in 0..192 {
-                                    let scanline =
-                                        &mut unsafe { &mut *shared_data.scanline_buffer.get() }
-                                            [y as usize];
-                                    if y < 191 {
-                                        raw_renderer.render_line(y + 1, rendering_data);
+                                let start = shared_data.time_counter.start();
+                                raw_renderer.render_frame(rendering_data, |y, scanline| {
+                                    unsafe {
+                                        (&mut *shared_data.scanline_buffer.get())[y as usize] =
+                                            *scanline;
                                     }
-                                    raw_renderer.postprocess_line(y, scanline, rendering_data);
                                     let _ = shared_data.processing_scanline.compare_exchange(
                                         y,
                                         y + 1,
                                         Ordering::Release,
                                         Ordering::Relaxed,
                                     );
-                                }
+                                });
+                                shared_data.time_counter.stop(start);
                             } else {
                                 thread::park();
                             }
@@ -0,0 +1,209 @@
+//! A TCP server letting an external controller drive emulated time, for co-simulation with
+//! external device models and reproducible integration tests.
+//!
+//! While the server is active, emulation only advances inside time slices granted by the
+//! connected controller (only one at a time is accepted), and stays frozen otherwise. The
+//! protocol is line-based text; all times are in ARM7 cycles (~33.51 MHz) since power-on:
+//! - `grant <cycles>`: extends the current time limit by the given amount, replying with
+//!   `ok <limit>`;
+//! - `until <time>`: sets the time limit to the given absolute time, if it's later than the current
+//!   one, replying with `ok <limit>`;
+//! - `time`: replies with `time <cur_time> <limit>`.
+//!
+//! Once emulation reaches the time limit, `done <cur_time>` is sent; if the system gets reset, the
+//! time limit is reset to 0 and `reset` is sent instead. Malformed commands are answered with
+//! `error <description>`.
+
+use dust_core::{cpu, emu::Timestamp, Emu};
+use std::{
+    io::{self, BufRead, BufReader, ErrorKind, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    thread,
+    time::{Duration, Instant},
+};
+
+struct Client {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    line: String,
+}
+
+impl Client {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Client {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            line: String::new(),
+        })
+    }
+
+    /// Returns the next full line sent by the controller, without its terminator, or `None` if
+    /// it hasn't been received yet.
+    fn try_recv_line(&mut self) -> io::Result<Option<&str>> {
+        // Any partial line received during the previous call is still in `self.line`
+        if self.line.ends_with('\n') {
+            self.line.clear();
+        }
+        match self.reader.read_line(&mut self.line) {
+            Ok(0) => Err(ErrorKind::UnexpectedEof.into()),
+            Ok(_) if self.line.ends_with('\n') => Ok(Some(self.line.trim_end())),
+            Ok(_) => Ok(None),
+            Err(err) if err.kind() == ErrorKind::WouldBlock => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn send(&mut self, message: &str) -> io::Result<()> {
+        let mut buffer = message.as_bytes();
+        loop {
+            match self.writer.write(buffer) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(written) => {
+                    buffer = &buffer[written..];
+                    if buffer.is_empty() {
+                        return Ok(());
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => thread::yield_now(),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+pub struct VirtualTimeServer {
+    listener: TcpListener,
+    client: Option<Client>,
+    awaiting_time_limit: bool,
+}
+
+impl VirtualTimeServer {
+    pub fn new(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(VirtualTimeServer {
+            listener,
+            client: None,
+            awaiting_time_limit: false,
+        })
+    }
+
+    pub fn attach<E: cpu::Engine>(&mut self, emu: &mut Emu<E>) {
+        emu.schedule.time_limit = emu.schedule.cur_time();
+    }
+
+    pub fn detach<E: cpu::Engine>(&mut self, emu: &mut Emu<E>) {
+        emu.schedule.time_limit = Timestamp(u64::MAX);
+        self.client = None;
+        self.awaiting_time_limit = false;
+    }
+
+    pub fn has_time_left<E: cpu::Engine>(&self, emu: &Emu<E>) -> bool {
+        emu.schedule.cur_time() < emu.schedule.time_limit
+    }
+
+    fn send(&mut self, message: &str) {
+        if let Some(client) = &mut self.client {
+            if let Err(err) = client.send(message) {
+                self.disconnect(err);
+            }
+        }
+    }
+
+    fn disconnect(&mut self, err: io::Error) {
+        if err.kind() != ErrorKind::UnexpectedEof {
+            error!(
+                "Virtual time server error",
+                "Disconnecting virtual time controller due to error: {err}"
+            );
+        }
+        self.client = None;
+        self.awaiting_time_limit = false;
+    }
+
+    fn handle_command<E: cpu::Engine>(&mut self, command: &str, emu: &mut Emu<E>) -> String {
+        let mut args = command.split_ascii_whitespace();
+        let Some(name) = args.next() else {
+            return "error empty command\n".to_string();
+        };
+        let arg = args.next().map(str::parse::<u64>);
+        if args.next().is_some() {
+            return format!("error too many arguments for {name}\n");
+        }
+        let schedule = &mut emu.schedule;
+        let new_limit = match (name, arg) {
+            ("time", None) => {
+                return format!("time {} {}\n", schedule.cur_time().0, schedule.time_limit.0);
+            }
+            ("grant", Some(Ok(cycles))) => schedule.time_limit.0.saturating_add(cycles),
+            ("until", Some(Ok(time))) => time.max(schedule.time_limit.0),
+            ("grant" | "until", Some(Err(err))) => return format!("error {err}\n"),
+            ("grant" | "until" | "time", _) => {
+                return format!("error wrong number of arguments for {name}\n");
+            }
+            _ => return format!("error unknown command {name}\n"),
+        };
+        schedule.time_limit = Timestamp(new_limit);
+        self.awaiting_time_limit = schedule.cur_time() < schedule.time_limit;
+        format!("ok {new_limit}\n")
+    }
+
+    /// Accepts incoming connections and processes any commands sent by the controller.
+    pub fn poll<E: cpu::Engine>(&mut self, emu: &mut Emu<E>) {
+        if self.client.is_none() {
+            match self.listener.accept() {
+                Ok((stream, _)) => match Client::new(stream) {
+                    Ok(client) => self.client = Some(client),
+                    Err(err) => self.disconnect(err),
+                },
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+                Err(err) => {
+                    error!(
+                        "Virtual time server error",
+                        "Couldn't accept virtual time controller connection: {err}"
+                    );
+                }
+            }
+        }
+
+        while let Some(client) = &mut self.client {
+            let command = match client.try_recv_line() {
+                Ok(Some(command)) => command.to_string(),
+                Ok(None) => break,
+                Err(err) => {
+                    self.disconnect(err);
+                    break;
+                }
+            };
+            let reply = self.handle_command(&command, emu);
+            self.send(&reply);
+        }
+    }
+
+    /// Waits until the controller grants more time, or until `timeout` elapses.
+    pub fn wait_for_time<E: cpu::Engine>(&mut self, emu: &mut Emu<E>, timeout: Duration) {
+        let end_time = Instant::now() + timeout;
+        while !self.has_time_left(emu) && Instant::now() < end_time {
+            thread::sleep(Duration::from_millis(1));
+            self.poll(emu);
+        }
+    }
+
+    /// Notifies the controller if the last granted time slice was used up; needs to be called
+    /// after running the emulator.
+    pub fn check_time_limit<E: cpu::Engine>(&mut self, emu: &Emu<E>) {
+        if self.awaiting_time_limit && !self.has_time_left(emu) {
+            self.awaiting_time_limit = false;
+            self.send(&format!("done {}\n", emu.schedule.cur_time().0));
+        }
+    }
+
+    /// Needs to be called after the emulator gets rebuilt, as emulated time restarts from 0.
+    pub fn emu_reset<E: cpu::Engine>(&mut self, emu: &mut Emu<E>) {
+        self.attach(emu);
+        self.awaiting_time_limit = false;
+        self.send("reset\n");
+    }
+}
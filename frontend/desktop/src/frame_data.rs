@@ -1,10 +1,33 @@
 #[cfg(feature = "debug-views")]
 use crate::debug_views;
-use dust_core::gpu::Framebuffer;
+use dust_core::{
+    emu::input::Keys,
+    gpu::{Engine2dId, Framebuffer},
+};
+
+/// The input state seen by the emulated console at the end of a frame.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct FrameInput {
+    pub keys: Keys,
+    pub touch_pos: Option<[u16; 2]>,
+}
+
+impl Default for FrameInput {
+    fn default() -> Self {
+        FrameInput {
+            keys: Keys::empty(),
+            touch_pos: None,
+        }
+    }
+}
 
 pub struct FrameData {
     pub fb: Box<Framebuffer>,
+    /// The 2D engines shown on the upper and lower screens at the end of the frame.
+    pub screen_engines: [Engine2dId; 2],
     pub fps: f32,
+    pub frame_count: u64,
+    pub input: FrameInput,
     #[cfg(feature = "debug-views")]
     pub debug: debug_views::FrameData,
 }
@@ -13,7 +36,10 @@ impl Default for FrameData {
     fn default() -> Self {
         FrameData {
             fb: unsafe { Box::new_zeroed().assume_init() },
+            screen_engines: [Engine2dId::B, Engine2dId::A],
             fps: 0.0,
+            frame_count: 0,
+            input: FrameInput::default(),
             #[cfg(feature = "debug-views")]
             debug: debug_views::FrameData::new(),
         }
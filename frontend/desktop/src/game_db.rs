@@ -1,6 +1,11 @@
+use dust_core::ds_slot::rom::header::Header;
 use serde::{Deserialize, Serialize};
 use std::{fs, io, path::Path};
 
+/// The newest database format version this build understands; databases using the legacy format
+/// (a bare array of entries) are treated as version 1.
+const VERSION: u32 = 2;
+
 #[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum SaveType {
@@ -28,6 +33,36 @@ pub enum SaveType {
 }
 
 impl SaveType {
+    pub const ALL: [SaveType; 11] = [
+        SaveType::None,
+        SaveType::Eeprom4k,
+        SaveType::EepromFram64k,
+        SaveType::EepromFram512k,
+        SaveType::EepromFram1m,
+        SaveType::Flash2m,
+        SaveType::Flash4m,
+        SaveType::Flash8m,
+        SaveType::Nand64m,
+        SaveType::Nand128m,
+        SaveType::Nand256m,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SaveType::None => "None",
+            SaveType::Eeprom4k => "EEPROM (4 Kbit)",
+            SaveType::EepromFram64k => "EEPROM/FRAM (64 Kbit)",
+            SaveType::EepromFram512k => "EEPROM/FRAM (512 Kbit)",
+            SaveType::EepromFram1m => "EEPROM/FRAM (1 Mbit)",
+            SaveType::Flash2m => "Flash (2 Mbit)",
+            SaveType::Flash4m => "Flash (4 Mbit)",
+            SaveType::Flash8m => "Flash (8 Mbit)",
+            SaveType::Nand64m => "NAND (64 Mbit)",
+            SaveType::Nand128m => "NAND (128 Mbit)",
+            SaveType::Nand256m => "NAND (256 Mbit)",
+        }
+    }
+
     pub fn expected_len(self) -> Option<usize> {
         match self {
             SaveType::None => None,
@@ -61,26 +96,99 @@ impl SaveType {
     }
 }
 
+/// Extra hardware a game uses, which may need to be enabled (or might not be emulated) for it to
+/// be fully playable.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Peripherals {
+    #[serde(skip_serializing_if = "is_false")]
+    pub microphone: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub rumble: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub infrared: bool,
+    /// Whether the game uses a GBA slot accessory or cartridge (e.g. for dual-slot features).
+    #[serde(skip_serializing_if = "is_false")]
+    pub gba_slot: bool,
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+impl Peripherals {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Problems a game is known to have when emulated, only present in version 2 databases.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct KnownIssues {
+    #[serde(skip_serializing_if = "is_false")]
+    pub graphics: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub audio: bool,
+    /// Whether the game is known to crash or hang at some point.
+    #[serde(skip_serializing_if = "is_false")]
+    pub crashes: bool,
+    /// Whether the game can't be played through at all (e.g. it doesn't boot).
+    #[serde(skip_serializing_if = "is_false")]
+    pub unplayable: bool,
+}
+
+impl KnownIssues {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 #[derive(Clone, Copy, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct Entry {
     pub code: u32,
     pub rom_size: u32,
     pub save_type: SaveType,
+    #[serde(default, skip_serializing_if = "Peripherals::is_empty")]
+    pub peripherals: Peripherals,
+    #[serde(default, skip_serializing_if = "KnownIssues::is_empty")]
+    pub known_issues: KnownIssues,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DatabaseFile {
+    Legacy(Vec<Entry>),
+    Versioned { version: u32, entries: Vec<Entry> },
+}
+
+#[derive(Clone)]
 pub struct Database(Vec<Entry>);
 
 pub enum Error {
     Io(io::Error),
     Json(serde_json::Error),
+    UnsupportedVersion(u32),
 }
 
 impl Database {
     pub fn read_from_file(path: &Path) -> Result<Self, Error> {
         let content = fs::read_to_string(path).map_err(Error::Io)?;
-        serde_json::from_str(&content).map_err(Error::Json)
+        let mut entries = match serde_json::from_str(&content).map_err(Error::Json)? {
+            DatabaseFile::Legacy(entries) => entries,
+            DatabaseFile::Versioned { version, entries } => {
+                if version > VERSION {
+                    return Err(Error::UnsupportedVersion(version));
+                }
+                entries
+            }
+        };
+        // Entries are looked up through binary search, so make sure they're in order even if the
+        // database was edited by hand
+        entries.sort_unstable_by_key(|entry| entry.code);
+        Ok(Database(entries))
     }
 
     pub fn lookup(&self, game_code: u32) -> Option<Entry> {
@@ -90,3 +198,137 @@ impl Database {
             .map(|i| self.0[i])
     }
 }
+
+/// Where the save type used for a game was taken from, in decreasing order of priority (except
+/// for `SaveFile`, which is only used when the size of an existing save file doesn't match the
+/// expected one, or no other source is available).
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SaveTypeSource {
+    Override,
+    Database,
+    Heuristic,
+    SaveFile,
+}
+
+impl SaveTypeSource {
+    pub fn description(self) -> &'static str {
+        match self {
+            SaveTypeSource::Override => "the per-game override",
+            SaveTypeSource::Database => "the game database",
+            SaveTypeSource::Heuristic => "the ROM header",
+            SaveTypeSource::SaveFile => "the existing save file",
+        }
+    }
+}
+
+/// Bit 27 of a cartridge's chip ID, set for cartridges with NAND memory.
+const CHIP_ID_NAND: u32 = 1 << 27;
+
+/// Reconstructs the chip ID that the cartridge a ROM was dumped from would report, based on the
+/// capacity and NAND layout specified in its header.
+fn chip_id(header: &Header) -> u32 {
+    let size_byte = match header.capacity().1 {
+        Some(capacity @ 0x10_0000..=0xFFF_FFFF) => (capacity >> 20) as u32 - 1,
+        Some(capacity @ 0x1000_0000..=0xFFFF_FFFF) => 0x100 - (capacity >> 28) as u32,
+        _ => 0,
+    };
+    let nand_flag = if header.nand_raw_rom_end() != 0 {
+        CHIP_ID_NAND
+    } else {
+        0
+    };
+    0xC2 | (size_byte << 8) | nand_flag
+}
+
+/// Guesses the save type of a game that isn't in the database from its ROM header: homebrew
+/// doesn't use save memory, and NAND cartridges store saves in the part of their NAND chip after
+/// the read-only area. Other save types can't be told apart without booting the game.
+pub fn detect_save_type(header: &Header) -> Option<SaveType> {
+    if header.game_code().0 == 0 {
+        return Some(SaveType::None);
+    }
+    if chip_id(header) & CHIP_ID_NAND == 0 {
+        return None;
+    }
+    let capacity = header.capacity().1?;
+    let save_len = capacity.saturating_sub((header.nand_raw_rw_start() as usize) << 17);
+    Some(if save_len <= 0x80_0000 {
+        SaveType::Nand64m
+    } else if save_len <= 0x100_0000 {
+        SaveType::Nand128m
+    } else {
+        SaveType::Nand256m
+    })
+}
+
+/// Returns the save type a game is expected to use before taking any existing save file into
+/// account, along with where it was taken from.
+pub fn expected_save_type(
+    save_type_override: Option<SaveType>,
+    db_entry: Option<&Entry>,
+    header: &Header,
+) -> Option<(SaveType, SaveTypeSource)> {
+    if let Some(save_type) = save_type_override {
+        Some((save_type, SaveTypeSource::Override))
+    } else if let Some(entry) = db_entry {
+        Some((entry.save_type, SaveTypeSource::Database))
+    } else {
+        detect_save_type(header).map(|save_type| (save_type, SaveTypeSource::Heuristic))
+    }
+}
+
+/// A GBA game that a DS game recognizes when inserted into the GBA slot, unlocking its dual-slot
+/// features.
+#[derive(Clone, Copy)]
+pub struct GbaTitle {
+    /// The first 3 characters of the game code, shared by all of the game's regional versions.
+    pub code_prefix: [u8; 3],
+    pub name: &'static str,
+}
+
+impl GbaTitle {
+    pub fn matches(&self, game_code: [u8; 4]) -> bool {
+        game_code[..3] == self.code_prefix
+    }
+}
+
+const POKEMON_GEN_3: &[GbaTitle] = &[
+    GbaTitle {
+        code_prefix: *b"AXV",
+        name: "Pokémon Ruby",
+    },
+    GbaTitle {
+        code_prefix: *b"AXP",
+        name: "Pokémon Sapphire",
+    },
+    GbaTitle {
+        code_prefix: *b"BPE",
+        name: "Pokémon Emerald",
+    },
+    GbaTitle {
+        code_prefix: *b"BPR",
+        name: "Pokémon FireRed",
+    },
+    GbaTitle {
+        code_prefix: *b"BPG",
+        name: "Pokémon LeafGreen",
+    },
+];
+
+/// DS games with dual-slot features, keyed by the first 3 characters of their game code.
+const DUAL_SLOT_GAMES: &[([u8; 3], &[GbaTitle])] = &[
+    (*b"ADA", POKEMON_GEN_3), // Pokémon Diamond
+    (*b"APA", POKEMON_GEN_3), // Pokémon Pearl
+    (*b"CPU", POKEMON_GEN_3), // Pokémon Platinum
+    (*b"IPG", POKEMON_GEN_3), // Pokémon SoulSilver
+    (*b"IPK", POKEMON_GEN_3), // Pokémon HeartGold
+];
+
+/// Returns the GBA games the DS game with the given game code recognizes in the GBA slot, if any.
+pub fn dual_slot_gba_titles(ds_game_code: u32) -> &'static [GbaTitle] {
+    let code = ds_game_code.to_le_bytes();
+    DUAL_SLOT_GAMES
+        .iter()
+        .find(|(prefix, _)| code[..3] == *prefix)
+        .map_or(&[], |(_, titles)| titles)
+}
@@ -1,7 +1,9 @@
+pub mod gamepad;
+pub use gamepad::Gamepads;
 mod map;
 pub use map::{GlobalMap, Map};
 mod state;
-pub use state::{Changes, State};
+pub use state::{Changes, FastPointerSettings, State, TouchSettings};
 pub mod key_codes;
 pub mod trigger;
 pub use key_codes::{KeyCode, ScanCode};
@@ -16,12 +18,38 @@ pub enum Action {
     ToggleFramerateLimit,
     ToggleSyncToAudio,
     ToggleFullWindowScreen,
+    SwapScreens,
+    SwapMagnifiedScreen,
+    NextScreenLayout,
+    PrevScreenLayout,
+    TogglePerfOverlay,
+    FastForward,
+    ToggleTurbo,
+    SlowMotion,
+    FrameAdvance,
+    RunFrames,
+    ReleasePointer,
+    Touch,
+    StylusUp,
+    StylusDown,
+    StylusLeft,
+    StylusRight,
+    StylusTouch,
+    SaveStateSlot,
+    LoadStateSlot,
+    NextStateSlot,
+    PrevStateSlot,
+    CancelStateLoad,
+    NextSaveSlot,
+    PrevSaveSlot,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum PressedKey {
     KeyCode(KeyCode),
     ScanCode(ScanCode),
+    GamepadButton(gamepad::Button),
+    GamepadAxis(gamepad::Axis, gamepad::AxisDir),
 }
 
 impl TryFrom<PhysicalKey> for PressedKey {
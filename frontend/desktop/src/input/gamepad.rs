@@ -0,0 +1,308 @@
+use super::PressedKey;
+use ahash::{AHashMap as HashMap, AHashSet as HashSet};
+use gilrs::{
+    ff::{BaseEffect, BaseEffectType, Effect, EffectBuilder, Repeat, Replay, Ticks},
+    EventType, GamepadId, Gilrs,
+};
+use std::{fmt, str::FromStr};
+
+macro_rules! named_enum_wrapper {
+    ($name: ident, $inner: ty, [$($variant: ident),*$(,)?]) => {
+        #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+        pub struct $name(pub $inner);
+
+        impl $name {
+            const NAMES: &'static [($inner, &'static str)] =
+                &[$((<$inner>::$variant, stringify!($variant))),*];
+
+            pub fn name(self) -> &'static str {
+                Self::NAMES
+                    .iter()
+                    .find_map(|(value, name)| (*value == self.0).then_some(*name))
+                    .unwrap_or("Unknown")
+            }
+        }
+
+        impl From<$inner> for $name {
+            fn from(value: $inner) -> Self {
+                $name(value)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ();
+
+            fn from_str(s: &str) -> Result<Self, ()> {
+                Self::NAMES
+                    .iter()
+                    .find_map(|(value, name)| (*name == s).then_some($name(*value)))
+                    .ok_or(())
+            }
+        }
+    };
+}
+
+named_enum_wrapper!(
+    Button,
+    gilrs::Button,
+    [
+        South,
+        East,
+        North,
+        West,
+        C,
+        Z,
+        LeftTrigger,
+        LeftTrigger2,
+        RightTrigger,
+        RightTrigger2,
+        Select,
+        Start,
+        Mode,
+        LeftThumb,
+        RightThumb,
+        DPadUp,
+        DPadDown,
+        DPadLeft,
+        DPadRight,
+    ]
+);
+
+named_enum_wrapper!(
+    Axis,
+    gilrs::Axis,
+    [
+        LeftStickX,
+        LeftStickY,
+        LeftZ,
+        RightStickX,
+        RightStickY,
+        RightZ,
+        DPadX,
+        DPadY
+    ]
+);
+
+/// The direction an axis needs to be pushed in past the dead zone to count as pressed; for the Y
+/// axes of sticks, positive values point up.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum AxisDir {
+    Negative,
+    Positive,
+}
+
+impl AxisDir {
+    pub fn sign(self) -> char {
+        match self {
+            AxisDir::Negative => '-',
+            AxisDir::Positive => '+',
+        }
+    }
+
+    pub fn from_sign(sign: char) -> Option<Self> {
+        match sign {
+            '-' => Some(AxisDir::Negative),
+            '+' => Some(AxisDir::Positive),
+            _ => None,
+        }
+    }
+}
+
+pub enum Event {
+    /// A button or axis direction was pressed or released on any connected gamepad; inputs held on
+    /// multiple gamepads at once are only reported as released once all of them release them.
+    Input(PressedKey, bool),
+    Connected(String),
+    Disconnected(String),
+}
+
+pub struct Gamepads {
+    gilrs: Option<Gilrs>,
+    held_inputs: HashMap<GamepadId, HashSet<PressedKey>>,
+    deadzone: f32,
+    rumble_effect: Option<Effect>,
+    rumble_enabled: bool,
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        let gilrs = Gilrs::new().ok();
+        let held_inputs = gilrs.as_ref().map_or_else(HashMap::new, |gilrs| {
+            gilrs
+                .gamepads()
+                .map(|(id, _)| (id, HashSet::new()))
+                .collect()
+        });
+        Gamepads {
+            gilrs,
+            held_inputs,
+            deadzone: 0.3,
+            rumble_effect: None,
+            rumble_enabled: false,
+        }
+    }
+
+    /// Returns whether gamepad support could be initialized on this system.
+    #[inline]
+    pub fn is_available(&self) -> bool {
+        self.gilrs.is_some()
+    }
+
+    /// Sets how far (from 0 to 1) axes need to be pushed in a direction to count as pressed.
+    pub fn set_deadzone(&mut self, deadzone: f32) {
+        self.deadzone = deadzone.clamp(0.0, 0.99);
+    }
+
+    /// Starts or stops rumbling on all connected gamepads supporting force feedback.
+    pub fn set_rumble(&mut self, enabled: bool) {
+        if enabled == self.rumble_enabled {
+            return;
+        }
+        self.rumble_enabled = enabled;
+        if enabled {
+            self.start_rumble();
+        } else if let Some(effect) = &self.rumble_effect {
+            let _ = effect.stop();
+        }
+    }
+
+    fn start_rumble(&mut self) {
+        let Some(gilrs) = &mut self.gilrs else {
+            return;
+        };
+        if self.rumble_effect.is_none() {
+            let ff_gamepads = gilrs
+                .gamepads()
+                .filter_map(|(id, gamepad)| gamepad.is_ff_supported().then_some(id))
+                .collect::<Vec<_>>();
+            if ff_gamepads.is_empty() {
+                return;
+            }
+            self.rumble_effect = EffectBuilder::new()
+                .add_effect(BaseEffect {
+                    kind: BaseEffectType::Strong { magnitude: 0xC000 },
+                    scheduling: Replay {
+                        play_for: Ticks::from_ms(50),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .repeat(Repeat::Infinitely)
+                .gamepads(&ff_gamepads)
+                .finish(gilrs)
+                .ok();
+        }
+        if let Some(effect) = &self.rumble_effect {
+            let _ = effect.play();
+        }
+    }
+
+    fn set_held(
+        held_inputs: &mut HashMap<GamepadId, HashSet<PressedKey>>,
+        id: GamepadId,
+        key: PressedKey,
+        held: bool,
+        f: &mut impl FnMut(Event),
+    ) {
+        let changed = {
+            let gamepad_inputs = held_inputs.entry(id).or_default();
+            if held {
+                gamepad_inputs.insert(key)
+            } else {
+                gamepad_inputs.remove(&key)
+            }
+        };
+        if changed
+            && !held_inputs
+                .iter()
+                .any(|(other_id, inputs)| *other_id != id && inputs.contains(&key))
+        {
+            f(Event::Input(key, held));
+        }
+    }
+
+    /// Processes all pending gamepad events, calling `f` for each resulting input change and for
+    /// each gamepad being connected or disconnected.
+    pub fn poll(&mut self, mut f: impl FnMut(Event)) {
+        let Some(gilrs) = &mut self.gilrs else {
+            return;
+        };
+        let mut connected_gamepads_changed = false;
+
+        while let Some(gilrs::Event { id, event, .. }) = gilrs.next_event() {
+            match event {
+                EventType::ButtonPressed(button, _) | EventType::ButtonReleased(button, _) => {
+                    if button == gilrs::Button::Unknown {
+                        continue;
+                    }
+                    Self::set_held(
+                        &mut self.held_inputs,
+                        id,
+                        PressedKey::GamepadButton(button.into()),
+                        matches!(event, EventType::ButtonPressed(..)),
+                        &mut f,
+                    );
+                }
+
+                EventType::AxisChanged(axis, value, _) => {
+                    if axis == gilrs::Axis::Unknown {
+                        continue;
+                    }
+                    let dir = if value >= self.deadzone {
+                        Some(AxisDir::Positive)
+                    } else if value <= -self.deadzone {
+                        Some(AxisDir::Negative)
+                    } else {
+                        None
+                    };
+                    for other_dir in [AxisDir::Negative, AxisDir::Positive] {
+                        Self::set_held(
+                            &mut self.held_inputs,
+                            id,
+                            PressedKey::GamepadAxis(axis.into(), other_dir),
+                            dir == Some(other_dir),
+                            &mut f,
+                        );
+                    }
+                }
+
+                EventType::Connected => {
+                    self.held_inputs.entry(id).or_default();
+                    connected_gamepads_changed = true;
+                    f(Event::Connected(gilrs.gamepad(id).name().to_owned()));
+                }
+
+                EventType::Disconnected => {
+                    if let Some(inputs) = self.held_inputs.remove(&id) {
+                        for key in inputs {
+                            if !self.held_inputs.values().any(|other| other.contains(&key)) {
+                                f(Event::Input(key, false));
+                            }
+                        }
+                    }
+                    connected_gamepads_changed = true;
+                    f(Event::Disconnected(gilrs.gamepad(id).name().to_owned()));
+                }
+
+                _ => {}
+            }
+        }
+
+        if connected_gamepads_changed {
+            // Force feedback effects are bound to a fixed set of gamepads, rebuild it to include
+            // newly connected ones
+            if let Some(effect) = self.rumble_effect.take() {
+                let _ = effect.stop();
+            }
+            if self.rumble_enabled {
+                self.start_rumble();
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use super::{
+    gamepad::AxisDir,
     trigger::{Op, Trigger},
     Action,
 };
@@ -37,8 +38,32 @@ static ACTION_IDENTS: &[(Action, &str)] = &[
         Action::ToggleFullWindowScreen,
         "toggle-whole-window-screen-drawing",
     ),
+    (Action::SwapScreens, "swap-screens"),
+    (Action::SwapMagnifiedScreen, "swap-magnified-screen"),
+    (Action::NextScreenLayout, "next-screen-layout"),
+    (Action::PrevScreenLayout, "prev-screen-layout"),
+    (Action::TogglePerfOverlay, "toggle-perf-overlay"),
     (Action::ToggleSyncToAudio, "toggle-sync-to-audio"),
     (Action::ToggleFramerateLimit, "toggle-framerate-limit"),
+    (Action::FastForward, "fast-forward"),
+    (Action::ToggleTurbo, "toggle-turbo"),
+    (Action::SlowMotion, "slow-motion"),
+    (Action::FrameAdvance, "frame-advance"),
+    (Action::RunFrames, "run-frames"),
+    (Action::ReleasePointer, "release-pointer"),
+    (Action::Touch, "touch"),
+    (Action::StylusUp, "stylus-up"),
+    (Action::StylusDown, "stylus-down"),
+    (Action::StylusLeft, "stylus-left"),
+    (Action::StylusRight, "stylus-right"),
+    (Action::StylusTouch, "stylus-touch"),
+    (Action::SaveStateSlot, "save-state-slot"),
+    (Action::LoadStateSlot, "load-state-slot"),
+    (Action::NextStateSlot, "next-state-slot"),
+    (Action::PrevStateSlot, "prev-state-slot"),
+    (Action::CancelStateLoad, "cancel-state-load"),
+    (Action::NextSaveSlot, "next-save-slot"),
+    (Action::PrevSaveSlot, "prev-save-slot"),
 ];
 
 #[derive(Clone)]
@@ -56,15 +81,52 @@ impl Map {
     }
 }
 
+fn key_or_gamepad(key_code: KeyCode, gamepad_triggers: &[Trigger]) -> Option<Trigger> {
+    let mut triggers = vec![Trigger::KeyCode(key_code.into())];
+    triggers.extend_from_slice(gamepad_triggers);
+    Some(Trigger::Chain(Op::Or, triggers))
+}
+
+fn button(button: gilrs::Button) -> Trigger {
+    Trigger::GamepadButton(button.into())
+}
+
+fn axis(axis: gilrs::Axis, dir: AxisDir) -> Trigger {
+    Trigger::GamepadAxis(axis.into(), dir)
+}
+
 fn default_keypad_map() -> HashMap<Keys, Option<Trigger>> {
+    use gilrs::{Axis, Button};
+
     [
-        (Keys::A, Some(Trigger::KeyCode(KeyCode::KeyX.into()))),
-        (Keys::B, Some(Trigger::KeyCode(KeyCode::KeyZ.into()))),
-        (Keys::X, Some(Trigger::KeyCode(KeyCode::KeyS.into()))),
-        (Keys::Y, Some(Trigger::KeyCode(KeyCode::KeyA.into()))),
-        (Keys::L, Some(Trigger::KeyCode(KeyCode::KeyQ.into()))),
-        (Keys::R, Some(Trigger::KeyCode(KeyCode::KeyW.into()))),
-        (Keys::START, Some(Trigger::KeyCode(KeyCode::Enter.into()))),
+        (
+            Keys::A,
+            key_or_gamepad(KeyCode::KeyX, &[button(Button::East)]),
+        ),
+        (
+            Keys::B,
+            key_or_gamepad(KeyCode::KeyZ, &[button(Button::South)]),
+        ),
+        (
+            Keys::X,
+            key_or_gamepad(KeyCode::KeyS, &[button(Button::North)]),
+        ),
+        (
+            Keys::Y,
+            key_or_gamepad(KeyCode::KeyA, &[button(Button::West)]),
+        ),
+        (
+            Keys::L,
+            key_or_gamepad(KeyCode::KeyQ, &[button(Button::LeftTrigger)]),
+        ),
+        (
+            Keys::R,
+            key_or_gamepad(KeyCode::KeyW, &[button(Button::RightTrigger)]),
+        ),
+        (
+            Keys::START,
+            key_or_gamepad(KeyCode::Enter, &[button(Button::Start)]),
+        ),
         (
             Keys::SELECT,
             Some(Trigger::Chain(
@@ -72,21 +134,49 @@ fn default_keypad_map() -> HashMap<Keys, Option<Trigger>> {
                 vec![
                     Trigger::KeyCode(KeyCode::ShiftLeft.into()),
                     Trigger::KeyCode(KeyCode::ShiftRight.into()),
+                    button(Button::Select),
                 ],
             )),
         ),
         (
             Keys::RIGHT,
-            Some(Trigger::KeyCode(KeyCode::ArrowRight.into())),
+            key_or_gamepad(
+                KeyCode::ArrowRight,
+                &[
+                    button(Button::DPadRight),
+                    axis(Axis::LeftStickX, AxisDir::Positive),
+                ],
+            ),
         ),
         (
             Keys::LEFT,
-            Some(Trigger::KeyCode(KeyCode::ArrowLeft.into())),
+            key_or_gamepad(
+                KeyCode::ArrowLeft,
+                &[
+                    button(Button::DPadLeft),
+                    axis(Axis::LeftStickX, AxisDir::Negative),
+                ],
+            ),
+        ),
+        (
+            Keys::UP,
+            key_or_gamepad(
+                KeyCode::ArrowUp,
+                &[
+                    button(Button::DPadUp),
+                    axis(Axis::LeftStickY, AxisDir::Positive),
+                ],
+            ),
         ),
-        (Keys::UP, Some(Trigger::KeyCode(KeyCode::ArrowUp.into()))),
         (
             Keys::DOWN,
-            Some(Trigger::KeyCode(KeyCode::ArrowDown.into())),
+            key_or_gamepad(
+                KeyCode::ArrowDown,
+                &[
+                    button(Button::DPadDown),
+                    axis(Axis::LeftStickY, AxisDir::Negative),
+                ],
+            ),
         ),
         (Keys::DEBUG, None),
     ]
@@ -100,8 +190,38 @@ fn default_hotkey_map() -> HashMap<Action, Option<Trigger>> {
         (Action::Reset, None),
         (Action::Stop, None),
         (Action::ToggleFullWindowScreen, None),
+        (Action::SwapScreens, None),
+        (Action::SwapMagnifiedScreen, None),
+        (Action::NextScreenLayout, None),
+        (Action::PrevScreenLayout, None),
+        (Action::TogglePerfOverlay, None),
         (Action::ToggleSyncToAudio, None),
         (Action::ToggleFramerateLimit, None),
+        (
+            Action::FastForward,
+            Some(Trigger::KeyCode(KeyCode::Tab.into())),
+        ),
+        (Action::ToggleTurbo, None),
+        (Action::SlowMotion, None),
+        (Action::FrameAdvance, None),
+        (Action::RunFrames, None),
+        (
+            Action::ReleasePointer,
+            Some(Trigger::KeyCode(KeyCode::Escape.into())),
+        ),
+        (Action::Touch, None),
+        (Action::StylusUp, None),
+        (Action::StylusDown, None),
+        (Action::StylusLeft, None),
+        (Action::StylusRight, None),
+        (Action::StylusTouch, None),
+        (Action::SaveStateSlot, None),
+        (Action::LoadStateSlot, None),
+        (Action::NextStateSlot, None),
+        (Action::PrevStateSlot, None),
+        (Action::CancelStateLoad, None),
+        (Action::NextSaveSlot, None),
+        (Action::PrevSaveSlot, None),
     ]
     .into_iter()
     .collect()
@@ -1,14 +1,64 @@
 use super::{Action, Map, PressedKey};
 use crate::ui::utils::mul2s;
 use ahash::AHashSet as HashSet;
-use dust_core::emu::input::Keys as EmuKeys;
+use dust_core::emu::input::{Change as EmuChange, Keys as EmuKeys};
+use std::{mem, time::Instant};
 use winit::{
     dpi::{LogicalPosition, LogicalSize},
-    event::{Event, KeyEvent, MouseButton, WindowEvent},
+    event::{DeviceEvent, Event, KeyEvent, MouseButton, WindowEvent},
+    window::WindowId,
 };
 
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    let x = b[0] - a[0];
+    let y = b[1] - a[1];
+    (x * x + y * y).sqrt()
+}
+
+/// Settings for the fast pointer mode, in which the mouse cursor gets captured when clicking on the
+/// touchscreen and raw mouse motion is mapped directly to touchscreen coordinates, bypassing the
+/// window system's cursor handling.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FastPointerSettings {
+    /// How many touchscreen pixels the pointer moves by for every unit of raw mouse motion.
+    pub sensitivity: f32,
+    /// How much faster mouse motion should additionally scale the pointer's movement by.
+    pub acceleration: f32,
+}
+
+/// Settings for the alternative ways of touching the touchscreen, through hotkeys instead of mouse
+/// clicks.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TouchSettings {
+    /// Whether the mouse should only touch the touchscreen (at the cursor's position) while the
+    /// touch hotkey is held, instead of while its left button is.
+    pub require_hotkey: bool,
+    /// How many touchscreen pixels per second the stylus moves by while a stylus movement hotkey
+    /// is held.
+    pub stylus_speed: f32,
+}
+
+impl Default for TouchSettings {
+    fn default() -> Self {
+        TouchSettings {
+            require_hotkey: false,
+            stylus_speed: 128.0,
+        }
+    }
+}
+
+/// The pressed keys and touch position after an input event, recorded so that the changes it
+/// caused can be timed individually once they're drained.
+struct InputSnapshot {
+    time: Instant,
+    pressed_keys: HashSet<PressedKey>,
+    /// `None` while the fast pointer is captured, as its changes are forwarded separately.
+    touch_pos: Option<Option<[u16; 2]>>,
+}
+
 pub struct State {
     pressed_keys: HashSet<PressedKey>,
+    touchscreen_window: Option<WindowId>,
     touchscreen_center: LogicalPosition<f64>,
     touchscreen_size: LogicalSize<f64>,
     touchscreen_half_size: LogicalSize<f64>,
@@ -17,8 +67,20 @@ pub struct State {
     mouse_pos: LogicalPosition<f64>,
     touch_pos: Option<[u16; 2]>,
     prev_touch_pos: Option<[u16; 2]>,
+    fast_pointer: Option<FastPointerSettings>,
+    pointer_captured: bool,
+    pointer_capture_changed: bool,
+    /// The fast pointer's position in touchscreen coordinates, tracked even while not touching;
+    /// also used as the position of the stylus moved through hotkeys.
+    pointer_pos: [f64; 2],
+    touch_settings: TouchSettings,
+    hotkey_touching: bool,
+    stylus_touching: bool,
+    stylus_visible: bool,
+    last_stylus_update_time: Instant,
     pressed_emu_keys: EmuKeys,
     pressed_hotkeys: HashSet<Action>,
+    input_history: Vec<InputSnapshot>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -26,12 +88,30 @@ pub struct Changes {
     pub pressed: EmuKeys,
     pub released: EmuKeys,
     pub touch_pos: Option<Option<[u16; 2]>>,
+    /// The time at which the input event causing the changes was received.
+    pub time: Instant,
+}
+
+impl Changes {
+    pub fn emu_changes(&self) -> impl Iterator<Item = EmuChange> {
+        [
+            (!self.pressed.is_empty()).then_some(EmuChange::PressKeys(self.pressed)),
+            (!self.released.is_empty()).then_some(EmuChange::ReleaseKeys(self.released)),
+            self.touch_pos.map(|touch_pos| match touch_pos {
+                Some(pos) => EmuChange::SetTouchPos(pos),
+                None => EmuChange::EndTouch,
+            }),
+        ]
+        .into_iter()
+        .flatten()
+    }
 }
 
 impl State {
     pub fn new() -> Self {
         State {
             pressed_keys: HashSet::new(),
+            touchscreen_window: None,
             touchscreen_size: Default::default(),
             touchscreen_center: Default::default(),
             touchscreen_half_size: Default::default(),
@@ -40,33 +120,174 @@ impl State {
             mouse_pos: Default::default(),
             touch_pos: None,
             prev_touch_pos: None,
+            fast_pointer: None,
+            pointer_captured: false,
+            pointer_capture_changed: false,
+            pointer_pos: [0.0; 2],
+            touch_settings: TouchSettings::default(),
+            hotkey_touching: false,
+            stylus_touching: false,
+            stylus_visible: false,
+            last_stylus_update_time: Instant::now(),
             pressed_emu_keys: EmuKeys::empty(),
             pressed_hotkeys: HashSet::new(),
+            input_history: Vec::new(),
         }
     }
 
+    /// Restricts mouse input used for the touchscreen to the window with the given ID; if `None`,
+    /// events from any window will be used.
+    pub fn set_touchscreen_window(&mut self, window_id: Option<WindowId>) {
+        if window_id != self.touchscreen_window {
+            self.touchscreen_window = window_id;
+            self.touch_pos = None;
+        }
+    }
+
+    pub fn set_fast_pointer(&mut self, settings: Option<FastPointerSettings>) {
+        self.fast_pointer = settings;
+        if settings.is_none() {
+            self.release_pointer();
+        }
+    }
+
+    pub fn set_touch_settings(&mut self, settings: TouchSettings) {
+        if settings.require_hotkey != self.touch_settings.require_hotkey {
+            self.touch_pos = None;
+            self.hotkey_touching = false;
+        }
+        self.touch_settings = settings;
+    }
+
+    /// Returns the position of the stylus moved through hotkeys, normalized to the 0-1 range, if
+    /// it's been used since the mouse last touched the touchscreen (and should thus be shown).
+    pub fn stylus_pos(&self) -> Option<[f32; 2]> {
+        self.stylus_visible.then(|| {
+            [
+                (self.pointer_pos[0] / 4096.0) as f32,
+                (self.pointer_pos[1] / 3072.0) as f32,
+            ]
+        })
+    }
+
+    #[inline]
+    pub fn pointer_captured(&self) -> bool {
+        self.pointer_captured
+    }
+
+    /// Returns the new pointer capture state if it changed since the last call, so that the
+    /// cursor can be grabbed or released accordingly.
+    pub fn take_pointer_capture_change(&mut self) -> Option<bool> {
+        mem::take(&mut self.pointer_capture_changed).then_some(self.pointer_captured)
+    }
+
+    pub fn release_pointer(&mut self) {
+        if self.pointer_captured {
+            self.pointer_captured = false;
+            self.pointer_capture_changed = true;
+            self.touch_pos = None;
+        }
+    }
+
+    /// Moves the pointer by the given delta in window space, in touchscreen pixels.
+    fn offset_pointer(&mut self, delta: [f64; 2]) {
+        // Undo the screen's rotation, so that moving to the right always moves the pointer to the
+        // right from the user's point of view
+        let delta = [
+            delta[0] * self.touchscreen_rot.1 + delta[1] * self.touchscreen_rot.0,
+            delta[1] * self.touchscreen_rot.1 - delta[0] * self.touchscreen_rot.0,
+        ];
+        // Touchscreen coordinates have 4 fractional bits per pixel
+        self.pointer_pos = [
+            (self.pointer_pos[0] + delta[0] * 16.0).clamp(0.0, 4095.0),
+            (self.pointer_pos[1] + delta[1] * 16.0).clamp(0.0, 3072.0),
+        ];
+        if self.touch_pos.is_some() {
+            self.touch_pos = Some(self.pointer_pos.map(|coord| coord as u16));
+        }
+    }
+
+    fn move_pointer(&mut self, delta: (f64, f64), settings: FastPointerSettings) {
+        let speed = (delta.0 * delta.0 + delta.1 * delta.1).sqrt();
+        let scale = settings.sensitivity as f64 * (1.0 + settings.acceleration as f64 * speed);
+        self.offset_pointer([delta.0 * scale, delta.1 * scale]);
+    }
+
+    /// Moves the stylus and touches with it according to the held stylus hotkeys, and touches at
+    /// the cursor's position while the touch hotkey is held if required.
+    fn update_hotkey_touch(&mut self) {
+        let now = Instant::now();
+        let elapsed = (now - mem::replace(&mut self.last_stylus_update_time, now)).as_secs_f64();
+
+        let dir = [
+            self.hotkey_held(Action::StylusRight) as i8
+                - self.hotkey_held(Action::StylusLeft) as i8,
+            self.hotkey_held(Action::StylusDown) as i8 - self.hotkey_held(Action::StylusUp) as i8,
+        ];
+        if dir != [0; 2] {
+            let scale = self.touch_settings.stylus_speed as f64 * elapsed;
+            self.stylus_visible = true;
+            self.offset_pointer(dir.map(|dir| dir as f64 * scale));
+        }
+
+        let stylus_touching = self.hotkey_held(Action::StylusTouch);
+        if stylus_touching != self.stylus_touching {
+            self.stylus_touching = stylus_touching;
+            if stylus_touching {
+                self.stylus_visible = true;
+                self.touch_pos = Some(self.pointer_pos.map(|coord| coord as u16));
+            } else {
+                self.touch_pos = None;
+            }
+        }
+
+        if self.touch_settings.require_hotkey && !self.pointer_captured && !self.stylus_touching {
+            let hotkey_touching = self.hotkey_held(Action::Touch);
+            if hotkey_touching != self.hotkey_touching {
+                self.hotkey_touching = hotkey_touching;
+                if hotkey_touching {
+                    self.stylus_visible = false;
+                    self.recalculate_touch_pos::<false>();
+                } else {
+                    self.touch_pos = None;
+                }
+            }
+        }
+    }
+
+    /// Sets the touchscreen's bounds from a quad only containing the bottom screen, rotated by `rot`
+    /// around `rot_center`.
     pub fn set_touchscreen_bounds_from_points(
         &mut self,
-        center: [f32; 2],
+        rot_center: [f32; 2],
         points: &[[f32; 2]; 4],
         rot: f32,
     ) {
-        fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
-            let x = b[0] - a[0];
-            let y = b[1] - a[1];
-            (x * x + y * y).sqrt()
-        }
-
         let size = [
             distance(points[0], points[1]),
-            distance(points[1], points[2]) * 0.5,
+            distance(points[1], points[2]),
+        ];
+        let center = [0, 1].map(|i| points.iter().map(|point| point[i]).sum::<f32>() * 0.25);
+        // Undo the rotation to find the center of the unrotated touchscreen
+        let (sin, cos) = rot.sin_cos();
+        let diff = [center[0] - rot_center[0], center[1] - rot_center[1]];
+        let center = [
+            rot_center[0] + diff[0] * cos + diff[1] * sin,
+            rot_center[1] - diff[0] * sin + diff[1] * cos,
         ];
+        self.set_touchscreen_bounds(rot_center.into(), center.into(), size.into(), rot as f64);
+    }
+
+    /// Disables touchscreen input from the mouse, for when the bottom screen isn't displayed.
+    pub fn clear_touchscreen_bounds(&mut self) {
         self.set_touchscreen_bounds(
-            center.into(),
-            (center[0], center[1] + size[1] * 0.5).into(),
-            size.into(),
-            rot as f64,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            0.0,
         );
+        self.touch_pos = None;
+        self.release_pointer();
     }
 
     pub fn set_touchscreen_bounds(
@@ -121,7 +342,11 @@ impl State {
         scale_factor: f64,
         catch_new: bool,
     ) {
-        if let Event::WindowEvent { event, .. } = event {
+        let mut input_changed = false;
+        if let Event::WindowEvent { window_id, event } = event {
+            let is_touchscreen_window = self
+                .touchscreen_window
+                .map_or(true, |touchscreen_window| touchscreen_window == *window_id);
             match event {
                 WindowEvent::KeyboardInput {
                     event:
@@ -135,6 +360,7 @@ impl State {
                     let Ok(key) = (*physical_key).try_into() else {
                         return;
                     };
+                    input_changed = true;
                     if state.is_pressed() {
                         if catch_new {
                             self.pressed_keys.insert(key);
@@ -144,9 +370,10 @@ impl State {
                     }
                 }
 
-                WindowEvent::CursorMoved { position, .. } => {
+                WindowEvent::CursorMoved { position, .. } if is_touchscreen_window => {
                     self.mouse_pos = position.to_logical(scale_factor);
-                    if self.touch_pos.is_some() {
+                    if self.touch_pos.is_some() && !self.pointer_captured && !self.stylus_touching {
+                        input_changed = true;
                         self.recalculate_touch_pos::<true>();
                     }
                 }
@@ -155,10 +382,20 @@ impl State {
                     state,
                     button: MouseButton::Left,
                     ..
-                } => {
+                } if is_touchscreen_window && !self.touch_settings.require_hotkey => {
+                    input_changed = true;
                     if state.is_pressed() {
-                        if catch_new {
+                        if self.pointer_captured {
+                            self.touch_pos = Some(self.pointer_pos.map(|coord| coord as u16));
+                        } else if catch_new {
+                            self.stylus_visible = false;
                             self.recalculate_touch_pos::<false>();
+                            if let (Some(touch_pos), Some(_)) = (self.touch_pos, self.fast_pointer)
+                            {
+                                self.pointer_captured = true;
+                                self.pointer_capture_changed = true;
+                                self.pointer_pos = touch_pos.map(f64::from);
+                            }
                         }
                     } else {
                         self.touch_pos = None;
@@ -166,20 +403,77 @@ impl State {
                 }
 
                 WindowEvent::Focused(false) => {
+                    input_changed = true;
                     self.pressed_keys.clear();
                     self.touch_pos = None;
+                    self.hotkey_touching = false;
+                    self.stylus_touching = false;
+                    self.release_pointer();
                 }
 
                 _ => {}
             }
+        } else if let Event::DeviceEvent {
+            event: DeviceEvent::MouseMotion { delta },
+            ..
+        } = event
+        {
+            // Forwarded through `take_fast_pointer_changes` instead
+            if let Some(settings) = self.fast_pointer.filter(|_| self.pointer_captured) {
+                self.move_pointer(*delta, settings);
+            }
+        }
+        if input_changed {
+            self.record_input();
         }
     }
 
-    pub fn drain_changes(
-        &mut self,
-        map: &Map,
-        emu_playing: bool,
-    ) -> (Vec<Action>, Option<Changes>) {
+    fn record_input(&mut self) {
+        self.input_history.push(InputSnapshot {
+            time: Instant::now(),
+            pressed_keys: self.pressed_keys.clone(),
+            touch_pos: (!self.pointer_captured).then_some(self.touch_pos),
+        });
+    }
+
+    /// Processes a gamepad button or axis direction being pressed or released; like keyboard keys,
+    /// newly pressed inputs are ignored unless `catch_new` is set.
+    pub fn process_gamepad_input(&mut self, key: PressedKey, pressed: bool, catch_new: bool) {
+        if pressed {
+            if catch_new {
+                self.pressed_keys.insert(key);
+            }
+        } else {
+            self.pressed_keys.remove(&key);
+        }
+        self.record_input();
+    }
+
+    /// Returns the touchscreen changes caused by the fast pointer since the last call, if any;
+    /// these are meant to be sent to the emulator as soon as the events causing them are
+    /// processed, without waiting for the next UI frame like [`State::drain_changes`] does.
+    pub fn take_fast_pointer_changes(&mut self) -> Option<Changes> {
+        if !self.pointer_captured || self.touch_pos == self.prev_touch_pos {
+            return None;
+        }
+        self.prev_touch_pos = self.touch_pos;
+        Some(Changes {
+            pressed: EmuKeys::empty(),
+            released: EmuKeys::empty(),
+            touch_pos: Some(self.touch_pos),
+            time: Instant::now(),
+        })
+    }
+
+    /// Returns whether the trigger for the given hotkey is currently held down (as of the last
+    /// call to [`State::drain_changes`]), for actions that only apply while held.
+    pub fn hotkey_held(&self, action: Action) -> bool {
+        self.pressed_hotkeys.contains(&action)
+    }
+
+    /// Returns the triggered hotkey actions and the emulator-visible input changes since the last
+    /// call; changes are split up by the input event that caused them, each with its own time.
+    pub fn drain_changes(&mut self, map: &Map, emu_active: bool) -> (Vec<Action>, Vec<Changes>) {
         let mut actions = Vec::new();
         for (&action, trigger) in &map.hotkeys {
             if let Some(trigger) = trigger {
@@ -193,38 +487,50 @@ impl State {
             }
         }
 
-        if !emu_playing {
-            return (actions, None);
+        if !emu_active {
+            self.input_history.clear();
+            return (actions, Vec::new());
         }
 
-        let mut new_pressed_emu_keys = EmuKeys::empty();
-        for (&emu_key, trigger) in &map.keypad {
-            if let Some(trigger) = trigger {
-                new_pressed_emu_keys.set(emu_key, trigger.activated(&self.pressed_keys));
+        // Touches through hotkeys (and changes to the keypad mapping) are only picked up here, so
+        // they're timed as happening now; this includes stylus movement while the fast pointer is
+        // captured, which isn't forwarded as it happens
+        self.update_hotkey_touch();
+        self.input_history.push(InputSnapshot {
+            time: Instant::now(),
+            pressed_keys: self.pressed_keys.clone(),
+            touch_pos: Some(self.touch_pos),
+        });
+
+        let mut changes = Vec::new();
+        for snapshot in mem::take(&mut self.input_history) {
+            let mut new_pressed_emu_keys = EmuKeys::empty();
+            for (&emu_key, trigger) in &map.keypad {
+                if let Some(trigger) = trigger {
+                    new_pressed_emu_keys.set(emu_key, trigger.activated(&snapshot.pressed_keys));
+                }
             }
-        }
 
-        let pressed = new_pressed_emu_keys & !self.pressed_emu_keys;
-        let released = self.pressed_emu_keys & !new_pressed_emu_keys;
-        let touch_pos = if self.touch_pos == self.prev_touch_pos {
-            None
-        } else {
-            Some(self.touch_pos)
-        };
+            let pressed = new_pressed_emu_keys & !self.pressed_emu_keys;
+            let released = self.pressed_emu_keys & !new_pressed_emu_keys;
+            let touch_pos = snapshot
+                .touch_pos
+                .filter(|&touch_pos| touch_pos != self.prev_touch_pos);
 
-        (
-            actions,
             if touch_pos.is_some() || new_pressed_emu_keys != self.pressed_emu_keys {
                 self.pressed_emu_keys = new_pressed_emu_keys;
-                self.prev_touch_pos = self.touch_pos;
-                Some(Changes {
+                if let Some(touch_pos) = touch_pos {
+                    self.prev_touch_pos = touch_pos;
+                }
+                changes.push(Changes {
                     pressed,
                     released,
                     touch_pos,
-                })
-            } else {
-                None
-            },
-        )
+                    time: snapshot.time,
+                });
+            }
+        }
+
+        (actions, changes)
     }
 }
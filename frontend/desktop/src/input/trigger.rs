@@ -1,4 +1,7 @@
-use super::{KeyCode, PressedKey, ScanCode};
+use super::{
+    gamepad::{Axis, AxisDir, Button},
+    KeyCode, PressedKey, ScanCode,
+};
 use serde::{Deserialize, Serialize};
 use std::{
     error::Error,
@@ -28,6 +31,8 @@ impl fmt::Display for Op {
 pub enum Trigger {
     KeyCode(KeyCode),
     ScanCode(ScanCode),
+    GamepadButton(Button),
+    GamepadAxis(Axis, AxisDir),
     Not(Box<Trigger>),
     Chain(Op, Vec<Trigger>),
 }
@@ -44,6 +49,12 @@ impl Trigger {
             Trigger::ScanCode(scan_code) => pressed_keys.into_iter().any(
                 |key| matches!(key, PressedKey::ScanCode(scan_code_) if scan_code_ == scan_code),
             ),
+            &Trigger::GamepadButton(button) => pressed_keys
+                .into_iter()
+                .any(|key| *key == PressedKey::GamepadButton(button)),
+            &Trigger::GamepadAxis(axis, dir) => pressed_keys
+                .into_iter()
+                .any(|key| *key == PressedKey::GamepadAxis(axis, dir)),
             Trigger::Not(trigger) => !trigger.activated(pressed_keys),
             Trigger::Chain(op, triggers) => match op {
                 Op::And => triggers
@@ -71,6 +82,12 @@ impl ToString for Trigger {
                 &Trigger::ScanCode(scan_code) => {
                     write!(result, "s{}", scan_code.to_string()).unwrap();
                 }
+                &Trigger::GamepadButton(button) => {
+                    write!(result, "g{button}").unwrap();
+                }
+                &Trigger::GamepadAxis(axis, dir) => {
+                    write!(result, "a{}{axis}", dir.sign()).unwrap();
+                }
                 Trigger::Not(trigger) => {
                     result.push('!');
                     write_trigger(result, trigger, true);
@@ -103,6 +120,17 @@ impl ToString for Trigger {
     }
 }
 
+impl From<PressedKey> for Trigger {
+    fn from(value: PressedKey) -> Self {
+        match value {
+            PressedKey::KeyCode(key_code) => Trigger::KeyCode(key_code),
+            PressedKey::ScanCode(scan_code) => Trigger::ScanCode(scan_code),
+            PressedKey::GamepadButton(button) => Trigger::GamepadButton(button),
+            PressedKey::GamepadAxis(axis, dir) => Trigger::GamepadAxis(axis, dir),
+        }
+    }
+}
+
 impl From<Trigger> for String {
     fn from(value: Trigger) -> Self {
         value.to_string()
@@ -114,6 +142,7 @@ pub enum ParseErrorKind {
     UnexpectedCharacter,
     UnexpectedClosingParen,
     InvalidKeyScanCode,
+    InvalidAxisDirection,
     ExpectedValue,
     UnexpectedValue,
     UnexpectedUnaryOperator,
@@ -125,7 +154,8 @@ impl fmt::Display for ParseErrorKind {
         match self {
             Self::UnexpectedCharacter => f.write_str("unexpected character"),
             Self::UnexpectedClosingParen => f.write_str("unexpected closing parens"),
-            Self::InvalidKeyScanCode => f.write_str("invalid key/scan code"),
+            Self::InvalidKeyScanCode => f.write_str("invalid key/scan code or gamepad input"),
+            Self::InvalidAxisDirection => f.write_str("expected + or - before axis name"),
             Self::ExpectedValue => f.write_str("expected value"),
             Self::UnexpectedValue => f.write_str("unexpected value"),
             Self::UnexpectedUnaryOperator => f.write_str("unexpected unary operator after values"),
@@ -310,7 +340,7 @@ impl<'a> TriggerParser<'a> {
                 _ => {}
             }
 
-            if !matches!(next_char, 'v' | 's' | '(') {
+            if !matches!(next_char, 'v' | 's' | 'g' | 'a' | '(') {
                 return Err(ParseError {
                     pos: self.pos,
                     kind: ParseErrorKind::UnexpectedCharacter,
@@ -327,6 +357,16 @@ impl<'a> TriggerParser<'a> {
             let trigger = match next_char {
                 'v' => Trigger::KeyCode(self.parse_value::<KeyCode>()?),
                 's' => Trigger::ScanCode(self.parse_value::<ScanCode>()?),
+                'g' => Trigger::GamepadButton(self.parse_value::<Button>()?),
+                'a' => {
+                    let Some(dir) = self.consume_char().and_then(AxisDir::from_sign) else {
+                        return Err(ParseError {
+                            pos: self.pos,
+                            kind: ParseErrorKind::InvalidAxisDirection,
+                        });
+                    };
+                    Trigger::GamepadAxis(self.parse_value::<Axis>()?, dir)
+                }
                 '(' => {
                     self.commit();
                     self.parse_trigger(true)?
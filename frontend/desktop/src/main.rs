@@ -22,14 +22,33 @@ mod audio;
 #[cfg(feature = "debug-views")]
 mod debug_views;
 mod frame_data;
-use frame_data::FrameData;
+use frame_data::{FrameData, FrameInput};
 mod game_db;
 mod input;
+mod notifications;
+mod rom_info;
 
 mod emu;
 mod ui;
 
-fn main() {
+use std::{env, path::Path, process::ExitCode};
+
+fn main() -> ExitCode {
     emu_utils::app::setup_current_dir();
+
+    let mut args = env::args_os().skip(1);
+    if args.next().is_some_and(|arg| arg == "--inspect") {
+        let Some(rom_path) = args.next() else {
+            eprintln!("Usage: dust-desktop --inspect <ROM path>");
+            return ExitCode::FAILURE;
+        };
+        return if ui::print_rom_info(Path::new(&rom_path)) {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        };
+    }
+
     ui::main();
+    ExitCode::SUCCESS
 }
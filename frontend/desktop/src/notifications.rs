@@ -0,0 +1,86 @@
+//! A bus through which the emulation thread and other parts of the frontend can post user-facing
+//! messages about events that would otherwise go unnoticed, shown by the UI as on-screen toasts.
+
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+/// What a notification is about; newer notifications of the same kind (other than
+/// [`Kind::Other`]) replace older ones still on screen instead of piling up.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    SaveWritten,
+    SaveWriteFailed,
+    SaveSlotChanged,
+    SavestateCreated,
+    SavestateLoaded,
+    SavestateFailed,
+    SavestateSlotChanged,
+    RendererFallback,
+    ResolutionScaleChanged,
+    AudioDeviceChanged,
+    DeviceLost,
+    GamepadConnected,
+    GamepadDisconnected,
+    Other,
+}
+
+#[derive(Clone, Debug)]
+pub struct Notification {
+    pub kind: Kind,
+    pub level: Level,
+    pub message: String,
+}
+
+impl Notification {
+    pub fn new(kind: Kind, level: Level, message: impl Into<String>) -> Self {
+        Notification {
+            kind,
+            level,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Notification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+#[derive(Clone)]
+pub struct Sender(crossbeam_channel::Sender<Notification>);
+
+impl Sender {
+    /// Posts a notification; it's silently dropped if the UI isn't running anymore.
+    pub fn post(&self, notification: Notification) {
+        let _ = self.0.send(notification);
+    }
+}
+
+pub struct Bus {
+    tx: Sender,
+    rx: crossbeam_channel::Receiver<Notification>,
+}
+
+impl Bus {
+    pub fn new() -> Self {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        Bus { tx: Sender(tx), rx }
+    }
+
+    #[inline]
+    pub fn sender(&self) -> &Sender {
+        &self.tx
+    }
+
+    /// Returns all notifications posted since the last call.
+    pub fn drain(&self) -> impl Iterator<Item = Notification> + '_ {
+        self.rx.try_iter()
+    }
+}
@@ -0,0 +1,276 @@
+//! Metadata read from a DS ROM without booting it, shown by the ROM inspector and printed when
+//! running with `--inspect`.
+
+use crate::{
+    emu::ds_slot_rom::{CreationError, DsSlotRom},
+    game_db::{self, KnownIssues, Peripherals, SaveType, SaveTypeSource},
+};
+use dust_core::{
+    ds_slot::rom::{
+        header::{Header, Region, UnitCode},
+        icon_title::{IconTitle, Title},
+        Contents,
+    },
+    utils::{zeroed_box, Bytes},
+    Model,
+};
+use std::{fmt, fs, path::Path};
+
+pub struct RomInfo {
+    pub file_size: u64,
+    header_bytes: Box<Bytes<0x170>>,
+    pub icon_title: Option<Box<IconTitle>>,
+    pub db_entry: Option<game_db::Entry>,
+    /// The save type that would be used when launching the game, following the same rules as the
+    /// emulator (an existing save file of a recognized size overrides a mismatching database
+    /// entry or heuristic, but not a per-game override).
+    pub save_type: Option<(SaveType, SaveTypeSource)>,
+}
+
+impl RomInfo {
+    pub fn read(
+        path: &Path,
+        game_db: Option<&game_db::Database>,
+        save_path: Option<&Path>,
+        save_type_override: Option<SaveType>,
+    ) -> Result<Self, CreationError> {
+        // Only read the header, icon and title from the file instead of loading it into memory
+        let rom = DsSlotRom::new(path, None, 0, Model::Ds)?;
+        let file_size = fs::metadata(path)?.len();
+
+        let mut header_bytes = zeroed_box::<Bytes<0x170>>();
+        rom.read_header(&mut header_bytes);
+        let icon_title =
+            IconTitle::decode_at_offset(Header::new(&header_bytes).icon_title_offset(), &rom)
+                .ok()
+                .map(Box::new);
+
+        let db_entry = game_db.and_then(|db| db.lookup(rom.game_code()));
+        let save_len = save_path
+            .and_then(|path| fs::metadata(path).ok())
+            .map(|metadata| metadata.len() as usize);
+        let detected_save_type = save_len.and_then(SaveType::from_save_len);
+        let save_type = match (
+            game_db::expected_save_type(
+                save_type_override,
+                db_entry.as_ref(),
+                &Header::new(&header_bytes),
+            ),
+            detected_save_type,
+        ) {
+            (Some((expected_save_type, source)), Some(detected_save_type))
+                if source != SaveTypeSource::Override
+                    && expected_save_type.expected_len() != save_len =>
+            {
+                Some((detected_save_type, SaveTypeSource::SaveFile))
+            }
+            (Some(expected), _) => Some(expected),
+            (None, Some(detected_save_type)) => {
+                Some((detected_save_type, SaveTypeSource::SaveFile))
+            }
+            (None, None) => None,
+        };
+
+        Ok(RomInfo {
+            file_size,
+            header_bytes,
+            icon_title,
+            db_entry,
+            save_type,
+        })
+    }
+
+    pub fn header(&self) -> Header<'_> {
+        Header::new(&self.header_bytes)
+    }
+
+    pub fn peripherals(&self) -> Peripherals {
+        self.db_entry
+            .map(|entry| entry.peripherals)
+            .unwrap_or_default()
+    }
+
+    pub fn known_issues(&self) -> KnownIssues {
+        self.db_entry
+            .map(|entry| entry.known_issues)
+            .unwrap_or_default()
+    }
+
+    /// Returns the icon's titles in each language, skipping the ones not present in the ROM.
+    pub fn titles(&self) -> Vec<(&'static str, String)> {
+        let Some(icon_title) = &self.icon_title else {
+            return Vec::new();
+        };
+        let titles = &icon_title.titles;
+        let title = |title: &Title| title.as_deref().unwrap_or("<invalid UTF-16>").to_owned();
+        let mut result = vec![
+            ("Japanese", title(&titles.japanese)),
+            ("English", title(&titles.english)),
+            ("French", title(&titles.french)),
+            ("German", title(&titles.german)),
+            ("Italian", title(&titles.italian)),
+            ("Spanish", title(&titles.spanish)),
+        ];
+        if let Some(chinese) = &titles.chinese {
+            result.push(("Chinese", title(chinese)));
+        }
+        if let Some(korean) = &titles.korean {
+            result.push(("Korean", title(korean)));
+        }
+        result
+    }
+
+    /// Returns the header fields, save type, known peripherals and known issues as displayable
+    /// name/value pairs.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let header = self.header();
+        let mut result = vec![
+            (
+                "Game title",
+                header
+                    .game_title()
+                    .map_or_else(|| "<invalid UTF-8>".to_owned(), |s| format!("{s:?}")),
+            ),
+            ("Game code", {
+                let (code, str) = header.game_code();
+                if code == 0 {
+                    "Homebrew (0)".to_owned()
+                } else if let Some(str) = str {
+                    format!("{str:?} ({code:#010X})")
+                } else {
+                    format!("{code:#010X}")
+                }
+            }),
+            ("Maker code", {
+                let (code, str) = header.maker_code();
+                if code == 0 {
+                    "Homebrew (0)".to_owned()
+                } else if let Some(str) = str {
+                    format!("{str:?} ({code:#06X})")
+                } else {
+                    format!("{code:#06X}")
+                }
+            }),
+            (
+                "Unit code",
+                match header.unit_code() {
+                    Ok(UnitCode::Ds) => "DS".to_owned(),
+                    Ok(UnitCode::DsAndDsi) => "DS and DSi".to_owned(),
+                    Ok(UnitCode::Dsi) => "DSi".to_owned(),
+                    Err(code) => format!("Unknown ({code:#04X})"),
+                },
+            ),
+            (
+                "Region",
+                match header.region() {
+                    Ok(Region::Normal) => "Normal".to_owned(),
+                    Ok(Region::Korea) => "Korea".to_owned(),
+                    Ok(Region::China) => "China".to_owned(),
+                    Err(code) => format!("Unknown ({code:#04X})"),
+                },
+            ),
+            ("Version", format!("{:#04X}", header.version())),
+            (
+                "ROM size",
+                format!(
+                    "{} B (used: {} B, capacity: {} B)",
+                    self.file_size,
+                    header.used_rom_size(),
+                    1_u64 << (header.capacity().0 as u32 + 17).min(63)
+                ),
+            ),
+        ];
+
+        if let Some(entry) = &self.db_entry {
+            if entry.rom_size as u64 != self.file_size.next_power_of_two() {
+                result.push((
+                    "ROM size warning",
+                    format!("expected {} B by the game database", entry.rom_size),
+                ));
+            }
+        }
+
+        result.push((
+            "Save type",
+            match self.save_type {
+                Some((save_type, source)) => {
+                    format!("{} (from {})", save_type.name(), source.description())
+                }
+                None => "Unknown (not in the game database, no existing save file)".to_owned(),
+            },
+        ));
+
+        let peripherals = self.peripherals();
+        let game_code = header.game_code().0;
+        let peripheral_names = [
+            ("Microphone", peripherals.microphone),
+            ("Rumble Pak", peripherals.rumble),
+            // Infrared games are also recognizable from the first character of their game code
+            ("Infrared", peripherals.infrared || game_code as u8 == b'I'),
+            ("GBA slot", peripherals.gba_slot),
+        ]
+        .into_iter()
+        .filter_map(|(name, used)| used.then_some(name))
+        .collect::<Vec<_>>();
+        result.push((
+            "Peripherals",
+            if peripheral_names.is_empty() {
+                if self.db_entry.is_some() {
+                    "None".to_owned()
+                } else {
+                    "Unknown (not in the game database)".to_owned()
+                }
+            } else {
+                peripheral_names.join(", ")
+            },
+        ));
+
+        if self.db_entry.is_some() {
+            let known_issues = self.known_issues();
+            let issue_names = [
+                ("Graphics", known_issues.graphics),
+                ("Audio", known_issues.audio),
+                ("Crashes", known_issues.crashes),
+                ("Unplayable", known_issues.unplayable),
+            ]
+            .into_iter()
+            .filter_map(|(name, present)| present.then_some(name))
+            .collect::<Vec<_>>();
+            result.push((
+                "Known issues",
+                if issue_names.is_empty() {
+                    "None".to_owned()
+                } else {
+                    issue_names.join(", ")
+                },
+            ));
+        }
+
+        result
+    }
+}
+
+impl fmt::Display for RomInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields = self.fields();
+        let titles = self.titles();
+        let name_width = fields
+            .iter()
+            .map(|(name, _)| name.len() + 1)
+            .chain(titles.iter().map(|(language, _)| language.len() + 9))
+            .max()
+            .unwrap_or(0);
+        for (name, value) in &fields {
+            writeln!(f, "{:<name_width$}  {value}", format!("{name}:"))?;
+        }
+        for (language, title) in &titles {
+            let title = title.replace('\n', " / ");
+            writeln!(
+                f,
+                "{:<name_width$}  {title}",
+                format!("Title ({language}):")
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -1,16 +1,46 @@
 #[macro_use]
 pub mod utils;
+mod adaptive_resolution;
+use adaptive_resolution::AdaptiveResolution;
 mod config_editor;
 use config_editor::Editor as ConfigEditor;
+mod dual_slot;
+use dual_slot::Panel as DualSlotPanel;
+mod game_bundle;
+use game_bundle::GameBundleWindow;
+mod input_overlay;
+use input_overlay::InputOverlay;
+mod osd;
+use osd::Osd;
+mod perf_overlay;
+use perf_overlay::{PerfOverlay, SubsystemTimes};
+mod peripheral_info;
+use peripheral_info::Panel as PeripheralInfo;
+mod post_process;
+mod rom_inspector;
+use rom_inspector::Inspector as RomInspector;
 mod save_slot_editor;
 use save_slot_editor::Editor as SaveSlotEditor;
 mod savestate_editor;
 use savestate_editor::Editor as SavestateEditor;
+mod screen_layout_editor;
+use screen_layout_editor::Editor as ScreenLayoutEditor;
+mod screen_filter;
 mod title_menu_bar;
 use title_menu_bar::TitleMenuBarState;
+mod watch_folder;
+use watch_folder::WatchFolder;
 
+#[cfg(all(feature = "fault-injection", debug_assertions))]
+mod fault_injection;
+#[cfg(all(feature = "fault-injection", debug_assertions))]
+use fault_injection::FaultInjection;
 #[cfg(feature = "logging")]
 mod log;
+#[cfg(feature = "debug-views")]
+mod mem_transfer;
+#[cfg(feature = "debug-views")]
+use mem_transfer::MemTransfer;
 #[allow(dead_code)]
 pub mod window;
 
@@ -18,19 +48,28 @@ pub mod window;
 use crate::debug_views;
 use crate::{
     audio,
-    config::{self, Launch, Renderer2dKind, Renderer3dKind},
+    config::{
+        self, play_stats, saves, Launch, LcdColorProfile, PresentMode, Renderer2dKind,
+        Renderer3dKind, ScreenFilter,
+    },
     emu::{
         self,
         ds_slot_rom::{self, DsSlotRom},
+        hang_detection::HangKind,
+        rom_archive,
     },
     game_db, input,
-    utils::{base_dirs, Lazy},
+    notifications::{self, Notification},
+    rom_info::RomInfo,
+    utils::{base_dirs, HomePathBuf, Lazy},
     FrameData,
 };
 use dust_core::{
-    ds_slot::rom::Contents,
-    gpu::{engine_2d, engine_3d, Framebuffer, SCREEN_HEIGHT, SCREEN_WIDTH},
-    utils::zeroed_box,
+    ds_slot::rom::{header::Header, Contents},
+    gpu::{engine_2d, engine_3d, Engine2dId, Framebuffer, SCREEN_HEIGHT, SCREEN_WIDTH},
+    profiling::TimeCounter,
+    utils::{zeroed_box, Bytes},
+    Model,
 };
 use emu_utils::triple_buffer;
 #[cfg(feature = "logging")]
@@ -43,16 +82,16 @@ use std::num::NonZeroU32;
 #[cfg(feature = "discord-presence")]
 use std::time::SystemTime;
 use std::{
-    env, fs, io, panic,
+    env, fs, io, mem,
+    num::NonZeroUsize,
+    panic,
     path::{Path, PathBuf},
     slice,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
+    sync::{atomic::Ordering, Arc},
     thread,
+    time::{Duration, Instant},
 };
-use utils::{add2, scale_to_fit_rotated};
+use utils::{add2, scale_to_fit_rotated, ScreenLayout};
 
 #[cfg(feature = "xq-audio")]
 fn adjust_custom_sample_rate(sample_rate: Option<NonZeroU32>) -> Option<NonZeroU32> {
@@ -63,15 +102,55 @@ fn adjust_custom_sample_rate(sample_rate: Option<NonZeroU32>) -> Option<NonZeroU
 }
 
 enum Renderer2dData {
-    Soft,
+    Soft {
+        #[cfg(feature = "frame-dump")]
+        layer_capture: dust_soft_2d::layer_capture::LayerCapture,
+        time_counter: TimeCounter,
+        /// Whether the renderer runs on the emulation thread, in which case its time is also
+        /// included in the emulation thread's.
+        sync: bool,
+    },
     Wgpu(dust_wgpu_2d::threaded::lockstep_scanlines::FrontendChannels),
 }
 
+impl Renderer2dData {
+    fn time_counter(&self) -> TimeCounter {
+        match self {
+            Renderer2dData::Soft { time_counter, .. } => time_counter.clone(),
+            Renderer2dData::Wgpu(channels) => channels.time_counter(),
+        }
+    }
+
+    fn is_sync(&self) -> bool {
+        matches!(self, Renderer2dData::Soft { sync: true, .. })
+    }
+}
+
 enum Renderer3dData {
-    Soft,
+    Soft {
+        #[cfg(feature = "frame-dump")]
+        layer_capture: emu::soft_renderer_3d::LayerCapture,
+        time_counter: TimeCounter,
+    },
     Wgpu(dust_wgpu_3d::threaded::FrontendChannels),
 }
 
+impl Renderer3dData {
+    fn texture_cache(&self) -> Option<dust_wgpu_3d::threaded::TextureCache> {
+        match self {
+            Renderer3dData::Wgpu(channels) => Some(channels.texture_cache()),
+            _ => None,
+        }
+    }
+
+    fn time_counter(&self) -> TimeCounter {
+        match self {
+            Renderer3dData::Soft { time_counter, .. } => time_counter.clone(),
+            Renderer3dData::Wgpu(channels) => channels.time_counter(),
+        }
+    }
+}
+
 struct EmuState {
     playing: bool,
     title: String,
@@ -79,6 +158,22 @@ struct EmuState {
     save_path_update: Option<emu::SavePathUpdate>,
     #[cfg(feature = "gdb-server")]
     gdb_server_addr: Option<SocketAddr>,
+    speed_override: Option<emu::SpeedOverride>,
+    hang_popup_dismissed: bool,
+    crash: Option<Box<dust_core::emu::crash::Crash>>,
+    /// The loaded DS ROM's game code and containing directory, used to look up and find the GBA
+    /// games it recognizes for its dual-slot features.
+    ds_game_code: Option<u32>,
+    rom_dir: Option<PathBuf>,
+    /// The save type the loaded DS ROM would use without a per-game override, shown in the
+    /// settings next to it.
+    detected_save_type: Option<(game_db::SaveType, game_db::SaveTypeSource)>,
+    #[cfg(feature = "ffmpeg")]
+    recording: bool,
+    #[cfg(feature = "frame-dump")]
+    frame_dumping: bool,
+    #[cfg(feature = "wav-dump")]
+    wav_dumping: bool,
 
     thread: thread::JoinHandle<triple_buffer::Sender<FrameData>>,
 
@@ -90,6 +185,9 @@ struct EmuState {
 
     renderer_2d: Renderer2dData,
     renderer_3d: Renderer3dData,
+    adaptive_resolution: Option<AdaptiveResolution>,
+
+    play_session: play_stats::Session,
 }
 
 impl EmuState {
@@ -98,6 +196,71 @@ impl EmuState {
             .send(msg)
             .expect("couldn't send message to emulation thread");
     }
+
+    fn set_resolution_scale_shift(&self, value: u8) {
+        match &self.renderer_2d {
+            Renderer2dData::Soft { .. } => {}
+            Renderer2dData::Wgpu(channels) => {
+                channels.set_resolution_scale_shift(value);
+            }
+        }
+        match &self.renderer_3d {
+            Renderer3dData::Soft { .. } => {}
+            Renderer3dData::Wgpu(channels) => {
+                channels.set_resolution_scale_shift(value);
+            }
+        }
+    }
+
+    /// Feeds the GPU time of the latest measured 3D frame to the adaptive resolution scale
+    /// controller, if enabled, and applies any resulting change to the renderers, returning it
+    /// along with the frame time that caused it.
+    fn update_adaptive_resolution(
+        &mut self,
+        thresholds: adaptive_resolution::Thresholds,
+    ) -> Option<(adaptive_resolution::Change, Duration)> {
+        let Renderer3dData::Wgpu(channels) = &self.renderer_3d else {
+            return None;
+        };
+        let adaptive_resolution = self.adaptive_resolution.as_mut()?;
+        let frame_time = channels.take_gpu_frame_time()?;
+        let change = adaptive_resolution.update(frame_time, thresholds)?;
+        let shift = adaptive_resolution.shift();
+        self.set_resolution_scale_shift(shift);
+        Some((change, frame_time))
+    }
+
+    fn set_profiling_enabled(&self, value: bool) {
+        for counter in [
+            &self.shared_state.emulation_time,
+            &self.shared_state.audio_time,
+            &self.renderer_2d.time_counter(),
+            &self.renderer_3d.time_counter(),
+        ] {
+            counter.set_enabled(value);
+        }
+    }
+
+    /// Takes the time measured for each subsystem, excluding the time spent by the audio mixer
+    /// and any synchronous renderer from the emulation thread's time.
+    fn take_subsystem_times(&self) -> SubsystemTimes {
+        let renderer_2d = self.renderer_2d.time_counter().take();
+        let audio = self.shared_state.audio_time.take();
+        let mut emulation = self
+            .shared_state
+            .emulation_time
+            .take()
+            .saturating_sub(audio);
+        if self.renderer_2d.is_sync() {
+            emulation = emulation.saturating_sub(renderer_2d);
+        }
+        SubsystemTimes {
+            emulation,
+            renderer_2d,
+            renderer_3d: self.renderer_3d.time_counter().take(),
+            audio,
+        }
+    }
 }
 
 struct Config {
@@ -105,6 +268,8 @@ struct Config {
     config: config::Config,
     global_path: Option<PathBuf>,
     game_path: Option<PathBuf>,
+    /// The current game's play time statistics, saved along with its configuration.
+    play_stats: play_stats::PlayStats,
 }
 
 impl Config {
@@ -135,6 +300,7 @@ impl Config {
             config: config::Config::from_global(&global.contents),
             global_path: global.path,
             game_path: None,
+            play_stats: Default::default(),
         }
     }
 }
@@ -202,17 +368,41 @@ pub struct UiState {
     fb_texture: FbTexture,
     frame_tx: Option<triple_buffer::Sender<FrameData>>,
     frame_rx: triple_buffer::Receiver<FrameData>,
+    /// The 2D engines shown on the upper and lower screens in the last received frame.
+    screen_engines: [Engine2dId; 2],
 
     title_menu_bar: TitleMenuBarState,
 
     screen_focused: bool,
 
     input: input::State,
+    gamepads: input::Gamepads,
+    turbo_enabled: bool,
 
     config_editor: Option<ConfigEditor>,
+    screen_layout_editor: Option<ScreenLayoutEditor>,
+    peripheral_info: Option<(PeripheralInfo, bool)>,
+    dual_slot: Option<DualSlotPanel>,
+    watch_folder: Option<WatchFolder>,
+    rom_inspector: Option<RomInspector>,
+    game_bundle: Option<GameBundleWindow>,
+    archive_rom_picker: Option<ArchiveRomPicker>,
+
+    /// The hardware 3D renderer's pipeline creation failures during this session; once a fatal one
+    /// happens, the software 3D renderer is used instead until restarting.
+    wgpu_3d_failures: Vec<dust_wgpu_3d::PipelineFailure>,
 
     save_slot_editor: SaveSlotEditor,
     savestate_editor: SavestateEditor,
+    /// The savestate slot targeted by the savestate hotkeys.
+    savestate_slot: u8,
+    /// When the load hotkey started being held, while the preview of the slot it would load is
+    /// being shown.
+    savestate_load_preview_start: Option<Instant>,
+
+    osd: Osd,
+    input_overlay: InputOverlay,
+    perf_overlay: PerfOverlay,
 
     audio_channel: Option<audio::output::Channel>,
 
@@ -221,12 +411,28 @@ pub struct UiState {
 
     #[cfg(feature = "debug-views")]
     debug_views: debug_views::UiState,
+    #[cfg(feature = "debug-views")]
+    mem_transfer: Option<MemTransfer>,
+    #[cfg(all(feature = "fault-injection", debug_assertions))]
+    fault_injection: Option<FaultInjection>,
 
     #[cfg(feature = "discord-presence")]
     discord_presence: Option<DiscordPresence>,
 }
 
 static ALLOWED_ROM_EXTENSIONS: &[&str] = &["nds", "bin"];
+static ALLOWED_GAME_EXTENSIONS: &[&str] = &["nds", "bin", "zip", "7z"];
+
+/// An archive containing multiple ROMs, waiting for the user to choose which one to load.
+struct ArchiveRomPicker {
+    path: PathBuf,
+    kind: rom_archive::Kind,
+    names: Vec<String>,
+}
+
+/// How long the load savestate hotkey needs to be held for the previewed savestate to be loaded on
+/// release, so that accidental presses don't discard any progress.
+const SAVESTATE_LOAD_HOLD_TIME: Duration = Duration::from_millis(500);
 
 impl UiState {
     fn play_pause(&mut self) {
@@ -238,28 +444,363 @@ impl UiState {
         }
     }
 
+    fn advance_frames(&mut self, frames: u32) {
+        if let Some(emu) = &self.emu {
+            if !emu.playing && frames != 0 {
+                emu.send_message(emu::Message::AdvanceFrames(frames));
+            }
+        }
+    }
+
+    #[cfg(any(feature = "ffmpeg", feature = "frame-dump"))]
+    fn capture_layout(config: &Config) -> emu::capture::Layout {
+        match config!(config.config, capture_layout) {
+            config::CaptureLayout::MatchScreen => {
+                // Captures always contain both screens, even if the bottom one is detached
+                let screen_layout = match config.config.custom_screen_layout() {
+                    Some(layout) => ScreenLayout::custom(layout),
+                    None if config!(config.config, magnified_screen) => ScreenLayout::magnified(
+                        config!(config.config, magnified_screen_is_top),
+                        config!(config.config, small_screen_scale),
+                    ),
+                    None => ScreenLayout::new(
+                        false,
+                        config!(config.config, single_screen),
+                        config!(config.config, swap_screens),
+                        config!(config.config, screen_gap),
+                    ),
+                };
+                emu::capture::Layout::new(
+                    screen_layout.size,
+                    screen_layout.screen_rects,
+                    screen_layout.top_screen_above,
+                    emu::capture::Rotation::from_degrees(config!(config.config, screen_rot)),
+                )
+            }
+            config::CaptureLayout::RawStacked => emu::capture::Layout::raw_stacked(),
+        }
+    }
+
+    #[cfg(feature = "ffmpeg")]
+    fn toggle_recording(&mut self, config: &Config) {
+        let Some(emu) = &mut self.emu else {
+            return;
+        };
+
+        if emu.recording {
+            emu.send_message(emu::Message::StopRecording);
+            emu.recording = false;
+            return;
+        }
+
+        let container = config!(config.config, recording_container);
+        let Some(output_path) = FileDialog::new()
+            .add_filter(container.name(), &[container.extension()])
+            .set_file_name(format!("{}.{}", emu.title, container.extension()))
+            .save_file()
+        else {
+            return;
+        };
+
+        emu.send_message(emu::Message::StartRecording(emu::recording::Settings {
+            ffmpeg_path: config!(config.config, &ffmpeg_path)
+                .as_ref()
+                .map(|path| path.0.clone()),
+            output_path,
+            container,
+            video_codec: config!(config.config, recording_video_codec),
+            video_bitrate_kbps: config!(config.config, recording_video_bitrate_kbps),
+            audio_bitrate_kbps: config!(config.config, recording_audio_bitrate_kbps),
+            layout: Self::capture_layout(config),
+        }));
+        emu.recording = true;
+    }
+
+    #[cfg(feature = "frame-dump")]
+    fn toggle_frame_dump(&mut self, config: &Config) {
+        let Some(emu) = &mut self.emu else {
+            return;
+        };
+
+        if emu.frame_dumping {
+            emu.send_message(emu::Message::StopFrameDump);
+            emu.frame_dumping = false;
+            return;
+        }
+
+        let layer_3d = if config!(config.config, frame_dump_3d_layer) {
+            match &emu.renderer_3d {
+                Renderer3dData::Soft { layer_capture, .. } => Some(layer_capture.clone()),
+                Renderer3dData::Wgpu(_) => {
+                    warning!(
+                        "3D layer dumps unavailable",
+                        "The 3D layer can only be dumped when using the software 3D renderer; \
+                         only the screens will be dumped."
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // The hardware 2D renderer is rejected by the emulation thread as a whole, so there's no
+        // need to warn about its layers being unavailable here
+        let layers_2d = match &emu.renderer_2d {
+            Renderer2dData::Soft { layer_capture, .. }
+                if config!(config.config, frame_dump_2d_layers) =>
+            {
+                Some(layer_capture.clone())
+            }
+            _ => None,
+        };
+
+        emu.send_message(emu::Message::StartFrameDump(emu::frame_dump::Settings {
+            dir_path: config!(config.config, &frame_dump_dir_path).0.clone(),
+            name_template: config!(config.config, &frame_dump_name_template).clone(),
+            format: config!(config.config, frame_dump_format),
+            title: emu.title.clone(),
+            layout: Self::capture_layout(config),
+            layer_3d,
+            layers_2d,
+        }));
+        emu.frame_dumping = true;
+    }
+
+    #[cfg(feature = "wav-dump")]
+    fn toggle_wav_dump(&mut self, config: &Config) {
+        let Some(emu) = &mut self.emu else {
+            return;
+        };
+
+        if emu.wav_dumping {
+            emu.send_message(emu::Message::StopWavDump);
+            emu.wav_dumping = false;
+            return;
+        }
+
+        let Some(output_path) = FileDialog::new()
+            .add_filter("WAV file", &["wav"])
+            .set_file_name(format!("{}.wav", emu.title))
+            .save_file()
+        else {
+            return;
+        };
+
+        emu.send_message(emu::Message::StartWavDump(audio::wav_dump::Settings {
+            output_path,
+            channels: config!(config.config, wav_dump_channels),
+        }));
+        emu.wav_dumping = true;
+    }
+
     fn reset(&mut self) {
         if let Some(emu) = &mut self.emu {
             emu.send_message(emu::Message::Reset);
+            // Interrupt the current frame in case the emulated software is stuck
+            emu.shared_state.run_cancel_token.cancel();
         }
     }
 }
 
 impl UiState {
+    fn game_db(&mut self, config: &config::Config) -> Option<&game_db::Database> {
+        self.game_db
+            .get(|| {
+                config!(config, game_db_path).as_ref().and_then(|path| {
+                    match game_db::Database::read_from_file(&path.0) {
+                        Ok(db) => Some(db),
+                        Err(err) => {
+                            match err {
+                                game_db::Error::Io(err) => {
+                                    if err.kind() == io::ErrorKind::NotFound {
+                                        warning!(
+                                            "Missing game database",
+                                            "The game database was not found at `{}`.",
+                                            path.0.display()
+                                        );
+                                    } else {
+                                        config_error!(
+                                            "Couldn't read game database at `{}`: {err}",
+                                            path.0.display()
+                                        );
+                                    }
+                                }
+                                game_db::Error::Json(err) => {
+                                    config_error!(
+                                        "Couldn't load game database at `{}`: {err}",
+                                        path.0.display()
+                                    );
+                                }
+                                game_db::Error::UnsupportedVersion(version) => {
+                                    config_error!(
+                                        "The game database at `{}` uses an unsupported format \
+                                         version ({version}), it might need a newer version of \
+                                         the emulator.",
+                                        path.0.display()
+                                    );
+                                }
+                            }
+                            None
+                        }
+                    }
+                })
+            })
+            .as_ref()
+    }
+
+    fn inspect_rom(&mut self, path: &Path, config: &Config, window: &window::Window) {
+        let game_title = path.file_stem().and_then(|game_title| game_title.to_str());
+        let save_path = game_title.and_then(|game_title| config.config.save_path(game_title));
+        // Errors are ignored, as they'll be reported when the game's configuration is loaded
+        let (save_type_override, play_stats) = game_title
+            .zip(config.games_base_path.as_ref())
+            .and_then(|(game_title, base_path)| {
+                config::File::<config::GameFile>::read(
+                    &base_path.join(format!("{game_title}.json")),
+                    false,
+                )
+                .ok()
+            })
+            .map(|game_config| {
+                let contents = game_config.contents;
+                (
+                    contents.settings.save_type_override,
+                    Some(contents.play_stats).filter(|play_stats| !play_stats.is_empty()),
+                )
+            })
+            .unwrap_or_default();
+        let info = match RomInfo::read(
+            path,
+            self.game_db(&config.config),
+            save_path.as_deref(),
+            save_type_override,
+        ) {
+            Ok(info) => info,
+            Err(err) => {
+                error!(
+                    "Couldn't inspect ROM file",
+                    "Couldn't read the specified ROM file: {err}"
+                );
+                return;
+            }
+        };
+        if let Some(prev) = self.rom_inspector.replace(RomInspector::new(
+            path.to_path_buf(),
+            info,
+            play_stats,
+            window,
+        )) {
+            prev.close(window);
+        }
+    }
+
     fn load_from_rom_path(
         &mut self,
         path: &Path,
         config: &mut Config,
         window: &mut window::Window,
     ) {
+        if let Some(kind) = rom_archive::Kind::from_path(path) {
+            match rom_archive::list_roms(path, kind) {
+                Ok(mut names) => match names.len() {
+                    0 => {
+                        error!(
+                            "No ROM found",
+                            "The specified archive doesn't contain any NDS ROM files."
+                        );
+                    }
+                    1 => {
+                        let name = names.pop().unwrap();
+                        self.load_from_archive(path, kind, &name, config, window);
+                    }
+                    _ => {
+                        self.archive_rom_picker = Some(ArchiveRomPicker {
+                            path: path.to_path_buf(),
+                            kind,
+                            names,
+                        });
+                    }
+                },
+                Err(err) => {
+                    error!(
+                        "Couldn't open archive",
+                        "Couldn't open the specified archive: {err}"
+                    );
+                }
+            }
+            return;
+        }
+
         let Some(game_title) = path.file_stem().and_then(|path| path.to_str()) else {
             error!("Invalid ROM path", "Invalid ROM path provided: {path:?}");
             return;
         };
+        let patch_path = ds_slot_rom::find_patch(path);
+        self.load_game(
+            path,
+            game_title,
+            patch_path,
+            |patch_path, in_memory_max_size, model| {
+                DsSlotRom::new(path, patch_path, in_memory_max_size, model)
+            },
+            config,
+            window,
+        );
+    }
+
+    /// Loads the ROM called `name` inside the archive at `path`; the game is identified by the
+    /// ROM's own file name rather than the archive's, so that saves and per-game settings are
+    /// shared with the extracted ROM.
+    fn load_from_archive(
+        &mut self,
+        path: &Path,
+        kind: rom_archive::Kind,
+        name: &str,
+        config: &mut Config,
+        window: &mut window::Window,
+    ) {
+        let inner_path = Path::new(name);
+        let Some(game_title) = inner_path.file_stem().and_then(|path| path.to_str()) else {
+            error!(
+                "Invalid ROM path",
+                "Invalid ROM path provided: {inner_path:?}"
+            );
+            return;
+        };
+        let patch_path = inner_path
+            .file_name()
+            .and_then(|file_name| ds_slot_rom::find_patch(&path.with_file_name(file_name)));
+        self.load_game(
+            path,
+            game_title,
+            patch_path,
+            |patch_path, _, model| {
+                let contents = rom_archive::read_rom(path, kind, name)
+                    .map_err(ds_slot_rom::CreationError::Archive)?;
+                DsSlotRom::from_bytes(contents, patch_path, model)
+            },
+            config,
+            window,
+        );
+    }
 
+    fn load_game(
+        &mut self,
+        path: &Path,
+        game_title: &str,
+        patch_path: Option<PathBuf>,
+        read_rom: impl FnOnce(
+            Option<&Path>,
+            u32,
+            Model,
+        ) -> Result<DsSlotRom, ds_slot_rom::CreationError>,
+        config: &mut Config,
+        window: &mut window::Window,
+    ) {
         self.stop(config, window);
 
-        let game_config: config::File<config::Game> = config
+        let game_config: config::File<config::GameFile> = config
             .games_base_path
             .as_ref()
             .map(|base_path| {
@@ -267,7 +808,18 @@ impl UiState {
             })
             .unwrap_or_default();
 
-        config.config.deserialize_game(&game_config.contents);
+        config
+            .config
+            .deserialize_game(&game_config.contents.settings);
+
+        // A patch found next to the ROM takes precedence over the one activated from the game's
+        // watch folder
+        let patch_path = patch_path.or_else(|| {
+            config!(config.config, &watch_folder_patch_path)
+                .as_ref()
+                .map(|path| path.0.clone())
+                .filter(|path| path.is_file())
+        });
 
         match config::Launch::new(&config.config, false) {
             Ok((launch_config, warnings)) => {
@@ -275,8 +827,8 @@ impl UiState {
                     config_warning!("{}", format_list!(warnings));
                 }
 
-                let ds_slot_rom = match DsSlotRom::new(
-                    path,
+                let ds_slot_rom = match read_rom(
+                    patch_path.as_deref(),
                     config!(config.config, ds_slot_rom_in_memory_max_size),
                     launch_config.model,
                 ) {
@@ -293,10 +845,33 @@ impl UiState {
                             ds_slot_rom::CreationError::InvalidFileSize(got) => {
                                 error!("Invalid ROM file", "Invalid ROM file size: {got} B");
                             }
+                            ds_slot_rom::CreationError::Patch(err) => {
+                                error!(
+                                    "Couldn't apply ROM patch",
+                                    "Couldn't apply the patch at {}: {err}.",
+                                    patch_path.unwrap_or_default().display()
+                                );
+                            }
+                            ds_slot_rom::CreationError::Archive(err) => {
+                                error!(
+                                    "Couldn't load ROM file",
+                                    "Couldn't extract the ROM from the specified archive: {err}"
+                                );
+                            }
                         }
                         return;
                     }
                 };
+                if let Some(patch_path) = &patch_path {
+                    self.osd.post(Notification::new(
+                        notifications::Kind::Other,
+                        notifications::Level::Info,
+                        format!(
+                            "Applied patch {}",
+                            patch_path.file_name().unwrap_or_default().to_string_lossy()
+                        ),
+                    ));
+                }
 
                 self.start(
                     config,
@@ -307,6 +882,7 @@ impl UiState {
                     window,
                 );
                 config.game_path = game_config.path;
+                config.play_stats = game_config.contents.play_stats;
             }
 
             Err(errors) => {
@@ -348,7 +924,10 @@ impl UiState {
     fn create_renderers(
         window: &window::Window,
         config: &config::Config,
+        wgpu_3d_failed: bool,
         fb_texture: &mut FbTexture,
+        osd: &mut Osd,
+        #[cfg(feature = "logging")] logger: &slog::Logger,
     ) -> (
         bool,
         Box<dyn engine_2d::Renderer + Send>,
@@ -356,24 +935,94 @@ impl UiState {
         Renderer2dData,
         Renderer3dData,
     ) {
+        let mut fallback = |message: String| {
+            #[cfg(feature = "logging")]
+            slog::warn!(logger, "{}", message);
+            osd.post(Notification::new(
+                notifications::Kind::RendererFallback,
+                notifications::Level::Warning,
+                message,
+            ));
+        };
+
         let mut renderer_2d_kind = config!(config, renderer_2d_kind);
-        let renderer_3d_kind = config!(config, renderer_3d_kind);
+        let mut renderer_3d_kind = config!(config, renderer_3d_kind);
+        let mut resolution_scale_shift = config!(config, resolution_scale_shift);
+        let mut msaa_enabled = config!(config, msaa_3d);
+        let mut compute_rasterizer_enabled = config!(config, compute_rasterizer_3d);
+
+        if renderer_3d_kind == Renderer3dKind::Wgpu && wgpu_3d_failed {
+            renderer_3d_kind = Renderer3dKind::Soft;
+            fallback(
+                "The hardware 3D renderer couldn't create a pipeline it requires, falling back to \
+                 the software 3D renderer (see Config > Copy renderer diagnostics)"
+                    .to_owned(),
+            );
+        }
+
+        if renderer_3d_kind == Renderer3dKind::Wgpu {
+            match dust_wgpu_3d::check_support(window.gfx_adapter(), window.gfx_device()) {
+                Ok(support) => {
+                    if msaa_enabled && !support.msaa {
+                        msaa_enabled = false;
+                        fallback(
+                            "The graphics device doesn't support 4x multisampling, disabling 3D \
+                             multisampling"
+                                .to_owned(),
+                        );
+                    }
+                    if compute_rasterizer_enabled && !support.compute_rasterizer {
+                        compute_rasterizer_enabled = false;
+                        fallback(
+                            "The graphics device doesn't support the compute shader 3D \
+                             rasterizer, disabling it"
+                                .to_owned(),
+                        );
+                    }
+                }
+                Err(err) => {
+                    renderer_3d_kind = Renderer3dKind::Soft;
+                    fallback(format!(
+                        "The hardware 3D renderer can't be used ({err}), falling back to the \
+                         software 3D renderer"
+                    ));
+                }
+            }
+        }
+
         if renderer_3d_kind == Renderer3dKind::Wgpu {
             renderer_2d_kind = Renderer2dKind::WgpuLockstepScanlines;
         }
 
-        let resolution_scale_shift = config!(config, resolution_scale_shift);
+        if renderer_2d_kind == Renderer2dKind::WgpuLockstepScanlines {
+            let max_resolution_scale_shift = max_resolution_scale_shift(window);
+            if resolution_scale_shift > max_resolution_scale_shift {
+                fallback(format!(
+                    "The graphics device's maximum texture size is too small for a {}x resolution \
+                     scale, reducing it to {}x",
+                    1 << resolution_scale_shift,
+                    1 << max_resolution_scale_shift,
+                ));
+                resolution_scale_shift = max_resolution_scale_shift;
+            }
+        }
 
         let (renderer_2d, renderer_3d_tx, renderer_2d_data, renderer_3d_data) = {
             match renderer_2d_kind {
                 Renderer2dKind::WgpuLockstepScanlines => {
                     let (tx_3d, rx_3d_2d_data, renderer_3d_data) = match renderer_3d_kind {
                         Renderer3dKind::Soft => {
-                            let (tx_3d, rx_3d) = emu::soft_renderer_3d::init();
+                            let (tx_3d, rx_3d) =
+                                emu::soft_renderer_3d::init(soft_renderer_3d_threads(config));
+                            let renderer_3d_data = Renderer3dData::Soft {
+                                #[cfg(feature = "frame-dump")]
+                                layer_capture: rx_3d.layer_capture(),
+                                time_counter: tx_3d.time_counter(),
+                            };
                             (
                                 Box::new(tx_3d) as Box<dyn engine_3d::RendererTx + Send>,
                                 dust_wgpu_2d::Renderer3dRx::Soft(Box::new(rx_3d)),
-                                Renderer3dData::Soft,
+                                renderer_3d_data,
                             )
                         }
 
@@ -383,7 +1032,15 @@ impl UiState {
                                     Arc::clone(window.gfx_device()),
                                     Arc::clone(window.gfx_queue()),
                                     resolution_scale_shift,
+                                    texture_filtering(config),
+                                    msaa_enabled,
                                 );
+                            renderer_3d_channels
+                                .set_compute_rasterizer_enabled(compute_rasterizer_enabled);
+                            renderer_3d_channels
+                                .set_texture_cache_mode(config!(config, texture_cache_mode).into());
+                            renderer_3d_channels.set_texture_dump_dir(texture_dump_dir(config));
+                            renderer_3d_channels.set_texture_pack_dir(texture_pack_dir(config));
                             (
                                 Box::new(tx_3d) as Box<dyn engine_3d::RendererTx + Send>,
                                 dust_wgpu_2d::Renderer3dRx::Accel {
@@ -397,14 +1054,15 @@ impl UiState {
                         }
                     };
 
-                    let (renderer_2d, color_output_view, renderer_2d_data) =
+                    let (renderer_2d, color_output_texture, renderer_2d_data) =
                         dust_wgpu_2d::threaded::lockstep_scanlines::Renderer::new(
                             Arc::clone(window.gfx_device()),
                             Arc::clone(window.gfx_queue()),
                             resolution_scale_shift,
+                            config!(config, hi_res_2d_bgs),
                             rx_3d_2d_data,
                         );
-                    fb_texture.set_view(window, color_output_view);
+                    fb_texture.set_view(window, color_output_texture);
 
                     (
                         Box::new(renderer_2d) as Box<dyn engine_2d::Renderer + Send>,
@@ -415,14 +1073,26 @@ impl UiState {
                 }
 
                 _ => {
-                    let (tx_3d, rx_3d) = emu::soft_renderer_3d::init();
+                    let (tx_3d, rx_3d) =
+                        emu::soft_renderer_3d::init(soft_renderer_3d_threads(config));
+                    let renderer_3d_data = Renderer3dData::Soft {
+                        #[cfg(feature = "frame-dump")]
+                        layer_capture: rx_3d.layer_capture(),
+                        time_counter: tx_3d.time_counter(),
+                    };
 
                     let (renderer_2d, renderer_2d_data) = match renderer_2d_kind {
                         Renderer2dKind::SoftSync => {
                             let renderer_2d = dust_soft_2d::sync::Renderer::new(Box::new(rx_3d));
+                            let renderer_2d_data = Renderer2dData::Soft {
+                                #[cfg(feature = "frame-dump")]
+                                layer_capture: renderer_2d.layer_capture(),
+                                time_counter: renderer_2d.time_counter(),
+                                sync: true,
+                            };
                             (
                                 Box::new(renderer_2d) as Box<dyn engine_2d::Renderer + Send>,
-                                Renderer2dData::Soft,
+                                renderer_2d_data,
                             )
                         }
 
@@ -431,9 +1101,15 @@ impl UiState {
                                 dust_soft_2d::threaded::lockstep_scanlines::Renderer::new(
                                     Box::new(rx_3d),
                                 );
+                            let renderer_2d_data = Renderer2dData::Soft {
+                                #[cfg(feature = "frame-dump")]
+                                layer_capture: renderer_2d.layer_capture(),
+                                time_counter: renderer_2d.time_counter(),
+                                sync: false,
+                            };
                             (
                                 Box::new(renderer_2d) as Box<dyn engine_2d::Renderer + Send>,
-                                Renderer2dData::Soft,
+                                renderer_2d_data,
                             )
                         }
 
@@ -445,7 +1121,7 @@ impl UiState {
                         renderer_2d,
                         Box::new(tx_3d) as Box<dyn engine_3d::RendererTx + Send>,
                         renderer_2d_data,
-                        Renderer3dData::Soft,
+                        renderer_3d_data,
                     )
                 }
             }
@@ -474,7 +1150,7 @@ impl UiState {
             presence.start(&title);
         }
 
-        let playing = !config!(config.config, pause_on_launch);
+        let mut playing = !config!(config.config, pause_on_launch);
         let game_loaded = ds_slot_rom.is_some();
 
         self.savestate_editor.update_game(
@@ -495,69 +1171,62 @@ impl UiState {
             window,
         );
 
+        let mut peripherals = game_db::Peripherals::default();
+        let mut ds_game_code = None;
+        let mut detected_save_type = None;
+
         #[allow(unused_mut, clippy::bind_instead_of_map)]
         let ds_slot = ds_slot_rom.and_then(|mut rom| {
             let game_code = rom.game_code();
-
-            let save_type = self
-                .game_db
-                .get(|| {
-                    config!(config.config, game_db_path)
-                        .as_ref()
-                        .and_then(|path| match game_db::Database::read_from_file(&path.0) {
-                            Ok(db) => Some(db),
-                            Err(err) => {
-                                match err {
-                                    game_db::Error::Io(err) => {
-                                        if err.kind() == io::ErrorKind::NotFound {
-                                            warning!(
-                                                "Missing game database",
-                                                "The game database was not found at `{}`.",
-                                                path.0.display()
-                                            );
-                                        } else {
-                                            config_error!(
-                                                "Couldn't read game database at `{}`: {err}",
-                                                path.0.display()
-                                            );
-                                        }
-                                    }
-                                    game_db::Error::Json(err) => {
-                                        config_error!(
-                                            "Couldn't load game database at `{}`: {err}",
-                                            path.0.display()
-                                        );
-                                    }
-                                }
-                                None
-                            }
-                        })
-                })
-                .as_ref()
-                .and_then(|db| db.lookup(game_code))
-                .map(|entry| {
-                    if entry.rom_size as u64 != rom.len() {
-                        warning!(
-                            "Unexpected ROM size",
-                            "Unexpected ROM size: expected {} B, got {} B",
-                            entry.rom_size,
-                            rom.len()
-                        );
-                    }
-                    entry.save_type
-                });
+            ds_game_code = Some(game_code);
+
+            let entry = self
+                .game_db(&config.config)
+                .and_then(|db| db.lookup(game_code));
+            if let Some(entry) = entry {
+                if entry.rom_size as u64 != rom.len() {
+                    warning!(
+                        "Unexpected ROM size",
+                        "Unexpected ROM size: expected {} B, got {} B",
+                        entry.rom_size,
+                        rom.len()
+                    );
+                }
+                peripherals = entry.peripherals;
+            }
+            let mut header_bytes = zeroed_box::<Bytes<0x170>>();
+            rom.read_header(&mut header_bytes);
+            detected_save_type =
+                game_db::expected_save_type(None, entry.as_ref(), &Header::new(&header_bytes));
             Some(emu::DsSlot {
                 rom,
-                save_type,
-                has_ir: game_code as u8 == b'I',
+                save_type: config!(config.config, save_type_override)
+                    .map(|save_type| (save_type, game_db::SaveTypeSource::Override))
+                    .or(detected_save_type),
+                has_ir: peripherals.infrared || game_code as u8 == b'I',
             })
         });
 
+        let gba_slot = config!(config.config, &gba_slot_rom_path)
+            .as_ref()
+            .and_then(|path| emu::load_gba_slot(&path.0));
+
         let frame_tx = self
             .frame_tx
             .take()
             .expect("expected frame_tx to be Some while the emulator is stopped");
 
+        // Buffer settings can only be applied by recreating the output channel, which can't be done
+        // while the emulator is using it
+        let buffer_len = config!(config.config, audio_output_buffer_len) as usize;
+        let latency_ms = config!(config.config, audio_output_latency_ms);
+        if self.audio_channel.as_ref().map_or(false, |channel| {
+            !channel.matches_buffer_config(buffer_len, latency_ms)
+        }) {
+            self.audio_channel = None;
+            self.audio_channel = create_audio_channel(&config.config);
+        }
+
         // TODO: False positive
         #[allow(clippy::useless_asref)]
         let audio_tx_data = self
@@ -577,22 +1246,77 @@ impl UiState {
             (None, None)
         };
 
+        // Only flash save chips are emulated with an IR transceiver attached
+        let ds_slot_has_ir = ds_slot.as_ref().is_some_and(|ds_slot| {
+            ds_slot.has_ir
+                && matches!(
+                    ds_slot.save_type,
+                    Some((
+                        game_db::SaveType::Flash2m
+                            | game_db::SaveType::Flash4m
+                            | game_db::SaveType::Flash8m,
+                        _
+                    ))
+                )
+        });
+        self.peripheral_info = PeripheralInfo::new(
+            title.clone(),
+            peripherals,
+            mic_input_stream.is_some(),
+            ds_slot_has_ir,
+            gba_slot.is_some(),
+            ds_game_code.is_some_and(|code| !game_db::dual_slot_gba_titles(code).is_empty()),
+        )
+        .map(|panel| {
+            // Keep the game paused until the user has acknowledged the panel
+            let resume_on_close = playing;
+            playing = false;
+            (panel, resume_on_close)
+        });
+
+        self.watch_folder = ds_game_code.map(|code| {
+            WatchFolder::new(
+                title.clone(),
+                watch_folder::dir_path(&config!(config.config, &watch_folder_dir_path).0, code),
+                config!(config.config, &watch_folder_patch_path)
+                    .as_ref()
+                    .map(|path| path.0.clone()),
+            )
+        });
+        if let Some(watch_folder) = &self.watch_folder {
+            let inactive_patches = watch_folder.inactive_patches();
+            if inactive_patches != 0 {
+                self.osd.post(Notification::new(
+                    notifications::Kind::Other,
+                    notifications::Level::Info,
+                    format!(
+                        "{inactive_patches} inactive patch{} available in the watch folder",
+                        if inactive_patches == 1 { "" } else { "es" },
+                    ),
+                ));
+            }
+        }
+
         let (to_emu, from_ui) = crossbeam_channel::unbounded::<emu::Message>();
         let (to_ui, from_emu) = crossbeam_channel::unbounded::<emu::Notification>();
 
-        let shared_state = Arc::new(emu::SharedState {
-            playing: AtomicBool::new(playing),
-
-            #[cfg(feature = "gdb-server")]
-            gdb_server_active: AtomicBool::new(false),
-        });
+        let shared_state = Arc::new(emu::SharedState::new(playing));
 
         let (renderer_2d_is_accel, renderer_2d, renderer_3d_tx, renderer_2d_data, renderer_3d_data) =
-            Self::create_renderers(window, &config.config, &mut self.fb_texture);
+            Self::create_renderers(
+                window,
+                &config.config,
+                self.wgpu_3d_failed(),
+                &mut self.fb_texture,
+                &mut self.osd,
+                #[cfg(feature = "logging")]
+                &logger,
+            );
 
         let launch_data = emu::LaunchData {
             sys_files: launch_config.sys_files,
             ds_slot,
+            gba_slot,
             #[cfg(feature = "dldi")]
             dldi: ds_slot_rom_path.and_then(|rom_path| {
                 Some(emu::Dldi {
@@ -603,6 +1327,8 @@ impl UiState {
 
             model: launch_config.model,
             skip_firmware: launch_config.skip_firmware,
+            batch_duration: config!(config.config, batch_duration).max(1),
+            threaded_3d_geometry: config!(config.config, threaded_3d_geometry),
 
             save_path,
             save_interval_ms: config!(config.config, save_interval_ms),
@@ -610,6 +1336,7 @@ impl UiState {
             shared_state: Arc::clone(&shared_state),
             from_ui,
             to_ui,
+            notifications: self.osd.sender().clone(),
 
             audio_tx_data,
             mic_rx,
@@ -622,17 +1349,31 @@ impl UiState {
             paused_framerate_limit: config!(config.config, paused_framerate_limit),
 
             sync_to_audio: config!(config.config, sync_to_audio),
+            sub_frame_input: config!(config.config, sub_frame_input),
+            touch_pressure: config!(config.config, touch_pressure),
+            solar_sensor_level: config!(config.config, solar_sensor_level),
+            return_to_menu_on_shutdown: config!(config.config, return_to_menu_on_shutdown),
             audio_sample_chunk_size: config!(config.config, audio_sample_chunk_size),
+            audio_stereo_mode: config!(config.config, audio_stereo_mode),
             #[cfg(feature = "xq-audio")]
             audio_custom_sample_rate: config!(config.config, audio_custom_sample_rate),
             #[cfg(feature = "xq-audio")]
             audio_channel_interp_method: config!(config.config, audio_channel_interp_method),
 
             rtc_time_offset_seconds: config!(config.config, rtc_time_offset_seconds),
+            rtc_frozen_date_time: config!(config.config, rtc_frozen_date_time),
+            ir_link_addrs: config!(config.config, ir_link_enabled).then(|| {
+                (
+                    config!(config.config, ir_link_local_addr),
+                    config!(config.config, ir_link_peer_addr),
+                )
+            }),
+            crash_screen_enabled: config!(config.config, crash_screen_enabled),
 
             renderer_2d_is_accel,
             renderer_2d,
             renderer_3d_tx,
+            texture_cache: renderer_3d_data.texture_cache(),
 
             #[cfg(feature = "logging")]
             logger,
@@ -651,6 +1392,20 @@ impl UiState {
             title,
             game_loaded,
             save_path_update: None,
+            speed_override: None,
+            hang_popup_dismissed: false,
+            crash: None,
+            ds_game_code,
+            detected_save_type,
+            rom_dir: ds_slot_rom_path
+                .and_then(Path::parent)
+                .map(Path::to_path_buf),
+            #[cfg(feature = "ffmpeg")]
+            recording: false,
+            #[cfg(feature = "frame-dump")]
+            frame_dumping: false,
+            #[cfg(feature = "wav-dump")]
+            wav_dumping: false,
             #[cfg(feature = "gdb-server")]
             gdb_server_addr: None,
 
@@ -664,7 +1419,12 @@ impl UiState {
 
             renderer_2d: renderer_2d_data,
             renderer_3d: renderer_3d_data,
+            adaptive_resolution: adaptive_resolution(&config.config, window),
+
+            play_session: play_stats::Session::new(),
         });
+
+        self.savestate_editor.offer_crash_recovery(&self.emu);
     }
 
     fn stop_emu(&mut self, config: &mut Config, _window: &mut window::Window) {
@@ -673,11 +1433,21 @@ impl UiState {
             self.debug_views.emu_stopped(_window, &emu.to_emu);
 
             emu.send_message(emu::Message::Stop);
+            emu.shared_state.run_cancel_token.cancel();
             self.frame_tx = Some(emu.thread.join().expect("couldn't join emulation thread"));
 
             if let Some(path) = config.game_path.take() {
+                let mut play_stats = mem::take(&mut config.play_stats);
+                if config!(config.config, play_stats_enabled) {
+                    let mut play_session = emu.play_session;
+                    play_session.update(emu.playing);
+                    play_stats.record(&play_session);
+                }
                 let game_config = config::File {
-                    contents: config.config.serialize_game(),
+                    contents: config::GameFile {
+                        settings: config.config.serialize_game(),
+                        play_stats,
+                    },
                     path: Some(path),
                 };
                 if let Err(err) = game_config.write() {
@@ -690,8 +1460,31 @@ impl UiState {
         }
     }
 
+    fn open_dual_slot(&mut self, config: &Config) {
+        let Some(emu) = &self.emu else {
+            return;
+        };
+        let Some(ds_game_code) = emu.ds_game_code else {
+            return;
+        };
+        self.dual_slot = DualSlotPanel::new(
+            emu.title.clone(),
+            ds_game_code,
+            emu.rom_dir.as_deref(),
+            config!(config.config, &gba_slot_rom_path)
+                .as_ref()
+                .map(|path| path.0.as_path()),
+        );
+    }
+
     fn stop(&mut self, config: &mut Config, window: &mut window::Window) {
         self.stop_emu(config, window);
+        self.peripheral_info = None;
+        self.dual_slot = None;
+        self.watch_folder = None;
+        // TODO: Also drive this from the emulated Rumble Pak once GBA slot accessories are
+        // supported; for now, this only makes sure no rumble outlives the game.
+        self.gamepads.set_rumble(false);
 
         self.savestate_editor
             .update_game(window, &config.config, None);
@@ -715,6 +1508,7 @@ impl UiState {
                         fb.fill(0);
                     }
                     data.fps = 0.0;
+                    data.frame_count = 0;
                     #[cfg(feature = "debug-views")]
                     data.debug.clear();
                 }
@@ -730,102 +1524,665 @@ impl UiState {
 
         self.fb_texture.set_owned(window);
         self.fb_texture.clear(window);
+        let (gfx_device, gfx_queue) = (
+            Arc::clone(window.gfx_device()),
+            Arc::clone(window.gfx_queue()),
+        );
+        if let Some(screen_window) = window.screen_window_mut() {
+            screen_window.set_source_texture(&gfx_device, None);
+            screen_window.clear(&gfx_queue);
+        }
     }
 
-    fn playing(&self) -> bool {
-        self.emu.as_ref().map_or(false, |emu| emu.playing)
+    fn wgpu_3d_failed(&self) -> bool {
+        self.wgpu_3d_failures.iter().any(|failure| failure.fatal)
     }
-}
 
-struct FbTexture {
-    id: imgui::TextureId,
-    is_view: bool,
-}
+    fn post_savestate_slot_notification(&mut self, kind: notifications::Kind, message: String) {
+        self.osd
+            .post(Notification::new(kind, notifications::Level::Info, message));
+    }
 
-impl FbTexture {
-    fn create_owned(window: &window::Window) -> imgui::TextureId {
-        window.imgui_gfx.create_and_add_owned_texture(
-            Some("Framebuffer".into()),
-            imgui_wgpu::TextureDescriptor {
+    fn handle_savestate_action(
+        &mut self,
+        action: input::Action,
+        config: &Config,
+        window: &window::Window,
+    ) {
+        if self.emu.is_none() {
+            return;
+        }
+        let slot_name = savestate_editor::slot_name(self.savestate_slot);
+        match action {
+            input::Action::SaveStateSlot => {
+                if !self.savestate_editor.create_slot(
+                    self.savestate_slot,
+                    window,
+                    &config.config,
+                    &self.emu,
+                ) {
+                    self.post_savestate_slot_notification(
+                        notifications::Kind::SavestateFailed,
+                        "Savestates are only available while playing a game".to_owned(),
+                    );
+                }
+            }
+
+            input::Action::LoadStateSlot => {
+                if self
+                    .savestate_editor
+                    .slot_preview(self.savestate_slot)
+                    .is_some()
+                {
+                    self.savestate_load_preview_start = Some(Instant::now());
+                } else {
+                    self.post_savestate_slot_notification(
+                        notifications::Kind::SavestateFailed,
+                        format!("{slot_name} is empty"),
+                    );
+                }
+            }
+
+            input::Action::NextStateSlot | input::Action::PrevStateSlot => {
+                self.savestate_slot = if action == input::Action::NextStateSlot {
+                    (self.savestate_slot + 1) % savestate_editor::SLOTS
+                } else {
+                    (self.savestate_slot + savestate_editor::SLOTS - 1) % savestate_editor::SLOTS
+                };
+                let slot_name = savestate_editor::slot_name(self.savestate_slot);
+                if self
+                    .savestate_editor
+                    .slot_preview(self.savestate_slot)
+                    .is_some()
+                {
+                    self.post_savestate_slot_notification(
+                        notifications::Kind::SavestateSlotChanged,
+                        format!("Selected savestate {slot_name}"),
+                    );
+                } else {
+                    // The previewed slot can't be loaded anymore
+                    self.savestate_load_preview_start = None;
+                    self.post_savestate_slot_notification(
+                        notifications::Kind::SavestateSlotChanged,
+                        format!("Selected savestate {slot_name} (empty)"),
+                    );
+                }
+            }
+
+            input::Action::CancelStateLoad => {
+                if self.savestate_load_preview_start.take().is_some() {
+                    self.post_savestate_slot_notification(
+                        notifications::Kind::SavestateLoaded,
+                        "Savestate load cancelled".to_owned(),
+                    );
+                }
+            }
+
+            _ => unreachable!(),
+        }
+    }
+
+    fn handle_save_slot_action(&mut self, action: input::Action, config: &mut Config) {
+        let Some(emu) = &mut self.emu else {
+            return;
+        };
+        if !emu.game_loaded {
+            return;
+        }
+        let forward = action == input::Action::NextSaveSlot;
+        let switched = save_slot_editor::switch_slot(&mut config.config, emu, |path_config| {
+            path_config.switch_to_adjacent_slot(forward)
+        });
+        let path_config = config!(config.config, &save_path_config).as_ref();
+        let (level, message) = if switched {
+            let slot_name = path_config
+                .and_then(saves::PathConfig::current_slot_name)
+                .unwrap_or_default();
+            (
+                notifications::Level::Info,
+                format!("Switched to save slot {slot_name}"),
+            )
+        } else if path_config
+            .is_some_and(|path_config| path_config.slots.kind() == saves::SlotsKind::Multiple)
+        {
+            (
+                notifications::Level::Info,
+                "There are no other save slots to switch to".to_owned(),
+            )
+        } else {
+            (
+                notifications::Level::Warning,
+                "Multiple save slots aren't enabled for this game".to_owned(),
+            )
+        };
+        self.osd.post(Notification::new(
+            notifications::Kind::SaveSlotChanged,
+            level,
+            message,
+        ));
+    }
+
+    /// Loads the previewed savestate once the load hotkey is released, if it was held long
+    /// enough.
+    fn update_savestate_load_preview(&mut self) {
+        let Some(start) = self.savestate_load_preview_start else {
+            return;
+        };
+        if self.emu.is_none() {
+            self.savestate_load_preview_start = None;
+            return;
+        }
+        if self.input.hotkey_held(input::Action::LoadStateSlot) {
+            return;
+        }
+        self.savestate_load_preview_start = None;
+        if start.elapsed() < SAVESTATE_LOAD_HOLD_TIME {
+            self.post_savestate_slot_notification(
+                notifications::Kind::SavestateLoaded,
+                "Hold the load savestate hotkey to load the previewed savestate".to_owned(),
+            );
+        } else {
+            self.savestate_editor
+                .load_slot(self.savestate_slot, &self.emu);
+        }
+    }
+
+    fn draw_savestate_load_preview(&self, ui: &imgui::Ui, config: &Config) {
+        let Some(start) = self.savestate_load_preview_start else {
+            return;
+        };
+        let Some(texture_id) = self.savestate_editor.slot_preview(self.savestate_slot) else {
+            return;
+        };
+
+        let display_size = ui.io().display_size;
+        let image_height = display_size[1] * 0.5;
+        let image_width = image_height * SCREEN_WIDTH as f32 / (SCREEN_HEIGHT * 2) as f32;
+        ui.window("##savestate_load_preview")
+            .position(
+                [display_size[0] * 0.5, display_size[1] * 0.5],
+                imgui::Condition::Always,
+            )
+            .position_pivot([0.5, 0.5])
+            .bg_alpha(0.85)
+            .no_decoration()
+            .always_auto_resize(true)
+            .movable(false)
+            .focus_on_appearing(false)
+            .no_nav()
+            .build(|| {
+                ui.text(format!(
+                    "Load savestate {}?",
+                    savestate_editor::slot_name(self.savestate_slot)
+                ));
+                imgui::Image::new(texture_id, [image_width, image_height]).build(ui);
+                if start.elapsed() < SAVESTATE_LOAD_HOLD_TIME {
+                    ui.text_disabled("Keep holding to load on release");
+                } else {
+                    ui.text("Release to load");
+                }
+                if let Some(Some(trigger)) = config!(config.config, &input_map)
+                    .hotkeys
+                    .get(&input::Action::CancelStateLoad)
+                {
+                    ui.text_disabled(format!("Press {trigger} to cancel"));
+                }
+            });
+    }
+
+    fn draw_archive_rom_picker(
+        &mut self,
+        ui: &imgui::Ui,
+        config: &mut Config,
+        window: &mut window::Window,
+    ) {
+        let Some(picker) = &self.archive_rom_picker else {
+            return;
+        };
+
+        const POPUP_ID: &str = "Choose ROM";
+        if !ui.is_popup_open(POPUP_ID) {
+            ui.open_popup(POPUP_ID);
+        }
+        let mut chosen = None;
+        let mut close = false;
+        ui.modal_popup_config(POPUP_ID)
+            .always_auto_resize(true)
+            .build(|| {
+                ui.text("The archive contains multiple ROMs; choose the one to load:");
+                for (i, name) in picker.names.iter().enumerate() {
+                    if ui.selectable(name) {
+                        chosen = Some(i);
+                    }
+                }
+                ui.separator();
+                if ui.button("Cancel") {
+                    close = true;
+                }
+                if chosen.is_some() || close {
+                    ui.close_current_popup();
+                }
+            });
+
+        if chosen.is_some() || close {
+            let picker = self.archive_rom_picker.take().unwrap();
+            if let Some(i) = chosen {
+                self.load_from_archive(&picker.path, picker.kind, &picker.names[i], config, window);
+            }
+        }
+    }
+
+    #[allow(unused_variables)]
+    fn draw_crash_popup(&mut self, ui: &imgui::Ui, window: &mut window::Window) {
+        let Some(emu) = &mut self.emu else {
+            return;
+        };
+        let Some(crash) = &emu.crash else {
+            return;
+        };
+
+        const POPUP_ID: &str = "Guru meditation";
+        if !ui.is_popup_open(POPUP_ID) {
+            ui.open_popup(POPUP_ID);
+        }
+        let report = crash_report(crash);
+        #[cfg(feature = "debug-views")]
+        let is_arm9 = crash.core == dust_core::cpu::Core::Arm9;
+        let mut close = false;
+        let mut reset = false;
+        #[cfg(feature = "debug-views")]
+        let mut debug = false;
+        ui.modal_popup_config(POPUP_ID)
+            .always_auto_resize(true)
+            .build(|| {
+                ui.text(
+                    "A CPU hit an exception the game doesn't handle; emulation has been paused.",
+                );
+                ui.separator();
+                ui.text(&report);
+                ui.separator();
+                if ui.button("Reset") {
+                    reset = true;
+                    close = true;
+                }
+                #[cfg(feature = "debug-views")]
+                if is_arm9 {
+                    ui.same_line();
+                    if ui.button("Debug") {
+                        debug = true;
+                        close = true;
+                    }
+                }
+                ui.same_line();
+                if ui.button("Copy") {
+                    ui.set_clipboard_text(&report);
+                }
+                ui.same_line();
+                if ui.button("Close") {
+                    close = true;
+                }
+                if close {
+                    ui.close_current_popup();
+                }
+            });
+
+        if close {
+            emu.crash = None;
+        }
+        if reset {
+            self.reset();
+            self.play_pause();
+        }
+        #[cfg(feature = "debug-views")]
+        if debug {
+            let emu = self.emu.as_ref().unwrap();
+            self.debug_views.open_arm9_disasm(window, &emu.to_emu);
+        }
+    }
+
+    fn playing(&self) -> bool {
+        self.emu.as_ref().map_or(false, |emu| emu.playing)
+    }
+
+    #[allow(unused_variables)]
+    fn draw_hang_popup(&mut self, ui: &imgui::Ui, config: &Config, window: &mut window::Window) {
+        let Some(emu) = &mut self.emu else {
+            return;
+        };
+
+        let (timeout_enabled, timeout_secs) = config!(config.config, hang_timeout_secs);
+        let hang = emu
+            .shared_state
+            .hang_state
+            .hung_frames()
+            .map(|(kind, frames)| (kind, frames as f32 / audio::ORIG_FRAME_RATE as f32));
+        let is_hung = timeout_enabled
+            && emu.playing
+            && hang.map_or(false, |(_, hung_secs)| hung_secs >= timeout_secs);
+        if !is_hung {
+            emu.hang_popup_dismissed = false;
+            return;
+        }
+        if emu.hang_popup_dismissed {
+            return;
+        }
+
+        const POPUP_ID: &str = "Game appears hung";
+        if !ui.is_popup_open(POPUP_ID) {
+            ui.open_popup(POPUP_ID);
+        }
+        let mut reset = false;
+        #[cfg(feature = "debug-views")]
+        let mut debug = false;
+        ui.modal_popup_config(POPUP_ID)
+            .always_auto_resize(true)
+            .build(|| {
+                let (kind, hung_secs) = hang.unwrap_or((HangKind::StalledPc, 0.0));
+                ui.text(match kind {
+                    HangKind::StalledPc => format!(
+                        "The ARM9 has been stuck in the same loop without handling VBlank \
+                         interrupts for {hung_secs:.0} seconds of emulated time; the game might \
+                         have hung."
+                    ),
+                    HangKind::UnacknowledgedVBlank => format!(
+                        "The game hasn't handled VBlank interrupts for {hung_secs:.0} seconds of \
+                         emulated time; it might have hung."
+                    ),
+                });
+                if ui.button("Reset") {
+                    reset = true;
+                    ui.close_current_popup();
+                }
+                #[cfg(feature = "debug-views")]
+                {
+                    ui.same_line();
+                    if ui.button("Pause and debug") {
+                        debug = true;
+                        ui.close_current_popup();
+                    }
+                }
+                ui.same_line();
+                if ui.button("Keep waiting") {
+                    emu.hang_popup_dismissed = true;
+                    ui.close_current_popup();
+                }
+            });
+
+        if reset {
+            self.reset();
+        }
+        #[cfg(feature = "debug-views")]
+        if debug {
+            self.play_pause();
+            let emu = self.emu.as_mut().unwrap();
+            emu.hang_popup_dismissed = true;
+            emu.shared_state.run_cancel_token.cancel();
+            self.debug_views.open_arm9_disasm(window, &emu.to_emu);
+        }
+    }
+}
+
+fn crash_report(crash: &dust_core::emu::crash::Crash) -> String {
+    use dust_core::{cpu::Core, emu::crash::Exception};
+    use std::fmt::Write;
+
+    let mut report = format!(
+        "{} {} @ {:#010X} ({} state)",
+        match crash.core {
+            Core::Arm7 => "ARM7",
+            Core::Arm9 => "ARM9",
+        },
+        match crash.exception {
+            Exception::UndefinedInstr => "undefined instruction".to_owned(),
+            Exception::PrefetchAbort => "prefetch abort".to_owned(),
+            Exception::DataAbort { addr } => format!("data abort accessing {addr:#010X}"),
+            Exception::UnsupportedHleSwi { number } => {
+                format!("unsupported HLE BIOS SWI {number:#04X}")
+            }
+        },
+        crash.instr_addr,
+        if crash.thumb { "Thumb" } else { "ARM" },
+    );
+    for (i, value) in crash.regs.gprs.iter().enumerate() {
+        let _ = write!(
+            report,
+            "{}{:<4} {value:#010X}",
+            if i % 4 == 0 { "\n" } else { "  " },
+            format!("r{i}:"),
+        );
+    }
+    let _ = write!(report, "\nCPSR: {:#010X}", crash.cpsr.raw());
+    if let Exception::UnsupportedHleSwi { .. } = crash.exception {
+        report.push_str(
+            "\n\nThis SWI reads its data through callbacks only the original BIOS can call; set \
+             the BIOS paths and disable \"Prefer HLE BIOS\" to run this game.",
+        );
+    }
+    report
+}
+
+struct FbTexture {
+    id: imgui::TextureId,
+    is_view: bool,
+    source_texture: Option<Arc<wgpu::Texture>>,
+    filter: ScreenFilter,
+    color_profile: LcdColorProfile,
+    post_process_shader: Option<post_process::Shader>,
+    filter_pass: Option<screen_filter::Pass>,
+}
+
+impl FbTexture {
+    fn create_owned(window: &window::Window, filter: ScreenFilter) -> imgui::TextureId {
+        window.imgui_gfx.create_and_add_owned_texture(
+            Some("Framebuffer".into()),
+            imgui_wgpu::TextureDescriptor {
                 width: SCREEN_WIDTH as u32,
                 height: SCREEN_HEIGHT as u32 * 2,
                 format: wgpu::TextureFormat::Rgba8Unorm,
                 ..Default::default()
             },
             imgui_wgpu::SamplerDescriptor {
-                mag_filter: wgpu::FilterMode::Nearest,
+                mag_filter: filter.mag_filter(),
                 min_filter: wgpu::FilterMode::Linear,
                 ..Default::default()
             },
         )
     }
 
-    fn create_view(window: &window::Window, view: wgpu::TextureView) -> imgui::TextureId {
+    fn create_view(
+        window: &window::Window,
+        view: wgpu::TextureView,
+        filter: ScreenFilter,
+    ) -> imgui::TextureId {
         window.imgui_gfx.create_and_add_texture_view(
             Some("Framebuffer".into()),
             view,
             imgui_wgpu::SamplerDescriptor {
-                mag_filter: wgpu::FilterMode::Nearest,
+                mag_filter: filter.mag_filter(),
                 min_filter: wgpu::FilterMode::Linear,
                 ..Default::default()
             },
         )
     }
 
-    fn new(window: &window::Window) -> Self {
-        let result = FbTexture {
-            id: Self::create_owned(window),
+    fn create_filter_pass(
+        window: &window::Window,
+        filter: ScreenFilter,
+        color_profile: LcdColorProfile,
+        post_process_shader: Option<&post_process::Shader>,
+    ) -> Option<screen_filter::Pass> {
+        screen_filter::Pass::new(
+            window.gfx_device(),
+            color_profile,
+            filter,
+            post_process_shader,
+        )
+        .unwrap_or_else(|err| {
+            error!(
+                "Post-processing shader error",
+                "Couldn't compile the post-processing shader at `{}`:\n\n{err}",
+                post_process_shader.unwrap().path().display(),
+            );
+            screen_filter::Pass::new(window.gfx_device(), color_profile, filter, None)
+                .ok()
+                .flatten()
+        })
+    }
+
+    fn new(
+        window: &window::Window,
+        filter: ScreenFilter,
+        color_profile: LcdColorProfile,
+        post_process_shader: Option<post_process::Shader>,
+    ) -> Self {
+        let filter_pass =
+            Self::create_filter_pass(window, filter, color_profile, post_process_shader.as_ref());
+        let id = match &filter_pass {
+            Some(pass) => Self::create_view(
+                window,
+                pass.output().create_view(&Default::default()),
+                filter,
+            ),
+            None => Self::create_owned(window, filter),
+        };
+        let mut result = FbTexture {
+            id,
             is_view: false,
+            source_texture: None,
+            filter,
+            color_profile,
+            post_process_shader,
+            filter_pass,
         };
         result.clear(window);
         result
     }
 
-    fn set_owned(&mut self, window: &window::Window) {
-        if !self.is_view {
+    fn rebuild(&mut self, window: &window::Window, filter: ScreenFilter) {
+        window.imgui_gfx.remove_texture(self.id);
+        let source_texture = self.source_texture.take();
+        *self = Self::new(
+            window,
+            filter,
+            self.color_profile,
+            self.post_process_shader.take(),
+        );
+        if let Some(source_texture) = source_texture {
+            self.set_view(window, source_texture);
+        }
+    }
+
+    fn set_filter(&mut self, window: &window::Window, filter: ScreenFilter) {
+        if filter == self.filter {
             return;
         }
-        window.imgui_gfx.remove_texture(self.id);
-        self.id = Self::create_owned(window);
-        self.is_view = false;
+        self.rebuild(window, filter);
     }
 
-    fn set_view(&mut self, window: &window::Window, view: wgpu::TextureView) {
-        if self.is_view {
+    fn set_color_profile(&mut self, window: &window::Window, color_profile: LcdColorProfile) {
+        if color_profile == self.color_profile {
+            return;
+        }
+        self.color_profile = color_profile;
+        self.rebuild(window, self.filter);
+    }
+
+    fn set_post_process_shader(
+        &mut self,
+        window: &window::Window,
+        shader: Option<post_process::Shader>,
+    ) {
+        self.post_process_shader = shader;
+        self.rebuild(window, self.filter);
+    }
+
+    fn reload_post_process_shader_if_modified(&mut self, window: &window::Window) {
+        if self
+            .post_process_shader
+            .as_mut()
+            .is_some_and(post_process::Shader::reload_if_modified)
+        {
+            self.rebuild(window, self.filter);
+        }
+    }
+
+    fn update_filter_output_view(&self, window: &window::Window) {
+        if let Some(pass) = &self.filter_pass {
             window
                 .imgui_gfx
                 .texture_mut(self.id)
                 .unwrap_view_mut()
-                .set_texture_view(view);
+                .set_texture_view(pass.output().create_view(&Default::default()));
+        }
+    }
+
+    fn set_owned(&mut self, window: &window::Window) {
+        if !self.is_view {
+            return;
+        }
+        self.is_view = false;
+        self.source_texture = None;
+        if let Some(pass) = &mut self.filter_pass {
+            if pass.set_input(None) {
+                self.update_filter_output_view(window);
+            }
         } else {
             window.imgui_gfx.remove_texture(self.id);
-            self.id = Self::create_view(window, view);
-            self.is_view = true;
+            self.id = Self::create_owned(window, self.filter);
+        }
+    }
+
+    fn set_view(&mut self, window: &window::Window, texture: Arc<wgpu::Texture>) {
+        if let Some(pass) = &mut self.filter_pass {
+            if pass.set_input(Some(&texture)) {
+                self.update_filter_output_view(window);
+            }
+        } else {
+            let view = texture.create_view(&Default::default());
+            if self.is_view {
+                window
+                    .imgui_gfx
+                    .texture_mut(self.id)
+                    .unwrap_view_mut()
+                    .set_texture_view(view);
+            } else {
+                window.imgui_gfx.remove_texture(self.id);
+                self.id = Self::create_view(window, view, self.filter);
+            }
         }
+        self.source_texture = Some(texture);
+        self.is_view = true;
     }
 
     fn id(&self) -> imgui::TextureId {
         self.id
     }
 
-    fn clear(&self, window: &window::Window) {
-        let mut data = zeroed_box::<[u8; SCREEN_WIDTH * SCREEN_HEIGHT * 8]>();
-        for i in (3..data.len()).step_by(4) {
-            data[i] = 0xFF;
+    fn filter(&self) -> ScreenFilter {
+        self.filter
+    }
+
+    /// Returns the texture that's actually drawn, if it's not owned by imgui.
+    fn source_texture(&self) -> Option<&Arc<wgpu::Texture>> {
+        match &self.filter_pass {
+            Some(pass) => Some(pass.output()),
+            None => self.source_texture.as_ref(),
         }
-        window
-            .imgui_gfx
-            .texture(self.id)
-            .unwrap_owned_ref()
-            .set_data(
-                window.gfx_device(),
-                window.gfx_queue(),
-                &*data,
-                imgui_wgpu::TextureSetRange::default(),
-            );
     }
 
-    fn set_data(&self, window: &window::Window, data: &Framebuffer) {
+    fn clear(&mut self, window: &window::Window) {
+        let mut data: Box<Framebuffer> = unsafe { Box::new_zeroed().assume_init() };
+        for screen in data.iter_mut() {
+            screen.fill(0xFF00_0000);
+        }
+        self.set_data(window, &data);
+    }
+
+    fn set_data(&mut self, window: &window::Window, data: &Framebuffer) {
+        if let Some(pass) = &mut self.filter_pass {
+            pass.set_framebuffer_data(window.gfx_queue(), data);
+            return;
+        }
         window
             .imgui_gfx
             .texture(self.id)
@@ -842,6 +2199,175 @@ impl FbTexture {
                 imgui_wgpu::TextureSetRange::default(),
             );
     }
+
+    fn apply_filter(&mut self, window: &window::Window) {
+        if let Some(pass) = &mut self.filter_pass {
+            pass.run(window.gfx_queue());
+        }
+    }
+}
+
+/// Draws a marker at the position of the stylus moved through hotkeys, given in normalized
+/// coordinates, over the bottom screen's quad.
+fn draw_stylus(draw_list: &imgui::DrawListMut, points: &[[f32; 2]; 4], pos: [f32; 2]) {
+    let center = [0, 1].map(|i| {
+        points[0][i]
+            + (points[1][i] - points[0][i]) * pos[0]
+            + (points[3][i] - points[0][i]) * pos[1]
+    });
+    draw_list
+        .add_circle(center, 4.0, [1.0, 1.0, 1.0, 0.9])
+        .filled(true)
+        .build();
+    draw_list
+        .add_circle(center, 4.0, [0.0, 0.0, 0.0, 0.9])
+        .thickness(1.5)
+        .build();
+}
+
+fn touch_settings(config: &config::Config) -> input::TouchSettings {
+    input::TouchSettings {
+        require_hotkey: config!(config, touch_requires_hotkey),
+        stylus_speed: config!(config, stylus_speed),
+    }
+}
+
+fn fast_pointer_settings(config: &config::Config) -> Option<input::FastPointerSettings> {
+    config!(config, fast_pointer).then(|| input::FastPointerSettings {
+        sensitivity: config!(config, fast_pointer_sensitivity),
+        acceleration: config!(config, fast_pointer_acceleration),
+    })
+}
+
+fn max_resolution_scale_shift(window: &window::Window) -> u8 {
+    (window.gfx_device().limits().max_texture_dimension_2d / SCREEN_WIDTH as u32).ilog2() as u8
+}
+
+fn audio_device_config(config: &config::Config) -> audio::output::DeviceConfig {
+    let name = config!(config, &audio_output_device);
+    let sample_rate = config!(config, audio_output_sample_rate);
+    audio::output::DeviceConfig {
+        name: (!name.is_empty()).then(|| name.clone()),
+        sample_rate: (sample_rate != 0).then_some(sample_rate),
+    }
+}
+
+fn present_config(config: &config::Config) -> window::PresentConfig {
+    window::PresentConfig {
+        mode: match config!(config, present_mode) {
+            PresentMode::Vsync => wgpu::PresentMode::AutoVsync,
+            PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+            PresentMode::Immediate => wgpu::PresentMode::AutoNoVsync,
+        },
+        max_frame_latency: config!(config, max_frame_latency).clamp(1, 3) as u32,
+    }
+}
+
+fn create_audio_channel(config: &config::Config) -> Option<audio::output::Channel> {
+    audio::output::Channel::new(
+        audio_device_config(config),
+        config!(config, audio_output_interp_method),
+        config!(config, audio_volume),
+        config!(config, audio_output_profile),
+        config!(config, audio_output_buffer_len) as usize,
+        config!(config, audio_output_latency_ms),
+        #[cfg(feature = "xq-audio")]
+        adjust_custom_sample_rate(config!(config, audio_custom_sample_rate)),
+    )
+}
+
+fn adaptive_resolution(
+    config: &config::Config,
+    window: &window::Window,
+) -> Option<AdaptiveResolution> {
+    config!(config, adaptive_resolution_scale).then(|| {
+        AdaptiveResolution::new(
+            config!(config, resolution_scale_shift).min(max_resolution_scale_shift(window)),
+        )
+    })
+}
+
+fn adaptive_resolution_thresholds(config: &config::Config) -> adaptive_resolution::Thresholds {
+    adaptive_resolution::Thresholds {
+        lower: config!(config, adaptive_resolution_lower_threshold),
+        raise: config!(config, adaptive_resolution_raise_threshold),
+        hold_frames: config!(config, adaptive_resolution_hold_frames).max(1),
+    }
+}
+
+fn texture_filtering(config: &config::Config) -> dust_wgpu_3d::TextureFiltering {
+    dust_wgpu_3d::TextureFiltering {
+        filter: config!(config, texture_filter).into(),
+        max_anisotropy: 1 << config!(config, texture_anisotropy_shift),
+    }
+}
+
+fn soft_renderer_3d_threads(config: &config::Config) -> NonZeroUsize {
+    NonZeroUsize::new(config!(config, soft_renderer_3d_threads) as usize)
+        .unwrap_or(NonZeroUsize::MIN)
+}
+
+fn texture_dump_dir(config: &config::Config) -> Option<PathBuf> {
+    config!(config, dump_3d_textures).then(|| config!(config, &texture_dump_dir_path).0.clone())
+}
+
+fn texture_pack_dir(config: &config::Config) -> Option<PathBuf> {
+    config!(config, replace_3d_textures).then(|| config!(config, &texture_pack_dir_path).0.clone())
+}
+
+fn load_post_process_shader(config: &config::Config) -> Option<post_process::Shader> {
+    let name = config!(config, &post_process_shader);
+    if name.is_empty() {
+        return None;
+    }
+    let path = config!(config, &shader_dir_path).0.join(name);
+    post_process::Shader::load(path.clone())
+        .map_err(|err| {
+            error!(
+                "Post-processing shader error",
+                "Couldn't read the post-processing shader at `{}`: {err}",
+                path.display(),
+            );
+        })
+        .ok()
+}
+
+/// Prints a ROM's metadata to the standard output without opening a window, returning whether it
+/// could be read.
+pub fn print_rom_info(rom_path: &Path) -> bool {
+    let config = Config::new();
+    let game_db = config!(config.config, game_db_path)
+        .as_ref()
+        .and_then(|path| game_db::Database::read_from_file(&path.0).ok());
+    let game_title = rom_path
+        .file_stem()
+        .and_then(|game_title| game_title.to_str());
+    let save_path = game_title.and_then(|game_title| config.config.save_path(game_title));
+    let save_type_override = game_title
+        .zip(config.games_base_path.as_ref())
+        .and_then(|(game_title, base_path)| {
+            config::File::<config::GameFile>::read(
+                &base_path.join(format!("{game_title}.json")),
+                false,
+            )
+            .ok()
+        })
+        .and_then(|game_config| game_config.contents.settings.save_type_override);
+    match RomInfo::read(
+        rom_path,
+        game_db.as_ref(),
+        save_path.as_deref(),
+        save_type_override,
+    ) {
+        Ok(info) => {
+            print!("{info}");
+            true
+        }
+        Err(err) => {
+            eprintln!("Couldn't read ROM file at `{}`: {err}", rom_path.display());
+            false
+        }
+    }
 }
 
 pub fn main() {
@@ -850,9 +2376,18 @@ pub fn main() {
         let thread = std::thread::current();
         let thread_name = thread.name().unwrap_or("<unnamed>");
 
+        // The emulation thread's save chip contents would otherwise be lost along with any
+        // progress made since the last periodic flush
+        let save_flush_message = match config::saves::flush_on_panic() {
+            Some(Err(err)) => {
+                format!("\n\nCouldn't write the save file, the latest progress was lost: {err}")
+            }
+            _ => String::new(),
+        };
+
         error!(
             "Unexpected panic",
-            "Thread \"{thread_name}\" {info}\n\nThe emulator will now quit."
+            "Thread \"{thread_name}\" {info}{save_flush_message}\n\nThe emulator will now quit."
         );
 
         panic_hook(info);
@@ -867,6 +2402,7 @@ pub fn main() {
     let mut window_builder = pollster::block_on(window::Builder::new(
         "Dust",
         wgpu::Features::empty(),
+        dust_wgpu_3d::FRAME_TIMING_FEATURES,
         window::AdapterSelection::Auto(wgpu::PowerPreference::LowPower),
         config.config.window_size,
         window::SrgbMode::None,
@@ -883,12 +2419,7 @@ pub fn main() {
         }
     }
 
-    let audio_channel = audio::output::Channel::new(
-        config!(config.config, audio_output_interp_method),
-        config!(config.config, audio_volume),
-        #[cfg(feature = "xq-audio")]
-        adjust_custom_sample_rate(config!(config.config, audio_custom_sample_rate)),
-    );
+    let audio_channel = create_audio_channel(&config.config);
 
     let (frame_tx, frame_rx) = triple_buffer::init([
         FrameData::default(),
@@ -898,7 +2429,14 @@ pub fn main() {
 
     window_builder.run(
         move |window| {
-            let fb_texture = FbTexture::new(window);
+            window.set_present_config(present_config(&config.config));
+
+            let fb_texture = FbTexture::new(
+                window,
+                config!(config.config, screen_filter),
+                config!(config.config, lcd_color_profile),
+                load_post_process_shader(&config.config),
+            );
 
             let mut state = UiState {
                 game_db: Lazy::new(),
@@ -908,17 +2446,35 @@ pub fn main() {
                 fb_texture,
                 frame_tx: Some(frame_tx),
                 frame_rx,
+                screen_engines: [Engine2dId::B, Engine2dId::A],
 
                 title_menu_bar: TitleMenuBarState::new(&config.config),
 
                 screen_focused: true,
 
                 input: input::State::new(),
+                gamepads: input::Gamepads::new(),
+                turbo_enabled: false,
 
                 config_editor: None,
+                screen_layout_editor: None,
+                peripheral_info: None,
+                dual_slot: None,
+                watch_folder: None,
+                rom_inspector: None,
+                game_bundle: None,
+                archive_rom_picker: None,
+
+                wgpu_3d_failures: Vec::new(),
 
                 save_slot_editor: SaveSlotEditor::new(),
                 savestate_editor: SavestateEditor::new(),
+                savestate_slot: 0,
+                savestate_load_preview_start: None,
+
+                osd: Osd::new(),
+                input_overlay: InputOverlay::new(),
+                perf_overlay: PerfOverlay::new(),
 
                 audio_channel,
 
@@ -927,6 +2483,10 @@ pub fn main() {
 
                 #[cfg(feature = "debug-views")]
                 debug_views: debug_views::UiState::new(),
+                #[cfg(feature = "debug-views")]
+                mem_transfer: None,
+                #[cfg(all(feature = "fault-injection", debug_assertions))]
+                fault_injection: None,
 
                 #[cfg(feature = "discord-presence")]
                 discord_presence: if config!(config.config, discord_presence_enabled) {
@@ -941,6 +2501,34 @@ pub fn main() {
                 discord_presence.stop();
             }
 
+            state
+                .input
+                .set_fast_pointer(fast_pointer_settings(&config.config));
+            state.input.set_touch_settings(touch_settings(&config.config));
+            state
+                .gamepads
+                .set_deadzone(config!(config.config, gamepad_deadzone));
+            if !state.gamepads.is_available() {
+                state.osd.post(Notification::new(
+                    notifications::Kind::Other,
+                    notifications::Level::Warning,
+                    "Couldn't initialize gamepad support",
+                ));
+            }
+
+            window.gfx_device().set_device_lost_callback({
+                let notifications = state.osd.sender().clone();
+                move |reason, message| {
+                    if reason != wgpu::DeviceLostReason::Destroyed {
+                        notifications.post(Notification::new(
+                            notifications::Kind::DeviceLost,
+                            notifications::Level::Error,
+                            format!("Graphics device lost, restart the emulator: {message}"),
+                        ));
+                    }
+                }
+            });
+
             if let Some(rom_path) = env::args_os().nth(1) {
                 state.load_from_rom_path(Path::new(&rom_path), &mut config, window);
             }
@@ -958,26 +2546,75 @@ pub fn main() {
                 state.load_from_rom_path(path, config, window);
             }
 
-            state
-                .input
-                .process_event(event, window.scale_factor(), state.screen_focused);
+            let screen_window_event = match (event, window.screen_window()) {
+                (Event::WindowEvent { window_id, .. }, Some(screen_window)) => {
+                    (*window_id == screen_window.id()).then(|| screen_window.scale_factor())
+                }
+                _ => None,
+            };
+            if let Some(scale_factor) = screen_window_event {
+                state.input.process_event(event, scale_factor, true);
+            } else {
+                state
+                    .input
+                    .process_event(event, window.scale_factor(), state.screen_focused);
+            }
+
+            if let Some(captured) = state.input.take_pointer_capture_change() {
+                match window.screen_window() {
+                    Some(screen_window) => screen_window.set_cursor_captured(captured),
+                    None => window.set_cursor_captured(captured),
+                }
+            }
+
+            // Forward fast pointer motion right away instead of on the next UI frame, to minimize
+            // latency
+            if let Some(changes) = state.input.take_fast_pointer_changes() {
+                if let Some(emu) = &state.emu {
+                    emu.send_message(emu::Message::UpdateInput(changes));
+                }
+            }
 
             if let Some(config_editor) = &mut state.config_editor {
                 config_editor.process_event(event, config);
             }
         },
         |window, (config, state), ui| {
+            // Poll gamepads, picking up connected and disconnected ones
+            state.gamepads.poll(|event| match event {
+                input::gamepad::Event::Input(key, pressed) => {
+                    state
+                        .input
+                        .process_gamepad_input(key, pressed, state.screen_focused);
+                    if let Some(config_editor) = &mut state.config_editor {
+                        config_editor.process_gamepad_input(key, pressed, config);
+                    }
+                }
+                input::gamepad::Event::Connected(name) => {
+                    state.osd.post(Notification::new(
+                        notifications::Kind::GamepadConnected,
+                        notifications::Level::Info,
+                        format!("Gamepad connected: {name}"),
+                    ));
+                }
+                input::gamepad::Event::Disconnected(name) => {
+                    state.osd.post(Notification::new(
+                        notifications::Kind::GamepadDisconnected,
+                        notifications::Level::Info,
+                        format!("Gamepad disconnected: {name}"),
+                    ));
+                }
+            });
+
             // Drain input updates
-            let (input_actions, emu_input_changes) = state.input.drain_changes(
-                config!(config.config, &input_map),
-                if let Some(emu) = &state.emu {
-                    emu.playing
-                } else {
-                    false
-                },
-            );
+            // NOTE: Input changes are forwarded while paused too, so that they're visible to the
+            // emulator when advancing frames.
+            let (input_actions, emu_input_changes) = state
+                .input
+                .drain_changes(config!(config.config, &input_map), state.emu.is_some());
 
             // Process input actions
+            let mut frames_to_advance = 0_u32;
             for action in input_actions {
                 match action {
                     input::Action::PlayPause => state.play_pause(),
@@ -995,6 +2632,71 @@ pub fn main() {
                     input::Action::ToggleFullWindowScreen => {
                         toggle_config!(config.config, full_window_screen)
                     }
+                    input::Action::SwapScreens => toggle_config!(config.config, swap_screens),
+                    input::Action::SwapMagnifiedScreen => {
+                        toggle_config!(config.config, magnified_screen_is_top)
+                    }
+                    input::Action::NextScreenLayout => config.config.cycle_screen_layout(true),
+                    input::Action::PrevScreenLayout => config.config.cycle_screen_layout(false),
+                    input::Action::TogglePerfOverlay => {
+                        toggle_config!(config.config, show_perf_overlay)
+                    }
+                    input::Action::ToggleTurbo => state.turbo_enabled = !state.turbo_enabled,
+                    input::Action::FastForward | input::Action::SlowMotion => {}
+                    input::Action::FrameAdvance => frames_to_advance += 1,
+                    input::Action::ReleasePointer => state.input.release_pointer(),
+                    // Handled by the input state while held
+                    input::Action::Touch
+                    | input::Action::StylusUp
+                    | input::Action::StylusDown
+                    | input::Action::StylusLeft
+                    | input::Action::StylusRight
+                    | input::Action::StylusTouch => {}
+                    input::Action::RunFrames => {
+                        frames_to_advance = frames_to_advance
+                            .saturating_add(config!(config.config, run_frames_count));
+                    }
+                    input::Action::SaveStateSlot
+                    | input::Action::LoadStateSlot
+                    | input::Action::NextStateSlot
+                    | input::Action::PrevStateSlot
+                    | input::Action::CancelStateLoad => {
+                        state.handle_savestate_action(action, config, window);
+                    }
+                    input::Action::NextSaveSlot | input::Action::PrevSaveSlot => {
+                        state.handle_save_slot_action(action, config);
+                    }
+                }
+            }
+            state.update_savestate_load_preview();
+
+            // Update the emulation speed override for held/toggled speed hotkeys
+            if let Some(emu) = &mut state.emu {
+                let speed_limit_override = |(active, value): (bool, f32)| {
+                    if active {
+                        emu::SpeedOverride::Ratio(value)
+                    } else {
+                        emu::SpeedOverride::Unlimited
+                    }
+                };
+                let speed_override = if state.input.hotkey_held(input::Action::SlowMotion) {
+                    Some(emu::SpeedOverride::Ratio(config!(
+                        config.config,
+                        slow_motion_speed
+                    )))
+                } else if state.input.hotkey_held(input::Action::FastForward) {
+                    Some(speed_limit_override(config!(
+                        config.config,
+                        fast_forward_speed_limit
+                    )))
+                } else if state.turbo_enabled {
+                    Some(speed_limit_override(config!(config.config, turbo_speed_limit)))
+                } else {
+                    None
+                };
+                if speed_override != emu.speed_override {
+                    emu.speed_override = speed_override;
+                    emu.send_message(emu::Message::UpdateSpeedOverride(speed_override));
                 }
             }
 
@@ -1027,10 +2729,45 @@ pub fn main() {
                     }
                 }
 
+                if config_changed!(
+                    config.config,
+                    fast_pointer | fast_pointer_sensitivity | fast_pointer_acceleration
+                ) {
+                    state
+                        .input
+                        .set_fast_pointer(fast_pointer_settings(&config.config));
+                }
+
+                if config_changed!(config.config, touch_requires_hotkey | stylus_speed) {
+                    state.input.set_touch_settings(touch_settings(&config.config));
+                }
+
+                if let Some(value) = config_changed_value!(config.config, gamepad_deadzone) {
+                    state.gamepads.set_deadzone(value);
+                }
+
+                if config_changed!(config.config, present_mode | max_frame_latency) {
+                    window.set_present_config(present_config(&config.config));
+                }
+
                 if config_changed!(config.config, game_db_path) {
                     state.game_db.invalidate();
                 }
 
+                if let Some(value) = config_changed_value!(config.config, screen_filter) {
+                    state.fb_texture.set_filter(window, value);
+                }
+
+                if let Some(value) = config_changed_value!(config.config, lcd_color_profile) {
+                    state.fb_texture.set_color_profile(window, value);
+                }
+
+                if config_changed!(config.config, post_process_shader | shader_dir_path) {
+                    state
+                        .fb_texture
+                        .set_post_process_shader(window, load_post_process_shader(&config.config));
+                }
+
                 if let Some(emu) = &mut state.emu {
                     if let Some((active, value)) =
                         config_changed_value!(config.config, framerate_ratio_limit)
@@ -1072,16 +2809,44 @@ pub fn main() {
                         emu.send_message(emu::Message::UpdateRtcTimeOffsetSeconds(value));
                     }
 
+                    if let Some(value) = config_changed_value!(config.config, rtc_frozen_date_time)
+                    {
+                        emu.send_message(emu::Message::UpdateRtcFrozenDateTime(value));
+                    }
+
                     if let Some(value) = config_changed_value!(config.config, sync_to_audio) {
                         emu.send_message(emu::Message::UpdateSyncToAudio(value));
                     }
 
+                    if let Some(value) = config_changed_value!(config.config, sub_frame_input) {
+                        emu.send_message(emu::Message::UpdateSubFrameInput(value));
+                    }
+
+                    if let Some(value) = config_changed_value!(config.config, touch_pressure) {
+                        emu.send_message(emu::Message::UpdateTouchPressure(value));
+                    }
+
+                    if let Some(value) = config_changed_value!(config.config, solar_sensor_level)
+                    {
+                        emu.send_message(emu::Message::UpdateSolarSensorLevel(value));
+                    }
+
+                    if let Some(value) =
+                        config_changed_value!(config.config, return_to_menu_on_shutdown)
+                    {
+                        emu.send_message(emu::Message::UpdateReturnToMenuOnShutdown(value));
+                    }
+
                     if let Some(value) =
                         config_changed_value!(config.config, audio_sample_chunk_size)
                     {
                         emu.send_message(emu::Message::UpdateAudioSampleChunkSize(value));
                     }
 
+                    if let Some(value) = config_changed_value!(config.config, audio_stereo_mode) {
+                        emu.send_message(emu::Message::UpdateAudioStereoMode(value));
+                    }
+
                     #[cfg(feature = "xq-audio")]
                     {
                         if let Some(value) =
@@ -1126,7 +2891,40 @@ pub fn main() {
                         }
                     }
 
-                    if config_changed!(config.config, renderer_2d_kind | renderer_3d_kind) {
+                    let mut recreate_renderers = config_changed!(
+                        config.config,
+                        renderer_2d_kind | renderer_3d_kind | soft_renderer_3d_threads
+                    );
+
+                    if let Renderer3dData::Wgpu(channels) = &emu.renderer_3d {
+                        for failure in channels.pipeline_failures() {
+                            if state.wgpu_3d_failures.contains(&failure) {
+                                continue;
+                            }
+                            #[cfg(feature = "logging")]
+                            slog::warn!(
+                                state.log.logger(),
+                                "3D renderer pipeline creation failed: {}",
+                                failure
+                            );
+                            if failure.fatal {
+                                recreate_renderers = true;
+                            } else {
+                                state.osd.post(Notification::new(
+                                    notifications::Kind::RendererFallback,
+                                    notifications::Level::Warning,
+                                    format!(
+                                        "The hardware 3D renderer couldn't create its {} \
+                                         pipeline, disabling the effect",
+                                        failure.key
+                                    ),
+                                ));
+                            }
+                            state.wgpu_3d_failures.push(failure);
+                        }
+                    }
+
+                    if recreate_renderers {
                         let (
                             renderer_2d_is_accel,
                             renderer_2d,
@@ -1136,33 +2934,103 @@ pub fn main() {
                         ) = UiState::create_renderers(
                             window,
                             &config.config,
+                            state.wgpu_3d_failures.iter().any(|failure| failure.fatal),
                             &mut state.fb_texture,
+                            &mut state.osd,
+                            #[cfg(feature = "logging")]
+                            state.log.logger(),
                         );
 
+                        let texture_cache = renderer_3d_data.texture_cache();
                         emu.renderer_2d = renderer_2d_data;
                         emu.renderer_3d = renderer_3d_data;
+                        emu.adaptive_resolution = adaptive_resolution(&config.config, window);
 
                         emu.send_message(emu::Message::UpdateRenderers {
                             renderer_2d_is_accel,
                             renderer_2d,
                             renderer_3d_tx,
+                            texture_cache,
                         });
                     }
 
-                    if let Some(value) =
-                        config_changed_value!(config.config, resolution_scale_shift)
+                    if config_changed!(
+                        config.config,
+                        resolution_scale_shift | adaptive_resolution_scale
+                    ) {
+                        emu.adaptive_resolution = adaptive_resolution(&config.config, window);
+                        emu.set_resolution_scale_shift(
+                            config!(config.config, resolution_scale_shift)
+                                .min(max_resolution_scale_shift(window)),
+                        );
+                    }
+
+                    if let Some((change, frame_time)) = emu
+                        .update_adaptive_resolution(adaptive_resolution_thresholds(&config.config))
                     {
-                        match &emu.renderer_2d {
-                            Renderer2dData::Soft => {}
-                            Renderer2dData::Wgpu(channels) => {
-                                channels.set_resolution_scale_shift(value);
-                            }
+                        let scale = 1 << emu.adaptive_resolution.as_ref().unwrap().shift();
+                        state.osd.post(Notification::new(
+                            notifications::Kind::ResolutionScaleChanged,
+                            notifications::Level::Info,
+                            format!(
+                                "{} 3D resolution scale to {scale}x (GPU frame time: {:.1} ms)",
+                                match change {
+                                    adaptive_resolution::Change::Lowered => "Lowered",
+                                    adaptive_resolution::Change::Raised => "Raised",
+                                },
+                                frame_time.as_secs_f64() * 1000.0,
+                            ),
+                        ));
+                    }
+
+                    if let Some(value) = config_changed_value!(config.config, hi_res_2d_bgs) {
+                        if let Renderer2dData::Wgpu(channels) = &emu.renderer_2d {
+                            channels.set_hi_res_affine_bgs(value);
                         }
-                        match &emu.renderer_3d {
-                            Renderer3dData::Soft => {}
-                            Renderer3dData::Wgpu(channels) => {
-                                channels.set_resolution_scale_shift(value);
-                            }
+                    }
+
+                    if let Renderer3dData::Wgpu(channels) = &emu.renderer_3d {
+                        if config_changed!(config.config, texture_filter | texture_anisotropy_shift)
+                        {
+                            channels.set_texture_filtering(texture_filtering(&config.config));
+                        }
+                        if let Some(value) = config_changed_value!(config.config, texture_cache_mode)
+                        {
+                            channels.set_texture_cache_mode(value.into());
+                        }
+                        if let Some(value) = config_changed_value!(config.config, msaa_3d) {
+                            channels.set_msaa_enabled(
+                                value
+                                    && dust_wgpu_3d::check_support(
+                                        window.gfx_adapter(),
+                                        window.gfx_device(),
+                                    )
+                                    .map_or(false, |support| support.msaa),
+                            );
+                        }
+                        if let Some(value) =
+                            config_changed_value!(config.config, compute_rasterizer_3d)
+                        {
+                            channels.set_compute_rasterizer_enabled(
+                                value
+                                    && dust_wgpu_3d::check_support(
+                                        window.gfx_adapter(),
+                                        window.gfx_device(),
+                                    )
+                                    .map_or(false, |support| support.compute_rasterizer),
+                            );
+                        }
+                        if config_changed!(
+                            config.config,
+                            dump_3d_textures | texture_dump_dir_path
+                        ) {
+                            channels.set_texture_dump_dir(texture_dump_dir(&config.config));
+                        }
+                        if config_changed!(
+                            config.config,
+                            replace_3d_textures | texture_pack_dir_path
+                        ) {
+                            channels.set_texture_pack_dir(texture_pack_dir(&config.config));
                         }
                     }
                 }
@@ -1178,26 +3046,60 @@ pub fn main() {
                         channel.output_stream.set_interp_method(value);
                     }
 
+                    if let Some(value) = config_changed_value!(config.config, audio_output_profile)
+                    {
+                        channel.output_stream.set_profile(value);
+                    }
+
                     #[cfg(feature = "xq-audio")]
                     if let Some(value) =
                         config_changed_value!(config.config, audio_custom_sample_rate)
                     {
                         channel.set_custom_sample_rate(adjust_custom_sample_rate(value));
                     }
+
+                    if config_changed!(
+                        config.config,
+                        audio_output_device | audio_output_sample_rate
+                    ) {
+                        if let Err(err) =
+                            channel.set_device_config(audio_device_config(&config.config))
+                        {
+                            error!(
+                                "Audio output error",
+                                "Couldn't switch audio output device: {err}"
+                            );
+                        }
+                    }
                 }
 
                 config.config.clear_updates();
             }
 
+            if let Some(channel) = state.audio_channel.as_mut() {
+                if channel.reconnect_if_disconnected() {
+                    state.osd.post(Notification::new(
+                        notifications::Kind::AudioDeviceChanged,
+                        notifications::Level::Warning,
+                        "Audio output device lost, reconnected",
+                    ));
+                }
+            }
+
+            state
+                .savestate_editor
+                .update_auto_savestate(window, &config.config, &state.emu);
+
             // Process emulator-visible input changes
-            if let Some(changes) = emu_input_changes {
-                if let Some(emu) = &mut state.emu {
-                    if emu.playing {
-                        emu.send_message(emu::Message::UpdateInput(changes));
-                    }
+            if let Some(emu) = &mut state.emu {
+                for changes in emu_input_changes {
+                    emu.send_message(emu::Message::UpdateInput(changes));
                 }
             }
 
+            // Advance frames after sending input changes, so that they apply to the new frames
+            state.advance_frames(frames_to_advance);
+
             // Update Discord presence
             #[cfg(feature = "discord-presence")]
             if let Some(presence) = &mut state.discord_presence {
@@ -1220,26 +3122,96 @@ pub fn main() {
                                 state.debug_views.handle_notif(notif, window);
                             }
 
+                            #[cfg(feature = "debug-views")]
+                            emu::Notification::PlayingChanged(playing) => {
+                                emu.playing = playing;
+                            }
+
+                            emu::Notification::Crashed(crash) => {
+                                emu.playing = false;
+                                emu.crash = Some(crash);
+                            }
+
                             emu::Notification::RtcTimeOffsetSecondsUpdated(value) => {
                                 set_config!(config.config, rtc_time_offset_seconds, value);
                                 config.config.rtc_time_offset_seconds.clear_updates();
                             }
 
+                            emu::Notification::RtcFrozenDateTimeUpdated(value) => {
+                                set_config!(config.config, rtc_frozen_date_time, value);
+                                config.config.rtc_frozen_date_time.clear_updates();
+                            }
+
+                            emu::Notification::SavestateCreated(name, savestate)
+                                if savestate_editor::is_auto_savestate_name(&name) =>
+                            {
+                                state.savestate_editor.auto_savestate_created(
+                                    name,
+                                    savestate,
+                                    window,
+                                    state.osd.sender(),
+                                );
+                            }
+
                             emu::Notification::SavestateCreated(name, savestate) => {
-                                state
-                                    .savestate_editor
-                                    .savestate_created(name, savestate, window);
+                                let notification = if state.savestate_editor.savestate_created(
+                                    name.clone(),
+                                    savestate,
+                                    window,
+                                ) {
+                                    Notification::new(
+                                        notifications::Kind::SavestateCreated,
+                                        notifications::Level::Info,
+                                        format!("Savestate \"{name}\" created"),
+                                    )
+                                } else {
+                                    Notification::new(
+                                        notifications::Kind::SavestateFailed,
+                                        notifications::Level::Error,
+                                        format!("Couldn't write savestate \"{name}\""),
+                                    )
+                                };
+                                state.osd.post(notification);
                             }
 
                             emu::Notification::SavestateFailed(name) => {
+                                state.osd.post(Notification::new(
+                                    notifications::Kind::SavestateFailed,
+                                    notifications::Level::Error,
+                                    format!("Couldn't create savestate \"{name}\""),
+                                ));
                                 state.savestate_editor.savestate_failed(name);
                             }
+
+                            #[cfg(feature = "ffmpeg")]
+                            emu::Notification::RecordingStopped => {
+                                emu.recording = false;
+                            }
+
+                            #[cfg(feature = "frame-dump")]
+                            emu::Notification::FrameDumpStopped => {
+                                emu.frame_dumping = false;
+                            }
+
+                            #[cfg(feature = "wav-dump")]
+                            emu::Notification::WavDumpStopped => {
+                                emu.wav_dumping = false;
+                            }
                         }
                     }
                 }
                 break;
             }
 
+            let profiling = config!(config.config, show_perf_overlay);
+            if let Some(emu) = &mut state.emu {
+                emu.set_profiling_enabled(profiling);
+                emu.play_session.update(emu.playing);
+            }
+            if !profiling {
+                state.perf_overlay.stop_profiling();
+            }
+
             // Process new frame data, if present
             if let Ok(frame) = state.frame_rx.get() {
                 #[cfg(feature = "debug-views")]
@@ -1249,9 +3221,23 @@ pub fn main() {
 
                 if !state.fb_texture.is_view {
                     state.fb_texture.set_data(window, &frame.fb);
+                    if let Some(screen_window) = window.screen_window() {
+                        screen_window.set_framebuffer_data(window.gfx_queue(), &frame.fb);
+                    }
                 }
 
+                state.screen_engines = frame.screen_engines;
+                state.input_overlay.update(frame.input);
                 state.title_menu_bar.update_fps(frame.fps);
+                state.perf_overlay.update_fps(frame.fps);
+                if let Some(emu) = state.emu.as_ref().filter(|_| profiling) {
+                    state
+                        .perf_overlay
+                        .update_subsystem_times(frame.frame_count, emu.take_subsystem_times());
+                }
+                state.title_menu_bar.update_frame_count(
+                    config!(config.config, show_frame_counter).then_some(frame.frame_count),
+                );
             }
 
             // Draw menu bar
@@ -1308,17 +3294,157 @@ pub fn main() {
                             );
                         });
 
+                        let paused = state.emu.as_ref().map_or(false, |emu| !emu.playing);
+                        if ui
+                            .menu_item_config("\u{f051} Advance frame")
+                            .enabled(paused)
+                            .build()
+                        {
+                            state.advance_frames(1);
+                        }
+                        let run_frames_count = config!(config.config, run_frames_count);
+                        if ui
+                            .menu_item_config(format!("\u{f050} Run {run_frames_count} frames"))
+                            .enabled(paused)
+                            .build()
+                        {
+                            state.advance_frames(run_frames_count);
+                        }
+
+                        let has_dual_slot = state.emu.as_ref().is_some_and(|emu| {
+                            emu.ds_game_code.is_some_and(|code| {
+                                !game_db::dual_slot_gba_titles(code).is_empty()
+                            })
+                        });
+                        if ui
+                            .menu_item_config("\u{f11b} GBA slot game...")
+                            .enabled(has_dual_slot)
+                            .build()
+                        {
+                            state.open_dual_slot(config);
+                        }
+                        if ui
+                            .menu_item_config("\u{f07c} Watch folder...")
+                            .enabled(state.watch_folder.is_some())
+                            .build()
+                        {
+                            if let Some(watch_folder) = &mut state.watch_folder {
+                                watch_folder.opened = true;
+                            }
+                        }
+
+                        #[cfg(feature = "ffmpeg")]
+                        {
+                            let recording = state.emu.as_ref().map_or(false, |emu| emu.recording);
+                            if ui
+                                .menu_item_config(if recording {
+                                    "\u{f28d} Stop recording"
+                                } else {
+                                    "\u{f03d} Start recording..."
+                                })
+                                .enabled(state.emu.is_some())
+                                .build()
+                            {
+                                state.toggle_recording(config);
+                            }
+                        }
+
+                        #[cfg(feature = "frame-dump")]
+                        {
+                            let frame_dumping =
+                                state.emu.as_ref().map_or(false, |emu| emu.frame_dumping);
+                            if ui
+                                .menu_item_config(if frame_dumping {
+                                    "\u{f28d} Stop frame dump"
+                                } else {
+                                    "\u{f03e} Start frame dump"
+                                })
+                                .enabled(state.emu.is_some())
+                                .build()
+                            {
+                                state.toggle_frame_dump(config);
+                            }
+                        }
+
+                        #[cfg(feature = "wav-dump")]
+                        {
+                            let wav_dumping =
+                                state.emu.as_ref().map_or(false, |emu| emu.wav_dumping);
+                            if ui
+                                .menu_item_config(if wav_dumping {
+                                    "\u{f28d} Stop audio dump"
+                                } else {
+                                    "\u{f1c7} Dump audio to WAV..."
+                                })
+                                .enabled(state.emu.is_some())
+                                .build()
+                            {
+                                state.toggle_wav_dump(config);
+                            }
+                        }
+
+                        #[cfg(feature = "remote-display")]
+                        {
+                            let active = state.emu.as_ref().map_or(false, |emu| {
+                                emu.shared_state
+                                    .remote_display_active
+                                    .load(Ordering::Relaxed)
+                            });
+                            if ui
+                                .menu_item_config(if active {
+                                    "\u{f28d} Stop remote display"
+                                } else {
+                                    "\u{f3cd} Start remote display"
+                                })
+                                .enabled(state.emu.is_some())
+                                .build()
+                            {
+                                if let Some(emu) = &state.emu {
+                                    emu.send_message(emu::Message::ToggleRemoteDisplay(
+                                        (!active).then(|| {
+                                            (
+                                                config!(config.config, remote_display_addr),
+                                                emu::remote_display::Settings {
+                                                    encoding: config!(
+                                                        config.config,
+                                                        remote_display_encoding
+                                                    ),
+                                                    jpeg_quality: config!(
+                                                        config.config,
+                                                        remote_display_jpeg_quality
+                                                    ),
+                                                    screens: config!(
+                                                        config.config,
+                                                        remote_display_screens
+                                                    ),
+                                                },
+                                            )
+                                        }),
+                                    ));
+                                }
+                            }
+                        }
+
                         ui.separator();
 
                         if ui.menu_item("\u{f07c} Load game...") {
                             if let Some(path) = FileDialog::new()
-                                .add_filter("NDS ROM file", ALLOWED_ROM_EXTENSIONS)
+                                .add_filter("NDS ROM file or archive", ALLOWED_GAME_EXTENSIONS)
                                 .pick_file()
                             {
                                 state.load_from_rom_path(&path, config, window);
                             }
                         }
 
+                        if ui.menu_item("\u{f05a} Inspect ROM...") {
+                            if let Some(path) = FileDialog::new()
+                                .add_filter("NDS ROM file", ALLOWED_ROM_EXTENSIONS)
+                                .pick_file()
+                            {
+                                state.inspect_rom(&path, config, window);
+                            }
+                        }
+
                         if ui.menu_item("\u{f2db} Load firmware") {
                             state.load_firmware(config, window);
                         }
@@ -1349,6 +3475,14 @@ pub fn main() {
                             );
                         }
 
+                        if ui.menu_item_config("\u{f1e0} Share game configurations...")
+                            .enabled(config.games_base_path.is_some())
+                            .build()
+                            && state.game_bundle.is_none()
+                        {
+                            state.game_bundle = Some(GameBundleWindow::new(config));
+                        }
+
                         ui.separator();
 
                         let audio_volume = config!(config.config, audio_volume);
@@ -1422,6 +3556,36 @@ pub fn main() {
                             }
                         });
 
+                        ui.menu("\u{f009} Screen layout", || {
+                            let active_name = config!(config.config, screen_layout);
+                            let mut new_name = None;
+                            if ui
+                                .menu_item_config("Default")
+                                .selected(active_name.is_empty())
+                                .build()
+                            {
+                                new_name = Some(String::new());
+                            }
+                            for layout in config!(config.config, &screen_layouts) {
+                                if ui
+                                    .menu_item_config(&layout.name)
+                                    .selected(layout.name == active_name)
+                                    .build()
+                                {
+                                    new_name = Some(layout.name.clone());
+                                }
+                            }
+                            if let Some(new_name) = new_name {
+                                set_config!(config.config, screen_layout, new_name);
+                            }
+                            ui.separator();
+                            if ui.menu_item("Edit layouts...") && state.screen_layout_editor.is_none()
+                            {
+                                state.screen_layout_editor =
+                                    Some(ScreenLayoutEditor::new(&config.config));
+                            }
+                        });
+
                         macro_rules! draw_config_toggle {
                             ($ident: ident, $desc: literal) => {{
                                 let mut value = config!(config.config, $ident);
@@ -1432,6 +3596,10 @@ pub fn main() {
                         }
 
                         draw_config_toggle!(full_window_screen, "\u{f31e} Full-window screen");
+                        draw_config_toggle!(swap_screens, "\u{f0ec} Swap screens");
+                        draw_config_toggle!(single_screen, "\u{f2d0} Single screen");
+                        draw_config_toggle!(show_input_overlay, "\u{f11b} Input overlay");
+                        draw_config_toggle!(show_perf_overlay, "\u{f3fd} Performance overlay");
 
                         ui.separator();
 
@@ -1445,13 +3613,34 @@ pub fn main() {
                             }
                         }
                         draw_config_toggle!(sync_to_audio, "\u{f026} Sync to audio");
+
+                        if !state.wgpu_3d_failures.is_empty() {
+                            ui.separator();
+                            if ui.menu_item("\u{f188} Copy renderer diagnostics") {
+                                ui.set_clipboard_text(dust_wgpu_3d::diagnostics_report(
+                                    &window.gfx_adapter().get_info(),
+                                    &state.wgpu_3d_failures,
+                                ));
+                                state.osd.post(Notification::new(
+                                    notifications::Kind::Other,
+                                    notifications::Level::Info,
+                                    "Copied the renderer diagnostics to the clipboard",
+                                ));
+                            }
+                        }
                     });
 
                     #[cfg(feature = "logging")]
                     let imgui_log_enabled = state.log.is_imgui();
                     #[cfg(not(feature = "logging"))]
                     let imgui_log_enabled = false;
-                    if cfg!(any(feature = "debug-views", feature = "gdb-server"))
+                    if cfg!(any(
+                        feature = "debug-views",
+                        feature = "gdb-server",
+                        feature = "virtual-time",
+                        feature = "lockstep-trace",
+                        all(feature = "fault-injection", debug_assertions)
+                    ))
                         || imgui_log_enabled
                     {
                         #[allow(unused_assignments)]
@@ -1509,6 +3698,102 @@ pub fn main() {
                                 }
                             }}
 
+                            #[cfg(feature = "virtual-time")]
+                            section! {{
+                                let active = state.emu.as_ref().map_or(
+                                    false,
+                                    |emu| emu.shared_state.virtual_time_server_active.load(
+                                        Ordering::Relaxed,
+                                    ),
+                                );
+                                if ui
+                                    .menu_item_config(if active {
+                                        "Stop virtual time server"
+                                    } else {
+                                        "Start virtual time server"
+                                    })
+                                    .enabled(state.emu.is_some())
+                                    .build()
+                                {
+                                    if let Some(emu) = &state.emu {
+                                        emu.send_message(emu::Message::ToggleVirtualTimeServer(
+                                            (!active).then(|| {
+                                                config!(config.config, virtual_time_server_addr)
+                                            }),
+                                        ));
+                                    }
+                                }
+                            }}
+
+                            #[cfg(feature = "lockstep-trace")]
+                            section! {{
+                                let active = state.emu.as_ref().map_or(
+                                    false,
+                                    |emu| emu.shared_state.lockstep_trace_active.load(
+                                        Ordering::Relaxed,
+                                    ),
+                                );
+                                if ui
+                                    .menu_item_config("Lockstep trace")
+                                    .selected(active)
+                                    .enabled(state.emu.is_some())
+                                    .build()
+                                {
+                                    if let Some(emu) = &state.emu {
+                                        emu.send_message(emu::Message::ToggleLockstepTrace(
+                                            !active,
+                                        ));
+                                    }
+                                }
+                                if ui
+                                    .menu_item_config("Dump lockstep trace...")
+                                    .enabled(state.emu.is_some())
+                                    .build()
+                                {
+                                    if let Some(path) = FileDialog::new()
+                                        .add_filter("Text file", &["txt"])
+                                        .set_file_name("trace.txt")
+                                        .save_file()
+                                    {
+                                        if let Some(emu) = &state.emu {
+                                            emu.send_message(emu::Message::DumpLockstepTrace(path));
+                                        }
+                                    }
+                                }
+                            }}
+
+                            #[cfg(all(feature = "fault-injection", debug_assertions))]
+                            section! {{
+                                if ui.menu_item("Fault injection...") {
+                                    state.fault_injection.get_or_insert_with(FaultInjection::new);
+                                }
+                            }}
+
+                            #[cfg(feature = "debug-views")]
+                            section! {{
+                                ui.menu_with_enabled("Export memory", state.emu.is_some(), || {
+                                    for region in dust_core::emu::mem_regions::Region::ALL {
+                                        if !ui.menu_item(region.name()) {
+                                            continue;
+                                        }
+                                        if let Some(path) = FileDialog::new()
+                                            .add_filter("Binary file", &["bin"])
+                                            .set_file_name(format!("{}.bin", region.id()))
+                                            .save_file()
+                                        {
+                                            if let Some(emu) = &state.emu {
+                                                emu.send_message(emu::Message::ExportMemRegion(
+                                                    region, path,
+                                                ));
+                                            }
+                                        }
+                                    }
+                                });
+                                if ui.menu_item("Export/import address range...") {
+                                    state.mem_transfer.get_or_insert_with(MemTransfer::new);
+                                }
+                            }}
+
                             #[cfg(feature = "debug-views")]
                             section! {{
                                 state.debug_views.draw_menu(ui, window, state.emu.as_ref().map(|emu| &emu.to_emu));
@@ -1553,6 +3838,214 @@ pub fn main() {
             #[cfg(feature = "debug-views")]
             state.debug_views.draw(ui, window, state.emu.as_ref().map(|emu| &emu.to_emu));
 
+            // Draw memory export/import window
+            #[cfg(feature = "debug-views")]
+            if let Some(mem_transfer) = &mut state.mem_transfer {
+                let playing = state.emu.as_ref().map_or(false, |emu| emu.playing);
+                match mem_transfer.draw(ui, state.emu.is_some(), playing) {
+                    Some(mem_transfer::Action::Export { arm9, addr, len }) => {
+                        if let Some(path) = FileDialog::new()
+                            .add_filter("Binary file", &["bin"])
+                            .set_file_name(format!("{addr:08X}.bin"))
+                            .save_file()
+                        {
+                            if let Some(emu) = &state.emu {
+                                emu.send_message(emu::Message::ExportMemRange {
+                                    arm9,
+                                    addr,
+                                    len,
+                                    path,
+                                });
+                            }
+                        }
+                    }
+                    Some(mem_transfer::Action::Import { arm9, addr }) => {
+                        if let Some(path) = FileDialog::new()
+                            .add_filter("Binary file", &["bin"])
+                            .pick_file()
+                        {
+                            if let Some(emu) = &state.emu {
+                                emu.send_message(emu::Message::ImportMemRange { arm9, addr, path });
+                            }
+                        }
+                    }
+                    Some(mem_transfer::Action::Close) => state.mem_transfer = None,
+                    None => {}
+                }
+            }
+
+            // Draw fault injection window
+            #[cfg(all(feature = "fault-injection", debug_assertions))]
+            if let Some(fault_injection) = &mut state.fault_injection {
+                match fault_injection.draw(ui, state.emu.is_some()) {
+                    Some(fault_injection::Action::Inject(fault)) => {
+                        if let Some(emu) = &state.emu {
+                            emu.send_message(emu::Message::InjectFault(fault));
+                        }
+                    }
+                    Some(fault_injection::Action::SetPeriodic(periodic)) => {
+                        if let Some(emu) = &state.emu {
+                            emu.send_message(emu::Message::SetPeriodicFault(periodic));
+                        }
+                    }
+                    Some(fault_injection::Action::Close) => {
+                        if let Some(emu) = &state.emu {
+                            emu.send_message(emu::Message::SetPeriodicFault(None));
+                        }
+                        state.fault_injection = None;
+                    }
+                    None => {}
+                }
+            }
+
+            // Draw hang notification
+            state.draw_hang_popup(ui, config, window);
+
+            // Draw crash screen
+            state.draw_crash_popup(ui, window);
+
+            // Draw archive ROM picker
+            state.draw_archive_rom_picker(ui, config, window);
+
+            window.set_idle(config!(config.config, idle_when_paused) && !state.playing());
+
+            // Draw on-screen messages
+            state.osd.draw(ui);
+
+            // Draw the preview of the savestate about to be loaded through hotkeys
+            state.draw_savestate_load_preview(ui, config);
+
+            // Draw input overlay
+            if state.emu.is_some() && config!(config.config, show_input_overlay) {
+                state.input_overlay.draw(ui);
+            }
+
+            // Draw performance overlay
+            if state.emu.is_some() && config!(config.config, show_perf_overlay) {
+                state.perf_overlay.update_frame_timings(window.frame_timings());
+                state.perf_overlay.draw(
+                    ui,
+                    state.audio_channel.as_ref(),
+                    config!(config.config, max_frame_latency),
+                    if state.title_menu_bar.menu_bar_is_visible() {
+                        ui.frame_height()
+                    } else {
+                        0.0
+                    },
+                );
+            }
+
+            // Draw peripheral info panel
+            if let Some((panel, resume_on_close)) = &mut state.peripheral_info {
+                match panel.draw(ui) {
+                    Some(peripheral_info::Action::Continue) => {
+                        let resume = *resume_on_close;
+                        state.peripheral_info = None;
+                        if resume && !state.playing() {
+                            state.play_pause();
+                        }
+                    }
+                    Some(peripheral_info::Action::OpenSettings(section)) => {
+                        state
+                            .config_editor
+                            .get_or_insert_with(ConfigEditor::new)
+                            .set_section(section);
+                    }
+                    Some(peripheral_info::Action::OpenDualSlot) => {
+                        state.open_dual_slot(config);
+                    }
+                    None => {}
+                }
+            }
+
+            // Draw dual-slot GBA game picker
+            if let Some(panel) = &mut state.dual_slot {
+                match panel.draw(ui) {
+                    Some(dual_slot::Action::Insert(path)) => {
+                        config
+                            .config
+                            .gba_slot_rom_path
+                            .inner_mut()
+                            .set_game(Some(Some(HomePathBuf(path.clone()))));
+                        panel.set_inserted(Some(path));
+                    }
+                    Some(dual_slot::Action::Eject) => {
+                        config
+                            .config
+                            .gba_slot_rom_path
+                            .inner_mut()
+                            .set_game(Some(None));
+                        panel.set_inserted(None);
+                    }
+                    Some(dual_slot::Action::Close) => {
+                        state.dual_slot = None;
+                    }
+                    None => {}
+                }
+            }
+
+            // Poll the watch folder for changes and draw its panel
+            if let Some(watch_folder) = &mut state.watch_folder {
+                for change in watch_folder.poll() {
+                    state.osd.post(Notification::new(
+                        notifications::Kind::Other,
+                        notifications::Level::Info,
+                        change.message(watch_folder.active_patch()),
+                    ));
+                }
+                match watch_folder.draw(ui) {
+                    Some(watch_folder::Action::Activate(path)) => {
+                        config
+                            .config
+                            .watch_folder_patch_path
+                            .inner_mut()
+                            .set_game(Some(Some(HomePathBuf(path.clone()))));
+                        watch_folder.set_active_patch(Some(path));
+                    }
+                    Some(watch_folder::Action::Deactivate) => {
+                        config
+                            .config
+                            .watch_folder_patch_path
+                            .inner_mut()
+                            .set_game(Some(None));
+                        watch_folder.set_active_patch(None);
+                    }
+                    Some(watch_folder::Action::Close) => {
+                        watch_folder.opened = false;
+                    }
+                    None => {}
+                }
+            }
+
+            // Draw ROM inspector
+            if let Some(inspector) = &mut state.rom_inspector {
+                match inspector.draw(ui) {
+                    Some(rom_inspector::Action::Launch) => {
+                        let inspector = state.rom_inspector.take().unwrap();
+                        let path = inspector.path().to_path_buf();
+                        inspector.close(window);
+                        state.load_from_rom_path(&path, config, window);
+                    }
+                    Some(rom_inspector::Action::Close) => {
+                        state.rom_inspector.take().unwrap().close(window);
+                    }
+                    None => {}
+                }
+            }
+
+            // Draw game configuration bundle window
+            if let Some(game_bundle) = &mut state.game_bundle {
+                let current_game = state
+                    .emu
+                    .as_ref()
+                    .filter(|emu| emu.game_loaded && config.game_path.is_some())
+                    .map(|emu| emu.title.as_str());
+                let action = game_bundle.draw(ui, config, current_game);
+                if let Some(game_bundle::Action::Close) = action {
+                    state.game_bundle = None;
+                }
+            }
+
             // Draw config editor
             if let Some(editor) = &mut state.config_editor {
                 let mut opened = true;
@@ -1562,42 +4055,132 @@ pub fn main() {
                 }
             }
 
+            // Draw screen layout editor
+            if let Some(editor) = &mut state.screen_layout_editor {
+                let mut opened = true;
+                editor.draw(ui, &mut config.config, state.fb_texture.id(), &mut opened);
+                if !opened {
+                    state.screen_layout_editor = None;
+                }
+            }
+
             // Draw screen
             if let Some(emu) = &mut state.emu {
                 match &emu.renderer_2d {
-                    Renderer2dData::Soft => {}
+                    Renderer2dData::Soft { .. } => {}
                     Renderer2dData::Wgpu(channels) => {
-                        if let Some(color_output_view) = channels.new_color_output_view() {
-                            state.fb_texture.set_view(window, color_output_view);
+                        if let Some(color_output_texture) = channels.new_color_output_texture()
+                        {
+                            state.fb_texture.set_view(window, color_output_texture);
                         }
                     }
                 }
             }
 
+            if config!(config.config, detached_bottom_screen) {
+                if window
+                    .screen_window()
+                    .map_or(false, |screen_window| screen_window.close_requested())
+                {
+                    set_config!(config.config, detached_bottom_screen, false);
+                    config.config.bottom_screen_window_size =
+                        window.screen_window().unwrap().inner_size().into();
+                    window.close_screen_window();
+                } else if window.screen_window().is_none() {
+                    window.request_screen_window(
+                        "Dust - Bottom screen",
+                        config.config.bottom_screen_window_size,
+                    );
+                }
+            } else if let Some(screen_window) = window.screen_window() {
+                config.config.bottom_screen_window_size = screen_window.inner_size().into();
+                window.close_screen_window();
+            }
+
+            state.fb_texture.reload_post_process_shader_if_modified(window);
+            state.fb_texture.apply_filter(window);
+
+            let gfx_device = Arc::clone(window.gfx_device());
+            let bottom_screen_detached = if let Some(screen_window) = window.screen_window_mut() {
+                screen_window.set_source_texture(&gfx_device, state.fb_texture.source_texture());
+                screen_window.set_mag_filter(&gfx_device, state.fb_texture.filter().mag_filter());
+                let bottom_screen_rot =
+                    (config!(config.config, bottom_screen_rot) as f32).to_radians();
+                let (center, points) = scale_to_fit_rotated(
+                    [SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32],
+                    config!(config.config, bottom_screen_integer_scale),
+                    bottom_screen_rot,
+                    screen_window.inner_size().into(),
+                );
+                screen_window.set_quad(points, [0.0, 0.5, 1.0, 1.0]);
+                state.input.set_touchscreen_window(Some(screen_window.id()));
+                state
+                    .input
+                    .set_touchscreen_bounds_from_points(center, &points, bottom_screen_rot);
+                true
+            } else {
+                state.input.set_touchscreen_window(None);
+                false
+            };
+
+            // When the bottom screen is detached, only the top one is drawn in the main window
+            let screen_layout = match config.config.custom_screen_layout() {
+                Some(layout) if !bottom_screen_detached => ScreenLayout::custom(layout),
+                _ if !bottom_screen_detached && config!(config.config, magnified_screen) => {
+                    ScreenLayout::magnified(
+                        config!(config.config, magnified_screen_is_top),
+                        config!(config.config, small_screen_scale),
+                    )
+                }
+                _ => ScreenLayout::new(
+                    bottom_screen_detached,
+                    config!(config.config, single_screen),
+                    // The touchscreen bounds follow the bottom screen wherever it's placed
+                    config!(config.config, swap_screens)
+                        ^ (!bottom_screen_detached
+                            && config!(config.config, engine_screen_assignment)
+                                .swaps_screens(state.screen_engines)),
+                    config!(config.config, screen_gap),
+                ),
+            };
+
             let window_size = window.inner_size();
             let screen_integer_scale = config!(config.config, screen_integer_scale);
             let screen_rot = (config!(config.config, screen_rot) as f32).to_radians();
             if config!(config.config, full_window_screen) {
                 let (center, points) = scale_to_fit_rotated(
-                    [SCREEN_WIDTH as f32, (2 * SCREEN_HEIGHT) as f32],
+                    screen_layout.size,
                     screen_integer_scale,
                     screen_rot,
                     window_size.into(),
                 );
-                ui.get_background_draw_list()
-                    .add_image_quad(
-                        state.fb_texture.id(),
-                        points[0],
-                        points[1],
-                        points[2],
-                        points[3],
-                    )
-                    .build();
+                let draw_list = ui.get_background_draw_list();
+                for (points, [v_start, v_end]) in screen_layout.screen_quads(&points) {
+                    draw_list
+                        .add_image_quad(
+                            state.fb_texture.id(),
+                            points[0],
+                            points[1],
+                            points[2],
+                            points[3],
+                        )
+                        .uv([0.0, v_start], [1.0, v_start], [1.0, v_end], [0.0, v_end])
+                        .build();
+                }
                 state.screen_focused =
                     !ui.is_window_focused_with_flags(imgui::WindowFocusedFlags::ANY_WINDOW);
-                state
-                    .input
-                    .set_touchscreen_bounds_from_points(center, &points, screen_rot);
+                if !bottom_screen_detached {
+                    if let Some(points) = screen_layout.bottom_screen_quad(&points) {
+                        if let Some(stylus_pos) = state.input.stylus_pos() {
+                            draw_stylus(&draw_list, &points, stylus_pos);
+                        }
+                        state
+                            .input
+                            .set_touchscreen_bounds_from_points(center, &points, screen_rot);
+                    } else {
+                        state.input.clear_touchscreen_bounds();
+                    }
+                }
             } else {
                 let _window_padding = ui.push_style_var(imgui::StyleVar::WindowPadding([0.0; 2]));
                 let title_bar_height = style!(ui, frame_padding)[1] * 2.0 + ui.current_font_size();
@@ -1621,7 +4204,7 @@ pub fn main() {
                     .position_pivot([0.5; 2])
                     .build(|| {
                         let (center, points) = scale_to_fit_rotated(
-                            [SCREEN_WIDTH as f32, (2 * SCREEN_HEIGHT) as f32],
+                            screen_layout.size,
                             screen_integer_scale,
                             screen_rot,
                             ui.content_region_avail(),
@@ -1643,21 +4226,35 @@ pub fn main() {
                             window_pos[1] + content_region_min[1],
                         ];
                         let abs_points = points.map(|point| add2(point, upper_left));
-                        ui.get_window_draw_list()
-                            .add_image_quad(
-                                state.fb_texture.id(),
-                                abs_points[0],
-                                abs_points[1],
-                                abs_points[2],
-                                abs_points[3],
-                            )
-                            .build();
+                        let draw_list = ui.get_window_draw_list();
+                        for (points, [v_start, v_end]) in screen_layout.screen_quads(&abs_points)
+                        {
+                            draw_list
+                                .add_image_quad(
+                                    state.fb_texture.id(),
+                                    points[0],
+                                    points[1],
+                                    points[2],
+                                    points[3],
+                                )
+                                .uv([0.0, v_start], [1.0, v_start], [1.0, v_end], [0.0, v_end])
+                                .build();
+                        }
                         state.screen_focused = ui.is_window_focused();
-                        state.input.set_touchscreen_bounds_from_points(
-                            [center[0] + upper_left[0], center[1] + upper_left[1]],
-                            &abs_points,
-                            screen_rot,
-                        );
+                        if !bottom_screen_detached {
+                            if let Some(points) = screen_layout.bottom_screen_quad(&abs_points) {
+                                if let Some(stylus_pos) = state.input.stylus_pos() {
+                                    draw_stylus(&draw_list, &points, stylus_pos);
+                                }
+                                state.input.set_touchscreen_bounds_from_points(
+                                    add2(center, upper_left),
+                                    &points,
+                                    screen_rot,
+                                );
+                            } else {
+                                state.input.clear_touchscreen_bounds();
+                            }
+                        }
                     });
             };
 
@@ -1704,8 +4301,14 @@ pub fn main() {
         },
         move |mut window, (mut config, mut state)| {
             state.stop_emu(&mut config, &mut window);
+            state
+                .savestate_editor
+                .update_game(&window, &config.config, None);
 
             config.config.window_size = window.inner_size().into();
+            if let Some(screen_window) = window.screen_window() {
+                config.config.bottom_screen_window_size = screen_window.inner_size().into();
+            }
 
             if let Some(path) = config.global_path {
                 let global_config = config::File {
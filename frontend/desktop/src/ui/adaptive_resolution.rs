@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+/// The time taken by each frame on the DS, which the GPU has to stay under to keep full speed.
+const FRAME_BUDGET: Duration = Duration::from_nanos(16_715_000);
+
+/// How the scale reacts to GPU frame times, as set in the config.
+#[derive(Clone, Copy, Debug)]
+pub struct Thresholds {
+    /// The percentage of the frame budget above which the scale is lowered.
+    pub lower: u8,
+    /// The percentage of the frame budget below which the scale is raised; as raising it once
+    /// quadruples the rendered pixels, this should be well below a quarter of `lower`, or the
+    /// scale will keep oscillating.
+    pub raise: u8,
+    /// How many consecutive frames need to be above or below a threshold for the scale to change.
+    pub hold_frames: u16,
+}
+
+pub enum Change {
+    Lowered,
+    Raised,
+}
+
+/// Adjusts the hardware renderers' resolution scale shift based on the time the GPU takes to
+/// render each 3D frame, lowering it during heavy scenes to keep full speed and raising it back up
+/// to the configured maximum elsewhere.
+pub struct AdaptiveResolution {
+    max_shift: u8,
+    cur_shift: u8,
+    frames_over: u16,
+    frames_under: u16,
+}
+
+impl AdaptiveResolution {
+    pub fn new(max_shift: u8) -> Self {
+        AdaptiveResolution {
+            max_shift,
+            cur_shift: max_shift,
+            frames_over: 0,
+            frames_under: 0,
+        }
+    }
+
+    #[inline]
+    pub fn shift(&self) -> u8 {
+        self.cur_shift
+    }
+
+    /// Feeds the GPU time taken by the latest measured frame, returning how the scale shift
+    /// changed in response, if it did.
+    pub fn update(&mut self, frame_time: Duration, thresholds: Thresholds) -> Option<Change> {
+        let percentage = frame_time.as_nanos() * 100 / FRAME_BUDGET.as_nanos();
+
+        if percentage > thresholds.lower as u128 && self.cur_shift > 0 {
+            self.frames_under = 0;
+            self.frames_over += 1;
+            if self.frames_over >= thresholds.hold_frames {
+                self.frames_over = 0;
+                self.cur_shift -= 1;
+                return Some(Change::Lowered);
+            }
+        } else if percentage < thresholds.raise as u128 && self.cur_shift < self.max_shift {
+            self.frames_over = 0;
+            self.frames_under += 1;
+            if self.frames_under >= thresholds.hold_frames {
+                self.frames_under = 0;
+                self.cur_shift += 1;
+                return Some(Change::Raised);
+            }
+        } else {
+            self.frames_over = 0;
+            self.frames_under = 0;
+        }
+        None
+    }
+}
@@ -114,17 +114,31 @@ macro_rules! modify_configs {
 mod input_map;
 #[allow(dead_code)]
 mod setting;
+mod sys_sets;
 
+#[cfg(any(feature = "ffmpeg", feature = "frame-dump"))]
+use crate::config::CaptureLayout;
+#[cfg(feature = "frame-dump")]
+use crate::config::FrameDumpFormat;
 #[cfg(feature = "logging")]
 use crate::config::LoggingKind;
 #[cfg(target_os = "macos")]
 use crate::config::TitleBarMode;
+#[cfg(feature = "ffmpeg")]
+use crate::config::{RecordingContainer, RecordingVideoCodec};
+#[cfg(feature = "remote-display")]
+use crate::config::{RemoteDisplayEncoding, RemoteDisplayScreens};
 use crate::{
     audio,
     config::{
-        self, saves, GameIconMode, ModelConfig, Renderer2dKind, Renderer3dKind, Setting as _,
+        self, saves, AccuracyPreset, AccuracySettings, EngineScreenAssignment, GameIconMode,
+        LcdColorProfile, ModelConfig, PresentMode, Renderer2dKind, Renderer3dKind, ScreenFilter,
+        Setting as _, TextureCacheMode, TextureFilter,
     },
+    game_db::SaveType,
+    input::PressedKey,
     ui::{
+        post_process,
         utils::{
             add2, add_y_spacing, combo_value, heading, heading_options, mul2s, sub2, sub2s,
             table_row_heading,
@@ -133,15 +147,18 @@ use crate::{
     },
     utils::HomePathBuf,
 };
+use chrono::{Duration, Local, NaiveDateTime};
 #[cfg(feature = "xq-audio")]
 use dust_core::audio::ChannelInterpMethod as AudioChannelInterpMethod;
+use dust_core::audio::StereoMode as AudioStereoMode;
 use imgui::{StyleColor, StyleVar, TableColumnFlags, TableColumnSetup, TableFlags, Ui};
 use input_map::Editor as InputMapEditor;
 use rfd::FileDialog;
 use setting::Setting;
-use std::borrow::Cow;
 #[cfg(feature = "xq-audio")]
 use std::num::NonZeroU32;
+use std::{borrow::Cow, iter};
+use sys_sets::Editor as SysSetsEditor;
 
 macro_rules! home_path {
     (nonoverridable $id: ident) => {
@@ -161,6 +178,32 @@ macro_rules! opt_home_path {
             $is_dir,
         )
     };
+    (overridable $id: ident, $placeholder: expr, $is_dir: expr) => {
+        (
+            setting::OptHomePath::new(
+                |config| config.$id.inner().global().as_ref(),
+                |config, value| config.$id.inner_mut().set_global(value),
+                $placeholder,
+                $is_dir,
+            ),
+            setting::OptHomePath::new(
+                |config| config.$id.inner().game().as_ref().unwrap().as_ref(),
+                |config, value| config.$id.inner_mut().set_game(Some(value)),
+                $placeholder,
+                $is_dir,
+            ),
+        )
+    };
+}
+
+#[allow(unused_macros)]
+macro_rules! string {
+    (nonoverridable $id: ident) => {
+        setting::String::new(
+            |config| config!(config, &$id).as_str(),
+            |config, value| set_config!(config, $id, value.to_owned()),
+        )
+    };
 }
 
 #[allow(unused_macros)]
@@ -174,11 +217,12 @@ macro_rules! socket_addr {
 }
 
 macro_rules! scalar {
-    (nonoverridable $id: ident, $step: expr, $display_format: expr) => {
+    (nonoverridable $id: ident, $step: expr, $max: expr, $display_format: expr) => {
         setting::Scalar::new(
             |config| config!(config, $id),
             |config, value| set_config!(config, $id, value),
             $step,
+            $max,
             $display_format,
         )
     };
@@ -202,6 +246,38 @@ macro_rules! scalar {
     };
 }
 
+macro_rules! date_time_offset {
+    (overridable $id: ident) => {
+        (
+            setting::DateTimeOffset::new(
+                |config| *config.$id.inner().global(),
+                |config, value| config.$id.inner_mut().set_global(value),
+            ),
+            setting::DateTimeOffset::new(
+                |config| config.$id.inner().game().unwrap(),
+                |config, value| config.$id.inner_mut().set_game(Some(value)),
+            ),
+        )
+    };
+}
+
+macro_rules! opt_date_time {
+    (overridable $id: ident, $default: expr) => {
+        (
+            setting::OptDateTime::new(
+                |config| *config.$id.inner().global(),
+                |config, value| config.$id.inner_mut().set_global(value),
+                $default,
+            ),
+            setting::OptDateTime::new(
+                |config| config.$id.inner().game().unwrap(),
+                |config, value| config.$id.inner_mut().set_game(Some(value)),
+                $default,
+            ),
+        )
+    };
+}
+
 #[cfg(feature = "xq-audio")]
 macro_rules! opt_nonzero_u32_slider {
     (overridable $id: ident, $default: expr, $min: expr, $max: expr, $display_format: expr) => {
@@ -387,6 +463,48 @@ macro_rules! combo {
     };
 }
 
+macro_rules! shader_file {
+    (overridable $id: ident) => {
+        (
+            setting::FileCombo::new(
+                |config| config.$id.inner().global().as_str(),
+                |config, value| config.$id.inner_mut().set_global(value.to_owned()),
+                list_shaders,
+                "None",
+            ),
+            setting::FileCombo::new(
+                |config| config.$id.inner().game().as_deref().unwrap(),
+                |config, value| config.$id.inner_mut().set_game(Some(value.to_owned())),
+                list_shaders,
+                "None",
+            ),
+        )
+    };
+}
+
+macro_rules! audio_output_device {
+    (nonoverridable $id: ident) => {
+        setting::FileCombo::new(
+            |config| config!(config, &$id).as_str(),
+            |config, value| set_config!(config, $id, value.to_owned()),
+            |_| audio::output::output_device_names(),
+            "Default",
+        )
+    };
+}
+
+fn list_shaders(config: &config::Config) -> Vec<String> {
+    post_process::list_shaders(&config!(config, &shader_dir_path).0)
+}
+
+/// Returns the date and time the RTC currently reports when following the host clock, to start
+/// from when freezing it.
+fn rtc_current_date_time(config: &config::Config) -> NaiveDateTime {
+    let now = Local::now().naive_local();
+    Duration::try_seconds(config!(config, rtc_time_offset_seconds))
+        .map_or(now, |offset| now + offset)
+}
+
 macro_rules! nonoverridable {
     ($id: ident, $inner: ident$(, $($args: tt)*)?) => {
         setting::NonOverridable::new(
@@ -512,7 +630,28 @@ struct UiSettings {
     game_icon_mode: setting::NonOverridable<setting::Combo<GameIconMode>>,
     full_window_screen: setting::Overridable<setting::Bool>,
     screen_integer_scale: setting::NonOverridable<setting::Bool>,
+    show_frame_counter: setting::NonOverridable<setting::Bool>,
+    show_input_overlay: setting::NonOverridable<setting::Bool>,
+    show_perf_overlay: setting::NonOverridable<setting::Bool>,
+    idle_when_paused: setting::NonOverridable<setting::Bool>,
+    play_stats_enabled: setting::NonOverridable<setting::Bool>,
+    present_mode: setting::NonOverridable<setting::Combo<PresentMode>>,
+    max_frame_latency: setting::NonOverridable<setting::Slider<u8>>,
     screen_rot: setting::Overridable<setting::Slider<u16>>,
+    screen_gap: setting::Overridable<setting::Slider<u16>>,
+    swap_screens: setting::Overridable<setting::Bool>,
+    engine_screen_assignment: setting::Overridable<setting::Combo<EngineScreenAssignment>>,
+    single_screen: setting::Overridable<setting::Bool>,
+    magnified_screen: setting::Overridable<setting::Bool>,
+    magnified_screen_is_top: setting::Overridable<setting::Bool>,
+    small_screen_scale: setting::Overridable<setting::Slider<f32>>,
+    screen_filter: setting::Overridable<setting::Combo<ScreenFilter>>,
+    lcd_color_profile: setting::Overridable<setting::Combo<LcdColorProfile>>,
+    shader_dir_path: setting::NonOverridable<setting::HomePath>,
+    post_process_shader: setting::Overridable<setting::FileCombo>,
+    detached_bottom_screen: setting::NonOverridable<setting::Bool>,
+    bottom_screen_integer_scale: setting::NonOverridable<setting::Bool>,
+    bottom_screen_rot: setting::Overridable<setting::Slider<u16>>,
 }
 
 impl UiSettings {
@@ -557,7 +696,73 @@ impl UiSettings {
             ),
             full_window_screen: overridable!(full_window_screen, bool),
             screen_integer_scale: nonoverridable!(screen_integer_scale, bool),
+            show_frame_counter: nonoverridable!(show_frame_counter, bool),
+            show_input_overlay: nonoverridable!(show_input_overlay, bool),
+            show_perf_overlay: nonoverridable!(show_perf_overlay, bool),
+            idle_when_paused: nonoverridable!(idle_when_paused, bool),
+            play_stats_enabled: nonoverridable!(play_stats_enabled, bool),
+            present_mode: nonoverridable!(
+                present_mode,
+                combo,
+                &[
+                    PresentMode::Vsync,
+                    PresentMode::Mailbox,
+                    PresentMode::Immediate,
+                ],
+                |mode| mode.name().into()
+            ),
+            max_frame_latency: nonoverridable!(max_frame_latency, slider, 1, 3, "%d frames"),
             screen_rot: overridable!(screen_rot, slider, 0, 359, "%d°"),
+            screen_gap: overridable!(screen_gap, slider, 0, 192, "%d px"),
+            swap_screens: overridable!(swap_screens, bool),
+            engine_screen_assignment: overridable!(
+                engine_screen_assignment,
+                combo,
+                &[
+                    EngineScreenAssignment::Auto,
+                    EngineScreenAssignment::EngineAOnTop,
+                    EngineScreenAssignment::EngineBOnTop,
+                ],
+                |assignment| assignment.name().into()
+            ),
+            single_screen: overridable!(single_screen, bool),
+            magnified_screen: overridable!(magnified_screen, bool),
+            magnified_screen_is_top: overridable!(magnified_screen_is_top, bool),
+            small_screen_scale: overridable!(
+                small_screen_scale,
+                slider,
+                10.0,
+                50.0,
+                "%.0f%%",
+                100.0
+            ),
+            screen_filter: overridable!(
+                screen_filter,
+                combo,
+                &[
+                    ScreenFilter::Nearest,
+                    ScreenFilter::Bilinear,
+                    ScreenFilter::Scale2x,
+                    ScreenFilter::Scanlines,
+                    ScreenFilter::LcdGrid,
+                ],
+                |filter| filter.name().into()
+            ),
+            lcd_color_profile: overridable!(
+                lcd_color_profile,
+                combo,
+                &[
+                    LcdColorProfile::None,
+                    LcdColorProfile::Ds,
+                    LcdColorProfile::DsLite,
+                ],
+                |profile| profile.name().into()
+            ),
+            shader_dir_path: nonoverridable!(shader_dir_path, home_path),
+            post_process_shader: overridable!(post_process_shader, shader_file),
+            detached_bottom_screen: nonoverridable!(detached_bottom_screen, bool),
+            bottom_screen_integer_scale: nonoverridable!(bottom_screen_integer_scale, bool),
+            bottom_screen_rot: overridable!(bottom_screen_rot, slider, 0, 359, "%d°"),
         }
     }
 }
@@ -565,11 +770,17 @@ impl UiSettings {
 struct AudioSettings {
     volume: setting::Overridable<setting::Slider<f32>>,
     sample_chunk_size: setting::Overridable<setting::Scalar<u16>>,
+    output_buffer_len: setting::NonOverridable<setting::Slider<u16>>,
+    output_latency_ms: setting::NonOverridable<setting::Slider<u16>>,
+    output_device: setting::NonOverridable<setting::FileCombo>,
+    output_sample_rate: setting::NonOverridable<setting::Combo<u32>>,
     #[cfg(feature = "xq-audio")]
     custom_sample_rate: setting::Overridable<setting::OptNonZeroU32Slider>,
     #[cfg(feature = "xq-audio")]
     channel_interp_method: setting::Overridable<setting::Combo<AudioChannelInterpMethod>>,
     output_interp_method: setting::Overridable<setting::Combo<audio::InterpMethod>>,
+    output_profile: setting::Overridable<setting::Combo<audio::output::OutputProfile>>,
+    stereo_mode: setting::Overridable<setting::Combo<AudioStereoMode>>,
     input_enabled: setting::Overridable<setting::Bool>,
     input_interp_method: setting::Overridable<setting::Combo<audio::InterpMethod>>,
 }
@@ -579,6 +790,27 @@ impl AudioSettings {
         AudioSettings {
             volume: overridable!(audio_volume, slider, 0.0, 100.0, "%.02f%%", 100.0),
             sample_chunk_size: overridable!(audio_sample_chunk_size, scalar, Some(128), None, "%d"),
+            output_buffer_len: nonoverridable!(
+                audio_output_buffer_len,
+                slider,
+                audio::output::MIN_BUFFER_LEN as u16,
+                audio::output::MAX_BUFFER_LEN as u16,
+                "%d samples"
+            ),
+            output_latency_ms: nonoverridable!(audio_output_latency_ms, slider, 0, 200, "%d ms"),
+            output_device: nonoverridable!(audio_output_device, audio_output_device),
+            output_sample_rate: nonoverridable!(
+                audio_output_sample_rate,
+                combo,
+                &[0, 32000, 44100, 48000, 96000],
+                |sample_rate| {
+                    if *sample_rate == 0 {
+                        "Auto".into()
+                    } else {
+                        format!("{sample_rate} Hz").into()
+                    }
+                }
+            ),
             #[cfg(feature = "xq-audio")]
             custom_sample_rate: overridable!(
                 audio_custom_sample_rate,
@@ -621,6 +853,38 @@ impl AudioSettings {
                     .into()
                 }
             ),
+            output_profile: overridable!(
+                audio_output_profile,
+                combo,
+                &[
+                    audio::output::OutputProfile::Headphones,
+                    audio::output::OutputProfile::Speakers,
+                ],
+                |profile| {
+                    match profile {
+                        audio::output::OutputProfile::Headphones => "Headphones",
+                        audio::output::OutputProfile::Speakers => "Speakers",
+                    }
+                    .into()
+                }
+            ),
+            stereo_mode: overridable!(
+                audio_stereo_mode,
+                combo,
+                &[
+                    AudioStereoMode::Stereo,
+                    AudioStereoMode::Swapped,
+                    AudioStereoMode::Mono,
+                ],
+                |stereo_mode| {
+                    match stereo_mode {
+                        AudioStereoMode::Stereo => "Stereo",
+                        AudioStereoMode::Swapped => "Swapped",
+                        AudioStereoMode::Mono => "Mono",
+                    }
+                    .into()
+                }
+            ),
             input_enabled: overridable!(audio_input_enabled, bool),
             input_interp_method: overridable!(
                 audio_input_interp_method,
@@ -642,6 +906,9 @@ struct SavesSettings {
     save_interval_ms: setting::Overridable<setting::Scalar<f32>>,
     reset_on_save_slot_switch: setting::NonOverridable<setting::Bool>,
     include_save_in_savestates: setting::Overridable<setting::Bool>,
+    auto_savestate_enabled: setting::NonOverridable<setting::Bool>,
+    auto_savestate_interval_mins: setting::NonOverridable<setting::Slider<u16>>,
+    auto_savestate_count: setting::NonOverridable<setting::Slider<u8>>,
     save_dir_path: setting::NonOverridable<setting::HomePath>,
     savestate_dir_path: setting::NonOverridable<setting::HomePath>,
 }
@@ -652,30 +919,126 @@ impl SavesSettings {
             save_interval_ms: overridable!(save_interval_ms, scalar, Some(100.0), None, "%.02f ms"),
             reset_on_save_slot_switch: nonoverridable!(reset_on_save_slot_switch, bool),
             include_save_in_savestates: overridable!(include_save_in_savestates, bool),
+            auto_savestate_enabled: nonoverridable!(auto_savestate_enabled, bool),
+            auto_savestate_interval_mins: nonoverridable!(
+                auto_savestate_interval_mins,
+                slider,
+                1,
+                60,
+                "%d min"
+            ),
+            auto_savestate_count: nonoverridable!(auto_savestate_count, slider, 1, 10, "%d"),
             save_dir_path: nonoverridable!(save_dir_path, home_path),
             savestate_dir_path: nonoverridable!(savestate_dir_path, home_path),
         }
     }
 }
 
+const ACCURACY_PRESET_ITEMS: [Option<AccuracyPreset>; 5] = [
+    Some(AccuracyPreset::Fast),
+    Some(AccuracyPreset::Balanced),
+    Some(AccuracyPreset::Accurate),
+    Some(AccuracyPreset::CycleSynced),
+    None,
+];
+
+fn accuracy_preset_label(preset: &Option<AccuracyPreset>) -> Cow<str> {
+    preset.map_or("Custom", AccuracyPreset::name).into()
+}
+
 struct EmulationSettings {
+    accuracy_preset: setting::Overridable<setting::Combo<Option<AccuracyPreset>>>,
     framerate_ratio_limit: setting::Overridable<setting::BoolAndValueSlider<f32>>,
     paused_framerate_limit: setting::Overridable<setting::Slider<f32>>,
+    fast_forward_speed_limit: setting::Overridable<setting::BoolAndValueSlider<f32>>,
+    turbo_speed_limit: setting::Overridable<setting::BoolAndValueSlider<f32>>,
+    slow_motion_speed: setting::Overridable<setting::Slider<f32>>,
+    hang_timeout_secs: setting::Overridable<setting::BoolAndValueSlider<f32>>,
+    crash_screen_enabled: setting::NonOverridable<setting::Bool>,
+    run_frames_count: setting::Overridable<setting::Scalar<u32>>,
     sync_to_audio: setting::Overridable<setting::Bool>,
+    sub_frame_input: setting::Overridable<setting::Bool>,
+    fast_pointer: setting::Overridable<setting::Bool>,
+    fast_pointer_sensitivity: setting::Overridable<setting::Slider<f32>>,
+    fast_pointer_acceleration: setting::Overridable<setting::Slider<f32>>,
+    touch_requires_hotkey: setting::Overridable<setting::Bool>,
+    stylus_speed: setting::Overridable<setting::Slider<f32>>,
+    touch_pressure: setting::Overridable<setting::Slider<u8>>,
+    gamepad_deadzone: setting::Overridable<setting::Slider<f32>>,
     pause_on_launch: setting::Overridable<setting::Bool>,
     skip_firmware: setting::Overridable<setting::Bool>,
+    return_to_menu_on_shutdown: setting::Overridable<setting::Bool>,
     prefer_hle_bios: setting::Overridable<setting::Bool>,
+    batch_duration: setting::Overridable<setting::Scalar<u32>>,
+    threaded_3d_geometry: setting::Overridable<setting::Bool>,
     model: setting::Overridable<setting::Combo<ModelConfig>>,
     ds_slot_rom_in_memory_max_size: setting::Overridable<setting::Scalar<u32>>,
-    rtc_time_offset_seconds: setting::Overridable<setting::Scalar<i64>>,
+    rtc_time_offset_seconds: setting::Overridable<setting::DateTimeOffset>,
+    rtc_frozen_date_time: setting::Overridable<setting::OptDateTime>,
+    gba_slot_rom_path: setting::Overridable<setting::OptHomePath>,
+    watch_folder_dir_path: setting::NonOverridable<setting::HomePath>,
+    watch_folder_patch_path: setting::Overridable<setting::OptHomePath>,
+    solar_sensor_level: setting::Overridable<setting::Slider<u8>>,
+    ir_link_enabled: setting::NonOverridable<setting::Bool>,
+    ir_link_local_addr: setting::NonOverridable<setting::SocketAddr>,
+    ir_link_peer_addr: setting::NonOverridable<setting::SocketAddr>,
     renderer_2d_kind: setting::Overridable<setting::Combo<Renderer2dKind>>,
     renderer_3d_kind: setting::Overridable<setting::Combo<Renderer3dKind>>,
+    soft_renderer_3d_threads: setting::Overridable<setting::StringFormatSlider<u8>>,
     resolution_scale_shift: setting::Overridable<setting::StringFormatSlider<u8>>,
+    adaptive_resolution_scale: setting::Overridable<setting::Bool>,
+    adaptive_resolution_lower_threshold: setting::NonOverridable<setting::Slider<u8>>,
+    adaptive_resolution_raise_threshold: setting::NonOverridable<setting::Slider<u8>>,
+    adaptive_resolution_hold_frames: setting::NonOverridable<setting::Scalar<u16>>,
+    hi_res_2d_bgs: setting::Overridable<setting::Bool>,
+    msaa_3d: setting::Overridable<setting::Bool>,
+    compute_rasterizer_3d: setting::Overridable<setting::Bool>,
+    texture_filter: setting::Overridable<setting::Combo<TextureFilter>>,
+    texture_anisotropy_shift: setting::Overridable<setting::StringFormatSlider<u8>>,
+    texture_cache_mode: setting::Overridable<setting::Combo<TextureCacheMode>>,
+    dump_3d_textures: setting::Overridable<setting::Bool>,
+    texture_dump_dir_path: setting::NonOverridable<setting::HomePath>,
+    replace_3d_textures: setting::Overridable<setting::Bool>,
+    texture_pack_dir_path: setting::NonOverridable<setting::HomePath>,
 }
 
 impl EmulationSettings {
     fn new() -> Self {
         EmulationSettings {
+            accuracy_preset: setting::Overridable::new(
+                (
+                    setting::Combo::new(
+                        |config| AccuracyPreset::matching(&AccuracySettings::global(config)),
+                        |config, preset| {
+                            if let Some(preset) = preset {
+                                preset.settings().set_global(config);
+                            }
+                        },
+                        &ACCURACY_PRESET_ITEMS,
+                        accuracy_preset_label,
+                    ),
+                    setting::Combo::new(
+                        |config| AccuracyPreset::matching(&AccuracySettings::game(config)),
+                        |config, preset| {
+                            if let Some(preset) = preset {
+                                preset.settings().set_game(config);
+                            }
+                        },
+                        &ACCURACY_PRESET_ITEMS,
+                        accuracy_preset_label,
+                    ),
+                ),
+                AccuracySettings::is_overridden,
+                |config, enabled| {
+                    if enabled {
+                        AccuracySettings::game(config).set_game(config);
+                    } else {
+                        AccuracySettings::unset_game(config);
+                    }
+                },
+                AccuracySettings::set_default_global,
+                AccuracySettings::set_default_game,
+            ),
             framerate_ratio_limit: overridable!(
                 framerate_ratio_limit,
                 bool_and_value_slider,
@@ -691,10 +1054,66 @@ impl EmulationSettings {
                 480.0,
                 "%.02f FPS"
             ),
+            fast_forward_speed_limit: overridable!(
+                fast_forward_speed_limit,
+                bool_and_value_slider,
+                100.0,
+                1600.0,
+                "%.02f%%",
+                100.0
+            ),
+            turbo_speed_limit: overridable!(
+                turbo_speed_limit,
+                bool_and_value_slider,
+                100.0,
+                1600.0,
+                "%.02f%%",
+                100.0
+            ),
+            slow_motion_speed: overridable!(
+                slow_motion_speed,
+                slider,
+                5.0,
+                100.0,
+                "%.02f%%",
+                100.0
+            ),
+            hang_timeout_secs: overridable!(
+                hang_timeout_secs,
+                bool_and_value_slider,
+                1.0,
+                60.0,
+                "%.01f s"
+            ),
+            crash_screen_enabled: nonoverridable!(crash_screen_enabled, bool),
+            run_frames_count: overridable!(run_frames_count, scalar, Some(1), None, "%d"),
             sync_to_audio: overridable!(sync_to_audio, bool),
+            sub_frame_input: overridable!(sub_frame_input, bool),
+            fast_pointer: overridable!(fast_pointer, bool),
+            fast_pointer_sensitivity: overridable!(
+                fast_pointer_sensitivity,
+                slider,
+                0.01,
+                2.0,
+                "%.02f px"
+            ),
+            fast_pointer_acceleration: overridable!(
+                fast_pointer_acceleration,
+                slider,
+                0.0,
+                0.5,
+                "%.03f"
+            ),
+            touch_requires_hotkey: overridable!(touch_requires_hotkey, bool),
+            stylus_speed: overridable!(stylus_speed, slider, 16.0, 1024.0, "%.0f px/s"),
+            touch_pressure: overridable!(touch_pressure, slider, 0, 255, "%d"),
+            gamepad_deadzone: overridable!(gamepad_deadzone, slider, 0.05, 0.95, "%.02f"),
             pause_on_launch: overridable!(pause_on_launch, bool),
             skip_firmware: overridable!(skip_firmware, bool),
+            return_to_menu_on_shutdown: overridable!(return_to_menu_on_shutdown, bool),
             prefer_hle_bios: overridable!(prefer_hle_bios, bool),
+            batch_duration: overridable!(batch_duration, scalar, Some(1), Some(4096), "%d cycles"),
+            threaded_3d_geometry: overridable!(threaded_3d_geometry, bool),
             model: overridable!(
                 model,
                 combo,
@@ -723,13 +1142,24 @@ impl EmulationSettings {
                 Some((u32::MAX >> 1) + 1),
                 "%d B"
             ),
-            rtc_time_offset_seconds: overridable!(
-                rtc_time_offset_seconds,
-                scalar,
-                Some(1),
-                None,
-                "%d s"
+            rtc_time_offset_seconds: overridable!(rtc_time_offset_seconds, date_time_offset),
+            rtc_frozen_date_time: overridable!(
+                rtc_frozen_date_time,
+                opt_date_time,
+                rtc_current_date_time
+            ),
+            gba_slot_rom_path: overridable!(gba_slot_rom_path, opt_home_path, "", false),
+            watch_folder_dir_path: nonoverridable!(watch_folder_dir_path, home_path),
+            watch_folder_patch_path: overridable!(
+                watch_folder_patch_path,
+                opt_home_path,
+                "",
+                false
             ),
+            solar_sensor_level: overridable!(solar_sensor_level, slider, 0, 255, "%d"),
+            ir_link_enabled: nonoverridable!(ir_link_enabled, bool),
+            ir_link_local_addr: nonoverridable!(ir_link_local_addr, socket_addr),
+            ir_link_peer_addr: nonoverridable!(ir_link_peer_addr, socket_addr),
             renderer_2d_kind: overridable!(
                 renderer_2d_kind,
                 combo,
@@ -756,6 +1186,13 @@ impl EmulationSettings {
                 }
                 .into()
             ),
+            soft_renderer_3d_threads: overridable!(
+                soft_renderer_3d_threads,
+                string_format_slider,
+                1,
+                8,
+                |value| value.to_string()
+            ),
             resolution_scale_shift: overridable!(
                 resolution_scale_shift,
                 string_format_slider,
@@ -763,11 +1200,67 @@ impl EmulationSettings {
                 3,
                 |value| format!("{}x", 1 << value)
             ),
+            adaptive_resolution_scale: overridable!(adaptive_resolution_scale, bool),
+            adaptive_resolution_lower_threshold: nonoverridable!(
+                adaptive_resolution_lower_threshold,
+                slider,
+                1,
+                200,
+                "%d%%"
+            ),
+            adaptive_resolution_raise_threshold: nonoverridable!(
+                adaptive_resolution_raise_threshold,
+                slider,
+                0,
+                100,
+                "%d%%"
+            ),
+            adaptive_resolution_hold_frames: nonoverridable!(
+                adaptive_resolution_hold_frames,
+                scalar,
+                Some(1),
+                Some(600),
+                "%d"
+            ),
+            hi_res_2d_bgs: overridable!(hi_res_2d_bgs, bool),
+            msaa_3d: overridable!(msaa_3d, bool),
+            compute_rasterizer_3d: overridable!(compute_rasterizer_3d, bool),
+            texture_filter: overridable!(
+                texture_filter,
+                combo,
+                &[TextureFilter::Nearest, TextureFilter::Bilinear],
+                |filter| filter.name().into()
+            ),
+            texture_anisotropy_shift: overridable!(
+                texture_anisotropy_shift,
+                string_format_slider,
+                0,
+                4,
+                |value| if value == 0 {
+                    "Off".to_owned()
+                } else {
+                    format!("{}x", 1 << value)
+                }
+            ),
+            texture_cache_mode: overridable!(
+                texture_cache_mode,
+                combo,
+                &[
+                    TextureCacheMode::Auto,
+                    TextureCacheMode::Combined,
+                    TextureCacheMode::PaletteSeparated,
+                ],
+                |mode| mode.name().into()
+            ),
+            dump_3d_textures: overridable!(dump_3d_textures, bool),
+            texture_dump_dir_path: nonoverridable!(texture_dump_dir_path, home_path),
+            replace_3d_textures: overridable!(replace_3d_textures, bool),
+            texture_pack_dir_path: nonoverridable!(texture_pack_dir_path, home_path),
         }
     }
 }
 
-#[cfg(any(feature = "logging", feature = "gdb-server"))]
+#[cfg(any(feature = "logging", feature = "gdb-server", feature = "virtual-time"))]
 struct DebugSettings {
     #[cfg(feature = "logging")]
     logging_kind: setting::NonOverridable<setting::Combo<LoggingKind>>,
@@ -775,9 +1268,11 @@ struct DebugSettings {
     imgui_log_history_capacity: setting::Overridable<setting::Scalar<u32>>,
     #[cfg(feature = "gdb-server")]
     gdb_server_addr: setting::NonOverridable<setting::SocketAddr>,
+    #[cfg(feature = "virtual-time")]
+    virtual_time_server_addr: setting::NonOverridable<setting::SocketAddr>,
 }
 
-#[cfg(any(feature = "logging", feature = "gdb-server"))]
+#[cfg(any(feature = "logging", feature = "gdb-server", feature = "virtual-time"))]
 impl DebugSettings {
     fn new() -> Self {
         DebugSettings {
@@ -802,6 +1297,149 @@ impl DebugSettings {
             ),
             #[cfg(feature = "gdb-server")]
             gdb_server_addr: nonoverridable!(gdb_server_addr, socket_addr),
+            #[cfg(feature = "virtual-time")]
+            virtual_time_server_addr: nonoverridable!(virtual_time_server_addr, socket_addr),
+        }
+    }
+}
+
+#[cfg(any(feature = "ffmpeg", feature = "frame-dump", feature = "wav-dump"))]
+struct RecordingSettings {
+    #[cfg(feature = "ffmpeg")]
+    ffmpeg_path: setting::NonOverridable<setting::OptHomePath>,
+    #[cfg(feature = "ffmpeg")]
+    container: setting::NonOverridable<setting::Combo<RecordingContainer>>,
+    #[cfg(feature = "ffmpeg")]
+    video_codec: setting::NonOverridable<setting::Combo<RecordingVideoCodec>>,
+    #[cfg(feature = "ffmpeg")]
+    video_bitrate_kbps: setting::NonOverridable<setting::Scalar<u32>>,
+    #[cfg(feature = "ffmpeg")]
+    audio_bitrate_kbps: setting::NonOverridable<setting::Scalar<u32>>,
+    #[cfg(feature = "frame-dump")]
+    frame_dump_dir_path: setting::NonOverridable<setting::HomePath>,
+    #[cfg(feature = "frame-dump")]
+    frame_dump_name_template: setting::NonOverridable<setting::String>,
+    #[cfg(feature = "frame-dump")]
+    frame_dump_format: setting::NonOverridable<setting::Combo<FrameDumpFormat>>,
+    #[cfg(feature = "frame-dump")]
+    frame_dump_3d_layer: setting::NonOverridable<setting::Bool>,
+    #[cfg(feature = "frame-dump")]
+    frame_dump_2d_layers: setting::NonOverridable<setting::Bool>,
+    #[cfg(any(feature = "ffmpeg", feature = "frame-dump"))]
+    capture_layout: setting::NonOverridable<setting::Combo<CaptureLayout>>,
+    #[cfg(feature = "wav-dump")]
+    wav_dump_channels: setting::NonOverridable<setting::Bool>,
+}
+
+#[cfg(any(feature = "ffmpeg", feature = "frame-dump", feature = "wav-dump"))]
+impl RecordingSettings {
+    fn new() -> Self {
+        RecordingSettings {
+            #[cfg(feature = "ffmpeg")]
+            ffmpeg_path: nonoverridable!(ffmpeg_path, opt_home_path, "ffmpeg", false),
+            #[cfg(feature = "ffmpeg")]
+            container: nonoverridable!(
+                recording_container,
+                combo,
+                &[
+                    RecordingContainer::Mp4,
+                    RecordingContainer::WebM,
+                    RecordingContainer::Matroska,
+                ],
+                |container| container.name().into()
+            ),
+            #[cfg(feature = "ffmpeg")]
+            video_codec: nonoverridable!(
+                recording_video_codec,
+                combo,
+                &[
+                    RecordingVideoCodec::H264,
+                    RecordingVideoCodec::H265,
+                    RecordingVideoCodec::Vp9,
+                    RecordingVideoCodec::Av1,
+                ],
+                |video_codec| video_codec.name().into()
+            ),
+            #[cfg(feature = "ffmpeg")]
+            video_bitrate_kbps: nonoverridable!(
+                recording_video_bitrate_kbps,
+                scalar,
+                Some(100),
+                None,
+                "%d kbps"
+            ),
+            #[cfg(feature = "ffmpeg")]
+            audio_bitrate_kbps: nonoverridable!(
+                recording_audio_bitrate_kbps,
+                scalar,
+                Some(16),
+                None,
+                "%d kbps"
+            ),
+            #[cfg(feature = "frame-dump")]
+            frame_dump_dir_path: nonoverridable!(frame_dump_dir_path, home_path),
+            #[cfg(feature = "frame-dump")]
+            frame_dump_name_template: nonoverridable!(frame_dump_name_template, string),
+            #[cfg(feature = "frame-dump")]
+            frame_dump_format: nonoverridable!(
+                frame_dump_format,
+                combo,
+                &[FrameDumpFormat::Png, FrameDumpFormat::Raw],
+                |format| format.name().into()
+            ),
+            #[cfg(feature = "frame-dump")]
+            frame_dump_3d_layer: nonoverridable!(frame_dump_3d_layer, bool),
+            #[cfg(feature = "frame-dump")]
+            frame_dump_2d_layers: nonoverridable!(frame_dump_2d_layers, bool),
+            #[cfg(any(feature = "ffmpeg", feature = "frame-dump"))]
+            capture_layout: nonoverridable!(
+                capture_layout,
+                combo,
+                &[CaptureLayout::MatchScreen, CaptureLayout::RawStacked],
+                |layout| layout.name().into()
+            ),
+            #[cfg(feature = "wav-dump")]
+            wav_dump_channels: nonoverridable!(wav_dump_channels, bool),
+        }
+    }
+}
+
+#[cfg(feature = "remote-display")]
+struct RemoteDisplaySettings {
+    addr: setting::NonOverridable<setting::SocketAddr>,
+    encoding: setting::NonOverridable<setting::Combo<RemoteDisplayEncoding>>,
+    jpeg_quality: setting::NonOverridable<setting::Scalar<u8>>,
+    screens: setting::NonOverridable<setting::Combo<RemoteDisplayScreens>>,
+}
+
+#[cfg(feature = "remote-display")]
+impl RemoteDisplaySettings {
+    fn new() -> Self {
+        RemoteDisplaySettings {
+            addr: nonoverridable!(remote_display_addr, socket_addr),
+            encoding: nonoverridable!(
+                remote_display_encoding,
+                combo,
+                &[RemoteDisplayEncoding::Rgb565, RemoteDisplayEncoding::Mjpeg],
+                |encoding| encoding.name().into()
+            ),
+            jpeg_quality: nonoverridable!(
+                remote_display_jpeg_quality,
+                scalar,
+                Some(5),
+                Some(100),
+                "%d"
+            ),
+            screens: nonoverridable!(
+                remote_display_screens,
+                combo,
+                &[
+                    RemoteDisplayScreens::Both,
+                    RemoteDisplayScreens::Top,
+                    RemoteDisplayScreens::Bottom,
+                ],
+                |screens| screens.name().into()
+            ),
         }
     }
 }
@@ -826,8 +1464,12 @@ struct Settings {
     audio: AudioSettings,
     saves: SavesSettings,
     emulation: EmulationSettings,
-    #[cfg(any(feature = "logging", feature = "gdb-server"))]
+    #[cfg(any(feature = "ffmpeg", feature = "frame-dump", feature = "wav-dump"))]
+    recording: RecordingSettings,
+    #[cfg(any(feature = "logging", feature = "gdb-server", feature = "virtual-time"))]
     debug: DebugSettings,
+    #[cfg(feature = "remote-display")]
+    remote_display: RemoteDisplaySettings,
     #[cfg(feature = "discord-presence")]
     discord_presence: DiscordPresenceSettings,
 }
@@ -839,17 +1481,22 @@ enum Tab {
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
-enum Section {
+pub(super) enum Section {
     Paths,
     Ui,
     Audio,
     Saves,
     Emulation,
     Input,
-    #[cfg(any(feature = "logging", feature = "gdb-server"))]
+    #[cfg(any(feature = "ffmpeg", feature = "frame-dump", feature = "wav-dump"))]
+    Recording,
+    #[cfg(any(feature = "logging", feature = "gdb-server", feature = "virtual-time"))]
     Debug,
+    #[cfg(feature = "remote-display")]
+    RemoteDisplay,
     #[cfg(feature = "discord-presence")]
     DiscordPresence,
+    ChangedSettings,
 }
 
 struct SettingsData {
@@ -893,8 +1540,12 @@ impl Settings {
             audio: AudioSettings::new(),
             saves: SavesSettings::new(),
             emulation: EmulationSettings::new(),
-            #[cfg(any(feature = "logging", feature = "gdb-server"))]
+            #[cfg(any(feature = "ffmpeg", feature = "frame-dump", feature = "wav-dump"))]
+            recording: RecordingSettings::new(),
+            #[cfg(any(feature = "logging", feature = "gdb-server", feature = "virtual-time"))]
             debug: DebugSettings::new(),
+            #[cfg(feature = "remote-display")]
+            remote_display: RemoteDisplaySettings::new(),
             #[cfg(feature = "discord-presence")]
             discord_presence: DiscordPresenceSettings::new(),
         }
@@ -905,6 +1556,7 @@ pub(super) struct Editor {
     settings: Settings,
     cur_section: Section,
     input_map_editor: Option<InputMapEditor>,
+    sys_sets_editor: Option<SysSetsEditor>,
     data: SettingsData,
 }
 
@@ -916,6 +1568,7 @@ impl Editor {
             settings: Settings::new(),
             cur_section: Section::Paths,
             input_map_editor: None,
+            sys_sets_editor: None,
             data: SettingsData {
                 game_loaded: false,
                 help_buttons_enabled: false,
@@ -926,12 +1579,24 @@ impl Editor {
         }
     }
 
+    pub fn set_section(&mut self, section: Section) {
+        self.data.cur_help_item = None;
+        self.data.next_help_item = None;
+        self.cur_section = section;
+    }
+
     pub fn process_event(&mut self, event: &winit::event::Event<()>, config: &mut Config) {
         if let Some(input_map_editor) = &mut self.input_map_editor {
             input_map_editor.process_event(event, &mut config.config);
         }
     }
 
+    pub fn process_gamepad_input(&mut self, key: PressedKey, pressed: bool, config: &mut Config) {
+        if let Some(input_map_editor) = &mut self.input_map_editor {
+            input_map_editor.process_gamepad_input(key, pressed, &mut config.config);
+        }
+    }
+
     pub fn emu_stopped(&mut self) {
         if let Some(input_map_editor) = &mut self.input_map_editor {
             input_map_editor.emu_stopped();
@@ -1078,6 +1743,93 @@ impl Editor {
                 );
             }
         }
+
+        ui.text("Save type: ");
+        ui.same_line();
+
+        let mut save_type_override = config!(config.config, save_type_override);
+        let items = iter::once(None)
+            .chain(SaveType::ALL.into_iter().map(Some))
+            .collect::<Vec<_>>();
+        ui.set_next_item_width(ui.content_region_avail()[0]);
+        if combo_value(
+            ui,
+            "##save_type",
+            &mut save_type_override,
+            &items,
+            |save_type| match save_type {
+                Some(save_type) => save_type.name().into(),
+                None => match emu_state.detected_save_type {
+                    Some((save_type, source)) => format!(
+                        "Auto-detect ({}, from {})",
+                        save_type.name(),
+                        source.description()
+                    )
+                    .into(),
+                    None => "Auto-detect (from the existing save file)".into(),
+                },
+            },
+        ) {
+            set_config!(config.config, save_type_override, save_type_override);
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text(
+                "Overrides the save memory type reported by the game database, for games that are \
+                 missing from it or misdetected; takes effect after restarting the game.",
+            );
+        }
+    }
+
+    fn draw_changed_settings(&self, ui: &Ui, config: &mut Config, emu_state: Option<&EmuState>) {
+        let diff = config.config.diff();
+        let game_title = emu_state
+            .filter(|emu_state| emu_state.game_loaded)
+            .map(|emu_state| emu_state.title.as_str());
+
+        if ui.button("\u{f0c5} Copy report") {
+            ui.set_clipboard_text(diff.report(game_title));
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text(
+                "Copy a list of all changed settings to the clipboard, to attach to bug reports",
+            );
+        }
+
+        add_y_spacing(ui, 8.0);
+        heading(ui, "Global", 16.0, 5.0, BORDER_WIDTH);
+        if let Some(name) = draw_diff_entries(
+            ui,
+            "global",
+            &diff.global,
+            "All global settings are at their default values",
+        ) {
+            config.config.reset_global_setting(name);
+        }
+
+        add_y_spacing(ui, 8.0);
+        match game_title {
+            Some(title) => {
+                heading(
+                    ui,
+                    &format!("Game overrides - {title}"),
+                    16.0,
+                    5.0,
+                    BORDER_WIDTH,
+                );
+                if let Some(name) = draw_diff_entries(
+                    ui,
+                    "game",
+                    &diff.game,
+                    "No settings are overridden for this game",
+                ) {
+                    config.config.reset_game_setting(name);
+                }
+            }
+            None => {
+                heading(ui, "Game overrides", 16.0, 5.0, BORDER_WIDTH);
+                ui.text_disabled("Load a game to see its overridden settings");
+            }
+        }
     }
 
     fn draw_control_buttons(&mut self, ui: &Ui, config: &mut Config, emu_state: Option<&EmuState>) {
@@ -1166,7 +1918,10 @@ impl Editor {
                 }
                 if game {
                     let _ = config::File {
-                        contents: config.config.serialize_game(),
+                        contents: config::GameFile {
+                            settings: config.config.serialize_game(),
+                            play_stats: config.play_stats.clone(),
+                        },
                         path: Some(config.game_path.as_ref().unwrap().clone()),
                     }
                     .write();
@@ -1253,10 +2008,15 @@ impl Editor {
             ("\u{f0c7} Saves", Section::Saves),
             ("\u{f2db} Emulation", Section::Emulation),
             ("\u{f11b} Input", Section::Input),
-            #[cfg(any(feature = "logging", feature = "gdb-server"))]
+            #[cfg(any(feature = "ffmpeg", feature = "frame-dump", feature = "wav-dump"))]
+            ("\u{f03d} Recording", Section::Recording),
+            #[cfg(any(feature = "logging", feature = "gdb-server", feature = "virtual-time"))]
             ("\u{f7d9} Debug", Section::Debug),
+            #[cfg(feature = "remote-display")]
+            ("\u{f3cd} Remote display", Section::RemoteDisplay),
             #[cfg(feature = "discord-presence")]
             ("\u{f392} Discord presence", Section::DiscordPresence),
+            ("\u{f1da} Changed settings", Section::ChangedSettings),
         ];
 
         ui.child_window("section_list")
@@ -1478,71 +2238,300 @@ impl Editor {
                                 )
                             ]
                         );
+
+                        add_y_spacing(ui, 8.0);
+                        heading(ui, "System file sets", 16.0, 5.0, BORDER_WIDTH);
+                        self.sys_sets_editor
+                            .get_or_insert_with(SysSetsEditor::new)
+                            .draw(ui, &mut config.config, &self.data);
                     }
 
                     Section::Ui => {
                         // title_bar_mode
                         // full_window_screen
                         // screen_integer_scale
+                        // show_frame_counter
+                        // show_input_overlay
+                        // show_perf_overlay
+                        // idle_when_paused
+                        // play_stats_enabled
+                        // present_mode
+                        // max_frame_latency
                         // screen_rot
+                        // screen_gap
+                        // swap_screens
+                        // engine_screen_assignment
+                        // single_screen
+                        // magnified_screen
+                        // magnified_screen_is_top
+                        // small_screen_scale
+                        // screen_filter
+                        // lcd_color_profile
+                        // shader_dir_path
+                        // post_process_shader
+                        // detached_bottom_screen
+                        // bottom_screen_integer_scale
+                        // bottom_screen_rot
 
                         draw!(
                             "UI",
                             ui,
-                            [(
-                                "General",
-                                [
-                                    #[cfg(target_os = "macos")]
-                                    (
-                                        title_bar_mode,
-                                        "Title bar mode",
-                                        "How to display the title bar:
+                            [
+                                (
+                                    "General",
+                                    [
+                                        #[cfg(target_os = "macos")]
+                                        (
+                                            title_bar_mode,
+                                            "Title bar mode",
+                                            "How to display the title bar:
 - System: will use the system title bar and display the emulator's menu under it
 - Mixed: will blend the emulator's menu with the transparent system title bar, used to display the \
 title and FPS
 - Imgui: will completely hide the system title bar and render the title and FPS as part of the \
 menu",
-                                    ),
-                                    #[cfg(any(target_os = "macos", target_os = "linux", target_os = "windows"))]
-                                    (
-                                        game_icon_mode,
-                                        "Game icon mode",
-                                        "How to display the currently running game's icon:
+                                        ),
+                                        #[cfg(any(target_os = "macos", target_os = "linux", target_os = "windows"))]
+                                        (
+                                            game_icon_mode,
+                                            "Game icon mode",
+                                            "How to display the currently running game's icon:
 - None: won't display any icon
 - File (macOS only): will display the system icon of the game file that was booted (won't work \
 with the Imgui title bar mode)
 - Game: will display the game's 32x32 icon provided in its binary",
-                                    ),
-                                    (
-                                        full_window_screen,
-                                        "Full-window screen",
-                                        "Whether the screen should be fill the entire emulator \
-                                         window background, instead of being rendered as its own \
-                                         Imgui window.",
-                                    ),
-                                    (
-                                        screen_integer_scale,
-                                        "Limit screen size to integer scales",
-                                        "Whether the screen should be shrunk down to limit its \
-                                         displayed size to multiples of 256x384 (intended to \
-                                         prevent uneven pixel scaling at lower resolutions).",
-                                    ),
-                                    (
-                                        screen_rot,
-                                        "Screen rotation",
-                                        "The clockwise rotation to apply to the screen in degrees \
-                                         (intended for games that require the physical system to \
-                                         be rotated).",
-                                    )
-                                ]
-                            )]
-                        );
-                    }
-
-                    Section::Audio => {
-                        // audio_volume
-                        // audio_sample_chunk_size
-                        // audio_custom_sample_rate
+                                        ),
+                                        (
+                                            full_window_screen,
+                                            "Full-window screen",
+                                            "Whether the screen should be fill the entire \
+                                             emulator window background, instead of being \
+                                             rendered as its own Imgui window.",
+                                        ),
+                                        (
+                                            screen_integer_scale,
+                                            "Limit screen size to integer scales",
+                                            "Whether the screen should be shrunk down to limit \
+                                             its displayed size to multiples of 256x384 (intended \
+                                             to prevent uneven pixel scaling at lower \
+                                             resolutions).",
+                                        ),
+                                        (
+                                            show_frame_counter,
+                                            "Show frame counter",
+                                            "Whether to display the number of frames emulated \
+                                             since the game was started or reset in the title.",
+                                        ),
+                                        (
+                                            show_input_overlay,
+                                            "Show input overlay",
+                                            "Whether to display the keys held and the touchscreen \
+                                             position seen by the emulated console every frame in \
+                                             a corner of the window (useful for streaming and for \
+                                             checking recorded inputs).",
+                                        ),
+                                        (
+                                            show_perf_overlay,
+                                            "Show performance overlay",
+                                            "Whether to display the emulation speed, the amount \
+                                             of buffered audio, how many times audio output ran \
+                                             out of samples (underruns) or had to drop them \
+                                             (overruns), how long frames take to be handed over \
+                                             to the display and the time taken by each part of \
+                                             the emulator every frame in a corner of the window.",
+                                        ),
+                                        (
+                                            idle_when_paused,
+                                            "Idle when paused",
+                                            "Whether to stop redrawing the window continuously \
+                                             while emulation is paused and nothing is being \
+                                             interacted with, only updating it on input or a few \
+                                             times per second, to save power.",
+                                        ),
+                                        (
+                                            play_stats_enabled,
+                                            "Track play time",
+                                            "Whether to record how long and how many times each \
+                                             game was played, and when it was last played, in its \
+                                             configuration (shown in the ROM info window).",
+                                        ),
+                                        (
+                                            screen_rot,
+                                            "Screen rotation",
+                                            "The clockwise rotation to apply to the screen in \
+                                             degrees (intended for games that require the \
+                                             physical system to be rotated).",
+                                        ),
+                                        (
+                                            screen_filter,
+                                            "Screen filter",
+                                            "The filter to apply when scaling the screens up for \
+                                             display:
+- Nearest: no filtering, keeping pixels sharp
+- Bilinear: smooth interpolation between pixels
+- Scale2x: doubles the resolution while rounding off diagonal edges, intended for pixel art
+- Scanlines: darkens every third line to imitate a CRT
+- LCD grid: darkens the edges of each pixel to imitate the gaps in an LCD's pixel grid",
+                                        ),
+                                        (
+                                            lcd_color_profile,
+                                            "LCD color profile",
+                                            "The color correction to apply to imitate the \
+                                             washed-out colors of the original screens, before \
+                                             any filter:
+- None: displays the colors output by the console as-is
+- DS: imitates the darker, less saturated screens of the original DS
+- DS Lite: imitates the brighter screens of the DS Lite",
+                                        )
+                                    ]
+                                ),
+                                (
+                                    "Screen layout",
+                                    [
+                                        (
+                                            screen_gap,
+                                            "Screen gap",
+                                            "The size of the gap between the two screens, in DS \
+                                             pixels (scaled along with the screens).",
+                                        ),
+                                        (
+                                            swap_screens,
+                                            "Swap screens",
+                                            "Whether the bottom screen should be displayed above \
+                                             the top one; in single-screen mode, whether the \
+                                             bottom screen should be the one displayed (can also \
+                                             be toggled with its hotkey).",
+                                        ),
+                                        (
+                                            engine_screen_assignment,
+                                            "Engine screen assignment",
+                                            "Which screen to display each 2D engine's output on; \
+                                             games can switch the screen the main engine (the \
+                                             only one able to display 3D graphics) is sent to at \
+                                             any time, which this can override. The touchscreen \
+                                             always follows the DS' bottom screen. Only applies \
+                                             to the standard layout while the bottom screen isn't \
+                                             detached.",
+                                        ),
+                                        (
+                                            single_screen,
+                                            "Single screen",
+                                            "Whether to only display one screen at a time (the top \
+                                             one, or the bottom one if swapped) in the main \
+                                             window, using all of the available space.",
+                                        ),
+                                        (
+                                            magnified_screen,
+                                            "Magnified screen",
+                                            "Whether to display one screen much larger than the \
+                                             other, with the smaller one in the top-right corner \
+                                             beside it, i.e. to make the touch screen easier to \
+                                             see and use; takes precedence over the single-screen \
+                                             mode and the screen gap.",
+                                        ),
+                                        (
+                                            magnified_screen_is_top,
+                                            "Magnify top screen",
+                                            "Whether the top screen should be the magnified one \
+                                             instead of the bottom one (can also be toggled with \
+                                             its hotkey).",
+                                        ),
+                                        (
+                                            small_screen_scale,
+                                            "Small screen size",
+                                            "The size of the smaller screen when a screen is \
+                                             magnified, relative to the magnified one.",
+                                        )
+                                    ]
+                                ),
+                                (
+                                    "Presentation",
+                                    [
+                                        (
+                                            present_mode,
+                                            "Present mode",
+                                            "How frames are synchronized with the display:
+- Vsync: waits for the display to refresh, never tearing
+- Low-latency vsync: replaces queued frames with newer ones instead of waiting for them to be \
+shown, if supported (uses vsync otherwise)
+- No vsync: presents frames immediately, with minimal latency but possible tearing (also \
+disables display sync on Metal)",
+                                        ),
+                                        (
+                                            max_frame_latency,
+                                            "Maximum queued frames",
+                                            "The maximum number of frames that can be waiting to \
+                                             be shown on the display at once; lower values reduce \
+                                             input latency at the cost of possible stutters. On \
+                                             Direct3D 12 this sets the flip-model swapchain's \
+                                             maximum frame latency, and on Metal the number of \
+                                             drawables.",
+                                        )
+                                    ]
+                                ),
+                                (
+                                    "Post-processing",
+                                    [
+                                        (
+                                            shader_dir_path,
+                                            "Shader directory",
+                                            "The directory to look for custom WGSL \
+                                             post-processing shaders in.",
+                                        ),
+                                        (
+                                            post_process_shader,
+                                            "Post-processing shader",
+                                            "A custom WGSL shader from the shader directory to \
+                                             apply over both screens after the screen filter \
+                                             (for example, for color correction or CRT \
+                                             emulation). Shaders only need to define an \
+                                             `fs_main` fragment entry point, and can set their \
+                                             output resolution to a multiple of the screens' \
+                                             with a `//! scale: <n>` line; they're reloaded \
+                                             automatically whenever they're modified.",
+                                        )
+                                    ]
+                                ),
+                                (
+                                    "Bottom screen window",
+                                    [
+                                        (
+                                            detached_bottom_screen,
+                                            "Show bottom screen in separate window",
+                                            "Whether the bottom screen should be displayed in its \
+                                             own system window, which will also receive \
+                                             touchscreen input, leaving only the top screen in \
+                                             the main one.",
+                                        ),
+                                        (
+                                            bottom_screen_integer_scale,
+                                            "Limit bottom screen size to integer scales",
+                                            "Whether the detached bottom screen should be shrunk \
+                                             down to limit its displayed size to multiples of \
+                                             256x192.",
+                                        ),
+                                        (
+                                            bottom_screen_rot,
+                                            "Bottom screen rotation",
+                                            "The clockwise rotation to apply to the detached \
+                                             bottom screen in degrees.",
+                                        )
+                                    ]
+                                )
+                            ]
+                        );
+                    }
+
+                    Section::Audio => {
+                        // audio_volume
+                        // audio_sample_chunk_size
+                        // audio_output_buffer_len
+                        // audio_output_latency_ms
+                        // audio_output_device
+                        // audio_output_sample_rate
+                        // audio_output_profile
+                        // audio_stereo_mode
+                        // audio_custom_sample_rate
                         // audio_channel_interp_method
                         // audio_interp_method
 
@@ -1564,6 +2553,56 @@ with the Imgui title bar mode)
                                             "(Advanced) How many samples to produce in the \
                                              emulator's core before they're queued to be played \
                                              back.",
+                                        ),
+                                        (
+                                            output_buffer_len,
+                                            "Buffer length",
+                                            "How many samples can be queued for playback at most \
+                                             (rounded up to a power of two); longer buffers are \
+                                             less prone to underruns on slow systems, at the cost \
+                                             of added latency. Changes are applied the next time \
+                                             a game is started.",
+                                        ),
+                                        (
+                                            output_latency_ms,
+                                            "Device latency",
+                                            "How often the audio output device should request \
+                                             new samples, in milliseconds, or 0 to let the \
+                                             device decide; lower values reduce latency but \
+                                             might cause crackling on slow systems. Changes are \
+                                             applied the next time a game is started.",
+                                        ),
+                                        (
+                                            output_device,
+                                            "Device",
+                                            "The audio output device to play sound on; if it's \
+                                             disconnected, playback switches to the system's \
+                                             default device.",
+                                        ),
+                                        (
+                                            output_sample_rate,
+                                            "Device sample rate",
+                                            "The sample rate to run the audio output device at; \
+                                             Auto uses the device's default rate, which avoids \
+                                             unusable configurations reported by some systems \
+                                             (e.g. PipeWire on Linux).",
+                                        ),
+                                        (
+                                            output_profile,
+                                            "Output profile",
+                                            "How the console's audio output should sound:
+- Headphones: plays it as-is, like the console's headphone jack
+- Speakers: imitates the console's built-in speakers, cutting off bass and the highest \
+frequencies and blending the two channels together somewhat",
+                                        ),
+                                        (
+                                            stereo_mode,
+                                            "Stereo mode",
+                                            "How the console's left and right outputs are mixed \
+                                             before playback:
+- Stereo: plays them as-is
+- Swapped: exchanges the left and right channels
+- Mono: plays the average of both on each channel",
                                         )
                                     ]
                                 ),
@@ -1628,6 +2667,9 @@ with the Imgui title bar mode)
                         // save_interval_ms
                         // reset_on_save_slot_switch
                         // include_save_in_savestates
+                        // auto_savestate_enabled
+                        // auto_savestate_interval_mins
+                        // auto_savestate_count
                         // save_dir_path
                         // save_path_config
 
@@ -1657,6 +2699,25 @@ with the Imgui title bar mode)
                                          corruption due to inconsistencies when loading a \
                                          savestate).",
                                     ),
+                                    (
+                                        auto_savestate_enabled,
+                                        "Auto-savestates",
+                                        "Whether to periodically create savestates in the \
+                                         background while playing, which can be restored after \
+                                         the emulator is closed unexpectedly.",
+                                    ),
+                                    (
+                                        auto_savestate_interval_mins,
+                                        "Auto-savestate interval",
+                                        "The interval at which auto-savestates are created, in \
+                                         minutes.",
+                                    ),
+                                    (
+                                        auto_savestate_count,
+                                        "Auto-savestate count",
+                                        "How many auto-savestates to keep for each game; once \
+                                         reached, the oldest one gets replaced.",
+                                    ),
                                     (
                                         save_dir_path,
                                         "Save directory path",
@@ -1694,115 +2755,509 @@ with the Imgui title bar mode)
                     }
 
                     Section::Emulation => {
+                        // accuracy_preset
                         // framerate_ratio_limit
                         // paused_framerate_limit
+                        // fast_forward_speed_limit
+                        // turbo_speed_limit
+                        // slow_motion_speed
+                        // hang_timeout_secs
+                        // crash_screen_enabled
+                        // run_frames_count
                         // sync_to_audio
+                        // sub_frame_input
+                        // fast_pointer
+                        // fast_pointer_sensitivity
+                        // fast_pointer_acceleration
+                        // touch_requires_hotkey
+                        // stylus_speed
+                        // touch_pressure
+                        // gamepad_deadzone
                         // pause_on_launch
                         // skip_firmware
+                        // return_to_menu_on_shutdown
                         // prefer_hle_bios
+                        // batch_duration
+                        // threaded_3d_geometry
                         // model
                         // ds_slot_rom_in_memory_max_size
                         // rtc_time_offset_seconds
+                        // rtc_frozen_date_time
+                        // gba_slot_rom_path
+                        // watch_folder_dir_path
+                        // watch_folder_patch_path
+                        // solar_sensor_level
+                        // ir_link_enabled
+                        // ir_link_local_addr
+                        // ir_link_peer_addr
                         // renderer_2d_kind
                         // renderer_3d_kind
+                        // soft_renderer_3d_threads
                         // resolution_scale_shift
+                        // adaptive_resolution_scale
+                        // adaptive_resolution_lower_threshold
+                        // adaptive_resolution_raise_threshold
+                        // adaptive_resolution_hold_frames
+                        // hi_res_2d_bgs
+                        // msaa_3d
+                        // compute_rasterizer_3d
+                        // texture_filter
+                        // texture_anisotropy_shift
+                        // texture_cache_mode
+                        // dump_3d_textures
+                        // texture_dump_dir_path
+                        // replace_3d_textures
+                        // texture_pack_dir_path
 
                         draw!(
                             "Emulation",
                             emulation,
-                            [(
-                                "General",
-                                [
-                                    (
-                                        framerate_ratio_limit,
-                                        "Framerate limit",
-                                        "The framerate limit to apply to the emulator when \
-                                         running, as a percentage of the console's native \
-                                         framerate (~60 FPS). I.e., 200% will run emulation at \
-                                         120 FPS, or 2x native speed.",
-                                    ),
-                                    (
-                                        paused_framerate_limit,
-                                        "Paused framerate limit",
-                                        "The framerate limit to apply to the emulator when \
-                                         paused, in FPS. This will affect components that read \
-                                         the emulator's state like debug views.",
-                                    ),
-                                    (
-                                        sync_to_audio,
-                                        "Sync to audio",
-                                        "Whether to sync the emulator to the audio stream's \
-                                         playback; this will always limit the emulator to at most \
-                                         the console's native speed (or less if a lower framerate \
-                                         limit is active).",
-                                    ),
-                                    (
-                                        pause_on_launch,
-                                        "Pause on launch",
-                                        "Whether to pause the emulator immediately after starting \
-                                         a game, requiring it to be resumed manually.",
-                                    ),
-                                    (
-                                        skip_firmware,
-                                        "Skip firmware",
-                                        "Whether to skip the firmware game selection menu and \
-                                         immediately boot the game (required for some homebrew \
-                                         titles that don't get recognized by the firmware).
+                            [
+                                (
+                                    "General",
+                                    [
+                                        (
+                                            accuracy_preset,
+                                            "Accuracy preset",
+                                            "A preset for all settings that trade off accuracy for \
+                                             performance (BIOS and CPU sync granularity), so \
+                                             they don't need to be tuned individually; the \
+                                             renderers aren't affected:
+- Fast: use the HLE BIOS and sync the CPUs less often
+- Balanced: the default settings
+- Accurate: sync the CPUs more often
+- Cycle-synced: sync the CPUs on every cycle; this is very slow
+Changing any of the settings manually will switch to \"Custom\".",
+                                        ),
+                                        (
+                                            framerate_ratio_limit,
+                                            "Framerate limit",
+                                            "The framerate limit to apply to the emulator when \
+                                             running, as a percentage of the console's native \
+                                             framerate (~60 FPS). I.e., 200% will run emulation at \
+                                             120 FPS, or 2x native speed.",
+                                        ),
+                                        (
+                                            paused_framerate_limit,
+                                            "Paused framerate limit",
+                                            "The framerate limit to apply to the emulator when \
+                                             paused, in FPS. This will affect components that read \
+                                             the emulator's state like debug views.",
+                                        ),
+                                        (
+                                            fast_forward_speed_limit,
+                                            "Fast-forward speed",
+                                            "The speed to run the emulator at while the \
+                                             fast-forward hotkey is held, as a percentage of the \
+                                             console's native framerate; if disabled, no limit \
+                                             will be applied.",
+                                        ),
+                                        (
+                                            turbo_speed_limit,
+                                            "Turbo speed",
+                                            "The speed to run the emulator at while turbo is \
+                                             toggled on through its hotkey, as a percentage of the \
+                                             console's native framerate; if disabled, no limit \
+                                             will be applied.",
+                                        ),
+                                        (
+                                            slow_motion_speed,
+                                            "Slow motion speed",
+                                            "The speed to run the emulator at while the slow \
+                                             motion hotkey is held, as a percentage of the \
+                                             console's native framerate.",
+                                        ),
+                                        (
+                                            hang_timeout_secs,
+                                            "Hang detection timeout",
+                                            "How long (in seconds of emulated time) the ARM9 \
+                                             can stay stuck in the same loop, or leave VBlank \
+                                             interrupts unhandled, before the game is reported \
+                                             as hung, offering to reset it; if disabled, hangs \
+                                             won't be reported.",
+                                        ),
+                                        (
+                                            crash_screen_enabled,
+                                            "Crash screen",
+                                            "Whether to pause emulation and show the CPU \
+                                             registers when a CPU hits an exception that the \
+                                             game doesn't handle (i.e. an undefined instruction \
+                                             or a data abort), instead of letting the BIOS hang \
+                                             silently. Useful when developing homebrew.",
+                                        ),
+                                        (
+                                            run_frames_count,
+                                            "Frames to run",
+                                            "The number of frames to emulate when the \"Run \
+                                             frames\" hotkey is pressed while paused.",
+                                        ),
+                                        (
+                                            sync_to_audio,
+                                            "Sync to audio",
+                                            "Whether to sync the emulator to the audio stream's \
+                                             playback; this will always limit the emulator to at \
+                                             most the console's native speed (or less if a lower \
+                                             framerate limit is active).",
+                                        ),
+                                        (
+                                            sub_frame_input,
+                                            "Sub-frame input timing",
+                                            "Whether to latch input changes at the scanline \
+                                             matching the time they happened at within the frame, \
+                                             instead of only at the start of each frame. This adds \
+                                             up to a frame of latency, but keeps the timing \
+                                             between inputs consistent, which can be noticeable in \
+                                             rhythm games; it only applies while a framerate limit \
+                                             is active.",
+                                        ),
+                                        (
+                                            fast_pointer,
+                                            "Fast pointer mode",
+                                            "Whether clicking on the touchscreen should capture \
+                                             the mouse cursor and move the stylus using raw mouse \
+                                             motion, bypassing the system's cursor handling for \
+                                             lower latency (useful for games using the stylus for \
+                                             aiming). The cursor can be released with the \
+                                             corresponding hotkey, or by switching to another \
+                                             window.",
+                                        ),
+                                        (
+                                            fast_pointer_sensitivity,
+                                            "Fast pointer sensitivity",
+                                            "How many touchscreen pixels the stylus moves by for \
+                                             every unit of mouse motion in fast pointer mode.",
+                                        ),
+                                        (
+                                            fast_pointer_acceleration,
+                                            "Fast pointer acceleration",
+                                            "How much faster mouse movements should additionally \
+                                             speed up the stylus in fast pointer mode; 0 disables \
+                                             acceleration.",
+                                        ),
+                                        (
+                                            touch_requires_hotkey,
+                                            "Touch only while hotkey held",
+                                            "Whether the mouse should only touch the touchscreen \
+                                             while the Touch at cursor hotkey is held, at the \
+                                             cursor's position, instead of when clicking (useful \
+                                             for pen tablets and touchpads).",
+                                        ),
+                                        (
+                                            stylus_speed,
+                                            "Stylus speed",
+                                            "How many touchscreen pixels per second the stylus \
+                                             moves by while one of the stylus movement hotkeys is \
+                                             held; these allow playing touch-based games with the \
+                                             keyboard or a gamepad, touching with the Touch with \
+                                             stylus hotkey.",
+                                        ),
+                                        (
+                                            touch_pressure,
+                                            "Touch pressure",
+                                            "The pressure reported to the game for touches, from 0 \
+                                             (lightest) to 255 (firmest); only a few games read \
+                                             it.",
+                                        ),
+                                        (
+                                            gamepad_deadzone,
+                                            "Gamepad dead zone",
+                                            "How far analog sticks and triggers need to be pushed \
+                                             (from 0 to 1) before they count as pressed when bound \
+                                             to keys or hotkeys, for instance to use the left \
+                                             stick as a D-pad.",
+                                        ),
+                                        (
+                                            pause_on_launch,
+                                            "Pause on launch",
+                                            "Whether to pause the emulator immediately after \
+                                             starting a game, requiring it to be resumed \
+                                             manually.",
+                                        ),
+                                        (
+                                            skip_firmware,
+                                            "Skip firmware",
+                                            "Whether to skip the firmware game selection menu and \
+                                             immediately boot the game (required for some homebrew \
+                                             titles that don't get recognized by the firmware).
 The firmware boot sequence will always be skipped if any system files are not provided.",
-                                    ),
-                                    (
-                                        prefer_hle_bios,
-                                        "Prefer HLE BIOS",
-                                        "Whether to use the HLE BIOS implementation even if BIOS \
-                                         files are provided.",
-                                    ),
-                                    (
-                                        model,
-                                        "Model",
-                                        "What model of Nintendo DS to emulate (currently only DS \
-                                         and DS Lite are functional).",
-                                    ),
-                                    (
-                                        ds_slot_rom_in_memory_max_size,
-                                        "DS slot ROM in-memory max size",
-                                        "The maximum size that a DS Slot ROM file can have to get \
-                                         directly loaded into memory, before falling back to \
-                                         streaming from the filesystem.",
-                                    ),
-                                    (
-                                        rtc_time_offset_seconds,
-                                        "RTC time offset",
-                                        "The offset to apply to the RTC time reported to the \
-                                         console compared to the device's local time.",
-                                    ),
-                                    (
-                                        renderer_2d_kind,
-                                        "2D renderer kind",
-                                        "Which 2D renderer to use:
+                                        ),
+                                        (
+                                            return_to_menu_on_shutdown,
+                                            "Return to menu on shutdown",
+                                            "When booting through the firmware, whether to boot \
+                                             the system again when it shuts down (i.e. when \
+                                             quitting a game or powering off from the firmware), \
+                                             instead of stopping the emulator, so that the game \
+                                             can be relaunched from the firmware menu.",
+                                        ),
+                                        (
+                                            prefer_hle_bios,
+                                            "Prefer HLE BIOS",
+                                            "Whether to use the HLE BIOS implementation even if \
+                                             BIOS files are provided.",
+                                        ),
+                                        (
+                                            batch_duration,
+                                            "CPU sync granularity",
+                                            "How many cycles the ARM7 and ARM9 can run for before \
+                                             being synchronized with each other and the rest of \
+                                             the system; lower values are more accurate, but \
+                                             slower. Changes are applied when the emulator is \
+                                             restarted.",
+                                        ),
+                                        (
+                                            threaded_3d_geometry,
+                                            "Threaded 3D geometry",
+                                            "Whether to transform, clip and assemble 3D polygons \
+                                             on a separate thread, which doesn't affect accuracy \
+                                             but can speed up 3D-heavy games on multi-core \
+                                             systems. Changes are applied when the emulator is \
+                                             restarted.",
+                                        ),
+                                        (
+                                            model,
+                                            "Model",
+                                            "What model of Nintendo DS to emulate (currently only \
+                                             DS and DS Lite are functional).",
+                                        ),
+                                        (
+                                            ds_slot_rom_in_memory_max_size,
+                                            "DS slot ROM in-memory max size",
+                                            "The maximum size that a DS Slot ROM file can have to \
+                                             get directly loaded into memory, before falling back \
+                                             to streaming it from the filesystem (through a memory \
+                                             mapping where supported).",
+                                        ),
+                                        (
+                                            rtc_time_offset_seconds,
+                                            "RTC date/time",
+                                            "The date and time reported by the console's RTC, \
+                                             which follows the device's local time with the \
+                                             chosen offset applied (press Enter to confirm an \
+                                             edit). Best set per game, i.e. to play time-based \
+                                             events again.",
+                                        ),
+                                        (
+                                            rtc_frozen_date_time,
+                                            "Freeze RTC",
+                                            "Stops the console's RTC at a fixed date and time \
+                                             instead of following the device's clock, so that \
+                                             games behave the same across runs (i.e. for TAS \
+                                             playback); times set by the game itself are kept \
+                                             frozen as well.",
+                                        ),
+                                        (
+                                            gba_slot_rom_path,
+                                            "GBA slot ROM",
+                                            "The GBA ROM image to insert into the GBA slot, for \
+                                             games that detect GBA games to unlock content (only \
+                                             the header is required); the cart's SRAM is loaded \
+                                             from and saved to a .sav file with the same name next \
+                                             to it, if present. Best set per game; changes are \
+                                             applied when the emulator is restarted.",
+                                        ),
+                                        (
+                                            watch_folder_dir_path,
+                                            "Watch folder directory",
+                                            "The directory containing each game's watch folder, \
+                                             named after its game code (i.e. ADME), where \
+                                             dropped patches are detected while the game is \
+                                             running and offered for activation through \
+                                             Emulation > Watch folder.",
+                                        ),
+                                        (
+                                            watch_folder_patch_path,
+                                            "Watch folder patch",
+                                            "The patch from the game's watch folder to apply \
+                                             when launching it, unless one is present next to \
+                                             the ROM file. Best set per game; changes are \
+                                             applied when the emulator is restarted.",
+                                        ),
+                                        (
+                                            solar_sensor_level,
+                                            "Solar sensor level",
+                                            "The light level reported by the solar sensor of GBA \
+                                             carts that have one (such as Boktai), from 0 \
+                                             (darkest) to 255 (brightest).",
+                                        ),
+                                        (
+                                            ir_link_enabled,
+                                            "IR link",
+                                            "Whether to connect the infrared transceiver of DS \
+                                             cartridges that have one (such as Pokémon \
+                                             HeartGold/SoulSilver) to another emulator instance \
+                                             over UDP, i.e. to trade or to connect to a Pokéwalker \
+                                             emulator. Changes are applied when the emulator is \
+                                             restarted.",
+                                        ),
+                                        (
+                                            ir_link_local_addr,
+                                            "IR link local address",
+                                            "The address to receive IR packets at.",
+                                        ),
+                                        (
+                                            ir_link_peer_addr,
+                                            "IR link peer address",
+                                            "The address of the other end of the IR link, which \
+                                             should use this instance's local address as its peer \
+                                             address.",
+                                        ),
+                                        (
+                                            renderer_2d_kind,
+                                            "2D renderer kind",
+                                            "Which 2D renderer to use:
 - Software, sync: render everything synchronously on the emulation thread
 - Software, async, per-scanline: render individual scanlines asynchronously on a worker thread
 - EXPERIMENTAL: Hardware, async, per-scanline: render individual scanline components \
-                                         asynchronously on a worker thread and apply blending, \
-                                         layering and color effects using hardware acceleration \
-                                         (required when using the hardware 3D renderer)",
-                                    ),
-                                    (
-                                        renderer_3d_kind,
-                                        "3D renderer kind",
-                                        "Which 3D renderer to use:
+                                             asynchronously on a worker thread and apply blending, \
+                                             layering and color effects using hardware \
+                                             acceleration (required when using the hardware 3D \
+                                             renderer)",
+                                        ),
+                                        (
+                                            renderer_3d_kind,
+                                            "3D renderer kind",
+                                            "Which 3D renderer to use:
 - Software: render 3D content asynchronously on a worker thread in software
 - EXPERIMENTAL: Hardware, async, per-scanline: render 3D content using hardware acceleration, at a \
-                                         higher resolution if selected",
-                                    ),
-                                    (
-                                        resolution_scale_shift,
-                                        "3D HW resolution scale",
-                                        "With the hardware 3D renderer enabled, the scale at \
-                                         which 3D graphics should be rendered compared to the \
-                                         native resolution.",
-                                    )
-                                ]
-                            )]
+                                             higher resolution if selected",
+                                        ),
+                                        (
+                                            soft_renderer_3d_threads,
+                                            "3D SW threads",
+                                            "With the software 3D renderer enabled, how many \
+                                             threads to split the rendering of scanlines across; \
+                                             using more can help reaching full speed on slower \
+                                             CPUs with multiple cores.",
+                                        ),
+                                        (
+                                            resolution_scale_shift,
+                                            "3D HW resolution scale",
+                                            "With the hardware 3D renderer enabled, the scale at \
+                                             which 3D graphics should be rendered compared to the \
+                                             native resolution.",
+                                        ),
+                                        (
+                                            adaptive_resolution_scale,
+                                            "3D HW adaptive resolution",
+                                            "With the hardware 3D renderer enabled, whether to \
+                                             lower the resolution scale during scenes the GPU \
+                                             can't render at full speed, and raise it back up to \
+                                             the selected one once it can; requires GPU \
+                                             timestamp support.",
+                                        ),
+                                        (
+                                            adaptive_resolution_lower_threshold,
+                                            "Adaptive res. lower threshold",
+                                            "The GPU frame time, as a percentage of the time \
+                                             available for each frame, above which the adaptive \
+                                             resolution scale is lowered.",
+                                        ),
+                                        (
+                                            adaptive_resolution_raise_threshold,
+                                            "Adaptive res. raise threshold",
+                                            "The GPU frame time, as a percentage of the time \
+                                             available for each frame, below which the adaptive \
+                                             resolution scale is raised; as each step quadruples \
+                                             the number of rendered pixels, this should be well \
+                                             under a quarter of the lower threshold.",
+                                        ),
+                                        (
+                                            adaptive_resolution_hold_frames,
+                                            "Adaptive res. hold frames",
+                                            "How many consecutive frames need to cross a \
+                                             threshold before the adaptive resolution scale is \
+                                             changed.",
+                                        ),
+                                        (
+                                            hi_res_2d_bgs,
+                                            "2D HW hi-res BGs",
+                                            "With the hardware 2D renderer enabled, whether to \
+                                             resample rotated and scaled backgrounds at the \
+                                             selected resolution scale instead of upscaling them \
+                                             from the native resolution.",
+                                        ),
+                                        (
+                                            msaa_3d,
+                                            "3D HW multisampling",
+                                            "With the hardware 3D renderer enabled, whether to \
+                                             render 3D graphics with 4x multisample \
+                                             anti-aliasing, smoothing out polygon edges \
+                                             independently of the game's own anti-aliasing \
+                                             settings.",
+                                        ),
+                                        (
+                                            compute_rasterizer_3d,
+                                            "3D HW compute rasterizer",
+                                            "With the hardware 3D renderer enabled, whether to \
+                                             rasterize polygons in compute shaders following the \
+                                             DS's own fill and interpolation rules, matching the \
+                                             software renderer's output at higher resolutions at \
+                                             a performance cost. Multisampling and texture \
+                                             filtering and replacement don't apply to it.",
+                                        )
+                                    ]
+                                ),
+                                (
+                                    "3D HW textures",
+                                    [
+                                        (
+                                            texture_filter,
+                                            "Texture filtering",
+                                            "With the hardware 3D renderer enabled, how to sample \
+                                             textures: nearest-neighbor sampling matches the DS's \
+                                             output, while bilinear filtering smooths textures \
+                                             out.",
+                                        ),
+                                        (
+                                            texture_anisotropy_shift,
+                                            "Anisotropic filtering",
+                                            "With the hardware 3D renderer and bilinear texture \
+                                             filtering enabled, the maximum level of anisotropic \
+                                             filtering to apply when rendering at a resolution \
+                                             scale above 1x, to keep textures viewed at steep \
+                                             angles sharp.",
+                                        ),
+                                        (
+                                            texture_cache_mode,
+                                            "Palette caching",
+                                            "With the hardware 3D renderer enabled, how to cache \
+                                             paletted textures: combined re-decodes textures \
+                                             whenever their palette changes, while separate \
+                                             palettes are looked up while rendering, which is \
+                                             faster for games that animate their palettes. Auto \
+                                             switches to separate palettes once that is detected.",
+                                        ),
+                                        (
+                                            dump_3d_textures,
+                                            "Dump textures",
+                                            "With the hardware 3D renderer enabled, whether to \
+                                             save every texture used by the game as a PNG image \
+                                             named after the hash of its contents.",
+                                        ),
+                                        (
+                                            texture_dump_dir_path,
+                                            "Texture dump directory",
+                                            "The directory to save dumped textures to.",
+                                        ),
+                                        (
+                                            replace_3d_textures,
+                                            "Replace textures",
+                                            "With the hardware 3D renderer enabled, whether to \
+                                             replace textures with PNG images from the texture \
+                                             pack directory whose file names end with the same \
+                                             hash as the corresponding dumped texture. \
+                                             Replacements can have any resolution.",
+                                        ),
+                                        (
+                                            texture_pack_dir_path,
+                                            "Texture pack directory",
+                                            "The directory (including its subdirectories) to load \
+                                             replacement textures from.",
+                                        )
+                                    ]
+                                )
+                            ]
                         );
                     }
 
@@ -1812,11 +3267,155 @@ The firmware boot sequence will always be skipped if any system files are not pr
                             .draw(ui, &mut config.config, &self.data);
                     }
 
-                    #[cfg(any(feature = "logging", feature = "gdb-server"))]
+                    #[cfg(any(feature = "ffmpeg", feature = "frame-dump", feature = "wav-dump"))]
+                    Section::Recording => {
+                        // ffmpeg_path
+                        // recording_container
+                        // recording_video_codec
+                        // recording_video_bitrate_kbps
+                        // recording_audio_bitrate_kbps
+                        // frame_dump_dir_path
+                        // frame_dump_name_template
+                        // frame_dump_format
+                        // frame_dump_3d_layer
+                        // frame_dump_2d_layers
+                        // capture_layout
+                        // wav_dump_channels
+
+                        draw!(
+                            "Recording",
+                            recording,
+                            [
+                                (
+                                    #[cfg(feature = "ffmpeg")]
+                                    "Video",
+                                    [
+                                        (
+                                            ffmpeg_path,
+                                            "FFmpeg executable",
+                                            "The location of the FFmpeg executable used to encode \
+                                             recordings; if not set, it will be looked up in the \
+                                             system's PATH.",
+                                        ),
+                                        (
+                                            container,
+                                            "Container",
+                                            "The file format to store recordings in; MP4 files \
+                                             will use AAC audio, while WebM and Matroska ones will \
+                                             use Opus.",
+                                        ),
+                                        (
+                                            video_codec,
+                                            "Video codec",
+                                            "The codec to encode recorded video with (WebM files \
+                                             only support VP9 and AV1).",
+                                        ),
+                                        (
+                                            video_bitrate_kbps,
+                                            "Video bitrate",
+                                            "The target bitrate for recorded video, in kilobits \
+                                             per second.",
+                                        ),
+                                        (
+                                            audio_bitrate_kbps,
+                                            "Audio bitrate",
+                                            "The target bitrate for recorded audio, in kilobits \
+                                             per second.",
+                                        )
+                                    ]
+                                ),
+                                (
+                                    #[cfg(feature = "frame-dump")]
+                                    "Frame dumps",
+                                    [
+                                        (
+                                            frame_dump_dir_path,
+                                            "Directory",
+                                            "The directory frame dumps will be written to.",
+                                        ),
+                                        (
+                                            frame_dump_name_template,
+                                            "File name template",
+                                            "The name of the dumped files, relative to the frame \
+                                             dump directory and without an extension; `{title}` \
+                                             is replaced with the game's title, `{frame}` with \
+                                             the frame number and `{layer}` with the name of the \
+                                             dumped layer (`screens`, `3d`, or `2d_a_top`, \
+                                             `2d_a_bottom`, `2d_a_ids` and their engine B \
+                                             counterparts). Raw dumps store all frames in a \
+                                             single file per layer, using the number of the \
+                                             first dumped frame for `{frame}`. A `manifest.json` \
+                                             file describing each frame is written next to the \
+                                             first one.",
+                                        ),
+                                        (
+                                            frame_dump_format,
+                                            "Format",
+                                            "Whether to dump frames as separate PNG images or as a \
+                                             single stream of raw RGBA8 pixels; the screens are \
+                                             arranged according to the capture layout.",
+                                        ),
+                                        (
+                                            frame_dump_3d_layer,
+                                            "Dump 3D layer",
+                                            "Whether to also dump the output of the 3D renderer \
+                                             before it's composited with the 2D layers; only \
+                                             available with the software 3D renderer.",
+                                        ),
+                                        (
+                                            frame_dump_2d_layers,
+                                            "Dump 2D layers",
+                                            "Whether to also dump each 2D engine's two topmost \
+                                             BG/OBJ layers before color effects are applied, \
+                                             along with an image identifying which layer each \
+                                             pixel belongs to (BGs 0-3, 4 for OBJs and 5 for the \
+                                             backdrop, in the red and green channels); the blend \
+                                             parameters needed to composite them are stored in \
+                                             the manifest.",
+                                        )
+                                    ]
+                                ),
+                                (
+                                    #[cfg(any(feature = "ffmpeg", feature = "frame-dump"))]
+                                    "Captures",
+                                    [(
+                                        capture_layout,
+                                        "Layout",
+                                        "How the screens are arranged in recordings and frame \
+                                         dumps: either like the current screen layout (including \
+                                         gaps, swapping, single-screen mode and custom layouts), \
+                                         with the screen rotation rounded to the nearest quarter \
+                                         turn and stored as metadata, or as the console's raw \
+                                         output, with both screens stacked vertically (top screen \
+                                         first) and no rotation.",
+                                    )]
+                                ),
+                                (
+                                    #[cfg(feature = "wav-dump")]
+                                    "Audio dumps",
+                                    [(
+                                        wav_dump_channels,
+                                        "Dump channels separately",
+                                        "Whether to also dump the output of each of the 16 sound \
+                                         channels (before panning and mixing) to separate 32768 \
+                                         Hz mono WAV files, named after the main one with a \
+                                         `_ch0`-`_ch15` suffix.",
+                                    )]
+                                )
+                            ]
+                        );
+                    }
+
+                    #[cfg(any(
+                        feature = "logging",
+                        feature = "gdb-server",
+                        feature = "virtual-time"
+                    ))]
                     Section::Debug => {
                         // logging_kind
                         // imgui_log_history_capacity
                         // gdb_server_addr
+                        // virtual_time_server_addr
 
                         draw!(
                             "Debug",
@@ -1849,11 +3448,67 @@ The firmware boot sequence will always be skipped if any system files are not pr
                                         "GDB server address",
                                         "The address to expose the GDB server at once started.",
                                     )]
+                                ),
+                                (
+                                    #[cfg(feature = "virtual-time")]
+                                    "Virtual time server",
+                                    [(
+                                        virtual_time_server_addr,
+                                        "Virtual time server address",
+                                        "The address to expose the virtual time server at once \
+                                         started; while it's running, emulated time only \
+                                         advances when granted by the connected controller.",
+                                    )]
                                 )
                             ]
                         );
                     }
 
+                    #[cfg(feature = "remote-display")]
+                    Section::RemoteDisplay => {
+                        // remote_display_addr
+                        // remote_display_encoding
+                        // remote_display_jpeg_quality
+                        // remote_display_screens
+
+                        draw!(
+                            "Remote display",
+                            remote_display,
+                            [(
+                                "General",
+                                [
+                                    (
+                                        addr,
+                                        "Server address",
+                                        "The address to expose the remote display server at once \
+                                         started; to connect from another device on the local \
+                                         network, this needs to be set to an address reachable \
+                                         from it (i.e. 0.0.0.0). Changes are applied the next \
+                                         time the server is started.",
+                                    ),
+                                    (
+                                        encoding,
+                                        "Encoding",
+                                        "How to encode the streamed screens; raw RGB565 has the \
+                                         lowest latency but needs a fast connection, while MJPEG \
+                                         uses much less bandwidth at the cost of some quality.",
+                                    ),
+                                    (
+                                        jpeg_quality,
+                                        "JPEG quality",
+                                        "The quality of MJPEG frames, from 1 to 100.",
+                                    ),
+                                    (
+                                        screens,
+                                        "Streamed screens",
+                                        "Which screens to send to the remote device; touch \
+                                         input is always mapped to the bottom screen.",
+                                    )
+                                ]
+                            )]
+                        );
+                    }
+
                     #[cfg(feature = "discord-presence")]
                     Section::DiscordPresence => {
                         // discord_presence_enabled
@@ -1873,11 +3528,18 @@ The firmware boot sequence will always be skipped if any system files are not pr
                             )]
                         );
                     }
+
+                    Section::ChangedSettings => {
+                        self.draw_changed_settings(ui, config, emu_state.as_deref());
+                    }
                 }
 
                 if self.cur_section != Section::Input {
                     self.input_map_editor = None;
                 }
+                if self.cur_section != Section::Paths {
+                    self.sys_sets_editor = None;
+                }
             });
     }
 
@@ -2051,3 +3713,71 @@ The firmware boot sequence will always be skipped if any system files are not pr
             });
     }
 }
+
+// Values longer than this are cut short in the changed settings list and shown in full in a
+// tooltip
+const MAX_DIFF_VALUE_CHARS: usize = 64;
+
+fn draw_diff_value(ui: &Ui, value: &str) {
+    match value.char_indices().nth(MAX_DIFF_VALUE_CHARS) {
+        Some((end, _)) => {
+            ui.text(format!("{}...", &value[..end]));
+            if ui.is_item_hovered() {
+                ui.tooltip(|| {
+                    let _wrap_pos = ui.push_text_wrap_pos_with_pos(ui.current_font_size() * 40.0);
+                    ui.text(value);
+                });
+            }
+        }
+        None => ui.text(value),
+    }
+}
+
+/// Lists the given changed settings, returning the name of the one whose reset button was pressed,
+/// if any.
+fn draw_diff_entries<'a>(
+    ui: &Ui,
+    id: &str,
+    entries: &'a [config::diff::Entry],
+    empty_message: &str,
+) -> Option<&'a str> {
+    if entries.is_empty() {
+        ui.text_disabled(empty_message);
+        return None;
+    }
+
+    let mut reset = None;
+    if let Some(_table) = ui.begin_table_with_flags(
+        id,
+        4,
+        TableFlags::BORDERS_INNER_H | TableFlags::SIZING_STRETCH_PROP,
+    ) {
+        for name in ["Setting", "Value", "Default"] {
+            ui.table_setup_column(name);
+        }
+        ui.table_setup_column_with(TableColumnSetup {
+            flags: TableColumnFlags::WIDTH_FIXED,
+            ..TableColumnSetup::new("")
+        });
+        ui.table_headers_row();
+
+        for entry in entries {
+            let _id = ui.push_id(entry.name.as_str());
+            ui.table_next_row();
+            ui.table_next_column();
+            ui.text(&entry.name);
+            ui.table_next_column();
+            draw_diff_value(ui, &entry.value);
+            ui.table_next_column();
+            draw_diff_value(ui, &entry.default);
+            ui.table_next_column();
+            if ui.button("\u{f2ea}") {
+                reset = Some(entry.name.as_str());
+            }
+            if ui.is_item_hovered() {
+                ui.tooltip_text("Reset to default");
+            }
+        }
+    }
+    reset
+}
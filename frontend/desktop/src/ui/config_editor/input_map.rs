@@ -94,6 +94,30 @@ static ACTIONS: &[(Action, &str)] = &[
     (Action::ToggleFramerateLimit, "Toggle framerate limit"),
     (Action::ToggleSyncToAudio, "Toggle sync to audio"),
     (Action::ToggleFullWindowScreen, "Toggle full-window screen"),
+    (Action::SwapScreens, "Swap screens"),
+    (Action::SwapMagnifiedScreen, "Swap magnified screen"),
+    (Action::NextScreenLayout, "Next screen layout"),
+    (Action::PrevScreenLayout, "Previous screen layout"),
+    (Action::TogglePerfOverlay, "Toggle performance overlay"),
+    (Action::FastForward, "Fast-forward (hold)"),
+    (Action::ToggleTurbo, "Toggle turbo"),
+    (Action::SlowMotion, "Slow motion (hold)"),
+    (Action::FrameAdvance, "Advance frame"),
+    (Action::RunFrames, "Run frames"),
+    (Action::ReleasePointer, "Release captured pointer"),
+    (Action::Touch, "Touch at cursor"),
+    (Action::StylusUp, "Move stylus up"),
+    (Action::StylusDown, "Move stylus down"),
+    (Action::StylusLeft, "Move stylus left"),
+    (Action::StylusRight, "Move stylus right"),
+    (Action::StylusTouch, "Touch with stylus"),
+    (Action::SaveStateSlot, "Save state to slot"),
+    (Action::LoadStateSlot, "Load state from slot (hold)"),
+    (Action::NextStateSlot, "Next savestate slot"),
+    (Action::PrevStateSlot, "Previous savestate slot"),
+    (Action::CancelStateLoad, "Cancel state load"),
+    (Action::NextSaveSlot, "Next save slot"),
+    (Action::PrevSaveSlot, "Previous save slot"),
 ];
 
 type InputMap = config::Overridable<Map, GlobalMap, Map, ()>;
@@ -471,43 +495,49 @@ impl Editor {
             let Ok(key) = (*physical_key).try_into() else {
                 return;
             };
-            if state.is_pressed() {
-                self.pressed_keys.insert(key);
-
-                if self.state.is_capturing() {
-                    let new_trigger = match key {
-                        PressedKey::KeyCode(key_code) => Trigger::KeyCode(key_code),
-                        PressedKey::ScanCode(scan_code) => Trigger::ScanCode(scan_code),
-                    };
-
-                    if let Some(trigger) = &mut self.current_trigger {
-                        match trigger {
-                            Trigger::Chain(trigger::Op::And, contents) => {
-                                if !contents.contains(&new_trigger) {
-                                    contents.push(new_trigger);
-                                }
+            self.process_key(key, state.is_pressed(), config);
+        }
+    }
+
+    /// Processes gamepad input, which can be captured into triggers just like keyboard input.
+    pub fn process_gamepad_input(&mut self, key: PressedKey, pressed: bool, config: &mut Config) {
+        self.process_key(key, pressed, config);
+    }
+
+    fn process_key(&mut self, key: PressedKey, pressed: bool, config: &mut Config) {
+        if pressed {
+            self.pressed_keys.insert(key);
+
+            if self.state.is_capturing() {
+                let new_trigger = Trigger::from(key);
+
+                if let Some(trigger) = &mut self.current_trigger {
+                    match trigger {
+                        Trigger::Chain(trigger::Op::And, contents) => {
+                            if !contents.contains(&new_trigger) {
+                                contents.push(new_trigger);
                             }
+                        }
 
-                            others => {
-                                if *others != new_trigger {
-                                    let others = self.current_trigger.take().unwrap();
-                                    self.current_trigger = Some(Trigger::Chain(
-                                        trigger::Op::And,
-                                        vec![others, new_trigger],
-                                    ));
-                                }
+                        others => {
+                            if *others != new_trigger {
+                                let others = self.current_trigger.take().unwrap();
+                                self.current_trigger = Some(Trigger::Chain(
+                                    trigger::Op::And,
+                                    vec![others, new_trigger],
+                                ));
                             }
                         }
-                    } else {
-                        self.current_trigger = Some(new_trigger);
                     }
+                } else {
+                    self.current_trigger = Some(new_trigger);
                 }
-            } else {
-                self.pressed_keys.remove(&key);
+            }
+        } else {
+            self.pressed_keys.remove(&key);
 
-                if self.state.is_capturing() {
-                    self.finalize(config.input_map.inner_mut());
-                }
+            if self.state.is_capturing() {
+                self.finalize(config.input_map.inner_mut());
             }
         }
     }
@@ -1,5 +1,6 @@
 use super::{SettingsData, Tab};
 use crate::{config::Config, ui::utils::combo_value, utils::HomePathBuf};
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, Timelike};
 use imgui::{internal::DataTypeKind, ItemHoveredFlags, SliderFlags, Ui, WindowHoveredFlags};
 use rfd::FileDialog;
 use std::{
@@ -349,6 +350,132 @@ impl<T: DataTypeKind + PartialOrd> RawSetting for Scalar<T> {
     }
 }
 
+/// Draws inputs for each component of a date and time on a single line, returning the new value
+/// once one of them is edited; the year is limited to the range the DS RTC supports, and the day
+/// is clamped to the length of the chosen month.
+fn date_time_input(ui: &Ui, date_time: NaiveDateTime, width: f32) -> Option<NaiveDateTime> {
+    let mut year = date_time.year();
+    let mut components = [
+        date_time.month(),
+        date_time.day(),
+        date_time.hour(),
+        date_time.minute(),
+        date_time.second(),
+    ];
+    // The year input is twice as wide as the others
+    let unit_width = (width - style!(ui, item_spacing)[0] * 5.0) / 7.0;
+    let mut updated = false;
+
+    ui.set_next_item_width(unit_width * 2.0);
+    updated |= ui
+        .input_scalar("##year", &mut year)
+        .display_format("%04d")
+        .enter_returns_true(true)
+        .build();
+    for (i, component) in components.iter_mut().enumerate() {
+        let _id = ui.push_id_usize(i);
+        ui.same_line();
+        ui.set_next_item_width(unit_width);
+        updated |= ui
+            .input_scalar("", component)
+            .display_format("%02d")
+            .enter_returns_true(true)
+            .build();
+    }
+    if !updated {
+        return None;
+    }
+
+    let [month, day, hour, minute, second] = components;
+    let year = year.clamp(2000, 2099);
+    let month = month.clamp(1, 12);
+    let date = (1..=day.clamp(1, 31))
+        .rev()
+        .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))?;
+    date.and_hms_opt(hour.min(23), minute.min(59), second.min(59))
+}
+
+/// An offset in seconds from the host's local time, edited as the resulting date and time.
+pub struct DateTimeOffset {
+    pub get: fn(&Config) -> i64,
+    pub set: fn(&mut Config, i64),
+}
+
+impl DateTimeOffset {
+    pub const fn new(get: fn(&Config) -> i64, set: fn(&mut Config, i64)) -> Self {
+        DateTimeOffset { get, set }
+    }
+}
+
+impl RawSetting for DateTimeOffset {
+    fn draw(&mut self, ui: &Ui, config: &mut Config, tooltip: &str, width: f32) {
+        let offset = (self.get)(config);
+        let now = Local::now().naive_local();
+        let date_time = Duration::try_seconds(offset).map_or(now, |offset| now + offset);
+
+        ui.group(|| {
+            if let Some(new_date_time) = date_time_input(ui, date_time, width) {
+                (self.set)(config, (new_date_time - now).num_seconds());
+            }
+        });
+
+        if ui.is_item_hovered_with_flags(ItemHoveredFlags::ALLOW_WHEN_DISABLED) {
+            let offset_text = format!("Offset from the local time: {offset} s");
+            ui.tooltip_text(if tooltip.is_empty() {
+                offset_text
+            } else {
+                format!("{tooltip}\n{offset_text}")
+            });
+        }
+    }
+}
+
+/// An optional date and time, edited through a checkbox to enable it followed by its components.
+pub struct OptDateTime {
+    pub get: fn(&Config) -> Option<NaiveDateTime>,
+    pub set: fn(&mut Config, Option<NaiveDateTime>),
+    /// Returns the value to start from when the setting is enabled.
+    pub default: fn(&Config) -> NaiveDateTime,
+}
+
+impl OptDateTime {
+    pub const fn new(
+        get: fn(&Config) -> Option<NaiveDateTime>,
+        set: fn(&mut Config, Option<NaiveDateTime>),
+        default: fn(&Config) -> NaiveDateTime,
+    ) -> Self {
+        OptDateTime { get, set, default }
+    }
+}
+
+impl RawSetting for OptDateTime {
+    fn draw(&mut self, ui: &Ui, config: &mut Config, tooltip: &str, width: f32) {
+        let value = (self.get)(config);
+
+        ui.group(|| {
+            let mut enabled = value.is_some();
+            if ui.checkbox("##enabled", &mut enabled) {
+                (self.set)(config, enabled.then(|| (self.default)(config)));
+            }
+
+            ui.same_line();
+            let width = width - (ui.frame_height() + style!(ui, item_spacing)[0]);
+            ui.enabled(value.is_some(), || {
+                let date_time = value.unwrap_or_else(|| (self.default)(config));
+                if let Some(new_date_time) = date_time_input(ui, date_time, width) {
+                    (self.set)(config, Some(new_date_time));
+                }
+            });
+        });
+
+        if !tooltip.is_empty()
+            && ui.is_item_hovered_with_flags(ItemHoveredFlags::ALLOW_WHEN_DISABLED)
+        {
+            ui.tooltip_text(tooltip);
+        }
+    }
+}
+
 pub struct OptNonZeroU32Slider {
     pub get: fn(&Config) -> Option<NonZeroU32>,
     pub set: fn(&mut Config, Option<NonZeroU32>),
@@ -659,6 +786,69 @@ impl<T: Clone + PartialEq + 'static> RawSetting for Combo<T> {
     }
 }
 
+/// A combo box to pick one of a list of names (such as the files in a directory), listed again
+/// every time the combo box is open, or none at all, represented by an empty string.
+pub struct FileCombo {
+    pub get: fn(&Config) -> &str,
+    pub set: fn(&mut Config, &str),
+    pub list: fn(&Config) -> Vec<StdString>,
+    pub none_label: &'static str,
+}
+
+impl FileCombo {
+    pub const fn new(
+        get: fn(&Config) -> &str,
+        set: fn(&mut Config, &str),
+        list: fn(&Config) -> Vec<StdString>,
+        none_label: &'static str,
+    ) -> Self {
+        FileCombo {
+            get,
+            set,
+            list,
+            none_label,
+        }
+    }
+}
+
+impl RawSetting for FileCombo {
+    fn draw(&mut self, ui: &Ui, config: &mut Config, tooltip: &str, width: f32) {
+        let value = (self.get)(config);
+        let preview = if value.is_empty() {
+            self.none_label
+        } else {
+            value
+        };
+        let mut new_value = None;
+
+        ui.set_next_item_width(width);
+        if let Some(_combo) = ui.begin_combo("", preview) {
+            if ui
+                .selectable_config(self.none_label)
+                .selected(value.is_empty())
+                .build()
+            {
+                new_value = Some(StdString::new());
+            }
+            for file in (self.list)(config) {
+                if ui.selectable_config(&file).selected(file == value).build() {
+                    new_value = Some(file);
+                }
+            }
+        }
+
+        if let Some(new_value) = new_value {
+            (self.set)(config, &new_value);
+        }
+
+        if !tooltip.is_empty()
+            && ui.is_item_hovered_with_flags(ItemHoveredFlags::ALLOW_WHEN_DISABLED)
+        {
+            ui.tooltip_text(tooltip);
+        }
+    }
+}
+
 fn is_row_hovered(ui: &Ui) -> bool {
     use imgui::sys::*;
 
@@ -0,0 +1,370 @@
+use super::{SettingsData, Tab};
+use crate::{
+    config::{
+        sys_files::{FileResult, SetInfo, Status},
+        Config, Setting, SysPathSet,
+    },
+    ui::utils::combo_value,
+    utils::HomePathBuf,
+};
+use dust_core::Model;
+use imgui::{TableColumnFlags, TableColumnSetup, TableFlags, TreeNodeFlags, Ui};
+use rfd::FileDialog;
+use std::borrow::Cow;
+
+fn set_label(name: &Option<String>) -> Cow<str> {
+    match name {
+        Some(name) => name.as_str().into(),
+        None => "Default paths".into(),
+    }
+}
+
+fn model_label(model: Model) -> &'static str {
+    match model {
+        Model::Ds => "DS",
+        Model::Lite => "DS Lite",
+        Model::Ique => "IQue DS",
+        Model::IqueLite => "IQue DS Lite",
+        Model::Dsi => "DSi",
+    }
+}
+
+fn draw_path(
+    ui: &Ui,
+    label: &str,
+    path: &mut Option<HomePathBuf>,
+    placeholder: &str,
+    is_dir: bool,
+) -> bool {
+    let _id = ui.push_id(label);
+    let mut updated = false;
+
+    ui.table_next_row();
+    ui.table_next_column();
+    ui.align_text_to_frame_padding();
+    ui.text(format!("{label}:"));
+
+    ui.table_next_column();
+    let mut buffer = path
+        .as_ref()
+        .map(|path| {
+            path.to_string()
+                .unwrap_or_else(|| "<invalid UTF-8>".into())
+                .into_owned()
+        })
+        .unwrap_or_default();
+    ui.set_next_item_width(
+        ui.content_region_avail()[0]
+            - (ui.calc_text_size("\u{f07c}")[0]
+                + style!(ui, frame_padding)[0] * 2.0
+                + style!(ui, item_spacing)[0]),
+    );
+    if ui
+        .input_text("", &mut buffer)
+        .auto_select_all(true)
+        .enter_returns_true(true)
+        .hint(placeholder)
+        .build()
+    {
+        *path = (!buffer.is_empty()).then(|| HomePathBuf::from(buffer.as_str()));
+        updated = true;
+    }
+
+    ui.same_line();
+    if ui.button("\u{f07c}") {
+        let new_path = if is_dir {
+            FileDialog::new().pick_folder()
+        } else {
+            FileDialog::new().pick_file()
+        };
+        if let Some(new_path) = new_path {
+            *path = Some(HomePathBuf(new_path));
+            updated = true;
+        }
+    }
+    if ui.is_item_hovered() {
+        ui.tooltip_text("Browse...");
+    }
+
+    updated
+}
+
+fn draw_set_info(ui: &Ui, info: &SetInfo) {
+    if let Some(_table) = ui.begin_table_with_flags("info", 4, TableFlags::BORDERS_INNER_H) {
+        for name in ["File", "Size", "CRC32"] {
+            ui.table_setup_column_with(TableColumnSetup {
+                flags: TableColumnFlags::WIDTH_FIXED,
+                ..TableColumnSetup::new(name)
+            });
+        }
+        ui.table_setup_column("Status");
+        ui.table_headers_row();
+
+        for (name, result) in [
+            ("ARM7 BIOS", &info.arm7_bios),
+            ("ARM9 BIOS", &info.arm9_bios),
+            ("Firmware", &info.firmware),
+        ] {
+            draw_file_info(ui, name, result);
+        }
+        if let Some(result) = &info.key1_table {
+            draw_file_info(ui, "KEY1 table", result);
+        }
+    }
+
+    if let Some(model) = info.model() {
+        ui.text(format!("Detected model: {}", model_label(model)));
+    }
+}
+
+fn draw_file_info(ui: &Ui, name: &str, result: &FileResult) {
+    const INVALID_COLOR: [f32; 4] = [1.0, 0.4, 0.4, 1.0];
+
+    ui.table_next_row();
+    ui.table_next_column();
+    ui.text(name);
+
+    match result {
+        Ok(info) => {
+            ui.table_next_column();
+            ui.text(format!("{} B", info.len));
+            ui.table_next_column();
+            ui.text(format!("{:08X}", info.crc32));
+            ui.table_next_column();
+            match &info.status {
+                Status::Known => ui.text("\u{f00c} Known good dump"),
+                Status::Unknown => ui.text("Valid"),
+                Status::Invalid(reason) => ui.text_colored(INVALID_COLOR, reason),
+            }
+        }
+        Err(err) => {
+            ui.table_next_column();
+            ui.text_disabled("-");
+            ui.table_next_column();
+            ui.text_disabled("-");
+            ui.table_next_column();
+            ui.text_colored(INVALID_COLOR, err.to_string());
+        }
+    }
+}
+
+pub struct Editor {
+    active_info: Option<SetInfo>,
+    set_infos: Vec<Option<SetInfo>>,
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        Editor {
+            active_info: None,
+            set_infos: Vec::new(),
+        }
+    }
+
+    fn draw_boot_set(&mut self, ui: &Ui, config: &mut Config, data: &SettingsData) {
+        let sys_paths = config.sys_paths.inner();
+        let items = [None]
+            .into_iter()
+            .chain(
+                sys_paths
+                    .global()
+                    .sets
+                    .iter()
+                    .map(|set| Some(set.name.clone())),
+            )
+            .collect::<Vec<_>>();
+
+        ui.align_text_to_frame_padding();
+        ui.text("Boot with: ");
+        ui.same_line();
+
+        if data.cur_tab == Tab::Global {
+            let mut set = sys_paths.global().set.clone();
+            if combo_value(ui, "##global", &mut set, &items, set_label) {
+                config
+                    .sys_paths
+                    .inner_mut()
+                    .update_global(|global| global.set = set);
+                self.active_info = None;
+            }
+        } else {
+            let mut game_set = sys_paths.game().set.clone();
+            let mut enabled = game_set.is_some();
+            if ui.checkbox("##game_enabled", &mut enabled) {
+                game_set = enabled.then(|| sys_paths.global().set.clone());
+                config
+                    .sys_paths
+                    .inner_mut()
+                    .update_game(|game| game.set = game_set.clone());
+                self.active_info = None;
+            }
+            if ui.is_item_hovered() {
+                ui.tooltip_text("Override for this game");
+            }
+            ui.same_line();
+            let mut set = match &game_set {
+                Some(set) => set.clone(),
+                None => config.sys_paths.inner().global().set.clone(),
+            };
+            ui.enabled(enabled, || {
+                if combo_value(ui, "##game", &mut set, &items, set_label) {
+                    config
+                        .sys_paths
+                        .inner_mut()
+                        .update_game(|game| game.set = Some(set));
+                    self.active_info = None;
+                }
+            });
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, config: &mut Config, data: &SettingsData) {
+        self.set_infos
+            .resize_with(config.sys_paths.inner().global().sets.len(), || None);
+
+        self.draw_boot_set(ui, config, data);
+
+        if let Some(set) = &config.sys_paths.get().set {
+            ui.text_disabled(format!("Games will boot using the \"{set}\" system files."));
+        }
+
+        if ui.button("Validate active files") {
+            self.active_info = Some(SetInfo::new(config.sys_paths.get()));
+        }
+        if let Some(info) = &self.active_info {
+            let _id = ui.push_id("active");
+            draw_set_info(ui, info);
+        }
+
+        let mut removed = None;
+
+        for i in 0..self.set_infos.len() {
+            let _id = ui.push_id_usize(i);
+            let mut set = Cow::Borrowed(&config.sys_paths.inner().global().sets[i]);
+
+            if !ui.collapsing_header(
+                format!(
+                    "{}###header",
+                    if set.name.is_empty() {
+                        "<unnamed>"
+                    } else {
+                        &set.name
+                    }
+                ),
+                TreeNodeFlags::NO_TREE_PUSH_ON_OPEN,
+            ) {
+                continue;
+            }
+
+            let mut renamed = None;
+
+            if let Some(_table) = ui.begin_table_with_flags("set", 2, TableFlags::NO_CLIP) {
+                ui.table_setup_column_with(TableColumnSetup {
+                    flags: TableColumnFlags::WIDTH_FIXED,
+                    ..TableColumnSetup::new("")
+                });
+                ui.table_setup_column("");
+
+                ui.table_next_row();
+                ui.table_next_column();
+                ui.align_text_to_frame_padding();
+                ui.text("Name:");
+                ui.table_next_column();
+                let mut name = set.name.clone();
+                ui.set_next_item_width(ui.content_region_avail()[0]);
+                if ui
+                    .input_text("##name", &mut name)
+                    .enter_returns_true(true)
+                    .build()
+                    && name != set.name
+                    && !config
+                        .sys_paths
+                        .inner()
+                        .global()
+                        .sets
+                        .iter()
+                        .any(|set| set.name == name)
+                {
+                    renamed = Some((set.name.clone(), name.clone()));
+                    set.to_mut().name = name;
+                }
+
+                let mut updated = false;
+                macro_rules! path {
+                    ($field: ident, $label: literal, $placeholder: literal, $is_dir: expr) => {
+                        let mut path = set.$field.clone();
+                        if draw_path(ui, $label, &mut path, $placeholder, $is_dir) {
+                            set.to_mut().$field = path;
+                            updated = true;
+                        }
+                    };
+                }
+                path!(dir, "System dir", "", true);
+                path!(arm7_bios, "ARM7 BIOS", "$sys_dir_path/biosnds7.bin", false);
+                path!(arm9_bios, "ARM9 BIOS", "$sys_dir_path/biosnds9.bin", false);
+                path!(firmware, "Firmware", "$sys_dir_path/firmware.bin", false);
+                if updated {
+                    self.set_infos[i] = None;
+                    self.active_info = None;
+                }
+            }
+
+            if ui.button("Validate") {
+                self.set_infos[i] = Some(SetInfo::new(&set.resolve()));
+            }
+            ui.same_line();
+            if ui.button("Remove") {
+                removed = Some(i);
+            }
+
+            if let Some(info) = &self.set_infos[i] {
+                draw_set_info(ui, info);
+            }
+
+            if let Cow::Owned(set) = set {
+                config.sys_paths.inner_mut().update_global(|global| {
+                    global.sets[i] = set;
+                    if let Some((prev_name, new_name)) = &renamed {
+                        if global.set.as_ref() == Some(prev_name) {
+                            global.set = Some(new_name.clone());
+                        }
+                    }
+                });
+                if let Some((prev_name, new_name)) = renamed {
+                    config.sys_paths.inner_mut().update_game(|game| {
+                        if let Some(Some(set)) = &mut game.set {
+                            if *set == prev_name {
+                                *set = new_name;
+                            }
+                        }
+                    });
+                }
+            }
+        }
+
+        if let Some(i) = removed {
+            self.set_infos.remove(i);
+            config.sys_paths.inner_mut().update_global(|global| {
+                let set = global.sets.remove(i);
+                if global.set.as_ref() == Some(&set.name) {
+                    global.set = None;
+                }
+            });
+            self.active_info = None;
+        }
+
+        if ui.button("\u{f067} Add set") {
+            let sets = &config.sys_paths.inner().global().sets;
+            let name = (1..)
+                .map(|i| format!("Set {i}"))
+                .find(|name| sets.iter().all(|set| set.name != *name))
+                .unwrap();
+            config.sys_paths.inner_mut().update_global(|global| {
+                global.sets.push(SysPathSet {
+                    name,
+                    ..Default::default()
+                });
+            });
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::game_db::{self, GbaTitle};
+use imgui::{StyleColor, TableFlags, Ui};
+use rfd::FileDialog;
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+pub(super) enum Action {
+    Insert(PathBuf),
+    Eject,
+    Close,
+}
+
+/// Reads the game code from the header of the GBA ROM at `path`.
+fn read_gba_game_code(path: &Path) -> io::Result<[u8; 4]> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(0xAC))?;
+    let mut game_code = [0; 4];
+    file.read_exact(&mut game_code)?;
+    Ok(game_code)
+}
+
+fn is_gba_rom(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gba"))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// A panel listing the GBA games a DS game recognizes in the GBA slot for its dual-slot features
+/// (as specified by [`game_db::dual_slot_gba_titles`]), along with any matching ROMs found next
+/// to the DS ROM, to pick the one to insert for the current game.
+pub(super) struct Panel {
+    title: String,
+    gba_titles: &'static [GbaTitle],
+    /// The matching GBA ROMs found in the DS ROM's directory, with the index of the title they
+    /// match.
+    found: Vec<(usize, PathBuf)>,
+    inserted: Option<PathBuf>,
+    error: Option<String>,
+}
+
+impl Panel {
+    pub fn new(
+        title: String,
+        ds_game_code: u32,
+        rom_dir: Option<&Path>,
+        inserted: Option<&Path>,
+    ) -> Option<Self> {
+        let gba_titles = game_db::dual_slot_gba_titles(ds_game_code);
+        if gba_titles.is_empty() {
+            return None;
+        }
+
+        let mut found = Vec::new();
+        if let Some(entries) = rom_dir.and_then(|dir| fs::read_dir(dir).ok()) {
+            for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+                if !is_gba_rom(&path) {
+                    continue;
+                }
+                let Ok(game_code) = read_gba_game_code(&path) else {
+                    continue;
+                };
+                if let Some(i) = gba_titles.iter().position(|title| title.matches(game_code)) {
+                    found.push((i, path));
+                }
+            }
+        }
+        found.sort_unstable();
+
+        Some(Panel {
+            title,
+            gba_titles,
+            found,
+            inserted: inserted.map(Path::to_path_buf),
+            error: None,
+        })
+    }
+
+    pub fn set_inserted(&mut self, path: Option<PathBuf>) {
+        self.inserted = path;
+    }
+
+    fn pick_file(&mut self, gba_title: &GbaTitle) -> Option<Action> {
+        let path = FileDialog::new()
+            .add_filter("GBA ROM file", &["gba"])
+            .pick_file()?;
+        match read_gba_game_code(&path) {
+            Ok(game_code) if gba_title.matches(game_code) => {
+                self.error = None;
+                Some(Action::Insert(path))
+            }
+            Ok(game_code) => {
+                self.error = Some(format!(
+                    "`{}` isn't a copy of {} (its game code is {}).",
+                    file_name(&path),
+                    gba_title.name,
+                    String::from_utf8_lossy(&game_code),
+                ));
+                None
+            }
+            Err(err) => {
+                self.error = Some(format!("Couldn't read `{}`: {err}", file_name(&path)));
+                None
+            }
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui) -> Option<Action> {
+        let mut action = None;
+        let mut opened = true;
+        ui.window(format!("Dual-slot - {}###dual_slot", self.title))
+            .always_auto_resize(true)
+            .collapsible(false)
+            .opened(&mut opened)
+            .build(|| {
+                ui.text("This game unlocks extra content when one of these GBA games is inserted:");
+                if let Some(_table) =
+                    ui.begin_table_with_flags("gba_titles", 3, TableFlags::SIZING_FIXED_FIT)
+                {
+                    let gba_titles = self.gba_titles;
+                    for (i, gba_title) in gba_titles.iter().enumerate() {
+                        let _id = ui.push_id_usize(i);
+                        ui.table_next_row();
+                        ui.table_next_column();
+                        ui.text(gba_title.name);
+
+                        ui.table_next_column();
+                        let mut any_found = false;
+                        for (j, (_, path)) in self
+                            .found
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, (title_index, _))| *title_index == i)
+                        {
+                            any_found = true;
+                            let _id = ui.push_id_usize(j);
+                            if self.inserted.as_deref() == Some(path.as_path()) {
+                                ui.text_colored([0.4, 0.8, 0.4, 1.0], "Inserted");
+                            } else if ui.small_button("Insert") {
+                                action = Some(Action::Insert(path.clone()));
+                            }
+                            ui.same_line();
+                            ui.text(file_name(path));
+                        }
+                        if !any_found {
+                            ui.text_colored(ui.style_color(StyleColor::TextDisabled), "Not found");
+                        }
+
+                        ui.table_next_column();
+                        if ui.small_button("Browse...") {
+                            if let Some(pick_action) = self.pick_file(gba_title) {
+                                action = Some(pick_action);
+                            }
+                        }
+                    }
+                }
+
+                if let Some(error) = &self.error {
+                    ui.text_colored([0.9, 0.3, 0.3, 1.0], error);
+                }
+
+                ui.separator();
+                match &self.inserted {
+                    Some(path) => ui.text(format!("Inserted: {}", file_name(path))),
+                    None => ui.text("No GBA game is inserted."),
+                }
+                ui.text_disabled("Changes take effect the next time the game is launched.");
+                if ui.button("Close") {
+                    action = Some(Action::Close);
+                }
+                if self.inserted.is_some() {
+                    ui.same_line();
+                    if ui.button("Eject") {
+                        action = Some(Action::Eject);
+                    }
+                }
+            });
+        if !opened {
+            action = Some(Action::Close);
+        }
+        action
+    }
+}
@@ -0,0 +1,171 @@
+use dust_core::{
+    cpu::{dma, Core},
+    emu::fault_injection::Fault,
+};
+use imgui::{StyleColor, Ui};
+
+pub(super) enum Action {
+    /// Any periodic injection should be stopped too.
+    Close,
+    Inject(Fault),
+    /// Starts or stops injecting a fault every given number of milliseconds of emulated time.
+    SetPeriodic(Option<(Fault, u32)>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    DelayDmaCompletion,
+    SpuriousIrq,
+    CorruptIpcFifoEntry,
+}
+
+impl Kind {
+    const ALL: [Kind; 3] = [
+        Kind::DelayDmaCompletion,
+        Kind::SpuriousIrq,
+        Kind::CorruptIpcFifoEntry,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Kind::DelayDmaCompletion => "Delay DMA completion",
+            Kind::SpuriousIrq => "Spurious IRQ",
+            Kind::CorruptIpcFifoEntry => "Corrupt IPC FIFO entry",
+        }
+    }
+}
+
+/// A window to inject hardware faults into the emulated system, either once or periodically, to
+/// check how the emulated software and the emulator itself handle them.
+pub(super) struct FaultInjection {
+    arm9: bool,
+    kind: Kind,
+    dma_channel: u8,
+    delay_input: String,
+    irqs_input: String,
+    mask_input: String,
+    interval_ms: u32,
+    periodic_active: bool,
+}
+
+impl FaultInjection {
+    pub fn new() -> Self {
+        FaultInjection {
+            arm9: true,
+            kind: Kind::DelayDmaCompletion,
+            dma_channel: 0,
+            delay_input: "100000".to_owned(),
+            irqs_input: "00000001".to_owned(),
+            mask_input: "00000001".to_owned(),
+            interval_ms: 1000,
+            periodic_active: false,
+        }
+    }
+
+    fn fault(&self) -> Option<Fault> {
+        let core = if self.arm9 { Core::Arm9 } else { Core::Arm7 };
+        Some(match self.kind {
+            Kind::DelayDmaCompletion => Fault::DelayDmaCompletion {
+                core,
+                channel: dma::Index::new(self.dma_channel),
+                delay: self.delay_input.parse().ok()?,
+            },
+            Kind::SpuriousIrq => Fault::SpuriousIrq {
+                core,
+                irqs: u32::from_str_radix(&self.irqs_input, 16).ok()?,
+            },
+            Kind::CorruptIpcFifoEntry => Fault::CorruptIpcFifoEntry {
+                core,
+                mask: u32::from_str_radix(&self.mask_input, 16).ok()?,
+            },
+        })
+    }
+
+    pub fn draw(&mut self, ui: &Ui, emu_running: bool) -> Option<Action> {
+        let mut action = None;
+        let mut opened = true;
+        ui.window("Fault injection###fault_injection")
+            .always_auto_resize(true)
+            .opened(&mut opened)
+            .build(|| {
+                ui.radio_button("ARM9", &mut self.arm9, true);
+                ui.same_line();
+                ui.radio_button("ARM7", &mut self.arm9, false);
+
+                let mut kind_index = Kind::ALL.iter().position(|k| *k == self.kind).unwrap();
+                if ui.combo("Fault", &mut kind_index, &Kind::ALL, |kind| {
+                    kind.name().into()
+                }) {
+                    self.kind = Kind::ALL[kind_index];
+                }
+
+                let hex_input_width = ui.calc_text_size("00000000")[0] * 2.0;
+                match self.kind {
+                    Kind::DelayDmaCompletion => {
+                        ui.slider("DMA channel", 0, 3, &mut self.dma_channel);
+                        ui.set_next_item_width(hex_input_width);
+                        ui.input_text("Delay (cycles)", &mut self.delay_input)
+                            .auto_select_all(true)
+                            .chars_decimal(true)
+                            .build();
+                    }
+                    Kind::SpuriousIrq => {
+                        ui.set_next_item_width(hex_input_width);
+                        ui.input_text("IRQ mask", &mut self.irqs_input)
+                            .auto_select_all(true)
+                            .chars_hexadecimal(true)
+                            .build();
+                    }
+                    Kind::CorruptIpcFifoEntry => {
+                        ui.set_next_item_width(hex_input_width);
+                        ui.input_text("XOR mask", &mut self.mask_input)
+                            .auto_select_all(true)
+                            .chars_hexadecimal(true)
+                            .build();
+                    }
+                }
+
+                let fault = self.fault();
+
+                ui.separator();
+                ui.disabled(!emu_running || fault.is_none(), || {
+                    if ui.button("Inject") {
+                        action = Some(Action::Inject(fault.unwrap()));
+                    }
+                });
+
+                ui.separator();
+                ui.set_next_item_width(hex_input_width);
+                ui.input_scalar("Interval (ms)", &mut self.interval_ms)
+                    .build();
+                self.interval_ms = self.interval_ms.max(1);
+                if self.periodic_active {
+                    if ui.button("Stop periodic injection") {
+                        self.periodic_active = false;
+                        action = Some(Action::SetPeriodic(None));
+                    }
+                } else {
+                    ui.disabled(!emu_running || fault.is_none(), || {
+                        if ui.button("Start periodic injection") {
+                            self.periodic_active = true;
+                            action = Some(Action::SetPeriodic(Some((
+                                fault.unwrap(),
+                                self.interval_ms,
+                            ))));
+                        }
+                    });
+                }
+                if !emu_running {
+                    self.periodic_active = false;
+                    ui.text_colored(
+                        ui.style_color(StyleColor::TextDisabled),
+                        "Start the emulator to inject faults.",
+                    );
+                }
+            });
+        if !opened {
+            action = Some(Action::Close);
+        }
+        action
+    }
+}
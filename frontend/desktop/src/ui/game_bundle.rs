@@ -0,0 +1,266 @@
+use super::Config;
+use crate::config::game_bundle::{self, Bundle};
+use imgui::Ui;
+use rfd::FileDialog;
+use std::path::Path;
+
+pub(super) enum Action {
+    Close,
+}
+
+struct Entry {
+    title: String,
+    selected: bool,
+    /// Whether the game already has a configuration file, whose settings will be merged with the
+    /// imported ones.
+    exists: bool,
+}
+
+enum Mode {
+    Export,
+    Import(Bundle),
+}
+
+/// A window to export the configuration overrides of a selection of games to a single shareable
+/// file, and to import the ones contained in such a file.
+pub(super) struct GameBundleWindow {
+    mode: Mode,
+    entries: Vec<Entry>,
+}
+
+fn games_in_dir(games_dir: &Path) -> Vec<String> {
+    game_bundle::list_games(games_dir).unwrap_or_else(|err| {
+        error!(
+            "Couldn't list game configurations",
+            "Couldn't list the game configurations in `{}`: {err}",
+            games_dir.display()
+        );
+        Vec::new()
+    })
+}
+
+impl GameBundleWindow {
+    pub fn new(config: &Config) -> Self {
+        let mut result = GameBundleWindow {
+            mode: Mode::Export,
+            entries: Vec::new(),
+        };
+        result.switch_to_export(config);
+        result
+    }
+
+    fn switch_to_export(&mut self, config: &Config) {
+        self.mode = Mode::Export;
+        self.entries = config
+            .games_base_path
+            .as_deref()
+            .map(games_in_dir)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|title| Entry {
+                title,
+                selected: false,
+                exists: true,
+            })
+            .collect();
+    }
+
+    fn switch_to_import(&mut self, config: &Config, bundle: Bundle) {
+        let existing = config
+            .games_base_path
+            .as_deref()
+            .map(games_in_dir)
+            .unwrap_or_default();
+        self.entries = bundle
+            .games
+            .keys()
+            .map(|title| Entry {
+                title: title.clone(),
+                selected: true,
+                exists: existing.contains(title),
+            })
+            .collect();
+        self.mode = Mode::Import(bundle);
+    }
+
+    fn export(&self, config: &Config, current_game: Option<&str>) {
+        let Some(games_dir) = &config.games_base_path else {
+            return;
+        };
+        let Some(path) = FileDialog::new()
+            .add_filter("Game configuration bundle", &["json"])
+            .set_file_name("game_configs.json")
+            .save_file()
+        else {
+            return;
+        };
+
+        let mut bundle = Bundle::default();
+        let mut errors = Vec::new();
+        for entry in self.entries.iter().filter(|entry| entry.selected) {
+            // The running game's file might be outdated, as it's only written when it's stopped
+            let result = if current_game == Some(entry.title.as_str()) {
+                bundle.insert(entry.title.clone(), &config.config.serialize_game())
+            } else {
+                bundle.insert_from_dir(games_dir, &entry.title)
+            };
+            if let Err(err) = result {
+                errors.push(format!("{}: {err}", entry.title));
+            }
+        }
+
+        if let Err(err) = bundle.write(&path) {
+            error!(
+                "Couldn't export game configurations",
+                "Couldn't write the bundle to `{}`: {err}",
+                path.display()
+            );
+        } else if !errors.is_empty() {
+            warning!(
+                "Couldn't export all game configurations",
+                "Some games' configurations couldn't be read, and weren't exported: {}",
+                format_list!(errors)
+            );
+        }
+    }
+
+    fn import(&self, bundle: &Bundle, config: &mut Config, current_game: Option<&str>) {
+        let Some(games_dir) = config.games_base_path.clone() else {
+            return;
+        };
+        let mut errors = Vec::new();
+        for entry in self.entries.iter().filter(|entry| entry.selected) {
+            // The running game's settings are written back to its file once it's stopped, so
+            // they have to be updated in place
+            let result = if current_game == Some(entry.title.as_str()) {
+                bundle
+                    .merge(&entry.title, &config.config.serialize_game())
+                    .map(|settings| config.config.deserialize_game(&settings))
+            } else {
+                bundle.import_into_dir(&games_dir, &entry.title)
+            };
+            if let Err(err) = result {
+                errors.push(format!("{}: {err}", entry.title));
+            }
+        }
+        if !errors.is_empty() {
+            error!(
+                "Couldn't import all game configurations",
+                "Some games' configurations couldn't be imported: {}",
+                format_list!(errors)
+            );
+        }
+    }
+
+    fn draw_entries(&mut self, ui: &Ui, empty_message: &str) {
+        if self.entries.is_empty() {
+            ui.text_disabled(empty_message);
+            return;
+        }
+
+        if ui.small_button("Select all") {
+            self.entries
+                .iter_mut()
+                .for_each(|entry| entry.selected = true);
+        }
+        ui.same_line();
+        if ui.small_button("Select none") {
+            self.entries
+                .iter_mut()
+                .for_each(|entry| entry.selected = false);
+        }
+
+        let height = (ui.text_line_height_with_spacing() * 12.0).min(
+            ui.frame_height_with_spacing() * self.entries.len() as f32
+                + style!(ui, window_padding)[1] * 2.0,
+        );
+        ui.child_window("entries")
+            .size([0.0, height])
+            .border(true)
+            .build(|| {
+                let is_import = matches!(self.mode, Mode::Import(_));
+                for entry in &mut self.entries {
+                    ui.checkbox(&entry.title, &mut entry.selected);
+                    if is_import && entry.exists {
+                        ui.same_line();
+                        ui.text_disabled("(will be merged)");
+                    }
+                }
+            });
+    }
+
+    pub fn draw(
+        &mut self,
+        ui: &Ui,
+        config: &mut Config,
+        current_game: Option<&str>,
+    ) -> Option<Action> {
+        let mut action = None;
+        let mut opened = true;
+        ui.window("Share game configurations###game_bundle")
+            .size([400.0, 0.0], imgui::Condition::FirstUseEver)
+            .opened(&mut opened)
+            .build(|| {
+                let any_selected = self.entries.iter().any(|entry| entry.selected);
+                match self.mode {
+                    Mode::Export => {
+                        ui.text_wrapped(
+                            "Select the games whose settings should be exported; paths, save \
+                             locations and input mappings are left out.",
+                        );
+                        self.draw_entries(ui, "No games have a configuration yet");
+
+                        ui.disabled(!any_selected, || {
+                            if ui.button("\u{f56e} Export selected...") {
+                                self.export(config, current_game);
+                            }
+                        });
+                        ui.same_line();
+                        if ui.button("\u{f56f} Import bundle...") {
+                            if let Some(path) = FileDialog::new()
+                                .add_filter("Game configuration bundle", &["json"])
+                                .pick_file()
+                            {
+                                match Bundle::read(&path) {
+                                    Ok(bundle) => self.switch_to_import(config, bundle),
+                                    Err(err) => {
+                                        error!(
+                                            "Couldn't import game configurations",
+                                            "Couldn't read the bundle at `{}`: {err}",
+                                            path.display()
+                                        );
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    Mode::Import(_) => {
+                        ui.text_wrapped(
+                            "Select the games whose settings should be imported; settings not \
+                             included in the bundle will be kept.",
+                        );
+                        self.draw_entries(ui, "The bundle doesn't contain any games");
+
+                        ui.disabled(!any_selected, || {
+                            if ui.button("\u{f56f} Import selected") {
+                                let Mode::Import(bundle) = &self.mode else {
+                                    unreachable!();
+                                };
+                                self.import(bundle, config, current_game);
+                                self.switch_to_export(config);
+                            }
+                        });
+                        ui.same_line();
+                        if ui.button("Cancel") {
+                            self.switch_to_export(config);
+                        }
+                    }
+                }
+            });
+        if !opened {
+            action = Some(Action::Close);
+        }
+        action
+    }
+}
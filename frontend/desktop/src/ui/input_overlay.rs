@@ -0,0 +1,138 @@
+use crate::FrameInput;
+use dust_core::emu::input::Keys;
+use imgui::{DrawListMut, Ui};
+
+const PRESSED_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.9];
+const RELEASED_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 0.8];
+const PRESSED_TEXT_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+fn draw_button(
+    ui: &Ui,
+    draw_list: &DrawListMut,
+    pressed: bool,
+    label: &str,
+    min: [f32; 2],
+    max: [f32; 2],
+    rounding: f32,
+) {
+    draw_list
+        .add_rect(
+            min,
+            max,
+            if pressed {
+                PRESSED_COLOR
+            } else {
+                RELEASED_COLOR
+            },
+        )
+        .filled(pressed)
+        .rounding(rounding)
+        .build();
+    let text_size = ui.calc_text_size(label);
+    draw_list.add_text(
+        [
+            (min[0] + max[0] - text_size[0]) * 0.5,
+            (min[1] + max[1] - text_size[1]) * 0.5,
+        ],
+        if pressed {
+            PRESSED_TEXT_COLOR
+        } else {
+            RELEASED_COLOR
+        },
+        label,
+    );
+}
+
+/// An overlay showing the keys held and the touchscreen position as seen by the emulated console
+/// at the end of the last frame, meant for streaming and for verifying input recordings.
+pub struct InputOverlay {
+    input: FrameInput,
+}
+
+impl InputOverlay {
+    pub fn new() -> Self {
+        InputOverlay {
+            input: FrameInput::default(),
+        }
+    }
+
+    pub fn update(&mut self, input: FrameInput) {
+        self.input = input;
+    }
+
+    pub fn draw(&self, ui: &Ui) {
+        let display_size = ui.io().display_size;
+        let padding = style!(ui, window_padding);
+        ui.window("##input_overlay")
+            .position(
+                [display_size[0] - padding[0], display_size[1] - padding[1]],
+                imgui::Condition::Always,
+            )
+            .position_pivot([1.0, 1.0])
+            .bg_alpha(0.75)
+            .no_decoration()
+            .always_auto_resize(true)
+            .movable(false)
+            .focus_on_appearing(false)
+            .no_nav()
+            .no_inputs()
+            .build(|| {
+                // Lay everything out on a grid of squares the size of a line of text
+                let u = ui.text_line_height();
+                let origin = ui.cursor_screen_pos();
+                let pos = |x: f32, y: f32| [origin[0] + x * u, origin[1] + y * u];
+                ui.dummy([12.0 * u, 12.5 * u]);
+
+                let draw_list = ui.get_window_draw_list();
+                let rounding = u * 0.25;
+
+                for (key, label, min, max, rounding) in [
+                    (Keys::L, "L", pos(0.0, 0.0), pos(3.0, 1.0), rounding),
+                    (Keys::R, "R", pos(9.0, 0.0), pos(12.0, 1.0), rounding),
+                    (Keys::UP, "", pos(1.5, 1.5), pos(2.5, 2.5), 0.0),
+                    (Keys::DOWN, "", pos(1.5, 3.5), pos(2.5, 4.5), 0.0),
+                    (Keys::LEFT, "", pos(0.5, 2.5), pos(1.5, 3.5), 0.0),
+                    (Keys::RIGHT, "", pos(2.5, 2.5), pos(3.5, 3.5), 0.0),
+                    (Keys::X, "X", pos(9.5, 1.5), pos(10.5, 2.5), u * 0.5),
+                    (Keys::B, "B", pos(9.5, 3.5), pos(10.5, 4.5), u * 0.5),
+                    (Keys::Y, "Y", pos(8.5, 2.5), pos(9.5, 3.5), u * 0.5),
+                    (Keys::A, "A", pos(10.5, 2.5), pos(11.5, 3.5), u * 0.5),
+                    (
+                        Keys::SELECT,
+                        "Sel",
+                        pos(3.75, 4.75),
+                        pos(5.75, 5.75),
+                        rounding,
+                    ),
+                    (
+                        Keys::START,
+                        "Sta",
+                        pos(6.25, 4.75),
+                        pos(8.25, 5.75),
+                        rounding,
+                    ),
+                ] {
+                    let pressed = self.input.keys.contains(key);
+                    draw_button(ui, &draw_list, pressed, label, min, max, rounding);
+                }
+
+                // Touchscreen, keeping its 4:3 aspect ratio
+                let (ts_min, ts_max) = (pos(2.0, 6.5), pos(10.0, 12.5));
+                draw_list
+                    .add_rect(ts_min, ts_max, RELEASED_COLOR)
+                    .rounding(rounding)
+                    .build();
+                if let Some([x, y]) = self.input.touch_pos {
+                    // Touch coordinates have 4 fractional bits per pixel
+                    let center = [
+                        ts_min[0] + (ts_max[0] - ts_min[0]) * (x as f32 / 4096.0),
+                        ts_min[1] + (ts_max[1] - ts_min[1]) * (y as f32 / 3072.0),
+                    ];
+                    draw_list
+                        .add_circle(center, u * 0.25, PRESSED_COLOR)
+                        .filled(true)
+                        .build();
+                }
+            });
+    }
+}
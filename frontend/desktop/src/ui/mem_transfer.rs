@@ -0,0 +1,84 @@
+use imgui::{StyleColor, Ui};
+
+pub(super) enum Action {
+    Close,
+    Export { arm9: bool, addr: u32, len: u32 },
+    Import { arm9: bool, addr: u32 },
+}
+
+/// A window to export an address range as seen by either CPU to a binary file, or to load one
+/// back into it.
+pub(super) struct MemTransfer {
+    arm9: bool,
+    addr_input: String,
+    len_input: String,
+}
+
+impl MemTransfer {
+    pub fn new() -> Self {
+        MemTransfer {
+            arm9: true,
+            addr_input: "02000000".to_owned(),
+            len_input: "400000".to_owned(),
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, emu_running: bool, playing: bool) -> Option<Action> {
+        let mut action = None;
+        let mut opened = true;
+        ui.window("Export/import memory###mem_transfer")
+            .always_auto_resize(true)
+            .opened(&mut opened)
+            .build(|| {
+                ui.radio_button("ARM9", &mut self.arm9, true);
+                ui.same_line();
+                ui.radio_button("ARM7", &mut self.arm9, false);
+
+                ui.set_next_item_width(ui.calc_text_size("00000000")[0] * 2.0);
+                ui.input_text("Address", &mut self.addr_input)
+                    .auto_select_all(true)
+                    .chars_hexadecimal(true)
+                    .build();
+                ui.set_next_item_width(ui.calc_text_size("00000000")[0] * 2.0);
+                ui.input_text("Length", &mut self.len_input)
+                    .auto_select_all(true)
+                    .chars_hexadecimal(true)
+                    .build();
+
+                let addr = u32::from_str_radix(&self.addr_input, 16).ok();
+                let len = u32::from_str_radix(&self.len_input, 16)
+                    .ok()
+                    .filter(|len| *len != 0);
+
+                ui.separator();
+                ui.disabled(!emu_running || addr.is_none() || len.is_none(), || {
+                    if ui.button("Export...") {
+                        action = Some(Action::Export {
+                            arm9: self.arm9,
+                            addr: addr.unwrap(),
+                            len: len.unwrap(),
+                        });
+                    }
+                });
+                ui.same_line();
+                ui.disabled(!emu_running || playing || addr.is_none(), || {
+                    if ui.button("Import...") {
+                        action = Some(Action::Import {
+                            arm9: self.arm9,
+                            addr: addr.unwrap(),
+                        });
+                    }
+                });
+                if emu_running && playing {
+                    ui.text_colored(
+                        ui.style_color(StyleColor::TextDisabled),
+                        "Pause the emulator to import memory.",
+                    );
+                }
+            });
+        if !opened {
+            action = Some(Action::Close);
+        }
+        action
+    }
+}
@@ -0,0 +1,87 @@
+use crate::notifications::{Bus, Kind, Level, Notification, Sender};
+use std::time::{Duration, Instant};
+
+const MESSAGE_DURATION: Duration = Duration::from_secs(6);
+
+const WARNING_COLOR: [f32; 4] = [1.0, 0.85, 0.3, 1.0];
+const ERROR_COLOR: [f32; 4] = [1.0, 0.4, 0.4, 1.0];
+
+/// Shows notifications as toasts over the emulator's output, whether posted directly from the UI
+/// thread or through the bus from other threads.
+pub struct Osd {
+    bus: Bus,
+    messages: Vec<(Notification, Instant)>,
+}
+
+impl Osd {
+    pub fn new() -> Self {
+        Osd {
+            bus: Bus::new(),
+            messages: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn sender(&self) -> &Sender {
+        self.bus.sender()
+    }
+
+    pub fn post(&mut self, notification: Notification) {
+        let now = Instant::now();
+        if notification.kind != Kind::Other {
+            if let Some(message) = self
+                .messages
+                .iter_mut()
+                .find(|(message, _)| message.kind == notification.kind)
+            {
+                *message = (notification, now);
+                return;
+            }
+        }
+        self.messages.push((notification, now));
+    }
+
+    pub fn draw(&mut self, ui: &imgui::Ui) {
+        let posted = self.bus.drain().collect::<Vec<_>>();
+        for notification in posted {
+            self.post(notification);
+        }
+
+        let now = Instant::now();
+        self.messages
+            .retain(|(_, shown_time)| now - *shown_time < MESSAGE_DURATION);
+        if self.messages.is_empty() {
+            return;
+        }
+
+        let display_size = ui.io().display_size;
+        let padding = style!(ui, window_padding);
+        ui.window("##osd")
+            .position(
+                [padding[0], display_size[1] - padding[1]],
+                imgui::Condition::Always,
+            )
+            .position_pivot([0.0, 1.0])
+            .size_constraints([0.0, 0.0], [display_size[0] * 0.5, f32::INFINITY])
+            .bg_alpha(0.75)
+            .no_decoration()
+            .always_auto_resize(true)
+            .movable(false)
+            .focus_on_appearing(false)
+            .no_nav()
+            .build(|| {
+                for (notification, _) in &self.messages {
+                    let _color = match notification.level {
+                        Level::Info => None,
+                        Level::Warning => {
+                            Some(ui.push_style_color(imgui::StyleColor::Text, WARNING_COLOR))
+                        }
+                        Level::Error => {
+                            Some(ui.push_style_color(imgui::StyleColor::Text, ERROR_COLOR))
+                        }
+                    };
+                    ui.text_wrapped(&notification.message);
+                }
+            });
+    }
+}
@@ -0,0 +1,143 @@
+use super::window::FrameTimings;
+use crate::audio;
+use imgui::Ui;
+use std::{sync::atomic::Ordering, time::Duration};
+
+// How much each new frame's timings contribute to the displayed averages
+const FRAME_TIMING_SMOOTHING: f32 = 0.05;
+
+/// The time spent by each subsystem since timings were last taken, in total across all emulated
+/// frames.
+pub struct SubsystemTimes {
+    pub emulation: Duration,
+    pub renderer_2d: Duration,
+    pub renderer_3d: Duration,
+    pub audio: Duration,
+}
+
+/// An overlay showing the emulation speed along with the state of the audio output buffer and the
+/// latency added by presentation, to help tune the audio buffer and presentation settings, and
+/// the time taken by each subsystem per emulated frame, to help find bottlenecks.
+pub struct PerfOverlay {
+    fps: f32,
+    acquire_wait_ms: f32,
+    present_delay_ms: f32,
+    last_frame_count: Option<u64>,
+    emulation_ms: f32,
+    renderer_2d_ms: f32,
+    renderer_3d_ms: f32,
+    audio_ms: f32,
+}
+
+impl PerfOverlay {
+    pub fn new() -> Self {
+        PerfOverlay {
+            fps: 0.0,
+            acquire_wait_ms: 0.0,
+            present_delay_ms: 0.0,
+            last_frame_count: None,
+            emulation_ms: 0.0,
+            renderer_2d_ms: 0.0,
+            renderer_3d_ms: 0.0,
+            audio_ms: 0.0,
+        }
+    }
+
+    /// Averages the given subsystem times over the frames emulated since the last update; the
+    /// first update after profiling was stopped only records the current frame count, as the
+    /// times it's passed don't cover a known number of frames.
+    pub fn update_subsystem_times(&mut self, frame_count: u64, times: SubsystemTimes) {
+        let Some(last_frame_count) = self.last_frame_count.replace(frame_count) else {
+            return;
+        };
+        // The frame count goes back to 0 whenever the emulator is restarted
+        let frames = match frame_count.checked_sub(last_frame_count) {
+            Some(0) | None => return,
+            Some(frames) => frames as f32,
+        };
+        let smooth = |avg: &mut f32, value: Duration| {
+            *avg += (value.as_secs_f32() * 1000.0 / frames - *avg) * FRAME_TIMING_SMOOTHING;
+        };
+        smooth(&mut self.emulation_ms, times.emulation);
+        smooth(&mut self.renderer_2d_ms, times.renderer_2d);
+        smooth(&mut self.renderer_3d_ms, times.renderer_3d);
+        smooth(&mut self.audio_ms, times.audio);
+    }
+
+    pub fn stop_profiling(&mut self) {
+        self.last_frame_count = None;
+    }
+
+    pub fn update_fps(&mut self, fps: f32) {
+        self.fps = fps;
+    }
+
+    pub fn update_frame_timings(&mut self, timings: FrameTimings) {
+        let smooth = |avg: &mut f32, value: f32| {
+            *avg += (value - *avg) * FRAME_TIMING_SMOOTHING;
+        };
+        smooth(
+            &mut self.acquire_wait_ms,
+            timings.acquire_wait.as_secs_f32() * 1000.0,
+        );
+        smooth(
+            &mut self.present_delay_ms,
+            timings.present_delay.as_secs_f32() * 1000.0,
+        );
+    }
+
+    pub fn draw(
+        &self,
+        ui: &Ui,
+        audio_channel: Option<&audio::output::Channel>,
+        max_frame_latency: u8,
+        top: f32,
+    ) {
+        let padding = style!(ui, window_padding);
+        ui.window("##perf_overlay")
+            .position([padding[0], top + padding[1]], imgui::Condition::Always)
+            .bg_alpha(0.75)
+            .no_decoration()
+            .always_auto_resize(true)
+            .movable(false)
+            .focus_on_appearing(false)
+            .no_nav()
+            .no_inputs()
+            .build(|| {
+                ui.text(format!("{:.1} FPS", self.fps));
+                ui.text(format!(
+                    "Present delay: {:.2} ms (swapchain wait: {:.2} ms)",
+                    self.present_delay_ms, self.acquire_wait_ms,
+                ));
+                ui.text(format!("Max queued frames: {max_frame_latency}"));
+
+                ui.separator();
+                ui.text("Time per frame:");
+                for (name, value) in [
+                    ("Emulation", self.emulation_ms),
+                    ("2D rendering", self.renderer_2d_ms),
+                    ("3D rendering", self.renderer_3d_ms),
+                    ("Audio", self.audio_ms),
+                    ("Presentation", self.present_delay_ms),
+                ] {
+                    ui.text(format!("  {name}: {value:.2} ms"));
+                }
+                ui.separator();
+
+                let Some(channel) = audio_channel else {
+                    ui.text_disabled("No audio output");
+                    return;
+                };
+
+                let (buffered, capacity) = channel.buffer_fill();
+                ui.text(format!("Audio buffer: {buffered}/{capacity} samples"));
+
+                let stats = channel.stats();
+                ui.text(format!(
+                    "Underruns: {}, overruns: {}",
+                    stats.underruns.load(Ordering::Relaxed),
+                    stats.overruns.load(Ordering::Relaxed),
+                ));
+            });
+    }
+}
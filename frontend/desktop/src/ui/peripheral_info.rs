@@ -0,0 +1,153 @@
+use super::config_editor::Section as ConfigSection;
+use crate::game_db::Peripherals;
+use imgui::{StyleColor, TableFlags, Ui};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Enabled,
+    Disabled,
+    Unsupported,
+}
+
+struct Requirement {
+    name: &'static str,
+    status: Status,
+    settings_section: Option<ConfigSection>,
+    /// Whether the GBA game to insert can be picked from the game's known dual-slot titles
+    /// instead of the settings.
+    opens_dual_slot: bool,
+}
+
+pub(super) enum Action {
+    Continue,
+    OpenSettings(ConfigSection),
+    OpenDualSlot,
+}
+
+/// A panel shown before a game starts running, listing the extra hardware it's known to use (as
+/// specified by the game database) and whether it's currently available.
+pub(super) struct Panel {
+    title: String,
+    requirements: Vec<Requirement>,
+}
+
+impl Panel {
+    pub fn new(
+        title: String,
+        peripherals: Peripherals,
+        mic_enabled: bool,
+        has_ir: bool,
+        gba_cart_inserted: bool,
+        has_dual_slot_titles: bool,
+    ) -> Option<Self> {
+        if peripherals.is_empty() {
+            return None;
+        }
+
+        let mut requirements = Vec::new();
+        if peripherals.microphone {
+            requirements.push(Requirement {
+                name: "Microphone",
+                status: if mic_enabled {
+                    Status::Enabled
+                } else {
+                    Status::Disabled
+                },
+                settings_section: Some(ConfigSection::Audio),
+                opens_dual_slot: false,
+            });
+        }
+        if peripherals.rumble {
+            // TODO: Update once rumble is supported
+            requirements.push(Requirement {
+                name: "Rumble Pak",
+                status: Status::Unsupported,
+                settings_section: None,
+                opens_dual_slot: false,
+            });
+        }
+        if peripherals.infrared {
+            requirements.push(Requirement {
+                name: "Infrared",
+                status: if has_ir {
+                    Status::Enabled
+                } else {
+                    Status::Unsupported
+                },
+                settings_section: None,
+                opens_dual_slot: false,
+            });
+        }
+        if peripherals.gba_slot {
+            // TODO: Distinguish accessories (which aren't emulated) from carts once the game
+            //       database records which one is used
+            requirements.push(Requirement {
+                name: "GBA slot cart",
+                status: if gba_cart_inserted {
+                    Status::Enabled
+                } else {
+                    Status::Disabled
+                },
+                settings_section: Some(ConfigSection::Emulation),
+                opens_dual_slot: has_dual_slot_titles,
+            });
+        }
+
+        Some(Panel {
+            title,
+            requirements,
+        })
+    }
+
+    pub fn draw(&mut self, ui: &Ui) -> Option<Action> {
+        let mut action = None;
+        let mut opened = true;
+        ui.window(format!("Peripherals - {}###peripheral_info", self.title))
+            .always_auto_resize(true)
+            .collapsible(false)
+            .opened(&mut opened)
+            .build(|| {
+                ui.text("This game is known to use the following hardware:");
+                if let Some(_table) =
+                    ui.begin_table_with_flags("peripherals", 3, TableFlags::SIZING_FIXED_FIT)
+                {
+                    for requirement in &self.requirements {
+                        let _id = ui.push_id(requirement.name);
+                        ui.table_next_row();
+                        ui.table_next_column();
+                        ui.text(requirement.name);
+
+                        ui.table_next_column();
+                        let (color, status) = match requirement.status {
+                            Status::Enabled => ([0.4, 0.8, 0.4, 1.0], "Enabled"),
+                            Status::Disabled => ([0.9, 0.7, 0.3, 1.0], "Disabled"),
+                            Status::Unsupported => {
+                                (ui.style_color(StyleColor::TextDisabled), "Not emulated")
+                            }
+                        };
+                        ui.text_colored(color, status);
+
+                        ui.table_next_column();
+                        if requirement.opens_dual_slot {
+                            if ui.small_button("Choose game") {
+                                action = Some(Action::OpenDualSlot);
+                            }
+                        } else if let Some(section) = requirement.settings_section {
+                            if requirement.status != Status::Enabled && ui.small_button("Settings")
+                            {
+                                action = Some(Action::OpenSettings(section));
+                            }
+                        }
+                    }
+                }
+                ui.separator();
+                if ui.button("Continue") {
+                    action = Some(Action::Continue);
+                }
+            });
+        if !opened {
+            action = Some(Action::Continue);
+        }
+        action
+    }
+}
@@ -0,0 +1,109 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+// How often the shader file is checked for modifications to be hot-reloaded
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+const MAX_SCALE: u32 = 8;
+
+/// A user-provided WGSL post-processing shader, applied over both screens after the built-in
+/// screen filter.
+///
+/// The vertex stage, the source texture, the samplers and the pass parameters are declared by a
+/// prelude that gets prepended to the shader, which then only needs to define a `fs_main` fragment
+/// entry point. The output resolution can be set to a multiple of the source's by including a
+/// `//! scale: <n>` line.
+pub struct Shader {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+    source: String,
+    scale: u32,
+}
+
+impl Shader {
+    pub fn load(path: PathBuf) -> io::Result<Self> {
+        let modified = fs::metadata(&path)?.modified().ok();
+        let source = fs::read_to_string(&path)?;
+        let scale = Self::parse_scale(&source);
+        Ok(Shader {
+            path,
+            modified,
+            last_check: Instant::now(),
+            source,
+            scale,
+        })
+    }
+
+    fn parse_scale(source: &str) -> u32 {
+        source
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("//!")?.split_once(':'))
+            .find(|(key, _)| key.trim() == "scale")
+            .and_then(|(_, value)| value.trim().parse().ok())
+            .map_or(1, |scale: u32| scale.clamp(1, MAX_SCALE))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn full_source(&self) -> String {
+        format!(
+            "{}\n{}",
+            include_str!("post_process_prelude.wgsl"),
+            self.source
+        )
+    }
+
+    /// Reloads the shader if its file was modified since it was last loaded, returning whether it
+    /// was.
+    ///
+    /// Errors are ignored, keeping the current version, as the file might just be in the middle of
+    /// being saved.
+    pub fn reload_if_modified(&mut self) -> bool {
+        let now = Instant::now();
+        if now - self.last_check < RELOAD_CHECK_INTERVAL {
+            return false;
+        }
+        self.last_check = now;
+
+        let modified = fs::metadata(&self.path)
+            .ok()
+            .and_then(|m| m.modified().ok());
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        match Self::load(self.path.clone()) {
+            Ok(shader) => {
+                *self = shader;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+/// Lists the names of the shaders in the given directory, sorted alphabetically.
+pub fn list_shaders(dir_path: &Path) -> Vec<String> {
+    let mut names = fs::read_dir(dir_path)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "wgsl" || !path.is_file() {
+                return None;
+            }
+            path.file_name()?.to_str().map(str::to_owned)
+        })
+        .collect::<Vec<_>>();
+    names.sort_unstable();
+    names
+}
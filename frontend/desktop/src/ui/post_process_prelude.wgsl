@@ -0,0 +1,33 @@
+// Prepended to every custom post-processing shader, which only has to define a `fs_main` fragment
+// entry point taking a `VertOutput`.
+
+struct VertOutput {
+    @builtin(position) pos: vec4<f32>,
+    // Normalized coordinates into the source texture
+    @location(0) uv: vec2<f32>,
+}
+
+struct Params {
+    // The size of the source texture in pixels; both screens are stacked vertically, with the top
+    // one first
+    source_size: vec2<f32>,
+    // The size of the texture being rendered to in pixels
+    output_size: vec2<f32>,
+}
+
+@group(0) @binding(0) var t_source: texture_2d<f32>;
+@group(0) @binding(1) var s_nearest: sampler;
+@group(0) @binding(2) var s_linear: sampler;
+@group(0) @binding(3) var<uniform> params: Params;
+
+// Covers the whole output texture with a single triangle
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertOutput {
+    let uv = vec2<f32>(f32(vertex_index & 1u), f32(vertex_index >> 1u)) * 2.0;
+    var output: VertOutput;
+    output.pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    output.uv = uv;
+    return output;
+}
@@ -0,0 +1,172 @@
+use super::window::Window;
+use crate::{
+    config::play_stats::{self, PlayStats},
+    rom_info::RomInfo,
+    utils::icon_data_to_rgba8,
+};
+use imgui::{
+    Image, StyleColor, TableColumnFlags, TableColumnSetup, TableFlags, TextureId, TreeNodeFlags, Ui,
+};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+pub(super) enum Action {
+    Close,
+    Launch,
+}
+
+/// A window showing a ROM's metadata (header, icon, titles, save type, known peripherals and
+/// issues) without booting it, along with the game's play time statistics.
+pub(super) struct Inspector {
+    path: PathBuf,
+    title: String,
+    info: RomInfo,
+    play_stats: Option<PlayStats>,
+    icon_texture_id: Option<TextureId>,
+}
+
+impl Inspector {
+    pub fn new(
+        path: PathBuf,
+        info: RomInfo,
+        play_stats: Option<PlayStats>,
+        window: &Window,
+    ) -> Self {
+        let title = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+
+        let icon_texture_id = info.icon_title.as_ref().map(|icon_title| {
+            let icon_tex = window.imgui_gfx.create_owned_texture(
+                Some("ROM inspector icon".into()),
+                imgui_wgpu::TextureDescriptor {
+                    width: 32,
+                    height: 32,
+                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    ..Default::default()
+                },
+                imgui_wgpu::SamplerDescriptor {
+                    mag_filter: wgpu::FilterMode::Nearest,
+                    min_filter: wgpu::FilterMode::Linear,
+                    ..Default::default()
+                },
+            );
+            icon_tex.set_data(
+                window.gfx_device(),
+                window.gfx_queue(),
+                &*icon_data_to_rgba8(
+                    &icon_title.default_icon.palette,
+                    &icon_title.default_icon.pixels,
+                ),
+                Default::default(),
+            );
+            window
+                .imgui_gfx
+                .add_texture(imgui_wgpu::Texture::Owned(icon_tex))
+        });
+
+        Inspector {
+            path,
+            title,
+            info,
+            play_stats,
+            icon_texture_id,
+        }
+    }
+
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn close(self, window: &Window) {
+        if let Some(texture_id) = self.icon_texture_id {
+            window.imgui_gfx.remove_texture(texture_id);
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui) -> Option<Action> {
+        let mut action = None;
+        let mut opened = true;
+        ui.window(format!("ROM info - {}###rom_inspector", self.title))
+            .size([400.0, 500.0], imgui::Condition::FirstUseEver)
+            .opened(&mut opened)
+            .build(|| {
+                macro_rules! table {
+                    ($id: expr, $rows: expr) => {
+                        if let Some(_table) = ui.begin_table_with_flags($id, 2, TableFlags::NO_CLIP)
+                        {
+                            ui.table_setup_column_with(TableColumnSetup {
+                                flags: TableColumnFlags::WIDTH_FIXED,
+                                ..TableColumnSetup::new("Name")
+                            });
+                            ui.table_setup_column("Value");
+                            for (name, value) in $rows {
+                                ui.table_next_row();
+                                ui.table_next_column();
+                                ui.text(format!("{name}:"));
+                                ui.table_next_column();
+                                ui.text_wrapped(value);
+                            }
+                        }
+                    };
+                }
+
+                if let Some(icon_texture_id) = self.icon_texture_id {
+                    let mut cursor_pos = ui.cursor_pos();
+                    cursor_pos[0] += (ui.content_region_avail()[0] - 64.0) * 0.5;
+                    ui.set_cursor_pos(cursor_pos);
+                    Image::new(icon_texture_id, [64.0; 2])
+                        .border_col(ui.style_color(StyleColor::Border))
+                        .build(ui);
+                }
+
+                let titles = self.info.titles();
+                if !titles.is_empty()
+                    && ui.collapsing_header("Titles", TreeNodeFlags::NO_TREE_PUSH_ON_OPEN)
+                {
+                    table!("titles", titles);
+                }
+
+                if ui.collapsing_header(
+                    "General",
+                    TreeNodeFlags::DEFAULT_OPEN | TreeNodeFlags::NO_TREE_PUSH_ON_OPEN,
+                ) {
+                    table!("general", self.info.fields());
+                }
+
+                if let Some(play_stats) = &self.play_stats {
+                    if ui.collapsing_header(
+                        "Play activity",
+                        TreeNodeFlags::DEFAULT_OPEN | TreeNodeFlags::NO_TREE_PUSH_ON_OPEN,
+                    ) {
+                        table!("play_stats", play_stats.fields());
+                        if !play_stats.log.is_empty() {
+                            ui.text_disabled("Recent sessions:");
+                            for session in play_stats.log.iter().rev() {
+                                ui.bullet_text(format!(
+                                    "{}: {}",
+                                    play_stats::format_date_time(session.started_at),
+                                    play_stats::format_duration(Duration::from_secs(
+                                        session.play_time_secs
+                                    )),
+                                ));
+                            }
+                        }
+                    }
+                }
+
+                ui.separator();
+                if ui.button("\u{f04b} Launch") {
+                    action = Some(Action::Launch);
+                }
+            });
+        if !opened {
+            action = Some(Action::Close);
+        }
+        action
+    }
+}
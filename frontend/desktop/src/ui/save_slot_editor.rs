@@ -1,9 +1,41 @@
 use super::EmuState;
 use crate::{
-    config::{saves, Config, Setting},
-    emu::SavePathUpdate,
+    config::{
+        saves::{self, convert},
+        Config, Setting,
+    },
+    emu::{self, SavePathUpdate},
 };
 use imgui::Ui;
+use rfd::FileDialog;
+
+/// Runs `switch` on the current game's save path configuration, and if that changed the current
+/// save slot, makes the emulator load the new slot's save file after writing back the previous
+/// one's. Returns whether the slot changed.
+pub(super) fn switch_slot(
+    config: &mut Config,
+    emu_state: &mut EmuState,
+    switch: impl FnOnce(&mut saves::PathConfig) -> bool,
+) -> bool {
+    let save_dir = &config!(config, &save_dir_path).0;
+    let reset = config!(config, reset_on_save_slot_switch);
+    let mut switched = false;
+    config.save_path_config.inner_mut().update(|path_config| {
+        let Some(path_config) = path_config else {
+            return;
+        };
+        if switch(path_config) {
+            switched = true;
+            emu_state.save_path_update = Some(SavePathUpdate {
+                new: path_config.path(save_dir, &emu_state.title),
+                new_prev: None,
+                reload: true,
+                reset,
+            });
+        }
+    });
+    switched
+}
 
 pub(super) struct Editor {
     editing_i: Option<usize>,
@@ -114,16 +146,8 @@ impl Editor {
                         }
 
                         if let Some(i) = switch {
-                            update_path_config!(|path_config| {
-                                if path_config.switch_slot(i) {
-                                    let new_path = path_config.path(save_dir, game_title).unwrap();
-                                    emu_state.save_path_update = Some(SavePathUpdate {
-                                        new: Some(new_path),
-                                        new_prev: None,
-                                        reload: true,
-                                        reset: config!(config, reset_on_save_slot_switch),
-                                    });
-                                }
+                            switch_slot(config, emu_state, |path_config| {
+                                path_config.switch_slot(i)
                             });
                         } else if let Some(i) = remove {
                             update_path_config!(|path_config| {
@@ -140,6 +164,36 @@ impl Editor {
                         });
                     }
                 }
+
+                ui.separator();
+
+                if ui.menu_item("\u{f56f} Import save file...") {
+                    if let Some(path) = FileDialog::new()
+                        .add_filter("Save file", &["sav", "dsv", "bin"])
+                        .pick_file()
+                    {
+                        if warning!(
+                            yes_no,
+                            "Import save file",
+                            "The current save file will be overwritten with the imported one, and \
+                             the game will be reset. Continue?"
+                        ) {
+                            emu_state.send_message(emu::Message::ImportSave(path));
+                        }
+                    }
+                }
+
+                if ui.menu_item("\u{f56e} Export save file...") {
+                    if let Some(path) = FileDialog::new()
+                        .add_filter("Raw save file", &["sav"])
+                        .add_filter("DeSmuME save file", &["dsv"])
+                        .set_file_name(format!("{}.sav", emu_state.title))
+                        .save_file()
+                    {
+                        let format = convert::Format::from_export_path(&path);
+                        emu_state.send_message(emu::Message::ExportSave(path, format));
+                    }
+                }
             },
         );
 
@@ -1,7 +1,12 @@
 use super::{window::Window, EmuState};
-use crate::{config::Config, emu};
+use crate::{
+    config::Config,
+    emu,
+    notifications::{self, Notification},
+};
 use chrono::DateTime;
 use dust_core::{
+    emu::savestate,
     gpu::{Framebuffer, SCREEN_HEIGHT, SCREEN_WIDTH},
     utils::mem_prelude::*,
 };
@@ -13,15 +18,18 @@ use miniz_oxide::{
 use std::{
     fmt, fs, io, mem,
     path::{Path, PathBuf},
-    slice,
-    time::SystemTime,
+    slice, thread,
+    time::{Duration, Instant, SystemTime},
 };
 
 struct Savestate {
+    header: savestate::Header,
     contents: Vec<u8>,
     save: Option<BoxedByteSlice>,
     framebuffer: Box<Framebuffer>,
+    texture_cache_keys: Vec<u64>,
     texture_id: TextureId,
+    modified: SystemTime,
 }
 
 enum EntryKind {
@@ -39,6 +47,7 @@ struct Entry {
 pub enum SavestateError {
     Io(io::Error),
     Decompression(DecompressError),
+    Container(savestate::Error),
     InvalidData,
 }
 
@@ -54,24 +63,50 @@ impl From<DecompressError> for SavestateError {
     }
 }
 
+impl From<savestate::Error> for SavestateError {
+    fn from(value: savestate::Error) -> Self {
+        SavestateError::Container(value)
+    }
+}
+
 impl fmt::Display for SavestateError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             SavestateError::Io(err) => write!(f, "I/O error: {err}"),
             SavestateError::Decompression(err) => write!(f, "decompression error: {err}"),
+            SavestateError::Container(err) => write!(f, "{err}"),
             SavestateError::InvalidData => f.write_str("invalid data"),
         }
     }
 }
 const SCREEN_SIZE: usize = SCREEN_WIDTH * SCREEN_HEIGHT;
+const FRAMEBUFFER_LEN: usize = 2 * 4 * SCREEN_SIZE;
+
+// The container's extra data holds the framebuffer, optionally followed by the keys of the textures
+// cached by the accelerated 3D renderer (as little-endian `u64`s)
+fn read_texture_cache_keys(data: &[u8]) -> Vec<u64> {
+    data.array_chunks::<8>()
+        .map(|bytes| u64::from_le_bytes(*bytes))
+        .collect()
+}
 
-proc_bitfield::bitfield! {
-    #[derive(Clone, Copy)]
-    struct SavestateInfo(pub u32) {
-        save_len: u32 @ 0..=27,
-        fb_is_le: bool @ 30,
-        has_save: bool @ 31,
+fn read_framebuffer(data: &[u8]) -> Box<Framebuffer> {
+    let mut buffer: Box<Framebuffer> = unsafe { Box::new_zeroed().assume_init() };
+    let [buffer_0, buffer_1] = &mut *buffer;
+    for (pixel, bytes) in buffer_0
+        .iter_mut()
+        .chain(buffer_1)
+        .zip(data.array_chunks::<4>())
+    {
+        *pixel = u32::from_le_bytes(*bytes);
     }
+    buffer
+}
+
+fn boxed_byte_slice(data: &[u8]) -> BoxedByteSlice {
+    let mut buffer = BoxedByteSlice::new_zeroed(data.len());
+    buffer.copy_from_slice(data);
+    buffer
 }
 
 impl Savestate {
@@ -93,9 +128,7 @@ impl Savestate {
         texture.set_data(
             window.gfx_device(),
             window.gfx_queue(),
-            unsafe {
-                slice::from_raw_parts(framebuffer.as_ptr().cast::<u8>(), 2 * 4 * SCREEN_SIZE)
-            },
+            unsafe { slice::from_raw_parts(framebuffer.as_ptr().cast::<u8>(), FRAMEBUFFER_LEN) },
             imgui_wgpu::TextureSetRange::default(),
         );
         window
@@ -104,121 +137,94 @@ impl Savestate {
     }
 
     fn load(path: &Path, window: &Window) -> Result<Self, SavestateError> {
-        let compressed_contents = fs::read(path)?;
-        let mut contents = decompress_to_vec(&compressed_contents)?;
-
-        let info = {
-            let pos = contents
-                .len()
-                .checked_sub(4)
-                .ok_or(SavestateError::InvalidData)?;
-            let value = SavestateInfo(contents[pos..].read_le(0));
-            contents.truncate(pos);
-            value
-        };
-
-        let framebuffer = {
-            let mut buffer: Box<Framebuffer> = unsafe { Box::new_zeroed().assume_init() };
-            let start_pos = contents
-                .len()
-                .checked_sub(2 * 4 * SCREEN_SIZE)
-                .ok_or(SavestateError::InvalidData)?;
-            unsafe {
-                let [buffer_0, buffer_1] = &mut *buffer;
-                let mut src = contents.as_ptr().add(start_pos).cast::<u32>();
-                if info.fb_is_le() == cfg!(target_endian = "little") {
-                    for pixel in buffer_0.iter_mut().chain(buffer_1) {
-                        *pixel = src.read_unaligned();
-                        src = src.add(1);
-                    }
-                } else {
-                    for pixel in buffer_0.iter_mut().chain(buffer_1) {
-                        *pixel = src.read_unaligned().swap_bytes();
-                        src = src.add(1);
-                    }
-                }
+        let compressed_data = fs::read(path)?;
+        let modified = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let data = decompress_to_vec(&compressed_data)?;
+
+        // Savestates from before versioning was introduced use the first versioned format's
+        // layout, which isn't supported anymore
+        if !savestate::is_container(&data) {
+            return Err(savestate::Error::TooOld {
+                format_version: savestate::LEGACY_FORMAT_VERSION,
             }
-            contents.truncate(start_pos);
-            buffer
-        };
-
-        let save = if info.has_save() {
-            let save_len = info.save_len() as usize;
-            let mut buffer = BoxedByteSlice::new_zeroed(save_len);
-            let start_pos = contents
-                .len()
-                .checked_sub(save_len)
-                .ok_or(SavestateError::InvalidData)?;
-            unsafe {
-                buffer
-                    .as_mut_ptr()
-                    .copy_from_nonoverlapping(contents.as_ptr().add(start_pos), save_len);
-            }
-            contents.truncate(start_pos);
-            Some(buffer)
-        } else {
-            None
-        };
-
-        contents.shrink_to_fit();
+            .into());
+        }
+        let container = savestate::read(&data)?;
+        if container.extra.len() < FRAMEBUFFER_LEN
+            || (container.extra.len() - FRAMEBUFFER_LEN) % 8 != 0
+        {
+            return Err(SavestateError::InvalidData);
+        }
+        let (framebuffer, texture_cache_keys_data) = container.extra.split_at(FRAMEBUFFER_LEN);
+        let framebuffer = read_framebuffer(framebuffer);
 
         let texture_id = Self::create_texture(window, &framebuffer);
 
         Ok(Savestate {
-            contents,
-            save,
+            header: container.header,
+            contents: container.state.to_vec(),
+            save: container.save.map(boxed_byte_slice),
             framebuffer,
+            texture_cache_keys: read_texture_cache_keys(texture_cache_keys_data),
             texture_id,
+            modified,
         })
     }
 
+    fn write(
+        path: &Path,
+        header: &savestate::Header,
+        contents: &[u8],
+        save: Option<&[u8]>,
+        framebuffer: &Framebuffer,
+        texture_cache_keys: &[u64],
+    ) -> io::Result<()> {
+        let mut extra = Vec::with_capacity(FRAMEBUFFER_LEN + texture_cache_keys.len() * 8);
+        for pixel in framebuffer[0].iter().chain(&framebuffer[1]) {
+            extra.extend_from_slice(&pixel.to_le_bytes());
+        }
+        for key in texture_cache_keys {
+            extra.extend_from_slice(&key.to_le_bytes());
+        }
+        fs::write(
+            path,
+            compress_to_vec(
+                &savestate::write(header, contents, save, &extra),
+                CompressionLevel::BestSpeed as u8,
+            ),
+        )
+    }
+
+    fn new(savestate: emu::Savestate, window: &Window) -> Self {
+        let texture_id = Self::create_texture(window, &savestate.framebuffer);
+        Savestate {
+            header: savestate.header,
+            contents: savestate.contents,
+            save: savestate.save,
+            framebuffer: savestate.framebuffer,
+            texture_cache_keys: savestate.texture_cache_keys,
+            texture_id,
+            modified: SystemTime::now(),
+        }
+    }
+
     fn create(
         name: &str,
-        mut contents: Vec<u8>,
-        save: Option<BoxedByteSlice>,
-        framebuffer: Box<Framebuffer>,
+        savestate: emu::Savestate,
         savestate_dir: &Path,
         window: &Window,
     ) -> io::Result<Self> {
-        let orig_len = contents.len();
-
-        let mut info = SavestateInfo(0).with_fb_is_le(cfg!(target_endian = "little"));
-        if let Some(save) = &save {
-            contents.extend_from_slice(save);
-            info.set_has_save(true);
-            info.set_save_len(save.len() as u32);
-        }
-
-        contents.reserve(2 * 4 * SCREEN_SIZE);
-
-        unsafe {
-            let prev_len = contents.len();
-            let mut dest = contents.as_mut_ptr().add(prev_len).cast::<u32>();
-            for pixel in framebuffer[0].iter().chain(&framebuffer[1]) {
-                dest.write_unaligned(*pixel);
-                dest = dest.add(1);
-            }
-            contents.set_len(prev_len + 2 * 4 * SCREEN_SIZE);
-        }
-
-        contents.extend_from_slice(&info.0.to_le_bytes());
-
-        fs::write(
-            savestate_dir.join(format!("{name}.state")),
-            compress_to_vec(&contents, CompressionLevel::BestSpeed as u8),
+        Self::write(
+            &savestate_dir.join(format!("{name}.state")),
+            &savestate.header,
+            &savestate.contents,
+            savestate.save.as_deref(),
+            &savestate.framebuffer,
+            &savestate.texture_cache_keys,
         )?;
-
-        contents.truncate(orig_len);
-        contents.shrink_to_fit();
-
-        let texture_id = Self::create_texture(window, &framebuffer);
-
-        Ok(Savestate {
-            contents,
-            save,
-            framebuffer,
-            texture_id,
-        })
+        Ok(Self::new(savestate, window))
     }
 
     fn rename(
@@ -242,17 +248,43 @@ impl Savestate {
 
     fn emu_savestate(&self) -> emu::Savestate {
         emu::Savestate {
+            header: self.header.clone(),
             contents: self.contents.clone(),
             save: self.save.clone(),
             framebuffer: self.framebuffer.clone(),
+            texture_cache_keys: self.texture_cache_keys.clone(),
         }
     }
 }
 
+/// The number of savestate slots reachable through hotkeys.
+pub const SLOTS: u8 = 10;
+
+pub fn slot_name(slot: u8) -> String {
+    format!("Slot {}", slot + 1)
+}
+
+const AUTO_SAVESTATE_PREFIX: &str = "Auto ";
+
+fn auto_savestate_name(i: u8) -> String {
+    format!("{AUTO_SAVESTATE_PREFIX}{}", i + 1)
+}
+
+pub fn is_auto_savestate_name(name: &str) -> bool {
+    name.strip_prefix(AUTO_SAVESTATE_PREFIX)
+        .map_or(false, |index| index.parse::<u8>().is_ok())
+}
+
+// Kept in a game's savestate directory while it's running; finding it when the game is started
+// means the emulator didn't get to stop it properly the last time.
+const RUNNING_MARKER_FILE_NAME: &str = ".running";
+
 pub(super) struct Editor {
     dir_path: Option<PathBuf>,
     entries: Vec<Entry>,
     editing_i: Option<usize>,
+    last_auto_savestate: Instant,
+    crash_recovery_pending: bool,
 }
 
 impl Editor {
@@ -261,6 +293,8 @@ impl Editor {
             dir_path: None,
             entries: Vec::new(),
             editing_i: None,
+            last_auto_savestate: Instant::now(),
+            crash_recovery_pending: false,
         }
     }
 
@@ -270,7 +304,12 @@ impl Editor {
         if new_dir_path == self.dir_path {
             return;
         }
+        if let Some(dir_path) = &self.dir_path {
+            let _ = fs::remove_file(dir_path.join(RUNNING_MARKER_FILE_NAME));
+        }
         self.dir_path = new_dir_path;
+        self.last_auto_savestate = Instant::now();
+        self.crash_recovery_pending = false;
 
         for entry in self.entries.drain(..) {
             if let EntryKind::Savestate(savestate) = entry.kind {
@@ -318,31 +357,239 @@ impl Editor {
                     format_list!(warnings)
                 );
             }
+
+            let marker_path = dir_path.join(RUNNING_MARKER_FILE_NAME);
+            self.crash_recovery_pending = marker_path.exists();
+            let _ = fs::write(marker_path, []);
         }
     }
 
-    pub fn savestate_created(&mut self, name: String, savestate: emu::Savestate, window: &Window) {
-        if let Some(dir_path) = &self.dir_path {
-            if let Ok(savestate) = Savestate::create(
-                &name,
-                savestate.contents,
-                savestate.save,
-                savestate.framebuffer,
-                dir_path,
-                window,
-            ) {
-                if let Some(entry) = self.entries.iter_mut().find(|e| {
-                    matches!(e, Entry {
-                    name: entry_name,
-                    kind: EntryKind::InProgress
-                } if *entry_name == name)
-                }) {
-                    entry.kind = EntryKind::Savestate(savestate);
+    fn auto_savestates(&self) -> impl Iterator<Item = (&str, &Savestate)> {
+        self.entries.iter().filter_map(|entry| match &entry.kind {
+            EntryKind::Savestate(savestate) if is_auto_savestate_name(&entry.name) => {
+                Some((entry.name.as_str(), savestate))
+            }
+            _ => None,
+        })
+    }
+
+    /// If the current game wasn't stopped properly the last time it was running (i.e. because the
+    /// emulator crashed), offers to restore its most recent auto-savestate.
+    pub fn offer_crash_recovery(&mut self, emu_state: &Option<EmuState>) {
+        if !mem::take(&mut self.crash_recovery_pending) {
+            return;
+        }
+        let Some((name, savestate)) = self
+            .auto_savestates()
+            .max_by_key(|(_, savestate)| savestate.modified)
+        else {
+            return;
+        };
+        let created_at = DateTime::<chrono::Local>::from(savestate.modified)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        if warning!(
+            yes_no,
+            "Crash recovery",
+            "The emulator wasn't closed properly the last time this game was running. Restore \
+             the most recent auto-savestate (\"{name}\", created at {created_at})?"
+        ) {
+            emu_state
+                .as_ref()
+                .unwrap()
+                .send_message(emu::Message::ApplySavestate(savestate.emu_savestate()));
+        }
+    }
+
+    /// Writes a savestate created by the emulator to disk, returning whether it succeeded.
+    pub fn savestate_created(
+        &mut self,
+        name: String,
+        savestate: emu::Savestate,
+        window: &Window,
+    ) -> bool {
+        let Some(dir_path) = &self.dir_path else {
+            return false;
+        };
+        if let Ok(savestate) = Savestate::create(&name, savestate, dir_path, window) {
+            if let Some(entry) = self.entries.iter_mut().find(|e| {
+                matches!(e, Entry {
+                name: entry_name,
+                kind: EntryKind::InProgress
+            } if *entry_name == name)
+            }) {
+                entry.kind = EntryKind::Savestate(savestate);
+            }
+            true
+        } else {
+            self.savestate_failed(name);
+            false
+        }
+    }
+
+    /// Adds an auto-savestate created by the emulator, writing it to disk in the background so that
+    /// compressing it doesn't stall the UI while playing.
+    pub fn auto_savestate_created(
+        &mut self,
+        name: String,
+        savestate: emu::Savestate,
+        window: &Window,
+        notifications: &notifications::Sender,
+    ) {
+        let Some(dir_path) = &self.dir_path else {
+            return;
+        };
+        let path = dir_path.join(format!("{name}.state"));
+        let savestate = Savestate::new(savestate, window);
+        let data = savestate.emu_savestate();
+
+        let notifications = notifications.clone();
+        let failed_name = name.clone();
+        thread::Builder::new()
+            .name("auto-savestate".to_owned())
+            .spawn(move || {
+                // Write to a temporary file first, so that a crash while writing can't leave a
+                // truncated auto-savestate behind
+                let tmp_path = path.with_extension("state.tmp");
+                let result = Savestate::write(
+                    &tmp_path,
+                    &data.header,
+                    &data.contents,
+                    data.save.as_deref(),
+                    &data.framebuffer,
+                    &data.texture_cache_keys,
+                )
+                .and_then(|_| fs::rename(&tmp_path, &path));
+                if let Err(err) = result {
+                    notifications.post(Notification::new(
+                        notifications::Kind::SavestateFailed,
+                        notifications::Level::Error,
+                        format!("Couldn't write auto-savestate \"{failed_name}\": {err}"),
+                    ));
                 }
-            } else {
-                self.savestate_failed(name);
+            })
+            .expect("couldn't spawn auto-savestate thread");
+
+        if let Some(entry) = self.entries.iter_mut().find(|e| {
+            matches!(e, Entry {
+                name: entry_name,
+                kind: EntryKind::InProgress
+            } if *entry_name == name)
+        }) {
+            entry.kind = EntryKind::Savestate(savestate);
+        } else {
+            window.imgui_gfx.remove_texture(savestate.texture_id);
+        }
+    }
+
+    fn create_savestate(&mut self, name: String, config: &Config, emu_state: &Option<EmuState>) {
+        emu_state
+            .as_ref()
+            .unwrap()
+            .send_message(emu::Message::CreateSavestate {
+                name: name.clone(),
+                include_save: config!(config, include_save_in_savestates),
+            });
+        self.entries.push(Entry {
+            name,
+            kind: EntryKind::InProgress,
+        });
+    }
+
+    fn slot_savestate(&self, slot: u8) -> Option<&Savestate> {
+        let name = slot_name(slot);
+        self.entries.iter().find_map(|entry| match &entry.kind {
+            EntryKind::Savestate(savestate) if entry.name == name => Some(savestate),
+            _ => None,
+        })
+    }
+
+    /// Creates a savestate in the given slot, replacing the one it contained, if any; returns
+    /// whether savestates are available for the current game.
+    pub fn create_slot(
+        &mut self,
+        slot: u8,
+        window: &Window,
+        config: &Config,
+        emu_state: &Option<EmuState>,
+    ) -> bool {
+        if self.dir_path.is_none() {
+            return false;
+        }
+        self.replace_savestate(slot_name(slot), window, config, emu_state);
+        true
+    }
+
+    fn replace_savestate(
+        &mut self,
+        name: String,
+        window: &Window,
+        config: &Config,
+        emu_state: &Option<EmuState>,
+    ) {
+        if let Some(i) = self.entries.iter().position(|entry| entry.name == name) {
+            if let EntryKind::Savestate(savestate) = self.entries.remove(i).kind {
+                window.imgui_gfx.remove_texture(savestate.texture_id);
             }
+            self.editing_i = None;
         }
+        self.create_savestate(name, config, emu_state);
+    }
+
+    /// Creates an auto-savestate if they're enabled and the configured interval has passed since
+    /// the last one, replacing the oldest one once the configured count has been reached.
+    pub fn update_auto_savestate(
+        &mut self,
+        window: &Window,
+        config: &Config,
+        emu_state: &Option<EmuState>,
+    ) {
+        if self.dir_path.is_none()
+            || !config!(config, auto_savestate_enabled)
+            || !emu_state.as_ref().map_or(false, |emu| emu.playing)
+        {
+            return;
+        }
+        let interval =
+            Duration::from_secs(config!(config, auto_savestate_interval_mins) as u64 * 60);
+        if self.last_auto_savestate.elapsed() < interval
+            || self.entries.iter().any(|entry| {
+                matches!(entry.kind, EntryKind::InProgress) && is_auto_savestate_name(&entry.name)
+            })
+        {
+            return;
+        }
+        self.last_auto_savestate = Instant::now();
+
+        // Empty slots sort before used ones, so they're filled first
+        let slot = (0..config!(config, auto_savestate_count).max(1))
+            .min_by_key(|&i| {
+                let name = auto_savestate_name(i);
+                self.auto_savestates()
+                    .find(|(entry_name, _)| *entry_name == name)
+                    .map(|(_, savestate)| savestate.modified)
+            })
+            .unwrap();
+        self.replace_savestate(auto_savestate_name(slot), window, config, emu_state);
+    }
+
+    /// Returns the texture containing the screens captured when the savestate in the given slot
+    /// was created, if there is one.
+    pub fn slot_preview(&self, slot: u8) -> Option<TextureId> {
+        self.slot_savestate(slot)
+            .map(|savestate| savestate.texture_id)
+    }
+
+    /// Applies the savestate in the given slot, returning whether there was one.
+    pub fn load_slot(&self, slot: u8, emu_state: &Option<EmuState>) -> bool {
+        let Some(savestate) = self.slot_savestate(slot) else {
+            return false;
+        };
+        emu_state
+            .as_ref()
+            .unwrap()
+            .send_message(emu::Message::ApplySavestate(savestate.emu_savestate()));
+        true
     }
 
     pub fn savestate_failed(&mut self, name: String) {
@@ -672,16 +919,7 @@ impl Editor {
                                 let name = DateTime::<chrono::Local>::from(SystemTime::now())
                                     .format("%Y-%m-%d %H:%M:%S%.3f")
                                     .to_string();
-                                emu_state.as_ref().unwrap().send_message(
-                                    emu::Message::CreateSavestate {
-                                        name: name.clone(),
-                                        include_save: config!(config, include_save_in_savestates),
-                                    },
-                                );
-                                self.entries.push(Entry {
-                                    name,
-                                    kind: EntryKind::InProgress,
-                                })
+                                self.create_savestate(name, config, emu_state);
                             }
                         }
 
@@ -0,0 +1,426 @@
+use super::post_process;
+use crate::config::{LcdColorProfile, ScreenFilter};
+use dust_core::gpu::{Framebuffer, SCREEN_HEIGHT, SCREEN_WIDTH};
+use std::{iter, mem, slice, sync::Arc};
+use wgpu::util::DeviceExt;
+
+impl ScreenFilter {
+    /// The fragment shader entry point and output scale of the filter, if it's applied through a
+    /// separate render pass rather than just the sampler used to draw the screens.
+    fn pass_params(self) -> Option<(&'static str, u32)> {
+        match self {
+            ScreenFilter::Nearest | ScreenFilter::Bilinear => None,
+            ScreenFilter::Scale2x => Some(("fs_scale2x", 2)),
+            ScreenFilter::Scanlines => Some(("fs_scanlines", 3)),
+            ScreenFilter::LcdGrid => Some(("fs_lcd_grid", 3)),
+        }
+    }
+
+    pub fn mag_filter(self) -> wgpu::FilterMode {
+        match self {
+            ScreenFilter::Bilinear => wgpu::FilterMode::Linear,
+            _ => wgpu::FilterMode::Nearest,
+        }
+    }
+}
+
+impl LcdColorProfile {
+    fn entry_point(self) -> Option<&'static str> {
+        match self {
+            LcdColorProfile::None => None,
+            LcdColorProfile::Ds => Some("fs_color_ds"),
+            LcdColorProfile::DsLite => Some("fs_color_ds_lite"),
+        }
+    }
+}
+
+struct Stage {
+    pipeline: wgpu::RenderPipeline,
+    scale: u32,
+    params: wgpu::Buffer,
+    bg: wgpu::BindGroup,
+    output: Arc<wgpu::Texture>,
+}
+
+/// A chain of render passes applying LCD color correction, a shader-based filter and/or a custom
+/// post-processing shader to the framebuffer, producing a texture (possibly at a higher resolution) that is then drawn in
+/// place of the framebuffer itself.
+pub struct Pass {
+    device: Arc<wgpu::Device>,
+    bg_layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    nearest_sampler: wgpu::Sampler,
+    linear_sampler: wgpu::Sampler,
+    stages: Vec<Stage>,
+    owned_input: wgpu::Texture,
+    input: Option<Arc<wgpu::Texture>>,
+    dirty: bool,
+}
+
+impl Pass {
+    /// Creates the passes needed to apply the given color profile, filter and post-processing
+    /// shader, if any; returns an error message if the post-processing shader couldn't be
+    /// compiled.
+    pub fn new(
+        device: &Arc<wgpu::Device>,
+        color_profile: LcdColorProfile,
+        filter: ScreenFilter,
+        post_process_shader: Option<&post_process::Shader>,
+    ) -> Result<Option<Self>, String> {
+        let color_entry_point = color_profile.entry_point();
+        let filter_params = filter.pass_params();
+        if color_entry_point.is_none() && filter_params.is_none() && post_process_shader.is_none() {
+            return Ok(None);
+        }
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let sampler_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        };
+        let bg_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Screen filter"),
+            entries: &[
+                texture_entry(0),
+                sampler_entry(1),
+                sampler_entry(2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Screen filter"),
+            bind_group_layouts: &[&bg_layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_sampler = |filter| {
+            device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("Screen filter"),
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                mag_filter: filter,
+                min_filter: filter,
+                ..Default::default()
+            })
+        };
+        let nearest_sampler = create_sampler(wgpu::FilterMode::Nearest);
+        let linear_sampler = create_sampler(wgpu::FilterMode::Linear);
+
+        let owned_input = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Screen filter input"),
+            size: wgpu::Extent3d {
+                width: SCREEN_WIDTH as u32,
+                height: SCREEN_HEIGHT as u32 * 2,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let mut result = Pass {
+            device: Arc::clone(device),
+            bg_layout,
+            pipeline_layout,
+            nearest_sampler,
+            linear_sampler,
+            stages: Vec::new(),
+            owned_input,
+            input: None,
+            dirty: true,
+        };
+
+        if color_entry_point.is_some() || filter_params.is_some() {
+            let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Screen filter"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("screen_filter.wgsl").into()),
+            });
+            // Colors are corrected first, so that filters that compare neighboring pixels still
+            // see exactly equal colors
+            if let Some(entry_point) = color_entry_point {
+                let pipeline = result.create_pipeline(&shader_module, entry_point);
+                result.push_stage(pipeline, 1);
+            }
+            if let Some((entry_point, scale)) = filter_params {
+                let pipeline = result.create_pipeline(&shader_module, entry_point);
+                result.push_stage(pipeline, scale);
+            }
+        }
+
+        if let Some(shader) = post_process_shader {
+            // Errors in user-provided shaders are reported through an error scope instead of the
+            // device's uncaptured error handler, which would panic
+            device.push_error_scope(wgpu::ErrorFilter::Validation);
+            let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Post-processing shader"),
+                source: wgpu::ShaderSource::Wgsl(shader.full_source().into()),
+            });
+            let pipeline = result.create_pipeline(&shader_module, "fs_main");
+            if let Some(err) = pollster::block_on(device.pop_error_scope()) {
+                return Err(err.to_string());
+            }
+            result.push_stage(pipeline, shader.scale());
+        }
+
+        Ok(Some(result))
+    }
+
+    fn create_pipeline(
+        &self,
+        shader_module: &wgpu::ShaderModule,
+        entry_point: &str,
+    ) -> wgpu::RenderPipeline {
+        self.device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Screen filter"),
+                layout: Some(&self.pipeline_layout),
+
+                vertex: wgpu::VertexState {
+                    module: shader_module,
+                    entry_point: Some("vs_main"),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    unclipped_depth: false,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                },
+
+                depth_stencil: None,
+
+                multisample: wgpu::MultisampleState::default(),
+
+                fragment: Some(wgpu::FragmentState {
+                    module: shader_module,
+                    entry_point: Some(entry_point),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: wgpu::TextureFormat::Rgba8Unorm,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+
+                multiview: None,
+                cache: None,
+            })
+    }
+
+    fn stage_input(&self, i: usize) -> &wgpu::Texture {
+        match i.checked_sub(1) {
+            Some(prev_i) => &self.stages[prev_i].output,
+            None => self.input.as_deref().unwrap_or(&self.owned_input),
+        }
+    }
+
+    fn push_stage(&mut self, pipeline: wgpu::RenderPipeline, scale: u32) {
+        let input = self.stage_input(self.stages.len());
+        let output = Self::create_output(&self.device, input.size(), scale);
+        let params = self.create_params(input.size(), output.size());
+        let bg = self.create_bg(input, &params);
+        self.stages.push(Stage {
+            pipeline,
+            scale,
+            params,
+            bg,
+            output,
+        });
+    }
+
+    fn create_output(
+        device: &wgpu::Device,
+        input_size: wgpu::Extent3d,
+        scale: u32,
+    ) -> Arc<wgpu::Texture> {
+        Arc::new(device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Screen filter output"),
+            size: wgpu::Extent3d {
+                width: input_size.width * scale,
+                height: input_size.height * scale,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        }))
+    }
+
+    fn create_params(
+        &self,
+        input_size: wgpu::Extent3d,
+        output_size: wgpu::Extent3d,
+    ) -> wgpu::Buffer {
+        let params = [
+            input_size.width as f32,
+            input_size.height as f32,
+            output_size.width as f32,
+            output_size.height as f32,
+        ];
+        self.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Screen filter parameters"),
+                contents: unsafe {
+                    slice::from_raw_parts(params.as_ptr() as *const u8, mem::size_of_val(&params))
+                },
+                usage: wgpu::BufferUsages::UNIFORM,
+            })
+    }
+
+    fn create_bg(&self, input: &wgpu::Texture, params: &wgpu::Buffer) -> wgpu::BindGroup {
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Screen filter"),
+            layout: &self.bg_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &input.create_view(&Default::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.nearest_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.linear_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: params.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    /// Sets the texture containing both screens to filter, or, if `None`, switches to an internal
+    /// texture to be updated using [`Pass::set_framebuffer_data`]; returns whether the output
+    /// texture was recreated as a result.
+    pub fn set_input(&mut self, texture: Option<&Arc<wgpu::Texture>>) -> bool {
+        let unchanged = match (&self.input, texture) {
+            (Some(prev), Some(new)) => Arc::ptr_eq(prev, new),
+            (None, None) => true,
+            _ => false,
+        };
+        if unchanged {
+            return false;
+        }
+        self.input = texture.cloned();
+        self.dirty = true;
+
+        let mut output_recreated = false;
+        for i in 0..self.stages.len() {
+            let input_size = self.stage_input(i).size();
+            let stage = &self.stages[i];
+            let output_size = wgpu::Extent3d {
+                width: input_size.width * stage.scale,
+                height: input_size.height * stage.scale,
+                depth_or_array_layers: 1,
+            };
+            output_recreated = stage.output.size() != output_size;
+            if output_recreated {
+                let output = Self::create_output(&self.device, input_size, stage.scale);
+                let params = self.create_params(input_size, output_size);
+                let stage = &mut self.stages[i];
+                stage.output = output;
+                stage.params = params;
+            }
+            let bg = self.create_bg(self.stage_input(i), &self.stages[i].params);
+            self.stages[i].bg = bg;
+        }
+        output_recreated
+    }
+
+    pub fn set_framebuffer_data(&mut self, queue: &wgpu::Queue, data: &Framebuffer) {
+        if self.input.is_some() {
+            return;
+        }
+        queue.write_texture(
+            self.owned_input.as_image_copy(),
+            unsafe {
+                slice::from_raw_parts(
+                    data.as_ptr() as *const u8,
+                    2 * 4 * SCREEN_WIDTH * SCREEN_HEIGHT,
+                )
+            },
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * SCREEN_WIDTH as u32),
+                rows_per_image: None,
+            },
+            self.owned_input.size(),
+        );
+        self.dirty = true;
+    }
+
+    pub fn output(&self) -> &Arc<wgpu::Texture> {
+        &self.stages.last().unwrap().output
+    }
+
+    pub fn run(&mut self, queue: &wgpu::Queue) {
+        // External input textures can be updated at any point, so they're always filtered again
+        if !self.dirty && self.input.is_none() {
+            return;
+        }
+        self.dirty = false;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Screen filter"),
+            });
+        for stage in &self.stages {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Screen filter"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &stage.output.create_view(&Default::default()),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&stage.pipeline);
+            render_pass.set_bind_group(0, &stage.bg, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        queue.submit(iter::once(encoder.finish()));
+    }
+}
@@ -0,0 +1,137 @@
+struct VertOutput {
+    @builtin(position) pos: vec4<f32>,
+}
+
+@group(0) @binding(0) var t_source: texture_2d<f32>;
+
+// Covers the whole output texture with a single triangle
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertOutput {
+    let uv = vec2<f32>(f32(vertex_index & 1u), f32(vertex_index >> 1u)) * 2.0;
+    var output: VertOutput;
+    output.pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return output;
+}
+
+// Loads a texel, clamping the coordinates to the screen it belongs to so that the two screens
+// (stacked vertically in the source texture) don't bleed into each other
+fn load(coords: vec2<i32>, top: i32) -> vec4<f32> {
+    let size = vec2<i32>(textureDimensions(t_source));
+    let screen_height = size.y / 2;
+    return textureLoad(
+        t_source,
+        vec2<i32>(
+            clamp(coords.x, 0, size.x - 1),
+            clamp(coords.y, top, top + screen_height - 1),
+        ),
+        0,
+    );
+}
+
+fn screen_top(coords: vec2<i32>) -> i32 {
+    let screen_height = i32(textureDimensions(t_source).y) / 2;
+    return select(0, screen_height, coords.y >= screen_height);
+}
+
+fn eq(a: vec4<f32>, b: vec4<f32>) -> bool {
+    return all(a == b);
+}
+
+// Scale2x/EPX: each source pixel is expanded to 2x2, rounding off diagonal edges
+@fragment
+fn fs_scale2x(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let out_coords = vec2<i32>(pos.xy);
+    let coords = out_coords / 2;
+    let sub = out_coords % 2;
+    let top = screen_top(coords);
+
+    let p = load(coords, top);
+    let a = load(coords + vec2<i32>(0, -1), top);
+    let b = load(coords + vec2<i32>(1, 0), top);
+    let c = load(coords + vec2<i32>(-1, 0), top);
+    let d = load(coords + vec2<i32>(0, 1), top);
+
+    if eq(a, d) || eq(b, c) {
+        return vec4<f32>(p.rgb, 1.0);
+    }
+    if sub.y == 0 {
+        if sub.x == 0 {
+            return vec4<f32>(select(p, a, eq(c, a)).rgb, 1.0);
+        }
+        return vec4<f32>(select(p, b, eq(a, b)).rgb, 1.0);
+    }
+    if sub.x == 0 {
+        return vec4<f32>(select(p, c, eq(d, c)).rgb, 1.0);
+    }
+    return vec4<f32>(select(p, d, eq(b, d)).rgb, 1.0);
+}
+
+// Each source pixel is expanded to 3x3, darkening the last row to imitate a CRT's scanlines
+@fragment
+fn fs_scanlines(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let out_coords = vec2<i32>(pos.xy);
+    let coords = out_coords / 3;
+    let color = load(coords, screen_top(coords));
+    let brightness = select(1.0, 0.5, out_coords.y % 3 == 2);
+    return vec4<f32>(color.rgb * brightness, 1.0);
+}
+
+// Each source pixel is expanded to 3x3, darkening its right and bottom edges to imitate the gaps
+// between an LCD's pixels
+@fragment
+fn fs_lcd_grid(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let out_coords = vec2<i32>(pos.xy);
+    let coords = out_coords / 3;
+    let color = load(coords, screen_top(coords));
+    let sub = out_coords % 3;
+    let brightness = select(1.0, 0.7, sub.x == 2 || sub.y == 2);
+    return vec4<f32>(color.rgb * brightness, 1.0);
+}
+
+// Approximates the color response of an LCD, given the gamma to linearize the source colors with,
+// the matrix mapping them to the colors actually displayed (each column being the contribution of
+// one source channel) and the screen's overall luminance
+fn correct_color(
+    color: vec3<f32>,
+    gamma: f32,
+    matrix: mat3x3<f32>,
+    luminance: f32,
+) -> vec4<f32> {
+    let linear = pow(color, vec3<f32>(gamma));
+    let corrected = clamp(matrix * linear * luminance, vec3<f32>(0.0), vec3<f32>(1.0));
+    return vec4<f32>(pow(corrected, vec3<f32>(1.0 / 2.2)), 1.0);
+}
+
+// The original DS's dim, cool-tinted front-lit screens
+@fragment
+fn fs_color_ds(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(t_source, vec2<i32>(pos.xy), 0).rgb;
+    return correct_color(
+        color,
+        2.4,
+        mat3x3<f32>(
+            vec3<f32>(0.835, 0.16, 0.0),
+            vec3<f32>(0.225, 0.725, 0.105),
+            vec3<f32>(-0.06, 0.115, 0.84),
+        ),
+        0.905,
+    );
+}
+
+// The DS Lite's brighter backlit screens, which are closer to sRGB but still slightly desaturated
+@fragment
+fn fs_color_ds_lite(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(t_source, vec2<i32>(pos.xy), 0).rgb;
+    return correct_color(
+        color,
+        2.2,
+        mat3x3<f32>(
+            vec3<f32>(0.9, 0.1, 0.0),
+            vec3<f32>(0.12, 0.82, 0.06),
+            vec3<f32>(-0.02, 0.08, 0.94),
+        ),
+        0.96,
+    );
+}
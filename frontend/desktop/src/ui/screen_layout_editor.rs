@@ -0,0 +1,318 @@
+use super::utils::{add2, mul2s, sub2};
+use crate::config::{Config, CustomScreenLayout, ScreenRect, Setting};
+use dust_core::gpu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use imgui::{StyleColor, TextureId, Ui};
+
+const MIN_SCREEN_SIZE: f32 = 16.0;
+const MAX_LAYOUT_SIZE: f32 = 4096.0;
+const SCREEN_NAMES: [&str; 2] = ["Top screen", "Bottom screen"];
+
+fn unique_name(layouts: &[CustomScreenLayout], base: &str) -> String {
+    let mut name = base.to_owned();
+    let mut i = 2;
+    while layouts.iter().any(|layout| layout.name == name) {
+        name = format!("{base} {i}");
+        i += 1;
+    }
+    name
+}
+
+fn clamp_rect(rect: &mut ScreenRect, layout_size: [f32; 2]) {
+    for i in 0..2 {
+        rect.size[i] = rect.size[i].clamp(MIN_SCREEN_SIZE, layout_size[i]);
+        rect.pos[i] = rect.pos[i].clamp(0.0, layout_size[i] - rect.size[i]);
+    }
+}
+
+/// A window to create custom screen layouts and edit them by dragging and resizing the screens
+/// over a preview of the layout's canvas.
+pub(super) struct Editor {
+    selected_i: usize,
+    name_buffer: Option<String>,
+}
+
+impl Editor {
+    pub fn new(config: &Config) -> Self {
+        let selected_i = config
+            .custom_screen_layout()
+            .and_then(|active| {
+                config!(config, &screen_layouts)
+                    .iter()
+                    .position(|layout| layout.name == active.name)
+            })
+            .unwrap_or(0);
+        Editor {
+            selected_i,
+            name_buffer: None,
+        }
+    }
+
+    fn draw_layout_list(&mut self, ui: &Ui, config: &mut Config) {
+        let layouts = config!(config, &screen_layouts);
+        let active_name = config!(config, &screen_layout).clone();
+
+        let preview = layouts
+            .get(self.selected_i)
+            .map_or("", |layout| layout.name.as_str());
+        if let Some(_combo) = ui.begin_combo("Layout", preview) {
+            for (i, layout) in layouts.iter().enumerate() {
+                let _id = ui.push_id_usize(i);
+                let label = if layout.name == active_name {
+                    format!("{} (active)", layout.name)
+                } else {
+                    layout.name.clone()
+                };
+                if ui
+                    .selectable_config(&label)
+                    .selected(i == self.selected_i)
+                    .build()
+                {
+                    self.selected_i = i;
+                    self.name_buffer = None;
+                }
+            }
+        }
+
+        let mut new_layout = None;
+        if ui.button("New") {
+            new_layout = Some(CustomScreenLayout {
+                name: unique_name(layouts, "Layout"),
+                ..Default::default()
+            });
+        }
+        ui.same_line();
+        if ui.button("Duplicate") {
+            if let Some(layout) = layouts.get(self.selected_i) {
+                new_layout = Some(CustomScreenLayout {
+                    name: unique_name(layouts, &format!("{} (copy)", layout.name)),
+                    ..layout.clone()
+                });
+            }
+        }
+        ui.same_line();
+        let delete = ui.button("Delete") && self.selected_i < layouts.len();
+
+        if let Some(layout) = new_layout {
+            config.screen_layouts.inner_mut().update(|layouts| {
+                self.selected_i = layouts.len();
+                layouts.push(layout);
+            });
+            self.name_buffer = None;
+        } else if delete {
+            let mut removed_name = String::new();
+            config.screen_layouts.inner_mut().update(|layouts| {
+                removed_name = layouts.remove(self.selected_i).name;
+                self.selected_i = self.selected_i.min(layouts.len().saturating_sub(1));
+            });
+            if removed_name == active_name {
+                set_config!(config, screen_layout, String::new());
+            }
+            self.name_buffer = None;
+        }
+    }
+
+    fn draw_selected_layout(&mut self, ui: &Ui, config: &mut Config, fb_texture_id: TextureId) {
+        let layouts = config!(config, &screen_layouts);
+        let Some(mut layout) = layouts.get(self.selected_i).cloned() else {
+            ui.text("No custom layouts have been created yet.");
+            return;
+        };
+        let orig_layout = layout.clone();
+        let active_name = config!(config, &screen_layout).clone();
+
+        let name_buffer = self.name_buffer.get_or_insert_with(|| layout.name.clone());
+        if ui
+            .input_text("Name", name_buffer)
+            .enter_returns_true(true)
+            .build()
+        {
+            if !name_buffer.is_empty()
+                && !layouts
+                    .iter()
+                    .enumerate()
+                    .any(|(i, other)| i != self.selected_i && other.name == *name_buffer)
+            {
+                if active_name == layout.name {
+                    set_config!(config, screen_layout, name_buffer.clone());
+                }
+                layout.name = name_buffer.clone();
+            } else {
+                *name_buffer = layout.name.clone();
+            }
+        }
+
+        if active_name == layout.name {
+            ui.text_disabled("Currently in use");
+        } else if ui.button("Use this layout") {
+            set_config!(config, screen_layout, layout.name.clone());
+        }
+
+        ui.separator();
+
+        if ui.input_float2("Canvas size", &mut layout.size).build() {
+            for coord in &mut layout.size {
+                *coord = coord.clamp(MIN_SCREEN_SIZE, MAX_LAYOUT_SIZE);
+            }
+        }
+        ui.checkbox(
+            "Draw top screen over bottom screen",
+            &mut layout.top_screen_above,
+        );
+
+        for (i, name) in SCREEN_NAMES.into_iter().enumerate() {
+            let _id = ui.push_id_usize(i);
+            let mut shown = layout.screens[i].is_some();
+            if ui.checkbox(name, &mut shown) {
+                layout.screens[i] = shown.then(|| ScreenRect {
+                    pos: [0.0; 2],
+                    size: [SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32],
+                });
+            }
+            if let Some(rect) = &mut layout.screens[i] {
+                let indent = ui.current_font_size();
+                ui.indent_by(indent);
+                ui.input_float2("Position", &mut rect.pos).build();
+                ui.input_float2("Size", &mut rect.size).build();
+                if ui.button("Restore aspect ratio") {
+                    rect.size[1] = rect.size[0] * SCREEN_HEIGHT as f32 / SCREEN_WIDTH as f32;
+                }
+                ui.unindent_by(indent);
+            }
+        }
+
+        ui.separator();
+        ui.text_disabled(
+            "Drag screens to move them, or their bottom right corners to resize them (hold Shift to \
+             keep their aspect ratio).",
+        );
+        self.draw_canvas(ui, &mut layout, fb_texture_id);
+
+        for rect in layout.screens.iter_mut().flatten() {
+            clamp_rect(rect, layout.size);
+        }
+
+        if layout != orig_layout {
+            config.screen_layouts.inner_mut().update(|layouts| {
+                layouts[self.selected_i] = layout;
+            });
+        }
+    }
+
+    fn draw_canvas(&self, ui: &Ui, layout: &mut CustomScreenLayout, fb_texture_id: TextureId) {
+        let avail = ui.content_region_avail();
+        let scale = (avail[0] / layout.size[0]).min(avail[1] / layout.size[1]);
+        if !scale.is_finite() || scale <= 0.0 {
+            return;
+        }
+        let canvas_size = mul2s(layout.size, scale);
+        let canvas_pos = add2(
+            ui.cursor_screen_pos(),
+            [((avail[0] - canvas_size[0]) * 0.5).max(0.0), 0.0],
+        );
+
+        let draw_list = ui.get_window_draw_list();
+        draw_list
+            .add_rect(
+                canvas_pos,
+                add2(canvas_pos, canvas_size),
+                ui.style_color(StyleColor::FrameBg),
+            )
+            .filled(true)
+            .build();
+
+        let draw_order = if layout.top_screen_above {
+            [1, 0]
+        } else {
+            [0, 1]
+        };
+        let handle_size = ui.current_font_size() * 0.75;
+
+        for i in draw_order {
+            let Some(rect) = &layout.screens[i] else {
+                continue;
+            };
+            let min = add2(canvas_pos, mul2s(rect.pos, scale));
+            let max = add2(min, mul2s(rect.size, scale));
+            let tex_v_start = i as f32 * 0.5;
+            draw_list
+                .add_image(fb_texture_id, min, max)
+                .uv_min([0.0, tex_v_start])
+                .uv_max([1.0, tex_v_start + 0.5])
+                .build();
+            draw_list
+                .add_rect(min, max, ui.style_color(StyleColor::Border))
+                .thickness(2.0)
+                .build();
+            draw_list.add_text(
+                add2(min, [4.0; 2]),
+                ui.style_color(StyleColor::Text),
+                SCREEN_NAMES[i],
+            );
+            draw_list
+                .add_rect(
+                    sub2(max, [handle_size; 2]),
+                    max,
+                    ui.style_color(StyleColor::ResizeGrip),
+                )
+                .filled(true)
+                .build();
+        }
+
+        // Items submitted first take precedence when hovering overlapping ones, so the screens need
+        // to be processed from the topmost one
+        let mouse_delta = mul2s(ui.io().mouse_delta, 1.0 / scale);
+        let keep_aspect_ratio = ui.io().key_shift;
+        for i in draw_order.into_iter().rev() {
+            let Some(rect) = &mut layout.screens[i] else {
+                continue;
+            };
+            let _id = ui.push_id_usize(i);
+            let min = add2(canvas_pos, mul2s(rect.pos, scale));
+            let size = mul2s(rect.size, scale);
+
+            ui.set_cursor_screen_pos(sub2(add2(min, size), [handle_size; 2]));
+            ui.invisible_button("##resize", [handle_size; 2]);
+            if ui.is_item_active() {
+                rect.size = add2(rect.size, mouse_delta);
+                if keep_aspect_ratio {
+                    rect.size[1] = rect.size[0] * SCREEN_HEIGHT as f32 / SCREEN_WIDTH as f32;
+                }
+            }
+            if ui.is_item_hovered() || ui.is_item_active() {
+                ui.set_mouse_cursor(Some(imgui::MouseCursor::ResizeNWSE));
+            }
+
+            ui.set_cursor_screen_pos(min);
+            ui.invisible_button("##move", size.map(|coord| coord.max(1.0)));
+            if ui.is_item_active() {
+                rect.pos = add2(rect.pos, mouse_delta);
+            }
+            if ui.is_item_hovered() || ui.is_item_active() {
+                ui.set_mouse_cursor(Some(imgui::MouseCursor::ResizeAll));
+            }
+        }
+
+        ui.set_cursor_screen_pos([canvas_pos[0], canvas_pos[1] + canvas_size[1]]);
+        ui.dummy([canvas_size[0], 0.0]);
+    }
+
+    pub fn draw(
+        &mut self,
+        ui: &Ui,
+        config: &mut Config,
+        fb_texture_id: TextureId,
+        opened: &mut bool,
+    ) {
+        ui.window("Screen layout editor")
+            .size(
+                mul2s(ui.io().display_size, 0.5),
+                imgui::Condition::FirstUseEver,
+            )
+            .opened(opened)
+            .build(|| {
+                self.draw_layout_list(ui, config);
+                ui.separator();
+                self.draw_selected_layout(ui, config, fb_texture_id);
+            });
+    }
+}
@@ -28,6 +28,7 @@ bitflags::bitflags! {
 
 pub struct TitleMenuBarState {
     fps_fixed: Option<u64>,
+    frame_count: Option<u64>,
     menu_bar_is_visible: bool,
 
     #[cfg(any(target_os = "macos", target_os = "linux", target_os = "windows"))]
@@ -48,6 +49,7 @@ impl TitleMenuBarState {
     pub fn new(_config: &Config) -> Self {
         TitleMenuBarState {
             fps_fixed: None,
+            frame_count: None,
             menu_bar_is_visible: true,
 
             #[cfg(any(target_os = "macos", target_os = "linux", target_os = "windows"))]
@@ -91,6 +93,13 @@ impl TitleMenuBarState {
                         buffer.push_str(" - ");
                     }
                     let _ = write!(buffer, "{:.01} FPS", fps_fixed as f32 / 10.0);
+                    needs_separator = true;
+                }
+                if let Some(frame_count) = self.frame_count {
+                    if needs_separator {
+                        buffer.push_str(" - ");
+                    }
+                    let _ = write!(buffer, "Frame {frame_count}");
                 }
             }
         } else if components.contains(TitleComponents::GAME_TITLE) {
@@ -130,6 +139,7 @@ impl TitleMenuBarState {
 
     pub fn stop_game(&mut self, config: &Config, _window: &Window) {
         self.menu_bar_is_visible = true;
+        self.frame_count = None;
 
         #[cfg(any(target_os = "macos", target_os = "linux", target_os = "windows"))]
         {
@@ -147,6 +157,10 @@ impl TitleMenuBarState {
         self.fps_fixed = Some((fps * 10.0).round() as u64);
     }
 
+    pub fn update_frame_count(&mut self, frame_count: Option<u64>) {
+        self.frame_count = frame_count;
+    }
+
     pub fn menu_bar_is_visible(&self) -> bool {
         self.menu_bar_is_visible
     }
@@ -1,4 +1,5 @@
-use crate::config::{self, File};
+use crate::config::{self, CustomScreenLayout, File};
+use dust_core::gpu::{SCREEN_HEIGHT, SCREEN_WIDTH};
 use imgui::{StyleColor, Ui};
 use serde::{Deserialize, Serialize};
 use std::{
@@ -102,6 +103,115 @@ pub fn scale_to_fit_rotated(
     )
 }
 
+/// Returns the part of a quad returned by [`scale_to_fit_rotated`] covering the given rectangle
+/// of the original one, whose corners are expressed as fractions of its size.
+pub fn quad_slice(points: &[[f32; 2]; 4], rect: [[f32; 2]; 2]) -> [[f32; 2]; 4] {
+    let x_axis = sub2(points[1], points[0]);
+    let y_axis = sub2(points[3], points[0]);
+    let point = |[x, y]: [f32; 2]| add2(points[0], add2(mul2s(x_axis, x), mul2s(y_axis, y)));
+    let [min, max] = rect;
+    [
+        point(min),
+        point([max[0], min[1]]),
+        point(max),
+        point([min[0], max[1]]),
+    ]
+}
+
+/// The arrangement of the screens displayed in the main window.
+pub struct ScreenLayout {
+    /// The size of the rectangle containing all displayed screens, in DS pixels.
+    pub size: [f32; 2],
+    /// The area covered by each screen (top, then bottom) inside the layout's rectangle, as the
+    /// fractions of its size its top-left and bottom-right corners are at, or `None` if it isn't
+    /// displayed.
+    pub screen_rects: [Option<[[f32; 2]; 2]>; 2],
+    /// Whether the top screen should be drawn over the bottom one.
+    pub top_screen_above: bool,
+}
+
+impl ScreenLayout {
+    pub fn new(bottom_screen_detached: bool, single_screen: bool, swap: bool, gap: u16) -> Self {
+        let screen_size = [SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32];
+        let mut screen_rects = [None; 2];
+        let size = if bottom_screen_detached {
+            screen_rects[0] = Some([[0.0; 2], [1.0; 2]]);
+            screen_size
+        } else if single_screen {
+            screen_rects[swap as usize] = Some([[0.0; 2], [1.0; 2]]);
+            screen_size
+        } else {
+            let height = (2 * SCREEN_HEIGHT) as f32 + gap as f32;
+            let screen_end = SCREEN_HEIGHT as f32 / height;
+            screen_rects[swap as usize] = Some([[0.0; 2], [1.0, screen_end]]);
+            screen_rects[!swap as usize] = Some([[0.0, 1.0 - screen_end], [1.0; 2]]);
+            [screen_size[0], height]
+        };
+        ScreenLayout {
+            size,
+            screen_rects,
+            top_screen_above: false,
+        }
+    }
+
+    /// Creates a layout with one screen magnified and the other one beside it in the top-right
+    /// corner, scaled down by `small_scale`.
+    pub fn magnified(magnify_top: bool, small_scale: f32) -> Self {
+        let small_scale = small_scale.clamp(0.0, 1.0);
+        let large_end = 1.0 / (1.0 + small_scale);
+        let mut screen_rects = [None; 2];
+        screen_rects[!magnify_top as usize] = Some([[0.0; 2], [large_end, 1.0]]);
+        screen_rects[magnify_top as usize] = Some([[large_end, 0.0], [1.0, small_scale]]);
+        ScreenLayout {
+            size: [
+                SCREEN_WIDTH as f32 * (1.0 + small_scale),
+                SCREEN_HEIGHT as f32,
+            ],
+            screen_rects,
+            top_screen_above: false,
+        }
+    }
+
+    pub fn custom(layout: &CustomScreenLayout) -> Self {
+        ScreenLayout {
+            size: layout.size,
+            screen_rects: layout.screens.map(|rect| {
+                rect.map(|rect| {
+                    [
+                        [0, 1].map(|i| rect.pos[i] / layout.size[i]),
+                        [0, 1].map(|i| (rect.pos[i] + rect.size[i]) / layout.size[i]),
+                    ]
+                })
+            }),
+            top_screen_above: layout.top_screen_above,
+        }
+    }
+
+    /// Returns the quad and the texture V coordinate range of each displayed screen, in drawing
+    /// order, given the quad containing the whole layout.
+    pub fn screen_quads(
+        &self,
+        points: &[[f32; 2]; 4],
+    ) -> impl Iterator<Item = ([[f32; 2]; 4], [f32; 2])> + '_ {
+        let points = *points;
+        let order = if self.top_screen_above {
+            [1, 0]
+        } else {
+            [0, 1]
+        };
+        order.into_iter().filter_map(move |i| {
+            let rect = self.screen_rects[i]?;
+            let tex_v_start = i as f32 * 0.5;
+            Some((quad_slice(&points, rect), [tex_v_start, tex_v_start + 0.5]))
+        })
+    }
+
+    /// Returns the quad covered by the bottom screen, if it's displayed.
+    pub fn bottom_screen_quad(&self, points: &[[f32; 2]; 4]) -> Option<[[f32; 2]; 4]> {
+        self.screen_rects[1].map(|rect| quad_slice(points, rect))
+    }
+}
+
 pub fn add_y_spacing(ui: &Ui, spacing: f32) {
     let mut cursor_pos = ui.cursor_screen_pos();
     cursor_pos[1] += spacing;
@@ -0,0 +1,205 @@
+use dust_core::ds_slot::rom::patch;
+use imgui::{TableFlags, Ui};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+pub(super) enum Action {
+    /// Applies the patch at the given path on the next launch of the game.
+    Activate(PathBuf),
+    Deactivate,
+    Close,
+}
+
+fn is_patch(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            patch::EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+        })
+}
+
+pub(super) enum Change {
+    Added(PathBuf),
+    Modified(PathBuf),
+}
+
+impl Change {
+    /// Returns a user-facing description of the change, given the patch that's currently
+    /// activated for the game.
+    pub fn message(&self, active_patch: Option<&Path>) -> String {
+        match self {
+            Change::Added(path) => format!(
+                "Found patch {} in the watch folder; activate it from Emulation > Watch folder to \
+                 apply it on the next launch",
+                file_name(path),
+            ),
+            Change::Modified(path) if active_patch == Some(path.as_path()) => format!(
+                "Patch {} was modified; the changes will be applied on the next launch",
+                file_name(path),
+            ),
+            Change::Modified(path) => format!("Patch {} was modified", file_name(path)),
+        }
+    }
+}
+
+struct File {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Returns the watch folder for the game with the given code, named after the code itself so that
+/// it's shared by all dumps and revisions of the game.
+pub(super) fn dir_path(base_path: &Path, ds_game_code: u32) -> PathBuf {
+    base_path.join(String::from_utf8_lossy(&ds_game_code.to_le_bytes()).as_ref())
+}
+
+/// A per-game folder the user can drop ROM patches into, which is polled for changes while the game
+/// is running; patches are only applied on launch, so new ones are offered for activation on the
+/// next one. Other files (such as cheats or scripts) are ignored, as there's nothing to apply them
+/// with.
+pub(super) struct WatchFolder {
+    title: String,
+    dir_path: PathBuf,
+    files: Vec<File>,
+    last_poll: Instant,
+    active_patch: Option<PathBuf>,
+    pub opened: bool,
+}
+
+impl WatchFolder {
+    const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+    pub fn new(title: String, dir_path: PathBuf, active_patch: Option<PathBuf>) -> Self {
+        let mut result = WatchFolder {
+            title,
+            dir_path,
+            files: Vec::new(),
+            last_poll: Instant::now(),
+            active_patch,
+            opened: false,
+        };
+        result.scan();
+        result
+    }
+
+    /// Returns the number of patches in the folder that aren't currently activated.
+    pub fn inactive_patches(&self) -> usize {
+        self.files
+            .iter()
+            .filter(|file| self.active_patch.as_ref() != Some(&file.path))
+            .count()
+    }
+
+    pub fn active_patch(&self) -> Option<&Path> {
+        self.active_patch.as_deref()
+    }
+
+    pub fn set_active_patch(&mut self, path: Option<PathBuf>) {
+        self.active_patch = path;
+    }
+
+    fn scan(&mut self) -> Vec<Change> {
+        let mut changes = Vec::new();
+        let mut files = Vec::new();
+        if let Ok(entries) = fs::read_dir(&self.dir_path) {
+            for entry in entries.filter_map(Result::ok) {
+                let path = entry.path();
+                if !is_patch(&path) {
+                    continue;
+                }
+                let modified = entry
+                    .metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .ok();
+                match self.files.iter().find(|file| file.path == path) {
+                    Some(prev_file) if prev_file.modified != modified => {
+                        changes.push(Change::Modified(path.clone()));
+                    }
+                    Some(_) => {}
+                    None => changes.push(Change::Added(path.clone())),
+                }
+                files.push(File { path, modified });
+            }
+        }
+        files.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+        self.files = files;
+        changes
+    }
+
+    /// Rescans the folder if enough time passed since the last time, returning the files that
+    /// were added or modified since then.
+    pub fn poll(&mut self) -> Vec<Change> {
+        let now = Instant::now();
+        if now - self.last_poll < Self::POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = now;
+        self.scan()
+    }
+
+    pub fn draw(&mut self, ui: &Ui) -> Option<Action> {
+        if !self.opened {
+            return None;
+        }
+        let mut action = None;
+        let mut opened = true;
+        ui.window(format!("Watch folder - {}###watch_folder", self.title))
+            .always_auto_resize(true)
+            .collapsible(false)
+            .opened(&mut opened)
+            .build(|| {
+                ui.text(format!("Watching `{}`.", self.dir_path.display()));
+                if ui.small_button("Open folder") {
+                    let _ = fs::create_dir_all(&self.dir_path);
+                    let _ = opener::open(&self.dir_path);
+                }
+
+                if self.files.is_empty() {
+                    ui.text_disabled("No patches (.ips, .ups, .bps) found.");
+                } else if let Some(_table) =
+                    ui.begin_table_with_flags("files", 2, TableFlags::SIZING_FIXED_FIT)
+                {
+                    for (i, file) in self.files.iter().enumerate() {
+                        let _id = ui.push_id_usize(i);
+                        ui.table_next_row();
+                        ui.table_next_column();
+                        ui.text(file_name(&file.path));
+
+                        ui.table_next_column();
+                        if self.active_patch.as_ref() == Some(&file.path) {
+                            ui.text_colored([0.4, 0.8, 0.4, 1.0], "Active");
+                            ui.same_line();
+                            if ui.small_button("Deactivate") {
+                                action = Some(Action::Deactivate);
+                            }
+                        } else if ui.small_button("Activate") {
+                            action = Some(Action::Activate(file.path.clone()));
+                        }
+                    }
+                }
+
+                ui.separator();
+                ui.text_disabled(
+                    "Only ROM patches are handled; patches next to the ROM file take precedence, \
+                     and changes take effect the next time the game is launched.",
+                );
+                if ui.button("Close") {
+                    action = Some(Action::Close);
+                }
+            });
+        if !opened {
+            action = Some(Action::Close);
+        }
+        action
+    }
+}
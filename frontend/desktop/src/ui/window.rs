@@ -1,3 +1,6 @@
+mod screen_window;
+pub use screen_window::ScreenWindow;
+
 pub use imgui_wgpu::SrgbMode;
 
 #[cfg(target_os = "macos")]
@@ -17,9 +20,9 @@ use std::{
 use winit::window::Icon;
 use winit::{
     dpi::{LogicalSize, PhysicalSize},
-    event::{Event, WindowEvent},
-    event_loop::EventLoop,
-    window::Window as WinitWindow,
+    event::{Event, StartCause, WindowEvent},
+    event_loop::{ControlFlow as WinitControlFlow, EventLoop},
+    window::{CursorGrabMode, Window as WinitWindow},
 };
 #[cfg(target_os = "macos")]
 use winit::{
@@ -27,6 +30,19 @@ use winit::{
     raw_window_handle::{HasWindowHandle, RawWindowHandle},
 };
 
+/// Hides the cursor and locks it in place (or, if unsupported, confines it to the window), or
+/// restores it.
+fn set_cursor_captured(window: &WinitWindow, captured: bool) {
+    if captured {
+        if window.set_cursor_grab(CursorGrabMode::Locked).is_err() {
+            let _ = window.set_cursor_grab(CursorGrabMode::Confined);
+        }
+    } else {
+        let _ = window.set_cursor_grab(CursorGrabMode::None);
+    }
+    window.set_cursor_visible(!captured);
+}
+
 pub enum AdapterSelection {
     Auto(wgpu::PowerPreference),
     Manual(wgpu::Backends, Box<dyn FnMut(&wgpu::Adapter) -> bool>),
@@ -40,7 +56,11 @@ pub struct GfxDevice {
 }
 
 impl GfxDevice {
-    async fn new(features: wgpu::Features, adapter: AdapterSelection) -> Self {
+    async fn new(
+        features: wgpu::Features,
+        optional_features: wgpu::Features,
+        adapter: AdapterSelection,
+    ) -> Self {
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
             backends: wgpu::Backends::all(),
             ..Default::default()
@@ -63,14 +83,17 @@ impl GfxDevice {
         }
         .expect("couldn't create graphics adapter");
 
+        // Request what the adapter supports up to what the renderers can use, so that devices with
+        // lower limits can still fall back to the software renderers instead of failing here
+        let adapter_limits = adapter.limits();
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
-                    required_features: features,
+                    required_features: features | (adapter.features() & optional_features),
                     required_limits: wgpu::Limits {
-                        max_texture_dimension_2d: 4096,
-                        max_bind_groups: 5,
+                        max_texture_dimension_2d: adapter_limits.max_texture_dimension_2d.min(4096),
+                        max_bind_groups: adapter_limits.max_bind_groups.min(5),
                         ..wgpu::Limits::downlevel_webgl2_defaults()
                     },
                     memory_hints: wgpu::MemoryHints::MemoryUsage,
@@ -101,6 +124,39 @@ impl GfxDevice {
     }
 }
 
+/// How rendered frames are handed over to the display, applied to the swapchains of all windows.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PresentConfig {
+    /// The present mode to use, if the surface supports it (otherwise, vsync is used); on Metal,
+    /// display sync is disabled for anything other than FIFO.
+    pub mode: wgpu::PresentMode,
+    /// The maximum number of frames that can be queued up for display before acquiring the next
+    /// swapchain texture blocks; maps to the DXGI flip-model maximum frame latency on Direct3D 12
+    /// and to the drawable count on Metal.
+    pub max_frame_latency: u32,
+}
+
+impl Default for PresentConfig {
+    fn default() -> Self {
+        PresentConfig {
+            mode: wgpu::PresentMode::AutoVsync,
+            max_frame_latency: 2,
+        }
+    }
+}
+
+/// Times measured while drawing the last frame of the main window, used to estimate how much
+/// latency presentation adds.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameTimings {
+    /// The time spent waiting to acquire a swapchain texture, which grows when the GPU or the
+    /// display can't keep up with the frames being queued.
+    pub acquire_wait: Duration,
+    /// The time between the start of the frame (when input was last processed) and the frame
+    /// being handed over for presentation.
+    pub present_delay: Duration,
+}
+
 pub struct GfxSurface {
     surface: wgpu::Surface<'static>,
     config: wgpu::SurfaceConfiguration,
@@ -109,7 +165,12 @@ pub struct GfxSurface {
 }
 
 impl GfxSurface {
-    fn new(window: &WinitWindow, gfx: &GfxDevice, srgb_mode: SrgbMode) -> Self {
+    fn new(
+        window: &WinitWindow,
+        gfx: &GfxDevice,
+        srgb_mode: SrgbMode,
+        present_config: PresentConfig,
+    ) -> Self {
         let surface = unsafe {
             gfx.instance.create_surface_unsafe(
                 wgpu::SurfaceTargetUnsafe::from_window(window)
@@ -134,10 +195,10 @@ impl GfxSurface {
             },
             width: size.width,
             height: size.height,
-            present_mode: wgpu::PresentMode::AutoVsync,
+            present_mode: Self::supported_present_mode(&surface, gfx, present_config.mode),
             alpha_mode: wgpu::CompositeAlphaMode::Auto,
             view_formats: Vec::new(),
-            desired_maximum_frame_latency: 2,
+            desired_maximum_frame_latency: present_config.max_frame_latency,
         };
         surface.configure(&gfx.device, &config);
 
@@ -161,6 +222,32 @@ impl GfxSurface {
         self.format_changed
     }
 
+    fn supported_present_mode(
+        surface: &wgpu::Surface,
+        gfx: &GfxDevice,
+        mode: wgpu::PresentMode,
+    ) -> wgpu::PresentMode {
+        if matches!(
+            mode,
+            wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync
+        ) || surface
+            .get_capabilities(&gfx.adapter)
+            .present_modes
+            .contains(&mode)
+        {
+            mode
+        } else {
+            wgpu::PresentMode::AutoVsync
+        }
+    }
+
+    fn set_present_config(&mut self, gfx: &GfxDevice, present_config: PresentConfig) {
+        self.config.present_mode =
+            Self::supported_present_mode(&self.surface, gfx, present_config.mode);
+        self.config.desired_maximum_frame_latency = present_config.max_frame_latency;
+        self.invalidate_swapchain();
+    }
+
     fn invalidate_swapchain(&mut self) {
         self.needs_rebuild = true;
     }
@@ -356,10 +443,18 @@ pub struct Window {
     pub imgui_gfx: imgui_wgpu::Renderer,
 
     is_occluded: bool,
+    idle: bool,
+    last_input: Instant,
     #[cfg(target_os = "macos")]
     macos_title_bar_is_transparent: bool,
     #[cfg(target_os = "macos")]
     macos_title_bar_height: f32,
+
+    srgb_mode: SrgbMode,
+    present_config: PresentConfig,
+    frame_timings: FrameTimings,
+    screen_window: Option<ScreenWindow>,
+    screen_window_request: Option<screen_window::Request>,
 }
 
 impl Window {
@@ -400,12 +495,79 @@ impl Window {
         }
     }
 
+    /// Sets whether the window is allowed to stop redrawing continuously (i.e. because emulation
+    /// is paused); once [`IDLE_GRACE_PERIOD`] has passed since the last input, it will then only be
+    /// redrawn on input or every [`IDLE_FRAME_INTERVAL`].
+    #[inline]
+    pub fn set_idle(&mut self, idle: bool) {
+        self.idle = idle;
+    }
+
+    fn is_idle(&self) -> bool {
+        self.idle && self.last_input.elapsed() >= IDLE_GRACE_PERIOD
+    }
+
+    #[inline]
+    pub fn set_cursor_captured(&self, captured: bool) {
+        set_cursor_captured(&self.window, captured);
+    }
+
     #[cfg(any(target_os = "linux", target_os = "windows"))]
     #[inline]
     pub fn set_icon(&self, icon: Option<Icon>) {
         self.window.set_window_icon(icon)
     }
 
+    /// Requests a secondary screen window to be opened; it will be created during the next event
+    /// loop iteration, at which point it will be available through [`Window::screen_window`].
+    pub fn request_screen_window(&mut self, title: impl Into<String>, logical_size: (u32, u32)) {
+        if self.screen_window.is_none() {
+            self.screen_window_request = Some(screen_window::Request {
+                title: title.into(),
+                logical_size,
+            });
+        }
+    }
+
+    pub fn close_screen_window(&mut self) {
+        self.screen_window_request = None;
+        self.screen_window = None;
+    }
+
+    #[inline]
+    pub fn screen_window(&self) -> Option<&ScreenWindow> {
+        self.screen_window.as_ref()
+    }
+
+    #[inline]
+    pub fn screen_window_mut(&mut self) -> Option<&mut ScreenWindow> {
+        self.screen_window.as_mut()
+    }
+
+    /// Changes how frames are presented in both the main and the bottom screen window; takes effect
+    /// starting from the next frame.
+    pub fn set_present_config(&mut self, present_config: PresentConfig) {
+        if present_config == self.present_config {
+            return;
+        }
+        self.present_config = present_config;
+        self.gfx_surface
+            .set_present_config(&self.gfx_device, present_config);
+        if let Some(screen_window) = &mut self.screen_window {
+            screen_window.set_present_config(&self.gfx_device, present_config);
+        }
+    }
+
+    #[inline]
+    pub fn frame_timings(&self) -> FrameTimings {
+        self.frame_timings
+    }
+
+    #[inline]
+    pub fn gfx_adapter(&self) -> &wgpu::Adapter {
+        &self.gfx_device.adapter
+    }
+
     #[inline]
     pub fn gfx_device(&self) -> &Arc<wgpu::Device> {
         &self.gfx_device.device
@@ -509,6 +671,13 @@ impl Window {
     }
 }
 
+/// How often an idle window gets redrawn without any input, to keep animations and notifications
+/// from the emulation thread somewhat up to date.
+const IDLE_FRAME_INTERVAL: Duration = Duration::from_millis(100);
+/// How long to keep redrawing continuously after the last input before going idle, to let the UI
+/// settle (i.e. finish hover animations and process clicks spanning multiple frames).
+const IDLE_GRACE_PERIOD: Duration = Duration::from_millis(500);
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum ControlFlow {
     Continue,
@@ -524,6 +693,7 @@ impl Builder {
     pub async fn new(
         title: impl Into<String>,
         features: wgpu::Features,
+        optional_features: wgpu::Features,
         adapter: AdapterSelection,
         default_logical_size: (u32, u32),
         srgb_mode: SrgbMode,
@@ -531,7 +701,7 @@ impl Builder {
     ) -> Self {
         let event_loop = EventLoop::new().expect("couldn't create event loop");
 
-        let gfx_device = GfxDevice::new(features, adapter).await;
+        let gfx_device = GfxDevice::new(features, optional_features, adapter).await;
 
         let imgui = imgui::Context::create();
 
@@ -636,8 +806,12 @@ impl Builder {
                         imgui_winit_support::HiDpiMode::Default,
                     );
 
-                    let gfx_surface =
-                        GfxSurface::new(&winit_window, &window.gfx_device, window.srgb_mode);
+                    let gfx_surface = GfxSurface::new(
+                        &winit_window,
+                        &window.gfx_device,
+                        window.srgb_mode,
+                        PresentConfig::default(),
+                    );
                     let imgui_gfx = imgui_wgpu::Renderer::new(
                         &window.gfx_device.device,
                         &window.gfx_device.queue,
@@ -656,10 +830,17 @@ impl Builder {
                         imgui_winit,
                         imgui_gfx,
                         is_occluded: false,
+                        idle: false,
+                        last_input: Instant::now(),
                         #[cfg(target_os = "macos")]
                         macos_title_bar_is_transparent: window.macos_title_bar_is_hidden,
                         #[cfg(target_os = "macos")]
                         macos_title_bar_height: 0.0,
+                        srgb_mode: window.srgb_mode,
+                        present_config: PresentConfig::default(),
+                        frame_timings: FrameTimings::default(),
+                        screen_window: None,
+                        screen_window_request: None,
                     };
 
                     #[cfg(target_os = "macos")]
@@ -678,6 +859,52 @@ impl Builder {
             };
             let state = unsafe { state_.as_mut().unwrap_unchecked() };
 
+            if let Some(request) = window.screen_window_request.take() {
+                window.screen_window = Some(ScreenWindow::new(
+                    elwt,
+                    &window.gfx_device,
+                    window.srgb_mode,
+                    window.present_config,
+                    request,
+                ));
+            }
+
+            if let Event::WindowEvent { event, .. } = &event {
+                if !matches!(event, WindowEvent::RedrawRequested) {
+                    // Leave idle mode right away on input, so that the UI responds instantly
+                    window.last_input = Instant::now();
+                    if !window.is_occluded {
+                        window.window.request_redraw();
+                    }
+                }
+            }
+
+            if let Event::WindowEvent {
+                window_id,
+                event: window_event,
+            } = &event
+            {
+                if let Some(screen_window) = window
+                    .screen_window
+                    .as_mut()
+                    .filter(|screen_window| screen_window.id() == *window_id)
+                {
+                    match window_event {
+                        WindowEvent::CloseRequested => screen_window.handle_close_requested(),
+                        WindowEvent::Resized(_) => screen_window.handle_resized(),
+                        WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                            screen_window.handle_scale_factor_changed(*scale_factor);
+                        }
+                        WindowEvent::Occluded(is_occluded) => {
+                            screen_window.handle_occluded(*is_occluded);
+                        }
+                        _ => {}
+                    }
+                    process_event(window, state, &event);
+                    return;
+                }
+            }
+
             window
                 .imgui_winit
                 .handle_event(imgui.io_mut(), &window.window, &event);
@@ -708,9 +935,11 @@ impl Builder {
                     elwt.exit();
                 }
 
+                let acquire_start = Instant::now();
                 let frame = window
                     .gfx_surface
                     .start_frame(&window.gfx_device, window.window.inner_size());
+                let acquire_wait = acquire_start.elapsed();
                 let mut encoder = window
                     .gfx_device
                     .device
@@ -734,15 +963,36 @@ impl Builder {
                 window.gfx_device.queue.submit(iter::once(encoder.finish()));
                 window.window.pre_present_notify();
                 frame.present();
+                window.frame_timings = FrameTimings {
+                    acquire_wait,
+                    present_delay: now.elapsed(),
+                };
+
+                if let Some(screen_window) = &mut window.screen_window {
+                    screen_window.render(&window.gfx_device);
+                }
 
                 window.gfx_device.device.poll(wgpu::Maintain::Poll);
 
-                if !window.is_occluded {
+                if window.is_occluded {
+                    elwt.set_control_flow(WinitControlFlow::Wait);
+                } else if window.is_idle() {
+                    elwt.set_control_flow(WinitControlFlow::WaitUntil(
+                        Instant::now() + IDLE_FRAME_INTERVAL,
+                    ));
+                } else {
+                    elwt.set_control_flow(WinitControlFlow::Wait);
                     window.window.request_redraw();
                 }
             };
 
             match event {
+                Event::NewEvents(StartCause::ResumeTimeReached { .. }) => {
+                    if !window.is_occluded {
+                        window.window.request_redraw();
+                    }
+                }
+
                 Event::WindowEvent {
                     event: WindowEvent::CloseRequested,
                     ..
@@ -0,0 +1,437 @@
+use super::{set_cursor_captured, GfxDevice, GfxSurface, PresentConfig, SrgbMode};
+use dust_core::{
+    gpu::{Framebuffer, SCREEN_HEIGHT, SCREEN_WIDTH},
+    utils::zeroed_box,
+};
+use std::{iter, slice, sync::Arc};
+use winit::{
+    dpi::LogicalSize,
+    event_loop::ActiveEventLoop,
+    window::{Window as WinitWindow, WindowId},
+};
+
+pub(super) struct Request {
+    pub title: String,
+    pub logical_size: (u32, u32),
+}
+
+/// A secondary OS window displaying part of the emulator's framebuffer on its own (i.e. a detached
+/// bottom screen), drawn without going through imgui.
+pub struct ScreenWindow {
+    window: WinitWindow,
+    scale_factor: f64,
+    gfx_surface: GfxSurface,
+    is_occluded: bool,
+    close_requested: bool,
+
+    pipeline: wgpu::RenderPipeline,
+    bg_layout: wgpu::BindGroupLayout,
+    bg: wgpu::BindGroup,
+    sampler: wgpu::Sampler,
+    mag_filter: wgpu::FilterMode,
+    corners_buffer: wgpu::Buffer,
+    corners: [[f32; 4]; 4],
+    owned_texture: wgpu::Texture,
+    source_texture: Option<Arc<wgpu::Texture>>,
+}
+
+impl ScreenWindow {
+    pub(super) fn new(
+        elwt: &ActiveEventLoop,
+        gfx: &GfxDevice,
+        srgb_mode: SrgbMode,
+        present_config: PresentConfig,
+        request: Request,
+    ) -> Self {
+        let window = elwt
+            .create_window(
+                WinitWindow::default_attributes()
+                    .with_title(request.title)
+                    .with_inner_size(LogicalSize::new(
+                        request.logical_size.0,
+                        request.logical_size.1,
+                    )),
+            )
+            .expect("couldn't create screen window");
+        let scale_factor = window.scale_factor();
+        let gfx_surface = GfxSurface::new(&window, gfx, srgb_mode, present_config);
+
+        let bg_layout = gfx
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Screen window"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let mag_filter = wgpu::FilterMode::Nearest;
+        let sampler = Self::create_sampler(&gfx.device, mag_filter);
+
+        let corners_buffer = gfx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Screen window corners"),
+            size: 4 * 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let owned_texture = gfx.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Screen window framebuffer"),
+            size: wgpu::Extent3d {
+                width: SCREEN_WIDTH as u32,
+                height: SCREEN_HEIGHT as u32 * 2,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let bg = Self::create_bg(
+            &gfx.device,
+            &bg_layout,
+            &corners_buffer,
+            &owned_texture,
+            &sampler,
+        );
+        let pipeline = Self::create_pipeline(gfx, &bg_layout, gfx_surface.config().format);
+
+        ScreenWindow {
+            window,
+            scale_factor,
+            gfx_surface,
+            is_occluded: false,
+            close_requested: false,
+
+            pipeline,
+            bg_layout,
+            bg,
+            sampler,
+            mag_filter,
+            corners_buffer,
+            corners: [[0.0; 4]; 4],
+            owned_texture,
+            source_texture: None,
+        }
+    }
+
+    fn create_sampler(device: &wgpu::Device, mag_filter: wgpu::FilterMode) -> wgpu::Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Screen window"),
+            mag_filter,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        })
+    }
+
+    fn create_bg(
+        device: &wgpu::Device,
+        bg_layout: &wgpu::BindGroupLayout,
+        corners_buffer: &wgpu::Buffer,
+        texture: &wgpu::Texture,
+        sampler: &wgpu::Sampler,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Screen window"),
+            layout: bg_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: corners_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(
+                        &texture.create_view(&Default::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        })
+    }
+
+    fn create_pipeline(
+        gfx: &GfxDevice,
+        bg_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        let pipeline_layout = gfx
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Screen window"),
+                bind_group_layouts: &[bg_layout],
+                push_constant_ranges: &[],
+            });
+
+        let shader_module = gfx
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Screen window"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("screen_window.wgsl").into()),
+            });
+
+        gfx.device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Screen window"),
+                layout: Some(&pipeline_layout),
+
+                vertex: wgpu::VertexState {
+                    module: &shader_module,
+                    entry_point: None,
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleStrip,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    unclipped_depth: false,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                },
+
+                depth_stencil: None,
+
+                multisample: wgpu::MultisampleState::default(),
+
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader_module,
+                    entry_point: None,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+
+                multiview: None,
+                cache: None,
+            })
+    }
+
+    #[inline]
+    pub fn id(&self) -> WindowId {
+        self.window.id()
+    }
+
+    #[inline]
+    pub fn inner_size(&self) -> LogicalSize<f64> {
+        self.window.inner_size().to_logical(self.scale_factor)
+    }
+
+    #[inline]
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    #[inline]
+    pub fn set_cursor_captured(&self, captured: bool) {
+        set_cursor_captured(&self.window, captured);
+    }
+
+    #[inline]
+    pub fn close_requested(&self) -> bool {
+        self.close_requested
+    }
+
+    /// Sets the texture containing both screens to sample from, or, if `None`, switches to an
+    /// internal texture to be updated using [`ScreenWindow::set_framebuffer_data`].
+    pub fn set_source_texture(
+        &mut self,
+        device: &wgpu::Device,
+        texture: Option<&Arc<wgpu::Texture>>,
+    ) {
+        let unchanged = match (&self.source_texture, texture) {
+            (Some(prev), Some(new)) => Arc::ptr_eq(prev, new),
+            (None, None) => true,
+            _ => false,
+        };
+        if unchanged {
+            return;
+        }
+        self.source_texture = texture.cloned();
+        self.bg = Self::create_bg(
+            device,
+            &self.bg_layout,
+            &self.corners_buffer,
+            self.source_texture
+                .as_deref()
+                .unwrap_or(&self.owned_texture),
+            &self.sampler,
+        );
+    }
+
+    /// Sets the filter to use when magnifying the source texture.
+    pub fn set_mag_filter(&mut self, device: &wgpu::Device, mag_filter: wgpu::FilterMode) {
+        if mag_filter == self.mag_filter {
+            return;
+        }
+        self.mag_filter = mag_filter;
+        self.sampler = Self::create_sampler(device, mag_filter);
+        self.bg = Self::create_bg(
+            device,
+            &self.bg_layout,
+            &self.corners_buffer,
+            self.source_texture
+                .as_deref()
+                .unwrap_or(&self.owned_texture),
+            &self.sampler,
+        );
+    }
+
+    fn write_owned_texture(&self, queue: &wgpu::Queue, data: &[u8]) {
+        if self.source_texture.is_some() {
+            return;
+        }
+        queue.write_texture(
+            self.owned_texture.as_image_copy(),
+            data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * SCREEN_WIDTH as u32),
+                rows_per_image: None,
+            },
+            self.owned_texture.size(),
+        );
+    }
+
+    pub fn set_framebuffer_data(&self, queue: &wgpu::Queue, data: &Framebuffer) {
+        self.write_owned_texture(queue, unsafe {
+            slice::from_raw_parts(
+                data.as_ptr() as *const u8,
+                2 * 4 * SCREEN_WIDTH * SCREEN_HEIGHT,
+            )
+        });
+    }
+
+    pub fn clear(&self, queue: &wgpu::Queue) {
+        self.write_owned_texture(
+            queue,
+            &*zeroed_box::<[u8; SCREEN_WIDTH * SCREEN_HEIGHT * 8]>(),
+        );
+    }
+
+    /// Sets the quad to draw, as its corners' logical positions inside the window (in clockwise
+    /// order starting from the top left corner of the source image) and the source UV rectangle
+    /// (as `[min_u, min_v, max_u, max_v]`).
+    pub fn set_quad(&mut self, points: [[f32; 2]; 4], uv_rect: [f32; 4]) {
+        let size = self.inner_size();
+        let to_ndc = |[x, y]: [f32; 2]| {
+            [
+                x / size.width as f32 * 2.0 - 1.0,
+                1.0 - y / size.height as f32 * 2.0,
+            ]
+        };
+        let uvs = [
+            [uv_rect[0], uv_rect[1]],
+            [uv_rect[2], uv_rect[1]],
+            [uv_rect[2], uv_rect[3]],
+            [uv_rect[0], uv_rect[3]],
+        ];
+        // Triangle strip order: top left, top right, bottom left, bottom right
+        for (corner, i) in self.corners.iter_mut().zip([0, 1, 3, 2]) {
+            let [x, y] = to_ndc(points[i]);
+            *corner = [x, y, uvs[i][0], uvs[i][1]];
+        }
+    }
+
+    pub(super) fn handle_close_requested(&mut self) {
+        self.close_requested = true;
+    }
+
+    pub(super) fn handle_resized(&mut self) {
+        self.gfx_surface.invalidate_swapchain();
+    }
+
+    pub(super) fn handle_scale_factor_changed(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+        self.gfx_surface.invalidate_swapchain();
+    }
+
+    pub(super) fn set_present_config(&mut self, gfx: &GfxDevice, present_config: PresentConfig) {
+        self.gfx_surface.set_present_config(gfx, present_config);
+    }
+
+    pub(super) fn handle_occluded(&mut self, is_occluded: bool) {
+        self.is_occluded = is_occluded;
+    }
+
+    pub(super) fn render(&mut self, gfx: &GfxDevice) {
+        if self.is_occluded {
+            return;
+        }
+
+        let frame = self.gfx_surface.start_frame(gfx, self.window.inner_size());
+        if self.gfx_surface.surface_format_changed() {
+            self.pipeline =
+                Self::create_pipeline(gfx, &self.bg_layout, self.gfx_surface.config().format);
+        }
+
+        gfx.queue.write_buffer(&self.corners_buffer, 0, unsafe {
+            slice::from_raw_parts(self.corners.as_ptr() as *const u8, 4 * 16)
+        });
+
+        let mut encoder = gfx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Screen window"),
+            });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Screen window"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &frame.texture.create_view(&Default::default()),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.bg, &[]);
+            render_pass.draw(0..4, 0..1);
+        }
+
+        gfx.queue.submit(iter::once(encoder.finish()));
+        self.window.pre_present_notify();
+        frame.present();
+    }
+}
@@ -0,0 +1,27 @@
+struct VertOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// xy: position in normalized device coordinates, zw: texture coordinates; in triangle strip order
+@group(0) @binding(0) var<uniform> corners: array<vec4<f32>, 4>;
+@group(0) @binding(1) var t_screen: texture_2d<f32>;
+@group(0) @binding(2) var s_screen: sampler;
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertOutput {
+    let corner = corners[vertex_index];
+    var output: VertOutput;
+    output.pos = vec4<f32>(corner.xy, 0.0, 1.0);
+    output.uv = corner.zw;
+    return output;
+}
+
+@fragment
+fn fs_main(
+    @location(0) uv: vec2<f32>,
+) -> @location(0) vec4<f32> {
+    return vec4<f32>(textureSample(t_screen, s_screen, uv).rgb, 1.0);
+}
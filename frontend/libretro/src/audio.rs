@@ -0,0 +1,27 @@
+use dust_core::audio::OutputSample;
+use std::{cell::RefCell, rc::Rc};
+
+/// Collects the emulator's output as interleaved signed 16-bit samples, to be handed to the
+/// frontend in a single batch after each frame.
+pub struct Backend {
+    samples: Rc<RefCell<Vec<i16>>>,
+}
+
+impl Backend {
+    pub fn new(samples: Rc<RefCell<Vec<i16>>>) -> Self {
+        Backend { samples }
+    }
+}
+
+impl dust_core::audio::Backend for Backend {
+    fn handle_sample_chunk(&mut self, samples: &mut Vec<[OutputSample; 2]>) {
+        let mut buffer = self.samples.borrow_mut();
+        buffer.reserve(samples.len() * 2);
+        for sample in samples.drain(..).flatten() {
+            #[cfg(not(feature = "xq-audio"))]
+            buffer.push((sample as i16 - 0x200) << 6);
+            #[cfg(feature = "xq-audio")]
+            buffer.push((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16);
+        }
+    }
+}
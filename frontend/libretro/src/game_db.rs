@@ -0,0 +1,87 @@
+use serde::Deserialize;
+
+// The database is embedded, as libretro cores are distributed as a single library
+static DATABASE: &str = include_str!("../../../game_db.json");
+
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SaveType {
+    None,
+    #[serde(rename = "eeprom-4k")]
+    Eeprom4k,
+    #[serde(rename = "eeprom-fram-64k")]
+    EepromFram64k,
+    #[serde(rename = "eeprom-fram-512k")]
+    EepromFram512k,
+    #[serde(rename = "eeprom-fram-1m")]
+    EepromFram1m,
+    #[serde(rename = "flash-2m")]
+    Flash2m,
+    #[serde(rename = "flash-4m")]
+    Flash4m,
+    #[serde(rename = "flash-8m")]
+    Flash8m,
+    #[serde(rename = "nand-64m")]
+    Nand64m,
+    #[serde(rename = "nand-128m")]
+    Nand128m,
+    #[serde(rename = "nand-256m")]
+    Nand256m,
+}
+
+impl SaveType {
+    pub fn expected_len(self) -> Option<usize> {
+        match self {
+            SaveType::None => None,
+            SaveType::Eeprom4k => Some(0x200),
+            SaveType::EepromFram64k => Some(0x2000),
+            SaveType::EepromFram512k => Some(0x1_0000),
+            SaveType::EepromFram1m => Some(0x2_0000),
+            SaveType::Flash2m => Some(0x4_0000),
+            SaveType::Flash4m => Some(0x8_0000),
+            SaveType::Flash8m => Some(0x10_0000),
+            SaveType::Nand64m => Some(0x80_0000),
+            SaveType::Nand128m => Some(0x100_0000),
+            SaveType::Nand256m => Some(0x200_0000),
+        }
+    }
+
+    pub fn from_save_len(len: usize) -> Option<Self> {
+        match len {
+            0x200 => Some(SaveType::Eeprom4k),
+            0x2000 => Some(SaveType::EepromFram64k),
+            0x1_0000 => Some(SaveType::EepromFram512k),
+            0x2_0000 => Some(SaveType::EepromFram1m),
+            0x4_0000 => Some(SaveType::Flash2m),
+            0x8_0000 => Some(SaveType::Flash4m),
+            0x10_0000 => Some(SaveType::Flash8m),
+            0x80_0000 => Some(SaveType::Nand64m),
+            0x100_0000 => Some(SaveType::Nand128m),
+            0x200_0000 => Some(SaveType::Nand256m),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct Peripherals {
+    pub infrared: bool,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Entry {
+    pub code: u32,
+    pub save_type: SaveType,
+    #[serde(default)]
+    pub peripherals: Peripherals,
+}
+
+pub fn lookup(game_code: u32) -> Option<Entry> {
+    let entries: Vec<Entry> = serde_json::from_str(DATABASE).ok()?;
+    entries
+        .binary_search_by_key(&game_code, |entry| entry.code)
+        .ok()
+        .map(|i| entries[i])
+}
@@ -0,0 +1,749 @@
+#![feature(new_zeroed_alloc)]
+
+mod audio;
+mod game_db;
+#[cfg(feature = "log")]
+mod logger;
+mod renderer_3d;
+mod retro;
+
+use dust_core::{
+    cpu::{arm7, arm9, interpreter::Interpreter},
+    ds_slot::{self, rom::Contents as _},
+    emu::{self, input::Keys, savestate, Emu, RunOutput},
+    flash::Flash,
+    gpu::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    rtc,
+    spi::firmware,
+    utils::{zeroed_box, BoxedByteSlice, Bytes, PersistentReadSavestate, PersistentWriteSavestate},
+    Model, SaveContents,
+};
+use game_db::SaveType;
+use std::{
+    cell::{Cell, RefCell},
+    ffi::{c_char, c_uint, c_void, CStr, CString},
+    fmt, fs,
+    path::{Path, PathBuf},
+    ptr,
+    rc::Rc,
+    slice,
+};
+
+// One frame lasts 263 scanlines of 355 dots, each taking 6 cycles of the 33.554432 MHz system
+// clock
+const FPS: f64 = (1 << 25) as f64 / (263 * 355 * 6) as f64;
+const SAMPLE_RATE: f64 = 32768.0;
+
+// Savestates can grow slightly depending on the emulator's state, but the frontend expects their
+// size to stay the same while a game is running (rewind and netplay allocate their buffers
+// according to it), so the reported size leaves this much room after the first one
+const SERIALIZE_SIZE_HEADROOM: usize = 0x1_0000;
+
+const KEY_MAP: [(c_uint, Keys); 12] = [
+    (retro::DEVICE_ID_JOYPAD_A, Keys::A),
+    (retro::DEVICE_ID_JOYPAD_B, Keys::B),
+    (retro::DEVICE_ID_JOYPAD_X, Keys::X),
+    (retro::DEVICE_ID_JOYPAD_Y, Keys::Y),
+    (retro::DEVICE_ID_JOYPAD_L, Keys::L),
+    (retro::DEVICE_ID_JOYPAD_R, Keys::R),
+    (retro::DEVICE_ID_JOYPAD_START, Keys::START),
+    (retro::DEVICE_ID_JOYPAD_SELECT, Keys::SELECT),
+    (retro::DEVICE_ID_JOYPAD_UP, Keys::UP),
+    (retro::DEVICE_ID_JOYPAD_DOWN, Keys::DOWN),
+    (retro::DEVICE_ID_JOYPAD_LEFT, Keys::LEFT),
+    (retro::DEVICE_ID_JOYPAD_RIGHT, Keys::RIGHT),
+];
+
+#[derive(Clone, Copy, Default)]
+struct Callbacks {
+    environment: Option<retro::EnvironmentFn>,
+    video_refresh: Option<retro::VideoRefreshFn>,
+    audio_sample_batch: Option<retro::AudioSampleBatchFn>,
+    input_poll: Option<retro::InputPollFn>,
+    input_state: Option<retro::InputStateFn>,
+    log: Option<retro::LogPrintfFn>,
+}
+
+// The libretro API is driven from a single thread
+thread_local! {
+    static CALLBACKS: Cell<Callbacks> = Cell::new(Callbacks::default());
+    static CORE: RefCell<Option<Core>> = const { RefCell::new(None) };
+}
+
+fn callbacks() -> Callbacks {
+    CALLBACKS.with(Cell::get)
+}
+
+fn update_callbacks(f: impl FnOnce(&mut Callbacks)) {
+    CALLBACKS.with(|callbacks| {
+        let mut value = callbacks.get();
+        f(&mut value);
+        callbacks.set(value);
+    });
+}
+
+fn print_log(level: c_uint, message: &str) {
+    let Ok(message) = CString::new(message) else {
+        return;
+    };
+    if let Some(log) = callbacks().log {
+        unsafe { log(level, c"%s\n".as_ptr(), message.as_ptr()) };
+    } else {
+        eprintln!("[Dust] {}", message.to_string_lossy());
+    }
+}
+
+unsafe fn environment(cmd: c_uint, data: *mut c_void) -> bool {
+    match callbacks().environment {
+        Some(environment) => environment(cmd, data),
+        None => false,
+    }
+}
+
+fn environment_dir(cmd: c_uint) -> Option<PathBuf> {
+    let mut dir: *const c_char = ptr::null();
+    if !unsafe { environment(cmd, &mut dir as *mut _ as *mut c_void) } || dir.is_null() {
+        return None;
+    }
+    Some(PathBuf::from(
+        unsafe { CStr::from_ptr(dir) }
+            .to_string_lossy()
+            .into_owned(),
+    ))
+}
+
+fn load_bios<const LEN: usize>(sys_dir: &Path, name: &str) -> Option<Box<Bytes<LEN>>> {
+    let contents = fs::read(sys_dir.join(name)).ok()?;
+    if contents.len() != LEN {
+        print_log(
+            retro::LOG_WARN,
+            &format!(
+                "Ignoring {name}: expected {LEN} bytes, got {}.",
+                contents.len()
+            ),
+        );
+        return None;
+    }
+    let mut buf = zeroed_box::<Bytes<LEN>>();
+    buf.copy_from_slice(&contents);
+    Some(buf)
+}
+
+fn load_firmware(sys_dir: &Path, model: Model) -> BoxedByteSlice {
+    if let Ok(contents) = fs::read(sys_dir.join("firmware.bin")) {
+        if firmware::is_valid_size(contents.len()) {
+            let mut buf = BoxedByteSlice::new_zeroed(contents.len());
+            buf.copy_from_slice(&contents);
+            return buf;
+        }
+        print_log(
+            retro::LOG_WARN,
+            &format!(
+                "Ignoring firmware.bin: invalid size ({} bytes).",
+                contents.len()
+            ),
+        );
+    }
+    firmware::default(model)
+}
+
+/// Returns the length of the save file the frontend will load for the given game, if any, to
+/// detect the save type of games missing from the database.
+fn existing_save_len(game_path: &Path) -> Option<usize> {
+    let save_dir = environment_dir(retro::ENVIRONMENT_GET_SAVE_DIRECTORY)?;
+    let mut file_name = game_path.file_stem()?.to_os_string();
+    file_name.push(".srm");
+    Some(fs::metadata(save_dir.join(file_name)).ok()?.len() as usize)
+}
+
+fn create_ds_slot_spi(
+    save_type: SaveType,
+    has_ir: bool,
+    #[cfg(feature = "log")] logger: &slog::Logger,
+) -> Result<ds_slot::spi::Spi, String> {
+    let Some(expected_len) = save_type.expected_len() else {
+        return Ok(ds_slot::spi::Empty::new(
+            #[cfg(feature = "log")]
+            logger.new(slog::o!("ds_spi" => "empty")),
+        )
+        .into());
+    };
+    // The frontend loads the save file's contents into the save chip's memory directly, after the
+    // game is loaded
+    let save_contents = SaveContents::New(expected_len);
+    fn map_err(err: impl fmt::Display) -> String {
+        format!("Couldn't create the save chip: {err}.")
+    }
+    Ok(match save_type {
+        SaveType::None => unreachable!(),
+        SaveType::Eeprom4k => ds_slot::spi::eeprom_4k::Eeprom4k::new(
+            save_contents,
+            None,
+            #[cfg(feature = "log")]
+            logger.new(slog::o!("ds_spi" => "eeprom_4k")),
+        )
+        .map_err(map_err)?
+        .into(),
+        SaveType::EepromFram64k | SaveType::EepromFram512k | SaveType::EepromFram1m => {
+            ds_slot::spi::eeprom_fram::EepromFram::new(
+                save_contents,
+                None,
+                #[cfg(feature = "log")]
+                logger.new(slog::o!("ds_spi" => "eeprom_fram")),
+            )
+            .map_err(map_err)?
+            .into()
+        }
+        SaveType::Flash2m | SaveType::Flash4m | SaveType::Flash8m => {
+            ds_slot::spi::flash::Flash::new(
+                save_contents,
+                [0; 20],
+                has_ir,
+                #[cfg(feature = "log")]
+                logger.new(slog::o!("ds_spi" => if has_ir { "flash_ir" } else { "flash" })),
+            )
+            .map_err(map_err)?
+            .into()
+        }
+        SaveType::Nand64m | SaveType::Nand128m | SaveType::Nand256m => {
+            ds_slot::spi::nand::Nand::new(
+                save_contents,
+                #[cfg(feature = "log")]
+                logger.new(slog::o!("ds_spi" => "nand")),
+            )
+            .map_err(map_err)?
+            .into()
+        }
+    })
+}
+
+struct Core {
+    #[cfg(feature = "log")]
+    logger: slog::Logger,
+    model: Model,
+    emu: Emu<Interpreter>,
+    arm7_bios: Option<Box<Bytes<{ arm7::BIOS_SIZE }>>>,
+    arm9_bios: Option<Box<Bytes<{ arm9::BIOS_SIZE }>>>,
+    audio_samples: Rc<RefCell<Vec<i16>>>,
+    video_buffer: Box<[u32]>,
+    serialize_size: Option<usize>,
+}
+
+impl Core {
+    fn new(rom_contents: &[u8], rom_path: Option<&Path>) -> Result<Self, String> {
+        #[cfg(feature = "log")]
+        let logger = slog::Logger::root(logger::RetroLog, slog::o!());
+
+        let model = Model::Ds;
+
+        let mut rom = BoxedByteSlice::new_zeroed(rom_contents.len().next_power_of_two());
+        rom[..rom_contents.len()].copy_from_slice(rom_contents);
+        if !ds_slot::rom::is_valid_size(rom.len() as u64, model) {
+            return Err("Invalid ROM size.".to_string());
+        }
+
+        let sys_dir = environment_dir(retro::ENVIRONMENT_GET_SYSTEM_DIRECTORY);
+        let arm7_bios = sys_dir
+            .as_deref()
+            .and_then(|dir| load_bios::<{ arm7::BIOS_SIZE }>(dir, "biosnds7.bin"));
+        let arm9_bios = sys_dir
+            .as_deref()
+            .and_then(|dir| load_bios::<{ arm9::BIOS_SIZE }>(dir, "biosnds9.bin"));
+        let firmware = match &sys_dir {
+            Some(dir) => load_firmware(dir, model),
+            None => firmware::default(model),
+        };
+
+        let game_code = rom.game_code();
+        let db_entry = game_db::lookup(game_code);
+        let save_type = if let Some(entry) = db_entry {
+            entry.save_type
+        } else if let Some(save_len) = rom_path.and_then(existing_save_len) {
+            SaveType::from_save_len(save_len).unwrap_or_else(|| {
+                print_log(
+                    retro::LOG_ERROR,
+                    &format!(
+                        "Unrecognized save file size ({save_len} B) and no database entry found, \
+                         defaulting to an empty save."
+                    ),
+                );
+                SaveType::None
+            })
+        } else {
+            print_log(
+                retro::LOG_WARN,
+                "No existing save file present and no database entry found, defaulting to an \
+                 empty save.",
+            );
+            SaveType::None
+        };
+        let has_ir =
+            db_entry.is_some_and(|entry| entry.peripherals.infrared) || game_code as u8 == b'I';
+
+        let ds_slot_spi = create_ds_slot_spi(
+            save_type,
+            has_ir,
+            #[cfg(feature = "log")]
+            &logger,
+        )?;
+
+        let audio_samples = Rc::new(RefCell::new(Vec::new()));
+        let (tx_3d, rx_3d) = renderer_3d::init();
+
+        // The RTC always reports the same time, so that emulation stays deterministic for netplay
+        let mut emu_builder = emu::Builder::new(
+            Flash::new(
+                SaveContents::Existing(firmware),
+                firmware::id_for_model(model),
+                #[cfg(feature = "log")]
+                logger.new(slog::o!("fw" => "")),
+            )
+            .map_err(|err| format!("Couldn't load firmware: {err}."))?,
+            Some(Box::new(rom)),
+            ds_slot_spi,
+            Box::new(audio::Backend::new(Rc::clone(&audio_samples))),
+            None,
+            Box::new(rtc::DummyBackend),
+            Box::new(dust_soft_2d::sync::Renderer::new(Box::new(rx_3d))),
+            Box::new(tx_3d),
+            None,
+            #[cfg(feature = "log")]
+            logger.clone(),
+        );
+
+        emu_builder.arm7_bios.clone_from(&arm7_bios);
+        emu_builder.arm9_bios.clone_from(&arm9_bios);
+
+        emu_builder.model = model;
+        emu_builder.direct_boot = true;
+
+        let emu = emu_builder
+            .build(Interpreter)
+            .map_err(|err| format!("Couldn't start emulator: {err}."))?;
+
+        Ok(Core {
+            #[cfg(feature = "log")]
+            logger,
+            model,
+            emu,
+            arm7_bios,
+            arm9_bios,
+            audio_samples,
+            video_buffer: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT * 2].into_boxed_slice(),
+            serialize_size: None,
+        })
+    }
+
+    fn reset(self) -> Result<Self, String> {
+        let emu = self.emu;
+
+        let (renderer_2d, renderer_3d_tx) = emu.gpu.into_renderers();
+
+        let mut emu_builder = emu::Builder::new(
+            emu.spi.firmware.reset(),
+            emu.ds_slot.rom.into_contents(),
+            emu.ds_slot.spi.reset(),
+            emu.audio.backend,
+            None,
+            emu.rtc.backend,
+            renderer_2d,
+            renderer_3d_tx,
+            None,
+            #[cfg(feature = "log")]
+            self.logger.clone(),
+        );
+
+        emu_builder.arm7_bios.clone_from(&self.arm7_bios);
+        emu_builder.arm9_bios.clone_from(&self.arm9_bios);
+
+        emu_builder.model = self.model;
+        emu_builder.direct_boot = true;
+
+        let emu = emu_builder
+            .build(Interpreter)
+            .map_err(|err| format!("Couldn't start emulator: {err}."))?;
+
+        self.audio_samples.borrow_mut().clear();
+        Ok(Core { emu, ..self })
+    }
+
+    fn update_input(&mut self, input_state: retro::InputStateFn) {
+        let mut pressed = Keys::empty();
+        let mut released = Keys::empty();
+        for (id, keys) in KEY_MAP {
+            if unsafe { input_state(0, retro::DEVICE_JOYPAD, 0, id) } != 0 {
+                pressed |= keys;
+            } else {
+                released |= keys;
+            }
+        }
+        self.emu.press_keys(pressed);
+        self.emu.release_keys(released);
+
+        // Pointer coordinates range from -0x7FFF to 0x7FFF across the whole video output, which
+        // contains both screens stacked vertically; touch coordinates are in 1/16ths of a pixel
+        let pointer = |id| unsafe { input_state(0, retro::DEVICE_POINTER, 0, id) } as i32;
+        let touch_pos = if pointer(retro::DEVICE_ID_POINTER_PRESSED) != 0 {
+            let x = (pointer(retro::DEVICE_ID_POINTER_X) + 0x7FFF) * (SCREEN_WIDTH * 16) as i32
+                / 0xFFFF;
+            let y = (pointer(retro::DEVICE_ID_POINTER_Y) + 0x7FFF) * (SCREEN_HEIGHT * 32) as i32
+                / 0xFFFF
+                - (SCREEN_HEIGHT * 16) as i32;
+            (y >= 0).then(|| {
+                [
+                    x.clamp(0, (SCREEN_WIDTH * 16 - 1) as i32) as u16,
+                    y.min((SCREEN_HEIGHT * 16 - 1) as i32) as u16,
+                ]
+            })
+        } else {
+            None
+        };
+        match touch_pos {
+            Some(pos) => self.emu.set_touch_pos(pos),
+            None => self.emu.end_touch(),
+        }
+    }
+
+    fn run_frame(&mut self) {
+        let callbacks = callbacks();
+
+        if let Some(input_poll) = callbacks.input_poll {
+            unsafe { input_poll() };
+        }
+        if let Some(input_state) = callbacks.input_state {
+            self.update_input(input_state);
+        }
+
+        if let RunOutput::Shutdown = self.emu.run() {
+            unsafe { environment(retro::ENVIRONMENT_SHUTDOWN, ptr::null_mut()) };
+        }
+
+        if let Some(video_refresh) = callbacks.video_refresh {
+            // The framebuffer's pixels are stored as 0xAABBGGRR, while XRGB8888 expects 0xXXRRGGBB
+            for (dst, src) in self
+                .video_buffer
+                .iter_mut()
+                .zip(self.emu.gpu.renderer_2d().framebuffer().iter().flatten())
+            {
+                *dst = (src & 0xFF) << 16 | (src & 0xFF00) | (src >> 16 & 0xFF);
+            }
+            unsafe {
+                video_refresh(
+                    self.video_buffer.as_ptr() as *const c_void,
+                    SCREEN_WIDTH as c_uint,
+                    (SCREEN_HEIGHT * 2) as c_uint,
+                    SCREEN_WIDTH * 4,
+                );
+            }
+        }
+
+        let mut samples = self.audio_samples.borrow_mut();
+        if let Some(audio_sample_batch) = callbacks.audio_sample_batch {
+            let mut remaining = &samples[..];
+            while !remaining.is_empty() {
+                let written =
+                    unsafe { audio_sample_batch(remaining.as_ptr(), remaining.len() / 2) };
+                if written == 0 {
+                    break;
+                }
+                remaining = &remaining[(written * 2).min(remaining.len())..];
+            }
+        }
+        samples.clear();
+    }
+
+    fn savestate(&mut self) -> Option<Vec<u8>> {
+        let mut contents = Vec::new();
+        PersistentWriteSavestate::new(&mut contents)
+            .store(&mut self.emu)
+            .ok()?;
+        // Save files are managed separately by the frontend, so they're left out
+        Some(savestate::write(
+            &savestate::Header::new(self.model),
+            &contents,
+            None,
+            &[],
+        ))
+    }
+
+    fn serialize_size(&mut self) -> usize {
+        if self.serialize_size.is_none() {
+            let len = self.savestate().map_or(0, |savestate| savestate.len());
+            self.serialize_size = Some(len + SERIALIZE_SIZE_HEADROOM);
+        }
+        self.serialize_size.unwrap()
+    }
+
+    fn serialize(&mut self, output: &mut [u8]) -> bool {
+        let Some(savestate) = self.savestate() else {
+            print_log(retro::LOG_ERROR, "Couldn't create savestate.");
+            return false;
+        };
+        if savestate.len() > output.len() {
+            print_log(
+                retro::LOG_ERROR,
+                &format!(
+                    "Couldn't create savestate: it's {} B long, but only {} B are available.",
+                    savestate.len(),
+                    output.len()
+                ),
+            );
+            return false;
+        }
+        let (contents, padding) = output.split_at_mut(savestate.len());
+        contents.copy_from_slice(&savestate);
+        padding.fill(0);
+        true
+    }
+
+    fn unserialize(&mut self, data: &[u8]) -> bool {
+        let savestate = match savestate::read(data).and_then(|savestate| {
+            savestate.header.check_compatible(self.model)?;
+            Ok(savestate)
+        }) {
+            Ok(savestate) => savestate,
+            Err(err) => {
+                print_log(
+                    retro::LOG_ERROR,
+                    &format!("Couldn't load savestate: {err}."),
+                );
+                return false;
+            }
+        };
+        if PersistentReadSavestate::new(savestate.state)
+            .and_then(|mut reader| reader.load_into(&mut self.emu).map_err(drop))
+            .is_err()
+        {
+            print_log(
+                retro::LOG_ERROR,
+                &format!(
+                    "Couldn't load savestate: incompatible or corrupted data (created by version \
+                     {}).",
+                    savestate.header.core_version
+                ),
+            );
+            return false;
+        }
+        self.audio_samples.borrow_mut().clear();
+        true
+    }
+
+    fn memory(&mut self, id: c_uint) -> Option<&mut [u8]> {
+        match id {
+            retro::MEMORY_SAVE_RAM => {
+                Some(self.emu.ds_slot.spi.contents_mut()).filter(|contents| !contents.is_empty())
+            }
+            retro::MEMORY_SYSTEM_RAM => Some(unsafe {
+                slice::from_raw_parts_mut(
+                    self.emu.main_mem().as_mut_ptr(),
+                    self.emu.main_mem_mask().get() as usize + 1,
+                )
+            }),
+            _ => None,
+        }
+    }
+}
+
+fn with_core<T>(f: impl FnOnce(&mut Core) -> T) -> Option<T> {
+    CORE.with_borrow_mut(|core| core.as_mut().map(f))
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    retro::API_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(environment: retro::EnvironmentFn) {
+    let mut log_callback = retro::LogCallback { log: None };
+    let log = unsafe {
+        environment(
+            retro::ENVIRONMENT_GET_LOG_INTERFACE,
+            &mut log_callback as *mut _ as *mut c_void,
+        )
+    }
+    .then_some(log_callback.log)
+    .flatten();
+    update_callbacks(|callbacks| {
+        callbacks.environment = Some(environment);
+        callbacks.log = log;
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(video_refresh: retro::VideoRefreshFn) {
+    update_callbacks(|callbacks| callbacks.video_refresh = Some(video_refresh));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_audio_sample: retro::AudioSampleFn) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(audio_sample_batch: retro::AudioSampleBatchFn) {
+    update_callbacks(|callbacks| callbacks.audio_sample_batch = Some(audio_sample_batch));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(input_poll: retro::InputPollFn) {
+    update_callbacks(|callbacks| callbacks.input_poll = Some(input_poll));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(input_state: retro::InputStateFn) {
+    update_callbacks(|callbacks| callbacks.input_state = Some(input_state));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    CORE.take();
+}
+
+/// # Safety
+/// `info` must point to a valid `retro_system_info` struct.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut retro::SystemInfo) {
+    info.write(retro::SystemInfo {
+        library_name: c"Dust".as_ptr(),
+        library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+        valid_extensions: c"nds".as_ptr(),
+        need_fullpath: false,
+        block_extract: false,
+    });
+}
+
+/// # Safety
+/// `info` must point to a valid `retro_system_av_info` struct.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut retro::SystemAvInfo) {
+    info.write(retro::SystemAvInfo {
+        geometry: retro::GameGeometry {
+            base_width: SCREEN_WIDTH as c_uint,
+            base_height: (SCREEN_HEIGHT * 2) as c_uint,
+            max_width: SCREEN_WIDTH as c_uint,
+            max_height: (SCREEN_HEIGHT * 2) as c_uint,
+            aspect_ratio: SCREEN_WIDTH as f32 / (SCREEN_HEIGHT * 2) as f32,
+        },
+        timing: retro::SystemTiming {
+            fps: FPS,
+            sample_rate: SAMPLE_RATE,
+        },
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    CORE.with_borrow_mut(|core| {
+        *core = core.take().and_then(|core| {
+            core.reset()
+                .map_err(|err| print_log(retro::LOG_ERROR, &err))
+                .ok()
+        });
+        if core.is_none() {
+            unsafe { environment(retro::ENVIRONMENT_SHUTDOWN, ptr::null_mut()) };
+        }
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    with_core(Core::run_frame);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    with_core(Core::serialize_size).unwrap_or(0)
+}
+
+/// # Safety
+/// `data` must be valid for writes of `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    with_core(|core| core.serialize(slice::from_raw_parts_mut(data as *mut u8, size)))
+        .unwrap_or(false)
+}
+
+/// # Safety
+/// `data` must be valid for reads of `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    with_core(|core| core.unserialize(slice::from_raw_parts(data as *const u8, size)))
+        .unwrap_or(false)
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+/// # Safety
+/// `game` must point to a valid `retro_game_info` struct, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const retro::GameInfo) -> bool {
+    let Some(game) = game.as_ref().filter(|game| !game.data.is_null()) else {
+        print_log(retro::LOG_ERROR, "No ROM data provided.");
+        return false;
+    };
+
+    let mut pixel_format = retro::PIXEL_FORMAT_XRGB8888;
+    if !environment(
+        retro::ENVIRONMENT_SET_PIXEL_FORMAT,
+        &mut pixel_format as *mut _ as *mut c_void,
+    ) {
+        print_log(retro::LOG_ERROR, "XRGB8888 is not supported.");
+        return false;
+    }
+
+    let rom_contents = slice::from_raw_parts(game.data as *const u8, game.size);
+    let rom_path = (!game.path.is_null())
+        .then(|| PathBuf::from(CStr::from_ptr(game.path).to_string_lossy().into_owned()));
+    match Core::new(rom_contents, rom_path.as_deref()) {
+        Ok(core) => {
+            CORE.set(Some(core));
+            true
+        }
+        Err(err) => {
+            print_log(retro::LOG_ERROR, &err);
+            false
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const retro::GameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    CORE.take();
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    retro::REGION_NTSC
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(id: c_uint) -> *mut c_void {
+    with_core(|core| {
+        core.memory(id)
+            .map_or(ptr::null_mut(), |memory| memory.as_mut_ptr() as *mut c_void)
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(id: c_uint) -> usize {
+    with_core(|core| core.memory(id).map_or(0, |memory| memory.len())).unwrap_or(0)
+}
@@ -0,0 +1,37 @@
+use crate::{print_log, retro};
+use slog::{Drain, Key, Level, Never, OwnedKVList, Record, KV};
+use std::fmt::{self, Write};
+
+/// Forwards the core's log messages to the frontend's log interface.
+pub struct RetroLog;
+
+struct Serializer<'a>(&'a mut String);
+
+impl slog::Serializer for Serializer<'_> {
+    fn emit_arguments(&mut self, key: Key, val: &fmt::Arguments) -> slog::Result {
+        let _ = write!(self.0, ", {key}: {val}");
+        Ok(())
+    }
+}
+
+impl Drain for RetroLog {
+    type Ok = ();
+    type Err = Never;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> Result<Self::Ok, Self::Err> {
+        let mut message = record.msg().to_string();
+        let mut serializer = Serializer(&mut message);
+        let _ = values.serialize(record, &mut serializer);
+        let _ = record.kv().serialize(record, &mut serializer);
+        print_log(
+            match record.level() {
+                Level::Critical | Level::Error => retro::LOG_ERROR,
+                Level::Warning => retro::LOG_WARN,
+                Level::Info => retro::LOG_INFO,
+                Level::Debug | Level::Trace => retro::LOG_DEBUG,
+            },
+            &message,
+        );
+        Ok(())
+    }
+}
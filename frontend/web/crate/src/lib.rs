@@ -4,22 +4,32 @@
 mod audio;
 #[cfg(feature = "log")]
 mod console_log;
+mod mic;
+#[cfg(feature = "soft-3d")]
 pub mod renderer_3d;
+#[cfg(not(feature = "soft-3d"))]
+#[path = "renderer_3d_dummy.rs"]
+pub mod renderer_3d;
+mod rtc;
 
 use dust_core::{
     cpu::{self, arm7, arm9, interpreter::Interpreter},
     ds_slot,
-    emu::{self, input::Keys, Emu},
+    emu::{self, input::Keys, savestate, Emu},
     flash::Flash,
     gpu::{SCREEN_HEIGHT, SCREEN_WIDTH},
-    rtc,
-    spi::firmware,
-    utils::{zeroed_box, BoxedByteSlice, Bytes},
-    Model, SaveContents,
+    spi::{firmware, tsc::MicBackend},
+    utils::{
+        zeroed_box, BoxedByteSlice, Bytes, PersistentReadSavestate, PersistentWriteSavestate,
+        ReadSavestate, WriteSavestate,
+    },
+    Model, SaveContents, SaveReloadContents,
 };
-use js_sys::{Function, Uint32Array, Uint8Array};
+use js_sys::{Float32Array, Function, Uint32Array, Uint8Array};
 use wasm_bindgen::prelude::*;
 
+const MAIN_MEM_START: u32 = 0x0200_0000;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[wasm_bindgen]
 pub enum SaveType {
@@ -99,31 +109,24 @@ pub struct EmuState {
     emu: Option<Emu<Interpreter>>,
     arm7_bios: Option<Box<Bytes<{ arm7::BIOS_SIZE }>>>,
     arm9_bios: Option<Box<Bytes<{ arm9::BIOS_SIZE }>>>,
+    mic_tx: mic::Sender,
+    rumble_callback: Option<Function>,
+    ram_writes_locked: bool,
 }
 
-fn build_emu<E: cpu::Engine>(emu_builder: emu::Builder, engine: E) -> emu::Emu<E> {
-    match emu_builder.build(engine) {
-        Ok(emu) => emu,
-        Err(err) => match err {
-            emu::BuildError::MissingRom => unreachable!("Missing DS slot ROM"),
-            emu::BuildError::MissingSysFiles => unreachable!("Missing emulator system files"),
-            emu::BuildError::RomCreation(err) => match err {
-                ds_slot::rom::normal::CreationError::InvalidSize => {
-                    unreachable!("Invalid DS slot ROM file size")
-                }
-            },
-            emu::BuildError::RomNeedsDecryptionButNoBiosProvided => {
-                panic!("Couldn't start emulator: ROM needs decryption but no BIOS provided.");
-            }
-        },
-    }
+fn build_emu<E: cpu::Engine>(emu_builder: emu::Builder, engine: E) -> Result<Emu<E>, JsError> {
+    emu_builder
+        .build(engine)
+        .map_err(|err| JsError::new(&format!("Couldn't start emulator: {err}.")))
 }
 
 // rust-analyzer needs this not to trigger a warning about generated function names
 #[allow(non_snake_case)]
 #[wasm_bindgen]
 impl EmuState {
-    pub fn reset(&mut self) {
+    pub fn reset(&mut self) -> Result<(), JsError> {
+        self.stop_rumble();
+
         let emu = self.emu.take().unwrap();
 
         let (renderer_2d, renderer_3d_tx) = emu.gpu.into_renderers();
@@ -133,7 +136,7 @@ impl EmuState {
             emu.ds_slot.rom.into_contents(),
             emu.ds_slot.spi.reset(),
             emu.audio.backend,
-            None,
+            emu.spi.tsc.mic_data.map(|mic_data| mic_data.backend),
             emu.rtc.backend,
             renderer_2d,
             renderer_3d_tx,
@@ -148,7 +151,8 @@ impl EmuState {
         emu_builder.model = self.model;
         emu_builder.direct_boot = true;
 
-        self.emu = Some(build_emu(emu_builder, Interpreter));
+        self.emu = Some(build_emu(emu_builder, Interpreter)?);
+        Ok(())
     }
 
     pub fn load_save(&mut self, ram_arr: Uint8Array) {
@@ -159,6 +163,65 @@ impl EmuState {
         Uint8Array::from(self.emu.as_ref().unwrap().ds_slot.spi.contents())
     }
 
+    /// Exports the save contents only if they were modified since the last time this was called.
+    pub fn export_save_if_dirty(&mut self) -> Option<Uint8Array> {
+        let spi = &mut self.emu.as_mut().unwrap().ds_slot.spi;
+        if !spi.contents_dirty() {
+            return None;
+        }
+        spi.mark_contents_flushed();
+        Some(Uint8Array::from(spi.contents()))
+    }
+
+    /// Creates a savestate of the current emulator state, including the current save file
+    /// contents.
+    pub fn save_state(&mut self) -> Result<Uint8Array, JsError> {
+        let emu = self.emu.as_mut().unwrap();
+        let mut contents = Vec::new();
+        PersistentWriteSavestate::new(&mut contents)
+            .store(emu)
+            .map_err(|_| JsError::new("Couldn't create savestate."))?;
+        let savestate = savestate::write(
+            &savestate::Header::new(self.model),
+            &contents,
+            Some(emu.ds_slot.spi.contents()),
+            &[],
+        );
+        Ok(Uint8Array::from(&savestate[..]))
+    }
+
+    /// Loads a savestate created by [`save_state`](Self::save_state), replacing the save file
+    /// contents with the ones stored in it, if any.
+    pub fn load_state(&mut self, savestate_arr: Uint8Array) -> Result<(), JsError> {
+        let data = savestate_arr.to_vec();
+        let savestate = savestate::read(&data)
+            .and_then(|savestate| {
+                savestate.header.check_compatible(self.model)?;
+                Ok(savestate)
+            })
+            .map_err(|err| JsError::new(&format!("Couldn't load savestate: {err}.")))?;
+
+        let emu = self.emu.as_mut().unwrap();
+        PersistentReadSavestate::new(savestate.state)
+            .and_then(|mut reader| reader.load_into(emu).map_err(drop))
+            .map_err(|()| {
+                JsError::new(&format!(
+                    "Couldn't load savestate: incompatible or corrupted data (created by version \
+                     {}).",
+                    savestate.header.core_version
+                ))
+            })?;
+        if let Some(save) = savestate.save {
+            let mut contents = BoxedByteSlice::new_zeroed(save.len());
+            contents.copy_from_slice(save);
+            emu.ds_slot
+                .spi
+                .reload_contents(SaveReloadContents::Existing(contents));
+        }
+        self.stop_rumble();
+        Ok(())
+    }
+
     pub fn update_input(&mut self, pressed: u32, released: u32) {
         let emu = self.emu.as_mut().unwrap();
         emu.press_keys(Keys::from_bits_truncate(pressed));
@@ -174,6 +237,80 @@ impl EmuState {
         }
     }
 
+    /// Pushes mono mic samples in the -1.0..=1.0 range (i.e. as captured from `getUserMedia`
+    /// through an `AudioWorklet`), recorded at `sample_rate` Hz.
+    pub fn push_mic_samples(&mut self, samples: Float32Array, sample_rate: f64) {
+        self.mic_tx.push_samples(&samples.to_vec(), sample_rate);
+    }
+
+    /// Silences the mic input; should be called when the browser stops capturing audio.
+    pub fn stop_mic(&mut self) {
+        self.mic_tx.clear();
+    }
+
+    /// Sets the function to be called with a boolean whenever rumble should start or stop (i.e.
+    /// to forward it to `GamepadHapticActuator.playEffect`).
+    pub fn set_rumble_callback(&mut self, callback: Option<Function>) {
+        self.stop_rumble();
+        self.rumble_callback = callback;
+    }
+
+    /// Reads `len` bytes of main RAM starting at `addr`, as seen by the ARM9 (i.e. starting at
+    /// 0x0200_0000); accesses reaching outside of main RAM, including its mirrors and I/O
+    /// registers, are rejected.
+    pub fn read_ram(&mut self, addr: u32, len: u32) -> Result<Uint8Array, JsError> {
+        self.check_ram_range(addr, len as usize)?;
+        let emu = self.emu.as_mut().unwrap();
+        Ok(Uint8Array::from(&emu.export_mem_range(true, addr, len)[..]))
+    }
+
+    /// Writes `bytes` to main RAM starting at `addr`, with the same restrictions as
+    /// [`read_ram`](Self::read_ram); fails if RAM writes were locked through
+    /// [`lock_ram_writes`](Self::lock_ram_writes).
+    pub fn write_ram(&mut self, addr: u32, bytes: Uint8Array) -> Result<(), JsError> {
+        if self.ram_writes_locked {
+            return Err(JsError::new("RAM writes are locked."));
+        }
+        let bytes = bytes.to_vec();
+        self.check_ram_range(addr, bytes.len())?;
+        let emu = self.emu.as_mut().unwrap();
+        emu.import_mem_range(true, addr, &bytes);
+        Ok(())
+    }
+
+    /// Makes all further [`write_ram`](Self::write_ram) calls fail for the lifetime of this
+    /// emulator instance (including across resets); this can't be undone, so that code granted
+    /// access to the instance afterwards can't modify RAM.
+    pub fn lock_ram_writes(&mut self) {
+        self.ram_writes_locked = true;
+    }
+
+    pub fn ram_writes_locked(&self) -> bool {
+        self.ram_writes_locked
+    }
+
+    /// Returns the offset of the emulated RTC from the browser's local time, in seconds, which
+    /// changes whenever the game sets the time.
+    pub fn rtc_time_offset_seconds(&self) -> f64 {
+        self.rtc_backend().time_offset_seconds()
+    }
+
+    pub fn set_rtc_time_offset_seconds(&mut self, value: f64) {
+        self.rtc_backend_mut().set_time_offset_seconds(value);
+    }
+
+    /// Returns the time the emulated RTC is frozen at, in milliseconds since the Unix epoch (i.e.
+    /// as returned by `Date.getTime()`), if frozen.
+    pub fn rtc_frozen_time_ms(&self) -> Option<f64> {
+        self.rtc_backend().frozen_time_ms()
+    }
+
+    /// Freezes the emulated RTC at the given time in milliseconds since the Unix epoch, or makes
+    /// it follow the browser's local time again if `undefined`.
+    pub fn set_rtc_frozen_time_ms(&mut self, value: Option<f64>) {
+        self.rtc_backend_mut().set_frozen_time_ms(value);
+    }
+
     pub fn run_frame(&mut self) -> Uint32Array {
         // TODO: Handle an eventual shutdown
         let emu = self.emu.as_mut().unwrap();
@@ -187,6 +324,55 @@ impl EmuState {
     }
 }
 
+impl EmuState {
+    fn check_ram_range(&self, addr: u32, len: usize) -> Result<(), JsError> {
+        let main_mem_len = self.emu.as_ref().unwrap().main_mem_mask().get() as usize + 1;
+        let in_range = addr
+            .checked_sub(MAIN_MEM_START)
+            .and_then(|offset| (offset as usize).checked_add(len))
+            .is_some_and(|end| end <= main_mem_len);
+        if in_range {
+            Ok(())
+        } else {
+            Err(JsError::new(&format!(
+                "Invalid RAM range: {len} B at {addr:#010X} would reach outside of main RAM \
+                 ({MAIN_MEM_START:#010X}-{:#010X}).",
+                MAIN_MEM_START as usize + main_mem_len - 1
+            )))
+        }
+    }
+
+    fn rtc_backend(&self) -> &rtc::Backend {
+        self.emu
+            .as_ref()
+            .unwrap()
+            .rtc
+            .backend
+            .as_any()
+            .downcast_ref()
+            .unwrap()
+    }
+
+    fn rtc_backend_mut(&mut self) -> &mut rtc::Backend {
+        self.emu
+            .as_mut()
+            .unwrap()
+            .rtc
+            .backend
+            .as_any_mut()
+            .downcast_mut()
+            .unwrap()
+    }
+
+    // TODO: Also drive the rumble callback from the emulated Rumble Pak once GBA slot accessories
+    //       are supported; for now, this only makes sure no rumble outlives the emulated state.
+    fn stop_rumble(&self) {
+        if let Some(callback) = &self.rumble_callback {
+            let _ = callback.call1(&JsValue::UNDEFINED, &JsValue::FALSE);
+        }
+    }
+}
+
 // Wasm-bindgen creates invalid output using a constructor, for some reason
 #[wasm_bindgen]
 #[allow(clippy::too_many_arguments)]
@@ -200,7 +386,8 @@ pub fn create_emu_state(
     has_ir: bool,
     model: WbgModel,
     audio_callback: Function,
-) -> EmuState {
+) -> Result<EmuState, JsError> {
+    #[cfg(feature = "panic-hook")]
     console_error_panic_hook::set_once();
 
     #[cfg(feature = "log")]
@@ -230,9 +417,6 @@ pub fn create_emu_state(
 
     let mut rom = BoxedByteSlice::new_zeroed(rom_arr.length().next_power_of_two() as usize);
     rom_arr.copy_to(&mut rom[..rom_arr.length() as usize]);
-    if !ds_slot::rom::is_valid_size(rom.len() as u64, model) {
-        panic!("Invalid ROM size");
-    }
 
     let save_contents = save_contents_arr.map(|save_contents_arr| {
         let mut save_contents = BoxedByteSlice::new_zeroed(save_contents_arr.length() as usize);
@@ -323,8 +507,7 @@ pub fn create_emu_state(
                     #[cfg(feature = "log")]
                     logger.new(slog::o!("ds_spi" => "eeprom_4k")),
                 )
-                // NOTE: The save contents' size is ensured beforehand, this should never occur.
-                .expect("couldn't create 4 Kib EEPROM DS slot SPI device")
+                .map_err(|err| JsError::new(&format!("Couldn't create the save chip: {err}.")))?
                 .into(),
                 SaveType::EepromFram64k | SaveType::EepromFram512k | SaveType::EepromFram1m => {
                     ds_slot::spi::eeprom_fram::EepromFram::new(
@@ -333,8 +516,7 @@ pub fn create_emu_state(
                         #[cfg(feature = "log")]
                         logger.new(slog::o!("ds_spi" => "eeprom_fram")),
                     )
-                    // NOTE: The save contents' size is ensured beforehand, this should never occur.
-                    .expect("couldn't create EEPROM/FRAM DS slot SPI device")
+                    .map_err(|err| JsError::new(&format!("Couldn't create the save chip: {err}.")))?
                     .into()
                 }
                 SaveType::Flash2m | SaveType::Flash4m | SaveType::Flash8m => {
@@ -343,22 +525,18 @@ pub fn create_emu_state(
                         [0; 20],
                         has_ir,
                         #[cfg(feature = "log")]
-                        logger.new(slog::o!("ds_spi" => if has_ir { "flash" } else { "flash_ir" })),
+                        logger.new(slog::o!("ds_spi" => if has_ir { "flash_ir" } else { "flash" })),
                     )
-                    // NOTE: The save contents' size is ensured beforehand, this should never occur.
-                    .expect("couldn't create FLASH DS slot SPI device")
+                    .map_err(|err| JsError::new(&format!("Couldn't create the save chip: {err}.")))?
                     .into()
                 }
                 SaveType::Nand64m | SaveType::Nand128m | SaveType::Nand256m => {
-                    #[cfg(feature = "log")]
-                    slog::error!(
-                        logger,
-                        "TODO: NAND saves are currently unsupported, falling back to no save file."
-                    );
-                    ds_slot::spi::Empty::new(
+                    ds_slot::spi::nand::Nand::new(
+                        save_contents,
                         #[cfg(feature = "log")]
-                        logger.new(slog::o!("ds_spi" => "nand_todo")),
+                        logger.new(slog::o!("ds_spi" => "nand")),
                     )
+                    .map_err(|err| JsError::new(&format!("Couldn't create the save chip: {err}.")))?
                     .into()
                 }
             }
@@ -366,6 +544,7 @@ pub fn create_emu_state(
     };
 
     let (tx_3d, rx_3d) = renderer_3d::init();
+    let (mic_tx, mic_rx) = mic::channel();
 
     let mut emu_builder = emu::Builder::new(
         Flash::new(
@@ -374,12 +553,12 @@ pub fn create_emu_state(
             #[cfg(feature = "log")]
             logger.new(slog::o!("fw" => "")),
         )
-        .expect("couldn't build firmware"),
+        .map_err(|err| JsError::new(&format!("Couldn't load firmware: {err}.")))?,
         Some(Box::new(rom)),
         ds_slot_spi,
         Box::new(audio::Backend::new(audio_callback)),
-        None,
-        Box::new(rtc::DummyBackend),
+        Some(Box::new(mic_rx) as Box<dyn MicBackend>),
+        Box::new(rtc::Backend::new()),
         Box::new(dust_soft_2d::sync::Renderer::new(Box::new(rx_3d))),
         Box::new(tx_3d),
         None,
@@ -393,16 +572,19 @@ pub fn create_emu_state(
     emu_builder.model = model;
     emu_builder.direct_boot = true;
 
-    let emu = build_emu(emu_builder, Interpreter);
+    let emu = build_emu(emu_builder, Interpreter)?;
 
-    EmuState {
+    Ok(EmuState {
         #[cfg(feature = "log")]
         logger,
         model,
         emu: Some(emu),
         arm7_bios,
         arm9_bios,
-    }
+        mic_tx,
+        rumble_callback: None,
+        ram_writes_locked: false,
+    })
 }
 
 #[wasm_bindgen]
@@ -0,0 +1,91 @@
+use dust_core::spi::tsc::{MicBackend, MIC_SAMPLES_PER_FRAME};
+use std::{cell::RefCell, rc::Rc};
+
+/// The rate the touchscreen controller samples the mic at, once every 128 cycles.
+const OUTPUT_SAMPLE_RATE: f64 = (1 << 25) as f64 / 128.0;
+
+struct Buffer {
+    write_pos: usize,
+    data: Box<[i16; MIC_SAMPLES_PER_FRAME]>,
+}
+
+/// The JS-facing end of the mic input, resampling the samples pushed from the browser (i.e. from
+/// an `AudioWorklet` fed by `getUserMedia`) to the rate expected by the core.
+pub struct Sender {
+    buffer: Rc<RefCell<Buffer>>,
+    last_sample: f32,
+    fract: f64,
+}
+
+impl Sender {
+    pub fn push_samples(&mut self, samples: &[f32], sample_rate: f64) {
+        let sample_rate_ratio = sample_rate / OUTPUT_SAMPLE_RATE;
+        let mut buffer = self.buffer.borrow_mut();
+        let mut write_pos = buffer.write_pos;
+        let mut fract = self.fract;
+        for &sample in samples {
+            while fract < 1.0 {
+                let result = self.last_sample + (sample - self.last_sample) * fract as f32;
+                buffer.data[write_pos] = (result * 32768.0).clamp(-32768.0, 32767.0) as i16;
+                write_pos += 1;
+                if write_pos >= MIC_SAMPLES_PER_FRAME {
+                    write_pos = 0;
+                }
+                fract += sample_rate_ratio;
+            }
+            fract -= 1.0;
+            self.last_sample = sample;
+        }
+        buffer.write_pos = write_pos;
+        self.fract = fract;
+    }
+
+    /// Silences the mic input, to avoid replaying the last buffered samples after the browser
+    /// stops providing new ones.
+    pub fn clear(&mut self) {
+        self.buffer.borrow_mut().data.fill(0);
+        self.last_sample = 0.0;
+        self.fract = 0.0;
+    }
+}
+
+pub struct Receiver {
+    buffer: Rc<RefCell<Buffer>>,
+    frame_start_i: usize,
+}
+
+impl MicBackend for Receiver {
+    fn start_frame(&mut self) {
+        self.frame_start_i = self.buffer.borrow().write_pos;
+    }
+
+    fn read_frame_samples(&mut self, offset: usize, samples: &mut [i16]) {
+        let buffer = self.buffer.borrow();
+        let mut i = (self.frame_start_i + offset) % MIC_SAMPLES_PER_FRAME;
+        for sample in samples {
+            *sample = buffer.data[i];
+            i += 1;
+            if i >= MIC_SAMPLES_PER_FRAME {
+                i = 0;
+            }
+        }
+    }
+}
+
+pub fn channel() -> (Sender, Receiver) {
+    let buffer = Rc::new(RefCell::new(Buffer {
+        write_pos: 0,
+        data: unsafe { Box::new_zeroed().assume_init() },
+    }));
+    (
+        Sender {
+            buffer: Rc::clone(&buffer),
+            last_sample: 0.0,
+            fract: 0.0,
+        },
+        Receiver {
+            buffer,
+            frame_start_i: 0,
+        },
+    )
+}
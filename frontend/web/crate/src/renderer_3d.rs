@@ -1,10 +1,14 @@
+//! The 3D renderers, running on a dedicated web worker (started through [`run_worker`]) and
+//! handing scanlines back to the emulation one through shared memory.
+//!
+//! The software renderer is always available; with the `webgpu-3d` feature, a WebGPU one can be
+//! picked through [`set_renderer_3d_kind`] instead (see the `webgpu` module).
+
 #![allow(unused_unsafe)]
 
 use dust_core::{
     gpu::{
-        engine_3d::{
-            Polygon, RendererTx, RenderingState as CoreRenderingState, ScreenVertex, SoftRendererRx,
-        },
+        engine_3d::{GxSnapshot, RendererTx, RenderingState as CoreRenderingState, SoftRendererRx},
         Scanline, SCREEN_HEIGHT,
     },
     utils::mem_prelude::*,
@@ -15,11 +19,24 @@ use std::{
     hint,
     sync::{
         atomic::{AtomicBool, AtomicU8, Ordering},
-        OnceLock,
+        Arc, OnceLock,
     },
 };
 use wasm_bindgen::prelude::*;
 
+#[cfg(feature = "webgpu-3d")]
+mod webgpu;
+
+/// The 3D renderers that can be picked through [`set_renderer_3d_kind`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum Renderer3dKind {
+    Software,
+    /// Only available with the `webgpu-3d` feature and in browsers that support WebGPU; the
+    /// software renderer is used otherwise.
+    WebGpu,
+}
+
 static SHARED_DATA: OnceLock<SharedData> = OnceLock::new();
 
 macro_rules! shared_data {
@@ -29,17 +46,37 @@ macro_rules! shared_data {
 }
 
 struct SharedData {
+    kind: AtomicU8,
+    resolution_scale_shift: AtomicU8,
     rendering_data: Box<UnsafeCell<RenderingData>>,
+    #[cfg(feature = "webgpu-3d")]
+    webgpu_frame_data: Box<UnsafeCell<dust_wgpu_3d::FrameData>>,
     scanline_buffer: Box<UnsafeCell<[Scanline<u32>; SCREEN_HEIGHT]>>,
     processing_scanline: AtomicU8,
     stopped: AtomicBool,
+    worker_running: AtomicBool,
+    worker_requested: AtomicBool,
 }
 
 unsafe impl Sync for SharedData {}
 
-pub struct Tx;
+pub struct Tx {
+    #[cfg(feature = "webgpu-3d")]
+    webgpu: webgpu::TxState,
+}
 
 impl Tx {
+    #[cfg(feature = "webgpu-3d")]
+    fn uses_webgpu(&self) -> bool {
+        shared_data!().kind.load(Ordering::Relaxed) == Renderer3dKind::WebGpu as u8
+    }
+
+    fn request_frame(&self) {
+        shared_data!()
+            .processing_scanline
+            .store(u8::MAX, Ordering::Release);
+    }
+
     fn wait_for_frame_end(&self) {
         while {
             let processing_scanline = shared_data!().processing_scanline.load(Ordering::Acquire);
@@ -53,18 +90,23 @@ impl Tx {
 impl RendererTx for Tx {
     fn set_capture_enabled(&mut self, _capture_enabled: bool) {}
 
-    fn swap_buffers(
-        &mut self,
-        vert_ram: &[ScreenVertex],
-        poly_ram: &[Polygon],
-        state: &CoreRenderingState,
-    ) {
+    fn swap_buffers(&mut self, gx: &Arc<GxSnapshot>, state: &CoreRenderingState) {
         self.wait_for_frame_end();
-        unsafe { &mut *shared_data!().rendering_data.get() }.prepare(vert_ram, poly_ram, state);
+        #[cfg(feature = "webgpu-3d")]
+        if self.uses_webgpu() {
+            let frame_data = unsafe { &mut *shared_data!().webgpu_frame_data.get() };
+            return self.webgpu.swap_buffers(frame_data, gx, state);
+        }
+        unsafe { &mut *shared_data!().rendering_data.get() }.prepare(gx, state);
     }
 
     fn repeat_last_frame(&mut self, state: &CoreRenderingState) {
         self.wait_for_frame_end();
+        #[cfg(feature = "webgpu-3d")]
+        if self.uses_webgpu() {
+            let frame_data = unsafe { &mut *shared_data!().webgpu_frame_data.get() };
+            return self.webgpu.repeat_last_frame(frame_data, state);
+        }
         unsafe { &mut *shared_data!().rendering_data.get() }.repeat_last_frame(state);
     }
 
@@ -74,11 +116,24 @@ impl RendererTx for Tx {
         tex_pal: &Bytes<0x1_8000>,
         state: &CoreRenderingState,
     ) {
-        unsafe { &mut *shared_data!().rendering_data.get() }.copy_vram(texture, tex_pal, state);
+        // The worker is only started once the game first uses the 3D engine, until then frames
+        // are skipped (and read back as blank scanlines)
+        if !shared_data!().worker_running.load(Ordering::Acquire) {
+            shared_data!()
+                .worker_requested
+                .store(true, Ordering::Relaxed);
+            return;
+        }
 
-        shared_data!()
-            .processing_scanline
-            .store(u8::MAX, Ordering::Release);
+        #[cfg(feature = "webgpu-3d")]
+        if self.uses_webgpu() {
+            let frame_data = unsafe { &mut *shared_data!().webgpu_frame_data.get() };
+            self.webgpu
+                .start_rendering(frame_data, texture, tex_pal, state);
+            return self.request_frame();
+        }
+        unsafe { &mut *shared_data!().rendering_data.get() }.copy_vram(texture, tex_pal, state);
+        self.request_frame();
     }
 
     fn skip_rendering(&mut self) {}
@@ -126,19 +181,96 @@ impl SoftRendererRx for Rx {
 pub fn init() -> (Tx, Rx) {
     SHARED_DATA.get_or_init(|| unsafe {
         SharedData {
+            kind: AtomicU8::new(Renderer3dKind::Software as u8),
+            resolution_scale_shift: AtomicU8::new(0),
             rendering_data: Box::new_zeroed().assume_init(),
+            #[cfg(feature = "webgpu-3d")]
+            webgpu_frame_data: Box::new_zeroed().assume_init(),
             scanline_buffer: Box::new_zeroed().assume_init(),
             processing_scanline: AtomicU8::new(SCREEN_HEIGHT as u8),
             stopped: AtomicBool::new(false),
+            worker_running: AtomicBool::new(false),
+            worker_requested: AtomicBool::new(false),
         }
     });
-    (Tx, Rx { next_scanline: 0 })
+    (
+        Tx {
+            #[cfg(feature = "webgpu-3d")]
+            webgpu: webgpu::TxState::new(),
+        },
+        Rx { next_scanline: 0 },
+    )
+}
+
+/// Picks the 3D renderer to use; only takes effect if called before the renderer worker is
+/// started.
+#[wasm_bindgen]
+pub fn set_renderer_3d_kind(kind: Renderer3dKind) {
+    let Some(shared_data) = SHARED_DATA.get() else {
+        return;
+    };
+    if shared_data.worker_running.load(Ordering::Acquire) {
+        return;
+    }
+    let kind = if cfg!(feature = "webgpu-3d") {
+        kind
+    } else {
+        Renderer3dKind::Software
+    };
+    shared_data.kind.store(kind as u8, Ordering::Relaxed);
 }
 
+/// Sets the factor to scale the 3D output's resolution by, as a power of two; only used by the
+/// WebGPU renderer, which supersamples its output back down to the DS's resolution as the 2D
+/// engines are still composited in software.
+#[wasm_bindgen]
+pub fn set_resolution_scale_shift(value: u8) {
+    if let Some(shared_data) = SHARED_DATA.get() {
+        shared_data
+            .resolution_scale_shift
+            .store(value.min(3), Ordering::Relaxed);
+    }
+}
+
+/// Whether the renderer worker needs to be started, as the emulated game has started using the 3D
+/// engine.
+#[wasm_bindgen]
+pub fn renderer_3d_requested() -> bool {
+    SHARED_DATA.get().map_or(false, |shared_data| {
+        shared_data.worker_requested.load(Ordering::Relaxed)
+    })
+}
+
+#[cfg(not(feature = "webgpu-3d"))]
 #[wasm_bindgen]
 pub fn run_worker() {
+    run_software_worker(shared_data!());
+}
+
+/// Runs the chosen renderer until the emulator is stopped; if WebGPU isn't available, the software
+/// renderer is used instead.
+#[cfg(feature = "webgpu-3d")]
+#[wasm_bindgen]
+pub async fn run_worker() {
     let shared_data = shared_data!();
+    if shared_data.kind.load(Ordering::Relaxed) == Renderer3dKind::WebGpu as u8 {
+        if let Some(renderer) = webgpu::Renderer::new().await {
+            shared_data.worker_running.store(true, Ordering::Release);
+            return renderer.run(shared_data).await;
+        }
+        web_sys::console::warn_1(
+            &"WebGPU isn't available, falling back to the software 3D renderer".into(),
+        );
+        shared_data
+            .kind
+            .store(Renderer3dKind::Software as u8, Ordering::Relaxed);
+    }
+    run_software_worker(shared_data);
+}
+
+fn run_software_worker(shared_data: &SharedData) {
     let mut raw_renderer = Renderer::new();
+    shared_data.worker_running.store(true, Ordering::Release);
     loop {
         loop {
             if shared_data.stopped.load(Ordering::Relaxed) {
@@ -0,0 +1,261 @@
+//! The WebGPU 3D renderer, running `dust-wgpu-3d` on the renderer worker through wgpu's WebGPU
+//! backend.
+//!
+//! As the 2D engines are still composited in software on the emulation worker, each frame is read
+//! back from the GPU; when rendering at a higher resolution, it's averaged back down to the DS's
+//! one, so upscaling only results in supersampled 3D graphics.
+
+use super::SharedData;
+use dust_core::{
+    gpu::{
+        engine_3d::{GxSnapshot, RenderingState as CoreRenderingState},
+        Scanline, SCREEN_HEIGHT, SCREEN_WIDTH,
+    },
+    utils::mem_prelude::*,
+};
+use dust_wgpu_3d::{FrameData, TextureFiltering};
+use std::{
+    mem,
+    sync::{atomic::Ordering, Arc, OnceLock},
+};
+use wasm_bindgen::JsCast;
+
+/// The emulation worker's side of the renderer, filling in the shared frame data.
+pub(super) struct TxState {
+    texture_dirty: u8,
+    tex_pal_dirty: u8,
+}
+
+impl TxState {
+    pub(super) fn new() -> Self {
+        // Nothing has been copied to the frame data yet, so the first frame needs all of VRAM
+        TxState {
+            texture_dirty: 0xF,
+            tex_pal_dirty: 0x3F,
+        }
+    }
+
+    pub(super) fn swap_buffers(
+        &mut self,
+        frame_data: &mut FrameData,
+        gx: &Arc<GxSnapshot>,
+        state: &CoreRenderingState,
+    ) {
+        frame_data.gx.prepare(gx, state);
+        frame_data.rendering.prepare(state);
+    }
+
+    pub(super) fn repeat_last_frame(
+        &mut self,
+        frame_data: &mut FrameData,
+        state: &CoreRenderingState,
+    ) {
+        frame_data.rendering.prepare(state);
+    }
+
+    pub(super) fn start_rendering(
+        &mut self,
+        frame_data: &mut FrameData,
+        texture: &Bytes<0x8_0000>,
+        tex_pal: &Bytes<0x1_8000>,
+        state: &CoreRenderingState,
+    ) {
+        frame_data.rendering.copy_vram(
+            texture,
+            tex_pal,
+            mem::replace(&mut self.texture_dirty, 0) | state.texture_dirty,
+            mem::replace(&mut self.tex_pal_dirty, 0) | state.tex_pal_dirty,
+        );
+    }
+}
+
+fn output_row_len(resolution_scale_shift: u8) -> u32 {
+    // Always a multiple of the required 256-byte alignment
+    (SCREEN_WIDTH as u32 * 4) << resolution_scale_shift
+}
+
+fn create_readback_buffer(device: &wgpu::Device, resolution_scale_shift: u8) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("3D renderer readback"),
+        size: (output_row_len(resolution_scale_shift) as u64 * SCREEN_HEIGHT as u64)
+            << resolution_scale_shift,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    })
+}
+
+/// Yields to the worker's event loop, which needs to run for WebGPU to report finished work.
+async fn yield_to_event_loop() {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let _ = js_sys::global()
+            .unchecked_into::<web_sys::WorkerGlobalScope>()
+            .set_timeout_with_callback(&resolve);
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+/// Averages each `2^resolution_scale_shift`-sized square of RGBA8 pixels, converting it to the
+/// RGB6 + 5-bit alpha format the software 2D renderer expects.
+fn downsample(
+    data: &[u8],
+    resolution_scale_shift: u8,
+    scanlines: &mut [Scanline<u32>; SCREEN_HEIGHT],
+) {
+    let scale = 1 << resolution_scale_shift;
+    let row_len = output_row_len(resolution_scale_shift) as usize;
+    for (y, scanline) in scanlines.iter_mut().enumerate() {
+        for (x, pixel) in scanline.0.iter_mut().enumerate() {
+            let mut sums = [0_u32; 4];
+            for sub_y in 0..scale {
+                let row = &data[((y << resolution_scale_shift) + sub_y) * row_len..];
+                for sub_x in 0..scale {
+                    let i = ((x << resolution_scale_shift) + sub_x) * 4;
+                    for (sum, &value) in sums.iter_mut().zip(&row[i..i + 4]) {
+                        *sum += value as u32;
+                    }
+                }
+            }
+            let [r, g, b, a] = sums.map(|sum| sum >> (resolution_scale_shift << 1));
+            let rgb6 = |value: u32| (value * 63 + 127) / 255;
+            *pixel = rgb6(r) | rgb6(g) << 6 | rgb6(b) << 12 | ((a * 31 + 127) / 255) << 18;
+        }
+    }
+}
+
+pub(super) struct Renderer {
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    raw: dust_wgpu_3d::Renderer,
+    readback_buffer: wgpu::Buffer,
+}
+
+impl Renderer {
+    /// Creates the renderer, returning `None` if the browser doesn't support WebGPU or no suitable
+    /// adapter is available.
+    pub(super) async fn new() -> Option<Self> {
+        if !wgpu::util::is_browser_webgpu_supported().await {
+            return None;
+        }
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::BROWSER_WEBGPU,
+            ..Default::default()
+        });
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions::default())
+            .await?;
+        let adapter_limits = adapter.limits();
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("3D renderer device"),
+                    required_features: wgpu::Features::empty(),
+                    required_limits: wgpu::Limits {
+                        max_texture_dimension_2d: adapter_limits.max_texture_dimension_2d.min(4096),
+                        max_bind_groups: adapter_limits.max_bind_groups.min(5),
+                        ..wgpu::Limits::downlevel_defaults()
+                    },
+                    memory_hints: wgpu::MemoryHints::MemoryUsage,
+                },
+                None,
+            )
+            .await
+            .ok()?;
+        let device = Arc::new(device);
+        let queue = Arc::new(queue);
+        let raw = dust_wgpu_3d::Renderer::new(
+            Arc::clone(&device),
+            Arc::clone(&queue),
+            0,
+            TextureFiltering::default(),
+            false,
+        );
+        let readback_buffer = create_readback_buffer(&device, 0);
+        Some(Renderer {
+            device,
+            queue,
+            raw,
+            readback_buffer,
+        })
+    }
+
+    /// Renders frames as the emulation worker requests them, until it's stopped.
+    pub(super) async fn run(mut self, shared_data: &SharedData) {
+        loop {
+            if shared_data.stopped.load(Ordering::Relaxed) {
+                return;
+            }
+            if shared_data.processing_scanline.load(Ordering::Acquire) != u8::MAX {
+                yield_to_event_loop().await;
+                continue;
+            }
+            shared_data.processing_scanline.store(0, Ordering::Relaxed);
+
+            let resolution_scale_shift = shared_data.resolution_scale_shift.load(Ordering::Relaxed);
+            if resolution_scale_shift != self.raw.resolution_scale_shift() {
+                self.raw.set_resolution_scale_shift(resolution_scale_shift);
+                self.readback_buffer = create_readback_buffer(&self.device, resolution_scale_shift);
+            }
+
+            // The frame data is only accessed here, the emulation worker won't touch it until the
+            // frame is marked as finished below
+            let frame_data = unsafe { &*shared_data.webgpu_frame_data.get() };
+            let render_commands = self.raw.render_frame(frame_data);
+            let mut command_encoder =
+                self.device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                        label: Some("3D renderer readback command encoder"),
+                    });
+            command_encoder.copy_texture_to_buffer(
+                self.raw.output_texture().as_image_copy(),
+                wgpu::ImageCopyBuffer {
+                    buffer: &self.readback_buffer,
+                    layout: wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(output_row_len(resolution_scale_shift)),
+                        rows_per_image: None,
+                    },
+                },
+                wgpu::Extent3d {
+                    width: (SCREEN_WIDTH as u32) << resolution_scale_shift,
+                    height: (SCREEN_HEIGHT as u32) << resolution_scale_shift,
+                    depth_or_array_layers: 1,
+                },
+            );
+            self.queue
+                .submit([render_commands, command_encoder.finish()]);
+            self.raw.frames_submitted();
+
+            let scanlines = unsafe { &mut *shared_data.scanline_buffer.get() };
+            self.read_back(scanlines).await;
+            shared_data
+                .processing_scanline
+                .store(SCREEN_HEIGHT as u8, Ordering::Release);
+        }
+    }
+
+    /// Waits for the last frame to be copied to the readback buffer and downsamples it into
+    /// `scanlines`; if mapping the buffer fails, the previous frame's scanlines are kept.
+    async fn read_back(&self, scanlines: &mut [Scanline<u32>; SCREEN_HEIGHT]) {
+        let slice = self.readback_buffer.slice(..);
+        let mapped = Arc::new(OnceLock::new());
+        let mapped_ = Arc::clone(&mapped);
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = mapped_.set(result.is_ok());
+        });
+        let mapped = loop {
+            if let Some(&mapped) = mapped.get() {
+                break mapped;
+            }
+            yield_to_event_loop().await;
+        };
+        if !mapped {
+            return;
+        }
+        downsample(
+            &slice.get_mapped_range(),
+            self.raw.resolution_scale_shift(),
+            scanlines,
+        );
+        self.readback_buffer.unmap();
+    }
+}
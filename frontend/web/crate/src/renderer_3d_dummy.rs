@@ -0,0 +1,76 @@
+//! Stand-in for the software 3D renderer when the `soft-3d` feature is disabled, leaving 3D
+//! graphics blank; it exposes the same interface, so no renderer worker is ever requested.
+
+use dust_core::{
+    gpu::{
+        engine_3d::{GxSnapshot, RendererTx, RenderingState as CoreRenderingState, SoftRendererRx},
+        Scanline,
+    },
+    utils::mem_prelude::*,
+};
+use std::sync::Arc;
+use wasm_bindgen::prelude::*;
+
+pub struct Tx;
+
+impl RendererTx for Tx {
+    fn set_capture_enabled(&mut self, _capture_enabled: bool) {}
+
+    fn swap_buffers(&mut self, _gx: &Arc<GxSnapshot>, _state: &CoreRenderingState) {}
+
+    fn repeat_last_frame(&mut self, _state: &CoreRenderingState) {}
+
+    fn start_rendering(
+        &mut self,
+        _texture: &Bytes<0x8_0000>,
+        _tex_pal: &Bytes<0x1_8000>,
+        _state: &CoreRenderingState,
+    ) {
+    }
+
+    fn skip_rendering(&mut self) {}
+}
+
+pub struct Rx {
+    scanline: Box<Scanline<u32>>,
+}
+
+impl SoftRendererRx for Rx {
+    fn start_frame(&mut self) {}
+
+    fn read_scanline(&mut self) -> &Scanline<u32> {
+        &self.scanline
+    }
+
+    fn skip_scanline(&mut self) {}
+}
+
+pub fn init() -> (Tx, Rx) {
+    (
+        Tx,
+        Rx {
+            scanline: unsafe { Box::new_zeroed().assume_init() },
+        },
+    )
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum Renderer3dKind {
+    Software,
+    WebGpu,
+}
+
+#[wasm_bindgen]
+pub fn set_renderer_3d_kind(_kind: Renderer3dKind) {}
+
+#[wasm_bindgen]
+pub fn set_resolution_scale_shift(_value: u8) {}
+
+#[wasm_bindgen]
+pub fn renderer_3d_requested() -> bool {
+    false
+}
+
+#[wasm_bindgen]
+pub fn run_worker() {}
@@ -0,0 +1,112 @@
+use core::any::Any;
+use dust_core::rtc::{self, Date, Time};
+use js_sys::Date as JsDate;
+use wasm_bindgen::JsValue;
+
+/// An RTC backend following the browser's local time with a configurable offset, which can also
+/// be frozen at a fixed point in time.
+pub struct Backend {
+    time_offset_ms: f64,
+    /// The frozen time, in milliseconds since the Unix epoch (as returned by `Date.getTime()`).
+    frozen_time_ms: Option<f64>,
+}
+
+impl Backend {
+    pub fn new() -> Self {
+        Backend {
+            time_offset_ms: 0.0,
+            frozen_time_ms: None,
+        }
+    }
+
+    pub fn time_offset_seconds(&self) -> f64 {
+        (self.time_offset_ms / 1000.0).trunc()
+    }
+
+    pub fn set_time_offset_seconds(&mut self, value: f64) {
+        self.time_offset_ms = value.trunc() * 1000.0;
+    }
+
+    pub fn frozen_time_ms(&self) -> Option<f64> {
+        self.frozen_time_ms
+    }
+
+    pub fn set_frozen_time_ms(&mut self, value: Option<f64>) {
+        self.frozen_time_ms = value;
+    }
+
+    fn date_time(&self) -> JsDate {
+        let time_ms = self
+            .frozen_time_ms
+            .unwrap_or_else(|| JsDate::now() + self.time_offset_ms);
+        JsDate::new(&JsValue::from_f64(time_ms))
+    }
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn time(date_time: &JsDate) -> Time {
+    Time {
+        hour: date_time.get_hours() as u8,
+        minute: date_time.get_minutes() as u8,
+        second: date_time.get_seconds() as u8,
+    }
+}
+
+impl rtc::Backend for Backend {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn get_time(&mut self) -> Time {
+        time(&self.date_time())
+    }
+
+    fn get_date_time(&mut self) -> (Date, Time) {
+        let date_time = self.date_time();
+        (
+            Date {
+                years_since_2000: date_time.get_full_year().wrapping_sub(2000) as u8,
+                month: date_time.get_month() as u8 + 1,
+                day: date_time.get_date() as u8,
+                days_from_sunday: date_time.get_day() as u8,
+            },
+            time(&date_time),
+        )
+    }
+
+    fn set_date_time(&mut self, (date, time): (Date, Time)) {
+        // JS dates silently wrap out-of-range components over, so validate them beforehand
+        if !(1..=12).contains(&date.month)
+            || !(1..=31).contains(&date.day)
+            || time.hour > 23
+            || time.minute > 59
+            || time.second > 59
+        {
+            return;
+        }
+        let time_ms = JsDate::new_with_year_month_day_hr_min_sec(
+            date.years_since_2000 as u32 + 2000,
+            date.month as i32 - 1,
+            date.day as i32,
+            time.hour as i32,
+            time.minute as i32,
+            time.second as i32,
+        )
+        .get_time();
+        // A frozen clock stays frozen, just at the time the game set
+        if let Some(frozen_time_ms) = &mut self.frozen_time_ms {
+            *frozen_time_ms = time_ms;
+        } else {
+            self.time_offset_ms = time_ms - JsDate::now();
+        }
+    }
+}
@@ -0,0 +1,68 @@
+// Builds the web crate with several feature combinations and reports the size of the resulting
+// WASM module for each, both uncompressed and as it would be served (gzip and Brotli).
+//
+// Usage: npm run size-report [-- <features>...]
+// Each extra argument is a comma-separated feature list to add to the report (use "" for no
+// features at all).
+
+const { execFileSync } = require("child_process");
+const { mkdtempSync, readFileSync, readdirSync, rmSync } = require("fs");
+const { tmpdir } = require("os");
+const { join, resolve } = require("path");
+const zlib = require("zlib");
+
+const crateDir = resolve(__dirname, "crate");
+
+const configs = [
+    { name: "minimal", features: "" },
+    { name: "no 3D", features: "panic-hook" },
+    { name: "default", features: null },
+    { name: "default + log", features: "soft-3d,panic-hook,log" },
+    { name: "default + WebGPU", features: "webgpu-3d,panic-hook" },
+].concat(
+    process.argv
+        .slice(2)
+        .map((features) => ({ name: features || "(none)", features }))
+);
+
+function build(features) {
+    const outDir = mkdtempSync(join(tmpdir(), "dust-web-size-"));
+    const args = ["build", "--release", "--target", "web", "--out-dir", outDir, crateDir, "--"];
+    if (features !== null) {
+        args.push("--no-default-features", `--features=${features}`);
+    }
+    args.push("-Zbuild-std=panic_abort,std");
+    execFileSync("wasm-pack", args, { stdio: ["ignore", "ignore", "inherit"] });
+    const wasmFile = readdirSync(outDir).find((file) => file.endsWith(".wasm"));
+    const contents = readFileSync(join(outDir, wasmFile));
+    rmSync(outDir, { recursive: true, force: true });
+    return contents;
+}
+
+function formatSize(size) {
+    return `${(size / 1024).toFixed(1)} KiB`.padStart(12);
+}
+
+const rows = [];
+for (const { name, features } of configs) {
+    console.error(`Building ${name}...`);
+    const wasm = build(features);
+    rows.push({
+        name,
+        raw: wasm.length,
+        gzip: zlib.gzipSync(wasm, { level: 9 }).length,
+        brotli: zlib.brotliCompressSync(wasm).length,
+    });
+}
+
+const nameWidth = Math.max(...rows.map((row) => row.name.length), "Features".length);
+console.log(
+    `${"Features".padEnd(nameWidth)} ${"Raw".padStart(12)} ${"Gzip".padStart(12)} ${"Brotli".padStart(12)}`
+);
+for (const row of rows) {
+    console.log(
+        `${row.name.padEnd(nameWidth)} ${formatSize(row.raw)} ${formatSize(row.gzip)} ${formatSize(
+            row.brotli
+        )}`
+    );
+}
@@ -0,0 +1,59 @@
+import { AudioRingBuffer } from "../transport";
+
+// The global scope of audio worklets isn't covered by TypeScript's standard libraries
+declare class AudioWorkletProcessor {
+    constructor(options?: any);
+}
+declare function registerProcessor(
+    name: string,
+    processorCtor: new (options?: any) => AudioWorkletProcessor
+): void;
+declare const sampleRate: number;
+
+const SYS_CLOCK_FREQ = 1 << 25;
+const ORIG_FRAME_RATE = SYS_CLOCK_FREQ / (6.0 * 355.0 * 263.0);
+// The emulator's output is slightly stretched, as it runs at exactly 60 FPS rather than the DS'
+// native framerate
+const INPUT_SAMPLE_RATE = ((SYS_CLOCK_FREQ / 1024.0) * 60.0) / ORIG_FRAME_RATE;
+
+// Plays the samples the emulator worker writes to a shared ring buffer, resampling them to the
+// audio context's rate; running on the audio rendering thread, it keeps playing smoothly even
+// when the UI thread is busy.
+class EmuAudioProcessor extends AudioWorkletProcessor {
+    private rx: AudioRingBuffer.Receiver;
+    private ratio: number;
+    private fract: number;
+    private prev: [number, number];
+    private next: [number, number];
+
+    constructor(options: { processorOptions: { buffer: SharedArrayBuffer } }) {
+        super(options);
+        this.rx = new AudioRingBuffer.Receiver(options.processorOptions.buffer);
+        this.ratio = INPUT_SAMPLE_RATE / sampleRate;
+        this.fract = 0;
+        this.prev = [0, 0];
+        this.next = [0, 0];
+    }
+
+    process(_inputs: Float32Array[][], outputs: Float32Array[][]): boolean {
+        const output = outputs[0]!;
+        const l = output[0]!;
+        const r = output[1] ?? l;
+        for (let i = 0; i < l.length; i++) {
+            while (this.fract >= 1) {
+                this.fract -= 1;
+                this.prev = this.next;
+                // On underruns, the last sample is held to avoid pops
+                if (this.rx.available) {
+                    this.next = this.rx.read();
+                }
+            }
+            l[i] = this.prev[0] + (this.next[0] - this.prev[0]) * this.fract;
+            r[i] = this.prev[1] + (this.next[1] - this.prev[1]) * this.fract;
+            this.fract += this.ratio;
+        }
+        return true;
+    }
+}
+
+registerProcessor("emu-audio", EmuAudioProcessor);
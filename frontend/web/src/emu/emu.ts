@@ -1,4 +1,5 @@
 import { UiToEmu, EmuToUi } from "../message";
+import { AudioRingBuffer, FrameTripleBuffer } from "../transport";
 import type * as wasm from "../../pkg";
 
 function sendMessage(message: EmuToUi.Message, transfer?: Transferable[]) {
@@ -62,28 +63,39 @@ class FpsLimiter {
     let playing = false;
     let fpsLimiter = new FpsLimiter(60, frame);
     let emu: wasm.EmuState | undefined;
+    let frameTx: FrameTripleBuffer.Sender | undefined;
+    let rendererStarted = false;
 
     let lastSave = performance.now();
 
     function frame() {
         if (!playing) return;
         const buffer = emu!.run_frame();
-        sendMessage(
-            {
-                type: EmuToUi.MessageType.RenderFrame,
-                buffer,
-            },
-            [buffer.buffer]
-        );
+        // The 3D renderer worker is only started once the game actually uses the 3D engine, so
+        // that 2D-only games don't spawn it at all; it instantiates this same WASM module to share
+        // its memory though, so the renderer's code is always part of the initial download
+        if (!rendererStarted && wasm.renderer_3d_requested()) {
+            rendererStarted = true;
+            sendMessage({
+                type: EmuToUi.MessageType.StartRenderer,
+                module: wasm.internal_get_module(),
+                memory: wasm.internal_get_memory(),
+            });
+        }
+        frameTx!.send(buffer);
         const now = performance.now();
         if (now - lastSave >= 1000) {
             lastSave = now;
-            const buffer = emu!.export_save();
-            sendMessage({
-                type: EmuToUi.MessageType.ExportSave,
-                buffer,
-                triggerDownload: false,
-            }, [buffer.buffer]);
+            // Only export the save when the game actually wrote to it, so that the UI doesn't
+            // report saves that never happened
+            const buffer = emu!.export_save_if_dirty();
+            if (buffer) {
+                sendMessage({
+                    type: EmuToUi.MessageType.ExportSave,
+                    buffer,
+                    triggerDownload: false,
+                }, [buffer.buffer]);
+            }
         }
     }
 
@@ -91,31 +103,33 @@ class FpsLimiter {
         const message = e.data as UiToEmu.Message;
         switch (message.type) {
             case UiToEmu.MessageType.Start: {
-                emu = wasm.create_emu_state(
-                    message.bios7,
-                    message.bios9,
-                    message.firmware,
-                    message.rom,
-                    undefined,
-                    message.saveType as number | undefined,
-                    message.hasIR,
-                    wasm.WbgModel.Lite,
-                    (l: Float32Array, r: Float32Array) => {
-                        sendMessage(
-                            {
-                                type: EmuToUi.MessageType.PlayAudioChunk,
-                                l,
-                                r,
-                            },
-                            [l.buffer, r.buffer]
-                        );
-                    }
-                );
-                sendMessage({
-                    type: EmuToUi.MessageType.StartRenderer,
-                    module: wasm.internal_get_module(),
-                    memory: wasm.internal_get_memory(),
-                });
+                frameTx = new FrameTripleBuffer.Sender(message.frameBuffer);
+                const audioTx = new AudioRingBuffer.Sender(message.audioBuffer);
+                try {
+                    emu = wasm.create_emu_state(
+                        message.bios7,
+                        message.bios9,
+                        message.firmware,
+                        message.rom,
+                        undefined,
+                        message.saveType as number | undefined,
+                        message.hasIR,
+                        wasm.WbgModel.Lite,
+                        (l: Float32Array, r: Float32Array) => {
+                            audioTx.send(l, r);
+                        }
+                    );
+                    // Only takes effect when the renderer worker starts, i.e. once the game uses 3D
+                    wasm.set_renderer_3d_kind(message.renderer3dKind as number);
+                    wasm.set_resolution_scale_shift(message.resolutionScaleShift);
+                } catch (err) {
+                    sendMessage({
+                        type: EmuToUi.MessageType.StartFailed,
+                        message:
+                            err instanceof Error ? err.message : String(err),
+                    });
+                    close();
+                }
                 break;
             }
 
@@ -177,6 +191,11 @@ class FpsLimiter {
                 fpsLimiter.limit = message.value ? 60.0 : null;
                 break;
             }
+
+            case UiToEmu.MessageType.UpdateResolutionScaleShift: {
+                wasm.set_resolution_scale_shift(message.value);
+                break;
+            }
         }
     };
 
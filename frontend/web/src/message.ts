@@ -26,6 +26,12 @@ export const saveTypes = {
     "nand-256m": SaveType.Nand256m,
 };
 
+// Matches the WASM module's `Renderer3dKind`
+export const enum Renderer3dKind {
+    Software,
+    WebGpu,
+}
+
 export const enum InputBits {
     A = 1 << 0,
     B = 1 << 1,
@@ -52,6 +58,7 @@ export namespace UiToEmu {
         UpdateInput,
         UpdatePlaying,
         UpdateFramerateLimit,
+        UpdateResolutionScaleShift,
     }
 
     export interface StartMessage {
@@ -62,6 +69,10 @@ export namespace UiToEmu {
         firmware: Uint8Array | undefined;
         saveType: SaveType | undefined;
         hasIR: boolean;
+        renderer3dKind: Renderer3dKind;
+        resolutionScaleShift: number;
+        frameBuffer: SharedArrayBuffer;
+        audioBuffer: SharedArrayBuffer;
     }
 
     export interface RawMessage {
@@ -85,12 +96,18 @@ export namespace UiToEmu {
         value: boolean;
     }
 
+    export interface UpdateResolutionScaleShiftMessage {
+        type: MessageType.UpdateResolutionScaleShift;
+        value: number;
+    }
+
     export type Message =
         | StartMessage
         | RawMessage
         | LoadSaveMessage
         | UpdateInputMessage
-        | UpdateFlagMessage;
+        | UpdateFlagMessage
+        | UpdateResolutionScaleShiftMessage;
 }
 
 export namespace EmuToUi {
@@ -98,9 +115,8 @@ export namespace EmuToUi {
         Loaded,
         StartRenderer,
         ExportSave,
-        RenderFrame,
         Stopped,
-        PlayAudioChunk,
+        StartFailed,
     }
 
     export interface LoadedMessage {
@@ -124,15 +140,9 @@ export namespace EmuToUi {
         triggerDownload: boolean;
     }
 
-    export interface RenderFrameMessage {
-        type: MessageType.RenderFrame;
-        buffer: Uint32Array;
-    }
-
-    export interface PlayAudioChunkMessage {
-        type: MessageType.PlayAudioChunk;
-        l: Float32Array;
-        r: Float32Array;
+    export interface StartFailedMessage {
+        type: MessageType.StartFailed;
+        message: string;
     }
 
     export type Message =
@@ -140,6 +150,5 @@ export namespace EmuToUi {
         | StartRendererMessage
         | StopMessage
         | ExportSaveMessage
-        | RenderFrameMessage
-        | PlayAudioChunkMessage;
+        | StartFailedMessage;
 }
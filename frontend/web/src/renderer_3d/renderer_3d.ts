@@ -11,7 +11,8 @@ function sendMessage(message: EmuToUi.Message, transfer?: Transferable[]) {
     self.onmessage = async (e) => {
         const message = e.data;
         await wasm.default(message.module, message.memory);
-        wasm.run_worker();
+        // Asynchronous when built with the WebGPU renderer
+        await wasm.run_worker();
         close();
     };
 })();
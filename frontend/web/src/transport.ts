@@ -0,0 +1,133 @@
+// Lock-free transports through which the emulator worker hands frames and audio over to the UI
+// thread and the audio worklet using shared memory, so that neither depends on the other's event
+// loop being responsive (unlike with `postMessage`).
+
+const FRAME_LEN = 256 * 384;
+
+// The index of the buffer shared between the two sides, along with a flag signaling whether it
+// contains a frame the receiver hasn't seen yet
+const TRIPLE_BUFFER_NEW_DATA = 1 << 2;
+
+// A triple buffer holding whole frames, like the desktop frontend's: the sender and receiver each
+// own one buffer, and atomically exchange it with the shared one when done with it, so the sender
+// never waits and the receiver always gets the latest complete frame.
+export namespace FrameTripleBuffer {
+    export function create(): SharedArrayBuffer {
+        const buffer = new SharedArrayBuffer(4 + FRAME_LEN * 4 * 3);
+        new Int32Array(buffer, 0, 1)[0] = 1;
+        return buffer;
+    }
+
+    export class Sender {
+        private state: Int32Array;
+        private buffers: Uint32Array[];
+        private index: number;
+
+        constructor(buffer: SharedArrayBuffer) {
+            this.state = new Int32Array(buffer, 0, 1);
+            this.buffers = [0, 1, 2].map(
+                (i) => new Uint32Array(buffer, 4 + FRAME_LEN * 4 * i, FRAME_LEN)
+            );
+            this.index = 0;
+        }
+
+        send(frame: Uint32Array) {
+            this.buffers[this.index]!.set(frame);
+            this.index =
+                Atomics.exchange(
+                    this.state,
+                    0,
+                    this.index | TRIPLE_BUFFER_NEW_DATA
+                ) & 3;
+        }
+    }
+
+    export class Receiver {
+        private state: Int32Array;
+        private buffers: Uint8Array[];
+        private index: number;
+
+        constructor(buffer: SharedArrayBuffer) {
+            this.state = new Int32Array(buffer, 0, 1);
+            this.buffers = [0, 1, 2].map(
+                (i) =>
+                    new Uint8Array(buffer, 4 + FRAME_LEN * 4 * i, FRAME_LEN * 4)
+            );
+            this.index = 2;
+        }
+
+        // Returns the latest frame as RGBA8 data if a new one was sent since the last call; the
+        // returned view stays valid until the next call.
+        get(): Uint8Array | undefined {
+            if (!(Atomics.load(this.state, 0) & TRIPLE_BUFFER_NEW_DATA)) {
+                return undefined;
+            }
+            this.index = Atomics.exchange(this.state, 0, this.index) & 3;
+            return this.buffers[this.index];
+        }
+    }
+}
+
+// A single-producer single-consumer ring buffer of stereo samples; samples that don't fit are
+// dropped, as the receiver is expected to keep up in real time.
+export namespace AudioRingBuffer {
+    // Enough for ~250 ms of audio at the emulator's output sample rate
+    const CAPACITY = 8192;
+
+    const WRITE_POS = 0;
+    const READ_POS = 1;
+
+    export function create(): SharedArrayBuffer {
+        return new SharedArrayBuffer(8 + CAPACITY * 2 * 4);
+    }
+
+    export class Sender {
+        private positions: Int32Array;
+        private samples: Float32Array;
+
+        constructor(buffer: SharedArrayBuffer) {
+            this.positions = new Int32Array(buffer, 0, 2);
+            this.samples = new Float32Array(buffer, 8, CAPACITY * 2);
+        }
+
+        send(l: Float32Array, r: Float32Array) {
+            let writePos = Atomics.load(this.positions, WRITE_POS);
+            const readPos = Atomics.load(this.positions, READ_POS);
+            const free = (readPos - writePos - 1 + CAPACITY) % CAPACITY;
+            const len = Math.min(l.length, free);
+            for (let i = 0; i < len; i++) {
+                this.samples[writePos * 2] = l[i]!;
+                this.samples[writePos * 2 + 1] = r[i]!;
+                writePos = (writePos + 1) % CAPACITY;
+            }
+            Atomics.store(this.positions, WRITE_POS, writePos);
+        }
+    }
+
+    export class Receiver {
+        private positions: Int32Array;
+        private samples: Float32Array;
+
+        constructor(buffer: SharedArrayBuffer) {
+            this.positions = new Int32Array(buffer, 0, 2);
+            this.samples = new Float32Array(buffer, 8, CAPACITY * 2);
+        }
+
+        get available(): number {
+            const writePos = Atomics.load(this.positions, WRITE_POS);
+            const readPos = Atomics.load(this.positions, READ_POS);
+            return (writePos - readPos + CAPACITY) % CAPACITY;
+        }
+
+        // Reads the oldest sample; must only be called when at least one is available.
+        read(): [number, number] {
+            const readPos = Atomics.load(this.positions, READ_POS);
+            const result: [number, number] = [
+                this.samples[readPos * 2]!,
+                this.samples[readPos * 2 + 1]!,
+            ];
+            Atomics.store(this.positions, READ_POS, (readPos + 1) % CAPACITY);
+            return result;
+        }
+    }
+}
@@ -0,0 +1,30 @@
+// User-facing notifications about emulator events, emitted as `dust-notification` events on
+// `window` so that embedding pages can show them however they want
+
+export const enum Kind {
+    SaveWritten = "save-written",
+    DeviceLost = "device-lost",
+    Other = "other",
+}
+
+export const enum Level {
+    Info = "info",
+    Warning = "warning",
+    Error = "error",
+}
+
+export interface Notification {
+    kind: Kind;
+    level: Level;
+    message: string;
+}
+
+export const EVENT_NAME = "dust-notification";
+
+export function post(kind: Kind, level: Level, message: string) {
+    window.dispatchEvent(
+        new CustomEvent<Notification>(EVENT_NAME, {
+            detail: { kind, level, message },
+        })
+    );
+}
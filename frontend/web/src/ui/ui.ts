@@ -1,17 +1,31 @@
-import { UiToEmu, EmuToUi, SaveType, saveTypes } from "../message";
+import {
+    UiToEmu,
+    EmuToUi,
+    Renderer3dKind,
+    SaveType,
+    saveTypes,
+} from "../message";
+import { AudioRingBuffer, FrameTripleBuffer } from "../transport";
 import { FileId, Files, dbLookup } from "./files";
 import { Input, Rect } from "./input";
+import * as notifications from "./notifications";
 import vertShaderSource from "raw-loader!../shaders/screen.vert";
 import fragShaderSource from "raw-loader!../shaders/screen.frag";
 import { isMobileBrowser } from "./utils";
 
+// How long it takes to fade audio in or out when it starts or stops playing (i.e. when pausing,
+// resuming or after an underrun), in seconds, to avoid pops
+const AUDIO_FADE_DURATION = 0.005;
+
 export class Ui {
     private canvasContainer: HTMLElement;
     private canvas: HTMLCanvasElement;
 
     private input: Input;
     private audio: AudioContext;
-    private audioTime: number;
+    private audioGain: GainNode;
+    private audioWorkletLoaded: Promise<void>;
+    private audioNode: AudioWorkletNode | undefined;
 
     private exportSaveButton: HTMLButtonElement;
     private playButton: HTMLButtonElement;
@@ -29,6 +43,9 @@ export class Ui {
     private gl: WebGLRenderingContext;
     private fbProgram: WebGLProgram;
     private fbCoordsAttrib: number;
+    private frameRx: FrameTripleBuffer.Receiver | undefined;
+    // WebGL doesn't accept views into shared memory, so frames are copied here before uploading
+    private frameData: Uint8Array;
 
     private worker: Worker | undefined;
     private rendererWorker: Worker | undefined;
@@ -40,6 +57,9 @@ export class Ui {
 
     private playing: boolean;
 
+    private renderer3dKind = Renderer3dKind.Software;
+    private resolutionScaleShift = 0;
+
     constructor(touch: boolean) {
         this.canvasContainer = document.getElementById(
             "canvas-container"
@@ -49,7 +69,11 @@ export class Ui {
         this.input = new Input(touch, this.pause.bind(this));
         this.audio = new (window.AudioContext ||
             (window as any).webkitAudioContext)();
-        this.audioTime = 0;
+        this.audioGain = this.audio.createGain();
+        this.audioGain.connect(this.audio.destination);
+        this.audioWorkletLoaded = this.audio.audioWorklet.addModule(
+            "audio_worklet.bundle.js"
+        );
 
         const startAudioContext = () => {
             this.audio.resume();
@@ -144,6 +168,13 @@ export class Ui {
             throw new Error("Couldn't create WebGL context");
         }
         this.gl = gl;
+        this.canvas.addEventListener("webglcontextlost", () => {
+            notifications.post(
+                notifications.Kind.DeviceLost,
+                notifications.Level.Error,
+                "Graphics context lost, reload the page to restore the display"
+            );
+        });
 
         const fbTexture = gl.createTexture()!;
         gl.bindTexture(gl.TEXTURE_2D, fbTexture);
@@ -208,6 +239,8 @@ export class Ui {
             gl.STATIC_DRAW
         );
 
+        this.frameData = new Uint8Array(256 * 384 * 4);
+
         this.playing = false;
 
         this.frame();
@@ -226,6 +259,15 @@ export class Ui {
         }
     }
 
+    toggleControlsEnabled(enabled: boolean) {
+        this.files.toggleEnabled(FileId.Save, enabled);
+        this.exportSaveButton.disabled = !enabled;
+        this.playButton.disabled = !enabled;
+        this.stopButton.disabled = !enabled;
+        this.resetButton.disabled = !enabled;
+        this.limitFramerateCheckbox.disabled = !enabled;
+    }
+
     sendMessage(message: UiToEmu.Message, transfer?: Transferable[]) {
         this.worker!.postMessage(message, transfer as any);
     }
@@ -236,12 +278,7 @@ export class Ui {
             return;
         }
 
-        this.files.toggleEnabled(FileId.Save, true);
-        this.exportSaveButton.disabled = false;
-        this.playButton.disabled = false;
-        this.stopButton.disabled = false;
-        this.resetButton.disabled = false;
-        this.limitFramerateCheckbox.disabled = false;
+        this.toggleControlsEnabled(true);
         this.limitFramerateCheckbox.checked = true;
 
         const romFilenameExtStart = this.nextRomFilename!.lastIndexOf(".");
@@ -260,14 +297,24 @@ export class Ui {
         const dbEntry = dbLookup(this.files.gameDb!, gameCode);
         if (dbEntry) {
             if (this.nextRomBuffer!.length !== dbEntry["rom-size"]) {
-                console.warn(
-                    `Unexpected ROM size: expected ${
-                        dbEntry["rom-size"]
-                    } B, got ${this.nextRomBuffer!.length} B`
+                const message = `Unexpected ROM size: expected ${
+                    dbEntry["rom-size"]
+                } B, got ${this.nextRomBuffer!.length} B`;
+                console.warn(message);
+                notifications.post(
+                    notifications.Kind.Other,
+                    notifications.Level.Warning,
+                    message
                 );
             }
             saveType = saveTypes[dbEntry["save-type"]];
         }
+
+        const frameBuffer = FrameTripleBuffer.create();
+        const audioBuffer = AudioRingBuffer.create();
+        this.frameRx = new FrameTripleBuffer.Receiver(frameBuffer);
+        this.startAudio(audioBuffer);
+
         this.sendMessage(
             {
                 type: UiToEmu.MessageType.Start,
@@ -277,6 +324,10 @@ export class Ui {
                 firmware: this.firmware,
                 saveType,
                 hasIR: (gameCode & 0xff) === 0x49,
+                renderer3dKind: this.renderer3dKind,
+                resolutionScaleShift: this.resolutionScaleShift,
+                frameBuffer,
+                audioBuffer,
             },
             [this.nextRomBuffer!.buffer]
         );
@@ -306,6 +357,11 @@ export class Ui {
                     message.buffer,
                     this.gameTitle!
                 );
+                notifications.post(
+                    notifications.Kind.SaveWritten,
+                    notifications.Level.Info,
+                    "Save file written"
+                );
                 if (message.triggerDownload) {
                     const file = new Blob([message.buffer], {
                         type: "application/octet-stream;charset=utf-8",
@@ -322,59 +378,22 @@ export class Ui {
                 break;
             }
 
-            case EmuToUi.MessageType.RenderFrame: {
-                this.gl.texSubImage2D(
-                    this.gl.TEXTURE_2D,
-                    0,
-                    0,
-                    0,
-                    256,
-                    384,
-                    this.gl.RGBA,
-                    this.gl.UNSIGNED_BYTE,
-                    new Uint8Array(message.buffer.buffer)
+            case EmuToUi.MessageType.StartFailed: {
+                console.error(message.message);
+                notifications.post(
+                    notifications.Kind.Other,
+                    notifications.Level.Error,
+                    message.message
                 );
-                break;
-            }
-
-            case EmuToUi.MessageType.PlayAudioChunk: {
-                const sysClockFreq = 1 << 25;
-                const origFrameRate = sysClockFreq / (6.0 * 355.0 * 263.0);
-                const inputSampleRate =
-                    ((sysClockFreq / 1024.0) * 60.0) / origFrameRate;
-
-                const currentTime =
-                    this.audio.currentTime + (this.audio.baseLatency || 1 / 60);
-                if (
-                    this.audioTime >
-                    currentTime + message.l.length / inputSampleRate
-                ) {
-                    break;
-                }
-                if (this.audioTime < currentTime) {
-                    this.audioTime = currentTime;
-                }
-                const buffer = this.audio.createBuffer(
-                    2,
-                    message.l.length,
-                    inputSampleRate
-                );
-                if (buffer.copyToChannel) {
-                    buffer.copyToChannel(message.l, 0);
-                    buffer.copyToChannel(message.r, 1);
-                } else {
-                    buffer.getChannelData(0).set(message.l);
-                    buffer.getChannelData(1).set(message.r);
-                }
-                const src = this.audio.createBufferSource();
-                src.buffer = buffer;
-                src.connect(this.audio.destination);
-                if (src.start) {
-                    src.start(this.audioTime);
-                } else if ((src as any).noteOn) {
-                    (src as any).noteOn(this.audioTime);
-                }
-                this.audioTime += message.l.length / inputSampleRate;
+                // The worker closes itself after reporting the error, so there's nothing to stop
+                this.worker = undefined;
+                this.stopAudio();
+                this.frameRx = undefined;
+                this.toggleControlsEnabled(false);
+                this.files.unloadRom();
+                this.saveFilename = undefined;
+                this.gameTitle = undefined;
+                this.tryStartQueuedWorker();
                 break;
             }
         }
@@ -403,17 +422,14 @@ export class Ui {
     requestStop() {
         if (!this.worker) return;
 
-        this.files.toggleEnabled(FileId.Save, false);
-        this.exportSaveButton.disabled = true;
-        this.playButton.disabled = true;
-        this.stopButton.disabled = true;
-        this.resetButton.disabled = true;
-        this.limitFramerateCheckbox.disabled = true;
+        this.toggleControlsEnabled(false);
 
         this.sendMessage({
             type: UiToEmu.MessageType.Stop,
         });
         this.worker.onmessage = this.handleClosingWorkerMessage.bind(this);
+        this.stopAudio();
+        this.frameRx = undefined;
 
         this.files.unloadRom();
         this.gl.texSubImage2D(
@@ -466,6 +482,25 @@ export class Ui {
         });
     }
 
+    // Picks the 3D renderer for the games started from now on; WebGPU is only used if the WASM
+    // module was built with the `webgpu-3d` feature and the browser supports it, otherwise the
+    // software renderer is used
+    setRenderer3dKind(kind: Renderer3dKind) {
+        this.renderer3dKind = kind;
+    }
+
+    // Sets the WebGPU renderer's resolution scale as a power of two (from 0 to 3); as the 2D
+    // engines are composited at the DS's resolution, this supersamples 3D graphics
+    setResolutionScaleShift(value: number) {
+        this.resolutionScaleShift = value;
+        if (this.worker) {
+            this.sendMessage({
+                type: UiToEmu.MessageType.UpdateResolutionScaleShift,
+                value,
+            });
+        }
+    }
+
     loadSave(filename: string, buffer: ArrayBuffer) {
         this.files.storeSaveToStorage(filename, buffer, this.gameTitle!);
         this.saveFilename = filename;
@@ -478,7 +513,42 @@ export class Ui {
         );
     }
 
+    startAudio(buffer: SharedArrayBuffer) {
+        this.audioWorkletLoaded.then(() => {
+            // The emulator might have been stopped while the worklet was loading
+            if (!this.frameRx) {
+                return;
+            }
+            this.audioNode = new AudioWorkletNode(this.audio, "emu-audio", {
+                outputChannelCount: [2],
+                processorOptions: { buffer },
+            });
+            this.audioNode.connect(this.audioGain);
+        });
+    }
+
+    stopAudio() {
+        this.audioNode?.disconnect();
+        this.audioNode = undefined;
+    }
+
     frame() {
+        const frame = this.frameRx?.get();
+        if (frame) {
+            this.frameData.set(frame);
+            this.gl.texSubImage2D(
+                this.gl.TEXTURE_2D,
+                0,
+                0,
+                0,
+                256,
+                384,
+                this.gl.RGBA,
+                this.gl.UNSIGNED_BYTE,
+                this.frameData
+            );
+        }
+
         if (this.playing) {
             const containerWidth = this.canvasContainer.clientWidth;
             const containerHeight = this.canvasContainer.clientHeight;
@@ -529,9 +599,20 @@ export class Ui {
         requestAnimationFrame(this.frame.bind(this));
     }
 
+    fadeAudio(fadeIn: boolean, startTime: number) {
+        const gain = this.audioGain.gain;
+        gain.cancelScheduledValues(startTime);
+        gain.setValueAtTime(fadeIn ? 0 : 1, startTime);
+        gain.linearRampToValueAtTime(
+            fadeIn ? 1 : 0,
+            startTime + AUDIO_FADE_DURATION
+        );
+    }
+
     play() {
         document.body.classList.remove("paused");
         this.playing = true;
+        this.fadeAudio(true, this.audio.currentTime);
         this.sendMessage({
             type: UiToEmu.MessageType.UpdatePlaying,
             value: true,
@@ -541,6 +622,8 @@ export class Ui {
     pause() {
         document.body.classList.add("paused");
         this.playing = false;
+        // Fade out the audio that's still buffered instead of cutting it off abruptly
+        this.fadeAudio(false, this.audio.currentTime);
         this.sendMessage({
             type: UiToEmu.MessageType.UpdatePlaying,
             value: false,
@@ -13,6 +13,12 @@ const dist = resolve(__dirname, "dist");
 const mode = process.env.BUILD_MODE ?? "development";
 const sourceMap = mode === "development";
 const optimize = mode === "production";
+// Comma-separated list of features to build the crate with instead of the default ones
+const features = process.env.DUST_WEB_FEATURES;
+const featureArgs =
+    features === undefined
+        ? ""
+        : ` --no-default-features --features=${features}`;
 
 const plugins = [
     new WasmPackPlugin({
@@ -21,7 +27,7 @@ const plugins = [
         outDir: resolve(__dirname, pkg),
         forceMode: "production",
         pluginLogLevel: "warn",
-        extraArgs: "--target web -- . -Zbuild-std=panic_abort,std",
+        extraArgs: `--target web -- .${featureArgs} -Zbuild-std=panic_abort,std`,
     }),
     new MiniCssExtractPlugin(),
     new CopyPlugin({
@@ -195,4 +201,16 @@ module.exports = [
         },
         baseConfig
     ),
+    Object.assign(
+        {
+            name: "audio_worklet",
+            plugins: pluginsForDir(resolve(src, "audio_worklet")),
+            entry: {
+                audio_worklet: resolve(src, "audio_worklet/audio_worklet.ts"),
+            },
+            target: "webworker",
+            dependencies: ["ui"],
+        },
+        baseConfig
+    ),
 ];
@@ -12,7 +12,7 @@ use dust_core::{
 
 impl<R: Role> RenderingData for Engine2d<R> {
     fn control(&self) -> Control {
-        self.control()
+        self.render_control()
     }
 
     fn master_brightness_control(&self) -> BrightnessControl {
@@ -28,7 +28,7 @@ impl<R: Role> RenderingData for Engine2d<R> {
     }
 
     fn bg_priority(&self, i: BgIndex) -> u8 {
-        self.bgs[i.get() as usize].priority()
+        self.bg_render_priority(i)
     }
 
     fn bg_scroll(&self, i: BgIndex) -> [u16; 2] {
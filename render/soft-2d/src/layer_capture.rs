@@ -0,0 +1,56 @@
+pub use crate::common::BgObjPixel;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use dust_core::gpu::{Scanline, SCREEN_HEIGHT};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+struct Data {
+    enabled: AtomicBool,
+    scanlines: Mutex<Box<[[Scanline<BgObjPixel>; SCREEN_HEIGHT]; 2]>>,
+}
+
+/// A copy of each 2D engine's BG/OBJ layers as they were before color effects were applied,
+/// updated while enabled.
+///
+/// Each pixel holds the color and attributes of the two topmost layers at its position, with
+/// `color_effects_mask` identifying which layer each one belongs to (bits 0-3 for BGs, 4 for OBJs
+/// and 5 for the backdrop); scanlines that weren't rendered (i.e. when the engine's display mode
+/// didn't require it) have no layer bits set.
+#[derive(Clone)]
+pub struct LayerCapture(Arc<Data>);
+
+impl LayerCapture {
+    pub(crate) fn new() -> Self {
+        LayerCapture(Arc::new(Data {
+            enabled: AtomicBool::new(false),
+            scanlines: Mutex::new(unsafe { Box::new_zeroed().assume_init() }),
+        }))
+    }
+
+    pub fn set_enabled(&self, value: bool) {
+        self.0.enabled.store(value, Ordering::Relaxed);
+    }
+
+    /// Returns the last captured frame, with engine A's scanlines first.
+    pub fn scanlines(&self) -> MutexGuard<'_, Box<[[Scanline<BgObjPixel>; SCREEN_HEIGHT]; 2]>> {
+        self.0.scanlines.lock().unwrap()
+    }
+
+    #[inline]
+    pub(crate) fn capture(
+        &self,
+        is_engine_b: bool,
+        line: u8,
+        bg_obj_scanline: Option<&Scanline<BgObjPixel>>,
+    ) {
+        if !self.0.enabled.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut scanlines = self.scanlines();
+        let dst = &mut scanlines[is_engine_b as usize][line as usize];
+        match bg_obj_scanline {
+            Some(src) => dst.0.copy_from_slice(&src.0),
+            None => dst.0.fill(BgObjPixel(0)),
+        }
+    }
+}
@@ -3,6 +3,8 @@
 #![allow(incomplete_features)]
 
 mod common;
+#[cfg(feature = "layer-capture")]
+pub mod layer_capture;
 pub mod sync;
 #[cfg(feature = "threaded")]
 pub mod threaded;
@@ -5,14 +5,19 @@ use crate::common::{
     render::{self, objs::prerender_objs},
     rgb5_to_rgb6_64, BgObjPixel, ObjPixel, WindowPixel,
 };
+#[cfg(feature = "layer-capture")]
+use crate::layer_capture::LayerCapture;
 use core::cell::UnsafeCell;
 use dust_core::{
     gpu::{
-        engine_2d::{Engine2d, EngineA, EngineB, Renderer as RendererTrait, Role},
+        engine_2d::{
+            window_x_spans, BgIndex, Engine2d, EngineA, EngineB, Renderer as RendererTrait, Role,
+        },
         engine_3d,
         vram::Vram,
         Framebuffer, Scanline, SCREEN_HEIGHT, SCREEN_WIDTH,
     },
+    profiling::TimeCounter,
     utils::mem_prelude::*,
 };
 
@@ -31,6 +36,9 @@ pub struct Renderer {
     renderer_3d_rx: Box<dyn engine_3d::SoftRendererRx>,
     buffers: [Buffers; 2],
     framebuffer: Box<[[Scanline<u32>; SCREEN_HEIGHT]; 2]>,
+    time_counter: TimeCounter,
+    #[cfg(feature = "layer-capture")]
+    layer_capture: LayerCapture,
 }
 
 unsafe impl Send for Renderer {}
@@ -53,9 +61,23 @@ impl Renderer {
             renderer_3d_rx,
             buffers: [buffers!(), buffers!()],
             framebuffer: unsafe { Box::new_zeroed().assume_init() },
+            time_counter: TimeCounter::new(),
+            #[cfg(feature = "layer-capture")]
+            layer_capture: LayerCapture::new(),
         }
     }
 
+    /// Returns a counter measuring the time spent rendering scanlines; as this renderer runs on
+    /// the emulation thread, it's included in the time taken to run the emulator.
+    pub fn time_counter(&self) -> TimeCounter {
+        self.time_counter.clone()
+    }
+
+    #[cfg(feature = "layer-capture")]
+    pub fn layer_capture(&self) -> LayerCapture {
+        self.layer_capture.clone()
+    }
+
     fn render_scanline<R: Role>(
         &mut self,
         line: u8,
@@ -87,6 +109,8 @@ impl Renderer {
             // TODO: Display capture interaction?
 
             scanline_buffer.0.fill(0xFFFF_FFFF);
+            #[cfg(feature = "layer-capture")]
+            self.layer_capture.capture(!R::IS_A, line, None);
             return;
         }
 
@@ -102,7 +126,8 @@ impl Renderer {
                 && !engine.capture_control().src_a_3d_only());
 
         let scanline_3d = if R::IS_A && engine.engine_3d_enabled_in_frame() {
-            let enabled_in_bg_obj = engine.bgs[0].priority() != 4 && engine.control().bg0_3d();
+            let enabled_in_bg_obj =
+                engine.bg_render_priority(BgIndex::new(0)) != 4 && engine.control().bg0_3d();
             if (engine.capture_enabled_in_frame()
                 && (engine.capture_control().src_a_3d_only() || enabled_in_bg_obj))
                 || (display_mode == 1 && enabled_in_bg_obj)
@@ -139,13 +164,13 @@ impl Renderer {
                     continue;
                 }
 
-                let x_range = &engine.window_x_ranges()[i];
-                let x_start = x_range.0 as usize;
-                let mut x_end = x_range.1 as usize;
-                if x_end < x_start {
-                    x_end = 256;
+                let window_pixel = WindowPixel(engine.window_control()[i].0);
+                for x_span in window_x_spans(
+                    engine.window_x_ranges()[i],
+                    engine.windows_active().0 & 4 << i != 0,
+                ) {
+                    window.0[x_span].fill(window_pixel);
                 }
-                window.0[x_start..x_end].fill(WindowPixel(engine.window_control()[i].0));
             }
 
             let backdrop = BgObjPixel(rgb5_to_rgb6_64(
@@ -167,10 +192,18 @@ impl Renderer {
                     vram,
                     scanline_3d,
                 );
+            }
+            #[cfg(feature = "layer-capture")]
+            self.layer_capture
+                .capture(!R::IS_A, line, Some(buffers.bg_obj_scanline.get_mut()));
+            unsafe {
                 fns.apply_color_effects[engine.color_effects_control().color_effect() as usize](
                     buffers, engine,
                 );
             }
+        } else {
+            #[cfg(feature = "layer-capture")]
+            self.layer_capture.capture(!R::IS_A, line, None);
         }
 
         match display_mode {
@@ -259,7 +292,9 @@ impl RendererTrait for Renderer {
         engines: (&mut Engine2d<EngineA>, &mut Engine2d<EngineB>),
         vram: &mut Vram,
     ) {
+        let start = self.time_counter.start();
         self.render_scanline(line, vcount, engines.0, vram);
         self.render_scanline(line, vcount, engines.1, vram);
+        self.time_counter.stop(start);
     }
 }
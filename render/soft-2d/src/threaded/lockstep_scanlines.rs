@@ -5,6 +5,8 @@ use crate::common::{
     render::{self, objs::prerender_objs},
     rgb5_to_rgb6_64, BgObjPixel, ObjPixel, WindowPixel,
 };
+#[cfg(feature = "layer-capture")]
+use crate::layer_capture::LayerCapture;
 use core::{
     cell::UnsafeCell,
     hint,
@@ -13,11 +15,13 @@ use core::{
 use dust_core::{
     gpu::{
         engine_2d::{
-            BgControl, BrightnessControl, CaptureControl, ColorEffectsControl, Control, Engine2d,
-            EngineA, EngineB, Renderer as RendererTrait, Role, WindowControl, WindowsActive,
+            window_x_spans, BgControl, BgIndex, BrightnessControl, CaptureControl,
+            ColorEffectsControl, Control, Engine2d, EngineA, EngineB, Renderer as RendererTrait,
+            Role, WindowControl, WindowsActive,
         },
         engine_3d, vram, Framebuffer, Scanline, SCREEN_HEIGHT, SCREEN_WIDTH,
     },
+    profiling::TimeCounter,
     utils::mem_prelude::*,
 };
 use std::{sync::Arc, thread};
@@ -94,7 +98,7 @@ impl<R: Role> From<&Engine2d<R>> for RenderingData {
                         let bg = &other.bgs[$i];
                         Bg {
                             control: bg.control(),
-                            priority: bg.priority(),
+                            priority: other.bg_render_priority(BgIndex::new($i)),
                             scroll: bg.scroll,
                         }
                     }
@@ -119,7 +123,7 @@ impl<R: Role> From<&Engine2d<R>> for RenderingData {
             is_enabled: other.is_enabled(),
             engine_3d_enabled_in_frame: other.engine_3d_enabled_in_frame(),
             is_on_lower_screen: other.is_on_lower_screen(),
-            control: other.control(),
+            control: other.render_control(),
             master_brightness_control: other.master_brightness_control(),
             master_brightness_factor: other.master_brightness_factor(),
             bgs: bgs!(0, 1, 2, 3),
@@ -141,6 +145,9 @@ pub struct Renderer {
     affine_bg_pos: [[[i32; 2]; 2]; 2],
     shared_data: Arc<SharedData>,
     thread: Option<thread::JoinHandle<()>>,
+    time_counter: TimeCounter,
+    #[cfg(feature = "layer-capture")]
+    layer_capture: LayerCapture,
 }
 
 impl Renderer {
@@ -213,12 +220,19 @@ impl Renderer {
             }
         });
 
+        let time_counter = TimeCounter::new();
+        #[cfg(feature = "layer-capture")]
+        let layer_capture = LayerCapture::new();
+
         let thread_data = ThreadData {
             cur_scanline: 0,
             shared_data: Arc::clone(&shared_data),
             fns: (FnPtrs::new(), FnPtrs::new()),
             renderer_3d_rx,
             buffers: [buffers!(), buffers!()],
+            time_counter: time_counter.clone(),
+            #[cfg(feature = "layer-capture")]
+            layer_capture: layer_capture.clone(),
         };
 
         Renderer {
@@ -232,9 +246,22 @@ impl Renderer {
                     })
                     .expect("couldn't spawn 2D rendering thread"),
             ),
+            time_counter,
+            #[cfg(feature = "layer-capture")]
+            layer_capture,
         }
     }
 
+    /// Returns a counter measuring the time the rendering thread spends rendering scanlines.
+    pub fn time_counter(&self) -> TimeCounter {
+        self.time_counter.clone()
+    }
+
+    #[cfg(feature = "layer-capture")]
+    pub fn layer_capture(&self) -> LayerCapture {
+        self.layer_capture.clone()
+    }
+
     fn flush_vram_updates<R: Role>(&mut self, vram: &mut vram::Vram) {
         let shared_vram = unsafe { &mut *self.shared_data.vram.get() };
         let updates = unsafe { vram.bg_obj_updates.as_mut().unwrap_unchecked() }.get_mut();
@@ -513,6 +540,9 @@ struct ThreadData {
     fns: (FnPtrs<EngineA>, FnPtrs<EngineB>),
     renderer_3d_rx: Box<dyn engine_3d::SoftRendererRx + Send + 'static>,
     buffers: [Buffers; 2],
+    time_counter: TimeCounter,
+    #[cfg(feature = "layer-capture")]
+    layer_capture: LayerCapture,
 }
 
 impl ThreadData {
@@ -556,6 +586,9 @@ impl ThreadData {
                     self.renderer_3d_rx.skip_scanline();
                 }
                 scanline_buffer.0.fill(0xFFFF_FFFF);
+                #[cfg(feature = "layer-capture")]
+                self.layer_capture
+                    .capture(!R::IS_A, self.cur_scanline as u8, None);
             } else {
                 let scanline_3d = if R::IS_A && data.engine_3d_enabled_in_frame {
                     let enabled_in_bg_obj = data.bgs[0].priority != 4 && data.control.bg0_3d();
@@ -597,13 +630,13 @@ impl ThreadData {
                             continue;
                         }
 
-                        let x_range = &data.window_x_ranges[i];
-                        let x_start = x_range.0 as usize;
-                        let mut x_end = x_range.1 as usize;
-                        if x_end < x_start {
-                            x_end = 256;
+                        let window_pixel = WindowPixel(data.window_control[i].0);
+                        for x_span in window_x_spans(
+                            data.window_x_ranges[i],
+                            data.windows_active.0 & 4 << i != 0,
+                        ) {
+                            window.0[x_span].fill(window_pixel);
                         }
-                        window.0[x_start..x_end].fill(WindowPixel(data.window_control[i].0));
                     }
 
                     let backdrop = BgObjPixel(rgb5_to_rgb6_64(vram.palette.read_le::<u16>(0)))
@@ -623,10 +656,22 @@ impl ThreadData {
                             vram,
                             scanline_3d,
                         );
+                    }
+                    #[cfg(feature = "layer-capture")]
+                    self.layer_capture.capture(
+                        !R::IS_A,
+                        self.cur_scanline as u8,
+                        Some(buffers.bg_obj_scanline.get_mut()),
+                    );
+                    unsafe {
                         fns.apply_color_effects[data.color_effects_control.color_effect() as usize](
                             buffers, data,
                         );
                     }
+                } else {
+                    #[cfg(feature = "layer-capture")]
+                    self.layer_capture
+                        .capture(!R::IS_A, self.cur_scanline as u8, None);
                 }
 
                 match display_mode {
@@ -702,8 +747,10 @@ impl ThreadData {
             let vcount = self.shared_data.vcount.load(Ordering::Acquire);
             let vram = unsafe { &*self.shared_data.vram.get() };
 
+            let start = self.time_counter.start();
             self.render_scanline::<EngineA>(vcount, &vram.0);
             self.render_scanline::<EngineB>(vcount, &vram.1);
+            self.time_counter.stop(start);
 
             self.shared_data
                 .processing_line
@@ -2,10 +2,11 @@ use super::utils::expand_depth;
 use dust_core::{
     gpu::{
         engine_3d::RenderingState,
-        engine_3d::{Color, Polygon, RenderingControl, ScreenVertex},
+        engine_3d::{Color, GxSnapshot, RenderingControl},
     },
     utils::Bytes,
 };
+use std::sync::Arc;
 
 pub struct RenderingData {
     pub control: RenderingControl,
@@ -29,10 +30,9 @@ pub struct RenderingData {
     pub texture: Bytes<0x8_0000>,
     pub tex_pal: Bytes<0x2_0000>,
 
-    pub vert_ram: [ScreenVertex; 6144],
-
-    pub poly_ram: [Polygon; 2048],
-    pub poly_ram_level: u16,
+    /// The geometry to render, shared with the 3D engine; `None` until the first frame is
+    /// submitted.
+    pub gx: Option<Arc<GxSnapshot>>,
 }
 
 impl RenderingData {
@@ -62,17 +62,9 @@ impl RenderingData {
     }
 
     #[inline]
-    pub fn prepare(
-        &mut self,
-        vert_ram: &[ScreenVertex],
-        poly_ram: &[Polygon],
-        state: &RenderingState,
-    ) {
+    pub fn prepare(&mut self, gx: &Arc<GxSnapshot>, state: &RenderingState) {
         self.w_buffering = state.w_buffering;
-
-        self.vert_ram[..vert_ram.len()].copy_from_slice(vert_ram);
-        self.poly_ram[..poly_ram.len()].copy_from_slice(poly_ram);
-        self.poly_ram_level = poly_ram.len() as u16;
+        self.gx = Some(Arc::clone(gx));
 
         self.copy_rendering_data(state);
     }
@@ -3,6 +3,9 @@
 
 mod data;
 pub use data::RenderingData;
+pub mod tex_pal;
+#[cfg(feature = "threaded")]
+pub mod threaded;
 mod utils;
 
 use core::simd::{cmp::SimdOrd, num::SimdUint};
@@ -49,6 +52,9 @@ proc_bitfield::bitfield! {
     struct PixelAttrs(pub u32): Debug {
         // Edge flag for the topmost opaque polygon (used for edge marking)
         pub is_opaque_edge: bool @ 0,
+        // Antialiasing coverage of the topmost opaque polygon's edge pixels (0x1F for fully
+        // covered pixels, whose color doesn't get blended with the one below them)
+        pub edge_coverage: u8 @ 1..=5,
 
         // Used so that a < depth test for a front-facing pixel over an opaque + back-facing one
         // becomes <=
@@ -73,8 +79,9 @@ proc_bitfield::bitfield! {
 
 impl PixelAttrs {
     #[inline]
-    fn from_opaque_poly_attrs(poly: &RenderingPolygon, is_edge: bool) -> Self {
+    fn from_opaque_poly_attrs(poly: &RenderingPolygon, is_edge: bool, coverage: u8) -> Self {
         PixelAttrs((poly.attrs.raw() & 0x3F00_8000) | is_edge as u32)
+            .with_edge_coverage(coverage)
             .with_front_facing(poly.attrs.is_front_facing())
     }
 
@@ -150,7 +157,7 @@ fn process_pixel<const FORMAT: u8, const MODE: u8>(
                 rgb5_to_rgb6(decode_rgb5(
                     rendering_data
                         .tex_pal
-                        .read_le::<u16>((pal_base + (color_index << 1)) & 0x1_FFFF),
+                        .read_le::<u16>(tex_pal::addr(pal_base, color_index << 1)),
                     (raw_alpha << 2 | raw_alpha >> 1) as u16,
                 ))
             }
@@ -162,7 +169,7 @@ fn process_pixel<const FORMAT: u8, const MODE: u8>(
                 rgb5_to_rgb6(decode_rgb5(
                     rendering_data
                         .tex_pal
-                        .read_le::<u16>(pal_base | color_index << 1),
+                        .read_le::<u16>(tex_pal::addr(pal_base, color_index << 1)),
                     if tex_params.use_color_0_as_transparent() && color_index == 0 {
                         0
                     } else {
@@ -178,7 +185,7 @@ fn process_pixel<const FORMAT: u8, const MODE: u8>(
                 rgb5_to_rgb6(decode_rgb5(
                     rendering_data
                         .tex_pal
-                        .read_le::<u16>((pal_base + (color_index << 1)) & 0x1_FFFF),
+                        .read_le::<u16>(tex_pal::addr(pal_base, color_index << 1)),
                     if tex_params.use_color_0_as_transparent() && color_index == 0 {
                         0
                     } else {
@@ -192,7 +199,7 @@ fn process_pixel<const FORMAT: u8, const MODE: u8>(
                 rgb5_to_rgb6(decode_rgb5(
                     rendering_data
                         .tex_pal
-                        .read_le::<u16>((pal_base + (color_index << 1)) & 0x1_FFFF),
+                        .read_le::<u16>(tex_pal::addr(pal_base, color_index << 1)),
                     if tex_params.use_color_0_as_transparent() && color_index == 0 {
                         0
                     } else {
@@ -220,7 +227,7 @@ fn process_pixel<const FORMAT: u8, const MODE: u8>(
                         decode_rgb5(
                             rendering_data
                                 .tex_pal
-                                .read_le::<u16>((pal_base + ($i << 1)) & 0x1_FFFE),
+                                .read_le::<u16>(tex_pal::addr(pal_base, $i << 1)),
                             0x1F,
                         )
                     };
@@ -274,7 +281,7 @@ fn process_pixel<const FORMAT: u8, const MODE: u8>(
                 rgb5_to_rgb6(decode_rgb5(
                     rendering_data
                         .tex_pal
-                        .read_le::<u16>((pal_base | color_index << 1) & 0x1_FFFF),
+                        .read_le::<u16>(tex_pal::addr(pal_base, color_index << 1)),
                     alpha as u16,
                 ))
             }
@@ -368,6 +375,8 @@ static PROCESS_PIXEL_TEXTURES_DISABLED: [ProcessPixelFn; 4] = [
 
 pub struct Renderer {
     color_buffer: Box<[Scanline<Color>; 192]>,
+    // The colors opaque polygon edge pixels were drawn over, used for antialiasing
+    below_color_buffer: Box<[Scanline<Color>; 192]>,
     depth_buffer: Box<[Scanline<u32, 258>; 194]>,
     attr_buffer: Box<[Scanline<PixelAttrs, 258>; 194]>,
     polys: Vec<RenderingPolygon>,
@@ -405,6 +414,7 @@ impl Renderer {
     pub fn new() -> Self {
         Renderer {
             color_buffer: unsafe { Box::new_zeroed().assume_init() },
+            below_color_buffer: unsafe { Box::new_zeroed().assume_init() },
             depth_buffer: unsafe { Box::new_zeroed().assume_init() },
             attr_buffer: unsafe { Box::new_zeroed().assume_init() },
             polys: Vec::with_capacity(2048),
@@ -412,11 +422,24 @@ impl Renderer {
     }
 
     pub fn start_frame(&mut self, rendering_data: &RenderingData) {
-        self.polys.clear();
+        Self::prepare_polys(&mut self.polys, rendering_data);
+        Self::fill_border_lines(
+            &mut self.depth_buffer,
+            &mut self.attr_buffer,
+            rendering_data,
+        );
+    }
+
+    fn prepare_polys(polys: &mut Vec<RenderingPolygon>, rendering_data: &RenderingData) {
+        polys.clear();
+
+        let Some(gx) = rendering_data.gx.as_deref() else {
+            return;
+        };
 
-        for poly_addr in 0..rendering_data.poly_ram_level {
+        for poly_addr in 0..gx.poly_ram_level() {
             let poly_addr = unsafe { PolyAddr::new_unchecked(poly_addr) };
-            let poly = &rendering_data.poly_ram[poly_addr.get() as usize];
+            let poly = &gx.poly_ram()[poly_addr.get() as usize];
             let verts_len = unsafe { poly.attrs.verts_len() };
 
             if verts_len.get() < 3 {
@@ -464,7 +487,7 @@ impl Renderer {
                 let mut top_i = PolyVertIndex::new(0);
                 let mut bot_i = top_i;
                 let mut top_vert_addr = poly.verts[0];
-                let mut top_vert = &rendering_data.vert_ram[top_vert_addr.get() as usize];
+                let mut top_vert = &gx.vert_ram()[top_vert_addr.get() as usize];
                 let mut bot_vert_addr = top_vert_addr;
                 let mut bot_vert = top_vert;
 
@@ -472,7 +495,7 @@ impl Renderer {
                     ($i: expr) => {{
                         let i = $i;
                         let vert_addr = poly.verts[i.get() as usize];
-                        let vert = &rendering_data.vert_ram[vert_addr.get() as usize];
+                        let vert = &gx.vert_ram()[vert_addr.get() as usize];
                         if vert.coords[0] < top_vert.coords[0] {
                             top_i = i;
                             top_vert_addr = vert_addr;
@@ -489,7 +512,7 @@ impl Renderer {
                 vert!(PolyVertIndex::new(1));
                 vert!(PolyVertIndex::new(verts_len.get() - 1));
 
-                self.polys.push(RenderingPolygon {
+                polys.push(RenderingPolygon {
                     poly_addr,
                     attrs: poly.attrs,
                     is_shadow,
@@ -518,7 +541,7 @@ impl Renderer {
                     for i in 0..verts_len.get() as usize {
                         let i = PolyVertIndex::new(i as u8);
                         let vert_addr = poly.verts[i.get() as usize];
-                        let vert = &rendering_data.vert_ram[vert_addr.get() as usize];
+                        let vert = &gx.vert_ram()[vert_addr.get() as usize];
                         if vert.coords[1] as u8 == top_y && top_vert.is_none() {
                             top_i = i;
                             top_vert = Some((vert_addr, vert));
@@ -535,7 +558,7 @@ impl Renderer {
                     ($i: expr) => {{
                         let i = $i;
                         let addr = poly.verts[i.get() as usize];
-                        (i, addr, &rendering_data.vert_ram[addr.get() as usize])
+                        (i, addr, &gx.vert_ram()[addr.get() as usize])
                     }};
                 }
 
@@ -548,7 +571,7 @@ impl Renderer {
                     other_verts.swap(0, 1);
                 }
 
-                self.polys.push(RenderingPolygon {
+                polys.push(RenderingPolygon {
                     poly_addr,
                     attrs: poly.attrs,
                     is_shadow,
@@ -587,21 +610,44 @@ impl Renderer {
                 });
             }
         }
+    }
 
+    fn fill_border_lines(
+        depth_buffer: &mut [Scanline<u32, 258>; 194],
+        attr_buffer: &mut [Scanline<PixelAttrs, 258>; 194],
+        rendering_data: &RenderingData,
+    ) {
         // The bitmap rear plane's out-of-screen pixels get the same depth and attributes as a
         // non-bitmap rear plane (but the fog flag isn't copied since it's unneeded)
         let outside_pixel_attrs = PixelAttrs(0).with_opaque_poly_id(rendering_data.clear_poly_id);
-        self.depth_buffer[0].0.fill(rendering_data.clear_depth);
-        self.depth_buffer[193].0.fill(rendering_data.clear_depth);
-        self.attr_buffer[0].0.fill(outside_pixel_attrs);
-        self.attr_buffer[193].0.fill(outside_pixel_attrs);
+        depth_buffer[0].0.fill(rendering_data.clear_depth);
+        depth_buffer[193].0.fill(rendering_data.clear_depth);
+        attr_buffer[0].0.fill(outside_pixel_attrs);
+        attr_buffer[193].0.fill(outside_pixel_attrs);
     }
 
     pub fn render_line(&mut self, y: u8, rendering_data: &RenderingData) {
-        let color_line = &mut self.color_buffer[y as usize].0;
-        let depth_full_line = &mut self.depth_buffer[y as usize + 1].0;
-        let attr_full_line = &mut self.attr_buffer[y as usize + 1].0;
+        Self::render_polys_line(
+            &mut self.polys,
+            y,
+            rendering_data,
+            &mut self.color_buffer[y as usize].0,
+            &mut self.below_color_buffer[y as usize].0,
+            &mut self.depth_buffer[y as usize + 1].0,
+            &mut self.attr_buffer[y as usize + 1].0,
+        );
+    }
 
+    #[allow(clippy::too_many_arguments)]
+    fn render_polys_line(
+        polys: &mut [RenderingPolygon],
+        y: u8,
+        rendering_data: &RenderingData,
+        color_line: &mut [Color; 256],
+        below_color_line: &mut [Color; 256],
+        depth_full_line: &mut [u32; 258],
+        attr_full_line: &mut [PixelAttrs; 258],
+    ) {
         if rendering_data.control.rear_plane_bitmap_enabled() {
             let line_base = (y.wrapping_add(rendering_data.clear_image_offset[1]) as usize) << 9;
             let mut x_in_image = rendering_data.clear_image_offset[0];
@@ -649,10 +695,14 @@ impl Renderer {
             );
         }
 
+        let Some(gx) = rendering_data.gx.as_deref() else {
+            return;
+        };
+
         let depth_line = <&mut [_; 256]>::try_from(&mut depth_full_line[1..257]).unwrap();
         let attr_line = <&mut [_; 256]>::try_from(&mut attr_full_line[1..257]).unwrap();
 
-        for poly in self.polys.iter_mut() {
+        for poly in polys.iter_mut() {
             if y.wrapping_sub(poly.top_y) >= poly.height {
                 continue;
             }
@@ -661,9 +711,10 @@ impl Renderer {
                 ranges,
                 fill_edges,
                 [(l_vert_color, l_uv, l_depth, l_w), (r_vert_color, r_uv, r_depth, r_w)],
+                line_edges,
             ) = match &mut poly.edges {
                 Edges::Normal(edges) => {
-                    let raw_poly = rendering_data.poly_ram[poly.poly_addr.get() as usize];
+                    let raw_poly = gx.poly_ram()[poly.poly_addr.get() as usize];
                     let verts_len = unsafe { raw_poly.attrs.verts_len() };
 
                     macro_rules! process_edge {
@@ -672,8 +723,7 @@ impl Renderer {
                             if y >= $edge.b_y() && $vert_i != poly.bot_i {
                                 let mut prev_i = $vert_i;
                                 let mut prev_vert_addr = $edge.b_addr();
-                                let mut prev_vert =
-                                    &rendering_data.vert_ram[prev_vert_addr.get() as usize];
+                                let mut prev_vert = &gx.vert_ram()[prev_vert_addr.get() as usize];
 
                                 while true {
                                     let i = if increasing {
@@ -682,7 +732,7 @@ impl Renderer {
                                         dec_poly_vert_index(prev_i, verts_len)
                                     };
                                     let vert_addr = raw_poly.verts[i.get() as usize];
-                                    let vert = &rendering_data.vert_ram[vert_addr.get() as usize];
+                                    let vert = &gx.vert_ram()[vert_addr.get() as usize];
 
                                     if vert.coords[1] as u8 > y || i == poly.bot_i {
                                         $edge = Edge::new(
@@ -722,8 +772,8 @@ impl Renderer {
                     macro_rules! interp_edge {
                         ($i: expr, $x: expr) => {{
                             let edge = edges[$i];
-                            let a = &rendering_data.vert_ram[edge.a_addr().get() as usize];
-                            let b = &rendering_data.vert_ram[edge.b_addr().get() as usize];
+                            let a = &gx.vert_ram()[edge.a_addr().get() as usize];
+                            let b = &gx.vert_ram()[edge.b_addr().get() as usize];
                             let interp = edge.edge_interp(y, $x);
                             let vert_color = interp.color(a.color, b.color);
                             let uv = interp.uv(a.uv, b.uv);
@@ -747,11 +797,12 @@ impl Renderer {
                                 || (y + 1 == poly.bot_y && edges[1].is_x_major() && next_is_horiz),
                         ],
                         [interp_edge!(0, ranges[0].0), interp_edge!(1, ranges[1].1)],
+                        Some([*edges[0], *edges[1]]),
                     )
                 }
                 Edges::Dummy(edges) => {
-                    let l_v = rendering_data.vert_ram[edges[0].addr().get() as usize];
-                    let r_v = rendering_data.vert_ram[edges[1].addr().get() as usize];
+                    let l_v = gx.vert_ram()[edges[0].addr().get() as usize];
+                    let r_v = gx.vert_ram()[edges[1].addr().get() as usize];
                     (
                         [edges[0].line_x_range(), edges[1].line_x_range()],
                         [true, true],
@@ -759,6 +810,7 @@ impl Renderer {
                             (l_v.color, l_v.uv, edges[0].z(), edges[0].w()),
                             (r_v.color, r_v.uv, edges[1].z(), edges[1].w()),
                         ],
+                        None,
                     )
                 }
             };
@@ -782,7 +834,7 @@ impl Renderer {
             let x_interp = InterpLineData::<false>::new(l_w, r_w);
 
             macro_rules! render_pixel {
-                ($x: expr, $is_edge: expr) => {{
+                ($x: expr, $is_edge: expr, $coverage: expr) => {{
                     let x = $x;
                     let is_edge = $is_edge;
 
@@ -801,9 +853,14 @@ impl Renderer {
                         let alpha = color[3];
                         if alpha > rendering_data.alpha_test_ref as u16 {
                             if alpha == 0x1F {
+                                let coverage = $coverage;
+                                if coverage != 0x1F {
+                                    below_color_line[x] = color_line[x];
+                                }
                                 color_line[x] = color.cast();
                                 depth_line[x] = depth;
-                                attr_line[x] = PixelAttrs::from_opaque_poly_attrs(poly, is_edge);
+                                attr_line[x] =
+                                    PixelAttrs::from_opaque_poly_attrs(poly, is_edge, coverage);
                             } else {
                                 let prev_attrs = attr_line[x];
                                 if prev_attrs.translucent_poly_id() != poly.id | 0x40 {
@@ -830,19 +887,31 @@ impl Renderer {
                 }};
             }
 
+            let line_edges = line_edges.filter(|_| rendering_data.control.antialiasing_enabled());
+
             for i in 0..2 {
                 if fill_edges[i] {
                     // If the range is out-of-screen don't render it
                     let (start, end) = clip_x_range(ranges[i]);
                     for x in start..=end {
-                        render_pixel!(x as u16, true);
+                        let x = x as u16;
+                        render_pixel!(
+                            x,
+                            true,
+                            line_edges.map_or(0x1F, |edges| edges[i].coverage(
+                                y,
+                                x,
+                                ranges[i],
+                                i == 1
+                            ))
+                        );
                     }
                 }
             }
 
             if !is_wireframe || is_at_y_boundary {
                 for x in ranges[0].1 + 1..ranges[1].0 {
-                    render_pixel!(x, is_at_y_boundary);
+                    render_pixel!(x, is_at_y_boundary, 0x1F);
                 }
             }
         }
@@ -854,33 +923,58 @@ impl Renderer {
         scanline: &mut Scanline<u32>,
         rendering_data: &RenderingData,
     ) {
-        let color_line = &mut self.color_buffer[y as usize].0;
+        let y = y as usize;
+        Self::postprocess_color_line(
+            &mut self.color_buffer[y].0,
+            &self.below_color_buffer[y].0,
+            [
+                &self.depth_buffer[y].0,
+                &self.depth_buffer[y + 1].0,
+                &self.depth_buffer[y + 2].0,
+            ],
+            [
+                &self.attr_buffer[y].0,
+                &self.attr_buffer[y + 1].0,
+                &self.attr_buffer[y + 2].0,
+            ],
+            scanline,
+            rendering_data,
+        );
+    }
 
+    // The depth and attribute lines go from the one above the current line to the one below it,
+    // as they're needed for edge marking
+    fn postprocess_color_line(
+        color_line: &mut [Color; 256],
+        below_color_line: &[Color; 256],
+        depth_lines: [&[u32; 258]; 3],
+        attr_lines: [&[PixelAttrs; 258]; 3],
+        scanline: &mut Scanline<u32>,
+        rendering_data: &RenderingData,
+    ) {
         if rendering_data.control.edge_marking_enabled() {
-            let y_ = y as usize + 1;
-
             for (x, color_dst) in color_line.iter_mut().enumerate() {
                 let x_ = x + 1;
 
-                let attrs = self.attr_buffer[y_].0[x_];
+                let attrs = attr_lines[1][x_];
                 if !attrs.is_opaque_edge() {
                     continue;
                 }
 
                 let opaque_poly_id = attrs.opaque_poly_id();
-                let depth = self.depth_buffer[y_].0[x_];
+                let depth = depth_lines[1][x_];
 
                 macro_rules! has_edge {
-                    ($x: expr, $y: expr) => {
-                        (depth < self.depth_buffer[$y].0[$x]
-                            && self.attr_buffer[$y].0[$x].opaque_poly_id() != opaque_poly_id)
+                    ($x: expr, $line: expr) => {
+                        (depth < depth_lines[$line][$x]
+                            && attr_lines[$line][$x].opaque_poly_id() != opaque_poly_id)
                     };
                 }
 
-                if has_edge!(x_, y_ - 1)
-                    || has_edge!(x_, y_ + 1)
-                    || has_edge!(x_ - 1, y_)
-                    || has_edge!(x_ + 1, y_)
+                if has_edge!(x_, 0)
+                    || has_edge!(x_, 2)
+                    || has_edge!(x_ - 1, 1)
+                    || has_edge!(x_ + 1, 1)
                 {
                     let edge_color = rendering_data.edge_colors[(opaque_poly_id >> 3) as usize];
                     if rendering_data.control.antialiasing_enabled() {
@@ -892,10 +986,30 @@ impl Renderer {
             }
         }
 
-        let depth_line =
-            <&mut [_; 256]>::try_from(&mut self.depth_buffer[y as usize + 1].0[1..257]).unwrap();
-        let attr_line =
-            <&mut [_; 256]>::try_from(&mut self.attr_buffer[y as usize + 1].0[1..257]).unwrap();
+        let depth_line = <&[_; 256]>::try_from(&depth_lines[1][1..257]).unwrap();
+        let attr_line = <&[_; 256]>::try_from(&attr_lines[1][1..257]).unwrap();
+
+        if rendering_data.control.antialiasing_enabled() {
+            for x in 0..256 {
+                let attrs = attr_line[x];
+                let coverage = attrs.edge_coverage() as u16;
+                if !attrs.is_opaque_edge() || attrs.translucent() || coverage == 0x1F {
+                    continue;
+                }
+                let below_color = below_color_line[x].cast::<u16>();
+                let mut color = color_line[x].cast::<u16>();
+                if below_color[3] == 0 {
+                    // Nothing was drawn below, so only fade the edge out to let the 2D layers
+                    // below show through
+                    color[3] = (color[3] * (coverage + 1)) >> 5;
+                } else {
+                    color = (color * InterpColor::splat(coverage + 1)
+                        + below_color * InterpColor::splat(31 - coverage))
+                        >> 5;
+                }
+                color_line[x] = color.cast();
+            }
+        }
 
         if rendering_data.control.fog_enabled() {
             macro_rules! fog_density {
@@ -0,0 +1,55 @@
+//! Texture palette addressing, shared by all 3D renderers so that they agree on what out-of-range
+//! palette bases read.
+//!
+//! The palette address space is 128 KiB, of which only the first 96 KiB can be mapped; the rest
+//! reads as zero. Addresses wrap around at its end, and bit 0 is ignored as entries are halfwords.
+
+/// The size of the texture palette address space, in bytes.
+pub const SIZE: usize = 0x2_0000;
+
+/// The mask applied to every texture palette address.
+pub const ADDR_MASK: usize = SIZE - 2;
+
+/// Returns the address of the palette entry `offset` bytes after `base`, wrapping around at the
+/// end of the texture palette address space.
+#[inline]
+pub fn addr(base: usize, offset: usize) -> usize {
+    (base + offset) & ADDR_MASK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn in_range() {
+        assert_eq!(addr(0, 0), 0);
+        assert_eq!(addr(0x1_8000, 0x1E), 0x1_801E);
+        assert_eq!(addr(0x1_FFF0, 0xE), 0x1_FFFE);
+    }
+
+    #[test]
+    fn wraps_around() {
+        assert_eq!(addr(0x1_FFF0, 0x10), 0);
+        assert_eq!(addr(0x1_FFF0, 0x12), 2);
+        assert_eq!(addr(SIZE, 0), 0);
+        assert_eq!(addr(SIZE * 3 + 4, 0x20), 0x24);
+    }
+
+    #[test]
+    fn ignores_bit_0() {
+        assert_eq!(addr(1, 0), 0);
+        assert_eq!(addr(0x10, 3), 0x12);
+        assert_eq!(addr(0x1_FFFF, 0), 0x1_FFFE);
+        assert_eq!(addr(0x1_FFFF, 2), 0);
+    }
+
+    #[test]
+    fn always_in_bounds_for_halfword_reads() {
+        for base in (0..SIZE * 2).step_by(0x7F) {
+            for offset in [0, 1, 2, 0x1FE, 0x1FF] {
+                assert!(addr(base, offset) + 1 < SIZE);
+            }
+        }
+    }
+}
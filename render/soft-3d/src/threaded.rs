@@ -0,0 +1,209 @@
+use super::{PixelAttrs, Renderer as RawRenderer, RenderingData, RenderingPolygon};
+use core::{
+    cell::UnsafeCell,
+    hint,
+    num::NonZeroUsize,
+    sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+};
+use dust_core::gpu::{engine_3d::Color, Scanline, SCREEN_HEIGHT};
+use std::{sync::Arc, thread};
+
+struct Buffers {
+    color: [Scanline<Color>; SCREEN_HEIGHT],
+    below_color: [Scanline<Color>; SCREEN_HEIGHT],
+    depth: [Scanline<u32, 258>; SCREEN_HEIGHT + 2],
+    attr: [Scanline<PixelAttrs, 258>; SCREEN_HEIGHT + 2],
+}
+
+struct SharedData {
+    stopped: AtomicBool,
+    // Incremented every time a new frame is started, to wake up workers
+    frame_index: AtomicU32,
+    running_workers: AtomicUsize,
+    // The next line that hasn't been claimed by any thread yet; since it only ever increases, each
+    // thread renders its lines in order, which lets it keep track of polygon edges incrementally
+    next_line: AtomicUsize,
+    rendered_lines: [AtomicBool; SCREEN_HEIGHT],
+
+    rendering_data: UnsafeCell<*const RenderingData>,
+    polys: UnsafeCell<Vec<RenderingPolygon>>,
+    buffers: Box<UnsafeCell<Buffers>>,
+}
+
+unsafe impl Sync for SharedData {}
+unsafe impl Send for SharedData {}
+
+impl SharedData {
+    /// # Safety
+    /// Must only be called while a frame is being rendered, once per line.
+    unsafe fn render_line(&self, polys: &mut [RenderingPolygon], y: usize) {
+        let buffers = self.buffers.get();
+        RawRenderer::render_polys_line(
+            polys,
+            y as u8,
+            &**self.rendering_data.get(),
+            &mut (*buffers).color[y].0,
+            &mut (*buffers).below_color[y].0,
+            &mut (*buffers).depth[y + 1].0,
+            &mut (*buffers).attr[y + 1].0,
+        );
+        self.rendered_lines[y].store(true, Ordering::Release);
+    }
+
+    /// Claims and renders the next line that hasn't been claimed yet, returning `false` if all
+    /// lines were already claimed.
+    ///
+    /// # Safety
+    /// Must only be called while a frame is being rendered.
+    unsafe fn steal_line(&self, polys: &mut [RenderingPolygon]) -> bool {
+        let y = self.next_line.fetch_add(1, Ordering::Relaxed);
+        if y >= SCREEN_HEIGHT {
+            return false;
+        }
+        self.render_line(polys, y);
+        true
+    }
+
+    fn is_line_rendered(&self, y: usize) -> bool {
+        y >= SCREEN_HEIGHT || self.rendered_lines[y].load(Ordering::Acquire)
+    }
+}
+
+/// A software 3D renderer that splits the rasterization of scanlines across a pool of worker
+/// threads, while still postprocessing them in order on the calling thread.
+pub struct Renderer {
+    shared_data: Arc<SharedData>,
+    workers: Vec<thread::JoinHandle<()>>,
+    polys: Vec<RenderingPolygon>,
+}
+
+impl Renderer {
+    /// Creates a renderer that will use `threads` threads in total, including the one calling
+    /// [`render_frame`](Self::render_frame).
+    pub fn new(threads: NonZeroUsize) -> Self {
+        let shared_data = Arc::new(SharedData {
+            stopped: AtomicBool::new(false),
+            frame_index: AtomicU32::new(0),
+            running_workers: AtomicUsize::new(0),
+            next_line: AtomicUsize::new(SCREEN_HEIGHT),
+            rendered_lines: [const { AtomicBool::new(false) }; SCREEN_HEIGHT],
+            rendering_data: UnsafeCell::new(core::ptr::null()),
+            polys: UnsafeCell::new(Vec::with_capacity(2048)),
+            buffers: unsafe { Box::new_zeroed().assume_init() },
+        });
+
+        let workers = (1..threads.get())
+            .map(|i| {
+                let shared_data = Arc::clone(&shared_data);
+                thread::Builder::new()
+                    .name(format!("3D rendering worker {i}"))
+                    .spawn(move || {
+                        let mut polys = Vec::with_capacity(2048);
+                        let mut last_frame_index = 0;
+                        loop {
+                            if shared_data.stopped.load(Ordering::Relaxed) {
+                                return;
+                            }
+                            let frame_index = shared_data.frame_index.load(Ordering::Acquire);
+                            if frame_index == last_frame_index {
+                                thread::park();
+                                continue;
+                            }
+                            last_frame_index = frame_index;
+                            unsafe {
+                                polys.clone_from(&*shared_data.polys.get());
+                                while shared_data.steal_line(&mut polys) {}
+                            }
+                            shared_data.running_workers.fetch_sub(1, Ordering::Release);
+                        }
+                    })
+                    .expect("couldn't spawn 3D rendering worker thread")
+            })
+            .collect();
+
+        Renderer {
+            shared_data,
+            workers,
+            polys: Vec::with_capacity(2048),
+        }
+    }
+
+    /// Renders a whole frame, calling `line_done` with each postprocessed scanline in order as soon
+    /// as it's ready.
+    pub fn render_frame(
+        &mut self,
+        rendering_data: &RenderingData,
+        mut line_done: impl FnMut(u8, &Scanline<u32>),
+    ) {
+        let shared_data = &*self.shared_data;
+
+        // No workers are running at this point, so the shared state can be freely modified
+        unsafe {
+            *shared_data.rendering_data.get() = rendering_data;
+            let polys = &mut *shared_data.polys.get();
+            RawRenderer::prepare_polys(polys, rendering_data);
+            self.polys.clone_from(polys);
+            let buffers = &mut *shared_data.buffers.get();
+            RawRenderer::fill_border_lines(&mut buffers.depth, &mut buffers.attr, rendering_data);
+        }
+        for rendered in &shared_data.rendered_lines {
+            rendered.store(false, Ordering::Relaxed);
+        }
+        shared_data.next_line.store(0, Ordering::Relaxed);
+        shared_data
+            .running_workers
+            .store(self.workers.len(), Ordering::Relaxed);
+        shared_data.frame_index.fetch_add(1, Ordering::Release);
+        for worker in &self.workers {
+            worker.thread().unpark();
+        }
+
+        let mut scanline = Scanline([0; 256]);
+        for y in 0..SCREEN_HEIGHT {
+            // Edge marking needs the lines above and below the current one to be rendered too;
+            // the one above was already checked for the previous line
+            while !(shared_data.is_line_rendered(y) && shared_data.is_line_rendered(y + 1)) {
+                // Help rendering the remaining lines instead of waiting idly
+                if !unsafe { shared_data.steal_line(&mut self.polys) } {
+                    hint::spin_loop();
+                }
+            }
+
+            unsafe {
+                let buffers = shared_data.buffers.get();
+                RawRenderer::postprocess_color_line(
+                    &mut (*buffers).color[y].0,
+                    &(*buffers).below_color[y].0,
+                    [
+                        &(*buffers).depth[y].0,
+                        &(*buffers).depth[y + 1].0,
+                        &(*buffers).depth[y + 2].0,
+                    ],
+                    [
+                        &(*buffers).attr[y].0,
+                        &(*buffers).attr[y + 1].0,
+                        &(*buffers).attr[y + 2].0,
+                    ],
+                    &mut scanline,
+                    rendering_data,
+                );
+            }
+            line_done(y as u8, &scanline);
+        }
+
+        // Wait for all workers to stop accessing the frame's data before returning
+        while shared_data.running_workers.load(Ordering::Acquire) != 0 {
+            hint::spin_loop();
+        }
+    }
+}
+
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        self.shared_data.stopped.store(true, Ordering::Relaxed);
+        for worker in self.workers.drain(..) {
+            worker.thread().unpark();
+            let _ = worker.join();
+        }
+    }
+}
@@ -198,13 +198,17 @@ impl Edge {
         self.is_x_major
     }
 
-    pub fn line_x_range(&self, y: u8) -> (u16, u16) {
+    fn line_start_frac_x(&self, y: u8) -> u32 {
         let line_x_disp = self.x_incr * (y - self.a_y) as u32;
-        let start_frac_x = if self.is_negative {
+        if self.is_negative {
             self.x_ref - line_x_disp
         } else {
             self.x_ref + line_x_disp
-        };
+        }
+    }
+
+    pub fn line_x_range(&self, y: u8) -> (u16, u16) {
+        let start_frac_x = self.line_start_frac_x(y);
         let start_x = (start_frac_x >> 18) as u16;
         if self.is_x_major {
             if self.is_negative {
@@ -226,6 +230,28 @@ impl Edge {
         }
     }
 
+    /// Returns the approximate coverage (from 0 to 0x1F) of the pixel at `x` in the given line by
+    /// the polygon the edge belongs to, for antialiasing. `x_range` is the edge's span in the line,
+    /// and `is_right` indicates whether the polygon's interior lies to the left of the edge.
+    pub fn coverage(&self, y: u8, x: u16, (start_x, end_x): (u16, u16), is_right: bool) -> u8 {
+        if self.is_x_major {
+            // The edge crosses the line diagonally, so coverage increases linearly towards the
+            // polygon's interior along its span
+            let pos = if is_right { end_x - x } else { x - start_x } as u32;
+            let len = (end_x - start_x + 1) as u32;
+            (((pos << 1 | 1) << 4) / len).min(0x1F) as u8
+        } else if self.x_incr == 0 {
+            0x1F
+        } else {
+            let frac_x = (self.line_start_frac_x(y) >> 13 & 0x1F) as u8;
+            if is_right {
+                frac_x
+            } else {
+                0x1F - frac_x
+            }
+        }
+    }
+
     pub fn edge_interp(&self, y: u8, x: u16) -> InterpData<true> {
         self.interp_data.set_x(
             if self.is_x_major {
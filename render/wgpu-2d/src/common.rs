@@ -77,3 +77,42 @@ impl ScanlineFlags {
         }
     }
 }
+
+/// The kinds of rotated/scaled BGs that can be resampled on the GPU; a kind of 0 is used for BGs
+/// that are disabled or can only be displayed at native resolution.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum AffineBgKind {
+    Affine = 1,
+    ExtendedTiled,
+    Bitmap,
+    DirectColorBitmap,
+}
+
+proc_bitfield::bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq, Default)]
+    #[repr(transparent)]
+    pub struct AffineBgParams(pub u32): Debug {
+        pub kind: u8 @ 0..=2,
+        pub display_area_overflow: bool @ 3,
+        pub size_key: u8 @ 4..=5,
+        pub is_engine_b: bool @ 6,
+        pub ext_pal_enabled: bool @ 7,
+        pub ext_pal_slot: u8 @ 8..=9,
+    }
+}
+
+/// The state of a rotated/scaled BG at the start of a scanline, used to resample it at the output
+/// resolution.
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+#[allow(dead_code)] // These are read from WGSL
+pub struct AffineBgLine {
+    pub pos: [i32; 2],
+    pub x_incr: [i32; 2],
+    pub y_incr: [i32; 2],
+    pub params: AffineBgParams,
+    pub map_base: u32,
+    pub tile_base: u32,
+    pub _padding: u32,
+}
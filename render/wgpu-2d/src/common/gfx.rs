@@ -1,8 +1,9 @@
-use super::{BgObjPixel, ScanlineFlags};
-use dust_core::gpu::{engine_3d, Scanline, SCREEN_HEIGHT, SCREEN_WIDTH};
+use super::{AffineBgLine, BgObjPixel, ScanlineFlags};
+use dust_core::gpu::{engine_2d::Role, engine_3d, Scanline, SCREEN_HEIGHT, SCREEN_WIDTH};
 use emu_utils::{resource_str, triple_buffer};
 use parking_lot::RwLock;
 use std::{
+    mem,
     num::NonZeroU64,
     slice,
     sync::{
@@ -66,7 +67,7 @@ pub struct OutputAttachments {
 }
 
 impl OutputAttachments {
-    pub fn new(device: &wgpu::Device, resolution_scale_shift: u8) -> (Self, wgpu::TextureView) {
+    pub fn new(device: &wgpu::Device, resolution_scale_shift: u8) -> (Self, Arc<wgpu::Texture>) {
         let resolution_scale = 1 << resolution_scale_shift;
 
         let color = device.create_texture(&wgpu::TextureDescriptor {
@@ -88,30 +89,28 @@ impl OutputAttachments {
             ..wgpu::TextureViewDescriptor::default()
         });
 
-        let color_view_clone = color.create_view(&Default::default());
-
-        (OutputAttachments { color_view }, color_view_clone)
+        (OutputAttachments { color_view }, Arc::new(color))
     }
 }
 
 pub struct FrontendChannels {
-    color_output_view_rx: crossbeam_channel::Receiver<wgpu::TextureView>,
+    color_output_texture_rx: crossbeam_channel::Receiver<Arc<wgpu::Texture>>,
     renderer_3d_rx_tx: crossbeam_channel::Sender<Renderer3dRx>,
 }
 
 impl FrontendChannels {
     pub fn new(
-        color_output_view_rx: crossbeam_channel::Receiver<wgpu::TextureView>,
+        color_output_texture_rx: crossbeam_channel::Receiver<Arc<wgpu::Texture>>,
         renderer_3d_rx_tx: crossbeam_channel::Sender<Renderer3dRx>,
     ) -> Self {
         FrontendChannels {
-            color_output_view_rx,
+            color_output_texture_rx,
             renderer_3d_rx_tx,
         }
     }
 
-    pub fn new_color_output_view(&self) -> Option<wgpu::TextureView> {
-        self.color_output_view_rx.try_iter().last()
+    pub fn new_color_output_texture(&self) -> Option<Arc<wgpu::Texture>> {
+        self.color_output_texture_rx.try_iter().last()
     }
 
     pub fn set_renderer_3d_rx(&self, renderer_3d_rx: Renderer3dRx) {
@@ -124,19 +123,25 @@ impl FrontendChannels {
 pub struct SharedData {
     stopped: AtomicBool,
     resolution_scale_shift: AtomicU8,
+    hi_res_affine_bgs: AtomicBool,
 }
 
 impl SharedData {
-    pub fn new(resolution_scale_shift: u8) -> Self {
+    pub fn new(resolution_scale_shift: u8, hi_res_affine_bgs: bool) -> Self {
         SharedData {
             stopped: AtomicBool::new(false),
             resolution_scale_shift: AtomicU8::new(resolution_scale_shift),
+            hi_res_affine_bgs: AtomicBool::new(hi_res_affine_bgs),
         }
     }
 
     pub fn set_resolution_scale_shift(&self, value: u8) {
         self.resolution_scale_shift.store(value, Ordering::Relaxed);
     }
+
+    pub fn set_hi_res_affine_bgs(&self, value: bool) {
+        self.hi_res_affine_bgs.store(value, Ordering::Relaxed);
+    }
 }
 
 struct RenderThreadChannels {
@@ -150,21 +155,53 @@ impl RenderThreadChannels {
 }
 
 struct GfxThreadChannels {
-    color_output_view_tx: crossbeam_channel::Sender<wgpu::TextureView>,
+    color_output_texture_tx: crossbeam_channel::Sender<Arc<wgpu::Texture>>,
 }
 
 impl GfxThreadChannels {
-    fn set_color_output_view(&self, color_output_view: wgpu::TextureView) {
-        self.color_output_view_tx
-            .send(color_output_view)
-            .expect("couldn't send new color output view");
+    fn set_color_output_texture(&self, color_output_texture: Arc<wgpu::Texture>) {
+        self.color_output_texture_tx
+            .send(color_output_texture)
+            .expect("couldn't send new color output texture");
     }
 }
 
+// The BG data needed to resample rotated/scaled BGs on the GPU, for both engines; it's only
+// captured at the end of the frame, so mid-frame updates will only be visible at native resolution.
+#[repr(C)]
+struct AffineBgVram {
+    bg_a: [u8; 0x8_0000],
+    bg_b: [u8; 0x2_0000],
+    bg_palettes: [[u8; 0x200]; 2],
+    bg_ext_palettes: [[u8; 0x8000]; 2],
+}
+
+// For each pixel, which affine BG (0 for none, 1 for BG2 or 2 for BG3) the top layer (bits 0-1)
+// and bottom layer (bits 2-3) come from
+type AffineBgLayers = [[[u8; SCREEN_WIDTH]; SCREEN_HEIGHT]; 2];
+
+// Windows are applied at native resolution before this, so a layer only gets resampled inside the
+// native pixels its BG was visible in and window edges stay aligned to them at any scale.
+fn affine_bg_layers(pixel: BgObjPixel) -> u8 {
+    fn layer(color_effects_mask: u8) -> u8 {
+        match color_effects_mask {
+            4 => 1,
+            8 => 2,
+            _ => 0,
+        }
+    }
+
+    layer(pixel.color_effects_mask()) | layer(pixel.bot_color_effects_mask()) << 2
+}
+
 struct FrameData {
     output_3d: Box<[Scanline<u32>; SCREEN_HEIGHT]>,
     framebuffer: Box<[[Scanline<BgObjPixel>; SCREEN_HEIGHT]; 2]>,
     fb_scanline_flags: Box<[[ScanlineFlags; SCREEN_HEIGHT]; 2]>,
+    affine_bg_lines: Box<[[[AffineBgLine; 2]; SCREEN_HEIGHT]; 2]>,
+    affine_bg_layers: Box<AffineBgLayers>,
+    affine_bg_vram: Box<AffineBgVram>,
+    hi_res_affine_bgs: bool,
     engine_3d_enabled: bool,
     frame_index: u64,
 }
@@ -176,6 +213,10 @@ impl Default for FrameData {
                 output_3d: Box::new_zeroed().assume_init(),
                 framebuffer: Box::new_zeroed().assume_init(),
                 fb_scanline_flags: Box::new_zeroed().assume_init(),
+                affine_bg_lines: Box::new_zeroed().assume_init(),
+                affine_bg_layers: Box::new_zeroed().assume_init(),
+                affine_bg_vram: Box::new_zeroed().assume_init(),
+                hi_res_affine_bgs: false,
                 engine_3d_enabled: false,
                 frame_index: 0,
             }
@@ -189,6 +230,7 @@ pub struct GfxData {
     frame_data_tx: triple_buffer::Sender<FrameData>,
     renderer_3d_data: Renderer3dRenderThreadData,
     renderer_3d_data_tx: crossbeam_channel::Sender<Renderer3dUpdateGfxThreadData>,
+    hi_res_affine_bgs_in_frame: bool,
     cur_frame_index: u64,
     thread: Option<thread::JoinHandle<()>>,
 }
@@ -222,11 +264,11 @@ impl GfxData {
         device: Arc<wgpu::Device>,
         queue: Arc<wgpu::Queue>,
         shared_data: Arc<SharedData>,
-        color_output_view_tx: crossbeam_channel::Sender<wgpu::TextureView>,
+        color_output_texture_tx: crossbeam_channel::Sender<Arc<wgpu::Texture>>,
         renderer_3d_rx_rx: crossbeam_channel::Receiver<Renderer3dRx>,
         resolution_scale_shift: u8,
         renderer_3d_rx: Renderer3dRx,
-    ) -> (Self, wgpu::TextureView) {
+    ) -> (Self, Arc<wgpu::Texture>) {
         let (renderer_3d_render_data, renderer_3d_gfx_data) =
             Self::create_renderer_3d_update_data(renderer_3d_rx);
 
@@ -237,12 +279,12 @@ impl GfxData {
         ]);
         let (renderer_3d_data_tx, renderer_3d_data_rx) = crossbeam_channel::unbounded();
 
-        let (thread_data, color_output_view) = GfxThreadData::new(
+        let (thread_data, color_output_texture) = GfxThreadData::new(
             device,
             queue,
             Arc::clone(&shared_data),
             GfxThreadChannels {
-                color_output_view_tx,
+                color_output_texture_tx,
             },
             resolution_scale_shift,
             frame_data_rx,
@@ -257,6 +299,7 @@ impl GfxData {
                 frame_data_tx,
                 renderer_3d_data: renderer_3d_render_data,
                 renderer_3d_data_tx,
+                hi_res_affine_bgs_in_frame: false,
                 cur_frame_index: 0,
                 thread: Some(
                     thread::Builder::new()
@@ -265,7 +308,7 @@ impl GfxData {
                         .expect("couldn't spawn 2D rendering graphics thread"),
                 ),
             },
-            color_output_view,
+            color_output_texture,
         )
     }
 
@@ -284,6 +327,15 @@ impl GfxData {
                 .expect("couldn't send new 3D renderer receiver");
         }
 
+        // Resampling at native resolution would produce the same output as the CPU renderer
+        self.hi_res_affine_bgs_in_frame =
+            self.shared_data.hi_res_affine_bgs.load(Ordering::Relaxed)
+                && self
+                    .shared_data
+                    .resolution_scale_shift
+                    .load(Ordering::Relaxed)
+                    != 0;
+
         if engine_3d_enabled_in_frame {
             match &mut self.renderer_3d_data {
                 Renderer3dRenderThreadData::Soft(rx) => rx.start_frame(),
@@ -325,6 +377,53 @@ impl GfxData {
         }
     }
 
+    pub fn hi_res_affine_bgs_in_frame(&self) -> bool {
+        self.hi_res_affine_bgs_in_frame
+    }
+
+    /// Records the state of the rotated/scaled BGs at the start of the given scanline, along with
+    /// which of them each pixel's top and bottom layers come from; must be called with the
+    /// scanline's BG/OBJ pixels before their color effects masks get cleared by windows.
+    pub fn set_affine_bg_scanline(
+        &mut self,
+        screen: usize,
+        cur_scanline: usize,
+        affine_bg_lines: &[AffineBgLine; 2],
+        bg_obj_scanline: &Scanline<BgObjPixel>,
+    ) {
+        let frame = self.frame_data_tx.current();
+        frame.affine_bg_lines[screen][cur_scanline] = *affine_bg_lines;
+        for (layers, pixel) in frame.affine_bg_layers[screen][cur_scanline]
+            .iter_mut()
+            .zip(&bg_obj_scanline.0)
+        {
+            *layers = affine_bg_layers(*pixel);
+        }
+    }
+
+    pub fn clear_affine_bg_scanline(&mut self, screen: usize, cur_scanline: usize) {
+        self.frame_data_tx.current().affine_bg_layers[screen][cur_scanline].fill(0);
+    }
+
+    /// Returns the buffers the BG VRAM, BG palette and BG extended palettes of the given engine
+    /// should be copied to at the end of a frame rendered with hi-res rotated/scaled BGs.
+    pub fn affine_bg_vram_mut<R: Role>(&mut self) -> (&mut [u8], &mut [u8], &mut [u8]) {
+        let vram = &mut *self.frame_data_tx.current().affine_bg_vram;
+        if R::IS_A {
+            (
+                &mut vram.bg_a,
+                &mut vram.bg_palettes[0],
+                &mut vram.bg_ext_palettes[0],
+            )
+        } else {
+            (
+                &mut vram.bg_b,
+                &mut vram.bg_palettes[1],
+                &mut vram.bg_ext_palettes[1],
+            )
+        }
+    }
+
     pub fn finish_frame(
         &mut self,
         framebuffer: &[[Scanline<BgObjPixel>; SCREEN_HEIGHT]; 2],
@@ -334,6 +433,7 @@ impl GfxData {
         let frame = self.frame_data_tx.current();
         frame.framebuffer.copy_from_slice(framebuffer);
         frame.fb_scanline_flags.copy_from_slice(fb_scanline_flags);
+        frame.hi_res_affine_bgs = self.hi_res_affine_bgs_in_frame;
         frame.engine_3d_enabled = engine_3d_enabled_in_frame;
         frame.frame_index = self.cur_frame_index;
         self.cur_frame_index += 1;
@@ -364,6 +464,10 @@ struct GfxThreadData {
 
     fb_texture: wgpu::Texture,
     fb_scanline_flags_buffer: wgpu::Buffer,
+    affine_bg_layers_texture: wgpu::Texture,
+    affine_bg_layers_cleared: bool,
+    affine_bg_lines_buffer: wgpu::Buffer,
+    affine_bg_vram_buffer: wgpu::Buffer,
     fb_data_bg_layout: wgpu::BindGroupLayout,
     fb_data_bg: wgpu::BindGroup,
 
@@ -410,11 +514,18 @@ impl GfxThreadData {
         let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("2D renderer"),
             source: wgpu::ShaderSource::Wgsl(
-                if accel {
-                    resource_str!("shaders/wgpu-2d-accel.wgsl", "shaders/wgpu-2d-accel.wgsl")
-                } else {
-                    resource_str!("shaders/wgpu-2d-soft.wgsl", "shaders/wgpu-2d-soft.wgsl")
-                }
+                format!(
+                    "{}\n{}",
+                    resource_str!(
+                        "shaders/wgpu-2d-affine-bgs.wgsl",
+                        "shaders/wgpu-2d-affine-bgs.wgsl"
+                    ),
+                    if accel {
+                        resource_str!("shaders/wgpu-2d-accel.wgsl", "shaders/wgpu-2d-accel.wgsl")
+                    } else {
+                        resource_str!("shaders/wgpu-2d-soft.wgsl", "shaders/wgpu-2d-soft.wgsl")
+                    }
+                )
                 .into(),
             ),
         });
@@ -556,6 +667,37 @@ impl GfxThreadData {
             mapped_at_creation: false,
         });
 
+        let affine_bg_layers_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("2D renderer affine BG layers texture"),
+            size: wgpu::Extent3d {
+                width: SCREEN_WIDTH as u32,
+                height: (SCREEN_HEIGHT * 2) as u32,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Uint,
+            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let affine_bg_layers_texture_view =
+            affine_bg_layers_texture.create_view(&Default::default());
+
+        let affine_bg_lines_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("2D renderer affine BG lines"),
+            size: mem::size_of::<[[[AffineBgLine; 2]; SCREEN_HEIGHT]; 2]>() as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let affine_bg_vram_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("2D renderer affine BG VRAM"),
+            size: mem::size_of::<AffineBgVram>() as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
         let fb_data_bg_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("2D renderer framebuffer texture"),
             entries: &[
@@ -579,6 +721,36 @@ impl GfxThreadData {
                     },
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Uint,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: NonZeroU64::new(affine_bg_lines_buffer.size()),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: NonZeroU64::new(affine_bg_vram_buffer.size()),
+                    },
+                    count: None,
+                },
             ],
         });
         let fb_data_bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
@@ -597,10 +769,22 @@ impl GfxThreadData {
                         size: NonZeroU64::new((SCREEN_HEIGHT * 2 * 16) as u64),
                     }),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&affine_bg_layers_texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: affine_bg_lines_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: affine_bg_vram_buffer.as_entire_binding(),
+                },
             ],
         });
 
-        let (output_attachments, color_output_view) =
+        let (output_attachments, color_output_texture) =
             OutputAttachments::new(&device, resolution_scale_shift);
 
         let (pipeline, color_output_3d_bg_layout) = Self::create_pipeline_and_output_3d_bg_layout(
@@ -631,6 +815,10 @@ impl GfxThreadData {
 
                 fb_texture,
                 fb_scanline_flags_buffer,
+                affine_bg_layers_texture,
+                affine_bg_layers_cleared: true,
+                affine_bg_lines_buffer,
+                affine_bg_vram_buffer,
                 fb_data_bg_layout,
                 fb_data_bg,
 
@@ -642,10 +830,27 @@ impl GfxThreadData {
 
                 pipeline,
             },
-            color_output_view,
+            color_output_texture,
         )
     }
 
+    fn write_affine_bg_layers(&self, layers: &AffineBgLayers) {
+        self.queue.write_texture(
+            self.affine_bg_layers_texture.as_image_copy(),
+            layers.as_flattened().as_flattened(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(SCREEN_WIDTH as u32),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: SCREEN_WIDTH as u32,
+                height: (SCREEN_HEIGHT * 2) as u32,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
     fn run(mut self) {
         loop {
             if self.shared_data.stopped.load(Ordering::Relaxed) {
@@ -658,10 +863,10 @@ impl GfxThreadData {
                     .load(Ordering::Relaxed);
                 if resolution_scale_shift != self.resolution_scale_shift {
                     self.resolution_scale_shift = resolution_scale_shift;
-                    let (output_attachments, color_output_view) =
+                    let (output_attachments, color_output_texture) =
                         OutputAttachments::new(&self.device, resolution_scale_shift);
                     self.output_attachments = output_attachments;
-                    self.channels.set_color_output_view(color_output_view);
+                    self.channels.set_color_output_texture(color_output_texture);
                 }
 
                 if let Some(renderer_3d_data) = self.renderer_3d_data_rx.try_iter().last() {
@@ -761,6 +966,30 @@ impl GfxThreadData {
                         )
                     });
 
+                if frame.hi_res_affine_bgs {
+                    self.write_affine_bg_layers(&frame.affine_bg_layers);
+                    self.affine_bg_layers_cleared = false;
+                    self.queue
+                        .write_buffer(&self.affine_bg_lines_buffer, 0, unsafe {
+                            slice::from_raw_parts(
+                                frame.affine_bg_lines.as_ptr() as *const u8,
+                                mem::size_of::<[[[AffineBgLine; 2]; SCREEN_HEIGHT]; 2]>(),
+                            )
+                        });
+                    self.queue
+                        .write_buffer(&self.affine_bg_vram_buffer, 0, unsafe {
+                            slice::from_raw_parts(
+                                &*frame.affine_bg_vram as *const AffineBgVram as *const u8,
+                                mem::size_of::<AffineBgVram>(),
+                            )
+                        });
+                } else if !self.affine_bg_layers_cleared {
+                    // With no layers marked as coming from affine BGs, the shader will leave all
+                    // pixels untouched
+                    self.write_affine_bg_layers(&[[[0; SCREEN_WIDTH]; SCREEN_HEIGHT]; 2]);
+                    self.affine_bg_layers_cleared = true;
+                }
+
                 let mut command_encoder =
                     self.device
                         .create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -811,3 +1040,31 @@ impl GfxThreadData {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(top_mask: u8, bot_mask: u8) -> BgObjPixel {
+        BgObjPixel(0)
+            .with_color_effects_mask(top_mask)
+            .with_bot_color_effects_mask(bot_mask)
+    }
+
+    #[test]
+    fn affine_bg_layers_from_masks() {
+        assert_eq!(affine_bg_layers(pixel(1 << 2, 1 << 5)), 1);
+        assert_eq!(affine_bg_layers(pixel(1 << 3, 1 << 5)), 2);
+        assert_eq!(affine_bg_layers(pixel(1 << 1, 1 << 2)), 1 << 2);
+        assert_eq!(affine_bg_layers(pixel(1 << 3, 1 << 2)), 2 | 1 << 2);
+    }
+
+    #[test]
+    fn affine_bg_layers_outside_windows() {
+        // Where a window hides the rotated/scaled BGs, the pixels come from other layers (here
+        // BG0, OBJs and the backdrop) and must be left at native resolution
+        assert_eq!(affine_bg_layers(pixel(1 << 0, 1 << 4)), 0);
+        assert_eq!(affine_bg_layers(pixel(1 << 4, 1 << 5)), 0);
+        assert_eq!(affine_bg_layers(pixel(1 << 5, 1 << 5)), 0);
+    }
+}
@@ -0,0 +1,164 @@
+// Resampling of rotated/scaled BGs at the output resolution, prepended to the main 2D shaders.
+
+struct AffineBgLine {
+    pos: vec2<i32>,
+    x_incr: vec2<i32>,
+    y_incr: vec2<i32>,
+    params: u32,
+    map_base: u32,
+    tile_base: u32,
+    padding: u32,
+}
+
+@group(0) @binding(2) var t_affine_bg_layers: texture_2d<u32>;
+@group(0) @binding(3) var<storage, read> affine_bg_lines: array<array<array<AffineBgLine, 2>, 192>, 2>;
+@group(0) @binding(4) var<storage, read> affine_bg_vram: array<u32>;
+
+const AFFINE_BG_KIND_AFFINE: u32 = 1u;
+const AFFINE_BG_KIND_EXTENDED_TILED: u32 = 2u;
+const AFFINE_BG_KIND_BITMAP: u32 = 3u;
+const AFFINE_BG_KIND_DIRECT_COLOR_BITMAP: u32 = 4u;
+
+const AFFINE_BG_VRAM_B_BASE: u32 = 0x80000u;
+const AFFINE_BG_PALETTE_BASE: u32 = 0xA0000u;
+const AFFINE_BG_EXT_PALETTE_BASE: u32 = 0xA0400u;
+
+fn read_affine_bg_vram_8(addr: u32) -> u32 {
+    return (affine_bg_vram[addr >> 2u] >> ((addr & 3u) << 3u)) & 0xFFu;
+}
+
+fn read_affine_bg_vram_16(addr: u32) -> u32 {
+    return (affine_bg_vram[addr >> 2u] >> ((addr & 2u) << 3u)) & 0xFFFFu;
+}
+
+fn rgb5_to_rgb6(value: u32) -> u32 {
+    return ((value << 1u) & 0x3Eu) | ((value << 2u) & 0xF80u) | ((value << 3u) & 0x3E000u);
+}
+
+// Returns the RGB5 color of the BG at the given 20.8 fixed-point position with bit 15 set, or 0 if
+// the BG is transparent there; mirrors the CPU renderer's affine and extended BG code.
+fn sample_affine_bg(bg: AffineBgLine, pos: vec2<i32>) -> u32 {
+    let kind = bg.params & 7u;
+    let display_area_overflow = (bg.params & (1u << 3u)) != 0u;
+    let size_key = (bg.params >> 4u) & 3u;
+    let is_engine_b = (bg.params & (1u << 6u)) != 0u;
+
+    let vram_base = select(0u, AFFINE_BG_VRAM_B_BASE, is_engine_b);
+    let vram_mask = select(0x7FFFFu, 0x1FFFFu, is_engine_b);
+    let palette_base = AFFINE_BG_PALETTE_BASE + select(0u, 0x200u, is_engine_b);
+
+    let x = u32(pos.x);
+    let y = u32(pos.y);
+
+    if kind == AFFINE_BG_KIND_AFFINE || kind == AFFINE_BG_KIND_EXTENDED_TILED {
+        let display_area_overflow_mask = ~((0x8000u << size_key) - 1u);
+        if !display_area_overflow && ((x | y) & display_area_overflow_mask) != 0u {
+            return 0u;
+        }
+
+        let map_row_shift = 4u + size_key;
+        let pos_map_mask = ((1u << map_row_shift) - 1u) << 11u;
+
+        if kind == AFFINE_BG_KIND_AFFINE {
+            let tile_addr = bg.map_base
+                + (((y & pos_map_mask) >> (11u - map_row_shift)) | ((x & pos_map_mask) >> 11u));
+            let tile = read_affine_bg_vram_8(vram_base + (tile_addr & vram_mask));
+
+            let pixel_addr = bg.tile_base + ((tile << 6u) | ((y >> 5u) & 0x38u) | ((x >> 8u) & 7u));
+            let color_index = read_affine_bg_vram_8(vram_base + (pixel_addr & vram_mask));
+            if color_index == 0u {
+                return 0u;
+            }
+            return read_affine_bg_vram_16(palette_base + (color_index << 1u)) | 0x8000u;
+        }
+
+        let tile_addr = bg.map_base
+            + (((y & pos_map_mask) >> (10u - map_row_shift)) | ((x & pos_map_mask) >> 10u));
+        let tile = read_affine_bg_vram_16(vram_base + (tile_addr & (vram_mask & ~1u)));
+
+        var x_offset = (x >> 8u) & 7u;
+        if (tile & (1u << 10u)) != 0u {
+            x_offset = (~x >> 8u) & 7u;
+        }
+        var y_offset = (y >> 5u) & 0x38u;
+        if (tile & (1u << 11u)) != 0u {
+            y_offset = (~y >> 5u) & 0x38u;
+        }
+
+        let pixel_addr = bg.tile_base + (((tile & 0x3FFu) << 6u) | y_offset | x_offset);
+        let color_index = read_affine_bg_vram_8(vram_base + (pixel_addr & vram_mask));
+        if color_index == 0u {
+            return 0u;
+        }
+        if (bg.params & (1u << 7u)) != 0u {
+            let ext_palette_base = AFFINE_BG_EXT_PALETTE_BASE
+                + select(0u, 0x8000u, is_engine_b)
+                + (((bg.params >> 8u) & 3u) << 13u);
+            return read_affine_bg_vram_16(
+                ext_palette_base + ((((tile >> 4u) & 0xF00u) | color_index) << 1u),
+            ) | 0x8000u;
+        }
+        return read_affine_bg_vram_16(palette_base + (color_index << 1u)) | 0x8000u;
+    }
+
+    if kind == AFFINE_BG_KIND_BITMAP || kind == AFFINE_BG_KIND_DIRECT_COLOR_BITMAP {
+        let x_shift = min(size_key, 2u);
+        let y_shift = x_shift - u32(size_key == 2u);
+
+        let display_area_x_overflow_mask = ~((0x8000u << x_shift) - 1u);
+        let display_area_y_overflow_mask = ~((0x8000u << y_shift) - 1u);
+        if !display_area_overflow
+            && ((x & display_area_x_overflow_mask) | (y & display_area_y_overflow_mask)) != 0u
+        {
+            return 0u;
+        }
+
+        let pos_x_map_mask = ((0x80u << x_shift) - 1u) << 8u;
+        let pos_y_map_mask = ((0x80u << y_shift) - 1u) << 8u;
+
+        if kind == AFFINE_BG_KIND_DIRECT_COLOR_BITMAP {
+            let pixel_addr = bg.map_base
+                + (((y & pos_y_map_mask) << x_shift) | ((x & pos_x_map_mask) >> 7u));
+            let color = read_affine_bg_vram_16(vram_base + (pixel_addr & (vram_mask & ~1u)));
+            if (color & 0x8000u) == 0u {
+                return 0u;
+            }
+            return color;
+        }
+
+        let pixel_addr = bg.map_base
+            + ((((y & pos_y_map_mask) >> 1u) << x_shift) | ((x & pos_x_map_mask) >> 8u));
+        let color_index = read_affine_bg_vram_8(vram_base + (pixel_addr & vram_mask));
+        if color_index == 0u {
+            return 0u;
+        }
+        return read_affine_bg_vram_16(palette_base + (color_index << 1u)) | 0x8000u;
+    }
+
+    return 0u;
+}
+
+// Resamples a framebuffer pixel half (top or bottom) at the given subpixel offset if `layer`
+// indicates it comes from a rotated/scaled BG, keeping all of its other attributes. Falls back to
+// the native resolution color when the BG is transparent at the new position.
+fn resample_affine_bg_pixel(
+    pixel: u32,
+    layer: u32,
+    screen_index: u32,
+    scanline_index: u32,
+    x: u32,
+    offset: vec2<f32>,
+) -> u32 {
+    if layer == 0u {
+        return pixel;
+    }
+    let bg = affine_bg_lines[screen_index][scanline_index][layer - 1u];
+    let pos = bg.pos
+        + i32(x) * bg.x_incr
+        + vec2<i32>(round(offset.x * vec2<f32>(bg.x_incr) + offset.y * vec2<f32>(bg.y_incr)));
+    let color = sample_affine_bg(bg, pos);
+    if color == 0u {
+        return pixel;
+    }
+    return (pixel & ~0x3FFFFu) | rgb5_to_rgb6(color);
+}
@@ -58,7 +58,30 @@ fn fs_main(
     let screen_index = u32(uv.y * 2.0);
     let scanline_index = u32(fract(uv.y * 2.0) * 192.0);
     let scanline_flags = scanline_flags[screen_index][scanline_index];
-    let pixel = textureLoad(t_output_2d, vec2<i32>(uv * vec2<f32>(256.0, 384.0)), 0);
+    let fb_coords = vec2<i32>(uv * vec2<f32>(256.0, 384.0));
+    var pixel = textureLoad(t_output_2d, fb_coords, 0);
+
+    let affine_bg_layers = textureLoad(t_affine_bg_layers, fb_coords, 0).r;
+    if affine_bg_layers != 0u {
+        let offset = fract(uv * vec2<f32>(256.0, 384.0)) - vec2<f32>(0.5);
+        let x = u32(fb_coords.x);
+        pixel.r = resample_affine_bg_pixel(
+            pixel.r,
+            affine_bg_layers & 3u,
+            screen_index,
+            scanline_index,
+            x,
+            offset,
+        );
+        pixel.g = resample_affine_bg_pixel(
+            pixel.g,
+            affine_bg_layers >> 2u,
+            screen_index,
+            scanline_index,
+            x,
+            offset,
+        );
+    }
 
     let uv_3d = fract(uv * vec2<f32>(1.0, 2.0));
     let pixel_3d_raw =
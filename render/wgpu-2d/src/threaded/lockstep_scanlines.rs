@@ -4,7 +4,8 @@ use crate::common::{
     self, capture,
     gfx::{self, GfxData, Renderer3dRx},
     render::{self, objs::prerender_objs},
-    rgb5_to_rgb6_64, BgObjPixel, ObjPixel, ScanlineFlags, WindowPixel,
+    rgb5_to_rgb6_64, AffineBgKind, AffineBgLine, AffineBgParams, BgObjPixel, ObjPixel,
+    ScanlineFlags, WindowPixel,
 };
 use core::{
     cell::UnsafeCell,
@@ -14,11 +15,13 @@ use core::{
 use dust_core::{
     gpu::{
         engine_2d::{
-            BgControl, BrightnessControl, CaptureControl, ColorEffectsControl, Control, Engine2d,
-            EngineA, EngineB, Renderer as RendererTrait, Role, WindowControl, WindowsActive,
+            window_x_spans, BgControl, BgIndex, BrightnessControl, CaptureControl,
+            ColorEffectsControl, Control, Engine2d, EngineA, EngineB, Renderer as RendererTrait,
+            Role, WindowControl, WindowsActive,
         },
         vram, Framebuffer, Scanline, SCREEN_HEIGHT, SCREEN_WIDTH,
     },
+    profiling::TimeCounter,
     utils::mem_prelude::*,
 };
 use std::{sync::Arc, thread};
@@ -96,7 +99,7 @@ impl<R: Role> From<&Engine2d<R>> for RenderingData {
                         let bg = &other.bgs[$i];
                         Bg {
                             control: bg.control(),
-                            priority: bg.priority(),
+                            priority: other.bg_render_priority(BgIndex::new($i)),
                             scroll: bg.scroll,
                         }
                     }
@@ -121,7 +124,7 @@ impl<R: Role> From<&Engine2d<R>> for RenderingData {
             is_enabled: other.is_enabled(),
             engine_3d_enabled_in_frame: other.engine_3d_enabled_in_frame(),
             is_on_lower_screen: other.is_on_lower_screen(),
-            control: other.control(),
+            control: other.render_control(),
             master_brightness_control: other.master_brightness_control(),
             master_brightness_factor: other.master_brightness_factor(),
             bgs: bgs!(0, 1, 2, 3),
@@ -140,14 +143,74 @@ impl<R: Role> From<&Engine2d<R>> for RenderingData {
     }
 }
 
+impl RenderingData {
+    fn affine_bg_lines<R: Role>(&self) -> [AffineBgLine; 2] {
+        let bg_mode = self.control.bg_mode();
+        let mut lines = [AffineBgLine::default(); 2];
+
+        for (i, line) in lines.iter_mut().enumerate() {
+            let bg = &self.bgs[i + 2];
+            if bg.priority == 4 {
+                continue;
+            }
+
+            let kind = match (i, bg_mode) {
+                (0, 2 | 4) | (1, 1 | 2) => AffineBgKind::Affine,
+                (0, 5) | (1, 3..=5) => {
+                    if !bg.control.use_bitmap_extended_bg() {
+                        AffineBgKind::ExtendedTiled
+                    } else if bg.control.use_direct_color_extended_bg() {
+                        AffineBgKind::DirectColorBitmap
+                    } else {
+                        AffineBgKind::Bitmap
+                    }
+                }
+                // Large bitmap BGs are left at native resolution
+                _ => continue,
+            };
+
+            let (map_base, tile_base) = match kind {
+                AffineBgKind::Bitmap | AffineBgKind::DirectColorBitmap => {
+                    (bg.control.map_base() << 3, 0)
+                }
+                _ if R::IS_A => (
+                    self.control.a_map_base() | bg.control.map_base(),
+                    self.control.a_tile_base() + bg.control.tile_base(),
+                ),
+                _ => (bg.control.map_base(), bg.control.tile_base()),
+            };
+
+            let affine_bg_data = &self.affine_bg_data[i];
+            *line = AffineBgLine {
+                pos: affine_bg_data.pos,
+                x_incr: affine_bg_data.x_incr.map(Into::into),
+                y_incr: affine_bg_data.y_incr.map(Into::into),
+                params: AffineBgParams(0)
+                    .with_kind(kind as u8)
+                    .with_display_area_overflow(bg.control.affine_display_area_overflow())
+                    .with_size_key(bg.control.size_key())
+                    .with_is_engine_b(!R::IS_A)
+                    .with_ext_pal_enabled(self.control.bg_ext_pal_enabled())
+                    .with_ext_pal_slot(i as u8 | 2),
+                map_base,
+                tile_base,
+                _padding: 0,
+            };
+        }
+
+        lines
+    }
+}
+
 pub struct FrontendChannels {
     common_shared_data: Arc<gfx::SharedData>,
     common: gfx::FrontendChannels,
+    time_counter: TimeCounter,
 }
 
 impl FrontendChannels {
-    pub fn new_color_output_view(&self) -> Option<wgpu::TextureView> {
-        self.common.new_color_output_view()
+    pub fn new_color_output_texture(&self) -> Option<Arc<wgpu::Texture>> {
+        self.common.new_color_output_texture()
     }
 
     pub fn set_renderer_3d_rx(&self, renderer_3d_rx: Renderer3dRx) {
@@ -157,6 +220,16 @@ impl FrontendChannels {
     pub fn set_resolution_scale_shift(&self, value: u8) {
         self.common_shared_data.set_resolution_scale_shift(value);
     }
+
+    pub fn set_hi_res_affine_bgs(&self, value: bool) {
+        self.common_shared_data.set_hi_res_affine_bgs(value);
+    }
+
+    /// Returns a counter measuring the time the rendering thread spends rendering scanlines and
+    /// submitting finished frames to the GPU.
+    pub fn time_counter(&self) -> TimeCounter {
+        self.time_counter.clone()
+    }
 }
 
 pub struct Renderer {
@@ -170,8 +243,9 @@ impl Renderer {
         device: Arc<wgpu::Device>,
         queue: Arc<wgpu::Queue>,
         resolution_scale_shift: u8,
+        hi_res_affine_bgs: bool,
         renderer_3d_rx: Renderer3dRx,
-    ) -> (Self, wgpu::TextureView, FrontendChannels) {
+    ) -> (Self, Arc<wgpu::Texture>, FrontendChannels) {
         const BG: Bg = Bg {
             control: BgControl(0),
             scroll: [0; 2],
@@ -204,7 +278,10 @@ impl Renderer {
             capture_height: 128,
         };
 
-        let common_shared_data = Arc::new(gfx::SharedData::new(resolution_scale_shift));
+        let common_shared_data = Arc::new(gfx::SharedData::new(
+            resolution_scale_shift,
+            hi_res_affine_bgs,
+        ));
 
         let shared_data = Arc::new(unsafe {
             SharedData {
@@ -232,19 +309,22 @@ impl Renderer {
             }
         });
 
-        let (color_output_view_tx, color_output_view_rx) = crossbeam_channel::unbounded();
+        let (color_output_texture_tx, color_output_texture_rx) = crossbeam_channel::unbounded();
 
         let (renderer_3d_rx_tx, renderer_3d_rx_rx) = crossbeam_channel::unbounded();
 
-        let (thread_data, color_output_view) = ThreadData::new(
+        let time_counter = TimeCounter::new();
+
+        let (thread_data, color_output_texture) = ThreadData::new(
             Arc::clone(&device),
             Arc::clone(&queue),
-            color_output_view_tx,
+            color_output_texture_tx,
             renderer_3d_rx_rx,
             Arc::clone(&common_shared_data),
             Arc::clone(&shared_data),
             resolution_scale_shift,
             renderer_3d_rx,
+            time_counter.clone(),
         );
 
         (
@@ -262,10 +342,11 @@ impl Renderer {
                         .expect("couldn't spawn 2D rendering thread"),
                 ),
             },
-            color_output_view,
+            color_output_texture,
             FrontendChannels {
                 common_shared_data,
-                common: gfx::FrontendChannels::new(color_output_view_rx, renderer_3d_rx_tx),
+                common: gfx::FrontendChannels::new(color_output_texture_rx, renderer_3d_rx_tx),
+                time_counter,
             },
         )
     }
@@ -528,6 +609,24 @@ impl Drop for Renderer {
     }
 }
 
+fn copy_affine_bg_vram<R: Role>(gfx_data: &mut GfxData, vram: &Vram<R>)
+where
+    [(); R::BG_VRAM_LEN]: Sized,
+    [(); R::OBJ_VRAM_LEN]: Sized,
+{
+    let (bg, palette, ext_palette) = gfx_data.affine_bg_vram_mut::<R>();
+    unsafe {
+        bg.as_mut_ptr()
+            .copy_from_nonoverlapping(vram.bg.as_ptr(), bg.len());
+        palette
+            .as_mut_ptr()
+            .copy_from_nonoverlapping(vram.palette.as_ptr(), palette.len());
+        ext_palette
+            .as_mut_ptr()
+            .copy_from_nonoverlapping(vram.bg_ext_palette.as_ptr(), ext_palette.len());
+    }
+}
+
 struct Buffers {
     obj_window: UnsafeCell<[u8; SCREEN_WIDTH / 8]>,
     obj_scanline: UnsafeCell<Scanline<ObjPixel>>,
@@ -546,6 +645,7 @@ struct ThreadData {
     buffers: [Buffers; 2],
     fb_scanline_flags: Box<[[ScanlineFlags; SCREEN_HEIGHT]; 2]>,
     gfx_data: GfxData,
+    time_counter: TimeCounter,
 }
 
 impl ThreadData {
@@ -553,13 +653,14 @@ impl ThreadData {
     fn new(
         device: Arc<wgpu::Device>,
         queue: Arc<wgpu::Queue>,
-        color_output_view_tx: crossbeam_channel::Sender<wgpu::TextureView>,
+        color_output_texture_tx: crossbeam_channel::Sender<Arc<wgpu::Texture>>,
         renderer_3d_rx_rx: crossbeam_channel::Receiver<Renderer3dRx>,
         common_shared_data: Arc<gfx::SharedData>,
         shared_data: Arc<SharedData>,
         resolution_scale_shift: u8,
         renderer_3d_rx: Renderer3dRx,
-    ) -> (Self, wgpu::TextureView) {
+        time_counter: TimeCounter,
+    ) -> (Self, Arc<wgpu::Texture>) {
         macro_rules! buffers {
             () => {
                 Buffers {
@@ -571,11 +672,11 @@ impl ThreadData {
             };
         }
 
-        let (gfx_data, color_output_view) = GfxData::new(
+        let (gfx_data, color_output_texture) = GfxData::new(
             device,
             queue,
             Arc::clone(&common_shared_data),
-            color_output_view_tx,
+            color_output_texture_tx,
             renderer_3d_rx_rx,
             resolution_scale_shift,
             renderer_3d_rx,
@@ -590,8 +691,9 @@ impl ThreadData {
                 buffers: [buffers!(), buffers!()],
                 fb_scanline_flags: unsafe { Box::new_zeroed().assume_init() },
                 gfx_data,
+                time_counter,
             },
-            color_output_view,
+            color_output_texture,
         )
     }
 
@@ -612,12 +714,12 @@ impl ThreadData {
         let buffers = &mut self.buffers[!R::IS_A as usize];
 
         let render_obj_line = if self.cur_scanline >= 0 {
+            let screen = data.is_on_lower_screen as usize;
             let (scanline_buffer, scanline_flags) = unsafe {
                 (
-                    (&mut *self.shared_data.framebuffer.get())[data.is_on_lower_screen as usize]
-                        .get_unchecked_mut(self.cur_scanline as usize),
-                    self.fb_scanline_flags[data.is_on_lower_screen as usize]
+                    (&mut *self.shared_data.framebuffer.get())[screen]
                         .get_unchecked_mut(self.cur_scanline as usize),
+                    self.fb_scanline_flags[screen].get_unchecked_mut(self.cur_scanline as usize),
                 )
             };
 
@@ -640,6 +742,10 @@ impl ThreadData {
                 }
                 scanline_buffer.0.fill(BgObjPixel(0x3_FFFF));
                 *scanline_flags = ScanlineFlags::default();
+                if self.gfx_data.hi_res_affine_bgs_in_frame() {
+                    self.gfx_data
+                        .clear_affine_bg_scanline(screen, self.cur_scanline as usize);
+                }
             } else {
                 if R::IS_A && data.engine_3d_enabled_in_frame {
                     let enabled_in_bg_obj = data.bgs[0].priority != 4 && data.control.bg0_3d();
@@ -654,6 +760,12 @@ impl ThreadData {
                     }
                 }
 
+                // The affine BG positions get incremented while rendering, so they need to be
+                // saved beforehand
+                let affine_bg_lines = (display_mode == 1
+                    && self.gfx_data.hi_res_affine_bgs_in_frame())
+                .then(|| data.affine_bg_lines::<R>());
+
                 if render_bg_obj_line {
                     let window = buffers.window.get_mut();
 
@@ -679,13 +791,13 @@ impl ThreadData {
                             continue;
                         }
 
-                        let x_range = &data.window_x_ranges[i];
-                        let x_start = x_range.0 as usize;
-                        let mut x_end = x_range.1 as usize;
-                        if x_end < x_start {
-                            x_end = 256;
+                        let window_pixel = WindowPixel(data.window_control[i].0);
+                        for x_span in window_x_spans(
+                            data.window_x_ranges[i],
+                            data.windows_active.0 & 4 << i != 0,
+                        ) {
+                            window.0[x_span].fill(window_pixel);
                         }
-                        window.0[x_start..x_end].fill(WindowPixel(data.window_control[i].0));
                     }
 
                     let backdrop = BgObjPixel(rgb5_to_rgb6_64(vram.palette.read_le::<u16>(0)))
@@ -736,6 +848,18 @@ impl ThreadData {
                     _ => {}
                 }
 
+                if let Some(affine_bg_lines) = &affine_bg_lines {
+                    self.gfx_data.set_affine_bg_scanline(
+                        screen,
+                        self.cur_scanline as usize,
+                        affine_bg_lines,
+                        buffers.bg_obj_scanline.get_mut(),
+                    );
+                } else if self.gfx_data.hi_res_affine_bgs_in_frame() {
+                    self.gfx_data
+                        .clear_affine_bg_scanline(screen, self.cur_scanline as usize);
+                }
+
                 if R::IS_A
                     && data.capture_enabled_in_frame
                     && self.cur_scanline < data.capture_height as i16
@@ -805,9 +929,20 @@ impl ThreadData {
             let vcount = self.shared_data.vcount.load(Ordering::Acquire);
             let vram = unsafe { &*self.shared_data.vram.get() };
 
+            let start = self.time_counter.start();
             self.render_scanline::<EngineA>(vcount, &vram.0);
             self.render_scanline::<EngineB>(vcount, &vram.1);
 
+            // VRAM can only be accessed until the emulation thread is notified that the scanline
+            // was processed
+            if self.cur_scanline == (SCREEN_HEIGHT - 1) as i16
+                && self.gfx_data.hi_res_affine_bgs_in_frame()
+            {
+                copy_affine_bg_vram(&mut self.gfx_data, &vram.0);
+                copy_affine_bg_vram(&mut self.gfx_data, &vram.1);
+            }
+            self.time_counter.stop(start);
+
             self.shared_data
                 .processing_line
                 .store(false, Ordering::Release);
@@ -816,6 +951,7 @@ impl ThreadData {
             if self.cur_scanline == SCREEN_HEIGHT as i16 {
                 self.cur_scanline = -1;
 
+                let start = self.time_counter.start();
                 unsafe {
                     self.gfx_data.finish_frame(
                         &*self.shared_data.framebuffer.get(),
@@ -823,6 +959,7 @@ impl ThreadData {
                         (*self.shared_data.rendering_data.get())[0].engine_3d_enabled_in_frame,
                     )
                 }
+                self.time_counter.stop(start);
 
                 thread::park();
             }
@@ -0,0 +1,1588 @@
+// Compute shader rasterizer, reimplementing the DS's rasterization rules (span-based filling,
+// per-edge fill conventions, and the hardware's linear/perspective-correct interpolation) on the
+// GPU, based on the software renderer's implementation but at an arbitrary resolution scale.
+//
+// Polygon edges are set up on the CPU as chains of segments from each polygon's top vertex to its
+// bottom one; then one invocation per output pixel walks through the polygons overlapping its
+// line in order, and a second pass applies edge marking, antialiasing and fog.
+
+use crate::{
+    utils::{expand_depth, rgb5_to_rgb6},
+    FrameData,
+};
+use core::{mem, slice};
+use dust_core::gpu::engine_3d::{Color, Polygon, ScreenVertex};
+
+// Has to match the shader's workgroup size
+const WORKGROUP_SIZE: u32 = 8;
+
+// Per-pixel state: color, depth, attributes and the color below opaque edges (for antialiasing)
+const PIXEL_SIZE: u64 = 16;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Params {
+    width: u32,
+    height: u32,
+    scale_shift: u32,
+    control: u32,
+    w_buffering: u32,
+    alpha_test_ref: u32,
+    clear_color: u32,
+    clear_depth: u32,
+    clear_attrs: u32,
+    clear_image_offset: u32,
+    fog_offset: u32,
+    fog_color: u32,
+    fog_densities: [u32; 0x24],
+    toon_colors: [u32; 0x20],
+    edge_colors: [u32; 8],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct GpuPolygon {
+    top_y: u32,
+    bot_y: u32,
+    attrs: u32,
+    tex_params: u32,
+    tex_palette_base: u32,
+    flags: u32,
+    // Start index and length (in the upper 16 bits) of each edge chain
+    edges: [u32; 2],
+}
+
+// Polygon flags (the lower 2 bits contain the polygon's mode)
+const DUMMY: u32 = 1 << 2;
+const DEPTH_TEST_SHIFT: u32 = 3;
+const TRANSLUCENT: u32 = 1 << 5;
+
+const DEPTH_TEST_EQUAL_W: u32 = 0;
+const DEPTH_TEST_EQUAL_Z: u32 = 1;
+const DEPTH_TEST_LESS_FRONT_FACING: u32 = 2;
+const DEPTH_TEST_LESS_BACK_FACING: u32 = 3;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct GpuEdge {
+    a_y: u32,
+    b_y: u32,
+    x_ref: u32,
+    x_incr: u32,
+    flags: u32,
+    interp_ref: u32,
+    interp_len: u32,
+    p_w0_numer: u32,
+    p_w0_denom: u32,
+    p_w1_denom: u32,
+    a_z: u32,
+    b_z: u32,
+    a_w: u32,
+    b_w: u32,
+    a_vert: u32,
+    b_vert: u32,
+}
+
+// Edge flags
+const X_MAJOR: u32 = 1 << 0;
+const NEGATIVE: u32 = 1 << 1;
+const FORCE_LINEAR: u32 = 1 << 2;
+
+#[derive(Clone, Copy)]
+struct EdgeVertex {
+    x: u32,
+    y: u32,
+    z: u32,
+    w: u16,
+    addr: u32,
+}
+
+impl GpuEdge {
+    fn new(a: EdgeVertex, b: EdgeVertex) -> Self {
+        // Slope calculation based on https://github.com/StrikerX3/nds-interp
+
+        let x_diff = b.x as i32 - a.x as i32;
+        let y_len = b.y.wrapping_sub(a.y);
+
+        let mut x_ref = a.x << 18;
+
+        let is_negative = x_diff < 0;
+        let x_len = if is_negative {
+            x_ref = x_ref.wrapping_sub(1);
+            -x_diff
+        } else {
+            x_diff
+        } as u32;
+
+        let is_x_major = x_len > y_len;
+        if x_len >= y_len {
+            if is_negative {
+                x_ref = x_ref.wrapping_sub(1 << 17);
+            } else {
+                x_ref += 1 << 17;
+            }
+        }
+
+        let x_incr = if y_len == 0 {
+            x_len << 18
+        } else {
+            x_len * ((1 << 18) / y_len)
+        };
+
+        let force_linear = a.w == b.w && (a.w | b.w) & 0x7E == 0;
+        let (p_w0_numer, p_w0_denom, p_w1_denom) = if a.w & 1 != 0 && b.w & 1 == 0 {
+            (a.w >> 1, ((a.w as u32 + 1) >> 1) as u16, b.w >> 1)
+        } else {
+            (a.w >> 1, a.w >> 1, b.w >> 1)
+        };
+
+        GpuEdge {
+            a_y: a.y,
+            b_y: b.y,
+            x_ref,
+            x_incr,
+            flags: if is_x_major { X_MAJOR } else { 0 }
+                | if is_negative { NEGATIVE } else { 0 }
+                | if force_linear { FORCE_LINEAR } else { 0 },
+            interp_ref: if is_x_major { a.x.min(b.x) } else { a.y },
+            interp_len: if is_x_major { x_len } else { y_len },
+            p_w0_numer: p_w0_numer as u32,
+            p_w0_denom: p_w0_denom as u32,
+            p_w1_denom: p_w1_denom as u32,
+            a_z: a.z,
+            b_z: b.z,
+            a_w: a.w as u32,
+            b_w: b.w as u32,
+            a_vert: a.addr,
+            b_vert: b.addr,
+        }
+    }
+
+    fn dummy(v: EdgeVertex) -> Self {
+        GpuEdge {
+            a_y: v.y,
+            b_y: v.y,
+            x_ref: v.x,
+            x_incr: 0,
+            flags: 0,
+            interp_ref: 0,
+            interp_len: 0,
+            p_w0_numer: 0,
+            p_w0_denom: 0,
+            p_w1_denom: 0,
+            a_z: v.z,
+            b_z: v.z,
+            a_w: v.w as u32,
+            b_w: v.w as u32,
+            a_vert: v.addr,
+            b_vert: v.addr,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct GpuVertex {
+    color: [u32; 4],
+    uv: [i32; 2],
+    _padding: [u32; 2],
+}
+
+// The first 193 entries contain the start offset of each line's list of polygon indices
+const LINE_POLYS_HEADER_LEN: usize = 193;
+const LINE_POLYS_LEN: usize = LINE_POLYS_HEADER_LEN + 2048 * 192;
+
+const MAX_EDGES: usize = 2048 * 10;
+
+fn as_bytes<T>(values: &[T]) -> &[u8] {
+    unsafe { slice::from_raw_parts(values.as_ptr() as *const u8, mem::size_of_val(values)) }
+}
+
+fn pack_color(color: Color) -> u32 {
+    u32::from_le_bytes(color.to_array())
+}
+
+fn pixels_buffer_size(resolution_scale_shift: u8) -> u64 {
+    (256 * 192 * PIXEL_SIZE) << (resolution_scale_shift << 1)
+}
+
+/// Returns whether the device can run the compute rasterizer at up to the given resolution scale.
+pub fn is_supported(
+    adapter: &wgpu::Adapter,
+    limits: &wgpu::Limits,
+    max_resolution_scale_shift: u8,
+) -> bool {
+    adapter
+        .get_downlevel_capabilities()
+        .flags
+        .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+        && limits.max_storage_buffers_per_shader_stage >= 6
+        && limits.max_storage_textures_per_shader_stage >= 1
+        && limits.max_compute_invocations_per_workgroup >= WORKGROUP_SIZE * WORKGROUP_SIZE
+        && limits.max_storage_buffer_binding_size as u64
+            >= pixels_buffer_size(max_resolution_scale_shift.min(3))
+}
+
+pub struct Rasterizer {
+    resolution_scale_shift: u8,
+
+    params_buffer: wgpu::Buffer,
+    polys_buffer: wgpu::Buffer,
+    edges_buffer: wgpu::Buffer,
+    verts_buffer: wgpu::Buffer,
+    line_polys_buffer: wgpu::Buffer,
+    vram_buffer: wgpu::Buffer,
+    vram_uploaded: bool,
+    pixels_buffer: wgpu::Buffer,
+
+    bg_layout: wgpu::BindGroupLayout,
+    bg: wgpu::BindGroup,
+    rasterize_pipeline: wgpu::ComputePipeline,
+    postprocess_pipeline: wgpu::ComputePipeline,
+
+    polys: Vec<GpuPolygon>,
+    edges: Vec<GpuEdge>,
+    verts: Vec<GpuVertex>,
+    poly_lines: Vec<(u8, u8)>,
+    line_polys: Vec<u32>,
+}
+
+fn create_pixels_buffer(device: &wgpu::Device, resolution_scale_shift: u8) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("3D compute rasterizer pixels"),
+        size: pixels_buffer_size(resolution_scale_shift),
+        usage: wgpu::BufferUsages::STORAGE,
+        mapped_at_creation: false,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_bg(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    params_buffer: &wgpu::Buffer,
+    polys_buffer: &wgpu::Buffer,
+    edges_buffer: &wgpu::Buffer,
+    verts_buffer: &wgpu::Buffer,
+    line_polys_buffer: &wgpu::Buffer,
+    vram_buffer: &wgpu::Buffer,
+    pixels_buffer: &wgpu::Buffer,
+    output_view: &wgpu::TextureView,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("3D compute rasterizer bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: polys_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: edges_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: verts_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: line_polys_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: vram_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: pixels_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 7,
+                resource: wgpu::BindingResource::TextureView(output_view),
+            },
+        ],
+    })
+}
+
+impl Rasterizer {
+    pub fn new(
+        device: &wgpu::Device,
+        resolution_scale_shift: u8,
+        output_view: &wgpu::TextureView,
+    ) -> Self {
+        macro_rules! buffer {
+            ($label: literal, $size: expr, $usage: ident) => {
+                device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some($label),
+                    size: $size as u64,
+                    usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::$usage,
+                    mapped_at_creation: false,
+                })
+            };
+        }
+
+        let params_buffer = buffer!(
+            "3D compute rasterizer parameters",
+            mem::size_of::<Params>(),
+            UNIFORM
+        );
+        let polys_buffer = buffer!(
+            "3D compute rasterizer polygons",
+            mem::size_of::<GpuPolygon>() * 2048,
+            STORAGE
+        );
+        let edges_buffer = buffer!(
+            "3D compute rasterizer edges",
+            mem::size_of::<GpuEdge>() * MAX_EDGES,
+            STORAGE
+        );
+        let verts_buffer = buffer!(
+            "3D compute rasterizer vertices",
+            mem::size_of::<GpuVertex>() * 6144,
+            STORAGE
+        );
+        let line_polys_buffer = buffer!(
+            "3D compute rasterizer line polygon lists",
+            4 * LINE_POLYS_LEN,
+            STORAGE
+        );
+        // Texture data, followed by texture palettes
+        let vram_buffer = buffer!("3D compute rasterizer VRAM", 0x8_0000 + 0x2_0000, STORAGE);
+        let pixels_buffer = create_pixels_buffer(device, resolution_scale_shift);
+
+        macro_rules! storage_buffer_entry {
+            ($binding: literal, $read_only: expr) => {
+                wgpu::BindGroupLayoutEntry {
+                    binding: $binding,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage {
+                            read_only: $read_only,
+                        },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }
+            };
+        }
+
+        let bg_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("3D compute rasterizer bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(mem::size_of::<Params>() as u64),
+                    },
+                    count: None,
+                },
+                storage_buffer_entry!(1, true),
+                storage_buffer_entry!(2, true),
+                storage_buffer_entry!(3, true),
+                storage_buffer_entry!(4, true),
+                storage_buffer_entry!(5, true),
+                storage_buffer_entry!(6, false),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 7,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::Rgba8Unorm,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let bg = create_bg(
+            device,
+            &bg_layout,
+            &params_buffer,
+            &polys_buffer,
+            &edges_buffer,
+            &verts_buffer,
+            &line_polys_buffer,
+            &vram_buffer,
+            &pixels_buffer,
+            output_view,
+        );
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("3D compute rasterizer pipeline layout"),
+            bind_group_layouts: &[&bg_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("3D compute rasterizer shader module"),
+            source: wgpu::ShaderSource::Wgsl(SHADER_SRC.into()),
+        });
+
+        let rasterize_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("3D compute rasterizer rasterization pipeline"),
+            layout: Some(&layout),
+            module: &shader_module,
+            entry_point: Some("rasterize"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let postprocess_pipeline =
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("3D compute rasterizer postprocessing pipeline"),
+                layout: Some(&layout),
+                module: &shader_module,
+                entry_point: Some("postprocess"),
+                compilation_options: Default::default(),
+                cache: None,
+            });
+
+        Rasterizer {
+            resolution_scale_shift,
+
+            params_buffer,
+            polys_buffer,
+            edges_buffer,
+            verts_buffer,
+            line_polys_buffer,
+            vram_buffer,
+            vram_uploaded: false,
+            pixels_buffer,
+
+            bg_layout,
+            bg,
+            rasterize_pipeline,
+            postprocess_pipeline,
+
+            polys: Vec::with_capacity(2048),
+            edges: Vec::with_capacity(MAX_EDGES),
+            verts: Vec::with_capacity(6144),
+            poly_lines: Vec::with_capacity(2048),
+            line_polys: Vec::with_capacity(LINE_POLYS_LEN),
+        }
+    }
+
+    /// Updates the output texture (which has to be recreated along with the resolution scale).
+    pub fn set_output(
+        &mut self,
+        device: &wgpu::Device,
+        resolution_scale_shift: u8,
+        output_view: &wgpu::TextureView,
+    ) {
+        if resolution_scale_shift != self.resolution_scale_shift {
+            self.resolution_scale_shift = resolution_scale_shift;
+            self.pixels_buffer = create_pixels_buffer(device, resolution_scale_shift);
+        }
+        self.bg = create_bg(
+            device,
+            &self.bg_layout,
+            &self.params_buffer,
+            &self.polys_buffer,
+            &self.edges_buffer,
+            &self.verts_buffer,
+            &self.line_polys_buffer,
+            &self.vram_buffer,
+            &self.pixels_buffer,
+            output_view,
+        );
+    }
+
+    fn scaled_coords(&self, vert: &ScreenVertex) -> [u32; 2] {
+        if self.resolution_scale_shift == 0 {
+            [vert.coords[0] as u32, vert.coords[1] as u8 as u32]
+        } else {
+            // Hi-res coordinates have 4 fractional bits
+            let coords = vert.hi_res_coords.to_array();
+            [
+                (coords[0] as u32) << self.resolution_scale_shift >> 4,
+                (coords[1] as u32) << self.resolution_scale_shift >> 4,
+            ]
+        }
+    }
+
+    fn push_poly(&mut self, frame: &FrameData, poly: &Polygon) {
+        let verts_len = unsafe { poly.attrs.verts_len() }.get() as usize;
+        if verts_len < 3 {
+            return;
+        }
+
+        // TODO: Shadow and shadow mask polygons (unsupported by the software renderer too)
+        if poly.attrs.mode() == 3 {
+            return;
+        }
+
+        let verts: [EdgeVertex; 10] = core::array::from_fn(|i| {
+            let addr = poly.verts[i.min(verts_len - 1)];
+            let [x, y] = self.scaled_coords(&frame.gx.vert_ram()[addr.get() as usize]);
+            EdgeVertex {
+                x,
+                y,
+                z: poly.depth_values[i],
+                w: poly.w_values[i],
+                addr: addr.get() as u32,
+            }
+        });
+        let verts = &verts[..verts_len];
+
+        let (top_y, bot_y) = if self.resolution_scale_shift == 0 {
+            (poly.top_y as u32, poly.bot_y as u32)
+        } else {
+            verts.iter().fold((u32::MAX, 0), |(top_y, bot_y), v| {
+                (top_y.min(v.y), bot_y.max(v.y))
+            })
+        };
+
+        let rendering = &frame.rendering;
+        let depth_test = if poly.attrs.depth_test_equal() {
+            if frame.gx.w_buffering {
+                DEPTH_TEST_EQUAL_W
+            } else {
+                DEPTH_TEST_EQUAL_Z
+            }
+        } else if poly.attrs.is_front_facing() {
+            DEPTH_TEST_LESS_FRONT_FACING
+        } else {
+            DEPTH_TEST_LESS_BACK_FACING
+        };
+        let mode = match poly.attrs.mode() {
+            2 => 2 + rendering.control.highlight_shading_enabled() as u32,
+            mode => mode as u32,
+        };
+        let mut flags = mode
+            | depth_test << DEPTH_TEST_SHIFT
+            | if poly.attrs.is_translucent() {
+                TRANSLUCENT
+            } else {
+                0
+            };
+
+        let edges_start = self.edges.len() as u32;
+        let edges = if top_y == bot_y {
+            flags |= DUMMY;
+
+            let (mut top_i, mut bot_i) = (0, 0);
+            for i in [1, verts_len - 1] {
+                if verts[i].x < verts[top_i].x {
+                    top_i = i;
+                }
+                if verts[i].x > verts[bot_i].x {
+                    bot_i = i;
+                }
+            }
+            self.edges.push(GpuEdge::dummy(verts[top_i]));
+            self.edges.push(GpuEdge::dummy(verts[bot_i]));
+            [edges_start | 1 << 16, (edges_start + 1) | 1 << 16]
+        } else {
+            let top_i = verts.iter().position(|v| v.y == top_y).unwrap_or(0);
+            let bot_i = verts.iter().rposition(|v| v.y == bot_y).unwrap_or(0);
+
+            let mut edges = [0; 2];
+            for (chain_i, increasing) in
+                [poly.attrs.is_front_facing(), !poly.attrs.is_front_facing()]
+                    .into_iter()
+                    .enumerate()
+            {
+                let start = self.edges.len() as u32;
+                let mut prev_i = top_i;
+                loop {
+                    let i = if increasing {
+                        (prev_i + 1) % verts_len
+                    } else {
+                        (prev_i + verts_len - 1) % verts_len
+                    };
+                    self.edges.push(GpuEdge::new(verts[prev_i], verts[i]));
+                    if i == bot_i {
+                        break;
+                    }
+                    prev_i = i;
+                }
+                edges[chain_i] = start | (self.edges.len() as u32 - start) << 16;
+            }
+            edges
+        };
+
+        let last_y = bot_y.max(top_y + 1) - 1;
+        let first_line = top_y >> self.resolution_scale_shift;
+        let last_line = (last_y >> self.resolution_scale_shift).min(191);
+        if first_line > last_line {
+            self.edges.truncate(edges_start as usize);
+            return;
+        }
+        self.poly_lines.push((first_line as u8, last_line as u8));
+
+        self.polys.push(GpuPolygon {
+            top_y,
+            bot_y,
+            attrs: poly.attrs.raw(),
+            tex_params: poly.tex_params.0,
+            tex_palette_base: poly.tex_palette_base as u32,
+            flags,
+            edges,
+        });
+    }
+
+    fn prepare(&mut self, frame: &FrameData) {
+        self.polys.clear();
+        self.edges.clear();
+        self.poly_lines.clear();
+
+        for poly in frame.gx.poly_ram() {
+            self.push_poly(frame, poly);
+        }
+
+        self.verts.clear();
+        self.verts
+            .extend(frame.gx.vert_ram().iter().map(|vert| GpuVertex {
+                color: vert.color.cast::<u32>().to_array(),
+                uv: vert.uv.cast::<i32>().to_array(),
+                _padding: [0; 2],
+            }));
+
+        // Build each line's list of polygons, in rendering order
+        self.line_polys.clear();
+        self.line_polys.resize(LINE_POLYS_HEADER_LEN, 0);
+        for &(first_line, last_line) in &self.poly_lines {
+            for line in first_line..=last_line {
+                self.line_polys[line as usize + 1] += 1;
+            }
+        }
+        self.line_polys[0] = LINE_POLYS_HEADER_LEN as u32;
+        for line in 1..LINE_POLYS_HEADER_LEN {
+            self.line_polys[line] += self.line_polys[line - 1];
+        }
+        self.line_polys
+            .resize(self.line_polys[LINE_POLYS_HEADER_LEN - 1] as usize, 0);
+        let mut line_ends = [0; 192];
+        line_ends.copy_from_slice(&self.line_polys[..192]);
+        for (poly_i, &(first_line, last_line)) in self.poly_lines.iter().enumerate() {
+            for line in first_line..=last_line {
+                let end = &mut line_ends[line as usize];
+                self.line_polys[*end as usize] = poly_i as u32;
+                *end += 1;
+            }
+        }
+    }
+
+    fn params(&self, frame: &FrameData) -> Params {
+        let rendering = &frame.rendering;
+        let fog_data = &rendering.fog_data;
+
+        let mut fog_densities = [0; 0x24];
+        fog_densities[0] = fog_data.densities[0] as u32;
+        for (dst, src) in fog_densities[1..0x21].iter_mut().zip(&fog_data.densities) {
+            *dst = *src as u32;
+        }
+        fog_densities[0x21] = fog_data.densities[0x1F] as u32;
+
+        let clear_attrs = (rendering.clear_poly_id as u32) << 24
+            | (rendering.rear_plane_fog_enabled as u32) << 15;
+
+        Params {
+            width: 256 << self.resolution_scale_shift,
+            height: 192 << self.resolution_scale_shift,
+            scale_shift: self.resolution_scale_shift as u32,
+            control: rendering.control.0 as u32,
+            w_buffering: frame.gx.w_buffering as u32,
+            alpha_test_ref: rendering.alpha_test_ref as u32,
+            clear_color: rgb5_to_rgb6(pack_color(rendering.clear_color)),
+            clear_depth: rendering.clear_depth,
+            clear_attrs,
+            clear_image_offset: rendering.clear_image_offset[0] as u32
+                | (rendering.clear_image_offset[1] as u32) << 8,
+            fog_offset: expand_depth(fog_data.offset),
+            fog_color: pack_color(fog_data.color),
+            fog_densities,
+            toon_colors: rendering.toon_colors.map(pack_color),
+            edge_colors: rendering.edge_colors.map(pack_color),
+        }
+    }
+
+    fn upload_vram(&mut self, queue: &wgpu::Queue, frame: &FrameData) {
+        let (texture_dirty, tex_pal_dirty) = if self.vram_uploaded {
+            (frame.rendering.texture_dirty, frame.rendering.tex_pal_dirty)
+        } else {
+            self.vram_uploaded = true;
+            (0xF, 0x3F)
+        };
+
+        for i in 0..4 {
+            if texture_dirty & 1 << i != 0 {
+                let range = i << 17..(i + 1) << 17;
+                queue.write_buffer(
+                    &self.vram_buffer,
+                    range.start as u64,
+                    &frame.rendering.texture[range],
+                );
+            }
+        }
+
+        for i in 0..6 {
+            if tex_pal_dirty & 1 << i != 0 {
+                let range = i << 14..(i + 1) << 14;
+                queue.write_buffer(
+                    &self.vram_buffer,
+                    0x8_0000 + range.start as u64,
+                    &frame.rendering.tex_pal[range],
+                );
+            }
+        }
+    }
+
+    pub fn render_frame(
+        &mut self,
+        queue: &wgpu::Queue,
+        frame: &FrameData,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) {
+        self.prepare(frame);
+        self.upload_vram(queue, frame);
+
+        let params = self.params(frame);
+        queue.write_buffer(&self.params_buffer, 0, as_bytes(slice::from_ref(&params)));
+        if !self.polys.is_empty() {
+            queue.write_buffer(&self.polys_buffer, 0, as_bytes(&self.polys));
+            queue.write_buffer(&self.edges_buffer, 0, as_bytes(&self.edges));
+        }
+        if !self.verts.is_empty() {
+            queue.write_buffer(&self.verts_buffer, 0, as_bytes(&self.verts));
+        }
+        queue.write_buffer(&self.line_polys_buffer, 0, as_bytes(&self.line_polys));
+
+        let workgroups_x = params.width.div_ceil(WORKGROUP_SIZE);
+        let workgroups_y = params.height.div_ceil(WORKGROUP_SIZE);
+
+        let mut compute_pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("3D compute rasterizer compute pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_bind_group(0, &self.bg, &[]);
+        compute_pass.set_pipeline(&self.rasterize_pipeline);
+        compute_pass.dispatch_workgroups(workgroups_x, workgroups_y, 1);
+        compute_pass.set_pipeline(&self.postprocess_pipeline);
+        compute_pass.dispatch_workgroups(workgroups_x, workgroups_y, 1);
+    }
+}
+
+const SHADER_SRC: &str = "
+struct Params {
+    width: u32,
+    height: u32,
+    scale_shift: u32,
+    control: u32,
+    w_buffering: u32,
+    alpha_test_ref: u32,
+    clear_color: u32,
+    clear_depth: u32,
+    clear_attrs: u32,
+    clear_image_offset: u32,
+    fog_offset: u32,
+    fog_color: u32,
+    fog_densities: array<vec4<u32>, 9>,
+    toon_colors: array<vec4<u32>, 8>,
+    edge_colors: array<vec4<u32>, 2>,
+}
+
+struct Polygon {
+    top_y: u32,
+    bot_y: u32,
+    attrs: u32,
+    tex_params: u32,
+    tex_palette_base: u32,
+    flags: u32,
+    edges: vec2<u32>,
+}
+
+struct Edge {
+    a_y: u32,
+    b_y: u32,
+    x_ref: u32,
+    x_incr: u32,
+    flags: u32,
+    interp_ref: u32,
+    interp_len: u32,
+    p_w0_numer: u32,
+    p_w0_denom: u32,
+    p_w1_denom: u32,
+    a_z: u32,
+    b_z: u32,
+    a_w: u32,
+    b_w: u32,
+    a_vert: u32,
+    b_vert: u32,
+}
+
+struct Vertex {
+    color: vec4<u32>,
+    uv: vec2<i32>,
+}
+
+struct Pixel {
+    color: u32,
+    depth: u32,
+    attrs: u32,
+    below_color: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> polys: array<Polygon>;
+@group(0) @binding(2) var<storage, read> edges: array<Edge>;
+@group(0) @binding(3) var<storage, read> verts: array<Vertex>;
+@group(0) @binding(4) var<storage, read> line_polys: array<u32>;
+@group(0) @binding(5) var<storage, read> vram: array<u32>;
+@group(0) @binding(6) var<storage, read_write> pixels: array<Pixel>;
+@group(0) @binding(7) var output: texture_storage_2d<rgba8unorm, write>;
+
+const CONTROL_TEXTURE_MAPPING: u32 = 0x1u;
+const CONTROL_ALPHA_BLENDING: u32 = 0x8u;
+const CONTROL_ANTIALIASING: u32 = 0x10u;
+const CONTROL_EDGE_MARKING: u32 = 0x20u;
+const CONTROL_FOG_ONLY_ALPHA: u32 = 0x40u;
+const CONTROL_FOG: u32 = 0x80u;
+const CONTROL_REAR_PLANE_BITMAP: u32 = 0x4000u;
+
+const POLY_MODE_MASK: u32 = 3u;
+const POLY_DUMMY: u32 = 4u;
+const POLY_TRANSLUCENT: u32 = 0x20u;
+
+const EDGE_X_MAJOR: u32 = 1u;
+const EDGE_NEGATIVE: u32 = 2u;
+const EDGE_FORCE_LINEAR: u32 = 4u;
+
+const ATTRS_OPAQUE_EDGE: u32 = 0x1u;
+const ATTRS_TRANSLUCENT: u32 = 0x2000u;
+const ATTRS_FRONT_FACING: u32 = 0x4000u;
+const ATTRS_FOG: u32 = 0x8000u;
+
+fn unpack_color(color: u32) -> vec4<u32> {
+    return (vec4(color) >> vec4(0u, 8u, 16u, 24u)) & vec4(0xFFu);
+}
+
+fn pack_color(color: vec4<u32>) -> u32 {
+    return color.r | (color.g << 8u) | (color.b << 16u) | (color.a << 24u);
+}
+
+fn decode_rgb5(color: u32, alpha: u32) -> vec4<u32> {
+    return vec4(color & 0x1Fu, (color >> 5u) & 0x1Fu, (color >> 10u) & 0x1Fu, alpha);
+}
+
+fn rgb5_to_rgb6(color: vec4<u32>) -> vec4<u32> {
+    return vec4((color.rgb << vec3(1u)) | vec3<u32>(color.rgb != vec3(0u)), color.a);
+}
+
+fn rgb5_to_rgb6_shift(color: vec4<u32>) -> vec4<u32> {
+    return vec4(color.rgb << vec3(1u), color.a);
+}
+
+fn expand_depth(depth: u32) -> u32 {
+    return (depth << 9u) | select(0u, 0x1FFu, depth == 0x7FFFu);
+}
+
+fn toon_color(index: u32) -> vec4<u32> {
+    return rgb5_to_rgb6(unpack_color(params.toon_colors[index >> 2u][index & 3u]));
+}
+
+fn tex_u8(addr: u32) -> u32 {
+    let addr_ = addr & 0x7FFFFu;
+    return (vram[addr_ >> 2u] >> ((addr_ & 3u) << 3u)) & 0xFFu;
+}
+
+fn tex_u16(addr: u32) -> u32 {
+    let addr_ = addr & 0x7FFFEu;
+    return (vram[addr_ >> 2u] >> ((addr_ & 2u) << 3u)) & 0xFFFFu;
+}
+
+// Matches `dust_soft_3d::tex_pal::addr`: out-of-range addresses wrap around at 128 KiB, and unmapped
+// palette slots read as zero
+fn pal_u16(addr: u32) -> u32 {
+    let addr_ = 0x80000u + (addr & 0x1FFFEu);
+    return (vram[addr_ >> 2u] >> ((addr_ & 2u) << 3u)) & 0xFFFFu;
+}
+
+// Returns the lower 32 bits of the 64-bit product of `a` and `b` shifted right by `shift` (which has
+// to be between 1 and 31)
+fn mul_shr(a: u32, b: u32, shift: u32) -> u32 {
+    let a_lo = a & 0xFFFFu;
+    let a_hi = a >> 16u;
+    let b_lo = b & 0xFFFFu;
+    let b_hi = b >> 16u;
+    let mid_0 = a_hi * b_lo;
+    let mid_1 = a_lo * b_hi;
+    var lo = a_lo * b_lo;
+    var hi = a_hi * b_hi + (mid_0 >> 16u) + (mid_1 >> 16u);
+    let mid_0_lo = mid_0 << 16u;
+    lo += mid_0_lo;
+    hi += u32(lo < mid_0_lo);
+    let mid_1_lo = mid_1 << 16u;
+    lo += mid_1_lo;
+    hi += u32(lo < mid_1_lo);
+    return (lo >> shift) | (hi << (32u - shift));
+}
+
+// Computes `(numer << shift) / denom` without overflowing
+fn div_shifted(numer: u32, denom: u32, shift: u32) -> u32 {
+    var quot = numer / denom;
+    var rem = numer % denom;
+    for (var i = 0u; i < shift; i++) {
+        rem <<= 1u;
+        quot <<= 1u;
+        if rem >= denom {
+            rem -= denom;
+            quot |= 1u;
+        }
+    }
+    return quot;
+}
+
+struct Interp {
+    x: u32,
+    len: u32,
+    l_denom: u32,
+    p_factor: u32,
+    persp_precision: u32,
+    force_linear: bool,
+}
+
+fn set_x(
+    x: u32,
+    len: u32,
+    p_w0_numer: u32,
+    p_w0_denom: u32,
+    p_w1_denom: u32,
+    persp_precision: u32,
+    force_linear: bool,
+) -> Interp {
+    var interp: Interp;
+    interp.x = x;
+    interp.len = len;
+    interp.l_denom = 0u;
+    if len != 0u {
+        interp.l_denom = (1u << 30u) / len;
+    }
+    let denom = x * p_w0_denom + ((len - x) & 0xFFFFu) * p_w1_denom;
+    interp.p_factor = 0u;
+    if denom != 0u {
+        interp.p_factor = div_shifted(x * p_w0_numer, denom, persp_precision) & 0xFFFFu;
+    }
+    interp.persp_precision = persp_precision;
+    interp.force_linear = force_linear;
+    return interp;
+}
+
+fn interp_diff(interp: Interp, diff: u32, rising: bool, linear: bool) -> u32 {
+    if linear {
+        let x = select((interp.len - interp.x) & 0xFFFFu, interp.x, rising);
+        return mul_shr(diff, x * interp.l_denom, 30u);
+    }
+    let factor = select((1u << interp.persp_precision) - interp.p_factor, interp.p_factor, rising);
+    return mul_shr(diff, factor, interp.persp_precision);
+}
+
+fn interp_u32(interp: Interp, a: u32, b: u32, linear: bool) -> u32 {
+    if b >= a {
+        return a + interp_diff(interp, b - a, true, linear);
+    }
+    return b + interp_diff(interp, a - b, false, linear);
+}
+
+fn interp_i32(interp: Interp, a: i32, b: i32, linear: bool) -> i32 {
+    if b >= a {
+        return a + i32(interp_diff(interp, u32(b - a), true, linear));
+    }
+    return b + i32(interp_diff(interp, u32(a - b), false, linear));
+}
+
+struct EdgeValues {
+    color: vec4<u32>,
+    uv: vec2<i32>,
+    depth: u32,
+    w: u32,
+}
+
+fn interp_values(interp: Interp, a: EdgeValues, b: EdgeValues) -> EdgeValues {
+    var result: EdgeValues;
+    let linear = interp.force_linear;
+    for (var i = 0; i < 4; i++) {
+        result.color[i] = interp_u32(interp, a.color[i], b.color[i], linear);
+    }
+    result.uv = vec2(
+        interp_i32(interp, a.uv.x, b.uv.x, linear),
+        interp_i32(interp, a.uv.y, b.uv.y, linear),
+    );
+    // Depth values are interpolated linearly with Z-buffering and perspective-correctly with
+    // W-buffering, independently of the W values
+    result.depth = interp_u32(interp, a.depth, b.depth, params.w_buffering == 0u);
+    result.w = interp_u32(interp, a.w, b.w, linear) & 0xFFFFu;
+    return result;
+}
+
+fn line_start_frac_x(edge: Edge, y: u32) -> u32 {
+    let line_x_disp = edge.x_incr * (y - edge.a_y);
+    if (edge.flags & EDGE_NEGATIVE) != 0u {
+        return edge.x_ref - line_x_disp;
+    }
+    return edge.x_ref + line_x_disp;
+}
+
+fn line_x_range(edge: Edge, y: u32) -> vec2<u32> {
+    let start_frac_x = line_start_frac_x(edge, y);
+    let start_x = start_frac_x >> 18u;
+    if (edge.flags & EDGE_X_MAJOR) == 0u {
+        return vec2(start_x);
+    }
+    if (edge.flags & EDGE_NEGATIVE) != 0u {
+        let end_frac_x = i32(start_frac_x + (0x1FFu - (start_frac_x & 0x1FFu))) - i32(edge.x_incr);
+        return vec2(u32((end_frac_x >> 18u) + 1) & 0xFFFFu, start_x);
+    }
+    return vec2(start_x, (((start_frac_x & ~0x1FFu) + edge.x_incr) >> 18u) - 1u) & vec2(0xFFFFu);
+}
+
+// Returns the approximate coverage (from 0 to 0x1F) of the pixel at `x` in the given line by the
+// polygon the edge belongs to, for antialiasing
+fn edge_coverage(edge: Edge, y: u32, x: u32, range: vec2<u32>, is_right: bool) -> u32 {
+    if (edge.flags & EDGE_X_MAJOR) != 0u {
+        let pos = select(x - range.x, range.y - x, is_right);
+        let len = range.y - range.x + 1u;
+        return min((((pos << 1u) | 1u) << 4u) / len, 0x1Fu);
+    }
+    if edge.x_incr == 0u {
+        return 0x1Fu;
+    }
+    let frac_x = (line_start_frac_x(edge, y) >> 13u) & 0x1Fu;
+    return select(0x1Fu - frac_x, frac_x, is_right);
+}
+
+fn edge_values(edge: Edge, y: u32, x: u32) -> EdgeValues {
+    var t: u32;
+    if (edge.flags & EDGE_X_MAJOR) != 0u {
+        let rel = (x - edge.interp_ref) & 0xFFFFu;
+        t = select(rel, (edge.interp_len - rel) & 0xFFFFu, (edge.flags & EDGE_NEGATIVE) != 0u);
+    } else {
+        t = y - edge.interp_ref;
+    }
+    let interp = set_x(
+        t,
+        edge.interp_len,
+        edge.p_w0_numer,
+        edge.p_w0_denom,
+        edge.p_w1_denom,
+        9u,
+        (edge.flags & EDGE_FORCE_LINEAR) != 0u,
+    );
+    let a_vert = verts[edge.a_vert];
+    let b_vert = verts[edge.b_vert];
+    return interp_values(
+        interp,
+        EdgeValues(a_vert.color, a_vert.uv, edge.a_z, edge.a_w),
+        EdgeValues(b_vert.color, b_vert.uv, edge.b_z, edge.b_w),
+    );
+}
+
+fn dummy_edge_values(edge: Edge) -> EdgeValues {
+    let vert = verts[edge.a_vert];
+    return EdgeValues(vert.color, vert.uv, edge.a_z, edge.a_w);
+}
+
+// Returns the segment of the edge chain that spans the given line
+fn find_edge(chain: u32, y: u32) -> u32 {
+    var i = chain & 0xFFFFu;
+    let end = i + (chain >> 16u) - 1u;
+    while i < end && edges[i].b_y <= y {
+        i++;
+    }
+    return i;
+}
+
+fn apply_tiling(coord: i32, size_shift: u32, repeat_enabled: bool, flip_enabled: bool) -> u32 {
+    let x = coord >> 4u;
+    let size_mask = (8 << size_shift) - 1;
+    if repeat_enabled {
+        if flip_enabled && (x & (8 << size_shift)) != 0 {
+            return u32(size_mask - (x & size_mask));
+        }
+        return u32(x & size_mask);
+    }
+    return u32(clamp(x, 0, size_mask));
+}
+
+fn sample_texture(poly: Polygon, uv: vec2<i32>, format: u32) -> vec4<u32> {
+    let tex_params = poly.tex_params;
+    let tex_base = (tex_params & 0xFFFFu) << 3u;
+    let pal_base = select(poly.tex_palette_base << 4u, poly.tex_palette_base << 3u, format == 2u);
+
+    let width_shift = (tex_params >> 20u) & 7u;
+    let height_shift = (tex_params >> 23u) & 7u;
+    let u = apply_tiling(
+        uv.x,
+        width_shift,
+        (tex_params & (1u << 16u)) != 0u,
+        (tex_params & (1u << 18u)) != 0u,
+    );
+    let v = apply_tiling(
+        uv.y,
+        height_shift,
+        (tex_params & (1u << 17u)) != 0u,
+        (tex_params & (1u << 19u)) != 0u,
+    );
+    let i = (v << (width_shift + 3u)) | u;
+    let color_0_transparent = (tex_params & (1u << 29u)) != 0u;
+
+    switch format {
+        case 1u: {
+            let pixel = tex_u8(tex_base + i);
+            let raw_alpha = pixel >> 5u;
+            let color = pal_u16(pal_base + ((pixel & 0x1Fu) << 1u));
+            return rgb5_to_rgb6(decode_rgb5(color, (raw_alpha << 2u) | (raw_alpha >> 1u)));
+        }
+
+        case 2u, 3u, 4u: {
+            var color_index: u32;
+            if format == 2u {
+                color_index = (tex_u8(tex_base + (i >> 2u)) >> ((i << 1u) & 7u)) & 3u;
+            } else if format == 3u {
+                color_index = (tex_u8(tex_base + (i >> 1u)) >> ((i << 2u) & 7u)) & 0xFu;
+            } else {
+                color_index = tex_u8(tex_base + i);
+            }
+            let color = pal_u16(pal_base + (color_index << 1u));
+            return rgb5_to_rgb6(decode_rgb5(
+                color,
+                select(0x1Fu, 0u, color_0_transparent && color_index == 0u),
+            ));
+        }
+
+        case 5u: {
+            let texel_block_addr = (tex_base & 0x40000u)
+                | ((tex_base + (((v >> 2u) << (width_shift + 3u)) | (u & ~3u))) & 0x1FFFFu);
+            let texel_value = (tex_u8(texel_block_addr | (v & 3u)) >> ((u & 3u) << 1u)) & 3u;
+
+            let pal_data_addr = 0x20000u
+                | ((texel_block_addr >> 1u) & 0xFFFEu)
+                | ((texel_block_addr >> 2u) & 0x10000u);
+            let pal_data = tex_u16(pal_data_addr);
+            let block_pal_base = pal_base + ((pal_data << 2u) & 0xFFFFu);
+            let mode = pal_data >> 14u;
+
+            var colors: array<vec4<u32>, 4>;
+            for (var j = 0u; j < 4u; j++) {
+                colors[j] = decode_rgb5(pal_u16(block_pal_base + (j << 1u)), 0x1Fu);
+            }
+
+            var color: vec4<u32>;
+            switch texel_value {
+                case 0u, 1u: {
+                    color = colors[texel_value];
+                }
+                case 2u: {
+                    switch mode {
+                        case 0u, 2u: {
+                            color = colors[2];
+                        }
+                        case 1u: {
+                            color = (colors[0] + colors[1]) >> vec4(1u);
+                        }
+                        default: {
+                            color = (colors[0] * 5u + colors[1] * 3u) >> vec4(3u);
+                        }
+                    }
+                }
+                default: {
+                    switch mode {
+                        case 0u, 1u: {
+                            color = vec4(0u);
+                        }
+                        case 2u: {
+                            color = colors[3];
+                        }
+                        default: {
+                            color = (colors[0] * 3u + colors[1] * 5u) >> vec4(3u);
+                        }
+                    }
+                }
+            }
+
+            if (mode & 1u) != 0u {
+                return rgb5_to_rgb6_shift(color);
+            }
+            return rgb5_to_rgb6(color);
+        }
+
+        case 6u: {
+            let pixel = tex_u8(tex_base + i);
+            let color = pal_u16(pal_base | ((pixel & 7u) << 1u));
+            return rgb5_to_rgb6(decode_rgb5(color, pixel >> 3u));
+        }
+
+        default: {
+            let color = tex_u16(tex_base + (i << 1u));
+            return rgb5_to_rgb6(decode_rgb5(color, select(0u, 0x1Fu, (color & 0x8000u) != 0u)));
+        }
+    }
+}
+
+fn process_pixel(poly: Polygon, uv: vec2<i32>, raw_vert_color: vec4<u32>) -> vec4<u32> {
+    let vert_color = raw_vert_color >> vec4(3u);
+    let mode = poly.flags & POLY_MODE_MASK;
+
+    var vert_blend_color: vec4<u32>;
+    if mode == 2u {
+        vert_blend_color = toon_color(min(vert_color.r >> 1u, 31u));
+    } else if mode == 3u {
+        vert_blend_color = vec4(vert_color.r);
+    } else {
+        vert_blend_color = vert_color;
+    }
+    let alpha = (poly.attrs >> 16u) & 0x1Fu;
+    vert_blend_color.a = select(alpha, 0x1Fu, alpha == 0u);
+
+    var blended_color = vert_blend_color;
+    let format = (poly.tex_params >> 26u) & 7u;
+    if (params.control & CONTROL_TEXTURE_MAPPING) != 0u && format != 0u {
+        let tex_color = sample_texture(poly, uv, format);
+        if mode == 1u {
+            if tex_color.a == 0x1Fu {
+                blended_color = vec4(tex_color.rgb, vert_blend_color.a);
+            } else if tex_color.a != 0u {
+                blended_color = vec4(
+                    (tex_color.rgb * tex_color.a + vert_blend_color.rgb * (31u - tex_color.a))
+                        >> vec3(5u),
+                    vert_blend_color.a,
+                );
+            }
+        } else {
+            blended_color = ((tex_color + 1u) * (vert_blend_color + 1u) - 1u)
+                >> vec4(6u, 6u, 6u, 5u);
+        }
+    }
+
+    if mode == 3u {
+        let toon_rgb = toon_color(min(vert_color.r >> 1u, 31u)).rgb;
+        blended_color = min(
+            blended_color + vec4(toon_rgb, 0u),
+            vec4(0x3Fu, 0x3Fu, 0x3Fu, 0x1Fu),
+        );
+    }
+
+    return blended_color;
+}
+
+fn depth_test(poly: Polygon, a: u32, b: u32, b_attrs: u32) -> bool {
+    switch (poly.flags >> 3u) & 3u {
+        case 0u: {
+            return a - b + 0xFFu <= 0x1FEu;
+        }
+        case 1u: {
+            return a - b + 0x200u <= 0x400u;
+        }
+        case 2u: {
+            if (b_attrs & (ATTRS_TRANSLUCENT | ATTRS_FRONT_FACING)) == 0u {
+                return a <= b;
+            }
+            return a < b;
+        }
+        default: {
+            return a < b;
+        }
+    }
+}
+
+fn render_pixel(
+    pixel: ptr<function, Pixel>,
+    poly: Polygon,
+    interp: Interp,
+    l: EdgeValues,
+    r: EdgeValues,
+    is_edge: bool,
+    coverage: u32,
+) {
+    let depth = interp_u32(interp, l.depth, r.depth, params.w_buffering == 0u) & 0xFFFFFFu;
+    if !depth_test(poly, depth, (*pixel).depth, (*pixel).attrs) {
+        return;
+    }
+
+    let values = interp_values(interp, l, r);
+    var color = process_pixel(poly, values.uv, values.color);
+    let alpha = color.a;
+    if alpha <= params.alpha_test_ref {
+        return;
+    }
+
+    let front_facing = poly.attrs & (1u << 30u);
+    if alpha == 0x1Fu {
+        if coverage != 0x1Fu {
+            (*pixel).below_color = (*pixel).color;
+        }
+        (*pixel).color = pack_color(color);
+        (*pixel).depth = depth;
+        (*pixel).attrs = (poly.attrs & 0x3F008000u)
+            | u32(is_edge)
+            | (coverage << 1u)
+            | (front_facing >> 16u);
+    } else {
+        let prev_attrs = (*pixel).attrs;
+        let id = ((poly.attrs >> 24u) & 0x3Fu) | 0x40u;
+        if ((prev_attrs >> 16u) & 0x7Fu) == id {
+            return;
+        }
+        if (params.control & CONTROL_ALPHA_BLENDING) != 0u {
+            let prev_color = unpack_color((*pixel).color);
+            if prev_color.a != 0u {
+                color = vec4(
+                    (color.rgb * (alpha + 1u) + prev_color.rgb * (31u - alpha)) >> vec3(5u),
+                    max(alpha, prev_color.a),
+                );
+            }
+        }
+        (*pixel).color = pack_color(color);
+        if (poly.attrs & (1u << 11u)) != 0u {
+            (*pixel).depth = depth;
+        }
+        (*pixel).attrs = (prev_attrs & 0x3F00800Fu & (poly.attrs | ~ATTRS_FOG))
+            | ATTRS_TRANSLUCENT
+            | (front_facing >> 16u)
+            | (id << 16u);
+    }
+}
+
+fn render_poly(pixel: ptr<function, Pixel>, poly: Polygon, x: u32, y: u32) {
+    let is_dummy = (poly.flags & POLY_DUMMY) != 0u;
+    if is_dummy {
+        if y != poly.top_y {
+            return;
+        }
+    } else if y < poly.top_y || y >= poly.bot_y {
+        return;
+    }
+
+    var edge_indices: vec2<u32>;
+    var ranges: array<vec2<u32>, 2>;
+    var fill_edges: array<bool, 2>;
+    var l: EdgeValues;
+    var r: EdgeValues;
+
+    if is_dummy {
+        edge_indices = poly.edges & vec2(0xFFFFu);
+        let l_edge = edges[edge_indices.x];
+        let r_edge = edges[edge_indices.y];
+        ranges[0] = vec2(l_edge.x_ref);
+        ranges[1] = vec2(r_edge.x_ref);
+        fill_edges = array(true, true);
+        l = dummy_edge_values(l_edge);
+        r = dummy_edge_values(r_edge);
+    } else {
+        edge_indices = vec2(find_edge(poly.edges.x, y), find_edge(poly.edges.y, y));
+        ranges[0] = line_x_range(edges[edge_indices.x], y);
+        ranges[1] = line_x_range(edges[edge_indices.y], y);
+        if ranges[1].x < ranges[0].x {
+            edge_indices = edge_indices.yx;
+            let range = ranges[0];
+            ranges[0] = ranges[1];
+            ranges[1] = range;
+        }
+        // The left edge cannot extend further right than the end of the right edge
+        ranges[0].y = min(ranges[0].y, ranges[1].y);
+
+        let l_edge = edges[edge_indices.x];
+        let r_edge = edges[edge_indices.y];
+        let l_x_major = (l_edge.flags & EDGE_X_MAJOR) != 0u;
+        let r_x_major = (r_edge.flags & EDGE_X_MAJOR) != 0u;
+        let next_is_horiz = l_edge.b_y == r_edge.b_y;
+        let is_last_line = y + 1u == poly.bot_y;
+        fill_edges[0] = (l_edge.flags & EDGE_NEGATIVE) != 0u
+            || !l_x_major
+            || (is_last_line && l_x_major && next_is_horiz);
+        fill_edges[1] = ((r_edge.flags & EDGE_NEGATIVE) == 0u && r_x_major)
+            || r_edge.x_incr == 0u
+            || (is_last_line && r_x_major && next_is_horiz);
+        l = edge_values(l_edge, y, ranges[0].x);
+        r = edge_values(r_edge, y, ranges[1].y);
+    }
+
+    let x_span_start = ranges[0].x;
+    let x_span_len = (ranges[1].y + 1u - x_span_start) & 0xFFFFu;
+    let is_wireframe = ((poly.attrs >> 16u) & 0x1Fu) == 0u;
+    let antialiasing = (params.control & CONTROL_ANTIALIASING) != 0u;
+    let fill_all_edges = antialiasing
+        || (params.control & CONTROL_EDGE_MARKING) != 0u
+        || is_wireframe
+        || ((poly.flags & POLY_TRANSLUCENT) != 0u
+            && (params.control & CONTROL_ALPHA_BLENDING) != 0u);
+    let is_at_y_boundary = y == poly.top_y || y + 1u == poly.bot_y;
+
+    let interp = set_x(
+        (x - x_span_start) & 0xFFFFu,
+        x_span_len,
+        l.w,
+        l.w,
+        r.w,
+        8u,
+        l.w == r.w && ((l.w | r.w) & 0x7Fu) == 0u,
+    );
+
+    // Edges are drawn in order (even if they overlap), followed by the polygon's interior
+    for (var i = 0u; i < 2u; i++) {
+        if !(fill_all_edges || fill_edges[i]) {
+            continue;
+        }
+        // If the range is out-of-screen don't render it
+        let range = ranges[i];
+        if range.x >= params.width || x < range.x || x > min(range.y, params.width - 1u) {
+            continue;
+        }
+        var coverage = 0x1Fu;
+        if antialiasing && !is_dummy {
+            coverage = edge_coverage(edges[edge_indices[i]], y, x, range, i == 1u);
+        }
+        render_pixel(pixel, poly, interp, l, r, true, coverage);
+    }
+
+    if (!is_wireframe || is_at_y_boundary) && x > ranges[0].y && x < ranges[1].x {
+        render_pixel(pixel, poly, interp, l, r, is_at_y_boundary, 0x1Fu);
+    }
+}
+
+fn clear_pixel(x: u32, y: u32) -> Pixel {
+    var pixel: Pixel;
+    pixel.below_color = 0u;
+    if (params.control & CONTROL_REAR_PLANE_BITMAP) != 0u {
+        let native_x = x >> params.scale_shift;
+        let native_y = y >> params.scale_shift;
+        let x_in_image = (native_x + (params.clear_image_offset & 0xFFu)) & 0xFFu;
+        let y_in_image = (native_y + (params.clear_image_offset >> 8u)) & 0xFFu;
+        let offset = (y_in_image << 9u) | (x_in_image << 1u);
+
+        let raw_color = tex_u16(0x40000u | offset);
+        pixel.color = pack_color(rgb5_to_rgb6(decode_rgb5(
+            raw_color,
+            select(0u, 0x1Fu, (raw_color & 0x8000u) != 0u),
+        )));
+
+        let raw_depth = tex_u16(0x60000u | offset);
+        pixel.depth = expand_depth(raw_depth & 0x7FFFu);
+        pixel.attrs = (params.clear_attrs & ~ATTRS_FOG) | (raw_depth & 0x8000u);
+    } else {
+        pixel.color = params.clear_color;
+        pixel.depth = params.clear_depth;
+        pixel.attrs = params.clear_attrs;
+    }
+    return pixel;
+}
+
+@compute @workgroup_size(8, 8)
+fn rasterize(@builtin(global_invocation_id) id: vec3<u32>) {
+    let x = id.x;
+    let y = id.y;
+    if x >= params.width || y >= params.height {
+        return;
+    }
+
+    var pixel = clear_pixel(x, y);
+    let line = y >> params.scale_shift;
+    let end = line_polys[line + 1u];
+    for (var i = line_polys[line]; i < end; i++) {
+        render_poly(&pixel, polys[line_polys[i]], x, y);
+    }
+    pixels[y * params.width + x] = pixel;
+}
+
+fn has_edge(x: i32, y: i32, depth: u32, opaque_poly_id: u32) -> bool {
+    // Out-of-screen pixels get the same depth and polygon ID as the rear plane
+    var other_depth = params.clear_depth;
+    var other_attrs = params.clear_attrs;
+    if x >= 0 && y >= 0 && u32(x) < params.width && u32(y) < params.height {
+        let other = pixels[u32(y) * params.width + u32(x)];
+        other_depth = other.depth;
+        other_attrs = other.attrs;
+    }
+    return depth < other_depth && ((other_attrs >> 24u) & 0x3Fu) != opaque_poly_id;
+}
+
+fn fog_density(index: u32) -> u32 {
+    return params.fog_densities[index >> 2u][index & 3u];
+}
+
+@compute @workgroup_size(8, 8)
+fn postprocess(@builtin(global_invocation_id) id: vec3<u32>) {
+    let x = id.x;
+    let y = id.y;
+    if x >= params.width || y >= params.height {
+        return;
+    }
+
+    let pixel = pixels[y * params.width + x];
+    var color = unpack_color(pixel.color);
+    let attrs = pixel.attrs;
+
+    if (params.control & CONTROL_EDGE_MARKING) != 0u && (attrs & ATTRS_OPAQUE_EDGE) != 0u {
+        let opaque_poly_id = (attrs >> 24u) & 0x3Fu;
+        let x_ = i32(x);
+        let y_ = i32(y);
+        if has_edge(x_, y_ - 1, pixel.depth, opaque_poly_id)
+            || has_edge(x_, y_ + 1, pixel.depth, opaque_poly_id)
+            || has_edge(x_ - 1, y_, pixel.depth, opaque_poly_id)
+            || has_edge(x_ + 1, y_, pixel.depth, opaque_poly_id)
+        {
+            let edge_color_index = opaque_poly_id >> 3u;
+            let edge_color = rgb5_to_rgb6(unpack_color(
+                params.edge_colors[edge_color_index >> 2u][edge_color_index & 3u],
+            ));
+            if (params.control & CONTROL_ANTIALIASING) != 0u {
+                color = (color + edge_color) >> vec4(1u);
+            } else {
+                color = edge_color;
+            }
+        }
+    }
+
+    if (params.control & CONTROL_ANTIALIASING) != 0u {
+        let coverage = (attrs >> 1u) & 0x1Fu;
+        if (attrs & ATTRS_OPAQUE_EDGE) != 0u
+            && (attrs & ATTRS_TRANSLUCENT) == 0u
+            && coverage != 0x1Fu
+        {
+            let below_color = unpack_color(pixel.below_color);
+            if below_color.a == 0u {
+                // Nothing was drawn below, so only fade the edge out to let the 2D layers below
+                // show through
+                color.a = (color.a * (coverage + 1u)) >> 5u;
+            } else {
+                color = (color * (coverage + 1u) + below_color * (31u - coverage)) >> vec4(5u);
+            }
+        }
+    }
+
+    if (params.control & CONTROL_FOG) != 0u && (attrs & ATTRS_FOG) != 0u {
+        var offset = 0u;
+        if pixel.depth >= params.fog_offset {
+            let depth_shift = (params.control >> 8u) & 0xFu;
+            offset = min(((pixel.depth - params.fog_offset) >> 2u) << depth_shift, 32u << 17u);
+        }
+        let index = offset >> 17u;
+        let frac = offset & 0x1FFFFu;
+        let density = (fog_density(index) * (0x20000u - frac) + fog_density(index + 1u) * frac)
+            >> 17u;
+
+        let fog_color = rgb5_to_rgb6(unpack_color(params.fog_color));
+        if (params.control & CONTROL_FOG_ONLY_ALPHA) != 0u {
+            color.a = (fog_color.a * density + color.a * (0x80u - density)) >> 7u;
+        } else {
+            color = (fog_color * density + color * (0x80u - density)) >> vec4(7u);
+        }
+    }
+
+    textureStore(
+        output,
+        vec2(x, y),
+        vec4<f32>(color) * vec4(1.0 / 63.0, 1.0 / 63.0, 1.0 / 63.0, 1.0 / 31.0),
+    );
+}
+";
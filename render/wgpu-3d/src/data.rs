@@ -1,43 +1,38 @@
 use super::utils::expand_depth;
 use dust_core::{
-    gpu::engine_3d::{Color, Polygon, RenderingControl, RenderingState, ScreenVertex},
+    gpu::engine_3d::{Color, GxSnapshot, Polygon, RenderingControl, RenderingState, ScreenVertex},
     utils::Bytes,
 };
+use std::sync::Arc;
 
 pub struct GxData {
     pub w_buffering: bool,
-
-    pub vert_ram: [ScreenVertex; 6144],
-    pub vert_ram_level: u16,
-
-    pub poly_ram: [Polygon; 2048],
-    pub poly_ram_level: u16,
+    /// The geometry to render, shared with the 3D engine; `None` until the first frame is
+    /// submitted.
+    pub snapshot: Option<Arc<GxSnapshot>>,
 }
 
 impl GxData {
-    pub fn prepare(
-        &mut self,
-        vert_ram: &[ScreenVertex],
-        poly_ram: &[Polygon],
-        state: &RenderingState,
-    ) {
+    pub fn prepare(&mut self, snapshot: &Arc<GxSnapshot>, state: &RenderingState) {
         self.w_buffering = state.w_buffering;
-
-        self.vert_ram[..vert_ram.len()].copy_from_slice(vert_ram);
-        self.vert_ram_level = vert_ram.len() as u16;
-        self.poly_ram[..poly_ram.len()].copy_from_slice(poly_ram);
-        self.poly_ram_level = poly_ram.len() as u16;
+        self.snapshot = Some(Arc::clone(snapshot));
     }
 
     pub fn copy_from(&mut self, other: &Self) {
         self.w_buffering = other.w_buffering;
+        self.snapshot.clone_from(&other.snapshot);
+    }
+
+    pub fn vert_ram(&self) -> &[ScreenVertex] {
+        self.snapshot.as_ref().map_or(&[], |snapshot| {
+            &snapshot.vert_ram()[..snapshot.vert_ram_level() as usize]
+        })
+    }
 
-        self.vert_ram_level = other.vert_ram_level;
-        self.vert_ram[..self.vert_ram_level as usize]
-            .copy_from_slice(&other.vert_ram[..self.vert_ram_level as usize]);
-        self.poly_ram_level = other.poly_ram_level;
-        self.poly_ram[..self.poly_ram_level as usize]
-            .copy_from_slice(&other.poly_ram[..self.poly_ram_level as usize]);
+    pub fn poly_ram(&self) -> &[Polygon] {
+        self.snapshot.as_ref().map_or(&[], |snapshot| {
+            &snapshot.poly_ram()[..snapshot.poly_ram_level() as usize]
+        })
     }
 }
 
@@ -0,0 +1,99 @@
+use core::fmt::{self, Write};
+
+/// A pipeline that couldn't be created, usually because the driver failed to compile one of its
+/// shaders.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PipelineFailure {
+    pub key: String,
+    pub error: String,
+    /// Whether the renderer can't produce any output without the pipeline (as opposed to only
+    /// losing the effect it implements).
+    pub fatal: bool,
+}
+
+impl fmt::Display for PipelineFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}: {}",
+            self.key,
+            if self.fatal { " (fatal)" } else { "" },
+            self.error.trim_end(),
+        )
+    }
+}
+
+/// Runs `create` inside a validation error scope, recording a failure with the given key instead
+/// of letting the error reach the device's uncaptured error handler (which would panic).
+pub(crate) fn create_checked<T>(
+    device: &wgpu::Device,
+    failures: &mut Vec<PipelineFailure>,
+    key: impl FnOnce() -> String,
+    fatal: bool,
+    create: impl FnOnce() -> T,
+) -> Option<T> {
+    // On the web, error scopes can only be popped asynchronously, and blocking on them would
+    // never return; validation errors are reported by the browser instead
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = (device, failures, key, fatal);
+        Some(create())
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        create_checked_blocking(device, failures, key, fatal, create)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn create_checked_blocking<T>(
+    device: &wgpu::Device,
+    failures: &mut Vec<PipelineFailure>,
+    key: impl FnOnce() -> String,
+    fatal: bool,
+    create: impl FnOnce() -> T,
+) -> Option<T> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let result = create();
+    match pollster::block_on(device.pop_error_scope()) {
+        None => Some(result),
+        Some(err) => {
+            failures.push(PipelineFailure {
+                key: key(),
+                error: err.to_string(),
+                fatal,
+            });
+            None
+        }
+    }
+}
+
+/// Formats a plain-text report of the given pipeline failures, along with information about the
+/// adapter they happened on, to be attached to bug reports.
+pub fn report(adapter_info: &wgpu::AdapterInfo, failures: &[PipelineFailure]) -> String {
+    let mut result = String::new();
+    let _ = writeln!(
+        result,
+        "Adapter: {} ({:?}, {:?})",
+        adapter_info.name, adapter_info.backend, adapter_info.device_type
+    );
+    let _ = writeln!(
+        result,
+        "Driver: {} ({})",
+        adapter_info.driver, adapter_info.driver_info
+    );
+    let _ = writeln!(
+        result,
+        "Vendor/device ID: {:#06X}/{:#06X}",
+        adapter_info.vendor, adapter_info.device
+    );
+    if failures.is_empty() {
+        result.push_str("No failed pipelines\n");
+    } else {
+        let _ = writeln!(result, "Failed pipelines ({}):", failures.len());
+        for failure in failures {
+            let _ = writeln!(result, "- {failure}");
+        }
+    }
+    result
+}
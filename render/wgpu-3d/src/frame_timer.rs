@@ -0,0 +1,139 @@
+use std::{
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// The device features required to measure how long the GPU takes to render each frame; they're
+/// optional, and frame times are just not reported if the device lacks them.
+pub const FRAME_TIMING_FEATURES: wgpu::Features =
+    wgpu::Features::TIMESTAMP_QUERY.union(wgpu::Features::TIMESTAMP_QUERY_INSIDE_ENCODERS);
+
+// Enough slots for a frame to be encoded while two others are still in flight
+const SLOTS: usize = 3;
+const RESULTS_SIZE: u64 = 2 * wgpu::QUERY_SIZE as u64;
+
+const FREE: u8 = 0;
+const ENCODED: u8 = 1;
+const MAPPING: u8 = 2;
+const MAPPED: u8 = 3;
+
+struct Slot {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    state: Arc<AtomicU8>,
+}
+
+/// Measures the GPU time taken by each frame through timestamps written at the start and end of
+/// its command buffer, read back asynchronously once the GPU is done with it.
+pub(crate) struct FrameTimer {
+    slots: [Slot; SLOTS],
+    cur_slot: Option<usize>,
+    timestamp_period: f32,
+}
+
+impl FrameTimer {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(FRAME_TIMING_FEATURES) {
+            return None;
+        }
+        Some(FrameTimer {
+            slots: [(); SLOTS].map(|_| Slot {
+                query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                    label: Some("3D renderer frame timestamps"),
+                    ty: wgpu::QueryType::Timestamp,
+                    count: 2,
+                }),
+                resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("3D renderer frame timestamp resolve buffer"),
+                    size: RESULTS_SIZE,
+                    usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                    mapped_at_creation: false,
+                }),
+                readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("3D renderer frame timestamp readback buffer"),
+                    size: RESULTS_SIZE,
+                    usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                    mapped_at_creation: false,
+                }),
+                state: Arc::new(AtomicU8::new(FREE)),
+            }),
+            cur_slot: None,
+            timestamp_period: queue.get_timestamp_period(),
+        })
+    }
+
+    /// Writes the frame's start timestamp, if there's a free slot to record it in (otherwise, the
+    /// frame is just not measured).
+    pub fn begin_frame(&mut self, command_encoder: &mut wgpu::CommandEncoder) {
+        self.cur_slot = self
+            .slots
+            .iter()
+            .position(|slot| slot.state.load(Ordering::Acquire) == FREE);
+        if let Some(i) = self.cur_slot {
+            command_encoder.write_timestamp(&self.slots[i].query_set, 0);
+        }
+    }
+
+    pub fn end_frame(&mut self, command_encoder: &mut wgpu::CommandEncoder) {
+        let Some(i) = self.cur_slot.take() else {
+            return;
+        };
+        let slot = &self.slots[i];
+        command_encoder.write_timestamp(&slot.query_set, 1);
+        command_encoder.resolve_query_set(&slot.query_set, 0..2, &slot.resolve_buffer, 0);
+        command_encoder.copy_buffer_to_buffer(
+            &slot.resolve_buffer,
+            0,
+            &slot.readback_buffer,
+            0,
+            RESULTS_SIZE,
+        );
+        slot.state.store(ENCODED, Ordering::Release);
+    }
+
+    /// Starts reading back the timestamps of all frames encoded so far; must be called after
+    /// their command buffers have been submitted.
+    pub fn frames_submitted(&mut self) {
+        for slot in &self.slots {
+            if slot.state.load(Ordering::Acquire) != ENCODED {
+                continue;
+            }
+            slot.state.store(MAPPING, Ordering::Release);
+            let state = Arc::clone(&slot.state);
+            slot.readback_buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    state.store(
+                        if result.is_ok() { MAPPED } else { FREE },
+                        Ordering::Release,
+                    );
+                });
+        }
+    }
+
+    /// Returns the GPU time taken by the most recent frame whose timestamps were read back since
+    /// the last call, if any.
+    pub fn poll(&mut self, device: &wgpu::Device) -> Option<Duration> {
+        device.poll(wgpu::Maintain::Poll);
+        let mut result = None;
+        for slot in &self.slots {
+            if slot.state.load(Ordering::Acquire) != MAPPED {
+                continue;
+            }
+            {
+                let data = slot.readback_buffer.slice(..).get_mapped_range();
+                let start = u64::from_le_bytes(data[..8].try_into().unwrap());
+                let end = u64::from_le_bytes(data[8..16].try_into().unwrap());
+                let nanos = end.saturating_sub(start) as f64 * self.timestamp_period as f64;
+                result = Some(Duration::from_nanos(nanos as u64));
+            }
+            slot.readback_buffer.unmap();
+            slot.state.store(FREE, Ordering::Release);
+        }
+        result
+    }
+}
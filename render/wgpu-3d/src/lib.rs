@@ -2,9 +2,19 @@
 #![warn(clippy::all)]
 #![allow(clippy::manual_div_ceil)]
 
+mod compute;
 mod data;
 pub use data::{FogData, FrameData, GxData, RenderingData};
+mod diagnostics;
+pub use diagnostics::{report as diagnostics_report, PipelineFailure};
+mod frame_timer;
+use frame_timer::FrameTimer;
+pub use frame_timer::FRAME_TIMING_FEATURES;
 mod render;
+mod support;
+pub use support::{check_support, DeviceSupport, UnsupportedError};
+mod texture_replacement;
+use texture_replacement::TextureReplacement;
 #[cfg(feature = "threaded")]
 pub mod threaded;
 mod utils;
@@ -17,15 +27,21 @@ use core::{
 };
 use dust_core::{
     gpu::engine_3d::{Color, Polygon, RenderingControl, ScreenVertex, TextureParams},
-    utils::mem_prelude::*,
+    utils::{mem_prelude::*, Bytes},
 };
-use std::sync::Arc;
+use dust_soft_3d::tex_pal;
+use std::{path::PathBuf, sync::Arc, time::Duration};
 use utils::{
     color_to_wgpu_f64, decode_rgb5, expand_depth, rgb5_to_rgb6, rgb5_to_rgb6_shift,
     round_up_to_alignment,
 };
 use wgpu::util::DeviceExt;
 
+// How many combined textures have to be invalidated by palette changes alone in a frame, for how
+// many consecutive frames, before palettes get separated in `TextureCacheMode::Auto`
+const PALETTE_THRASH_TEXTURES: usize = 4;
+const PALETTE_THRASH_FRAMES: u8 = 30;
+
 proc_bitfield::bitfield! {
     #[derive(Clone, Copy, PartialEq, Eq, Hash)]
     struct ControlFlags(pub u8): Debug {
@@ -41,8 +57,17 @@ proc_bitfield::bitfield! {
 
 impl From<RenderingControl> for ControlFlags {
     fn from(other: RenderingControl) -> Self {
-        ControlFlags(other.0 as u8 & 0xBB)
-            .with_attrs_enabled(other.fog_enabled() || other.edge_marking_enabled())
+        ControlFlags(other.0 as u8 & 0xBB).with_attrs_enabled(
+            other.fog_enabled() || other.edge_marking_enabled() || other.antialiasing_enabled(),
+        )
+    }
+}
+
+impl ControlFlags {
+    /// Whether polygon IDs need to be written to the attributes texture, to detect the edges used
+    /// by edge marking and antialiasing.
+    fn poly_ids_enabled(self) -> bool {
+        self.edge_marking_enabled() || self.antialiasing_enabled()
     }
 }
 
@@ -55,10 +80,13 @@ proc_bitfield::bitfield! {
         pub format: u8 @ 22..=24,
         pub color_0_is_transparent: bool @ 25,
         pub palette_base: u16 @ 26..=38,
+        pub indexed: bool @ 39,
     }
 }
 
 impl TextureKey {
+    const MASK: u64 = (1 << 40) - 1;
+
     pub fn new(params: TextureParams, tex_palette_base: u16) -> Self {
         TextureKey(
             (params.0 as u64 & 0xFFFF)
@@ -66,6 +94,50 @@ impl TextureKey {
                 | (tex_palette_base as u64) << 26,
         )
     }
+
+    fn is_paletted(self) -> bool {
+        matches!(self.format(), 1..=4 | 6)
+    }
+
+    /// The key of the texture holding the data sampled through this key; indexed textures don't
+    /// depend on the palette, so they're shared between all palettes.
+    fn data_key(self) -> Self {
+        if self.indexed() {
+            self.with_palette_base(0)
+        } else {
+            self
+        }
+    }
+
+    fn palette_key(self) -> PaletteKey {
+        PaletteKey(0)
+            .with_base(self.palette_base())
+            .with_format(self.format())
+    }
+}
+
+proc_bitfield::bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+    struct PaletteKey(pub u16): Debug {
+        pub base: u16 @ 0..=12,
+        pub format: u8 @ 13..=15,
+    }
+}
+
+impl PaletteKey {
+    fn addr(self) -> usize {
+        (self.base() as usize) << 3 << (self.format() != 2) as u8
+    }
+
+    fn len(self) -> usize {
+        match self.format() {
+            1 => 32,
+            2 => 4,
+            3 => 16,
+            6 => 8,
+            _ => 256,
+        }
+    }
 }
 
 proc_bitfield::bitfield! {
@@ -84,6 +156,46 @@ impl From<TextureParams> for SamplerKey {
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TextureFilter {
+    #[default]
+    Nearest,
+    Bilinear,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TextureCacheMode {
+    /// Decode paletted textures together with their palette, re-decoding them whenever either
+    /// changes.
+    Combined,
+    /// Decode paletted textures into color indices and look colors up in a separately cached
+    /// palette while rendering, so that palette changes only require re-uploading the palette.
+    PaletteSeparated,
+    /// Start out in combined mode and switch to separated palettes once textures are found to be
+    /// re-decoded on most frames because of palette changes (i.e. when palettes are animated).
+    #[default]
+    Auto,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TextureFiltering {
+    pub filter: TextureFilter,
+    /// The maximum anisotropy to use when sampling textures with bilinear filtering; only applied
+    /// when rendering at an increased resolution, as at native resolution the DS's texture
+    /// coordinates are too imprecise for it to make a difference. Values of 1 or less disable
+    /// anisotropic filtering.
+    pub max_anisotropy: u16,
+}
+
+impl Default for TextureFiltering {
+    fn default() -> Self {
+        TextureFiltering {
+            filter: TextureFilter::Nearest,
+            max_anisotropy: 1,
+        }
+    }
+}
+
 proc_bitfield::bitfield! {
     #[derive(Clone, Copy, PartialEq, Eq, Hash)]
     struct PipelineKey(pub u16): Debug {
@@ -96,6 +208,7 @@ proc_bitfield::bitfield! {
         pub attrs_enabled: bool @ 7,
         pub fog_enabled: bool @ 8,
         pub edge_marking_enabled: bool @ 8,
+        pub indexed_texture: bool @ 9,
     }
 }
 
@@ -133,7 +246,13 @@ enum BatchKind {
 }
 
 impl BatchKind {
-    pub fn new(control: ControlFlags, w_buffering: bool, alpha_ref: u8, poly: &Polygon) -> Self {
+    pub fn new(
+        control: ControlFlags,
+        w_buffering: bool,
+        alpha_ref: u8,
+        palettes_separated: bool,
+        poly: &Polygon,
+    ) -> Self {
         let mode = poly.attrs.mode();
         let id = poly.attrs.id();
         let depth_test_equal = poly.attrs.depth_test_equal();
@@ -144,6 +263,13 @@ impl BatchKind {
             let texture_mapping_enabled =
                 control.texture_mapping_enabled() && poly.tex_params.format() != 0;
             let global_fog_enabled = control.fog_enabled();
+            let texture = texture_mapping_enabled.then(|| {
+                let texture_key = TextureKey::new(poly.tex_params, poly.tex_palette_base);
+                (
+                    texture_key.with_indexed(palettes_separated && texture_key.is_paletted()),
+                    SamplerKey::from(poly.tex_params),
+                )
+            });
             let pipeline = PipelineKey(0)
                 .with_texture_mapping_enabled(texture_mapping_enabled)
                 .with_alpha_blending_enabled(
@@ -163,13 +289,8 @@ impl BatchKind {
                 .with_w_buffering(w_buffering)
                 .with_attrs_enabled(control.attrs_enabled())
                 .with_fog_enabled(global_fog_enabled)
-                .with_edge_marking_enabled(control.edge_marking_enabled());
-            let texture = texture_mapping_enabled.then(|| {
-                (
-                    TextureKey::new(poly.tex_params, poly.tex_palette_base),
-                    poly.tex_params.into(),
-                )
-            });
+                .with_edge_marking_enabled(control.poly_ids_enabled())
+                .with_indexed_texture(texture.is_some_and(|(key, _)| key.indexed()));
 
             let alpha = poly.attrs.alpha();
 
@@ -212,8 +333,46 @@ impl BatchKind {
 
 struct Texture {
     view: wgpu::TextureView,
+    params: wgpu::Buffer,
     texture_region_mask: u8,
     tex_pal_region_mask: u8,
+    /// Whether the texture was decoded by [`Renderer::prewarm_textures`] and no frame was rendered
+    /// since then.
+    prewarmed: bool,
+}
+
+/// The texture memory contents prewarmed textures were decoded from.
+struct PrewarmVram {
+    texture: Bytes<0x8_0000>,
+    tex_pal: Bytes<0x2_0000>,
+}
+
+impl PrewarmVram {
+    /// Returns the texture and texture palette regions whose contents in the given frame differ
+    /// from the ones textures were prewarmed with, in the same format as the frame's dirty masks.
+    fn changed_regions(&self, frame: &FrameData) -> (u8, u8) {
+        let mut texture_changed = 0;
+        for i in 0..4 {
+            let range = i << 17..(i + 1) << 17;
+            if self.texture[range.clone()] != frame.rendering.texture[range] {
+                texture_changed |= 1 << i;
+            }
+        }
+        let mut tex_pal_changed = 0;
+        for i in 0..6 {
+            let range = i << 14..(i + 1) << 14;
+            if self.tex_pal[range.clone()] != frame.rendering.tex_pal[range] {
+                tex_pal_changed |= 1 << i;
+            }
+        }
+        (texture_changed, tex_pal_changed)
+    }
+}
+
+struct Palette {
+    raw: wgpu::Texture,
+    view: wgpu::TextureView,
+    tex_pal_region_mask: u8,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
@@ -293,35 +452,22 @@ impl Vertex {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn create_texture(
     device: &wgpu::Device,
     queue: &wgpu::Queue,
     texture_key: TextureKey,
-    frame: &FrameData,
+    texture: &Bytes<0x8_0000>,
+    tex_pal: &Bytes<0x2_0000>,
     decode_buffer: &mut Vec<u32>,
+    texture_replacement: &mut TextureReplacement,
+    filter: TextureFilter,
 ) -> Texture {
     let width = 8 << texture_key.width_shift();
     let height = 8 << texture_key.height_shift();
     let total_shift = texture_key.width_shift() + texture_key.height_shift();
     let len = 64 << total_shift;
 
-    let size = wgpu::Extent3d {
-        width,
-        height,
-        depth_or_array_layers: 1,
-    };
-
-    let raw = device.create_texture(&wgpu::TextureDescriptor {
-        label: Some("3D renderer texture"),
-        size,
-        mip_level_count: 1,
-        sample_count: 1,
-        dimension: wgpu::TextureDimension::D2,
-        format: wgpu::TextureFormat::Rgba8Unorm,
-        usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
-        view_formats: &[],
-    });
-
     decode_buffer.clear();
     decode_buffer.reserve(len);
 
@@ -351,25 +497,36 @@ fn create_texture(
 
     macro_rules! read_palette {
         ($color_index: expr, $alpha: expr) => {{
-            let addr = (pal_base + ($color_index << 1)) & 0x1_FFFF;
+            let addr = tex_pal::addr(pal_base, $color_index << 1);
             tex_pal_region_mask |= 1 << (addr >> 14);
-            decode_rgb5(frame.rendering.tex_pal.read_le::<u16>(addr), $alpha)
+            decode_rgb5(tex_pal.read_le::<u16>(addr), $alpha)
         }};
     }
 
+    // Indexed textures store the color index in the red channel and the alpha in the green one,
+    // with the colors being looked up in the palette while rendering
+    let indexed = texture_key.indexed();
+
+    macro_rules! texel {
+        ($color_index: expr, $alpha: expr) => {
+            if indexed {
+                $color_index as u32 | ($alpha as u32) << 8
+            } else {
+                rgb5_to_rgb6(read_palette!($color_index, $alpha))
+            }
+        };
+    }
+
     match texture_key.format() {
         1 => {
             calc_range!(range, 8);
 
             let mut i = range.0;
             while i != range.1 || decode_buffer.len() != len {
-                let pixel = unsafe { *frame.rendering.texture.get_unchecked(i) };
+                let pixel = unsafe { *texture.get_unchecked(i) };
                 let color_index = pixel as usize & 0x1F;
                 let raw_alpha = pixel >> 5;
-                decode_buffer.push(rgb5_to_rgb6(read_palette!(
-                    color_index,
-                    raw_alpha << 2 | raw_alpha >> 1
-                )));
+                decode_buffer.push(texel!(color_index, raw_alpha << 2 | raw_alpha >> 1));
                 i = (i + 1) & 0x7_FFFF;
             }
         }
@@ -379,17 +536,17 @@ fn create_texture(
 
             let mut i = range.0;
             while i != range.1 || decode_buffer.len() != len {
-                let mut pixels = unsafe { *frame.rendering.texture.get_unchecked(i) };
+                let mut pixels = unsafe { *texture.get_unchecked(i) };
                 for _ in 0..4 {
                     let color_index = pixels as usize & 3;
-                    decode_buffer.push(rgb5_to_rgb6(read_palette!(
+                    decode_buffer.push(texel!(
                         color_index,
                         if texture_key.color_0_is_transparent() && color_index == 0 {
                             0
                         } else {
                             0x1F
                         }
-                    )));
+                    ));
                     pixels >>= 2;
                 }
                 i = (i + 1) & 0x7_FFFF;
@@ -401,17 +558,17 @@ fn create_texture(
 
             let mut i = range.0;
             while i != range.1 || decode_buffer.len() != len {
-                let mut pixels = unsafe { *frame.rendering.texture.get_unchecked(i) };
+                let mut pixels = unsafe { *texture.get_unchecked(i) };
                 for _ in 0..2 {
                     let color_index = pixels as usize & 0xF;
-                    decode_buffer.push(rgb5_to_rgb6(read_palette!(
+                    decode_buffer.push(texel!(
                         color_index,
                         if texture_key.color_0_is_transparent() && color_index == 0 {
                             0
                         } else {
                             0x1F
                         }
-                    )));
+                    ));
                     pixels >>= 4;
                 }
                 i = (i + 1) & 0x7_FFFF;
@@ -423,15 +580,15 @@ fn create_texture(
 
             let mut i = range.0;
             while i != range.1 || decode_buffer.len() != len {
-                let color_index = unsafe { *frame.rendering.texture.get_unchecked(i) } as usize;
-                decode_buffer.push(rgb5_to_rgb6(read_palette!(
+                let color_index = unsafe { *texture.get_unchecked(i) } as usize;
+                decode_buffer.push(texel!(
                     color_index,
                     if texture_key.color_0_is_transparent() && color_index == 0 {
                         0
                     } else {
                         0x1F
                     }
-                )));
+                ));
                 i = (i + 1) & 0x7_FFFF;
             }
         }
@@ -452,12 +609,9 @@ fn create_texture(
             let mut i = slot_0_2_range.0;
             while i != slot_0_2_range.1 {
                 unsafe {
-                    let mut pixels = frame.rendering.texture.read_le_aligned_unchecked::<u32>(i);
+                    let mut pixels = texture.read_le_aligned_unchecked::<u32>(i);
                     let pal_data_addr = 0x2_0000 | (i >> 1 & 0xFFFE) | (i >> 2 & 0x1_0000);
-                    let pal_data = frame
-                        .rendering
-                        .texture
-                        .read_le_aligned_unchecked::<u16>(pal_data_addr);
+                    let pal_data = texture.read_le_aligned_unchecked::<u16>(pal_data_addr);
                     let pal_base = pal_base + (pal_data << 2) as usize;
                     let mode = pal_data >> 14;
 
@@ -481,12 +635,9 @@ fn create_texture(
                         ($i: expr) => {
                             decode_rgb5(
                                 {
-                                    let addr = (pal_base + ($i << 1)) & 0x1_FFFE;
+                                    let addr = tex_pal::addr(pal_base, $i << 1);
                                     tex_pal_region_mask |= 1 << (addr >> 14);
-                                    frame
-                                        .rendering
-                                        .tex_pal
-                                        .read_le_aligned_unchecked::<u16>(addr)
+                                    tex_pal.read_le_aligned_unchecked::<u16>(addr)
                                 },
                                 0x1F,
                             )
@@ -550,10 +701,10 @@ fn create_texture(
 
             let mut i = range.0;
             while i != range.1 || decode_buffer.len() != len {
-                let pixel = unsafe { *frame.rendering.texture.get_unchecked(i) };
+                let pixel = unsafe { *texture.get_unchecked(i) };
                 let color_index = pixel as usize & 7;
                 let raw_alpha = pixel >> 3;
-                decode_buffer.push(rgb5_to_rgb6(read_palette!(color_index, raw_alpha)));
+                decode_buffer.push(texel!(color_index, raw_alpha));
                 i = (i + 1) & 0x7_FFFF;
             }
         }
@@ -563,7 +714,7 @@ fn create_texture(
 
             let mut i = range.0;
             while i != range.1 || decode_buffer.len() != len {
-                let color = unsafe { frame.rendering.texture.read_le_aligned_unchecked::<u16>(i) };
+                let color = unsafe { texture.read_le_aligned_unchecked::<u16>(i) };
                 decode_buffer.push(rgb5_to_rgb6(decode_rgb5(
                     color,
                     if color & 0x8000 != 0 { 0x1F } else { 0 },
@@ -573,29 +724,179 @@ fn create_texture(
         }
     }
 
-    unsafe {
-        queue.write_texture(
-            raw.as_image_copy(),
-            slice::from_raw_parts(decode_buffer.as_ptr() as *const u8, decode_buffer.len() * 4),
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(width << 2),
-                rows_per_image: None,
+    // Textures still need to be decoded when being replaced, as their hash depends on their
+    // decoded contents (which indexed textures don't have)
+    let replacement = if texture_replacement.is_enabled() && !indexed {
+        let hash = texture_replacement::hash(width, height, decode_buffer);
+        texture_replacement.dump(width, height, hash, decode_buffer);
+        texture_replacement.replacement(hash)
+    } else {
+        None
+    };
+
+    // Decoded textures contain 6-bit color components and 5-bit alpha, while replacements use the
+    // full 8-bit range
+    let (data, data_width, data_height, color_scale) = match &replacement {
+        Some(image) => (&image.data[..], image.width, image.height, [1.0, 1.0]),
+        None => (
+            unsafe {
+                slice::from_raw_parts(decode_buffer.as_ptr() as *const u8, decode_buffer.len() * 4)
             },
-            size,
-        );
-    }
+            width,
+            height,
+            [255.0 / 63.0, 255.0 / 31.0],
+        ),
+    };
+
+    let size = wgpu::Extent3d {
+        width: data_width,
+        height: data_height,
+        depth_or_array_layers: 1,
+    };
+
+    let raw = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("3D renderer texture"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+
+    queue.write_texture(
+        raw.as_image_copy(),
+        data,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(data_width << 2),
+            rows_per_image: None,
+        },
+        size,
+    );
 
     let view = raw.create_view(&wgpu::TextureViewDescriptor::default());
 
+    // Texture coordinates are always specified in texels of the original texture, so they're
+    // normalized using its size rather than the replacement's
+    let mut params = vec![width as f32, height as f32, color_scale[0], color_scale[1]];
+    if indexed {
+        // Indexed textures are always sampled as individual texels, and filtered manually after
+        // the palette lookup
+        let bilinear = if filter == TextureFilter::Bilinear {
+            1.0
+        } else {
+            0.0
+        };
+        params.extend_from_slice(&[bilinear, 0.0, 0.0, 0.0]);
+    }
+    let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("3D renderer texture params"),
+        contents: &params
+            .into_iter()
+            .flat_map(f32::to_ne_bytes)
+            .collect::<Vec<_>>(),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+
     Texture {
         view,
+        params,
         texture_region_mask,
         tex_pal_region_mask: tex_pal_region_mask & 0x3F,
+        prewarmed: false,
+    }
+}
+
+fn create_palette(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    palette_key: PaletteKey,
+    frame: &FrameData,
+    decode_buffer: &mut Vec<u32>,
+) -> Palette {
+    let raw = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("3D renderer palette"),
+        size: wgpu::Extent3d {
+            width: 256,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    let view = raw.create_view(&wgpu::TextureViewDescriptor::default());
+    let mut palette = Palette {
+        raw,
+        view,
+        tex_pal_region_mask: 0,
+    };
+    upload_palette(queue, &mut palette, palette_key, frame, decode_buffer);
+    palette
+}
+
+fn upload_palette(
+    queue: &wgpu::Queue,
+    palette: &mut Palette,
+    palette_key: PaletteKey,
+    frame: &FrameData,
+    decode_buffer: &mut Vec<u32>,
+) {
+    let len = palette_key.len();
+    let base = palette_key.addr();
+
+    decode_buffer.clear();
+    decode_buffer.reserve(len);
+
+    let mut tex_pal_region_mask = 0;
+    for i in 0..len {
+        let addr = tex_pal::addr(base, i << 1);
+        tex_pal_region_mask |= 1 << (addr >> 14);
+        decode_buffer.push(rgb5_to_rgb6(decode_rgb5(
+            frame.rendering.tex_pal.read_le::<u16>(addr),
+            0x1F,
+        )));
     }
+    palette.tex_pal_region_mask = tex_pal_region_mask & 0x3F;
+
+    queue.write_texture(
+        palette.raw.as_image_copy(),
+        unsafe { slice::from_raw_parts(decode_buffer.as_ptr() as *const u8, len * 4) },
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(len as u32 * 4),
+            rows_per_image: None,
+        },
+        wgpu::Extent3d {
+            width: len as u32,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+    );
 }
 
-fn create_sampler(device: &wgpu::Device, sampler_key: SamplerKey) -> wgpu::Sampler {
+fn create_sampler(
+    device: &wgpu::Device,
+    sampler_key: SamplerKey,
+    filtering: TextureFiltering,
+    resolution_scale_shift: u8,
+) -> wgpu::Sampler {
+    let filter_mode = match filtering.filter {
+        TextureFilter::Nearest => wgpu::FilterMode::Nearest,
+        TextureFilter::Bilinear => wgpu::FilterMode::Linear,
+    };
+    // Anisotropic filtering requires all filter modes to be linear
+    let anisotropy_clamp =
+        if filtering.filter == TextureFilter::Bilinear && resolution_scale_shift != 0 {
+            filtering.max_anisotropy.clamp(1, 16)
+        } else {
+            1
+        };
     device.create_sampler(&wgpu::SamplerDescriptor {
         label: Some("3D renderer texture descriptor"),
         address_mode_u: if sampler_key.repeat_s() {
@@ -616,40 +917,142 @@ fn create_sampler(device: &wgpu::Device, sampler_key: SamplerKey) -> wgpu::Sampl
         } else {
             wgpu::AddressMode::ClampToEdge
         },
+        mag_filter: filter_mode,
+        min_filter: filter_mode,
+        mipmap_filter: filter_mode,
+        anisotropy_clamp,
         ..Default::default()
     })
 }
 
+const MSAA_SAMPLE_COUNT: u32 = 4;
+
+struct MsaaAttachments {
+    color_view: wgpu::TextureView,
+    depth_view: wgpu::TextureView,
+    attrs_view: wgpu::TextureView,
+    depth_attrs_bg: wgpu::BindGroup,
+}
+
+impl MsaaAttachments {
+    fn new(
+        device: &wgpu::Device,
+        size: wgpu::Extent3d,
+        depth_attrs_bg_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let create_texture = |label, format| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size,
+                mip_level_count: 1,
+                sample_count: MSAA_SAMPLE_COUNT,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+        };
+
+        let color = create_texture(
+            "3D renderer multisampled color",
+            wgpu::TextureFormat::Rgba8Unorm,
+        );
+        let color_view = color.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("3D renderer multisampled color view"),
+            ..wgpu::TextureViewDescriptor::default()
+        });
+
+        let depth = create_texture(
+            "3D renderer multisampled depth",
+            wgpu::TextureFormat::Depth24PlusStencil8,
+        );
+        let depth_view = depth.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("3D renderer multisampled depth view"),
+            ..wgpu::TextureViewDescriptor::default()
+        });
+
+        let attrs = create_texture(
+            "3D renderer multisampled attributes",
+            wgpu::TextureFormat::Rgba8Unorm,
+        );
+        let attrs_view = attrs.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("3D renderer multisampled attributes view"),
+            ..wgpu::TextureViewDescriptor::default()
+        });
+
+        let depth_attrs_bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("3D renderer multisampled depth/attrs bind group"),
+            layout: depth_attrs_bg_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&depth.create_view(
+                        &wgpu::TextureViewDescriptor {
+                            label: Some("3D renderer multisampled depth only view"),
+                            aspect: wgpu::TextureAspect::DepthOnly,
+                            ..wgpu::TextureViewDescriptor::default()
+                        },
+                    )),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&attrs_view),
+                },
+            ],
+        });
+
+        MsaaAttachments {
+            color_view,
+            depth_view,
+            attrs_view,
+            depth_attrs_bg,
+        }
+    }
+}
+
 struct OutputAttachments {
     color: [(wgpu::Texture, wgpu::TextureView, wgpu::BindGroup); 2],
     depth_view: wgpu::TextureView,
     attrs_view: wgpu::TextureView,
     depth_attrs_bg: wgpu::BindGroup,
+    msaa: Option<MsaaAttachments>,
 }
 
 impl OutputAttachments {
     fn new(
         device: &wgpu::Device,
         resolution_scale_shift: u8,
-        color_bg_layout: &wgpu::BindGroupLayout,
-        depth_attrs_bg_layout: &wgpu::BindGroupLayout,
+        msaa_enabled: bool,
+        compute_rasterizer_enabled: bool,
+        bg_layouts: &BgLayouts,
     ) -> Self {
         let resolution_scale = 1 << resolution_scale_shift;
+        let size = wgpu::Extent3d {
+            width: 256 * resolution_scale,
+            height: 192 * resolution_scale,
+            depth_or_array_layers: 1,
+        };
 
         let color = [0, 1].map(|_| {
             let color = device.create_texture(&wgpu::TextureDescriptor {
                 label: Some("3D renderer color"),
-                size: wgpu::Extent3d {
-                    width: 256 * resolution_scale,
-                    height: 192 * resolution_scale,
-                    depth_or_array_layers: 1,
-                },
+                size,
                 mip_level_count: 1,
                 sample_count: 1,
                 dimension: wgpu::TextureDimension::D2,
                 format: wgpu::TextureFormat::Rgba8Unorm,
-                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
-                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                // The output can also be copied from, for frontends that read it back
+                usage: if compute_rasterizer_enabled {
+                    wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING
+                        | wgpu::TextureUsages::STORAGE_BINDING
+                        | wgpu::TextureUsages::COPY_SRC
+                } else {
+                    wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING
+                        | wgpu::TextureUsages::COPY_SRC
+                },
                 view_formats: &[],
             });
             let color_view = color.create_view(&wgpu::TextureViewDescriptor {
@@ -658,7 +1061,7 @@ impl OutputAttachments {
             });
             let color_bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
                 label: Some("3D renderer color bind group"),
-                layout: color_bg_layout,
+                layout: &bg_layouts.color,
                 entries: &[wgpu::BindGroupEntry {
                     binding: 0,
                     resource: wgpu::BindingResource::TextureView(&color_view),
@@ -669,11 +1072,7 @@ impl OutputAttachments {
 
         let depth = device.create_texture(&wgpu::TextureDescriptor {
             label: Some("3D renderer depth"),
-            size: wgpu::Extent3d {
-                width: 256 * resolution_scale,
-                height: 192 * resolution_scale,
-                depth_or_array_layers: 1,
-            },
+            size,
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
@@ -688,11 +1087,7 @@ impl OutputAttachments {
 
         let attrs = device.create_texture(&wgpu::TextureDescriptor {
             label: Some("3D renderer attributes"),
-            size: wgpu::Extent3d {
-                width: 256 * resolution_scale,
-                height: 192 * resolution_scale,
-                depth_or_array_layers: 1,
-            },
+            size,
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
@@ -707,7 +1102,7 @@ impl OutputAttachments {
 
         let depth_attrs_bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("3D renderer depth/attrs bind group"),
-            layout: depth_attrs_bg_layout,
+            layout: &bg_layouts.depth_attrs,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
@@ -726,11 +1121,23 @@ impl OutputAttachments {
             ],
         });
 
+        let msaa =
+            msaa_enabled.then(|| MsaaAttachments::new(device, size, &bg_layouts.msaa_depth_attrs));
+
         OutputAttachments {
             color,
             depth_view,
             attrs_view,
             depth_attrs_bg,
+            msaa,
+        }
+    }
+
+    fn sample_count(&self) -> u32 {
+        if self.msaa.is_some() {
+            MSAA_SAMPLE_COUNT
+        } else {
+            1
         }
     }
 }
@@ -738,10 +1145,12 @@ impl OutputAttachments {
 struct BgLayouts {
     color: wgpu::BindGroupLayout,
     depth_attrs: wgpu::BindGroupLayout,
+    msaa_depth_attrs: wgpu::BindGroupLayout,
     id: wgpu::BindGroupLayout,
     alpha_and_ref: wgpu::BindGroupLayout,
     fog_enabled: wgpu::BindGroupLayout,
     texture: wgpu::BindGroupLayout,
+    indexed_texture: wgpu::BindGroupLayout,
     toon: wgpu::BindGroupLayout,
     fog_data: wgpu::BindGroupLayout,
     edge_colors: wgpu::BindGroupLayout,
@@ -755,6 +1164,7 @@ pub struct Renderer {
     // hi_res_coords_mask: u16x2,
     color_output_index: u8,
     output_attachments: OutputAttachments,
+    compute_rasterizer: Option<compute::Rasterizer>,
 
     vtx_buffer: wgpu::Buffer,
     vtx_buffer_contents: Vec<Vertex>,
@@ -773,7 +1183,14 @@ pub struct Renderer {
     id_bg_elem_size: usize,
 
     textures: HashMap<TextureKey, Texture>,
+    texture_cache_changed: bool,
+    prewarm_vram: Option<Box<PrewarmVram>>,
+    palettes: HashMap<PaletteKey, Palette>,
+    texture_cache_mode: TextureCacheMode,
+    palette_thrash_frames: u8,
+    texture_replacement: TextureReplacement,
     // rear_plane_texture: wgpu::Texture,
+    texture_filtering: TextureFiltering,
     samplers: [Option<wgpu::Sampler>; 0x10],
     texture_bgs: HashMap<(TextureKey, SamplerKey), wgpu::BindGroup>,
     texture_decode_buffer: Vec<u32>,
@@ -794,9 +1211,16 @@ pub struct Renderer {
     trans_pipelines: HashMap<PipelineKey, [wgpu::RenderPipeline; 2]>,
     trans_no_depth_update_pipelines: HashMap<PipelineKey, [wgpu::RenderPipeline; 2]>,
     // rear_plane_bitmap_pipeline: Pipeline,
-    fog_pipelines: [wgpu::RenderPipeline; 2],
-    edge_marking_pipelines: [wgpu::RenderPipeline; 2],
+    // The post-processing pipelines are optional: if any of them fails to be created, only the
+    // corresponding effect is disabled
+    fog_pipelines: Option<[wgpu::RenderPipeline; 2]>,
+    edge_marking_pipelines: Option<[wgpu::RenderPipeline; 2]>,
+    antialiasing_pipeline: Option<wgpu::RenderPipeline>,
+    msaa_resolve_pipeline: Option<wgpu::RenderPipeline>,
+    pipeline_failures: Vec<PipelineFailure>,
     batches: Vec<PreparedBatch>,
+
+    frame_timer: Option<FrameTimer>,
 }
 
 impl Renderer {
@@ -804,6 +1228,8 @@ impl Renderer {
         device: Arc<wgpu::Device>,
         queue: Arc<wgpu::Queue>,
         resolution_scale_shift: u8,
+        texture_filtering: TextureFiltering,
+        msaa_enabled: bool,
     ) -> Self {
         let device_limits = device.limits();
         let min_uniform_buffer_offset_alignment = device_limits.min_uniform_buffer_offset_alignment;
@@ -865,6 +1291,33 @@ impl Renderer {
                 ],
             });
 
+        let msaa_depth_attrs_bg_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("3D renderer multisampled depth/attrs bind group layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Depth,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: true,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: true,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
         macro_rules! constant_buffer_bg {
             ($label: literal, $shader_stages: expr, $binding_size: expr, $contents: expr) => {{
                 let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -971,9 +1424,62 @@ impl Renderer {
                     ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(16),
+                    },
+                    count: None,
+                },
             ],
         });
 
+        let indexed_texture_bg_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("3D renderer indexed texture bind group layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: wgpu::BufferSize::new(32),
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
         let toon_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("3D renderer toon table"),
             size: 0x200,
@@ -1072,34 +1578,73 @@ impl Renderer {
             }],
         });
 
-        let output_attachments = OutputAttachments::new(
-            &device,
-            resolution_scale_shift,
-            &color_bg_layout,
-            &depth_attrs_bg_layout,
-        );
-
         let bg_layouts = BgLayouts {
             color: color_bg_layout,
             depth_attrs: depth_attrs_bg_layout,
+            msaa_depth_attrs: msaa_depth_attrs_bg_layout,
             alpha_and_ref: alpha_and_ref_bg_layout,
             fog_enabled: fog_enabled_bg_layout,
             id: id_bg_layout,
             texture: texture_bg_layout,
+            indexed_texture: indexed_texture_bg_layout,
             toon: toon_bg_layout,
             fog_data: fog_data_bg_layout,
             edge_colors: edge_colors_bg_layout,
         };
 
-        let fog_pipelines = [
-            render::fog::create_pipeline(false, &device, &bg_layouts),
-            render::fog::create_pipeline(true, &device, &bg_layouts),
-        ];
+        let mut pipeline_failures = Vec::new();
+
+        let fog_pipelines = diagnostics::create_checked(
+            &device,
+            &mut pipeline_failures,
+            || "fog".to_owned(),
+            false,
+            || {
+                [
+                    render::fog::create_pipeline(false, &device, &bg_layouts),
+                    render::fog::create_pipeline(true, &device, &bg_layouts),
+                ]
+            },
+        );
+
+        let edge_marking_pipelines = diagnostics::create_checked(
+            &device,
+            &mut pipeline_failures,
+            || "edge marking".to_owned(),
+            false,
+            || {
+                [
+                    render::edge_marking::create_pipeline(false, &device, &bg_layouts),
+                    render::edge_marking::create_pipeline(true, &device, &bg_layouts),
+                ]
+            },
+        );
+
+        let antialiasing_pipeline = diagnostics::create_checked(
+            &device,
+            &mut pipeline_failures,
+            || "antialiasing".to_owned(),
+            false,
+            || render::antialiasing::create_pipeline(&device, &bg_layouts),
+        );
+
+        let msaa_resolve_pipeline = diagnostics::create_checked(
+            &device,
+            &mut pipeline_failures,
+            || "MSAA resolve".to_owned(),
+            false,
+            || render::msaa_resolve::create_pipeline(&device, &bg_layouts),
+        );
+
+        let output_attachments = OutputAttachments::new(
+            &device,
+            resolution_scale_shift,
+            msaa_enabled && msaa_resolve_pipeline.is_some(),
+            false,
+            &bg_layouts,
+        );
 
-        let edge_marking_pipelines = [
-            render::edge_marking::create_pipeline(false, &device, &bg_layouts),
-            render::edge_marking::create_pipeline(true, &device, &bg_layouts),
-        ];
+        let frame_timer = FrameTimer::new(&device, &queue);
 
         Renderer {
             device,
@@ -1109,6 +1654,7 @@ impl Renderer {
             // hi_res_coords_mask,
             color_output_index: 0,
             output_attachments,
+            compute_rasterizer: None,
 
             vtx_buffer: vert_buffer,
             vtx_buffer_contents: Vec::new(),
@@ -1127,6 +1673,13 @@ impl Renderer {
             id_bg_elem_size,
 
             textures: HashMap::default(),
+            texture_cache_changed: false,
+            prewarm_vram: None,
+            palettes: HashMap::default(),
+            texture_cache_mode: TextureCacheMode::default(),
+            palette_thrash_frames: 0,
+            texture_replacement: TextureReplacement::default(),
+            texture_filtering,
             samplers: [const { None }; 0x10],
             texture_bgs: HashMap::default(),
             texture_decode_buffer: Vec::new(),
@@ -1153,8 +1706,13 @@ impl Renderer {
             trans_no_depth_update_pipelines: HashMap::default(),
             fog_pipelines,
             edge_marking_pipelines,
+            antialiasing_pipeline,
+            msaa_resolve_pipeline,
+            pipeline_failures,
 
             batches: Vec::new(),
+
+            frame_timer,
         }
     }
 
@@ -1168,6 +1726,18 @@ impl Renderer {
         &self.queue
     }
 
+    /// Returns the pipelines that failed to be created so far.
+    #[inline]
+    pub fn pipeline_failures(&self) -> &[PipelineFailure] {
+        &self.pipeline_failures
+    }
+
+    /// Returns whether a pipeline the renderer can't work without failed to be created; if so, it
+    /// stops producing output and should be replaced with the software renderer.
+    pub fn fallback_required(&self) -> bool {
+        self.pipeline_failures.iter().any(|failure| failure.fatal)
+    }
+
     #[inline]
     pub fn resolution_scale_shift(&self) -> u8 {
         self.resolution_scale_shift
@@ -1178,12 +1748,224 @@ impl Renderer {
             return;
         }
         self.resolution_scale_shift = value;
+        self.recreate_output_attachments(self.msaa_enabled());
+        if self.texture_filtering.max_anisotropy > 1 {
+            self.clear_samplers();
+        }
+    }
+
+    #[inline]
+    pub fn msaa_enabled(&self) -> bool {
+        self.output_attachments.msaa.is_some()
+    }
+
+    pub fn set_msaa_enabled(&mut self, value: bool) {
+        let value = value && self.msaa_resolve_pipeline.is_some();
+        if value == self.msaa_enabled() {
+            return;
+        }
+        self.recreate_output_attachments(value);
+        // The polygon pipelines' sample count has to match the one of the attachments
+        self.opaque_pipelines.clear();
+        self.trans_pipelines.clear();
+        self.trans_no_depth_update_pipelines.clear();
+    }
+
+    fn recreate_output_attachments(&mut self, msaa_enabled: bool) {
         self.output_attachments = OutputAttachments::new(
             &self.device,
-            value,
-            &self.bg_layouts.color,
-            &self.bg_layouts.depth_attrs,
+            self.resolution_scale_shift,
+            msaa_enabled,
+            self.compute_rasterizer.is_some(),
+            &self.bg_layouts,
         );
+        if let Some(compute_rasterizer) = &mut self.compute_rasterizer {
+            compute_rasterizer.set_output(
+                &self.device,
+                self.resolution_scale_shift,
+                &self.output_attachments.color[0].1,
+            );
+        }
+    }
+
+    #[inline]
+    pub fn compute_rasterizer_enabled(&self) -> bool {
+        self.compute_rasterizer.is_some()
+    }
+
+    /// Sets whether to rasterize polygons in compute shaders following the DS's own rules, instead
+    /// of approximating them through the regular render pipelines. Texture filtering, replacement
+    /// and multisampling have no effect while it's enabled.
+    pub fn set_compute_rasterizer_enabled(&mut self, value: bool) {
+        if value == self.compute_rasterizer_enabled() {
+            return;
+        }
+        if value {
+            // The output attachments need to be recreated with storage usage first
+            self.output_attachments = OutputAttachments::new(
+                &self.device,
+                self.resolution_scale_shift,
+                self.msaa_enabled(),
+                true,
+                &self.bg_layouts,
+            );
+            self.compute_rasterizer = diagnostics::create_checked(
+                &self.device,
+                &mut self.pipeline_failures,
+                || "compute rasterizer".to_owned(),
+                false,
+                || {
+                    compute::Rasterizer::new(
+                        &self.device,
+                        self.resolution_scale_shift,
+                        &self.output_attachments.color[0].1,
+                    )
+                },
+            );
+            if self.compute_rasterizer.is_none() {
+                self.recreate_output_attachments(self.msaa_enabled());
+            }
+        } else {
+            self.compute_rasterizer = None;
+            self.recreate_output_attachments(self.msaa_enabled());
+        }
+    }
+
+    #[inline]
+    pub fn texture_filtering(&self) -> TextureFiltering {
+        self.texture_filtering
+    }
+
+    pub fn set_texture_filtering(&mut self, value: TextureFiltering) {
+        if value == self.texture_filtering {
+            return;
+        }
+        self.texture_filtering = value;
+        self.clear_samplers();
+        // Indexed textures' parameters depend on the filter, as they're filtered manually
+        self.textures.retain(|key, _| !key.indexed());
+        self.texture_cache_changed = true;
+    }
+
+    #[inline]
+    pub fn texture_cache_mode(&self) -> TextureCacheMode {
+        self.texture_cache_mode
+    }
+
+    pub fn set_texture_cache_mode(&mut self, value: TextureCacheMode) {
+        if value == self.texture_cache_mode {
+            return;
+        }
+        self.texture_cache_mode = value;
+        self.palette_thrash_frames = 0;
+        self.textures.clear();
+        self.texture_cache_changed = true;
+        self.palettes.clear();
+        self.texture_bgs.clear();
+    }
+
+    /// Whether paletted textures are currently being decoded into color indices, with their
+    /// palettes cached separately.
+    pub fn palettes_separated(&self) -> bool {
+        // Texture replacement looks textures up by the hash of their decoded colors
+        !self.texture_replacement.is_enabled()
+            && match self.texture_cache_mode {
+                TextureCacheMode::Combined => false,
+                TextureCacheMode::PaletteSeparated => true,
+                TextureCacheMode::Auto => self.palette_thrash_frames >= PALETTE_THRASH_FRAMES,
+            }
+    }
+
+    fn clear_samplers(&mut self) {
+        self.samplers = [const { None }; 0x10];
+        self.texture_bgs.clear();
+    }
+
+    /// Sets the directory to dump decoded textures to as PNG images, named after their hash.
+    pub fn set_texture_dump_dir(&mut self, dir: Option<PathBuf>) {
+        self.texture_replacement.set_dump_dir(dir);
+        // Make sure all textures in use get dumped
+        self.textures.clear();
+        self.texture_cache_changed = true;
+        self.texture_bgs.clear();
+    }
+
+    /// Sets the directory to load replacement PNG images for textures from, looking them up by the
+    /// hash at the end of their file name (as produced by texture dumps).
+    pub fn set_texture_pack_dir(&mut self, dir: Option<PathBuf>) {
+        self.texture_replacement.set_pack_dir(dir);
+        self.textures.clear();
+        self.texture_cache_changed = true;
+        self.texture_bgs.clear();
+    }
+
+    /// Returns the keys of the textures currently cached, in a stable order; they can be stored
+    /// alongside savestates and passed to [`Renderer::prewarm_textures`] after loading them.
+    pub fn texture_cache_keys(&self) -> Vec<u64> {
+        let mut keys = self.textures.keys().map(|key| key.0).collect::<Vec<_>>();
+        keys.sort_unstable();
+        keys
+    }
+
+    /// Returns whether textures were added to or removed from the cache since the last call.
+    pub fn take_texture_cache_changed(&mut self) -> bool {
+        mem::take(&mut self.texture_cache_changed)
+    }
+
+    /// Decodes the textures with the given keys (as returned by [`Renderer::texture_cache_keys`])
+    /// from the given texture memory contents ahead of time, i.e. right after loading a savestate,
+    /// instead of when they're first used.
+    ///
+    /// The prewarmed textures are only kept by the next rendered frame if the regions they were
+    /// decoded from still have the same contents; keys that don't match the current texture cache
+    /// mode are ignored.
+    pub fn prewarm_textures(
+        &mut self,
+        keys: &[u64],
+        texture: &Bytes<0x8_0000>,
+        tex_pal: &Bytes<0x1_8000>,
+    ) {
+        if self.fallback_required() {
+            return;
+        }
+
+        let mut vram = unsafe { Box::<PrewarmVram>::new_zeroed().assume_init() };
+        vram.texture.copy_from_slice(&texture[..]);
+        vram.tex_pal[..0x1_8000].copy_from_slice(&tex_pal[..]);
+
+        let palettes_separated = self.palettes_separated();
+        for &raw_key in keys {
+            let key = TextureKey(raw_key & TextureKey::MASK);
+            // Indexed keys don't record the palette, so they can't be converted back to combined
+            // ones
+            if key.format() == 0 || (key.indexed() && !palettes_separated) {
+                continue;
+            }
+            let key = key
+                .with_indexed(palettes_separated && key.is_paletted())
+                .data_key();
+            let mut decoded = create_texture(
+                &self.device,
+                &self.queue,
+                key,
+                &vram.texture,
+                &vram.tex_pal,
+                &mut self.texture_decode_buffer,
+                &mut self.texture_replacement,
+                self.texture_filtering.filter,
+            );
+            decoded.prewarmed = true;
+            self.textures.insert(key, decoded);
+        }
+        // Bind groups referencing replaced textures need to be recreated
+        self.texture_bgs.retain(|(texture, _), _| {
+            !self
+                .textures
+                .get(&texture.data_key())
+                .is_some_and(|texture| texture.prewarmed)
+        });
+        self.texture_cache_changed = true;
+        self.prewarm_vram = Some(vram);
     }
 
     #[inline]
@@ -1197,29 +1979,118 @@ impl Renderer {
             .create_view(&Default::default())
     }
 
+    /// Returns the texture the last frame was rendered to; as post-processing passes alternate
+    /// between two textures, this is only valid until the next call to
+    /// [`render_frame`](Self::render_frame).
+    pub fn output_texture(&self) -> &wgpu::Texture {
+        &self.output_attachments.color[self.color_output_index as usize].0
+    }
+
     pub fn render_frame(&mut self, frame: &FrameData) -> wgpu::CommandBuffer {
+        let mut command_encoder =
+            self.device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("3D renderer command encoder"),
+                });
+        if let Some(frame_timer) = &mut self.frame_timer {
+            frame_timer.begin_frame(&mut command_encoder);
+        }
+        self.encode_frame(frame, &mut command_encoder);
+        if let Some(frame_timer) = &mut self.frame_timer {
+            frame_timer.end_frame(&mut command_encoder);
+        }
+        command_encoder.finish()
+    }
+
+    /// Starts reading back the GPU timings of the frames rendered so far; must be called after
+    /// submitting the command buffers returned by [`render_frame`](Self::render_frame).
+    pub fn frames_submitted(&mut self) {
+        if let Some(frame_timer) = &mut self.frame_timer {
+            frame_timer.frames_submitted();
+        }
+    }
+
+    /// Returns the time the GPU took to render the most recent frame measured since the last call,
+    /// if any; always `None` if the device doesn't support [`FRAME_TIMING_FEATURES`].
+    pub fn gpu_frame_time(&mut self) -> Option<Duration> {
+        self.frame_timer.as_mut()?.poll(&self.device)
+    }
+
+    fn encode_frame(&mut self, frame: &FrameData, command_encoder: &mut wgpu::CommandEncoder) {
+        if self.fallback_required() {
+            return;
+        }
+
+        // Prewarmed textures were decoded from the texture memory contents right after loading a
+        // savestate, which the frame's dirty masks don't account for; instead, they're compared
+        // directly against the frame's contents
+        let prewarm_changed_regions = self
+            .prewarm_vram
+            .take()
+            .map(|vram| vram.changed_regions(frame));
+        let mut palette_invalidations = 0;
+        let prev_textures = self.textures.len();
         self.textures.retain(|_, texture| {
-            (texture.texture_region_mask & frame.rendering.texture_dirty)
-                | (texture.tex_pal_region_mask & frame.rendering.tex_pal_dirty)
-                == 0
+            let (texture_dirty, tex_pal_dirty) = match prewarm_changed_regions {
+                Some(changed_regions) if texture.prewarmed => changed_regions,
+                _ => (frame.rendering.texture_dirty, frame.rendering.tex_pal_dirty),
+            };
+            texture.prewarmed = false;
+            if texture.texture_region_mask & texture_dirty != 0 {
+                return false;
+            }
+            if texture.tex_pal_region_mask & tex_pal_dirty != 0 {
+                palette_invalidations += 1;
+                return false;
+            }
+            true
         });
+        if self.textures.len() != prev_textures {
+            self.texture_cache_changed = true;
+        }
         self.texture_bgs
-            .retain(|(texture, _), _| self.textures.contains_key(texture));
+            .retain(|(texture, _), _| self.textures.contains_key(&texture.data_key()));
+
+        // Separated palettes are updated in place, so their bind groups stay valid
+        for (&palette_key, palette) in &mut self.palettes {
+            if palette.tex_pal_region_mask & frame.rendering.tex_pal_dirty != 0 {
+                upload_palette(
+                    &self.queue,
+                    palette,
+                    palette_key,
+                    frame,
+                    &mut self.texture_decode_buffer,
+                );
+            }
+        }
+
+        if self.texture_cache_mode == TextureCacheMode::Auto
+            && self.palette_thrash_frames < PALETTE_THRASH_FRAMES
+        {
+            if palette_invalidations >= PALETTE_THRASH_TEXTURES {
+                self.palette_thrash_frames += 1;
+            } else {
+                self.palette_thrash_frames = 0;
+            }
+        }
+        let palettes_separated = self.palettes_separated();
+
+        if let Some(compute_rasterizer) = &mut self.compute_rasterizer {
+            compute_rasterizer.render_frame(&self.queue, frame, command_encoder);
+            self.color_output_index = 0;
+            return;
+        }
 
         let control_flags = ControlFlags::from(frame.rendering.control);
 
         let mut toon_used = false;
         let mut fog_used = false;
 
-        let mut command_encoder =
-            self.device
-                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                    label: Some("3D renderer command encoder"),
-                });
+        let msaa = self.output_attachments.msaa.as_ref();
 
         let mut color_attachments = vec![Some(wgpu::RenderPassColorAttachment {
-            view: &self.output_attachments.color[0].1,
-            resolve_target: None,
+            view: msaa.map_or(&self.output_attachments.color[0].1, |msaa| &msaa.color_view),
+            resolve_target: msaa.map(|_| &self.output_attachments.color[0].1),
             ops: wgpu::Operations {
                 load: wgpu::LoadOp::Clear(if frame.rendering.control.rear_plane_bitmap_enabled() {
                     wgpu::Color::BLACK
@@ -1232,11 +2103,11 @@ impl Renderer {
 
         if control_flags.attrs_enabled() {
             color_attachments.push(Some(wgpu::RenderPassColorAttachment {
-                view: &self.output_attachments.attrs_view,
+                view: msaa.map_or(&self.output_attachments.attrs_view, |msaa| &msaa.attrs_view),
                 resolve_target: None,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color {
-                        r: if control_flags.edge_marking_enabled() {
+                        r: if control_flags.poly_ids_enabled() {
                             frame.rendering.clear_poly_id as f64 / 63.0
                         } else {
                             0.0
@@ -1260,7 +2131,7 @@ impl Renderer {
             label: Some("3D renderer render pass"),
             color_attachments: &color_attachments,
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &self.output_attachments.depth_view,
+                view: msaa.map_or(&self.output_attachments.depth_view, |msaa| &msaa.depth_view),
                 depth_ops: Some(wgpu::Operations {
                     load: wgpu::LoadOp::Clear(
                         if frame.rendering.control.rear_plane_bitmap_enabled() {
@@ -1288,7 +2159,7 @@ impl Renderer {
             // TODO
         }
 
-        let polys = &frame.gx.poly_ram[..frame.gx.poly_ram_level as usize];
+        let polys = frame.gx.poly_ram();
         if !polys.is_empty() && frame.rendering.alpha_test_ref < 0x1F {
             self.vtx_buffer_contents.clear();
             self.idx_buffer_contents.clear();
@@ -1302,30 +2173,67 @@ impl Renderer {
                 self.texture_bgs
                     .entry((texture_key, sampler_key))
                     .or_insert_with(|| {
-                        let texture = self.textures.entry(texture_key).or_insert_with(|| {
+                        let data_key = texture_key.data_key();
+                        let texture = self.textures.entry(data_key).or_insert_with(|| {
+                            self.texture_cache_changed = true;
                             create_texture(
                                 &self.device,
                                 &self.queue,
-                                texture_key,
-                                frame,
+                                data_key,
+                                &frame.rendering.texture,
+                                &frame.rendering.tex_pal,
                                 &mut self.texture_decode_buffer,
+                                &mut self.texture_replacement,
+                                self.texture_filtering.filter,
                             )
                         });
-                        let sampler = self.samplers[sampler_key.0 as usize]
-                            .get_or_insert_with(|| create_sampler(&self.device, sampler_key));
+                        let sampler =
+                            self.samplers[sampler_key.0 as usize].get_or_insert_with(|| {
+                                create_sampler(
+                                    &self.device,
+                                    sampler_key,
+                                    self.texture_filtering,
+                                    self.resolution_scale_shift,
+                                )
+                            });
+                        let mut entries = vec![
+                            wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: wgpu::BindingResource::TextureView(&texture.view),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 1,
+                                resource: wgpu::BindingResource::Sampler(sampler),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 2,
+                                resource: texture.params.as_entire_binding(),
+                            },
+                        ];
+                        if texture_key.indexed() {
+                            let palette_key = texture_key.palette_key();
+                            let palette = self.palettes.entry(palette_key).or_insert_with(|| {
+                                create_palette(
+                                    &self.device,
+                                    &self.queue,
+                                    palette_key,
+                                    frame,
+                                    &mut self.texture_decode_buffer,
+                                )
+                            });
+                            entries.push(wgpu::BindGroupEntry {
+                                binding: 3,
+                                resource: wgpu::BindingResource::TextureView(&palette.view),
+                            });
+                        }
                         self.device.create_bind_group(&wgpu::BindGroupDescriptor {
                             label: Some("3D renderer texture bind group"),
-                            layout: &self.bg_layouts.texture,
-                            entries: &[
-                                wgpu::BindGroupEntry {
-                                    binding: 0,
-                                    resource: wgpu::BindingResource::TextureView(&texture.view),
-                                },
-                                wgpu::BindGroupEntry {
-                                    binding: 1,
-                                    resource: wgpu::BindingResource::Sampler(sampler),
-                                },
-                            ],
+                            layout: if texture_key.indexed() {
+                                &self.bg_layouts.indexed_texture
+                            } else {
+                                &self.bg_layouts.texture
+                            },
+                            entries: &entries,
                         })
                     });
             };
@@ -1386,14 +2294,23 @@ impl Renderer {
                             fog_enabled, cur_fog_enabled, fog_enabled_changed
                         );
 
-                        if pipeline_changed {
-                            self.opaque_pipelines.entry(pipeline).or_insert_with(|| {
-                                render::opaque::create_pipeline(
-                                    pipeline,
-                                    &self.device,
-                                    &self.bg_layouts,
-                                )
-                            });
+                        if pipeline_changed && !self.opaque_pipelines.contains_key(&pipeline) {
+                            if let Some(new_pipeline) = diagnostics::create_checked(
+                                &self.device,
+                                &mut self.pipeline_failures,
+                                || format!("opaque {pipeline:?}"),
+                                true,
+                                || {
+                                    render::opaque::create_pipeline(
+                                        pipeline,
+                                        self.output_attachments.sample_count(),
+                                        &self.device,
+                                        &self.bg_layouts,
+                                    )
+                                },
+                            ) {
+                                self.opaque_pipelines.insert(pipeline, new_pipeline);
+                            }
                         }
 
                         if texture_changed || pipeline_changed {
@@ -1440,15 +2357,24 @@ impl Renderer {
                             fog_enabled, cur_fog_enabled, fog_enabled_changed
                         );
 
-                        if pipeline_changed {
-                            self.trans_pipelines.entry(pipeline).or_insert_with(|| {
-                                render::trans::create_pipeline(
-                                    pipeline,
-                                    true,
-                                    &self.device,
-                                    &self.bg_layouts,
-                                )
-                            });
+                        if pipeline_changed && !self.trans_pipelines.contains_key(&pipeline) {
+                            if let Some(new_pipelines) = diagnostics::create_checked(
+                                &self.device,
+                                &mut self.pipeline_failures,
+                                || format!("translucent {pipeline:?}"),
+                                true,
+                                || {
+                                    render::trans::create_pipeline(
+                                        pipeline,
+                                        true,
+                                        self.output_attachments.sample_count(),
+                                        &self.device,
+                                        &self.bg_layouts,
+                                    )
+                                },
+                            ) {
+                                self.trans_pipelines.insert(pipeline, new_pipelines);
+                            }
                         }
 
                         if texture_changed || pipeline_changed {
@@ -1495,17 +2421,27 @@ impl Renderer {
                             fog_enabled, cur_fog_enabled, fog_enabled_changed
                         );
 
-                        if pipeline_changed {
-                            self.trans_no_depth_update_pipelines
-                                .entry(pipeline)
-                                .or_insert_with(|| {
+                        if pipeline_changed
+                            && !self.trans_no_depth_update_pipelines.contains_key(&pipeline)
+                        {
+                            if let Some(new_pipelines) = diagnostics::create_checked(
+                                &self.device,
+                                &mut self.pipeline_failures,
+                                || format!("translucent (no depth update) {pipeline:?}"),
+                                true,
+                                || {
                                     render::trans::create_pipeline(
                                         pipeline,
                                         false,
+                                        self.output_attachments.sample_count(),
                                         &self.device,
                                         &self.bg_layouts,
                                     )
-                                });
+                                },
+                            ) {
+                                self.trans_no_depth_update_pipelines
+                                    .insert(pipeline, new_pipelines);
+                            }
                         }
 
                         if texture_changed || pipeline_changed {
@@ -1600,6 +2536,7 @@ impl Renderer {
                     control_flags,
                     frame.gx.w_buffering,
                     frame.rendering.alpha_test_ref,
+                    palettes_separated,
                     poly,
                 );
                 if match cur_batch {
@@ -1632,7 +2569,7 @@ impl Renderer {
                         .enumerate()
                         .map(|(i, vert_addr)| {
                             Vertex::new(
-                                &frame.gx.vert_ram[vert_addr.get() as usize],
+                                &frame.gx.vert_ram()[vert_addr.get() as usize],
                                 // self.hi_res_coords_mask,
                                 poly.depth_values[i],
                                 poly.w_values[i],
@@ -1648,6 +2585,12 @@ impl Renderer {
             }
             finish_batch!();
 
+            // Some of the batches' pipelines are missing, so nothing can be drawn
+            if self.fallback_required() {
+                drop(render_pass);
+                return;
+            }
+
             fog_used &= control_flags.fog_enabled();
 
             unsafe {
@@ -1891,9 +2834,44 @@ impl Renderer {
 
         drop(render_pass);
 
+        if let Some(msaa) = msaa.filter(|_| control_flags.attrs_enabled()) {
+            let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("3D renderer MSAA resolve render pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.output_attachments.attrs_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.output_attachments.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_bind_group(0, &msaa.depth_attrs_bg, &[]);
+            // MSAA can only be enabled if the resolve pipeline was created successfully
+            render_pass.set_pipeline(self.msaa_resolve_pipeline.as_ref().unwrap());
+            render_pass.draw(0..4, 0..1);
+        }
+
         self.color_output_index = 0;
 
-        if control_flags.edge_marking_enabled() {
+        if let Some(edge_marking_pipelines) = self
+            .edge_marking_pipelines
+            .as_ref()
+            .filter(|_| control_flags.edge_marking_enabled())
+        {
             if frame.rendering.edge_colors != self.edge_colors {
                 self.edge_colors = frame.rendering.edge_colors;
                 let mut edge_colors =
@@ -1926,12 +2904,12 @@ impl Renderer {
             render_pass.set_bind_group(0, &self.edge_colors_bg, &[]);
             render_pass.set_bind_group(1, &self.output_attachments.depth_attrs_bg, &[]);
             render_pass.set_pipeline(
-                &self.edge_marking_pipelines[control_flags.antialiasing_enabled() as usize],
+                &edge_marking_pipelines[control_flags.antialiasing_enabled() as usize],
             );
             render_pass.draw(0..4, 0..1);
         }
 
-        if fog_used {
+        if let Some(fog_pipelines) = self.fog_pipelines.as_ref().filter(|_| fog_used) {
             if frame.rendering.fog_data != self.fog_data {
                 self.fog_data.clone_from(&frame.rendering.fog_data);
                 let mut fog_data =
@@ -1978,12 +2956,38 @@ impl Renderer {
             render_pass.set_bind_group(0, &self.fog_data_bg, &[]);
             render_pass.set_bind_group(1, &input_color.2, &[]);
             render_pass.set_bind_group(2, &self.output_attachments.depth_attrs_bg, &[]);
-            render_pass.set_pipeline(
-                &self.fog_pipelines[frame.rendering.control.fog_only_alpha() as usize],
-            );
+            render_pass
+                .set_pipeline(&fog_pipelines[frame.rendering.control.fog_only_alpha() as usize]);
             render_pass.draw(0..4, 0..1);
         }
 
-        command_encoder.finish()
+        if let Some(antialiasing_pipeline) = self
+            .antialiasing_pipeline
+            .as_ref()
+            .filter(|_| control_flags.antialiasing_enabled())
+        {
+            let input_color = &self.output_attachments.color[self.color_output_index as usize];
+            self.color_output_index ^= 1;
+            let output_color = &self.output_attachments.color[self.color_output_index as usize];
+
+            let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("3D renderer antialiasing render pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &output_color.1,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_bind_group(0, &input_color.2, &[]);
+            render_pass.set_bind_group(1, &self.output_attachments.depth_attrs_bg, &[]);
+            render_pass.set_pipeline(antialiasing_pipeline);
+            render_pass.draw(0..4, 0..1);
+        }
     }
 }
@@ -22,7 +22,9 @@ pub mod fog;
 pub use fog::FogCode;
 pub mod edge_marking;
 pub use edge_marking::EdgeMarkingCode;
+pub mod msaa_resolve;
 
+pub mod antialiasing;
 pub mod opaque;
 pub mod trans;
 
@@ -0,0 +1,144 @@
+use crate::BgLayouts;
+
+// The DS blends the edge pixels of opaque polygons with the pixels below them based on how much
+// of the pixel the polygon covers; as only the topmost layer is available here, that's
+// approximated by blending edge pixels with their neighbors that belong to farther polygons.
+const SHADER_MODULE_SRC: &str = "
+@group(0) @binding(0) var color_texture: texture_2d<f32>;
+@group(1) @binding(0) var depth_texture: texture_depth_2d;
+@group(1) @binding(1) var attrs_texture: texture_2d<f32>;
+
+struct VertOutput {
+    @builtin(position) pos: vec4<f32>,
+}
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertOutput {
+    var vert_positions: array<vec2<f32>, 4> = array<vec2<f32>, 4>(
+        vec2(-1.0, 1.0),
+        vec2(1.0, 1.0),
+        vec2(-1.0, -1.0),
+        vec2(1.0, -1.0),
+    );
+
+    var output: VertOutput;
+    output.pos = vec4<f32>((*(&vert_positions))[vertex_index], 0.0, 1.0);
+    return output;
+}
+
+struct Neighbors {
+    rgb_sum: vec3<f32>,
+    rgb_count: f32,
+    alpha_sum: f32,
+    count: f32,
+}
+
+fn addNeighbor(
+    neighbors: ptr<function, Neighbors>,
+    coords: vec2<i32>,
+    depth: f32,
+    id: f32,
+) {
+    if (any(coords < vec2(0i)) || any(coords >= vec2<i32>(textureDimensions(color_texture)))) {
+        return;
+    }
+    if (textureLoad(attrs_texture, coords, 0).r == id ||
+        depth >= textureLoad(depth_texture, coords, 0)) {
+        return;
+    }
+    var color = textureLoad(color_texture, coords, 0);
+    (*neighbors).alpha_sum += color.a;
+    (*neighbors).count += 1.0;
+    if (color.a > 0.0) {
+        (*neighbors).rgb_sum += color.rgb;
+        (*neighbors).rgb_count += 1.0;
+    }
+}
+
+@fragment
+fn fs_main(
+    @builtin(position) position: vec4<f32>,
+) -> @location(0) vec4<f32> {
+    var coords = vec2<i32>(position.xy);
+    var color = textureLoad(color_texture, coords, 0);
+    var depth = textureLoad(depth_texture, coords, 0);
+    var id = textureLoad(attrs_texture, coords, 0).r;
+
+    var neighbors = Neighbors(vec3(0.0), 0.0, 0.0, 0.0);
+    addNeighbor(&neighbors, coords + vec2<i32>( 0i, -1i), depth, id);
+    addNeighbor(&neighbors, coords + vec2<i32>( 0i,  1i), depth, id);
+    addNeighbor(&neighbors, coords + vec2<i32>(-1i,  0i), depth, id);
+    addNeighbor(&neighbors, coords + vec2<i32>( 1i,  0i), depth, id);
+    if (neighbors.count == 0.0) {
+        return color;
+    }
+
+    // Pixels on a straight edge are covered by about half on average, corners by less
+    var weight = min(neighbors.count, 2.0) * 0.25;
+    if (neighbors.rgb_count > 0.0) {
+        color = vec4(
+            mix(color.rgb, neighbors.rgb_sum / neighbors.rgb_count, weight),
+            color.a,
+        );
+    }
+    color.a = mix(color.a, neighbors.alpha_sum / neighbors.count, weight);
+    return color;
+}";
+
+pub(crate) fn create_pipeline(
+    device: &wgpu::Device,
+    bg_layouts: &BgLayouts,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("3D renderer antialiasing pipeline layout"),
+        bind_group_layouts: &[&bg_layouts.color, &bg_layouts.depth_attrs],
+        push_constant_ranges: &[],
+    });
+
+    let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("3D renderer antialiasing shader module"),
+        source: wgpu::ShaderSource::Wgsl(SHADER_MODULE_SRC.into()),
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("3D renderer antialiasing pipeline"),
+        layout: Some(&layout),
+
+        vertex: wgpu::VertexState {
+            module: &shader_module,
+            entry_point: None,
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleStrip,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+
+        depth_stencil: None,
+
+        multisample: wgpu::MultisampleState::default(),
+
+        fragment: Some(wgpu::FragmentState {
+            module: &shader_module,
+            entry_point: None,
+            targets: &[Some(wgpu::ColorTargetState {
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+
+        multiview: None,
+        cache: None,
+    })
+}
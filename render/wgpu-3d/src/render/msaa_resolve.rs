@@ -0,0 +1,105 @@
+use crate::BgLayouts;
+
+// Depth and attributes can't be averaged (polygon IDs would get mixed up at edges), so the first
+// sample is copied to the single-sampled textures that edge marking and fog read from.
+const SHADER_MODULE_SRC: &str = "
+@group(0) @binding(0) var depth_texture: texture_depth_multisampled_2d;
+@group(0) @binding(1) var attrs_texture: texture_multisampled_2d<f32>;
+
+struct VertOutput {
+    @builtin(position) pos: vec4<f32>,
+}
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertOutput {
+    var vert_positions: array<vec2<f32>, 4> = array<vec2<f32>, 4>(
+        vec2(-1.0, 1.0),
+        vec2(1.0, 1.0),
+        vec2(-1.0, -1.0),
+        vec2(1.0, -1.0),
+    );
+
+    var output: VertOutput;
+    output.pos = vec4<f32>((*(&vert_positions))[vertex_index], 0.0, 1.0);
+    return output;
+}
+
+struct FragOutput {
+    @builtin(frag_depth) depth: f32,
+    @location(0) attrs: vec4<f32>,
+}
+
+@fragment
+fn fs_main(
+    @builtin(position) position: vec4<f32>,
+) -> FragOutput {
+    var coords = vec2<i32>(position.xy);
+    var output: FragOutput;
+    output.depth = textureLoad(depth_texture, coords, 0);
+    output.attrs = textureLoad(attrs_texture, coords, 0);
+    return output;
+}";
+
+pub(crate) fn create_pipeline(
+    device: &wgpu::Device,
+    bg_layouts: &BgLayouts,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("3D renderer MSAA resolve pipeline layout"),
+        bind_group_layouts: &[&bg_layouts.msaa_depth_attrs],
+        push_constant_ranges: &[],
+    });
+
+    let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("3D renderer MSAA resolve shader module"),
+        source: wgpu::ShaderSource::Wgsl(SHADER_MODULE_SRC.into()),
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("3D renderer MSAA resolve pipeline"),
+        layout: Some(&layout),
+
+        vertex: wgpu::VertexState {
+            module: &shader_module,
+            entry_point: None,
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleStrip,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth24PlusStencil8,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Always,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+
+        multisample: wgpu::MultisampleState::default(),
+
+        fragment: Some(wgpu::FragmentState {
+            module: &shader_module,
+            entry_point: None,
+            targets: &[Some(wgpu::ColorTargetState {
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+
+        multiview: None,
+        cache: None,
+    })
+}
@@ -37,7 +37,7 @@ fn shader_module_src(
         texture_get_color,
     } = ifdef!(
         pipeline.texture_mapping_enabled(),
-        TextureCode::new(texture_bg_index)
+        TextureCode::new(texture_bg_index, pipeline.indexed_texture())
     );
 
     let ToonCode {
@@ -121,6 +121,7 @@ fn fs_main(
 
 pub(crate) fn create_pipeline(
     pipeline: PipelineKey,
+    sample_count: u32,
     device: &wgpu::Device,
     bg_layouts: &BgLayouts,
 ) -> wgpu::RenderPipeline {
@@ -138,7 +139,11 @@ pub(crate) fn create_pipeline(
 
     let texture_bg_index = bg_layouts_.len() as u32;
     if pipeline.texture_mapping_enabled() {
-        bg_layouts_.push(&bg_layouts.texture);
+        bg_layouts_.push(if pipeline.indexed_texture() {
+            &bg_layouts.indexed_texture
+        } else {
+            &bg_layouts.texture
+        });
     }
 
     let toon_bg_index = bg_layouts_.len() as u32;
@@ -207,7 +212,10 @@ pub(crate) fn create_pipeline(
             bias: wgpu::DepthBiasState::default(),
         }),
 
-        multisample: wgpu::MultisampleState::default(),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
 
         fragment: Some(wgpu::FragmentState {
             module: &shader_module,
@@ -11,11 +11,19 @@ pub struct TextureCode {
 }
 
 impl TextureCode {
-    pub fn new(bg_index: u32) -> Self {
+    pub fn new(bg_index: u32, indexed: bool) -> Self {
+        if indexed {
+            return Self::new_indexed(bg_index);
+        }
         TextureCode {
             texture_uniforms: format!(
-                "@group({bg_index}) @binding(0) var t_texture: texture_2d<f32>;
-                @group({bg_index}) @binding(1) var s_texture: sampler;",
+                "struct TextureParams {{
+                    size: vec2<f32>,
+                    color_scale: vec2<f32>,
+                }}
+                @group({bg_index}) @binding(0) var t_texture: texture_2d<f32>;
+                @group({bg_index}) @binding(1) var s_texture: sampler;
+                @group({bg_index}) @binding(2) var<uniform> t_params: TextureParams;",
             ),
 
             texture_vert_inputs: "@location(3) uv: vec2<i32>,",
@@ -24,8 +32,58 @@ impl TextureCode {
 
             texture_frag_inputs: "@location(1) uv: vec2<f32>,",
             texture_get_color: "let t_color = textureSample(t_texture, s_texture, uv / \
-                                vec2<f32>(textureDimensions(t_texture))) * \
-                                vec4<f32>(vec3<f32>(255.0 / 63.0), 255.0 / 31.0);",
+                                t_params.size) * vec4<f32>(vec3<f32>(t_params.color_scale.x), \
+                                t_params.color_scale.y);",
+        }
+    }
+
+    // Indexed textures contain color indices in their red channel and alpha values in their
+    // green one; the 4 texels around the sample position are gathered, looked up in the palette
+    // and then blended (either bilinearly or by picking the nearest one).
+    fn new_indexed(bg_index: u32) -> Self {
+        TextureCode {
+            texture_uniforms: format!(
+                "struct TextureParams {{
+                    size: vec2<f32>,
+                    color_scale: vec2<f32>,
+                    bilinear: f32,
+                }}
+                @group({bg_index}) @binding(0) var t_texture: texture_2d<f32>;
+                @group({bg_index}) @binding(1) var s_texture: sampler;
+                @group({bg_index}) @binding(2) var<uniform> t_params: TextureParams;
+                @group({bg_index}) @binding(3) var t_palette: texture_2d<f32>;
+
+                fn sampleIndexed(uv: vec2<f32>) -> vec4<f32> {{
+                    let indices = textureGather(0, t_texture, s_texture, uv / t_params.size);
+                    let alphas = textureGather(1, t_texture, s_texture, uv / t_params.size);
+                    var frac = fract(uv - vec2(0.5));
+                    if t_params.bilinear == 0.0 {{
+                        frac = step(vec2(0.5), frac);
+                    }}
+                    let weights = vec4(
+                        (1.0 - frac.x) * frac.y,
+                        frac.x * frac.y,
+                        frac.x * (1.0 - frac.y),
+                        (1.0 - frac.x) * (1.0 - frac.y),
+                    );
+                    var color = vec4(0.0);
+                    for (var i = 0; i < 4; i++) {{
+                        let index = i32(round(indices[i] * 255.0));
+                        let rgb = textureLoad(t_palette, vec2(index, 0), 0).rgb;
+                        color += vec4(rgb, alphas[i]) * weights[i];
+                    }}
+                    return color;
+                }}",
+            ),
+
+            texture_vert_inputs: "@location(3) uv: vec2<i32>,",
+            texture_vert_outputs: "@location(1) uv: vec2<f32>,",
+            texture_set_vert_outputs: "output.uv = vec2<f32>(uv) * vec2<f32>(1.0 / 16.0);",
+
+            texture_frag_inputs: "@location(1) uv: vec2<f32>,",
+            texture_get_color: "let t_color = sampleIndexed(uv) * \
+                                vec4<f32>(vec3<f32>(t_params.color_scale.x), \
+                                t_params.color_scale.y);",
         }
     }
 }
@@ -0,0 +1,92 @@
+use crate::{compute, MSAA_SAMPLE_COUNT};
+use core::fmt;
+
+// Opaque polygons use up to 4 bind groups, translucent ones add the alpha/reference one
+const REQUIRED_BIND_GROUPS: u32 = 5;
+// The largest DS textures are 1024x1024
+const REQUIRED_TEXTURE_DIMENSION: u32 = 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UnsupportedError {
+    TooFewBindGroups { available: u32 },
+    TextureDimensionTooSmall { available: u32 },
+    DepthStencilFormatUnsupported,
+}
+
+impl fmt::Display for UnsupportedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnsupportedError::TooFewBindGroups { available } => write!(
+                f,
+                "the device supports {available} bind groups, but {REQUIRED_BIND_GROUPS} are \
+                 required",
+            ),
+            UnsupportedError::TextureDimensionTooSmall { available } => write!(
+                f,
+                "the device's maximum texture size is {available}, but \
+                 {REQUIRED_TEXTURE_DIMENSION} is required",
+            ),
+            UnsupportedError::DepthStencilFormatUnsupported => f.write_str(
+                "the device can't render to or sample from Depth24PlusStencil8 textures",
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DeviceSupport {
+    /// The highest resolution scale shift whose output textures fit within the device's maximum
+    /// texture size.
+    pub max_resolution_scale_shift: u8,
+    pub msaa: bool,
+    /// Whether the compute shader rasterizer can be used at resolution scales up to
+    /// `max_resolution_scale_shift`.
+    pub compute_rasterizer: bool,
+}
+
+/// Checks the device's limits and texture format support against the renderer's requirements,
+/// returning which of its optional settings can be used on it.
+pub fn check_support(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+) -> Result<DeviceSupport, UnsupportedError> {
+    let limits = device.limits();
+
+    if limits.max_bind_groups < REQUIRED_BIND_GROUPS {
+        return Err(UnsupportedError::TooFewBindGroups {
+            available: limits.max_bind_groups,
+        });
+    }
+
+    if limits.max_texture_dimension_2d < REQUIRED_TEXTURE_DIMENSION {
+        return Err(UnsupportedError::TextureDimensionTooSmall {
+            available: limits.max_texture_dimension_2d,
+        });
+    }
+
+    let depth_features =
+        adapter.get_texture_format_features(wgpu::TextureFormat::Depth24PlusStencil8);
+    if !depth_features
+        .allowed_usages
+        .contains(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+    {
+        return Err(UnsupportedError::DepthStencilFormatUnsupported);
+    }
+
+    let color_features = adapter.get_texture_format_features(wgpu::TextureFormat::Rgba8Unorm);
+    let max_resolution_scale_shift = (limits.max_texture_dimension_2d / 256).ilog2() as u8;
+
+    Ok(DeviceSupport {
+        max_resolution_scale_shift,
+        msaa: depth_features
+            .flags
+            .sample_count_supported(MSAA_SAMPLE_COUNT)
+            && color_features
+                .flags
+                .sample_count_supported(MSAA_SAMPLE_COUNT),
+        compute_rasterizer: color_features
+            .allowed_usages
+            .contains(wgpu::TextureUsages::STORAGE_BINDING)
+            && compute::is_supported(adapter, &limits, max_resolution_scale_shift),
+    })
+}
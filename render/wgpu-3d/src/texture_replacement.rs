@@ -0,0 +1,173 @@
+use ahash::{AHashMap as HashMap, AHashSet as HashSet};
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Computes the hash identifying a decoded texture in dumps and replacement packs, using 64-bit
+/// FNV-1a (which is stable across builds and platforms). Decoded colors include the palette, so
+/// the same texel data used with different palettes gets different hashes.
+pub fn hash(width: u32, height: u32, decoded: &[u32]) -> u64 {
+    let mut hash = 0xCBF2_9CE4_8422_2325_u64;
+    for value in [width, height].iter().chain(decoded) {
+        for byte in value.to_le_bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
+        }
+    }
+    hash
+}
+
+fn file_name(width: u32, height: u32, hash: u64) -> String {
+    format!("{width}x{height}_{hash:016x}.png")
+}
+
+/// Parses the hash at the end of a texture file's stem, so that pack authors can freely add a
+/// prefix to file names or organize them in subdirectories.
+fn parse_file_name(path: &Path) -> Option<u64> {
+    if !path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+    {
+        return None;
+    }
+    let stem = path.file_stem()?.to_str()?;
+    let hash = stem.get(stem.len().checked_sub(16)?..)?;
+    u64::from_str_radix(hash, 16).ok()
+}
+
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    /// 8-bit RGBA pixels, stored in row-major order.
+    pub data: Vec<u8>,
+}
+
+fn load_image(path: &Path) -> Result<Image, png::DecodingError> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    let pixels = &buffer[..info.buffer_size()];
+    let data = match info.color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 0xFF])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks_exact(2)
+            .flat_map(|la| [la[0], la[0], la[0], la[1]])
+            .collect(),
+        _ => pixels.iter().flat_map(|&l| [l, l, l, 0xFF]).collect(),
+    };
+    Ok(Image {
+        width: info.width,
+        height: info.height,
+        data,
+    })
+}
+
+fn save_image(path: &Path, width: u32, height: u32, decoded: &[u32]) -> io::Result<()> {
+    // Decoded textures contain 6-bit color components and 5-bit alpha
+    let data = decoded
+        .iter()
+        .flat_map(|&color| {
+            let [r, g, b, a] = color.to_le_bytes();
+            [
+                (r as u16 * 255 / 63) as u8,
+                (g as u16 * 255 / 63) as u8,
+                (b as u16 * 255 / 63) as u8,
+                (a as u16 * 255 / 31) as u8,
+            ]
+        })
+        .collect::<Vec<_>>();
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(())
+}
+
+fn index_pack(dir: &Path, index: &mut HashMap<u64, PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            index_pack(&path, index);
+        } else if let Some(hash) = parse_file_name(&path) {
+            index.insert(hash, path);
+        }
+    }
+}
+
+/// Dumps decoded textures as PNG images and/or replaces them with images loaded from a pack
+/// directory, identifying them by the hash of their decoded contents.
+#[derive(Default)]
+pub struct TextureReplacement {
+    dump_dir: Option<PathBuf>,
+    dumped: HashSet<u64>,
+    pack_index: HashMap<u64, PathBuf>,
+    // Failed loads are cached as well, to avoid retrying them every time the texture is decoded
+    loaded: HashMap<u64, Option<Arc<Image>>>,
+}
+
+impl TextureReplacement {
+    pub fn is_enabled(&self) -> bool {
+        self.dump_dir.is_some() || !self.pack_index.is_empty()
+    }
+
+    pub fn set_dump_dir(&mut self, dir: Option<PathBuf>) {
+        if dir == self.dump_dir {
+            return;
+        }
+        self.dumped.clear();
+        if let Some(dir) = &dir {
+            let _ = fs::create_dir_all(dir);
+            let mut index = HashMap::default();
+            index_pack(dir, &mut index);
+            self.dumped.extend(index.into_keys());
+        }
+        self.dump_dir = dir;
+    }
+
+    /// Sets the directory to load replacements from, rescanning it even if it didn't change.
+    pub fn set_pack_dir(&mut self, dir: Option<PathBuf>) {
+        self.pack_index.clear();
+        self.loaded.clear();
+        if let Some(dir) = &dir {
+            index_pack(dir, &mut self.pack_index);
+        }
+    }
+
+    /// Dumps the given decoded texture if needed.
+    pub fn dump(&mut self, width: u32, height: u32, hash: u64, decoded: &[u32]) {
+        let Some(dir) = &self.dump_dir else {
+            return;
+        };
+        if self.dumped.insert(hash) {
+            let _ = save_image(
+                &dir.join(file_name(width, height, hash)),
+                width,
+                height,
+                decoded,
+            );
+        }
+    }
+
+    /// Returns the replacement image for the texture with the given hash, if any.
+    pub fn replacement(&mut self, hash: u64) -> Option<Arc<Image>> {
+        let path = self.pack_index.get(&hash)?;
+        self.loaded
+            .entry(hash)
+            .or_insert_with(|| load_image(path).ok().map(Arc::new))
+            .clone()
+    }
+}
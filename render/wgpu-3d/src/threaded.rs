@@ -1,30 +1,54 @@
-use crate::{GxData, Renderer};
+use crate::{GxData, PipelineFailure, Renderer, TextureCacheMode, TextureFiltering};
 use dust_core::{
     gpu::{
         engine_3d::{
-            AccelRendererRx, Polygon, RendererTx, RenderingState as CoreRenderingState,
-            ScreenVertex,
+            AccelRendererRx, GxSnapshot, RendererTx, RenderingState as CoreRenderingState,
         },
         Scanline, SCREEN_HEIGHT,
     },
-    utils::Bytes,
+    profiling::TimeCounter,
+    utils::{zeroed_box, Bytes},
 };
 use dust_soft_3d as soft;
 use emu_utils::triple_buffer;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use std::{
     cell::UnsafeCell,
     hint, mem,
+    path::PathBuf,
     sync::{
         atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering},
-        Arc,
+        Arc, OnceLock,
     },
     thread,
+    time::Duration,
 };
 
+struct TexturePrewarm {
+    keys: Vec<u64>,
+    texture: Box<Bytes<0x8_0000>>,
+    tex_pal: Box<Bytes<0x1_8000>>,
+}
+
 struct SharedData {
     stopped: AtomicBool,
+    rendering_thread: OnceLock<thread::Thread>,
     resolution_scale_shift: AtomicU8,
+    msaa_enabled: AtomicBool,
+    compute_rasterizer_enabled: AtomicBool,
+    texture_filtering: Mutex<Option<TextureFiltering>>,
+    texture_cache_mode: Mutex<Option<TextureCacheMode>>,
+    texture_dump_dir: Mutex<Option<Option<PathBuf>>>,
+    texture_pack_dir: Mutex<Option<Option<PathBuf>>>,
+    pipeline_failures: Mutex<Vec<PipelineFailure>>,
+    /// The GPU time taken by the last measured frame in nanoseconds, or 0 if none was measured
+    /// since the frontend last read it.
+    gpu_frame_time_ns: AtomicU64,
+    /// The keys of the textures cached as of the last rendered frame.
+    texture_cache_keys: Mutex<Vec<u64>>,
+    texture_prewarm: Mutex<Option<TexturePrewarm>>,
+    /// Measures the CPU time the rendering thread spends preparing and submitting frames.
+    time_counter: TimeCounter,
 
     capture_rendering_data: Box<UnsafeCell<soft::RenderingData>>,
     capture_scanline_buffer: Box<UnsafeCell<[Scanline<u32>; SCREEN_HEIGHT]>>,
@@ -87,17 +111,11 @@ impl RendererTx for Tx {
         }
     }
 
-    fn swap_buffers(
-        &mut self,
-        vert_ram: &[ScreenVertex],
-        poly_ram: &[Polygon],
-        state: &CoreRenderingState,
-    ) {
+    fn swap_buffers(&mut self, gx: &Arc<GxSnapshot>, state: &CoreRenderingState) {
         self.wait_for_capture_frame_end();
-        unsafe { &mut *self.shared_data.capture_rendering_data.get() }
-            .prepare(vert_ram, poly_ram, state);
+        unsafe { &mut *self.shared_data.capture_rendering_data.get() }.prepare(gx, state);
 
-        self.last_gx_data.prepare(vert_ram, poly_ram, state);
+        self.last_gx_data.prepare(gx, state);
         let frame = self.frame_tx.current();
         frame.rendering_data.gx.copy_from(&self.last_gx_data);
         frame.rendering_data.rendering.prepare(state);
@@ -209,6 +227,110 @@ impl FrontendChannels {
             .resolution_scale_shift
             .store(value, Ordering::Relaxed);
     }
+
+    pub fn set_msaa_enabled(&self, value: bool) {
+        self.shared_data
+            .msaa_enabled
+            .store(value, Ordering::Relaxed);
+    }
+
+    pub fn set_compute_rasterizer_enabled(&self, value: bool) {
+        self.shared_data
+            .compute_rasterizer_enabled
+            .store(value, Ordering::Relaxed);
+    }
+
+    pub fn set_texture_filtering(&self, value: TextureFiltering) {
+        *self.shared_data.texture_filtering.lock() = Some(value);
+    }
+
+    pub fn set_texture_cache_mode(&self, value: TextureCacheMode) {
+        *self.shared_data.texture_cache_mode.lock() = Some(value);
+    }
+
+    pub fn set_texture_dump_dir(&self, value: Option<PathBuf>) {
+        *self.shared_data.texture_dump_dir.lock() = Some(value);
+    }
+
+    pub fn set_texture_pack_dir(&self, value: Option<PathBuf>) {
+        *self.shared_data.texture_pack_dir.lock() = Some(value);
+    }
+
+    /// Returns the pipelines the renderer failed to create so far.
+    pub fn pipeline_failures(&self) -> Vec<PipelineFailure> {
+        self.shared_data.pipeline_failures.lock().clone()
+    }
+
+    /// Returns whether the renderer stopped producing output because a pipeline it can't work
+    /// without failed to be created, and should be replaced with the software renderer.
+    pub fn fallback_required(&self) -> bool {
+        self.shared_data
+            .pipeline_failures
+            .lock()
+            .iter()
+            .any(|failure| failure.fatal)
+    }
+
+    /// Returns the GPU time taken by the most recent frame measured since the last call, if any;
+    /// frames are only measured on devices supporting
+    /// [`FRAME_TIMING_FEATURES`](crate::FRAME_TIMING_FEATURES).
+    pub fn take_gpu_frame_time(&self) -> Option<Duration> {
+        match self
+            .shared_data
+            .gpu_frame_time_ns
+            .swap(0, Ordering::Relaxed)
+        {
+            0 => None,
+            nanos => Some(Duration::from_nanos(nanos)),
+        }
+    }
+
+    pub fn texture_cache(&self) -> TextureCache {
+        TextureCache {
+            shared_data: Arc::clone(&self.shared_data),
+        }
+    }
+
+    /// Returns a counter measuring the CPU time the rendering thread spends preparing and
+    /// submitting frames; GPU time is reported separately by
+    /// [`take_gpu_frame_time`](Self::take_gpu_frame_time).
+    pub fn time_counter(&self) -> TimeCounter {
+        self.shared_data.time_counter.clone()
+    }
+}
+
+/// A handle to the renderer's texture cache that can be moved to the emulation thread, to store the
+/// cache's contents alongside savestates and restore them after loading one.
+#[derive(Clone)]
+pub struct TextureCache {
+    shared_data: Arc<SharedData>,
+}
+
+impl TextureCache {
+    /// Returns the keys of the textures cached as of the last rendered frame.
+    pub fn keys(&self) -> Vec<u64> {
+        self.shared_data.texture_cache_keys.lock().clone()
+    }
+
+    /// Makes the rendering thread decode the textures with the given keys from the given texture
+    /// memory contents while it's idle, so that they're already cached by the time the next frame
+    /// gets rendered; see [`Renderer::prewarm_textures`].
+    pub fn prewarm(&self, keys: Vec<u64>, texture: &Bytes<0x8_0000>, tex_pal: &Bytes<0x1_8000>) {
+        if keys.is_empty() {
+            return;
+        }
+        let mut prewarm = TexturePrewarm {
+            keys,
+            texture: zeroed_box(),
+            tex_pal: zeroed_box(),
+        };
+        prewarm.texture.copy_from_slice(&texture[..]);
+        prewarm.tex_pal.copy_from_slice(&tex_pal[..]);
+        *self.shared_data.texture_prewarm.lock() = Some(prewarm);
+        if let Some(thread) = self.shared_data.rendering_thread.get() {
+            thread.unpark();
+        }
+    }
 }
 
 pub struct Rx2dData {
@@ -221,11 +343,25 @@ pub fn init(
     device: Arc<wgpu::Device>,
     queue: Arc<wgpu::Queue>,
     resolution_scale_shift: u8,
+    texture_filtering: TextureFiltering,
+    msaa_enabled: bool,
 ) -> (Tx, Rx, FrontendChannels, Rx2dData) {
     let shared_data = Arc::new(unsafe {
         SharedData {
             stopped: AtomicBool::new(false),
+            rendering_thread: OnceLock::new(),
             resolution_scale_shift: AtomicU8::new(resolution_scale_shift),
+            msaa_enabled: AtomicBool::new(msaa_enabled),
+            compute_rasterizer_enabled: AtomicBool::new(false),
+            texture_filtering: Mutex::new(None),
+            texture_cache_mode: Mutex::new(None),
+            texture_dump_dir: Mutex::new(None),
+            texture_pack_dir: Mutex::new(None),
+            pipeline_failures: Mutex::new(Vec::new()),
+            gpu_frame_time_ns: AtomicU64::new(0),
+            texture_cache_keys: Mutex::new(Vec::new()),
+            texture_prewarm: Mutex::new(None),
+            time_counter: TimeCounter::new(),
 
             capture_rendering_data: Box::new_zeroed().assume_init(),
             capture_scanline_buffer: Box::new_zeroed().assume_init(),
@@ -236,7 +372,14 @@ pub fn init(
 
     let (frame_tx, mut frame_rx) = unsafe { triple_buffer::init_zeroed() };
 
-    let mut renderer = Renderer::new(device, queue, resolution_scale_shift);
+    let mut renderer = Renderer::new(
+        device,
+        queue,
+        resolution_scale_shift,
+        texture_filtering,
+        msaa_enabled,
+    );
+    *shared_data.pipeline_failures.lock() = renderer.pipeline_failures().to_vec();
 
     let color_output_view = renderer.create_output_view();
     let (color_output_view_tx, color_output_view_rx) = crossbeam_channel::unbounded();
@@ -258,12 +401,24 @@ pub fn init(
                 thread::Builder::new()
                     .name("3D rendering".to_owned())
                     .spawn(move || {
+                        let _ = shared_data.rendering_thread.set(thread::current());
                         let mut raw_soft_renderer = soft::Renderer::new();
                         let mut color_output_index = renderer.color_output_index();
                         loop {
                             if shared_data.stopped.load(Ordering::Relaxed) {
                                 break;
                             }
+                            if let Some(prewarm) = shared_data.texture_prewarm.lock().take() {
+                                renderer.prewarm_textures(
+                                    &prewarm.keys,
+                                    &prewarm.texture,
+                                    &prewarm.tex_pal,
+                                );
+                            }
+                            if renderer.take_texture_cache_changed() {
+                                *shared_data.texture_cache_keys.lock() =
+                                    renderer.texture_cache_keys();
+                            }
                             if shared_data
                                 .capture_processing_scanline
                                 .compare_exchange(u8::MAX, 0, Ordering::Acquire, Ordering::Acquire)
@@ -292,6 +447,7 @@ pub fn init(
                             }
                             if let Ok(frame) = frame_rx.get() {
                                 if frame.render {
+                                    let start = shared_data.time_counter.start();
                                     let resolution_scale_shift =
                                         shared_data.resolution_scale_shift.load(Ordering::Relaxed);
                                     let mut color_output_updated = false;
@@ -306,6 +462,44 @@ pub fn init(
                                         renderer.set_resolution_scale_shift(resolution_scale_shift);
                                     }
 
+                                    let msaa_enabled =
+                                        shared_data.msaa_enabled.load(Ordering::Relaxed);
+                                    if msaa_enabled != renderer.msaa_enabled() {
+                                        color_output_updated = true;
+                                        renderer.set_msaa_enabled(msaa_enabled);
+                                    }
+
+                                    let compute_rasterizer_enabled = shared_data
+                                        .compute_rasterizer_enabled
+                                        .load(Ordering::Relaxed);
+                                    if compute_rasterizer_enabled
+                                        != renderer.compute_rasterizer_enabled()
+                                    {
+                                        color_output_updated = true;
+                                        renderer.set_compute_rasterizer_enabled(
+                                            compute_rasterizer_enabled,
+                                        );
+                                    }
+
+                                    if let Some(value) = shared_data.texture_filtering.lock().take()
+                                    {
+                                        renderer.set_texture_filtering(value);
+                                    }
+
+                                    if let Some(value) =
+                                        shared_data.texture_cache_mode.lock().take()
+                                    {
+                                        renderer.set_texture_cache_mode(value);
+                                    }
+
+                                    if let Some(dir) = shared_data.texture_dump_dir.lock().take() {
+                                        renderer.set_texture_dump_dir(dir);
+                                    }
+
+                                    if let Some(dir) = shared_data.texture_pack_dir.lock().take() {
+                                        renderer.set_texture_pack_dir(dir);
+                                    }
+
                                     if color_output_updated {
                                         color_output_view_tx
                                             .send(renderer.create_output_view())
@@ -318,6 +512,23 @@ pub fn init(
                                     // let command_buffer =
                                     //     renderer.render_frame(&frame.rendering_data);
                                     // renderer.queue().submit([command_buffer]);
+                                    // renderer.frames_submitted();
+
+                                    if let Some(frame_time) = renderer.gpu_frame_time() {
+                                        shared_data.gpu_frame_time_ns.store(
+                                            (frame_time.as_nanos() as u64).max(1),
+                                            Ordering::Relaxed,
+                                        );
+                                    }
+
+                                    let mut pipeline_failures =
+                                        shared_data.pipeline_failures.lock();
+                                    if pipeline_failures.len() != renderer.pipeline_failures().len()
+                                    {
+                                        *pipeline_failures = renderer.pipeline_failures().to_vec();
+                                    }
+                                    drop(pipeline_failures);
+                                    shared_data.time_counter.stop(start);
                                 }
                                 last_submitted_frame
                                     .0
@@ -0,0 +1,231 @@
+#![feature(new_zeroed_alloc)]
+#![warn(clippy::all)]
+
+//! Runs a list of ROMs headlessly to measure the emulator's speed and record hashes of its output,
+//! then compares the reports produced by two builds to quantify the performance and accuracy
+//! impact of a change on real games.
+//!
+//! Typical usage, building the tool once on each revision to compare:
+//! ```text
+//! dust-bench run roms.txt -o base.json --label main
+//! dust-bench run roms.txt -o new.json --label my-branch
+//! dust-bench compare base.json new.json --html report.html
+//! ```
+
+mod renderer_3d;
+mod report;
+mod run;
+
+use dust_core::{emu::frame_limiter::NATIVE_FRAME_RATE, Model};
+use std::{env, fs, path::PathBuf, process::exit};
+
+const USAGE: &str = "\
+Usage:
+  dust-bench run <rom list> -o <report.json> [options]
+    --label <name>          Name of the build being measured (default: \"unnamed\")
+    --frames <n>            Number of frames to run each ROM for (default: 3600)
+    --hash-interval <n>     Number of frames between output hashes (default: 60)
+    --fps-limit <fps>       Pace frames like a frontend would, at <fps> or `native` (~59.83), to
+                            check whether the target framerate is held (default: unlimited)
+    --model <model>         ds, lite, ique, ique-lite or dsi (default: ds)
+    --arm7-bios <path>      ARM7 BIOS, needed for ROMs that require decryption
+    --arm9-bios <path>      ARM9 BIOS, needed for ROMs that require decryption
+    --firmware <path>       Firmware to use instead of the built-in one
+  dust-bench compare <base report.json> <new report.json> [options]
+    --json <path>           Write the comparison as JSON
+    --html <path>           Write the comparison as an HTML page
+
+The ROM list contains one path per line, relative to the list's directory; blank lines and
+lines starting with `#` are ignored.";
+
+fn fail(message: impl AsRef<str>) -> ! {
+    eprintln!("{}", message.as_ref());
+    exit(1);
+}
+
+fn fail_usage(message: impl AsRef<str>) -> ! {
+    eprintln!("{}\n\n{USAGE}", message.as_ref());
+    exit(2);
+}
+
+struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Self {
+        let mut result = Args {
+            positional: Vec::new(),
+            options: Vec::new(),
+        };
+        while let Some(arg) = args.next() {
+            if arg.starts_with('-') {
+                let Some(value) = args.next() else {
+                    fail_usage(format!("Missing value for `{arg}`"));
+                };
+                result.options.push((arg, value));
+            } else {
+                result.positional.push(arg);
+            }
+        }
+        result
+    }
+
+    fn take_option(&mut self, names: &[&str]) -> Option<String> {
+        let i = self
+            .options
+            .iter()
+            .position(|(name, _)| names.contains(&name.as_str()))?;
+        Some(self.options.remove(i).1)
+    }
+
+    fn take_parsed_option<T: std::str::FromStr>(&mut self, names: &[&str], default: T) -> T {
+        match self.take_option(names) {
+            Some(value) => value
+                .parse()
+                .unwrap_or_else(|_| fail_usage(format!("Invalid value for `{}`", names[0]))),
+            None => default,
+        }
+    }
+
+    fn finish(self, positional_len: usize) -> Vec<String> {
+        if let Some((name, _)) = self.options.first() {
+            fail_usage(format!("Unknown option `{name}`"));
+        }
+        if self.positional.len() != positional_len {
+            fail_usage("Wrong number of arguments");
+        }
+        self.positional
+    }
+}
+
+fn parse_model(value: &str) -> Model {
+    match value {
+        "ds" => Model::Ds,
+        "lite" => Model::Lite,
+        "ique" => Model::Ique,
+        "ique-lite" => Model::IqueLite,
+        "dsi" => Model::Dsi,
+        _ => fail_usage(format!("Unknown model `{value}`")),
+    }
+}
+
+fn parse_fps_limit(value: &str) -> f64 {
+    if value == "native" {
+        return NATIVE_FRAME_RATE;
+    }
+    match value.parse::<f64>() {
+        Ok(fps) if fps.is_finite() && fps > 0.0 => fps,
+        _ => fail_usage("Invalid value for `--fps-limit`"),
+    }
+}
+
+fn write_file(path: &str, contents: &str) {
+    if let Err(err) = fs::write(path, contents) {
+        fail(format!("Couldn't write `{path}`: {err}"));
+    }
+}
+
+fn run(mut args: Args) {
+    let output_path = args
+        .take_option(&["-o", "--output"])
+        .unwrap_or_else(|| fail_usage("Missing output path"));
+    let label = args
+        .take_option(&["--label"])
+        .unwrap_or_else(|| "unnamed".to_owned());
+    let frames = args.take_parsed_option(&["--frames"], 3600_u32);
+    let hash_interval = args.take_parsed_option(&["--hash-interval"], 60_u32);
+    if frames == 0 || hash_interval == 0 {
+        fail_usage("Frame counts must be positive");
+    }
+    let fps_limit = args
+        .take_option(&["--fps-limit"])
+        .map(|value| parse_fps_limit(&value));
+    let model = args
+        .take_option(&["--model"])
+        .map_or(Model::Ds, |value| parse_model(&value));
+    let arm7_bios = args.take_option(&["--arm7-bios"]).map(|path| {
+        run::load_bios(path.as_ref())
+            .unwrap_or_else(|err| fail(format!("Couldn't load ARM7 BIOS: {err}")))
+    });
+    let arm9_bios = args.take_option(&["--arm9-bios"]).map(|path| {
+        run::load_bios(path.as_ref())
+            .unwrap_or_else(|err| fail(format!("Couldn't load ARM9 BIOS: {err}")))
+    });
+    let firmware = args.take_option(&["--firmware"]).map(|path| {
+        run::load_firmware(path.as_ref())
+            .unwrap_or_else(|err| fail(format!("Couldn't load firmware: {err}")))
+    });
+    let [rom_list_path] = <[String; 1]>::try_from(args.finish(1)).unwrap();
+
+    let roms = run::read_rom_list(&PathBuf::from(&rom_list_path))
+        .unwrap_or_else(|err| fail(format!("Couldn't read ROM list: {err}")));
+
+    let report = run::run(
+        &roms,
+        &run::Settings {
+            label,
+            model,
+            frames,
+            hash_interval,
+            fps_limit,
+            arm7_bios,
+            arm9_bios,
+            firmware,
+        },
+        |path, result| match result {
+            Ok(result) => eprintln!(
+                "{}: {} frames in {:.2} s ({:.2} FPS)",
+                path.display(),
+                result.frames,
+                result.seconds,
+                result.fps
+            ),
+            Err(err) => eprintln!("{}: {err}", path.display()),
+        },
+    );
+
+    write_file(
+        &output_path,
+        &serde_json::to_string_pretty(&report).expect("couldn't serialize report"),
+    );
+}
+
+fn read_report(path: &str) -> report::RunReport {
+    let contents = fs::read_to_string(path)
+        .unwrap_or_else(|err| fail(format!("Couldn't read `{path}`: {err}")));
+    serde_json::from_str(&contents)
+        .unwrap_or_else(|err| fail(format!("Couldn't parse `{path}`: {err}")))
+}
+
+fn compare(mut args: Args) {
+    let json_path = args.take_option(&["--json"]);
+    let html_path = args.take_option(&["--html"]);
+    let [base_path, new_path] = <[String; 2]>::try_from(args.finish(2)).unwrap();
+
+    let comparison = report::compare(&read_report(&base_path), &read_report(&new_path));
+    println!("{comparison}");
+
+    if let Some(path) = json_path {
+        write_file(
+            &path,
+            &serde_json::to_string_pretty(&comparison).expect("couldn't serialize comparison"),
+        );
+    }
+    if let Some(path) = html_path {
+        write_file(&path, &comparison.to_html());
+    }
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let command = args.next();
+    let args = Args::parse(args);
+    match command.as_deref() {
+        Some("run") => run(args),
+        Some("compare") => compare(args),
+        Some("help" | "-h" | "--help") => println!("{USAGE}"),
+        _ => fail_usage("Unknown command"),
+    }
+}
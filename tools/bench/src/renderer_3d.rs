@@ -0,0 +1,95 @@
+use dust_core::{
+    gpu::{
+        engine_3d::{GxSnapshot, RendererTx, RenderingState as CoreRenderingState, SoftRendererRx},
+        Scanline, SCREEN_HEIGHT,
+    },
+    utils::mem_prelude::*,
+};
+use dust_soft_3d::{Renderer, RenderingData};
+use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+// Frames are rendered synchronously on the emulation thread, as soon as rendering starts; this
+// produces the same output as the desktop frontend's threaded renderer (which the 2D renderer
+// waits on anyway), and keeps runs deterministic and their timings comparable across machines
+// with different core counts.
+
+pub struct Tx {
+    renderer: Renderer,
+    rendering_data: Box<RenderingData>,
+    scanline_buffer: Rc<RefCell<Box<[Scanline<u32>; SCREEN_HEIGHT]>>>,
+}
+
+impl RendererTx for Tx {
+    fn set_capture_enabled(&mut self, _capture_enabled: bool) {}
+
+    fn swap_buffers(&mut self, gx: &Arc<GxSnapshot>, state: &CoreRenderingState) {
+        self.rendering_data.prepare(gx, state);
+    }
+
+    fn repeat_last_frame(&mut self, state: &CoreRenderingState) {
+        self.rendering_data.repeat_last_frame(state);
+    }
+
+    fn start_rendering(
+        &mut self,
+        texture: &Bytes<0x8_0000>,
+        tex_pal: &Bytes<0x1_8000>,
+        state: &CoreRenderingState,
+    ) {
+        self.rendering_data.copy_vram(texture, tex_pal, state);
+
+        let mut scanline_buffer = self.scanline_buffer.borrow_mut();
+        self.renderer.start_frame(&self.rendering_data);
+        self.renderer.render_line(0, &self.rendering_data);
+        for y in 0..SCREEN_HEIGHT as u8 {
+            if y < SCREEN_HEIGHT as u8 - 1 {
+                self.renderer.render_line(y + 1, &self.rendering_data);
+            }
+            self.renderer.postprocess_line(
+                y,
+                &mut scanline_buffer[y as usize],
+                &self.rendering_data,
+            );
+        }
+    }
+
+    fn skip_rendering(&mut self) {}
+}
+
+pub struct Rx {
+    next_scanline: u8,
+    scanline: Scanline<u32>,
+    scanline_buffer: Rc<RefCell<Box<[Scanline<u32>; SCREEN_HEIGHT]>>>,
+}
+
+impl SoftRendererRx for Rx {
+    fn start_frame(&mut self) {
+        self.next_scanline = 0;
+    }
+
+    fn read_scanline(&mut self) -> &Scanline<u32> {
+        self.scanline = self.scanline_buffer.borrow()[self.next_scanline as usize];
+        self.next_scanline += 1;
+        &self.scanline
+    }
+
+    fn skip_scanline(&mut self) {
+        self.next_scanline += 1;
+    }
+}
+
+pub fn init() -> (Tx, Rx) {
+    let scanline_buffer = Rc::new(RefCell::new(unsafe { Box::new_zeroed().assume_init() }));
+    (
+        Tx {
+            renderer: Renderer::new(),
+            rendering_data: unsafe { Box::new_zeroed().assume_init() },
+            scanline_buffer: Rc::clone(&scanline_buffer),
+        },
+        Rx {
+            next_scanline: 0,
+            scanline: Scanline([0; 256]),
+            scanline_buffer,
+        },
+    )
+}
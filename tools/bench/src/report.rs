@@ -0,0 +1,299 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::{self, Write},
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum RomError {
+    Io(String),
+    InvalidSize,
+    InvalidFirmware,
+    MissingBios,
+    Build,
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RomError::Io(err) => write!(f, "couldn't read ROM: {err}"),
+            RomError::InvalidSize => f.write_str("invalid ROM size"),
+            RomError::InvalidFirmware => f.write_str("invalid firmware"),
+            RomError::MissingBios => f.write_str("ROM needs decryption but no BIOS was provided"),
+            RomError::Build => f.write_str("couldn't build emulator"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RomResult {
+    /// The number of frames that were actually run, which is less than requested if the emulated
+    /// system shut down.
+    pub frames: u32,
+    pub seconds: f64,
+    pub fps: f64,
+    /// Hashes of the framebuffer's contents after every `hash_interval` frames and after the last
+    /// one, as (frame number, hex-encoded hash) pairs.
+    pub frame_hashes: Vec<(u32, String)>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RomEntry {
+    pub path: String,
+    pub result: Result<RomResult, RomError>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunReport {
+    pub label: String,
+    pub frames: u32,
+    pub hash_interval: u32,
+    pub roms: Vec<RomEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RomComparison {
+    pub path: String,
+    pub base_fps: Option<f64>,
+    pub new_fps: Option<f64>,
+    /// The relative speed change from the base build to the new one, in percent.
+    pub speed_change: Option<f64>,
+    /// The number of frame hashes both runs have in common.
+    pub compared_hashes: usize,
+    /// The first frame at which the two builds' output differed, if any.
+    pub first_mismatch: Option<u32>,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Comparison {
+    pub base_label: String,
+    pub new_label: String,
+    /// The geometric mean of the speed ratios of all ROMs that ran in both builds, as a relative
+    /// change in percent.
+    pub mean_speed_change: Option<f64>,
+    pub mismatches: usize,
+    pub roms: Vec<RomComparison>,
+}
+
+fn compare_rom(path: &str, base: Option<&RomEntry>, new: Option<&RomEntry>) -> RomComparison {
+    let mut result = RomComparison {
+        path: path.to_owned(),
+        base_fps: None,
+        new_fps: None,
+        speed_change: None,
+        compared_hashes: 0,
+        first_mismatch: None,
+        error: None,
+    };
+
+    let (base, new) = match (base.map(|e| &e.result), new.map(|e| &e.result)) {
+        (Some(Ok(base)), Some(Ok(new))) => (base, new),
+        (base, new) => {
+            result.base_fps = base.and_then(|r| r.as_ref().ok()).map(|r| r.fps);
+            result.new_fps = new.and_then(|r| r.as_ref().ok()).map(|r| r.fps);
+            let describe = |label: &str, r: Option<&Result<RomResult, RomError>>| match r {
+                None => Some(format!("{label}: not run")),
+                Some(Err(err)) => Some(format!("{label}: {err}")),
+                Some(Ok(_)) => None,
+            };
+            result.error = Some(
+                [describe("base", base), describe("new", new)]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            );
+            return result;
+        }
+    };
+
+    result.base_fps = Some(base.fps);
+    result.new_fps = Some(new.fps);
+    result.speed_change = Some((new.fps / base.fps - 1.0) * 100.0);
+
+    // Only hashes taken at the same frame number can be compared, in case the two runs used
+    // different settings
+    let base_hashes = base
+        .frame_hashes
+        .iter()
+        .map(|(frame, hash)| (*frame, hash))
+        .collect::<HashMap<_, _>>();
+    for (frame, hash) in &new.frame_hashes {
+        let Some(base_hash) = base_hashes.get(frame) else {
+            continue;
+        };
+        result.compared_hashes += 1;
+        if *base_hash != hash {
+            result.first_mismatch = Some(*frame);
+            break;
+        }
+    }
+
+    result
+}
+
+pub fn compare(base: &RunReport, new: &RunReport) -> Comparison {
+    let base_roms = base
+        .roms
+        .iter()
+        .map(|entry| (entry.path.as_str(), entry))
+        .collect::<HashMap<_, _>>();
+    let new_roms = new
+        .roms
+        .iter()
+        .map(|entry| (entry.path.as_str(), entry))
+        .collect::<HashMap<_, _>>();
+
+    let mut paths = base
+        .roms
+        .iter()
+        .map(|e| e.path.as_str())
+        .collect::<Vec<_>>();
+    paths.extend(
+        new.roms
+            .iter()
+            .map(|e| e.path.as_str())
+            .filter(|path| !base_roms.contains_key(path)),
+    );
+
+    let roms = paths
+        .into_iter()
+        .map(|path| {
+            compare_rom(
+                path,
+                base_roms.get(path).copied(),
+                new_roms.get(path).copied(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let (log_sum, count) = roms
+        .iter()
+        .filter_map(|rom| Some((rom.new_fps? / rom.base_fps?).ln()))
+        .fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+
+    Comparison {
+        base_label: base.label.clone(),
+        new_label: new.label.clone(),
+        mean_speed_change: (count != 0).then(|| ((log_sum / count as f64).exp() - 1.0) * 100.0),
+        mismatches: roms
+            .iter()
+            .filter(|rom| rom.first_mismatch.is_some())
+            .count(),
+        roms,
+    }
+}
+
+fn format_fps(value: Option<f64>) -> String {
+    value.map_or_else(|| "-".to_owned(), |value| format!("{value:.2}"))
+}
+
+fn format_change(value: Option<f64>) -> String {
+    value.map_or_else(|| "-".to_owned(), |value| format!("{value:+.2}%"))
+}
+
+fn format_accuracy(rom: &RomComparison) -> String {
+    if let Some(error) = &rom.error {
+        error.clone()
+    } else if let Some(frame) = rom.first_mismatch {
+        format!("differs from frame {frame}")
+    } else {
+        format!("matches ({} hashes)", rom.compared_hashes)
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} -> {}", self.base_label, self.new_label)?;
+        for rom in &self.roms {
+            writeln!(
+                f,
+                "{}: {} -> {} FPS ({}), {}",
+                rom.path,
+                format_fps(rom.base_fps),
+                format_fps(rom.new_fps),
+                format_change(rom.speed_change),
+                format_accuracy(rom),
+            )?;
+        }
+        write!(
+            f,
+            "Mean speed change: {}, {} ROM(s) with differing output",
+            format_change(self.mean_speed_change),
+            self.mismatches,
+        )
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+impl Comparison {
+    pub fn to_html(&self) -> String {
+        let mut rows = String::new();
+        for rom in &self.roms {
+            let speed_class = match rom.speed_change {
+                Some(change) if change >= 1.0 => "better",
+                Some(change) if change <= -1.0 => "worse",
+                _ => "",
+            };
+            let accuracy_class = if rom.error.is_some() || rom.first_mismatch.is_some() {
+                "worse"
+            } else {
+                ""
+            };
+            let _ = write!(
+                rows,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"{speed_class}\">{}</td><td \
+                 class=\"{accuracy_class}\">{}</td></tr>",
+                escape_html(&rom.path),
+                format_fps(rom.base_fps),
+                format_fps(rom.new_fps),
+                format_change(rom.speed_change),
+                escape_html(&format_accuracy(rom)),
+            );
+        }
+
+        format!(
+            "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>Dust benchmark: {base} vs {new}</title>
+<style>
+body {{ font-family: sans-serif; }}
+table {{ border-collapse: collapse; }}
+th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; }}
+.better {{ color: #080; }}
+.worse {{ color: #c00; }}
+</style>
+</head>
+<body>
+<h1>{base} vs {new}</h1>
+<p>Mean speed change: {mean_speed_change}; {mismatches} ROM(s) with differing output</p>
+<table>
+<tr><th>ROM</th><th>{base} FPS</th><th>{new} FPS</th><th>Speed change</th><th>Output</th></tr>
+{rows}
+</table>
+</body>
+</html>
+",
+            base = escape_html(&self.base_label),
+            new = escape_html(&self.new_label),
+            mean_speed_change = format_change(self.mean_speed_change),
+            mismatches = self.mismatches,
+        )
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{
+    renderer_3d,
+    report::{RomEntry, RomError, RomResult, RunReport},
+};
+use dust_core::{
+    audio::DummyBackend as DummyAudioBackend,
+    cpu::{arm7, arm9, interpreter::Interpreter},
+    ds_slot,
+    emu::{
+        self,
+        frame_limiter::{FramePacer, Limiter},
+        RunOutput,
+    },
+    flash::Flash,
+    gpu::Framebuffer,
+    rtc,
+    spi::firmware,
+    utils::{zeroed_box, BoxedByteSlice, Bytes},
+    Model, SaveContents,
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+pub struct Settings {
+    pub label: String,
+    pub model: Model,
+    pub frames: u32,
+    pub hash_interval: u32,
+    /// The framerate to pace emulation at, if any.
+    pub fps_limit: Option<f64>,
+    pub arm7_bios: Option<Box<Bytes<{ arm7::BIOS_SIZE }>>>,
+    pub arm9_bios: Option<Box<Bytes<{ arm9::BIOS_SIZE }>>>,
+    pub firmware: Option<Vec<u8>>,
+}
+
+/// Reads a ROM list, containing one path per line (relative to the list's directory); blank
+/// lines and lines starting with `#` are ignored.
+pub fn read_rom_list(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| base_dir.join(line))
+        .collect())
+}
+
+/// Hashes a frame using 64-bit FNV-1a, which (unlike the standard library's hashers) is stable
+/// across builds and platforms, so that hashes from different builds can be compared.
+fn hash_frame(framebuffer: &Framebuffer) -> u64 {
+    let mut hash = 0xCBF2_9CE4_8422_2325_u64;
+    for pixel in framebuffer.iter().flatten() {
+        for byte in pixel.to_le_bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
+        }
+    }
+    hash
+}
+
+fn load_rom(path: &Path, model: Model) -> Result<BoxedByteSlice, RomError> {
+    let contents = fs::read(path).map_err(|err| RomError::Io(err.to_string()))?;
+    let mut rom = BoxedByteSlice::new_zeroed(contents.len().next_power_of_two());
+    rom[..contents.len()].copy_from_slice(&contents);
+    if !ds_slot::rom::is_valid_size(rom.len() as u64, model) {
+        return Err(RomError::InvalidSize);
+    }
+    Ok(rom)
+}
+
+fn run_rom(
+    path: &Path,
+    settings: &Settings,
+    #[cfg(feature = "log")] logger: &slog::Logger,
+) -> Result<RomResult, RomError> {
+    let rom = load_rom(path, settings.model)?;
+
+    let firmware = match &settings.firmware {
+        Some(contents) => {
+            let mut buf = BoxedByteSlice::new_zeroed(contents.len());
+            buf.copy_from_slice(contents);
+            buf
+        }
+        None => firmware::default(settings.model),
+    };
+
+    let (tx_3d, rx_3d) = renderer_3d::init();
+
+    // Saves are always left empty and the RTC always reports the same time, so that runs are
+    // reproducible
+    let mut emu_builder = emu::Builder::new(
+        Flash::new(
+            SaveContents::Existing(firmware),
+            firmware::id_for_model(settings.model),
+            #[cfg(feature = "log")]
+            logger.new(slog::o!("fw" => "")),
+        )
+        .map_err(|_| RomError::InvalidFirmware)?,
+        Some(Box::new(rom)),
+        ds_slot::spi::Empty::new(
+            #[cfg(feature = "log")]
+            logger.new(slog::o!("ds_spi" => "empty")),
+        )
+        .into(),
+        Box::new(DummyAudioBackend),
+        None,
+        Box::new(rtc::DummyBackend),
+        Box::new(dust_soft_2d::sync::Renderer::new(Box::new(rx_3d))),
+        Box::new(tx_3d),
+        None,
+        #[cfg(feature = "log")]
+        logger.clone(),
+    );
+
+    emu_builder.arm7_bios.clone_from(&settings.arm7_bios);
+    emu_builder.arm9_bios.clone_from(&settings.arm9_bios);
+
+    emu_builder.model = settings.model;
+    emu_builder.direct_boot = true;
+
+    let mut emu = emu_builder.build(Interpreter).map_err(|err| match err {
+        emu::BuildError::RomSetup(_) => RomError::MissingBios,
+        _ => RomError::Build,
+    })?;
+
+    let mut frame_hashes = Vec::with_capacity((settings.frames / settings.hash_interval) as usize);
+    let mut limiter = settings
+        .fps_limit
+        .map(|fps| Limiter::new(FramePacer::with_target_fps(fps)));
+    let start_time = Instant::now();
+    let mut frames = 0;
+    while frames < settings.frames {
+        match emu.run() {
+            RunOutput::FrameFinished => {}
+            _ => break,
+        }
+        if let Some(limiter) = &mut limiter {
+            limiter.end_frame();
+        }
+        frames += 1;
+        if frames % settings.hash_interval == 0 || frames == settings.frames {
+            frame_hashes.push((
+                frames,
+                format!("{:016x}", hash_frame(emu.gpu.renderer_2d().framebuffer())),
+            ));
+        }
+    }
+    let seconds = start_time.elapsed().as_secs_f64();
+
+    Ok(RomResult {
+        frames,
+        seconds,
+        fps: frames as f64 / seconds,
+        frame_hashes,
+    })
+}
+
+pub fn run(
+    roms: &[PathBuf],
+    settings: &Settings,
+    mut on_progress: impl FnMut(&Path, &Result<RomResult, RomError>),
+) -> RunReport {
+    #[cfg(feature = "log")]
+    let logger = slog::Logger::root(slog::Discard, slog::o!());
+
+    let mut report = RunReport {
+        label: settings.label.clone(),
+        frames: settings.frames,
+        hash_interval: settings.hash_interval,
+        roms: Vec::with_capacity(roms.len()),
+    };
+    for path in roms {
+        let result = run_rom(
+            path,
+            settings,
+            #[cfg(feature = "log")]
+            &logger,
+        );
+        on_progress(path, &result);
+        report.roms.push(RomEntry {
+            path: path.to_string_lossy().into_owned(),
+            result,
+        });
+    }
+    report
+}
+
+pub fn load_bios<const LEN: usize>(path: &Path) -> io::Result<Box<Bytes<LEN>>> {
+    let contents = fs::read(path)?;
+    if contents.len() != LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("expected {LEN} bytes, got {}", contents.len()),
+        ));
+    }
+    let mut buf = zeroed_box::<Bytes<LEN>>();
+    buf.copy_from_slice(&contents);
+    Ok(buf)
+}
+
+pub fn load_firmware(path: &Path) -> io::Result<Vec<u8>> {
+    let contents = fs::read(path)?;
+    if !firmware::is_valid_size(contents.len()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid firmware size ({} bytes)", contents.len()),
+        ));
+    }
+    Ok(contents)
+}